@@ -0,0 +1,206 @@
+//! # Share Access Log Forwarding
+//!
+//! [`AuditSink`] delivering public share access events to the SIEM
+//! destination each organization configured (see `arshjul_core::access_log`):
+//!
+//! - **Webhook** - `POST` of a JSON array of events
+//! - **Event Hub** - Event Hubs REST batch send, one event per message,
+//!   authorized with the app's Managed Identity (the hub needs the
+//!   `Azure Event Hubs Data Sender` role for it)
+//!
+//! A batch is split by destination. If any destination fails transiently the
+//! whole batch is retried, so others may receive events twice.
+//!
+//! Webhook hosts are resolved to public addresses only and redirects are not
+//! followed, so a destination can't be pointed at the metadata endpoint or
+//! the private network after it was validated (e.g. by DNS rebinding).
+
+use arshjul_core::access_log::{self, AccessLogRecord};
+use arshjul_core::audit_export::{AuditExportError, AuditSink};
+use arshjul_core::models::{AccessLogDestination, ShareAccessEvent};
+use async_trait::async_trait;
+use azure_core::auth::TokenCredential;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Event Hubs token scope
+const EVENT_HUBS_SCOPE: &str = "https://eventhubs.azure.net/.default";
+
+/// Event Hubs REST API version
+const EVENT_HUBS_API_VERSION: &str = "2014-01";
+
+/// Forwards access events to webhooks and Event Hubs
+pub struct AccessLogSink {
+    credential: Arc<dyn TokenCredential>,
+    http: reqwest::Client,
+}
+
+impl AccessLogSink {
+    /// Create using Managed Identity / Azure CLI credentials (for Event Hubs)
+    pub fn new() -> Result<Self, AuditExportError> {
+        let credential = azure_identity::create_credential()
+            .map_err(|e| AuditExportError::Unavailable(format!("Failed to create Azure credential: {}", e)))?;
+        
+        let http = reqwest::Client::builder()
+            .dns_resolver(Arc::new(PublicOnlyResolver))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| AuditExportError::Unavailable(format!("Failed to create HTTP client: {}", e)))?;
+        
+        Ok(Self { credential, http })
+    }
+    
+    async fn deliver(&self, destination: &AccessLogDestination, events: &[&ShareAccessEvent]) -> Result<(), AuditExportError> {
+        let request = match destination {
+            AccessLogDestination::Webhook { url } => {
+                // IP literals skip the resolver; destinations set before hosts were checked may still name one
+                if !access_log::webhook_host(url).is_some_and(access_log::is_public_host) {
+                    return Err(AuditExportError::Rejected(format!("{} is not a public host", destination_name(destination))));
+                }
+                self.http.post(url).json(events)
+            }
+            AccessLogDestination::EventHub { namespace, hub } => {
+                let token = self.credential.get_token(&[EVENT_HUBS_SCOPE]).await
+                    .map_err(|e| AuditExportError::Unavailable(e.to_string()))?;
+                self.http.post(event_hub_url(namespace, hub))
+                    .bearer_auth(token.token.secret())
+                    .header("Content-Type", "application/vnd.microsoft.servicebus.json")
+                    .body(event_hub_batch(events).to_string())
+            }
+        };
+        
+        let response = request.send().await
+            .map_err(|e| AuditExportError::Unavailable(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let message = format!("{} returned {}", destination_name(destination), status);
+        // Throttling, expired tokens and server errors are transient; other 4xx mean the destination is misconfigured
+        if status.as_u16() == 429 || status.is_server_error() || status.as_u16() == 401 {
+            Err(AuditExportError::Unavailable(message))
+        } else {
+            Err(AuditExportError::Rejected(message))
+        }
+    }
+}
+
+/// Batch send endpoint of an event hub
+fn event_hub_url(namespace: &str, hub: &str) -> String {
+    format!("https://{}.servicebus.windows.net/{}/messages?api-version={}", namespace, hub, EVENT_HUBS_API_VERSION)
+}
+
+/// DNS resolution that leaves out loopback, private and link-local addresses
+struct PublicOnlyResolver;
+
+impl reqwest::dns::Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?
+                .filter(|addr| access_log::is_public_address(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Event Hubs batch body: one message per event, the event JSON as its body
+fn event_hub_batch(events: &[&ShareAccessEvent]) -> serde_json::Value {
+    events.iter()
+        .map(|event| serde_json::json!({ "Body": serde_json::to_string(event).unwrap_or_default() }))
+        .collect()
+}
+
+/// Destination for logs; webhook URLs may carry secrets, so only the host
+fn destination_name(destination: &AccessLogDestination) -> String {
+    match destination {
+        AccessLogDestination::Webhook { url } => {
+            let host = url.trim_start_matches("https://").split(['/', '?', '#']).next().unwrap_or_default();
+            format!("Webhook {}", host)
+        }
+        AccessLogDestination::EventHub { namespace, hub } => format!("Event hub {}/{}", namespace, hub),
+    }
+}
+
+/// Events grouped by destination, in order of first appearance
+fn by_destination(batch: &[AccessLogRecord]) -> Vec<(&AccessLogDestination, Vec<&ShareAccessEvent>)> {
+    let mut groups: Vec<(&AccessLogDestination, Vec<&ShareAccessEvent>)> = Vec::new();
+    for record in batch {
+        match groups.iter_mut().find(|(destination, _)| **destination == record.destination) {
+            Some((_, events)) => events.push(&record.event),
+            None => groups.push((&record.destination, vec![&record.event])),
+        }
+    }
+    groups
+}
+
+#[async_trait]
+impl AuditSink<AccessLogRecord> for AccessLogSink {
+    fn name(&self) -> &'static str {
+        "access-log"
+    }
+    
+    async fn send(&self, batch: &[AccessLogRecord]) -> Result<(), AuditExportError> {
+        let mut unavailable = None;
+        let mut rejected = None;
+        for (destination, events) in by_destination(batch) {
+            match self.deliver(destination, &events).await {
+                Ok(()) => {}
+                Err(e @ AuditExportError::Unavailable(_)) => unavailable = Some(e),
+                Err(e) => {
+                    tracing::warn!("Dropping {} access events: {}", events.len(), e);
+                    rejected = Some(e);
+                }
+            }
+        }
+        // Retry the batch if any destination may still take it
+        match unavailable.or(rejected) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arshjul_core::models::{PublicAccessEndpoint, PublicAccessResult};
+    
+    fn record(destination: &AccessLogDestination, share_id: &str) -> AccessLogRecord {
+        AccessLogRecord {
+            destination: destination.clone(),
+            event: ShareAccessEvent {
+                id: format!("event-{}", share_id),
+                organization_id: "org-1".to_string(),
+                share_id: share_id.to_string(),
+                occurred_at: "2025-03-01T12:00:00Z".parse().unwrap(),
+                endpoint: PublicAccessEndpoint::Share,
+                result: PublicAccessResult::Granted,
+                reason: None,
+                country: None,
+            },
+        }
+    }
+    
+    #[test]
+    fn test_batches_by_destination() {
+        let webhook = AccessLogDestination::Webhook { url: "https://siem.example.com/in?token=secret".to_string() };
+        let hub = AccessLogDestination::EventHub { namespace: "contoso-siem".to_string(), hub: "access".to_string() };
+        let batch = [record(&webhook, "s-1"), record(&hub, "s-2"), record(&webhook, "s-3")];
+        
+        let groups = by_destination(&batch);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].1.iter().map(|e| e.share_id.as_str()).collect::<Vec<_>>(), ["s-1", "s-3"]);
+        
+        let body = event_hub_batch(&groups[1].1);
+        let event: serde_json::Value = serde_json::from_str(body[0]["Body"].as_str().unwrap()).unwrap();
+        assert_eq!(event["shareId"], "s-2");
+        
+        assert_eq!(event_hub_url("contoso-siem", "access"), "https://contoso-siem.servicebus.windows.net/access/messages?api-version=2014-01");
+        assert_eq!(destination_name(&webhook), "Webhook siem.example.com");
+    }
+}
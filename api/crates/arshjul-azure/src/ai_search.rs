@@ -0,0 +1,206 @@
+//! # Azure AI Search
+//!
+//! [`ActivitySearchIndex`] backed by an Azure AI Search index, shared by all
+//! organizations and filtered by `organizationId` on every query.
+//!
+//! ## Index Schema
+//!
+//! | Field | Type | Attributes |
+//! |-------|------|------------|
+//! | `id` | `Edm.String` | key |
+//! | `organizationId` | `Edm.String` | filterable |
+//! | `title` | `Edm.String` | searchable |
+//! | `description` | `Edm.String` | searchable |
+//! | `scope` | `Edm.String` | filterable |
+//! | `type` | `Edm.String` | filterable |
+//! | `startYear` | `Edm.Int32` | filterable |
+//! | `endYear` | `Edm.Int32` | filterable |
+
+use arshjul_core::models::{Activity, SearchActivitiesRequest};
+use arshjul_core::search::{ActivitySearchIndex, SearchError};
+use async_trait::async_trait;
+use azure_core::auth::TokenCredential;
+use chrono::Datelike;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Search REST API version
+const SEARCH_API_VERSION: &str = "2024-07-01";
+
+/// Token scope for Managed Identity access
+const SEARCH_SCOPE: &str = "https://search.azure.com/.default";
+
+/// How requests to the service are authorized
+enum SearchAuth {
+    ApiKey(String),
+    Credential(Arc<dyn TokenCredential>),
+}
+
+/// Azure AI Search activity index
+pub struct AzureSearchIndex {
+    endpoint: String,
+    index: String,
+    auth: SearchAuth,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct SearchResults {
+    value: Vec<SearchHit>,
+}
+
+#[derive(Deserialize)]
+struct SearchHit {
+    id: String,
+}
+
+impl AzureSearchIndex {
+    /// Create with an admin API key, or Managed Identity when `api_key` is None
+    pub fn new(endpoint: &str, index: &str, api_key: Option<&str>) -> Result<Self, SearchError> {
+        let auth = match api_key {
+            Some(key) => SearchAuth::ApiKey(key.to_string()),
+            None => SearchAuth::Credential(azure_identity::create_credential()
+                .map_err(|e| SearchError::Unavailable(format!("Failed to create Azure credential: {}", e)))?),
+        };
+        
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            index: index.to_string(),
+            auth,
+            http: reqwest::Client::new(),
+        })
+    }
+    
+    fn docs_url(&self, operation: &str) -> String {
+        format!(
+            "{}/indexes/{}/docs/{}?api-version={}",
+            self.endpoint, self.index, operation, SEARCH_API_VERSION
+        )
+    }
+    
+    async fn post(&self, operation: &str, body: serde_json::Value) -> Result<reqwest::Response, SearchError> {
+        let request = self.http.post(self.docs_url(operation)).json(&body);
+        let request = match self.auth {
+            SearchAuth::ApiKey(ref key) => request.header("api-key", key),
+            SearchAuth::Credential(ref credential) => {
+                let token = credential.get_token(&[SEARCH_SCOPE]).await
+                    .map_err(|e| SearchError::Unavailable(e.to_string()))?;
+                request.bearer_auth(token.token.secret())
+            }
+        };
+        
+        let response = request.send().await
+            .map_err(|e| SearchError::Unavailable(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(SearchError::Unavailable(format!("Azure AI Search returned {}", response.status())));
+        }
+        Ok(response)
+    }
+    
+    async fn index_documents(&self, documents: Vec<serde_json::Value>) -> Result<(), SearchError> {
+        self.post("index", serde_json::json!({ "value": documents })).await.map(|_| ())
+    }
+}
+
+/// Quote a value for an OData filter
+fn odata_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// OData filter restricting a query to the organization and request filters
+fn build_filter(organization_id: &str, request: &SearchActivitiesRequest) -> String {
+    let mut clauses = vec![format!("organizationId eq {}", odata_string(organization_id))];
+    
+    if let Some(year) = request.year {
+        clauses.push(format!("startYear le {} and endYear ge {}", year, year));
+    }
+    if let Some(ref layer_ids) = request.layer_ids {
+        clauses.push(format!("search.in(scope, {}, '|')", odata_string(&layer_ids.join("|"))));
+    }
+    
+    clauses.join(" and ")
+}
+
+/// Escape simple query syntax operators so user text is matched literally
+fn escape_query(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "+-&|!(){}[]^\"~*?:\\/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[async_trait]
+impl ActivitySearchIndex for AzureSearchIndex {
+    fn name(&self) -> &'static str {
+        "azure-ai-search"
+    }
+    
+    async fn upsert(&self, activity: &Activity) -> Result<(), SearchError> {
+        let activity_type = serde_json::to_value(&activity.activity_type)
+            .map_err(|e| SearchError::InvalidResponse(e.to_string()))?;
+        
+        self.index_documents(vec![serde_json::json!({
+            "@search.action": "mergeOrUpload",
+            "id": activity.id,
+            "organizationId": activity.organization_id,
+            "title": activity.title,
+            "description": activity.description,
+            "scope": activity.scope,
+            "type": activity_type,
+            "startYear": activity.start_date.year(),
+            "endYear": activity.end_date.year(),
+        })]).await
+    }
+    
+    async fn remove(&self, _organization_id: &str, activity_id: &str) -> Result<(), SearchError> {
+        self.index_documents(vec![serde_json::json!({
+            "@search.action": "delete",
+            "id": activity_id,
+        })]).await
+    }
+    
+    async fn search(
+        &self,
+        organization_id: &str,
+        request: &SearchActivitiesRequest,
+        top: usize,
+    ) -> Result<Vec<String>, SearchError> {
+        let response = self.post("search", serde_json::json!({
+            "search": escape_query(request.q.trim()),
+            "searchMode": "all",
+            "searchFields": "title,description",
+            "filter": build_filter(organization_id, request),
+            "select": "id",
+            "top": top,
+        })).await?;
+        
+        let results: SearchResults = response.json().await
+            .map_err(|e| SearchError::InvalidResponse(e.to_string()))?;
+        Ok(results.value.into_iter().map(|hit| hit.id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_build_filter() {
+        let request = SearchActivitiesRequest {
+            q: "budget".to_string(),
+            layer_ids: Some(vec!["layer-1".to_string(), "layer-2".to_string()]),
+            year: Some(2025),
+            top: None,
+        };
+        
+        assert_eq!(
+            build_filter("org'1", &request),
+            "organizationId eq 'org''1' and startYear le 2025 and endYear ge 2025 and search.in(scope, 'layer-1|layer-2', '|')"
+        );
+        assert_eq!(escape_query("q3 (draft)"), "q3 \\(draft\\)");
+    }
+}
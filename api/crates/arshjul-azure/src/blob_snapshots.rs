@@ -0,0 +1,263 @@
+//! # Blob Snapshot Store
+//!
+//! Writes share snapshots to an Azure Blob Storage container (typically
+//! fronted by Azure Front Door / CDN) through the Blob REST API, authorized
+//! with a container SAS URL (read, write, delete and list permissions).
+//!
+//! The same store keeps organization snapshots
+//! ([`OrganizationSnapshotStore`]), in a private container of their own
+//! with no CDN in front, and the records and inputs of background
+//! operations ([`OperationStore`]).
+
+use arshjul_core::events::EventError;
+use arshjul_core::models::BulkOperation;
+use arshjul_core::operations::{input_path, operation_path, OperationStore};
+use arshjul_core::org_snapshots::{snapshot_path, OrganizationSnapshotStore, StoredSnapshot};
+use arshjul_core::snapshots::SnapshotStore;
+use arshjul_core::storage::StorageError;
+use async_trait::async_trait;
+
+/// Azure Blob Storage implementation of [`SnapshotStore`], [`OrganizationSnapshotStore`] and [`OperationStore`]
+pub struct BlobSnapshotStore {
+    /// Container URL without query (e.g., "https://acct.blob.core.windows.net/snapshots")
+    container_url: String,
+    /// SAS token query string (without leading '?')
+    sas: String,
+    http: reqwest::Client,
+}
+
+impl BlobSnapshotStore {
+    /// Create from a container SAS URL; `None` if it has no SAS token
+    pub fn from_sas_url(sas_url: &str) -> Option<Self> {
+        let (container_url, sas) = sas_url.split_once('?')?;
+        if sas.is_empty() {
+            return None;
+        }
+        
+        Some(Self {
+            container_url: container_url.trim_end_matches('/').to_string(),
+            sas: sas.to_string(),
+            http: reqwest::Client::new(),
+        })
+    }
+    
+    fn blob_url(&self, path: &str) -> String {
+        format!("{}/{}?{}", self.container_url, path, self.sas)
+    }
+    
+    /// List Blobs response for a prefix
+    async fn list_xml(&self, prefix: &str) -> Result<String, String> {
+        let url = format!("{}?restype=container&comp=list&{}", self.container_url, self.sas);
+        let response = self.http.get(&url)
+            .query(&[("prefix", prefix)])
+            .header("x-ms-version", "2021-08-06")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        
+        if !response.status().is_success() {
+            return Err(format!("Blob list returned {}", response.status()));
+        }
+        response.text().await.map_err(|e| e.to_string())
+    }
+    
+    /// Content of a blob; `NotFound` with `id` if there is none
+    async fn download(&self, path: &str, id: &str) -> Result<Vec<u8>, StorageError> {
+        self.download_tagged(path, id).await.map(|(body, _)| body)
+    }
+    
+    /// Content of a blob and its ETag
+    async fn download_tagged(&self, path: &str, id: &str) -> Result<(Vec<u8>, String), StorageError> {
+        let response = self.http.get(self.blob_url(path))
+            .header("x-ms-version", "2021-08-06")
+            .send()
+            .await
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(StorageError::Storage(format!("Blob download returned {}", response.status())));
+        }
+        let etag = response_etag(&response)?;
+        Ok((response.bytes().await.map_err(|e| StorageError::Storage(e.to_string()))?.to_vec(), etag))
+    }
+    
+    /// Names of blobs under a prefix
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, EventError> {
+        let xml = self.list_xml(prefix).await.map_err(EventError::Delivery)?;
+        Ok(parse_blobs(&xml).into_iter().map(|(name, _)| name).collect())
+    }
+}
+
+/// `ETag` header of a blob response
+fn response_etag(response: &reqwest::Response) -> Result<String, StorageError> {
+    response.headers().get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| StorageError::Storage("Blob response without ETag".to_string()))
+}
+
+/// Names and `<Content-Length>` of the blobs in a List Blobs response
+fn parse_blobs(xml: &str) -> Vec<(String, u64)> {
+    let element = |blob: &str, tag: &str| {
+        let (_, rest) = blob.split_once(&format!("<{}>", tag))?;
+        rest.split_once(&format!("</{}>", tag)).map(|(value, _)| value.to_string())
+    };
+    xml.split("<Blob>")
+        .skip(1)
+        .filter_map(|blob| {
+            let size = element(blob, "Content-Length").and_then(|s| s.parse().ok()).unwrap_or(0);
+            Some((element(blob, "Name")?, size))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl SnapshotStore for BlobSnapshotStore {
+    async fn put(&self, path: &str, content_type: &str, cache_control: &str, body: Vec<u8>) -> Result<(), EventError> {
+        let response = self.http.put(self.blob_url(path))
+            .header("x-ms-version", "2021-08-06")
+            .header("x-ms-blob-type", "BlockBlob")
+            .header("x-ms-blob-content-type", content_type)
+            .header("x-ms-blob-cache-control", cache_control)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(EventError::Delivery(format!("Blob upload returned {}", response.status())));
+        }
+        Ok(())
+    }
+    
+    async fn delete_prefix(&self, prefix: &str) -> Result<(), EventError> {
+        for name in self.list(prefix).await? {
+            let response = self.http.delete(self.blob_url(&name))
+                .header("x-ms-version", "2021-08-06")
+                .send()
+                .await
+                .map_err(|e| EventError::Delivery(e.to_string()))?;
+            
+            // Already gone is fine
+            if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+                return Err(EventError::Delivery(format!("Blob delete returned {}", response.status())));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrganizationSnapshotStore for BlobSnapshotStore {
+    async fn put(&self, organization_id: &str, snapshot_id: &str, body: Vec<u8>) -> Result<(), StorageError> {
+        SnapshotStore::put(self, &snapshot_path(organization_id, snapshot_id), "application/json", "no-store", body).await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+    
+    async fn get(&self, organization_id: &str, snapshot_id: &str) -> Result<Vec<u8>, StorageError> {
+        self.download(&snapshot_path(organization_id, snapshot_id), snapshot_id).await
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<StoredSnapshot>, StorageError> {
+        let prefix = format!("{}/", organization_id);
+        let xml = self.list_xml(&prefix).await.map_err(StorageError::Storage)?;
+        Ok(parse_blobs(&xml).into_iter()
+            .filter_map(|(name, bytes)| Some(StoredSnapshot {
+                id: name.strip_prefix(&prefix)?.strip_suffix(".json")?.to_string(),
+                bytes,
+            }))
+            .collect())
+    }
+    
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        let count = OrganizationSnapshotStore::list(self, organization_id).await?.len() as u64;
+        SnapshotStore::delete_prefix(self, &format!("{}/", organization_id)).await
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        Ok(count)
+    }
+}
+
+#[async_trait]
+impl OperationStore for BlobSnapshotStore {
+    async fn put(&self, operation: &BulkOperation) -> Result<(), StorageError> {
+        let body = serde_json::to_vec(operation).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        SnapshotStore::put(self, &operation_path(&operation.organization_id, &operation.id), "application/json", "no-store", body).await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+    
+    async fn get(&self, organization_id: &str, operation_id: &str) -> Result<BulkOperation, StorageError> {
+        let body = self.download(&operation_path(organization_id, operation_id), operation_id).await?;
+        serde_json::from_slice(&body).map_err(|e| StorageError::Serialization(e.to_string()))
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, operation_id: &str) -> Result<(BulkOperation, String), StorageError> {
+        let (body, etag) = self.download_tagged(&operation_path(organization_id, operation_id), operation_id).await?;
+        let operation = serde_json::from_slice(&body).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        Ok((operation, etag))
+    }
+    
+    /// Put Blob with `If-Match`, which answers 412 once someone else wrote the blob
+    async fn replace(&self, operation: &BulkOperation, etag: &str) -> Result<Option<String>, StorageError> {
+        let body = serde_json::to_vec(operation).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let response = self.http.put(self.blob_url(&operation_path(&operation.organization_id, &operation.id)))
+            .header("x-ms-version", "2021-08-06")
+            .header("x-ms-blob-type", "BlockBlob")
+            .header("x-ms-blob-content-type", "application/json")
+            .header("x-ms-blob-cache-control", "no-store")
+            .header(reqwest::header::IF_MATCH, etag)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        
+        match response.status() {
+            reqwest::StatusCode::PRECONDITION_FAILED => Ok(None),
+            reqwest::StatusCode::NOT_FOUND => Err(StorageError::NotFound(operation.id.clone())),
+            status if status.is_success() => response_etag(&response).map(Some),
+            status => Err(StorageError::Storage(format!("Blob upload returned {}", status))),
+        }
+    }
+    
+    async fn list(&self) -> Result<Vec<BulkOperation>, StorageError> {
+        let names = BlobSnapshotStore::list(self, "").await.map_err(|e| StorageError::Storage(e.to_string()))?;
+        let mut operations = Vec::new();
+        for name in names.iter().filter(|n| n.ends_with(".json") && !n.ends_with(".input.json")) {
+            let body = self.download(name, name).await?;
+            operations.push(serde_json::from_slice(&body).map_err(|e| StorageError::Serialization(e.to_string()))?);
+        }
+        Ok(operations)
+    }
+    
+    async fn delete(&self, organization_id: &str, operation_id: &str) -> Result<(), StorageError> {
+        // `{org}/{id}.` covers the record and the input
+        self.delete_prefix(&format!("{}/{}.", organization_id, operation_id)).await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+    
+    async fn put_input(&self, organization_id: &str, operation_id: &str, body: Vec<u8>) -> Result<(), StorageError> {
+        SnapshotStore::put(self, &input_path(organization_id, operation_id), "application/json", "no-store", body).await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+    
+    async fn get_input(&self, organization_id: &str, operation_id: &str) -> Result<Vec<u8>, StorageError> {
+        self.download(&input_path(organization_id, operation_id), operation_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sas_url_and_listing() {
+        let store = BlobSnapshotStore::from_sas_url("https://acct.blob.core.windows.net/snapshots/?sv=2022&sig=abc").unwrap();
+        assert_eq!(store.blob_url("s1/h.json"), "https://acct.blob.core.windows.net/snapshots/s1/h.json?sv=2022&sig=abc");
+        assert!(BlobSnapshotStore::from_sas_url("https://acct.blob.core.windows.net/snapshots").is_none());
+        
+        let xml = "<EnumerationResults><Blobs><Blob><Name>s1/a.json</Name><Properties><Content-Length>120</Content-Length></Properties></Blob><Blob><Name>s1/b.json</Name></Blob></Blobs></EnumerationResults>";
+        assert_eq!(parse_blobs(xml), vec![("s1/a.json".to_string(), 120), ("s1/b.json".to_string(), 0)]);
+    }
+}
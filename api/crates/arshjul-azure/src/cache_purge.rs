@@ -0,0 +1,109 @@
+//! # Cache Purge
+//!
+//! [`CacheInvalidator`] implementations for Azure-hosted caches:
+//! - [`FrontDoorPurger`] - purges public share paths from an Azure Front Door endpoint
+//! - `RedisInvalidator` - deletes cached responses from Azure Cache for Redis (`redis` feature)
+
+use arshjul_core::events::EventError;
+use arshjul_core::invalidation::{public_share_path, CacheInvalidator};
+use async_trait::async_trait;
+use azure_core::auth::TokenCredential;
+use std::sync::Arc;
+
+/// Azure Resource Manager API version for Front Door purges
+const FRONT_DOOR_API_VERSION: &str = "2024-02-01";
+
+/// Azure Resource Manager token scope
+const MANAGEMENT_SCOPE: &str = "https://management.azure.com/.default";
+
+/// Purges content from an Azure Front Door (Standard/Premium) endpoint
+pub struct FrontDoorPurger {
+    /// Endpoint resource ID (`/subscriptions/.../profiles/{profile}/afdEndpoints/{endpoint}`)
+    endpoint_resource_id: String,
+    credential: Arc<dyn TokenCredential>,
+    http: reqwest::Client,
+}
+
+impl FrontDoorPurger {
+    /// Create using Managed Identity / Azure CLI credentials
+    pub fn new(endpoint_resource_id: &str) -> Result<Self, EventError> {
+        let credential = azure_identity::create_credential()
+            .map_err(|e| EventError::Delivery(format!("Failed to create Azure credential: {}", e)))?;
+        
+        Ok(Self {
+            endpoint_resource_id: endpoint_resource_id.trim_end_matches('/').to_string(),
+            credential,
+            http: reqwest::Client::new(),
+        })
+    }
+    
+    fn purge_url(&self) -> String {
+        format!(
+            "https://management.azure.com{}/purge?api-version={}",
+            self.endpoint_resource_id, FRONT_DOOR_API_VERSION
+        )
+    }
+}
+
+#[async_trait]
+impl CacheInvalidator for FrontDoorPurger {
+    fn name(&self) -> &'static str {
+        "front-door"
+    }
+    
+    async fn invalidate(&self, short_codes: &[String]) -> Result<(), EventError> {
+        let token = self.credential.get_token(&[MANAGEMENT_SCOPE]).await
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        
+        let content_paths: Vec<String> = short_codes.iter().map(|c| public_share_path(c)).collect();
+        
+        // Accepted (202); the purge completes asynchronously within minutes
+        let response = self.http.post(self.purge_url())
+            .bearer_auth(token.token.secret())
+            .json(&serde_json::json!({ "contentPaths": content_paths }))
+            .send()
+            .await
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(EventError::Delivery(format!("Front Door purge returned {}", response.status())));
+        }
+        Ok(())
+    }
+}
+
+/// Deletes cached public share responses (`{prefix}{shortCode}`) from Redis
+#[cfg(feature = "redis")]
+pub struct RedisInvalidator {
+    client: redis::Client,
+    key_prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisInvalidator {
+    /// Create from a `rediss://` connection URL
+    pub fn new(url: &str, key_prefix: &str) -> Result<Self, EventError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        Ok(Self { client, key_prefix: key_prefix.to_string() })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl CacheInvalidator for RedisInvalidator {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+    
+    async fn invalidate(&self, short_codes: &[String]) -> Result<(), EventError> {
+        let mut connection = self.client.get_multiplexed_async_connection().await
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        
+        let keys: Vec<String> = short_codes.iter().map(|c| format!("{}{}", self.key_prefix, c)).collect();
+        redis::cmd("DEL").arg(&keys)
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| EventError::Delivery(e.to_string()))
+    }
+}
@@ -0,0 +1,1401 @@
+//! # Azure Cosmos DB
+//!
+//! Upgrade path for larger tenants: native TTL and global distribution.
+//! Containers use `/organizationId` as partition key path, except the short
+//! code index, which is partitioned by the code itself.
+//!
+//! ## Bootstrap
+//!
+//! Constructing a client makes no requests. The database and containers
+//! are created by [`CosmosStorageClient::create_containers`] at deploy time;
+//! at startup [`CosmosStorageClient::check_containers`] only lists them.
+//!
+//! ## Shares
+//!
+//! - Reads by `(organizationId, id)` are point reads
+//! - Public access looks shares up by `shortCode` with a cross-partition query
+//! - Expiry uses native TTL: each document carries its `ttl`, counted from its
+//!   last write (the container is created with TTL enabled). Every write,
+//!   including the view count patch, sets it to the time left until
+//!   `expiresAt`, so writes don't extend a share's life
+//! - The `shortcodes` container keeps codes unique across partitions and
+//!   holds the tombstones of deleted shares (see [`ShortCodeTombstone`])
+//! - Pages are in `id` order; the continuation token is the last `id` of the
+//!   previous page, as in the Table Storage and in-memory backends
+//! - `list_by_layer` is one query in the partition (`ARRAY_CONTAINS` over
+//!   `layerConfig.layerIds`)
+//! - `replace` (renewals) is conditional on the `_etag` of `get_tagged`; it
+//!   keeps the short code
+//!
+//! ## Activities
+//!
+//! - All queries stay in the organization's partition
+//! - `list_by_layers` is one query: `ARRAY_CONTAINS` over the layer IDs and a
+//!   year overlap on `startDate`/`endDate` (see [`Filter::layers_and_year`])
+//! - `list` pages like shares
+//! - `apply_changes` (splits and merges) is one transactional batch in the
+//!   organization's partition, with each replace and delete conditional on
+//!   the `_etag` of `get_tagged`. The SDK has no batch API, so the batch is
+//!   a REST request signed with the primary key (see [`MasterKey`]); clients
+//!   without a key write the changes one by one
+//!
+//! ## Layers
+//!
+//! - `layers` container; reads by `(organizationId, id)` are point reads
+//! - `list` reads the organization's partition, sorted by `ringIndex`
+//!
+//! ## Activity types
+//!
+//! - Documents use the type key as `id` (see [`KeyedDocument`])
+//! - `delete` refuses system types; the delete is conditional on the ETag of
+//!   the document checked, as with short code reclaims
+//!
+//! ## Policies
+//!
+//! - `policies` container, one document per organization with `id` `policy`
+//! - `get` returns the default policy for organizations that never set one
+//!
+//! ## Audit log
+//!
+//! - `audit` container; entry IDs are time-ordered, and `list` sorts by them
+//!   so entries come oldest first
+//! - `record` upserts, so a retried write leaves one entry
+//! - `delete_all` queries the partition's IDs and deletes entry by entry
+//!
+//! ## Counters
+//!
+//! - `counters` container; documents use the counter name as `id`. They
+//!   back the share `totalCount` (see [`arshjul_core::counters`])
+//! - `add` is a single increment patch, so concurrent changes don't
+//!   conflict; a counter patched below zero reads as zero
+//!
+//! ## Recycle bin
+//!
+//! [`DeletedItemPurger`] finds binned shares and activities with one
+//! cross-partition query per container on `deletedAt`, then deletes each
+//! conditional on the ETag it was read at, so an item restored meanwhile is
+//! kept. Purged shares leave a tombstone in `shortcodes`, as deletes do.
+//!
+//! ## User settings
+//!
+//! - `usersettings` container; documents use the user ID as `id`
+//! - `get` returns defaults for users who never saved settings, as the other
+//!   backends do
+//!
+//! ## Change feed
+//!
+//! The SDK has no change feed API, so [`ChangeFeed`] polls the `shares` and
+//! `activities` containers with a cross-partition query on the server
+//! timestamp `_ts`, which returns the latest version of each document
+//! written since, as the change feed's latest-version mode does. Deletes
+//! don't show. A changed share whose code is missing from `shortcodes`
+//! (written by a restore or a migration script) gets its entry back.
+//!
+//! ## Health
+//!
+//! The [`StorageProbe`] reads the properties of every container, so a
+//! missing container or a role assignment without read access fails
+//! `GET /api/health`. It writes nothing: a point read of an item can't tell
+//! a missing item from a missing container (both are 404), and an item
+//! written for the probe would show up in every query and the change feed.
+
+use arshjul_core::models::{Activity, ActivityTypeConfig, AuditEntry, Layer, OrganizationPolicy, ShareLink, ShortCodeTombstone, UserSettings};
+use arshjul_core::storage::memory_storage::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use arshjul_core::storage::{self, ActivityChanges, ActivityStorage, ActivityTypeStorage, AuditStorage, ChangeFeed, ChangedDocument, Counter, CounterStorage, DeletedItemPurger, Filter, LayerStorage, PolicyStorage, QueryOptions, QueryResult, ShareStorage, StorageError, StorageProbe, UserSettingsStorage};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine};
+use azure_core_cosmos::http::Etag;
+use azure_data_cosmos::clients::ContainerClient;
+use azure_data_cosmos::models::{ContainerProperties, PatchDocument};
+use azure_data_cosmos::{CosmosClient, ItemOptions, Query};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::time::Duration;
+
+// Re-export the Secret type from the azure_core that azure_data_cosmos uses (0.30)
+// We can't use our azure_core 0.21 for this
+
+/// Container names used by the application
+const CONTAINER_SHARES: &str = "shares";
+const CONTAINER_ACTIVITIES: &str = "activities";
+const CONTAINER_LAYERS: &str = "layers";
+const CONTAINER_ACTIVITY_TYPES: &str = "activitytypes";
+const CONTAINER_SHORT_CODES: &str = "shortcodes";
+const CONTAINER_USER_SETTINGS: &str = "usersettings";
+const CONTAINER_COUNTERS: &str = "counters";
+const CONTAINER_POLICIES: &str = "policies";
+const CONTAINER_AUDIT: &str = "audit";
+
+/// `id` of an organization's policy, the only document in its partition
+const POLICY_ID: &str = "policy";
+
+/// Default TTL of containers, so per-document `ttl` applies; documents
+/// without one practically never expire (the SDK can't express `-1`)
+const CONTAINER_DEFAULT_TTL_SECONDS: u64 = i32::MAX as u64;
+
+/// Reads and conditional patches counting one view before giving up;
+/// views of one share often arrive together
+const VIEW_PATCH_ATTEMPTS: usize = 16;
+
+/// A share as written at `now`; Cosmos DB counts `ttl` from the write, so a
+/// share that expires gets the time left until `expiresAt`
+fn expiring(mut share: ShareLink, now: DateTime<Utc>) -> ShareLink {
+    if share.ttl.is_some() {
+        share.ttl = Some((share.expires_at - now).num_seconds().max(1));
+    }
+    share
+}
+
+/// Short code index entry, keyed and partitioned by the code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShortCodeEntry {
+    /// The short code
+    id: String,
+    organization_id: String,
+    share_id: String,
+    /// Set once the share is deleted; the entry then expires with the tombstone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retired: Option<ShortCodeTombstone>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ttl: Option<i64>,
+    #[serde(rename = "_etag", default, skip_serializing)]
+    etag: Option<String>,
+}
+
+impl ShortCodeEntry {
+    fn for_share(share: &ShareLink) -> Self {
+        Self {
+            id: share.short_code.clone(),
+            organization_id: share.organization_id.clone(),
+            share_id: share.id.clone(),
+            retired: None,
+            ttl: None,
+            etag: None,
+        }
+    }
+}
+
+/// Document of a model keyed by something other than an `id` (activity
+/// type key, user ID, policy); Cosmos DB needs one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyedDocument<T> {
+    id: String,
+    #[serde(flatten)]
+    item: T,
+    #[serde(rename = "_etag", default, skip_serializing)]
+    etag: Option<String>,
+}
+
+impl<T> KeyedDocument<T> {
+    fn new(id: String, item: T) -> Self {
+        Self {
+            id,
+            item,
+            etag: None,
+        }
+    }
+}
+
+/// HTTP status of a failed request
+fn status(e: &azure_core_cosmos::Error) -> Option<u16> {
+    e.http_status().map(u16::from)
+}
+
+/// Map a failed request to a storage error about `id`
+fn storage_error(e: azure_core_cosmos::Error, id: &str) -> StorageError {
+    match status(&e) {
+        Some(404) => StorageError::NotFound(id.to_string()),
+        Some(409) | Some(412) => StorageError::AlreadyExists(id.to_string()),
+        _ => query_error(e),
+    }
+}
+
+/// Map a failed query, or a request without a single entity to blame
+///
+/// 429 is throttling, 449 a write conflict the client should retry and 503
+/// load shedding; none of them carried out the request.
+fn query_error(e: azure_core_cosmos::Error) -> StorageError {
+    match status(&e) {
+        Some(429) | Some(449) | Some(503) => StorageError::Transient(e.to_string()),
+        _ => StorageError::Storage(e.to_string()),
+    }
+}
+
+/// `WHERE` conditions and their named parameters
+type SqlConditions = (Vec<String>, Vec<(String, serde_json::Value)>);
+
+/// Query with a `WHERE` clause for the conditions
+fn build_query(select: &str, (conditions, parameters): SqlConditions, order_by: Option<&str>) -> Result<Query, StorageError> {
+    let mut sql = select.to_string();
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+    }
+    if let Some(order_by) = order_by {
+        sql.push_str(" ORDER BY ");
+        sql.push_str(order_by);
+    }
+    let mut query = Query::from(sql);
+    for (name, value) in parameters {
+        query = query.with_parameter(name, value).map_err(|e| StorageError::Storage(e.to_string()))?;
+    }
+    Ok(query)
+}
+
+/// Azure Cosmos DB client wrapper
+pub struct CosmosStorageClient {
+    client: CosmosClient,
+    database_name: String,
+    /// For requests the SDK can't make (transactional batches)
+    master_key: Option<MasterKey>,
+    http: reqwest::Client,
+}
+
+/// REST API version of transactional batches
+const BATCH_API_VERSION: &str = "2020-07-15";
+
+/// Most operations Cosmos DB takes in one transactional batch
+const MAX_BATCH_OPERATIONS: usize = 100;
+
+/// Endpoint and primary key for signing REST requests
+/// (`type=master` authorization)
+struct MasterKey {
+    endpoint: String,
+    key: Vec<u8>,
+}
+
+impl MasterKey {
+    fn new(endpoint: &str, primary_key: &str) -> Result<Self, StorageError> {
+        let key = BASE64_STANDARD.decode(primary_key)
+            .map_err(|e| StorageError::Storage(format!("Cosmos DB primary key is not base64: {}", e)))?;
+        Ok(Self { endpoint: endpoint.trim_end_matches('/').to_string(), key })
+    }
+    
+    /// `authorization` header of a request on `resource_link` at `date` (RFC 1123)
+    fn authorization(&self, verb: &str, resource_type: &str, resource_link: &str, date: &str) -> String {
+        let payload = format!("{}\n{}\n{}\n{}\n\n", verb.to_lowercase(), resource_type.to_lowercase(), resource_link, date.to_lowercase());
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        mac.update(payload.as_bytes());
+        let signature = BASE64_STANDARD.encode(mac.finalize().into_bytes());
+        crate::percent_encode(&format!("type=master&ver=1.0&sig={}", signature))
+    }
+}
+
+/// Result of one operation of a transactional batch
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchResult {
+    status_code: u16,
+}
+
+/// Operations of a transactional batch for the changes
+fn batch_operations(changes: &ActivityChanges) -> Vec<serde_json::Value> {
+    let if_match = |id: &str| changes.etags.get(id).cloned();
+    let mut operations = Vec::with_capacity(changes.len());
+    for activity in &changes.create {
+        operations.push(serde_json::json!({ "operationType": "Create", "resourceBody": activity }));
+    }
+    for activity in &changes.update {
+        let mut operation = serde_json::json!({ "operationType": "Replace", "id": activity.id, "resourceBody": activity });
+        if let Some(etag) = if_match(&activity.id) {
+            operation["ifMatch"] = etag.into();
+        }
+        operations.push(operation);
+    }
+    for activity_id in &changes.delete {
+        let mut operation = serde_json::json!({ "operationType": "Delete", "id": activity_id });
+        if let Some(etag) = if_match(activity_id) {
+            operation["ifMatch"] = etag.into();
+        }
+        operations.push(operation);
+    }
+    operations
+}
+
+/// IDs of the batch operations, in order
+fn batch_ids(changes: &ActivityChanges) -> impl Iterator<Item = &str> {
+    changes.create.iter().chain(&changes.update).map(|a| a.id.as_str())
+        .chain(changes.delete.iter().map(String::as_str))
+}
+
+/// Map a failed batch to an error about the failing operation (the others
+/// report 424), or about the batch if the response has no results
+fn batch_error(status: u16, results: &[BatchResult], changes: &ActivityChanges) -> StorageError {
+    let failed = results.iter().zip(batch_ids(changes))
+        .find(|(result, _)| !(200..300).contains(&result.status_code) && result.status_code != 424);
+    let (status, id) = match failed {
+        Some((result, id)) => (result.status_code, Some(id.to_string())),
+        None => (status, None),
+    };
+    match (status, id) {
+        (404, Some(id)) => StorageError::NotFound(id),
+        (409, Some(id)) => StorageError::AlreadyExists(id),
+        (412, Some(id)) => StorageError::Conflict(id),
+        (429 | 449 | 503, _) => StorageError::Transient(format!("Activity batch failed with status {}", status)),
+        _ => StorageError::Storage(format!("Activity batch failed with status {}", status)),
+    }
+}
+
+/// Check if an error string indicates a 409 Conflict (resource already exists)
+fn is_conflict_error_str(error_msg: &str) -> bool {
+    error_msg.contains("409") || error_msg.contains("Conflict") || error_msg.contains("conflict")
+}
+
+impl CosmosStorageClient {
+    /// Container names used by the application
+    const CONTAINER_NAMES: [&'static str; 9] = [
+        CONTAINER_SHARES,
+        CONTAINER_ACTIVITIES,
+        CONTAINER_LAYERS,
+        CONTAINER_ACTIVITY_TYPES,
+        CONTAINER_SHORT_CODES,
+        CONTAINER_USER_SETTINGS,
+        CONTAINER_COUNTERS,
+        CONTAINER_POLICIES,
+        CONTAINER_AUDIT,
+    ];
+    
+    /// Create using primary key authentication (requires key_auth feature)
+    /// The database and containers must exist (see [`Self::create_containers`])
+    /// 
+    /// # Arguments
+    /// * `endpoint` - Full endpoint URL (e.g., "https://myaccount.documents.azure.com")
+    /// * `database_name` - Name of the database to use/create
+    /// * `primary_key` - Cosmos DB primary key
+    #[cfg(feature = "key_auth")]
+    pub async fn new_with_key(endpoint: &str, database_name: &str, primary_key: &str) -> Result<Self, StorageError> {
+        use azure_data_cosmos::CosmosClient;
+        
+        tracing::info!("Connecting to Azure Cosmos DB endpoint: {} using primary key", endpoint);
+        
+        // Create client using with_key - convert to owned String for Secret
+        // The azure_data_cosmos 0.29 SDK expects a value that implements Into<Secret>
+        let key_string = primary_key.to_string();
+        let client = CosmosClient::with_key(endpoint, key_string.into(), None)
+            .map_err(|e| StorageError::Storage(format!("Failed to create Cosmos client: {}", e)))?;
+        
+        Ok(Self {
+            master_key: Some(MasterKey::new(endpoint, primary_key)?),
+            ..Self::connect(client, database_name)
+        })
+    }
+    
+    /// Create using Managed Identity authentication
+    /// The database and containers must exist (see [`Self::create_containers`])
+    /// 
+    /// # Arguments
+    /// * `endpoint` - Full endpoint URL (e.g., "https://myaccount.documents.azure.com")
+    /// * `database_name` - Name of the database to use/create
+    /// 
+    /// # Authentication
+    /// Uses DefaultAzureCredential which supports:
+    /// - Managed Identity (in Azure - App Service, Functions, AKS, VMs)
+    /// - Azure CLI credentials (for local development with `az login`)
+    pub async fn new_with_managed_identity(endpoint: &str, _database_name: &str) -> Result<Self, StorageError> {
+        tracing::info!("Connecting to Azure Cosmos DB endpoint: {} using Managed Identity", endpoint);
+        
+        // The azure_data_cosmos crate bundles its own azure_identity
+        // We need to use the types it expects
+        // For now, we'll create a DeveloperToolsCredential via azure_data_cosmos's re-export
+        // Unfortunately, azure_data_cosmos 0.29 doesn't re-export credential types
+        // So we need to add azure_identity 0.30 as a direct dependency for Cosmos only
+        
+        // Since we can't easily mix credential versions, we'll require key auth for now
+        // and use Managed Identity only for Table Storage
+        Err(StorageError::Storage(
+            "Managed Identity for Cosmos DB requires azure_identity 0.30 which conflicts with Table Storage SDK. \
+            Please provide COSMOS_PRIMARY_KEY or use Table Storage with Managed Identity instead.".to_string()
+        ))
+    }
+    
+    /// Legacy constructor - delegates to new_with_key if key provided, otherwise errors
+    /// 
+    /// Note: For Managed Identity with Cosmos DB, use a newer version of this SDK
+    /// or configure authentication at the Azure level (APIM, Functions Easy Auth)
+    pub async fn new(_endpoint: &str, _database_name: &str) -> Result<Self, StorageError> {
+        // Without a key, we can't authenticate to Cosmos DB in the current setup
+        Err(StorageError::Storage(
+            "Cosmos DB requires authentication. Provide COSMOS_PRIMARY_KEY or use Table Storage with Managed Identity.".to_string()
+        ))
+    }
+    
+    /// Client for the database (no requests)
+    fn connect(client: CosmosClient, database_name: &str) -> Self {
+        Self {
+            client,
+            database_name: database_name.to_string(),
+            master_key: None,
+            http: reqwest::Client::new(),
+        }
+    }
+    
+    /// Create the database and the containers that don't exist yet,
+    /// returning the containers created; existing containers without a
+    /// default TTL get one, since documents' `ttl` is ignored without it
+    ///
+    /// Control plane operations need more than data access, so this runs at
+    /// deploy time (`arshjul-api init-storage`), not on every start.
+    pub async fn create_containers(&self) -> Result<Vec<&'static str>, StorageError> {
+        let database_name = self.database_name.as_str();
+        
+        // Try to create database (ignore if exists - 409 Conflict)
+        match self.client.create_database(database_name, None).await {
+            Ok(_) => {
+                tracing::info!("Created Cosmos DB database: {}", database_name);
+            }
+            Err(e) => {
+                let error_msg = e.to_string();
+                if is_conflict_error_str(&error_msg) {
+                    tracing::debug!("Database already exists: {}", database_name);
+                } else {
+                    return Err(StorageError::Storage(format!("Failed to create database {}: {}", database_name, error_msg)));
+                }
+            }
+        }
+        
+        // Get database client for container operations
+        let db_client = self.database();
+        
+        // Create containers if they don't exist
+        // Data containers use /organizationId as partition key for multi-tenant isolation;
+        // short codes are unique across organizations, so the index is keyed by the code
+        let mut created = Vec::new();
+        for container_name in Self::CONTAINER_NAMES {
+            let partition_key = match container_name {
+                CONTAINER_SHORT_CODES => "/id",
+                _ => "/organizationId",
+            };
+            let properties = ContainerProperties {
+                id: Cow::Owned(container_name.to_string()),
+                partition_key: partition_key.into(),
+                default_ttl: Some(Duration::from_secs(CONTAINER_DEFAULT_TTL_SECONDS)),
+                ..Default::default()
+            };
+            
+            match db_client.create_container(properties, None).await {
+                Ok(_) => {
+                    tracing::info!("Created Cosmos DB container: {}", container_name);
+                    created.push(container_name);
+                }
+                Err(e) => {
+                    let error_msg = e.to_string();
+                    if is_conflict_error_str(&error_msg) {
+                        tracing::debug!("Container already exists: {}", container_name);
+                        self.enable_ttl(container_name).await?;
+                    } else {
+                        return Err(StorageError::Storage(format!("Failed to create container {}: {}", container_name, error_msg)));
+                    }
+                }
+            }
+        }
+        Ok(created)
+    }
+    
+    /// Turn on TTL of a container created without it
+    async fn enable_ttl(&self, container_name: &str) -> Result<(), StorageError> {
+        let container = self.container(container_name);
+        let error = |e: azure_core_cosmos::Error| StorageError::Storage(format!("Failed to enable TTL on container {}: {}", container_name, e));
+        let mut properties = container.read(None).await.map_err(error)?
+            .into_model().map_err(|e| StorageError::Serialization(e.to_string()))?;
+        if properties.default_ttl.is_some() {
+            return Ok(());
+        }
+        
+        properties.default_ttl = Some(Duration::from_secs(CONTAINER_DEFAULT_TTL_SECONDS));
+        container.replace(properties, None).await.map_err(error)?;
+        tracing::info!("Enabled TTL on Cosmos DB container: {}", container_name);
+        Ok(())
+    }
+    
+    /// Fail unless the database and every container exist, with one query
+    /// listing the database's containers
+    pub async fn check_containers(&self) -> Result<(), StorageError> {
+        let list_error = |e: azure_core_cosmos::Error| StorageError::Storage(format!("Failed to list containers of {}: {}", self.database_name, e));
+        let mut pager = self.database().query_containers("SELECT * FROM c", None).map_err(list_error)?;
+        let mut existing = Vec::new();
+        while let Some(container) = pager.next().await {
+            existing.push(container.map_err(list_error)?.id.into_owned());
+        }
+        
+        let missing: Vec<&str> = Self::CONTAINER_NAMES.iter().copied()
+            .filter(|name| !existing.iter().any(|e| e == name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(StorageError::Storage(format!(
+                "Missing containers: {} (run `arshjul-api init-storage`)", missing.join(", ")
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Get container names for documentation/setup
+    pub fn container_names() -> &'static [&'static str] {
+        &Self::CONTAINER_NAMES
+    }
+    
+    /// Get database client
+    pub fn database(&self) -> azure_data_cosmos::clients::DatabaseClient {
+        self.client.database_client(&self.database_name)
+    }
+    
+    /// Get container client
+    pub fn container(&self, name: &str) -> ContainerClient {
+        self.database().container_client(name)
+    }
+    
+    /// Point read; None when the item doesn't exist
+    async fn read<T: DeserializeOwned>(container: &ContainerClient, partition_key: &str, id: &str) -> Result<Option<T>, StorageError> {
+        match container.read_item::<T>(partition_key.to_string(), id, None).await {
+            Ok(response) => response.into_model().map(Some).map_err(|e| StorageError::Serialization(e.to_string())),
+            Err(e) if status(&e) == Some(404) => Ok(None),
+            Err(e) => Err(storage_error(e, id)),
+        }
+    }
+    
+    /// Run a query to the end
+    async fn query<T: DeserializeOwned + Send + 'static>(container: &ContainerClient, query: Query, partition_key: Option<&str>) -> Result<Vec<T>, StorageError> {
+        let mut pager = match partition_key {
+            Some(key) => container.query_items::<T>(query, key.to_string(), None),
+            None => container.query_items::<T>(query, (), None),
+        }.map_err(|e| StorageError::Storage(e.to_string()))?;
+        
+        let mut items = Vec::new();
+        while let Some(item) = pager.next().await {
+            items.push(item.map_err(query_error)?);
+        }
+        Ok(items)
+    }
+    
+    /// One page of an organization's items in `id` order
+    async fn list_page<T: DeserializeOwned + Send + 'static>(
+        &self,
+        container: &str,
+        organization_id: &str,
+        options: QueryOptions,
+        id_of: impl Fn(&T) -> String,
+    ) -> Result<QueryResult<T>, StorageError> {
+        let page_size = options.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+        let (mut conditions, mut parameters) = match options.filter {
+            Some(ref filter) => filter.to_sql("c", "p"),
+            None => (Vec::new(), Vec::new()),
+        };
+        // The token is the last id of the previous page
+        if let Some(token) = options.continuation_token {
+            conditions.push("c.id > @after".to_string());
+            parameters.push(("@after".to_string(), serde_json::Value::String(token)));
+        }
+        
+        // One extra row tells whether there is a next page
+        let select = format!("SELECT TOP {} * FROM c", page_size + 1);
+        let query = build_query(&select, (conditions, parameters), Some("c.id"))?;
+        
+        let mut items: Vec<T> = Self::query(&self.container(container), query, Some(organization_id)).await?;
+        let continuation_token = (items.len() > page_size).then(|| {
+            items.truncate(page_size);
+            items.last().map(&id_of)
+        }).flatten();
+        
+        Ok(QueryResult {
+            items,
+            continuation_token,
+            total_count: None,
+        })
+    }
+    
+    /// Reserve a share's short code; fails while another share or an active tombstone holds it
+    async fn claim_short_code(&self, share: &ShareLink, now: DateTime<Utc>) -> Result<(), StorageError> {
+        let index = self.container(CONTAINER_SHORT_CODES);
+        let code = &share.short_code;
+        let entry = ShortCodeEntry::for_share(share);
+        match index.create_item(code, &entry, None).await {
+            Ok(_) => return Ok(()),
+            Err(e) if status(&e) == Some(409) => {}
+            Err(e) => return Err(storage_error(e, code)),
+        }
+        
+        // Taken: reclaim only entries whose share expired (TTL deletes no index entry) or whose tombstone lapsed
+        let Some(existing) = Self::read::<ShortCodeEntry>(&index, code, code).await? else {
+            return Err(StorageError::AlreadyExists(code.clone()));
+        };
+        let stale = match existing.retired {
+            Some(ref tombstone) => !tombstone.is_active(now),
+            None => Self::read::<ShareLink>(&self.container(CONTAINER_SHARES), &existing.organization_id, &existing.share_id).await?
+                .is_none_or(|holder| holder.short_code != *code),
+        };
+        if !stale {
+            return Err(StorageError::AlreadyExists(code.clone()));
+        }
+        
+        // Conditional on the entry read, so two concurrent claims can't both win
+        let options = ItemOptions {
+            if_match_etag: existing.etag.map(Etag::from),
+            ..Default::default()
+        };
+        index.replace_item(code, code, &entry, Some(options)).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, code))
+    }
+    
+    /// Turn a deleted share's index entry into its tombstone, or drop it
+    async fn retire_short_code(&self, share: &ShareLink, now: DateTime<Utc>) -> Result<(), StorageError> {
+        match ShortCodeTombstone::for_deleted_share(share, now) {
+            Some(tombstone) => {
+                let entry = ShortCodeEntry {
+                    ttl: Some((tombstone.retired_until - now).num_seconds().max(1)),
+                    retired: Some(tombstone),
+                    ..ShortCodeEntry::for_share(share)
+                };
+                self.container(CONTAINER_SHORT_CODES).upsert_item(&share.short_code, &entry, None).await
+                    .map_err(|e| storage_error(e, &share.short_code))?;
+            }
+            None => self.release_short_code(&share.short_code).await,
+        }
+        Ok(())
+    }
+    
+    /// Drop a short code from the index (best effort)
+    async fn release_short_code(&self, code: &str) {
+        if let Err(e) = self.container(CONTAINER_SHORT_CODES).delete_item(code.to_string(), code, None).await {
+            if status(&e) != Some(404) {
+                tracing::warn!("Failed to release short code {}: {}", code, e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ShareStorage for CosmosStorageClient {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let share = expiring(share, Utc::now());
+        self.claim_short_code(&share, Utc::now()).await?;
+        
+        match self.container(CONTAINER_SHARES).create_item(&share.organization_id, &share, None).await {
+            Ok(_) => Ok(share),
+            Err(e) => {
+                self.release_short_code(&share.short_code).await;
+                Err(storage_error(e, &share.id))
+            }
+        }
+    }
+    
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        Self::read(&self.container(CONTAINER_SHARES), organization_id, share_id).await?
+            .ok_or_else(|| StorageError::NotFound(share_id.to_string()))
+    }
+    
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        let query = Query::from("SELECT * FROM c WHERE c.shortCode = @shortCode")
+            .with_parameter("@shortCode", short_code)
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        let shares: Vec<ShareLink> = Self::query(&self.container(CONTAINER_SHARES), query, None).await?;
+        shares.into_iter().next()
+            .ok_or_else(|| StorageError::NotFound(short_code.to_string()))
+    }
+    
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let share = expiring(share, Utc::now());
+        let shares = self.container(CONTAINER_SHARES);
+        let existing: ShareLink = Self::read(&shares, &share.organization_id, &share.id).await?
+            .ok_or_else(|| StorageError::NotFound(share.id.clone()))?;
+        
+        // Keep the index in step with a changed short code
+        let short_code_changed = existing.short_code != share.short_code;
+        if short_code_changed {
+            self.claim_short_code(&share, Utc::now()).await?;
+        }
+        
+        if let Err(e) = shares.replace_item(&share.organization_id, &share.id, &share, None).await {
+            if short_code_changed {
+                self.release_short_code(&share.short_code).await;
+            }
+            return Err(storage_error(e, &share.id));
+        }
+        if short_code_changed {
+            self.release_short_code(&existing.short_code).await;
+        }
+        Ok(share)
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        Self::read::<Tagged<ShareLink>>(&self.container(CONTAINER_SHARES), organization_id, share_id).await?
+            .map(|tagged| (tagged.item, tagged.etag))
+            .ok_or_else(|| StorageError::NotFound(share_id.to_string()))
+    }
+    
+    /// Conditional on the document's ETag; the short code can't change
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        let (existing, _) = ShareStorage::get_tagged(self, &share.organization_id, &share.id).await?;
+        if existing.short_code != share.short_code {
+            return Err(StorageError::Validation(format!("Short code of share {} can't change in a conditional update", share.id)));
+        }
+        
+        let share = expiring(share, Utc::now());
+        let options = ItemOptions {
+            if_match_etag: Some(Etag::from(etag.to_string())),
+            ..Default::default()
+        };
+        match self.container(CONTAINER_SHARES).replace_item(&share.organization_id, &share.id, &share, Some(options)).await {
+            Ok(_) => Ok(Some(share)),
+            Err(e) if status(&e) == Some(412) => Ok(None),
+            Err(e) => Err(storage_error(e, &share.id)),
+        }
+    }
+    
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        let shares = self.container(CONTAINER_SHARES);
+        let Some(share) = Self::read::<ShareLink>(&shares, organization_id, share_id).await? else {
+            return Ok(());
+        };
+        match shares.delete_item(organization_id.to_string(), share_id, None).await {
+            Ok(_) => {}
+            Err(e) if status(&e) == Some(404) => return Ok(()),
+            Err(e) => return Err(storage_error(e, share_id)),
+        }
+        
+        self.retire_short_code(&share, Utc::now()).await
+    }
+    
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        let entry: Option<ShortCodeEntry> = Self::read(&self.container(CONTAINER_SHORT_CODES), short_code, short_code).await?;
+        Ok(entry.and_then(|e| e.retired).filter(|t| t.is_active(Utc::now())))
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        self.list_page(CONTAINER_SHARES, organization_id, options, |s: &ShareLink| s.id.clone()).await
+    }
+    
+    async fn list_by_layer(&self, organization_id: &str, layer_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        let condition = (
+            vec!["ARRAY_CONTAINS(c.layerConfig.layerIds, @layer)".to_string()],
+            vec![("@layer".to_string(), serde_json::json!(layer_id))],
+        );
+        let query = build_query("SELECT * FROM c", condition, None)?;
+        Self::query(&self.container(CONTAINER_SHARES), query, Some(organization_id)).await
+    }
+    
+    /// A patch is a write too, so it sets `ttl` to the time left; conditional
+    /// on the read, so a renewal in between isn't undone
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        let shares = self.container(CONTAINER_SHARES);
+        for _ in 0..VIEW_PATCH_ATTEMPTS {
+            // A missing (or TTL-expired) share is not an error
+            let Some(Tagged { item: share, etag }) = Self::read::<Tagged<ShareLink>>(&shares, organization_id, share_id).await? else {
+                return Ok(());
+            };
+            let now = Utc::now();
+            let mut patch = PatchDocument::default()
+                .with_increment("/stats/viewCount", 1)
+                .and_then(|p| p.with_set("/stats/lastAccessedAt", now));
+            if let Some(ttl) = expiring(share, now).ttl {
+                patch = patch.and_then(|p| p.with_set("/ttl", ttl));
+            }
+            let patch = patch.map_err(|e| StorageError::Storage(e.to_string()))?;
+            
+            let options = ItemOptions {
+                if_match_etag: Some(Etag::from(etag)),
+                ..Default::default()
+            };
+            match shares.patch_item(organization_id.to_string(), share_id, patch, Some(options)).await {
+                Ok(_) => return Ok(()),
+                Err(e) if status(&e) == Some(404) => return Ok(()),
+                Err(e) if status(&e) == Some(412) => continue,
+                Err(e) => return Err(storage_error(e, share_id)),
+            }
+        }
+        Err(StorageError::Transient(format!("Share {} kept changing while counting a view", share_id)))
+    }
+}
+
+#[async_trait]
+impl ActivityStorage for CosmosStorageClient {
+    async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+        self.container(CONTAINER_ACTIVITIES).create_item(&activity.organization_id, &activity, None).await
+            .map_err(|e| storage_error(e, &activity.id))?;
+        Ok(activity)
+    }
+    
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        Self::read(&self.container(CONTAINER_ACTIVITIES), organization_id, activity_id).await?
+            .ok_or_else(|| StorageError::NotFound(activity_id.to_string()))
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, activity_id: &str) -> Result<(Activity, String), StorageError> {
+        Self::read::<Tagged<Activity>>(&self.container(CONTAINER_ACTIVITIES), organization_id, activity_id).await?
+            .map(|tagged| (tagged.item, tagged.etag))
+            .ok_or_else(|| StorageError::NotFound(activity_id.to_string()))
+    }
+    
+    async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+        self.container(CONTAINER_ACTIVITIES).replace_item(&activity.organization_id, &activity.id, &activity, None).await
+            .map_err(|e| storage_error(e, &activity.id))?;
+        Ok(activity)
+    }
+    
+    async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+        self.container(CONTAINER_ACTIVITIES).delete_item(organization_id.to_string(), activity_id, None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, activity_id))
+    }
+    
+    /// One transactional batch in the organization's partition
+    async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+        changes.check_organization(organization_id)?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let Some(ref master_key) = self.master_key else {
+            changes.check_etags(self, organization_id).await?;
+            for activity in changes.create {
+                ActivityStorage::create(self, activity).await?;
+            }
+            for activity in changes.update {
+                ActivityStorage::update(self, activity).await?;
+            }
+            for activity_id in changes.delete {
+                ActivityStorage::delete(self, organization_id, &activity_id).await?;
+            }
+            return Ok(());
+        };
+        if changes.len() > MAX_BATCH_OPERATIONS {
+            return Err(StorageError::Validation(format!("At most {} activities can change together", MAX_BATCH_OPERATIONS)));
+        }
+        
+        let resource_link = format!("dbs/{}/colls/{}", self.database_name, CONTAINER_ACTIVITIES);
+        let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let response = self.http.post(format!("{}/{}/docs", master_key.endpoint, resource_link))
+            .header("authorization", master_key.authorization("POST", "docs", &resource_link, &date))
+            .header("x-ms-date", &date)
+            .header("x-ms-version", BATCH_API_VERSION)
+            .header("x-ms-documentdb-partitionkey", serde_json::json!([organization_id]).to_string())
+            .header("x-ms-cosmos-is-batch-request", "True")
+            .header("x-ms-cosmos-batch-atomic", "True")
+            .json(&batch_operations(&changes))
+            .send().await
+            .map_err(|e| StorageError::Transient(e.to_string()))?;
+        
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        // A failed batch answers with the status of the failing operation
+        // and the results of all of them
+        let results: Vec<BatchResult> = response.json().await.unwrap_or_default();
+        Err(batch_error(status.as_u16(), &results, &changes))
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<Activity>, StorageError> {
+        self.list_page(CONTAINER_ACTIVITIES, organization_id, options, |a: &Activity| a.id.clone()).await
+    }
+    
+    /// `year` keeps activities overlapping that calendar year
+    async fn list_by_layers(
+        &self,
+        organization_id: &str,
+        layer_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<Activity>, StorageError> {
+        if layer_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let query = build_query("SELECT * FROM c", Filter::layers_and_year(layer_ids, year).to_sql("c", "p"), None)?;
+        
+        Self::query(&self.container(CONTAINER_ACTIVITIES), query, Some(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl LayerStorage for CosmosStorageClient {
+    async fn create(&self, layer: Layer) -> Result<Layer, StorageError> {
+        self.container(CONTAINER_LAYERS).create_item(&layer.organization_id, &layer, None).await
+            .map_err(|e| storage_error(e, &layer.id))?;
+        Ok(layer)
+    }
+    
+    async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError> {
+        Self::read(&self.container(CONTAINER_LAYERS), organization_id, layer_id).await?
+            .ok_or_else(|| StorageError::NotFound(layer_id.to_string()))
+    }
+    
+    async fn update(&self, layer: Layer) -> Result<Layer, StorageError> {
+        self.container(CONTAINER_LAYERS).replace_item(&layer.organization_id, &layer.id, &layer, None).await
+            .map_err(|e| storage_error(e, &layer.id))?;
+        Ok(layer)
+    }
+    
+    async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError> {
+        self.container(CONTAINER_LAYERS).delete_item(organization_id.to_string(), layer_id, None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, layer_id))
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError> {
+        let mut layers: Vec<Layer> = Self::query(&self.container(CONTAINER_LAYERS), Query::from("SELECT * FROM c"), Some(organization_id)).await?;
+        layers.sort_by_key(|l| l.ring_index);
+        Ok(layers)
+    }
+}
+
+#[async_trait]
+impl ActivityTypeStorage for CosmosStorageClient {
+    async fn upsert(&self, config: ActivityTypeConfig) -> Result<ActivityTypeConfig, StorageError> {
+        let document = KeyedDocument::new(config.key.clone(), config);
+        self.container(CONTAINER_ACTIVITY_TYPES).upsert_item(&document.item.organization_id, &document, None).await
+            .map_err(|e| storage_error(e, &document.id))?;
+        Ok(document.item)
+    }
+    
+    async fn get(&self, organization_id: &str, key: &str) -> Result<ActivityTypeConfig, StorageError> {
+        Self::read::<KeyedDocument<ActivityTypeConfig>>(&self.container(CONTAINER_ACTIVITY_TYPES), organization_id, key).await?
+            .map(|d| d.item)
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+    
+    async fn delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        let container = self.container(CONTAINER_ACTIVITY_TYPES);
+        let document = Self::read::<KeyedDocument<ActivityTypeConfig>>(&container, organization_id, key).await?
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))?;
+        storage::ensure_deletable(&document.item)?;
+        
+        let options = ItemOptions {
+            if_match_etag: document.etag.map(Etag::from),
+            ..Default::default()
+        };
+        container.delete_item(organization_id.to_string(), key, Some(options)).await
+            .map(|_| ())
+            .map_err(|e| match status(&e) {
+                Some(412) => StorageError::Storage(format!("Activity type changed while deleting: {}", key)),
+                _ => storage_error(e, key),
+            })
+    }
+    
+    async fn force_delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.container(CONTAINER_ACTIVITY_TYPES).delete_item(organization_id.to_string(), key, None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, key))
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<ActivityTypeConfig>, StorageError> {
+        let documents: Vec<KeyedDocument<ActivityTypeConfig>> = Self::query(&self.container(CONTAINER_ACTIVITY_TYPES), Query::from("SELECT * FROM c"), Some(organization_id)).await?;
+        let mut types: Vec<ActivityTypeConfig> = documents.into_iter().map(|d| d.item).collect();
+        types.sort_by_key(|t| t.sort_order);
+        Ok(types)
+    }
+}
+
+#[async_trait]
+impl UserSettingsStorage for CosmosStorageClient {
+    async fn get(&self, organization_id: &str, user_id: &str) -> Result<UserSettings, StorageError> {
+        Ok(Self::read::<KeyedDocument<UserSettings>>(&self.container(CONTAINER_USER_SETTINGS), organization_id, user_id).await?
+            .map(|d| d.item)
+            .unwrap_or_else(|| UserSettings::new(user_id.to_string(), organization_id.to_string())))
+    }
+    
+    async fn upsert(&self, settings: UserSettings) -> Result<UserSettings, StorageError> {
+        let document = KeyedDocument::new(settings.user_id.clone(), settings);
+        self.container(CONTAINER_USER_SETTINGS).upsert_item(&document.item.organization_id, &document, None).await
+            .map_err(|e| storage_error(e, &document.id))?;
+        Ok(document.item)
+    }
+    
+    async fn delete(&self, organization_id: &str, user_id: &str) -> Result<(), StorageError> {
+        self.container(CONTAINER_USER_SETTINGS).delete_item(organization_id.to_string(), user_id, None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, user_id))
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<UserSettings>, StorageError> {
+        let documents: Vec<KeyedDocument<UserSettings>> = Self::query(&self.container(CONTAINER_USER_SETTINGS), Query::from("SELECT * FROM c"), Some(organization_id)).await?;
+        Ok(documents.into_iter().map(|d| d.item).collect())
+    }
+}
+
+#[async_trait]
+impl PolicyStorage for CosmosStorageClient {
+    async fn get(&self, organization_id: &str) -> Result<OrganizationPolicy, StorageError> {
+        Ok(Self::read::<KeyedDocument<OrganizationPolicy>>(&self.container(CONTAINER_POLICIES), organization_id, POLICY_ID).await?
+            .map(|d| d.item)
+            .unwrap_or_else(|| OrganizationPolicy::new(organization_id)))
+    }
+    
+    async fn upsert(&self, policy: OrganizationPolicy) -> Result<OrganizationPolicy, StorageError> {
+        let document = KeyedDocument::new(POLICY_ID.to_string(), policy);
+        self.container(CONTAINER_POLICIES).upsert_item(&document.item.organization_id, &document, None).await
+            .map_err(|e| storage_error(e, &document.item.organization_id))?;
+        Ok(document.item)
+    }
+    
+    async fn delete(&self, organization_id: &str) -> Result<(), StorageError> {
+        self.container(CONTAINER_POLICIES).delete_item(organization_id.to_string(), POLICY_ID, None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, organization_id))
+    }
+}
+
+#[async_trait]
+impl AuditStorage for CosmosStorageClient {
+    async fn record(&self, entry: AuditEntry) -> Result<(), StorageError> {
+        self.container(CONTAINER_AUDIT).upsert_item(&entry.organization_id, &entry, None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, &entry.id))
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        _options: QueryOptions,
+    ) -> Result<QueryResult<AuditEntry>, StorageError> {
+        let mut items: Vec<AuditEntry> = Self::query(&self.container(CONTAINER_AUDIT), Query::from("SELECT * FROM c"), Some(organization_id)).await?;
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+        let total = items.len() as u64;
+        
+        Ok(QueryResult {
+            items,
+            continuation_token: None,
+            total_count: Some(total),
+        })
+    }
+    
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        let container = self.container(CONTAINER_AUDIT);
+        let ids: Vec<String> = Self::query(&container, Query::from("SELECT VALUE c.id FROM c"), Some(organization_id)).await?;
+        let mut removed = 0;
+        for id in ids {
+            match container.delete_item(organization_id.to_string(), &id, None).await {
+                Ok(_) => removed += 1,
+                Err(e) if status(&e) == Some(404) => {}
+                Err(e) => return Err(storage_error(e, &id)),
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// A counter, with the time of the full count it started from
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CounterDocument {
+    /// The counter name
+    id: String,
+    organization_id: String,
+    value: i64,
+    counted_at: DateTime<Utc>,
+}
+
+#[async_trait]
+impl CounterStorage for CosmosStorageClient {
+    async fn get(&self, organization_id: &str, name: &str) -> Result<Option<Counter>, StorageError> {
+        Ok(Self::read::<CounterDocument>(&self.container(CONTAINER_COUNTERS), organization_id, name).await?
+            .map(|d| Counter { value: d.value.max(0) as u64, counted_at: d.counted_at }))
+    }
+    
+    async fn add(&self, organization_id: &str, name: &str, delta: i64) -> Result<(), StorageError> {
+        let patch = PatchDocument::default()
+            .with_increment("/value", delta)
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        match self.container(CONTAINER_COUNTERS).patch_item(organization_id.to_string(), name, patch, None).await {
+            Ok(_) => Ok(()),
+            // Counters that were never set are left for the first full count
+            Err(e) if status(&e) == Some(404) => Ok(()),
+            Err(e) => Err(storage_error(e, name)),
+        }
+    }
+    
+    async fn set(&self, organization_id: &str, name: &str, counter: Counter) -> Result<(), StorageError> {
+        let document = CounterDocument {
+            id: name.to_string(),
+            organization_id: organization_id.to_string(),
+            value: i64::try_from(counter.value).unwrap_or(i64::MAX),
+            counted_at: counter.counted_at,
+        };
+        self.container(CONTAINER_COUNTERS).upsert_item(organization_id.to_string(), &document, None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, name))
+    }
+}
+
+#[async_trait]
+impl StorageProbe for CosmosStorageClient {
+    fn name(&self) -> &'static str {
+        "cosmos-db"
+    }
+    
+    fn targets(&self) -> Vec<&'static str> {
+        Self::CONTAINER_NAMES.to_vec()
+    }
+    
+    async fn probe(&self, target: &str) -> Result<(), StorageError> {
+        self.container(target).read(None).await
+            .map(|_| ())
+            .map_err(|e| storage_error(e, target))
+    }
+}
+
+/// A document with the ETag it was read at
+#[derive(Debug, Deserialize)]
+struct Tagged<T> {
+    #[serde(flatten)]
+    item: T,
+    #[serde(rename = "_etag")]
+    etag: String,
+}
+
+impl CosmosStorageClient {
+    /// Documents of a container that went to the recycle bin before `cutoff`, across partitions
+    async fn binned<T: DeserializeOwned + Send + 'static>(&self, container: &str, cutoff: DateTime<Utc>) -> Result<Vec<Tagged<T>>, StorageError> {
+        // RFC 3339 strings in UTC sort like the times they stand for; the caller checks the parsed time
+        let query = Query::from("SELECT * FROM c WHERE IS_DEFINED(c.deletedAt) AND c.deletedAt < @cutoff")
+            .with_parameter("@cutoff", cutoff.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        Self::query(&self.container(container), query, None).await
+    }
+    
+    /// Delete a document unless it changed since it was read; false if it did or is gone
+    async fn delete_unchanged(&self, container: &str, organization_id: &str, id: &str, etag: String) -> Result<bool, StorageError> {
+        let options = ItemOptions {
+            if_match_etag: Some(Etag::from(etag)),
+            ..Default::default()
+        };
+        match self.container(container).delete_item(organization_id.to_string(), id, Some(options)).await {
+            Ok(_) => Ok(true),
+            Err(e) if matches!(status(&e), Some(404) | Some(412)) => Ok(false),
+            Err(e) => Err(storage_error(e, id)),
+        }
+    }
+}
+
+#[async_trait]
+impl DeletedItemPurger for CosmosStorageClient {
+    fn name(&self) -> &'static str {
+        "cosmos-db"
+    }
+    
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+        let binned = |deleted_at: Option<DateTime<Utc>>| deleted_at.is_some_and(|at| at < cutoff);
+        let mut deleted = 0;
+        
+        for Tagged { item: share, etag } in self.binned::<ShareLink>(CONTAINER_SHARES, cutoff).await? {
+            if binned(share.deleted_at) && self.delete_unchanged(CONTAINER_SHARES, &share.organization_id, &share.id, etag).await? {
+                self.retire_short_code(&share, Utc::now()).await?;
+                deleted += 1;
+            }
+        }
+        for Tagged { item: activity, etag } in self.binned::<Activity>(CONTAINER_ACTIVITIES, cutoff).await? {
+            if binned(activity.deleted_at) && self.delete_unchanged(CONTAINER_ACTIVITIES, &activity.organization_id, &activity.id, etag).await? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Fields of a changed document the change feed needs
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedItem {
+    id: String,
+    organization_id: String,
+    #[serde(default)]
+    short_code: Option<String>,
+    #[serde(rename = "_ts")]
+    ts: i64,
+}
+
+impl CosmosStorageClient {
+    /// Documents of a container written at or after `since`, across partitions
+    async fn changed_since(&self, container: &str, since: i64) -> Result<Vec<ChangedDocument>, StorageError> {
+        let query = Query::from("SELECT c.id, c.organizationId, c.shortCode, c._ts FROM c WHERE c._ts >= @since ORDER BY c._ts")
+            .with_parameter("@since", since)
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        let items: Vec<FeedItem> = Self::query(&self.container(container), query, None).await?;
+        Ok(items.into_iter()
+            .map(|item| ChangedDocument {
+                organization_id: item.organization_id,
+                id: item.id,
+                short_code: item.short_code,
+                timestamp: item.ts,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ChangeFeed for CosmosStorageClient {
+    fn name(&self) -> &'static str {
+        "cosmos-db"
+    }
+    
+    async fn shares_changed_since(&self, since: i64) -> Result<Vec<ChangedDocument>, StorageError> {
+        self.changed_since(CONTAINER_SHARES, since).await
+    }
+    
+    async fn activities_changed_since(&self, since: i64) -> Result<Vec<ChangedDocument>, StorageError> {
+        self.changed_since(CONTAINER_ACTIVITIES, since).await
+    }
+    
+    async fn repair_short_code(&self, share: &ChangedDocument) -> Result<bool, StorageError> {
+        // Gone again, or written without a code: nothing to index
+        let Some(share) = Self::read::<ShareLink>(&self.container(CONTAINER_SHARES), &share.organization_id, &share.id).await? else {
+            return Ok(false);
+        };
+        if share.short_code.is_empty() {
+            return Ok(false);
+        }
+        
+        // An existing entry is left alone; claims settle who holds a code
+        let entry = ShortCodeEntry::for_share(&share);
+        match self.container(CONTAINER_SHORT_CODES).create_item(&share.short_code, &entry, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if status(&e) == Some(409) => Ok(false),
+            Err(e) => Err(storage_error(e, &share.short_code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Well-known key of the Cosmos DB emulator
+    const EMULATOR_KEY: &str = "C2y6yDjf5/R+ob0N8A7Cgv30VRDJIWEHLM+4QDU5DE2nQ9nDuVTqobD4b8mGGyPMbIZnqyMsEcaGQy67XIw/Jw==";
+    
+    /// Client for a fresh database on the emulator
+    async fn emulator() -> CosmosStorageClient {
+        let endpoint = std::env::var("COSMOS_EMULATOR_ENDPOINT").unwrap_or_else(|_| "https://localhost:8081".to_string());
+        let storage = CosmosStorageClient::new_with_key(&endpoint, "conformance", EMULATOR_KEY).await.unwrap();
+        storage.create_containers().await.unwrap();
+        storage
+    }
+    
+    /// Shares expire server-side, so the TTL suite waits on the wall clock
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_conformance_on_emulator() {
+        use arshjul_core::storage_tests::{self, WallTime};
+        
+        if !crate::emulators_enabled() {
+            return;
+        }
+        let storage = emulator().await;
+        storage_tests::share_storage_suite(&storage).await;
+        storage_tests::concurrent_views_suite(std::sync::Arc::new(emulator().await)).await;
+        storage_tests::share_ttl_suite(&storage, &WallTime).await;
+        storage_tests::activity_storage_suite(&storage).await;
+        storage_tests::layer_storage_suite(&storage).await;
+        storage_tests::policy_storage_suite(&storage).await;
+        storage_tests::audit_storage_suite(&storage).await;
+    }
+    
+    #[tokio::test]
+    async fn test_counters_on_emulator() {
+        if !crate::emulators_enabled() {
+            return;
+        }
+        let storage = emulator().await;
+        let org = "conformance-counters";
+        
+        // Unset counters stay unset until a full count
+        storage.add(org, "shares", 1).await.unwrap();
+        assert_eq!(CounterStorage::get(&storage, org, "shares").await.unwrap(), None);
+        
+        let counted_at = Utc::now();
+        storage.set(org, "shares", Counter { value: 1, counted_at }).await.unwrap();
+        storage.add(org, "shares", 2).await.unwrap();
+        assert_eq!(CounterStorage::get(&storage, org, "shares").await.unwrap().map(|c| c.value), Some(3));
+        storage.add(org, "shares", -5).await.unwrap();
+        assert_eq!(CounterStorage::get(&storage, org, "shares").await.unwrap().map(|c| c.value), Some(0));
+    }
+    
+    #[test]
+    fn test_layers_and_year_sql() {
+        let (conditions, parameters) = Filter::layers_and_year(&["l-1".to_string()], Some(2025)).to_sql("c", "p");
+        assert_eq!(conditions, ["ARRAY_CONTAINS(@p0, c.scope)", "c.startDate < @p1", "c.endDate >= @p2"]);
+        assert_eq!(parameters, [
+            ("@p0".to_string(), serde_json::json!(["l-1"])),
+            ("@p1".to_string(), serde_json::json!("2026")),
+            ("@p2".to_string(), serde_json::json!("2025")),
+        ]);
+        
+        // Stored dates compare against the bare year bounds the way their years do
+        for (date, before_2026) in [("2025-12-31T23:59:59.999Z", true), ("2026-01-01T00:00:00Z", false)] {
+            let stored = serde_json::to_value(date.parse::<DateTime<Utc>>().unwrap()).unwrap();
+            assert_eq!(stored.as_str().unwrap() < "2026", before_2026);
+        }
+    }
+    
+    #[test]
+    fn test_batch_is_conditional_and_blames_the_failing_operation() {
+        let changes = ActivityChanges {
+            delete: vec!["a-1".to_string(), "a-2".to_string()],
+            etags: [("a-2".to_string(), "\"0x1\"".to_string())].into(),
+            ..Default::default()
+        };
+        assert_eq!(batch_operations(&changes), [
+            serde_json::json!({ "operationType": "Delete", "id": "a-1" }),
+            serde_json::json!({ "operationType": "Delete", "id": "a-2", "ifMatch": "\"0x1\"" }),
+        ]);
+        
+        let results = [BatchResult { status_code: 424 }, BatchResult { status_code: 412 }];
+        assert!(matches!(batch_error(412, &results, &changes), StorageError::Conflict(id) if id == "a-2"));
+        assert!(matches!(batch_error(429, &[], &changes), StorageError::Transient(_)));
+        assert!(matches!(batch_error(404, &[], &changes), StorageError::Storage(_)));
+    }
+    
+    #[test]
+    fn test_master_key_authorization() {
+        let key = MasterKey::new("https://account.documents.azure.com/", &BASE64_STANDARD.encode("secret")).unwrap();
+        assert_eq!(key.endpoint, "https://account.documents.azure.com");
+        let authorization = key.authorization("POST", "docs", "dbs/db/colls/activities", "Sat, 18 Oct 2025 10:00:00 GMT");
+        assert!(authorization.starts_with("type%3Dmaster%26ver%3D1.0%26sig%3D"));
+        assert!(!authorization.contains(['+', '/', '=']));
+        assert!(MasterKey::new("https://account.documents.azure.com", "not base64!").is_err());
+    }
+    
+    #[test]
+    fn test_expiring_counts_from_the_write() {
+        let share: ShareLink = serde_json::from_value(serde_json::json!({
+            "id": "s-1", "shareKey": "k".repeat(64), "shortCode": "Code1",
+            "visibility": "public", "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2025-01-31T00:00:00Z", "ttl": 2_592_000,
+            "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap();
+        
+        // Ten days in, a write leaves the twenty days still to go
+        let now = "2025-01-11T00:00:00Z".parse().unwrap();
+        assert_eq!(expiring(share.clone(), now).ttl, Some(20 * 86_400));
+        assert_eq!(expiring(share.clone(), "2025-02-01T00:00:00Z".parse().unwrap()).ttl, Some(1));
+        assert_eq!(expiring(ShareLink { ttl: None, ..share }, now).ttl, None);
+    }
+    
+    #[test]
+    fn test_keyed_document() {
+        let document: KeyedDocument<ActivityTypeConfig> = serde_json::from_value(serde_json::json!({
+            "id": "meeting", "key": "meeting", "label": "Meeting", "icon": "event", "color": "#000000",
+            "highlightColor": "#ffffff", "organizationId": "org-1", "isSystem": true, "_etag": "\"0a\"", "_ts": 1,
+        })).unwrap();
+        assert_eq!(document.etag.as_deref(), Some("\"0a\""));
+        assert!(matches!(storage::ensure_deletable(&document.item), Err(StorageError::Validation(_))));
+        
+        let stored = serde_json::to_value(KeyedDocument::new("meeting".to_string(), document.item)).unwrap();
+        assert_eq!((stored["id"].as_str(), stored["key"].as_str()), (Some("meeting"), Some("meeting")));
+        assert!(stored.get("_etag").is_none());
+        
+        let settings = KeyedDocument::new("user-1".to_string(), UserSettings::new("user-1".to_string(), "org-1".to_string()));
+        let stored = serde_json::to_value(&settings).unwrap();
+        assert_eq!((stored["id"].as_str(), stored["userId"].as_str()), (Some("user-1"), Some("user-1")));
+    }
+}
+
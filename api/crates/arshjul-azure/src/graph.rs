@@ -0,0 +1,306 @@
+//! # Microsoft Graph
+//!
+//! Directory lookups against each organization's own tenant. The API's app
+//! registration needs the `User.Read.All` and `GroupMember.Read.All`
+//! application permissions, granted by a tenant admin; tokens are obtained per tenant with the client
+//! credentials flow and cached until shortly before they expire.
+
+use arshjul_core::directory::{DirectoryError, DirectoryService};
+use arshjul_core::models::{DirectoryEntry, DirectoryEntryKind};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Microsoft Graph base URL
+const GRAPH_BASE_URL: &str = "https://graph.microsoft.com/v1.0";
+
+/// Application permission scope
+const GRAPH_SCOPE: &str = "https://graph.microsoft.com/.default";
+
+/// Maximum IDs per `getByIds` request
+const MAX_IDS_PER_REQUEST: usize = 1000;
+
+/// Maximum groups per `checkMemberGroups` request
+const MAX_GROUPS_PER_CHECK: usize = 20;
+
+/// Tokens are renewed this long before they expire
+const TOKEN_REFRESH_MARGIN_SECONDS: i64 = 300;
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenError {
+    #[serde(default)]
+    error_codes: Vec<u32>,
+}
+
+/// Token error codes meaning the app isn't (fully) consented in the tenant
+const CONSENT_ERROR_CODES: &[u32] = &[65001, 700016, 7000229];
+
+#[derive(Deserialize)]
+struct GraphCollection<T> {
+    value: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DirectoryObject {
+    id: String,
+    display_name: Option<String>,
+    #[serde(default)]
+    mail: Option<String>,
+    #[serde(default)]
+    user_principal_name: Option<String>,
+}
+
+impl DirectoryObject {
+    fn into_entry(self, kind: DirectoryEntryKind) -> Option<DirectoryEntry> {
+        Some(DirectoryEntry {
+            id: self.id,
+            kind,
+            display_name: self.display_name?,
+            mail: self.mail,
+            user_principal_name: self.user_principal_name,
+        })
+    }
+}
+
+/// `$search` clause matching the start of any of the given properties
+fn search_clause(properties: &[&str], query: &str) -> String {
+    // Quotes and backslashes would end the search term early
+    let term: String = query.chars().filter(|c| *c != '"' && *c != '\\').collect();
+    properties.iter()
+        .map(|p| format!("\"{}:{}\"", p, term))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Decode a Graph response; 403 means the permission isn't granted
+async fn parse_response<T: for<'de> Deserialize<'de>>(path: &str, response: reqwest::Response) -> Result<T, DirectoryError> {
+    match response.status() {
+        reqwest::StatusCode::FORBIDDEN => Err(DirectoryError::PermissionDenied(format!("Graph {}", path))),
+        status if !status.is_success() => Err(DirectoryError::Unavailable(format!("Graph {} returned {}", path, status))),
+        _ => response.json().await.map_err(|e| DirectoryError::InvalidResponse(e.to_string())),
+    }
+}
+
+/// Microsoft Graph client using the API's app registration
+pub struct GraphClient {
+    client_id: String,
+    client_secret: String,
+    http: reqwest::Client,
+    tokens: Mutex<HashMap<String, (String, DateTime<Utc>)>>,
+}
+
+impl GraphClient {
+    pub fn new(client_id: &str, client_secret: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            http: reqwest::Client::new(),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// App-only token for a tenant
+    async fn token(&self, tenant_id: &str) -> Result<String, DirectoryError> {
+        if let Ok(tokens) = self.tokens.lock() {
+            if let Some((token, expires_at)) = tokens.get(tenant_id) {
+                if *expires_at > Utc::now() {
+                    return Ok(token.clone());
+                }
+            }
+        }
+        
+        let response = self.http
+            .post(format!("https://login.microsoftonline.com/{}/oauth2/v2.0/token", tenant_id))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("scope", GRAPH_SCOPE),
+            ])
+            .send()
+            .await
+            .map_err(|e| DirectoryError::Unavailable(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            let status = response.status();
+            let codes = response.json::<TokenError>().await.map(|e| e.error_codes).unwrap_or_default();
+            if let Some(code) = codes.iter().find(|c| CONSENT_ERROR_CODES.contains(c)) {
+                return Err(DirectoryError::ConsentRequired(format!(
+                    "AADSTS{}: the app is not consented in tenant {}", code, tenant_id
+                )));
+            }
+            return Err(DirectoryError::Unavailable(format!(
+                "Token request for tenant {} returned {}", tenant_id, status
+            )));
+        }
+        
+        let token: TokenResponse = response.json().await
+            .map_err(|e| DirectoryError::InvalidResponse(e.to_string()))?;
+        let expires_at = Utc::now() + Duration::seconds(token.expires_in - TOKEN_REFRESH_MARGIN_SECONDS);
+        
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.insert(tenant_id.to_string(), (token.access_token.clone(), expires_at));
+        }
+        Ok(token.access_token)
+    }
+    
+    /// GET a Graph collection in the tenant's context (advanced queries enabled)
+    async fn get<T: for<'de> Deserialize<'de>>(
+        &self,
+        tenant_id: &str,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, DirectoryError> {
+        let token = self.token(tenant_id).await?;
+        let response = self.http.get(format!("{}{}", GRAPH_BASE_URL, path))
+            .bearer_auth(token)
+            .header("ConsistencyLevel", "eventual")
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| DirectoryError::Unavailable(e.to_string()))?;
+        
+        parse_response(path, response).await
+    }
+    
+    /// Search one kind of directory object
+    async fn search_objects(
+        &self,
+        tenant_id: &str,
+        kind: DirectoryEntryKind,
+        query: &str,
+        top: usize,
+    ) -> Result<Vec<DirectoryEntry>, DirectoryError> {
+        let (path, properties, select) = match kind {
+            DirectoryEntryKind::User => (
+                "/users",
+                &["displayName", "mail", "userPrincipalName"][..],
+                "id,displayName,mail,userPrincipalName",
+            ),
+            DirectoryEntryKind::Group => ("/groups", &["displayName", "mail"][..], "id,displayName,mail"),
+        };
+        
+        let objects: GraphCollection<DirectoryObject> = self.get(tenant_id, path, &[
+            ("$search", search_clause(properties, query)),
+            ("$select", select.to_string()),
+            ("$top", top.to_string()),
+        ]).await?;
+        
+        Ok(objects.value.into_iter().filter_map(|o| o.into_entry(kind)).collect())
+    }
+    
+    /// POST a Graph request in the tenant's context
+    async fn post<T: for<'de> Deserialize<'de>>(
+        &self,
+        tenant_id: &str,
+        path: &str,
+        body: serde_json::Value,
+    ) -> Result<T, DirectoryError> {
+        let token = self.token(tenant_id).await?;
+        let response = self.http.post(format!("{}{}", GRAPH_BASE_URL, path))
+            .bearer_auth(token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| DirectoryError::Unavailable(e.to_string()))?;
+        
+        parse_response(path, response).await
+    }
+}
+
+#[async_trait]
+impl DirectoryService for GraphClient {
+    async fn display_names(
+        &self,
+        organization_id: &str,
+        user_ids: &[String],
+    ) -> Result<HashMap<String, String>, DirectoryError> {
+        let mut names = HashMap::new();
+        
+        for ids in user_ids.chunks(MAX_IDS_PER_REQUEST) {
+            let objects: GraphCollection<DirectoryObject> = self.post(
+                organization_id,
+                "/directoryObjects/getByIds",
+                serde_json::json!({ "ids": ids, "types": ["user"] }),
+            ).await?;
+            
+            names.extend(objects.value.into_iter()
+                .filter_map(|o| o.display_name.map(|name| (o.id, name))));
+        }
+        
+        Ok(names)
+    }
+    
+    async fn search(
+        &self,
+        organization_id: &str,
+        query: &str,
+        top: usize,
+    ) -> Result<Vec<DirectoryEntry>, DirectoryError> {
+        let mut entries = self.search_objects(organization_id, DirectoryEntryKind::User, query, top).await?;
+        entries.extend(self.search_objects(organization_id, DirectoryEntryKind::Group, query, top).await?);
+        Ok(entries)
+    }
+    
+    async fn member_groups(
+        &self,
+        organization_id: &str,
+        user_id: &str,
+        group_ids: &[String],
+    ) -> Result<HashSet<String>, DirectoryError> {
+        let mut member_of = HashSet::new();
+        
+        for groups in group_ids.chunks(MAX_GROUPS_PER_CHECK) {
+            let result: GraphCollection<String> = self.post(
+                organization_id,
+                &format!("/users/{}/checkMemberGroups", user_id),
+                serde_json::json!({ "groupIds": groups }),
+            ).await?;
+            member_of.extend(result.value);
+        }
+        
+        Ok(member_of)
+    }
+    
+    async fn permission_granted(&self, organization_id: &str, permission: &str) -> Result<bool, DirectoryError> {
+        // The cheapest read each permission allows
+        let path = match permission {
+            "User.Read.All" => "/users",
+            "GroupMember.Read.All" => "/groups",
+            other => return Err(DirectoryError::InvalidResponse(format!("No probe for {}", other))),
+        };
+        
+        let probe: Result<GraphCollection<serde_json::Value>, _> = self.get(organization_id, path, &[
+            ("$select", "id".to_string()),
+            ("$top", "1".to_string()),
+        ]).await;
+        
+        match probe {
+            Ok(_) => Ok(true),
+            Err(DirectoryError::PermissionDenied(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_search_clause() {
+        assert_eq!(
+            search_clause(&["displayName", "mail"], "ola \"x\\"),
+            "\"displayName:ola x\" OR \"mail:ola x\""
+        );
+    }
+}
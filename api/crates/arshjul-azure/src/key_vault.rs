@@ -0,0 +1,192 @@
+//! # Key Vault Signing Keys
+//!
+//! [`SigningKeyStore`] keeping signing keys as Azure Key Vault secrets, so
+//! every instance signs and verifies with the same keys. Each key is one
+//! secret, `{prefix}{kid}`, holding the key as JSON and tagged
+//! `purpose=signing-key`.
+//!
+//! Listing the vault returns secret names and tags only; values are read
+//! for enabled secrets with the prefix and the tag, so other secrets in a
+//! shared vault are never fetched. A key that can't be read or parsed is
+//! logged and left out rather than failing the list.
+//!
+//! Requires the `Key Vault Secrets Officer` role on the vault for the app's
+//! Managed Identity. Retired keys are deleted by [`KeyRing::rotate`]
+//! rather than given a secret expiry, which would lock out the signing key
+//! if rotation stalls. Deleted secrets are kept by the vault's soft delete;
+//! key IDs are never reused, so they don't need purging.
+//!
+//! [`KeyRing::rotate`]: arshjul_core::signing_keys::KeyRing::rotate
+
+use arshjul_core::signing_keys::{SigningKey, SigningKeyError, SigningKeyStore};
+use async_trait::async_trait;
+use azure_core::auth::TokenCredential;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Key Vault data plane API version
+const KEY_VAULT_API_VERSION: &str = "7.4";
+
+/// Key Vault token scope
+const KEY_VAULT_SCOPE: &str = "https://vault.azure.net/.default";
+
+/// Prefix of signing key secret names
+pub const DEFAULT_SECRET_PREFIX: &str = "arshjul-signing-";
+
+/// Tag marking signing key secrets
+const PURPOSE_TAG: (&str, &str) = ("purpose", "signing-key");
+
+/// Page of `GET /secrets`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretList {
+    #[serde(default)]
+    value: Vec<SecretItem>,
+    next_link: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SecretItem {
+    /// `https://{vault}/secrets/{name}`
+    id: String,
+    #[serde(default)]
+    tags: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    attributes: SecretAttributes,
+}
+
+#[derive(Default, Deserialize)]
+struct SecretAttributes {
+    enabled: Option<bool>,
+}
+
+impl SecretItem {
+    /// Key ID of a signing key secret
+    fn kid(&self, prefix: &str) -> Option<String> {
+        let name = self.id.rsplit('/').next()?;
+        let tagged = self.tags.get(PURPOSE_TAG.0).is_some_and(|v| v == PURPOSE_TAG.1);
+        (self.attributes.enabled != Some(false) && tagged).then_some(())?;
+        name.strip_prefix(prefix).map(str::to_string)
+    }
+}
+
+#[derive(Deserialize)]
+struct SecretBundle {
+    value: String,
+}
+
+/// Signing keys in an Azure Key Vault
+pub struct KeyVaultKeyStore {
+    /// `https://{vault}.vault.azure.net`
+    vault_url: String,
+    prefix: String,
+    credential: Arc<dyn TokenCredential>,
+    http: reqwest::Client,
+}
+
+impl KeyVaultKeyStore {
+    /// Create using Managed Identity / Azure CLI credentials
+    pub fn new(vault_url: &str, prefix: &str) -> Result<Self, SigningKeyError> {
+        let credential = azure_identity::create_credential()
+            .map_err(|e| SigningKeyError::Storage(format!("Failed to create Azure credential: {}", e)))?;
+        
+        Ok(Self {
+            vault_url: vault_url.trim_end_matches('/').to_string(),
+            prefix: prefix.to_string(),
+            credential,
+            http: reqwest::Client::new(),
+        })
+    }
+    
+    fn secret_url(&self, kid: &str) -> String {
+        format!("{}/secrets/{}{}?api-version={}", self.vault_url, self.prefix, kid, KEY_VAULT_API_VERSION)
+    }
+    
+    async fn read(&self, kid: &str) -> Result<SigningKey, SigningKeyError> {
+        let secret: SecretBundle = self.send(self.http.get(self.secret_url(kid))).await?
+            .json()
+            .await
+            .map_err(|e| SigningKeyError::Serialization(e.to_string()))?;
+        serde_json::from_str(&secret.value).map_err(|e| SigningKeyError::Serialization(e.to_string()))
+    }
+    
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, SigningKeyError> {
+        let token = self.credential.get_token(&[KEY_VAULT_SCOPE]).await
+            .map_err(|e| SigningKeyError::Storage(e.to_string()))?;
+        let response = request.bearer_auth(token.token.secret())
+            .send()
+            .await
+            .map_err(|e| SigningKeyError::Storage(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(SigningKeyError::Storage(format!("Key Vault returned {}", response.status())));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl SigningKeyStore for KeyVaultKeyStore {
+    fn name(&self) -> &'static str {
+        "key-vault"
+    }
+    
+    async fn list(&self) -> Result<Vec<SigningKey>, SigningKeyError> {
+        let mut kids = Vec::new();
+        let mut next = Some(format!("{}/secrets?api-version={}", self.vault_url, KEY_VAULT_API_VERSION));
+        while let Some(url) = next {
+            let page: SecretList = self.send(self.http.get(url)).await?
+                .json()
+                .await
+                .map_err(|e| SigningKeyError::Serialization(e.to_string()))?;
+            kids.extend(page.value.iter().filter_map(|item| item.kid(&self.prefix)));
+            next = page.next_link;
+        }
+        
+        let mut keys = Vec::with_capacity(kids.len());
+        for kid in kids {
+            match self.read(&kid).await {
+                Ok(key) => keys.push(key),
+                Err(e) => tracing::warn!(kid = %kid, error = %e, "Skipping unreadable signing key"),
+            }
+        }
+        Ok(keys)
+    }
+    
+    async fn save(&self, key: &SigningKey) -> Result<(), SigningKeyError> {
+        let value = serde_json::to_string(key).map_err(|e| SigningKeyError::Serialization(e.to_string()))?;
+        let body = serde_json::json!({
+            "value": value,
+            "contentType": "application/json",
+            "tags": { PURPOSE_TAG.0: PURPOSE_TAG.1 },
+        });
+        self.send(self.http.put(self.secret_url(&key.kid)).json(&body)).await?;
+        Ok(())
+    }
+    
+    async fn delete(&self, kid: &str) -> Result<(), SigningKeyError> {
+        let url = self.secret_url(kid);
+        self.send(self.http.delete(url)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_lists_only_tagged_signing_keys() {
+        let page: SecretList = serde_json::from_value(serde_json::json!({
+            "value": [
+                { "id": "https://v.vault.azure.net/secrets/arshjul-signing-20250101-ab12", "tags": { "purpose": "signing-key" }, "attributes": { "enabled": true } },
+                { "id": "https://v.vault.azure.net/secrets/arshjul-signing-20250201-cd34", "tags": { "purpose": "signing-key" }, "attributes": { "enabled": false } },
+                { "id": "https://v.vault.azure.net/secrets/arshjul-signing-notes" },
+                { "id": "https://v.vault.azure.net/secrets/database-password", "tags": { "purpose": "signing-key" } },
+            ],
+            "nextLink": null,
+        })).unwrap();
+        let kids: Vec<String> = page.value.iter().filter_map(|item| item.kid(DEFAULT_SECRET_PREFIX)).collect();
+        assert_eq!(kids, ["20250101-ab12"]);
+    }
+}
@@ -0,0 +1,56 @@
+//! # Annual Wheel (Årshjul) Azure Adapters
+//!
+//! Azure implementations of the extension points defined in `arshjul-core`.
+//! Only this crate pulls in the Azure SDK; self-hosted builds can leave it out.
+//!
+//! - [`table_storage`] - Azure Table Storage backend (default)
+//! - [`cosmos_storage`] - Azure Cosmos DB backend
+//! - [`signalr`] - Live updates via Azure SignalR Service
+//! - [`blob_snapshots`] - Static share snapshots and organization snapshots in Blob Storage
+//! - [`cache_purge`] - Front Door / Redis cache invalidation
+//! - [`ai_search`] - Activity full-text search via Azure AI Search
+//! - [`log_analytics`] - Audit export to Log Analytics / Sentinel
+//! - [`access_log_sink`] - Public share access forwarding to SIEM webhooks / Event Hubs
+//! - [`key_vault`] - Signing keys kept as Azure Key Vault secrets
+//! - `redis_share_cache` - Share lookups cached in Azure Cache for Redis (`redis` feature)
+//! - `redis_nonce_store` - Single-use public request nonces in Azure Cache for Redis (`redis` feature)
+//! - `redis_lock_store` - Activity edit locks in Azure Cache for Redis (`redis` feature)
+//! - `graph` - Directory lookups via Microsoft Graph (`graph` feature)
+
+pub mod table_storage;
+pub mod cosmos_storage;
+pub mod signalr;
+pub mod blob_snapshots;
+pub mod cache_purge;
+pub mod ai_search;
+pub mod log_analytics;
+pub mod access_log_sink;
+pub mod key_vault;
+#[cfg(feature = "redis")]
+pub mod redis_share_cache;
+#[cfg(feature = "redis")]
+pub mod redis_nonce_store;
+#[cfg(feature = "redis")]
+pub mod redis_lock_store;
+#[cfg(feature = "graph")]
+pub mod graph;
+
+/// Percent-encode everything but unreserved characters, for query values
+/// and path segments
+pub(crate) fn percent_encode(value: &str) -> String {
+    value.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+/// Whether tests may use the local storage emulators (`STORAGE_EMULATOR_TESTS=1`):
+/// Azurite on its default ports, and the Cosmos DB emulator at
+/// `COSMOS_EMULATOR_ENDPOINT` (default `https://localhost:8081`). Each run
+/// needs emulators with empty storage.
+#[cfg(test)]
+pub(crate) fn emulators_enabled() -> bool {
+    std::env::var("STORAGE_EMULATOR_TESTS")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
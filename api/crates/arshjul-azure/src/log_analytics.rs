@@ -0,0 +1,129 @@
+//! # Log Analytics Audit Export
+//!
+//! [`AuditSink`] sending audit entries to a Log Analytics workspace (and
+//! from there to Microsoft Sentinel) through the Logs Ingestion API.
+//!
+//! Requires a data collection endpoint (DCE), a data collection rule (DCR)
+//! mapping the stream below to a custom table, and the `Monitoring Metrics
+//! Publisher` role on the DCR for the app's Managed Identity.
+//!
+//! ## Stream Schema
+//!
+//! | Column | Type |
+//! |--------|------|
+//! | `TimeGenerated` | datetime |
+//! | `EntryId` | string |
+//! | `OrganizationId` | string |
+//! | `Action` | string |
+//! | `ActorId` | string |
+//! | `TargetId` | string |
+//! | `Details` | dynamic |
+
+use arshjul_core::audit_export::{AuditExportError, AuditSink};
+use arshjul_core::models::AuditEntry;
+use async_trait::async_trait;
+use azure_core::auth::TokenCredential;
+use std::sync::Arc;
+
+/// Logs Ingestion API version
+const INGESTION_API_VERSION: &str = "2023-01-01";
+
+/// Azure Monitor token scope
+const MONITOR_SCOPE: &str = "https://monitor.azure.com/.default";
+
+/// Logs Ingestion API client for one DCR stream
+pub struct LogAnalyticsSink {
+    /// Data collection endpoint URL
+    endpoint: String,
+    /// Immutable ID of the data collection rule (`dcr-...`)
+    rule_id: String,
+    stream: String,
+    credential: Arc<dyn TokenCredential>,
+    http: reqwest::Client,
+}
+
+impl LogAnalyticsSink {
+    /// Create using Managed Identity / Azure CLI credentials
+    pub fn new(endpoint: &str, rule_id: &str, stream: &str) -> Result<Self, AuditExportError> {
+        let credential = azure_identity::create_credential()
+            .map_err(|e| AuditExportError::Unavailable(format!("Failed to create Azure credential: {}", e)))?;
+        
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            rule_id: rule_id.to_string(),
+            stream: stream.to_string(),
+            credential,
+            http: reqwest::Client::new(),
+        })
+    }
+    
+    fn upload_url(&self) -> String {
+        format!(
+            "{}/dataCollectionRules/{}/streams/{}?api-version={}",
+            self.endpoint, self.rule_id, self.stream, INGESTION_API_VERSION
+        )
+    }
+}
+
+/// Row for the custom table
+fn to_record(entry: &AuditEntry) -> serde_json::Value {
+    serde_json::json!({
+        "TimeGenerated": entry.occurred_at.to_rfc3339(),
+        "EntryId": entry.id,
+        "OrganizationId": entry.organization_id,
+        "Action": entry.action,
+        "ActorId": entry.actor_id,
+        "TargetId": entry.target_id,
+        "Details": entry.details,
+    })
+}
+
+#[async_trait]
+impl AuditSink for LogAnalyticsSink {
+    fn name(&self) -> &'static str {
+        "log-analytics"
+    }
+    
+    async fn send(&self, batch: &[AuditEntry]) -> Result<(), AuditExportError> {
+        let token = self.credential.get_token(&[MONITOR_SCOPE]).await
+            .map_err(|e| AuditExportError::Unavailable(e.to_string()))?;
+        
+        let records: Vec<serde_json::Value> = batch.iter().map(to_record).collect();
+        let response = self.http.post(self.upload_url())
+            .bearer_auth(token.token.secret())
+            .json(&records)
+            .send()
+            .await
+            .map_err(|e| AuditExportError::Unavailable(e.to_string()))?;
+        
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let message = format!("Logs Ingestion returned {}", status);
+        // Throttling, expired tokens and server errors are transient; other 4xx mean the batch or DCR is wrong
+        if status.as_u16() == 429 || status.is_server_error() || status.as_u16() == 401 {
+            Err(AuditExportError::Unavailable(message))
+        } else {
+            Err(AuditExportError::Rejected(message))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_record_columns() {
+        let mut entry = AuditEntry::new("org-1", "share.reported", Some("actor"), Some("share-1"));
+        entry.details = Some(serde_json::json!({ "reason": "spam" }));
+        
+        let record = to_record(&entry);
+        assert_eq!(record["OrganizationId"], "org-1");
+        assert_eq!(record["Action"], "share.reported");
+        assert_eq!(record["TargetId"], "share-1");
+        assert_eq!(record["Details"]["reason"], "spam");
+        assert!(record["TimeGenerated"].as_str().unwrap().starts_with(&entry.occurred_at.format("%Y-%m-%d").to_string()));
+    }
+}
@@ -0,0 +1,109 @@
+//! # Redis Lock Store
+//!
+//! [`LockStore`] in Azure Cache for Redis, so an edit lock taken through one
+//! instance holds on every other. Each lock is a key
+//! `{prefix}{organizationId}:{activityId}` holding the [`EditLock`] as JSON,
+//! set with `PX` so it expires on its own. Acquiring and releasing are Lua
+//! scripts, so checking the holder and writing happen in one step.
+
+use arshjul_core::locks::{LockError, LockStore};
+use arshjul_core::models::EditLock;
+use async_trait::async_trait;
+use chrono::Utc;
+
+/// Take the lock unless someone else holds it; a renewal keeps `acquiredAt`.
+/// Returns `{1, stored}`, or `{0, held}` with the other holder's lock.
+const ACQUIRE_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+local lock = cjson.decode(ARGV[1])
+if current then
+    local existing = cjson.decode(current)
+    if existing.lockedBy ~= lock.lockedBy then
+        return {0, current}
+    end
+    lock.acquiredAt = existing.acquiredAt
+end
+local value = cjson.encode(lock)
+redis.call('SET', KEYS[1], value, 'PX', ARGV[2])
+return {1, value}
+"#;
+
+/// Delete the lock if `ARGV[1]` holds it; 1 if deleted
+const RELEASE_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current and cjson.decode(current).lockedBy == ARGV[1] then
+    redis.call('DEL', KEYS[1])
+    return 1
+end
+return 0
+"#;
+
+/// Edit locks in Redis
+pub struct RedisLockStore {
+    client: redis::Client,
+    key_prefix: String,
+}
+
+impl RedisLockStore {
+    /// Create from a `rediss://` connection URL
+    pub fn new(url: &str, key_prefix: &str) -> Result<Self, LockError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| LockError::Storage(e.to_string()))?;
+        Ok(Self { client, key_prefix: key_prefix.to_string() })
+    }
+    
+    fn key(&self, organization_id: &str, activity_id: &str) -> String {
+        format!("{}{}:{}", self.key_prefix, organization_id, activity_id)
+    }
+    
+    async fn connection(&self) -> Result<redis::aio::MultiplexedConnection, LockError> {
+        self.client.get_multiplexed_async_connection().await
+            .map_err(|e| LockError::Storage(e.to_string()))
+    }
+}
+
+fn parse(value: &str) -> Result<EditLock, LockError> {
+    serde_json::from_str(value).map_err(|e| LockError::Storage(e.to_string()))
+}
+
+#[async_trait]
+impl LockStore for RedisLockStore {
+    async fn acquire(&self, lock: EditLock) -> Result<EditLock, LockError> {
+        let value = serde_json::to_string(&lock).map_err(|e| LockError::Storage(e.to_string()))?;
+        let ttl_ms = (lock.expires_at - lock.acquired_at).num_milliseconds().max(1);
+        let mut connection = self.connection().await?;
+        let (acquired, stored): (i64, String) = redis::cmd("EVAL").arg(ACQUIRE_SCRIPT).arg(1)
+            .arg(self.key(&lock.organization_id, &lock.activity_id)).arg(value).arg(ttl_ms)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| LockError::Storage(e.to_string()))?;
+        
+        let stored = parse(&stored)?;
+        if acquired == 1 {
+            Ok(stored)
+        } else {
+            Err(LockError::Conflict(Box::new(stored)))
+        }
+    }
+    
+    async fn release(&self, organization_id: &str, activity_id: &str, user_id: &str) -> Result<bool, LockError> {
+        let mut connection = self.connection().await?;
+        let released: i64 = redis::cmd("EVAL").arg(RELEASE_SCRIPT).arg(1)
+            .arg(self.key(organization_id, activity_id)).arg(user_id)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| LockError::Storage(e.to_string()))?;
+        Ok(released == 1)
+    }
+    
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Option<EditLock>, LockError> {
+        let mut connection = self.connection().await?;
+        let value: Option<String> = redis::cmd("GET").arg(self.key(organization_id, activity_id))
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| LockError::Storage(e.to_string()))?;
+        
+        // Redis expires keys itself; this only covers clock skew between hosts
+        Ok(value.as_deref().map(parse).transpose()?.filter(|lock| !lock.is_expired(Utc::now())))
+    }
+}
@@ -0,0 +1,59 @@
+//! # Redis Nonce Store
+//!
+//! [`NonceStore`] in Azure Cache for Redis, so a nonce issued by one
+//! instance can be redeemed on any other, and only once. Each nonce is a key
+//! `{prefix}{nonce}` holding its scope, set with `EX`; redeeming uses
+//! `GETDEL` (Redis 6.2 or later), so two concurrent redemptions can't both
+//! succeed.
+
+use arshjul_core::nonce::NonceStore;
+use arshjul_core::storage::StorageError;
+use async_trait::async_trait;
+use chrono::Duration;
+
+/// Pending nonces in Redis
+pub struct RedisNonceStore {
+    client: redis::Client,
+    key_prefix: String,
+}
+
+impl RedisNonceStore {
+    /// Create from a `rediss://` connection URL
+    pub fn new(url: &str, key_prefix: &str) -> Result<Self, StorageError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        Ok(Self { client, key_prefix: key_prefix.to_string() })
+    }
+    
+    fn key(&self, nonce: &str) -> String {
+        format!("{}{}", self.key_prefix, nonce)
+    }
+    
+    async fn connection(&self) -> Result<redis::aio::MultiplexedConnection, StorageError> {
+        self.client.get_multiplexed_async_connection().await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl NonceStore for RedisNonceStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+    
+    async fn put(&self, nonce: &str, scope: &str, ttl: Duration) -> Result<(), StorageError> {
+        let mut connection = self.connection().await?;
+        redis::cmd("SET").arg(self.key(nonce)).arg(scope).arg("EX").arg(ttl.num_seconds().max(1))
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+    
+    async fn take(&self, nonce: &str) -> Result<Option<String>, StorageError> {
+        let mut connection = self.connection().await?;
+        redis::cmd("GETDEL").arg(self.key(nonce))
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+}
@@ -0,0 +1,72 @@
+//! # Redis Share Cache
+//!
+//! [`ShareCache`] in Azure Cache for Redis, shared by every instance, so a
+//! write on one instance is seen by all of them. Shares are stored as JSON
+//! under `{prefix}{shortCode}` and expire after the TTL.
+
+use arshjul_core::models::ShareLink;
+use arshjul_core::share_cache::ShareCache;
+use arshjul_core::storage::StorageError;
+use async_trait::async_trait;
+
+/// Shares cached in Redis
+pub struct RedisShareCache {
+    client: redis::Client,
+    key_prefix: String,
+    ttl_seconds: u64,
+}
+
+impl RedisShareCache {
+    /// Create from a `rediss://` connection URL
+    pub fn new(url: &str, key_prefix: &str, ttl_seconds: u64) -> Result<Self, StorageError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        Ok(Self { client, key_prefix: key_prefix.to_string(), ttl_seconds })
+    }
+    
+    fn key(&self, short_code: &str) -> String {
+        format!("{}{}", self.key_prefix, short_code)
+    }
+    
+    async fn connection(&self) -> Result<redis::aio::MultiplexedConnection, StorageError> {
+        self.client.get_multiplexed_async_connection().await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl ShareCache for RedisShareCache {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+    
+    async fn get(&self, short_code: &str) -> Result<Option<ShareLink>, StorageError> {
+        let mut connection = self.connection().await?;
+        let cached: Option<String> = redis::cmd("GET").arg(self.key(short_code))
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        
+        cached.map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| StorageError::Serialization(e.to_string()))
+    }
+    
+    async fn put(&self, share: &ShareLink) -> Result<(), StorageError> {
+        let json = serde_json::to_string(share)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let mut connection = self.connection().await?;
+        redis::cmd("SET").arg(self.key(&share.short_code)).arg(json).arg("EX").arg(self.ttl_seconds)
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+    
+    async fn remove(&self, short_code: &str) -> Result<(), StorageError> {
+        let mut connection = self.connection().await?;
+        redis::cmd("DEL").arg(self.key(short_code))
+            .query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| StorageError::Storage(e.to_string()))
+    }
+}
@@ -0,0 +1,265 @@
+//! # Live Updates (Azure SignalR Service)
+//!
+//! Pushes entity changes to every planner that has the wheel open, so
+//! concurrent edits show up without polling.
+//!
+//! Uses SignalR Service in serverless mode:
+//! 1. The Teams tab calls `POST /api/signalr/negotiate` and receives the
+//!    client URL plus a short-lived access token.
+//! 2. The negotiate handler adds the user to their organization's group.
+//! 3. [`SignalRBroadcaster`] is subscribed to the event bus and sends each
+//!    change to the organization group via the REST API. Clients then pull
+//!    the actual data through the delta endpoint.
+
+use arshjul_core::events::{DomainEvent, EventError, EventSubscriber, LiveUpdateService, NegotiateResponse};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Client access token lifetime
+const CLIENT_TOKEN_TTL_MINUTES: i64 = 60;
+
+/// Server (REST API) token lifetime
+const SERVER_TOKEN_TTL_MINUTES: i64 = 5;
+
+/// Client method invoked for every event
+pub const CLIENT_TARGET: &str = "wheelEvent";
+
+/// JWT claims for SignalR Service tokens
+#[derive(Debug, Serialize, Deserialize)]
+struct SignalRClaims {
+    aud: String,
+    exp: i64,
+    iat: i64,
+    /// User ID (required for user/group targeting)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nameid: Option<String>,
+}
+
+/// Azure SignalR Service configuration (serverless mode)
+#[derive(Debug, Clone)]
+pub struct SignalRConfig {
+    /// Service endpoint (e.g., "https://myservice.service.signalr.net")
+    pub endpoint: String,
+    /// Access key used to sign client and server tokens
+    pub access_key: String,
+    /// Hub name
+    pub hub: String,
+}
+
+impl SignalRConfig {
+    /// Parse from a connection string (`Endpoint=...;AccessKey=...;Version=1.0;`); `None` if incomplete
+    pub fn from_connection_string(connection_string: &str, hub: &str) -> Option<Self> {
+        let mut endpoint = None;
+        let mut access_key = None;
+        
+        for part in connection_string.split(';') {
+            if let Some((key, value)) = part.split_once('=') {
+                match key.trim().to_lowercase().as_str() {
+                    "endpoint" => endpoint = Some(value.trim().trim_end_matches('/').to_string()),
+                    "accesskey" => access_key = Some(value.trim().to_string()),
+                    _ => {}
+                }
+            }
+        }
+        
+        match (endpoint, access_key) {
+            (Some(endpoint), Some(access_key)) if !endpoint.is_empty() && !access_key.is_empty() => Some(Self {
+                endpoint,
+                access_key,
+                hub: hub.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Azure SignalR Service REST client
+pub struct SignalRClient {
+    config: SignalRConfig,
+    http: reqwest::Client,
+}
+
+impl SignalRClient {
+    pub fn new(config: SignalRConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+    
+    /// Group name for an organization
+    pub fn organization_group(organization_id: &str) -> String {
+        format!("org_{}", organization_id)
+    }
+    
+    /// Client connection URL for the hub
+    pub fn client_url(&self) -> String {
+        format!("{}/client/?hub={}", self.config.endpoint, self.config.hub)
+    }
+    
+    /// REST API URL of a group; names are percent-encoded, as organization
+    /// and user IDs come from tokens
+    fn group_url(&self, group: &str) -> String {
+        format!(
+            "{}/api/v1/hubs/{}/groups/{}",
+            self.config.endpoint, crate::percent_encode(&self.config.hub), crate::percent_encode(group)
+        )
+    }
+    
+    /// Sign a token for the given audience
+    fn sign(&self, audience: &str, user_id: Option<&str>, ttl: Duration) -> Result<String, EventError> {
+        let now = Utc::now();
+        let claims = SignalRClaims {
+            aud: audience.to_string(),
+            exp: (now + ttl).timestamp(),
+            iat: now.timestamp(),
+            nameid: user_id.map(str::to_string),
+        };
+        
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(self.config.access_key.as_bytes()),
+        ).map_err(|e| EventError::Serialization(e.to_string()))
+    }
+    
+    /// Build connection info for a client
+    pub fn negotiate(&self, user_id: &str) -> Result<NegotiateResponse, EventError> {
+        let url = self.client_url();
+        let access_token = self.sign(&url, Some(user_id), Duration::minutes(CLIENT_TOKEN_TTL_MINUTES))?;
+        Ok(NegotiateResponse { url, access_token })
+    }
+    
+    /// Add a user to a group (applies to current and future connections)
+    pub async fn add_user_to_group(&self, group: &str, user_id: &str) -> Result<(), EventError> {
+        let url = format!("{}/users/{}", self.group_url(group), crate::percent_encode(user_id));
+        let token = self.sign(&url, None, Duration::minutes(SERVER_TOKEN_TTL_MINUTES))?;
+        
+        // Membership expires with the client token; the tab renegotiates before then
+        let response = self.http.put(&url)
+            .query(&[("ttl", (CLIENT_TOKEN_TTL_MINUTES * 60).to_string())])
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(EventError::Delivery(format!("SignalR returned {}", response.status())));
+        }
+        Ok(())
+    }
+    
+    /// Send a message to every connection in a group
+    pub async fn send_to_group<T: Serialize>(&self, group: &str, target: &str, argument: &T) -> Result<(), EventError> {
+        let url = self.group_url(group);
+        let token = self.sign(&url, None, Duration::minutes(SERVER_TOKEN_TTL_MINUTES))?;
+        
+        let body = serde_json::json!({
+            "target": target,
+            "arguments": [argument],
+        });
+        
+        let response = self.http.post(&url)
+            .bearer_auth(token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(EventError::Delivery(format!("SignalR returned {}", response.status())));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LiveUpdateService for SignalRClient {
+    async fn connect(&self, organization_id: &str, user_id: &str) -> Result<NegotiateResponse, EventError> {
+        let response = self.negotiate(user_id)?;
+        
+        // Join the organization group so the user receives its broadcasts
+        self.add_user_to_group(&Self::organization_group(organization_id), user_id).await?;
+        Ok(response)
+    }
+}
+
+/// Event bus subscriber broadcasting every event to the organization group
+pub struct SignalRBroadcaster {
+    client: Arc<SignalRClient>,
+}
+
+impl SignalRBroadcaster {
+    pub fn new(client: Arc<SignalRClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for SignalRBroadcaster {
+    fn name(&self) -> &'static str {
+        "signalr"
+    }
+    
+    async fn handle(&self, event: &DomainEvent) -> Result<(), EventError> {
+        let group = SignalRClient::organization_group(event.organization_id());
+        self.client.send_to_group(&group, CLIENT_TARGET, event).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, DecodingKey, Validation};
+    
+    #[test]
+    fn test_negotiate_token() {
+        let client = SignalRClient::new(SignalRConfig {
+            endpoint: "https://example.service.signalr.net".to_string(),
+            access_key: "secret".to_string(),
+            hub: "arshjul".to_string(),
+        });
+        
+        let response = client.negotiate("user-1").unwrap();
+        assert_eq!(response.url, "https://example.service.signalr.net/client/?hub=arshjul");
+        
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_audience(&[response.url.as_str()]);
+        let claims = decode::<SignalRClaims>(
+            &response.access_token,
+            &DecodingKey::from_secret(b"secret"),
+            &validation,
+        ).unwrap().claims;
+        assert_eq!(claims.nameid.as_deref(), Some("user-1"));
+    }
+    
+    #[test]
+    fn test_group_urls_are_encoded() {
+        let client = SignalRClient::new(SignalRConfig {
+            endpoint: "https://example.service.signalr.net".to_string(),
+            access_key: "secret".to_string(),
+            hub: "arshjul".to_string(),
+        });
+        
+        let group = SignalRClient::organization_group("org/../x?y");
+        assert_eq!(client.group_url(&group), "https://example.service.signalr.net/api/v1/hubs/arshjul/groups/org_org%2F..%2Fx%3Fy");
+        assert_eq!(client.group_url("org_1"), "https://example.service.signalr.net/api/v1/hubs/arshjul/groups/org_1");
+    }
+    
+    #[test]
+    fn test_signalr_connection_string_parsing() {
+        let config = SignalRConfig::from_connection_string(
+            "Endpoint=https://example.service.signalr.net/;AccessKey=secret==;Version=1.0;",
+            "arshjul",
+        ).unwrap();
+        assert_eq!(config.endpoint, "https://example.service.signalr.net");
+        assert_eq!(config.access_key, "secret==");
+        assert_eq!(config.hub, "arshjul");
+        
+        assert!(SignalRConfig::from_connection_string("Endpoint=https://x;Version=1.0;", "hub").is_none());
+    }
+
+}
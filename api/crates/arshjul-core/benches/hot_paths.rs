@@ -0,0 +1,108 @@
+//! # Hot Path Benchmarks
+//!
+//! Before/after numbers for performance work on the public share path
+//! (caching, projections). Run with `cargo bench -p arshjul-core`; compare
+//! against a baseline with `--save-baseline main` / `--baseline main`.
+
+use arshjul_core::crypto;
+use arshjul_core::models::*;
+use arshjul_core::public_access;
+use arshjul_core::storage::memory_storage::MemoryShareStorage;
+use arshjul_core::storage::ShareStorage;
+use chrono::{DateTime, Duration, Utc};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+/// Activities in a busy organization's year
+const ORG_ACTIVITIES: usize = 1_000;
+
+fn start() -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap().to_utc()
+}
+
+fn share(short_code: String) -> ShareLink {
+    serde_json::from_value(serde_json::json!({
+        "id": format!("share-{}", short_code), "shareKey": crypto::generate_share_key(),
+        "shortCode": short_code, "visibility": "public", "organizationId": "org-1",
+        "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2099-01-01T00:00:00Z",
+        "layerConfig": { "layerIds": ["layer-0", "layer-1"] }, "viewSettings": {},
+    })).unwrap()
+}
+
+/// Mix of layers, approval states and markdown descriptions
+fn activities(count: usize) -> Vec<Activity> {
+    (0..count).map(|i| Activity {
+        id: format!("activity-{}", i),
+        title: format!("Activity {}", i),
+        start_date: start() + Duration::days((i % 365) as i64),
+        end_date: start() + Duration::days((i % 365) as i64 + 3),
+        activity_type: ActivityType::Other,
+        color: "#3b82f6".to_string(),
+        highlight_color: "#1d4ed8".to_string(),
+        description: Some(format!("**Owner:** team {}\n\n- agenda\n- [notes](https://example.com/{})", i % 7, i)),
+        description_format: DescriptionFormat::Markdown,
+        links: Vec::new(),
+        tags: vec!["planning".to_string()],
+        priority: 0,
+        scope: format!("layer-{}", i % 4),
+        scope_id: format!("layer-{}", i % 4),
+        organization_id: "org-1".to_string(),
+        created_by: Some("user-1".to_string()),
+        created_at: Some(start()),
+        updated_at: None,
+        edit_count: i as u32 % 5,
+        approval_status: if i % 10 == 0 { ApprovalStatus::PendingApproval } else { ApprovalStatus::Approved },
+        approval_review: None,
+        split_from: None,
+        merged_from: Vec::new(),
+        deleted_at: None,
+        recurrence: None,
+        created_by_name: None,
+    }).collect()
+}
+
+fn short_code_lookup(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let storage = MemoryShareStorage::new();
+    let codes: Vec<String> = (0..10_000).map(|_| crypto::generate_short_code()).collect();
+    runtime.block_on(async {
+        for code in &codes {
+            storage.create(share(code.clone())).await.unwrap();
+        }
+    });
+    
+    let mut i = 0;
+    c.bench_function("share_by_short_code/10k_shares", |b| b.iter(|| {
+        i = (i + 1) % codes.len();
+        runtime.block_on(storage.get_by_short_code(black_box(&codes[i]))).unwrap()
+    }));
+}
+
+fn projection(c: &mut Criterion) {
+    let share = share("AbCd2345".to_string());
+    let activities = activities(ORG_ACTIVITIES);
+    
+    c.bench_function("project/1k_activities", |b| b.iter_batched(
+        || activities.clone(),
+        |activities| public_access::project(black_box(&share), activities, 2025, ActivityOrder::Duration),
+        BatchSize::LargeInput,
+    ));
+}
+
+fn serialization(c: &mut Criterion) {
+    let share = share("AbCd2345".to_string());
+    let activities = activities(ORG_ACTIVITIES);
+    let response = public_access::project(&share, activities.clone(), 2025, ActivityOrder::Duration);
+    
+    let mut group = c.benchmark_group("serialize");
+    group.bench_function("public_share/1k_activities", |b| b.iter(|| serde_json::to_vec(black_box(&response)).unwrap()));
+    group.bench_function("activity_list/1k_activities", |b| b.iter(|| serde_json::to_vec(black_box(&activities)).unwrap()));
+    group.finish();
+}
+
+fn short_codes(c: &mut Criterion) {
+    c.bench_function("generate_short_code", |b| b.iter(crypto::generate_short_code));
+    c.bench_function("generate_share_key", |b| b.iter(crypto::generate_share_key));
+}
+
+criterion_group!(benches, short_code_lookup, projection, serialization, short_codes);
+criterion_main!(benches);
@@ -0,0 +1,378 @@
+//! # Share Access Log Forwarding
+//!
+//! Security teams can have public share access of their organization
+//! forwarded to their SIEM in near real time. Admins opt in per organization
+//! (`PUT /api/admin/policy/access-log`) with a destination - an HTTPS
+//! webhook or an Azure Event Hub - and a sample rate.
+//!
+//! Each forwarded access is a [`ShareAccessEvent`]: share ID, endpoint,
+//! outcome and the client's country, never its IP address. Requests for
+//! unknown short codes belong to no organization and are not forwarded.
+//!
+//! Events are queued on an [`AuditExporter`] per organization and batched,
+//! retried and dropped like audit exports. Delivery is at least once. Each
+//! organization's queue holds at most [`MAX_QUEUED_PER_ORGANIZATION`] events
+//! and the queues are flushed side by side, so an unreachable or slow
+//! endpoint only loses its own organization's events.
+//!
+//! Webhooks must be public HTTPS endpoints: loopback, private, link-local
+//! (including the `169.254.169.254` metadata endpoint), Azure platform and
+//! internal-only hosts are refused when the destination is set, and the
+//! sink checks the resolved addresses again before each delivery.
+
+use crate::audit_export::{AuditExporter, AuditSink};
+use crate::invalidation::RetryPolicy;
+use crate::models::{AccessLogDestination, AccessLogForwarding, PublicAccessEndpoint, PublicAccessResult, ShareAccessEvent, ShareLink};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Flush interval; access events are for alerting, so shorter than audit export's
+pub const DEFAULT_FLUSH_INTERVAL_SECONDS: u64 = 5;
+
+/// Longest accepted webhook URL
+pub const MAX_WEBHOOK_URL_LEN: usize = 2048;
+
+/// Events held per organization while its destination is unreachable; the oldest are dropped beyond this
+pub const MAX_QUEUED_PER_ORGANIZATION: usize = 1_000;
+
+/// Access log forwarding errors
+#[derive(Debug, Error, PartialEq)]
+pub enum AccessLogError {
+    #[error("Invalid destination: {0}")]
+    InvalidDestination(String),
+    
+    #[error("Sample rate must be greater than 0 and at most 1")]
+    InvalidSampleRate,
+}
+
+/// Queued event with the destination in effect when it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLogRecord {
+    pub destination: AccessLogDestination,
+    pub event: ShareAccessEvent,
+}
+
+/// Check forwarding settings before they are stored
+pub fn validate(forwarding: &AccessLogForwarding) -> Result<(), AccessLogError> {
+    if !(forwarding.sample_rate > 0.0 && forwarding.sample_rate <= 1.0) {
+        return Err(AccessLogError::InvalidSampleRate);
+    }
+    
+    match &forwarding.destination {
+        AccessLogDestination::Webhook { url } => {
+            let host = webhook_host(url).unwrap_or("");
+            if host.is_empty() || url.len() > MAX_WEBHOOK_URL_LEN || url.chars().any(char::is_whitespace) {
+                return Err(AccessLogError::InvalidDestination("Webhook must be an https:// URL".to_string()));
+            }
+            if !is_public_host(host) {
+                return Err(AccessLogError::InvalidDestination("Webhook must be a public host".to_string()));
+            }
+        }
+        AccessLogDestination::EventHub { namespace, hub } => {
+            // Namespaces: 6-50 letters, digits and hyphens, starting with a letter
+            let namespace_ok = (6..=50).contains(&namespace.len())
+                && namespace.starts_with(|c: char| c.is_ascii_alphabetic())
+                && namespace.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !namespace_ok {
+                return Err(AccessLogError::InvalidDestination(format!("Invalid Event Hubs namespace: {}", namespace)));
+            }
+            let hub_ok = (1..=256).contains(&hub.len())
+                && hub.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+            if !hub_ok {
+                return Err(AccessLogError::InvalidDestination(format!("Invalid event hub name: {}", hub)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Host of a webhook URL, without user info and port
+pub fn webhook_host(url: &str) -> Option<&str> {
+    let authority = url.strip_prefix("https://")?.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split_once(']').map(|(ip, _)| ip),
+        None => Some(host.split(':').next().unwrap_or(host)),
+    }
+}
+
+/// Whether a webhook host may be on the public internet: names must have a
+/// public-looking domain, IP literals a public address
+pub fn is_public_host(host: &str) -> bool {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return is_public_address(ip);
+    }
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    // Decimal, hex and short-form IPv4 literals ("2130706433", "0x7f.1") resolve too
+    let numeric = host.split('.').all(|label| label.chars().all(|c| c.is_ascii_hexdigit() || c == 'x'));
+    host.contains('.')
+        && !numeric
+        && !["localhost", "local", "internal", "localdomain", "home.arpa"].iter()
+            .any(|suffix| host == *suffix || host.ends_with(&format!(".{}", suffix)))
+}
+
+/// Whether an address is reachable on the public internet rather than
+/// inside the host's network (loopback, private, link-local, metadata)
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_ipv4(mapped),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space (carrier-grade NAT) and reserved ranges
+        || (a == 100 && (64..128).contains(&b))
+        || a >= 240
+        // Azure platform endpoint (wireserver)
+        || ip == Ipv4Addr::new(168, 63, 129, 16))
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10)
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80)
+}
+
+/// Access events waiting to be forwarded, queued per organization
+pub struct AccessLogForwarder {
+    sink: Arc<dyn AuditSink<AccessLogRecord>>,
+    retry: RetryPolicy,
+    queues: Mutex<HashMap<String, Arc<AuditExporter<AccessLogRecord>>>>,
+}
+
+impl AccessLogForwarder {
+    pub fn new(sink: Arc<dyn AuditSink<AccessLogRecord>>, retry: RetryPolicy) -> Self {
+        Self { sink, retry, queues: Mutex::new(HashMap::new()) }
+    }
+    
+    /// Queue an event for its organization's next flush
+    pub fn enqueue(&self, record: AccessLogRecord) {
+        let Ok(mut queues) = self.queues.lock() else { return };
+        queues.entry(record.event.organization_id.clone())
+            .or_insert_with(|| Arc::new(AuditExporter::new(self.sink.clone(), self.retry)
+                .with_max_queued(MAX_QUEUED_PER_ORGANIZATION)))
+            .enqueue(record);
+    }
+    
+    /// Events waiting to be sent, over all organizations
+    pub fn pending(&self) -> usize {
+        self.exporters().iter().map(|(_, exporter)| exporter.pending()).sum()
+    }
+    
+    /// Events waiting to be sent for one organization
+    pub fn pending_for(&self, organization_id: &str) -> usize {
+        self.queues.lock().ok()
+            .and_then(|queues| queues.get(organization_id).map(|exporter| exporter.pending()))
+            .unwrap_or(0)
+    }
+    
+    /// Flush every organization's queue side by side; returns the events delivered
+    pub async fn flush(&self) -> usize {
+        let mut flushes = tokio::task::JoinSet::new();
+        for (organization_id, exporter) in self.exporters() {
+            flushes.spawn(async move {
+                let result = exporter.flush().await;
+                if let Err(ref e) = result {
+                    tracing::warn!(organization_id = %organization_id, pending = exporter.pending(), error = %e, "Access log forwarding failed");
+                }
+                result.unwrap_or(0)
+            });
+        }
+        let mut delivered = 0;
+        while let Some(result) = flushes.join_next().await {
+            delivered += result.unwrap_or(0);
+        }
+        
+        // Forget organizations with nothing left to send
+        if let Ok(mut queues) = self.queues.lock() {
+            queues.retain(|_, exporter| exporter.pending() > 0);
+        }
+        delivered
+    }
+    
+    /// Flush periodically; spawn once at startup
+    pub async fn run(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            self.flush().await;
+        }
+    }
+    
+    fn exporters(&self) -> Vec<(String, Arc<AuditExporter<AccessLogRecord>>)> {
+        self.queues.lock()
+            .map(|queues| queues.iter().map(|(org, exporter)| (org.clone(), exporter.clone())).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether an access is forwarded; `roll` is uniform in `[0, 1)`
+pub fn sampled(forwarding: &AccessLogForwarding, roll: f64) -> bool {
+    roll < forwarding.sample_rate
+}
+
+/// Access event for a share
+pub fn event(
+    share: &ShareLink,
+    endpoint: PublicAccessEndpoint,
+    result: PublicAccessResult,
+    reason: Option<String>,
+    country: Option<&str>,
+    now: DateTime<Utc>,
+) -> ShareAccessEvent {
+    ShareAccessEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        organization_id: share.organization_id.clone(),
+        share_id: share.id.clone(),
+        occurred_at: now,
+        endpoint,
+        result,
+        reason,
+        country: country.map(str::to_string),
+    }
+}
+
+/// Queue the event if the organization forwards access and it is sampled
+pub fn forward(forwarder: &AccessLogForwarder, forwarding: Option<&AccessLogForwarding>, event: ShareAccessEvent) {
+    let Some(forwarding) = forwarding else { return };
+    if sampled(forwarding, rand::random()) {
+        forwarder.enqueue(AccessLogRecord {
+            destination: forwarding.destination.clone(),
+            event,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn webhook(url: &str, sample_rate: f64) -> AccessLogForwarding {
+        AccessLogForwarding {
+            destination: AccessLogDestination::Webhook { url: url.to_string() },
+            sample_rate,
+        }
+    }
+    
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(&webhook("https://siem.example.com/ingest?token=x", 0.25)), Ok(()));
+        assert!(matches!(validate(&webhook("http://siem.example.com", 1.0)), Err(AccessLogError::InvalidDestination(_))));
+        assert!(matches!(validate(&webhook("https:///path", 1.0)), Err(AccessLogError::InvalidDestination(_))));
+        for internal in [
+            "https://169.254.169.254/metadata/instance", "https://127.0.0.1:8443/", "https://10.0.0.5/ingest",
+            "https://[::1]/", "https://[fd00::1]/", "https://[::ffff:192.168.1.1]/", "https://localhost/",
+            "https://siem.internal/", "https://user@168.63.129.16/", "https://2130706433/", "https://intranet/",
+        ] {
+            assert!(matches!(validate(&webhook(internal, 1.0)), Err(AccessLogError::InvalidDestination(_))), "{}", internal);
+        }
+        assert_eq!(validate(&webhook("https://20.50.1.1:8443/ingest", 1.0)), Ok(()));
+        assert_eq!(validate(&webhook("https://siem.example.com", 0.0)), Err(AccessLogError::InvalidSampleRate));
+        assert_eq!(validate(&webhook("https://siem.example.com", f64::NAN)), Err(AccessLogError::InvalidSampleRate));
+        
+        let event_hub = |namespace: &str, hub: &str| AccessLogForwarding {
+            destination: AccessLogDestination::EventHub { namespace: namespace.to_string(), hub: hub.to_string() },
+            sample_rate: 1.0,
+        };
+        assert_eq!(validate(&event_hub("contoso-siem", "share-access")), Ok(()));
+        assert!(validate(&event_hub("contoso.evil.com/x", "share-access")).is_err());
+        assert!(validate(&event_hub("contoso-siem", "a/b")).is_err());
+    }
+    
+    /// Unavailable for one organization's webhook, records what others get
+    struct OneDown {
+        delivered: Mutex<Vec<String>>,
+    }
+    
+    #[async_trait::async_trait]
+    impl AuditSink<AccessLogRecord> for OneDown {
+        fn name(&self) -> &'static str {
+            "one-down"
+        }
+        
+        async fn send(&self, batch: &[AccessLogRecord]) -> Result<(), crate::audit_export::AuditExportError> {
+            if batch.iter().any(|r| r.event.organization_id == "org-down") {
+                return Err(crate::audit_export::AuditExportError::Unavailable("timeout".to_string()));
+            }
+            self.delivered.lock().unwrap().extend(batch.iter().map(|r| r.event.organization_id.clone()));
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_queues_per_organization() {
+        let sink = Arc::new(OneDown { delivered: Mutex::new(Vec::new()) });
+        let forwarder = AccessLogForwarder::new(sink.clone(), RetryPolicy { max_attempts: 1, base_delay: Duration::from_millis(1) });
+        let record = |org: &str| AccessLogRecord {
+            destination: AccessLogDestination::Webhook { url: format!("https://{}.example.com", org) },
+            event: ShareAccessEvent {
+                id: uuid::Uuid::new_v4().to_string(),
+                organization_id: org.to_string(),
+                share_id: "share-1".to_string(),
+                occurred_at: Utc::now(),
+                endpoint: PublicAccessEndpoint::Share,
+                result: PublicAccessResult::Granted,
+                reason: None,
+                country: None,
+            },
+        };
+        
+        for _ in 0..(MAX_QUEUED_PER_ORGANIZATION + 10) {
+            forwarder.enqueue(record("org-down"));
+        }
+        forwarder.enqueue(record("org-up"));
+        assert_eq!(forwarder.pending_for("org-down"), MAX_QUEUED_PER_ORGANIZATION);
+        
+        // The unreachable endpoint keeps its own events and holds back nobody
+        assert_eq!(forwarder.flush().await, 1);
+        assert_eq!(*sink.delivered.lock().unwrap(), ["org-up"]);
+        assert_eq!(forwarder.pending_for("org-down"), MAX_QUEUED_PER_ORGANIZATION);
+        assert_eq!(forwarder.pending(), MAX_QUEUED_PER_ORGANIZATION);
+    }
+    
+    #[test]
+    fn test_sampled_and_serialized() {
+        assert!(sampled(&webhook("https://siem.example.com", 1.0), 0.999));
+        assert!(!sampled(&webhook("https://siem.example.com", 0.1), 0.1));
+        
+        let forwarding: AccessLogForwarding = serde_json::from_value(serde_json::json!({
+            "destination": { "kind": "eventHub", "namespace": "contoso-siem", "hub": "access" },
+        })).unwrap();
+        assert_eq!(forwarding.sample_rate, 1.0);
+        
+        let event = ShareAccessEvent {
+            id: "event-1".to_string(),
+            organization_id: "org-1".to_string(),
+            share_id: "share-1".to_string(),
+            occurred_at: "2025-03-01T12:00:00Z".parse().unwrap(),
+            endpoint: PublicAccessEndpoint::Calendar,
+            result: PublicAccessResult::RateLimited,
+            reason: None,
+            country: Some("NO".to_string()),
+        };
+        assert_eq!(serde_json::to_value(&event).unwrap(), serde_json::json!({
+            "id": "event-1", "organizationId": "org-1", "shareId": "share-1", "occurredAt": "2025-03-01T12:00:00Z",
+            "endpoint": "calendar", "result": "rateLimited", "country": "NO",
+        }));
+    }
+}
@@ -0,0 +1,98 @@
+//! # Activity Type Usage
+//!
+//! Activities refer to their type by key (`meeting`, `holiday`, ...). Before
+//! an admin deletes a type they can check its impact with
+//! `GET /api/activity-types/{key}/usage`: the activities using it, counted
+//! per year (of the start date) and per layer.
+//!
+//! `DELETE /api/activity-types/{key}` is refused with 409 while the type is
+//! in use, unless `?reassignTo=` names a built-in type to move those
+//! activities to first. The moves and the deletion succeed or fail together;
+//! on a failure the handler puts back the activities it already moved.
+
+use crate::models::{Activity, ActivityType, ActivityTypeUsage, UsageByLayer, UsageByYear};
+use chrono::Datelike;
+use std::collections::BTreeMap;
+
+/// Key of a built-in type, as stored on activities
+pub fn key_of(activity_type: &ActivityType) -> String {
+    serde_json::to_value(activity_type).ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Built-in type for a key; custom types have none
+pub fn parse_key(key: &str) -> Option<ActivityType> {
+    serde_json::from_value(serde_json::Value::String(key.to_string())).ok()
+}
+
+/// Activities of the given type
+pub fn using<'a>(key: &str, activities: &'a [Activity]) -> Vec<&'a Activity> {
+    let Some(activity_type) = parse_key(key) else { return Vec::new() };
+    activities.iter().filter(|a| a.activity_type == activity_type).collect()
+}
+
+/// Usage of a type, years ascending and layers by ID
+pub fn usage(key: &str, activities: &[Activity]) -> ActivityTypeUsage {
+    let used = using(key, activities);
+    let mut by_year: BTreeMap<i32, u64> = BTreeMap::new();
+    let mut by_layer: BTreeMap<&str, u64> = BTreeMap::new();
+    for activity in &used {
+        *by_year.entry(activity.start_date.year()).or_default() += 1;
+        *by_layer.entry(activity.scope.as_str()).or_default() += 1;
+    }
+    
+    ActivityTypeUsage {
+        key: key.to_string(),
+        total: used.len() as u64,
+        by_year: by_year.into_iter().map(|(year, count)| UsageByYear { year, count }).collect(),
+        by_layer: by_layer.into_iter().map(|(layer_id, count)| UsageByLayer { layer_id: layer_id.to_string(), count }).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn activity(id: &str, activity_type: ActivityType, start: &str, layer: &str) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": id,
+            "startDate": start,
+            "endDate": start,
+            "type": key_of(&activity_type),
+            "color": "#000000",
+            "highlightColor": "#000000",
+            "scope": layer,
+            "scopeId": layer,
+            "organizationId": "org-1",
+        })).unwrap()
+    }
+    
+    #[test]
+    fn test_keys() {
+        assert_eq!(key_of(&ActivityType::Holiday), "holiday");
+        assert_eq!(parse_key("deadline"), Some(ActivityType::Deadline));
+        assert_eq!(parse_key("board-meeting"), None);
+    }
+    
+    #[test]
+    fn test_usage() {
+        let activities = [
+            activity("a-1", ActivityType::Meeting, "2024-11-05T09:00:00Z", "layer-2"),
+            activity("a-2", ActivityType::Meeting, "2025-01-10T09:00:00Z", "layer-1"),
+            activity("a-3", ActivityType::Meeting, "2025-03-01T09:00:00Z", "layer-1"),
+            activity("a-4", ActivityType::Holiday, "2025-03-01T09:00:00Z", "layer-1"),
+        ];
+        
+        let usage = usage("meeting", &activities);
+        assert_eq!(usage.total, 3);
+        assert_eq!(serde_json::to_value(&usage).unwrap(), serde_json::json!({
+            "key": "meeting",
+            "total": 3,
+            "byYear": [{ "year": 2024, "count": 1 }, { "year": 2025, "count": 2 }],
+            "byLayer": [{ "layerId": "layer-1", "count": 2 }, { "layerId": "layer-2", "count": 1 }],
+        }));
+        assert_eq!(super::usage("board-meeting", &activities).total, 0);
+    }
+}
@@ -0,0 +1,257 @@
+//! # Audit Export
+//!
+//! Forwards audit entries to an external SIEM (e.g. Azure Log Analytics /
+//! Sentinel) in addition to the queryable audit table, so security teams
+//! can alert on suspicious public-share access patterns.
+//!
+//! [`ExportingAuditStorage`] wraps the audit table: every recorded entry is
+//! also queued on an [`AuditExporter`], which ships batches to its
+//! [`AuditSink`] from a background task. Transient failures are retried with
+//! exponential backoff and the batch is kept for the next flush; rejected
+//! batches are dropped. The queue is bounded and in-process, so entries can
+//! be lost on a crash - the audit table stays the system of record.
+//!
+//! The queue is generic over what it carries (audit entries by default);
+//! share access events forwarded to SIEMs ride on it too (see
+//! [`crate::access_log`]).
+
+use crate::invalidation::RetryPolicy;
+use crate::models::AuditEntry;
+use crate::storage::{AuditStorage, QueryOptions, QueryResult, StorageError};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Entries per batch sent to the sink
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Entries held while the sink is unreachable; the oldest are dropped beyond this
+pub const MAX_QUEUED_ENTRIES: usize = 10_000;
+
+/// Default flush interval
+pub const DEFAULT_FLUSH_INTERVAL_SECONDS: u64 = 30;
+
+/// Audit export errors
+#[derive(Debug, Error)]
+pub enum AuditExportError {
+    /// Transient; the batch is retried
+    #[error("Audit sink unavailable: {0}")]
+    Unavailable(String),
+    
+    /// Permanent (malformed batch, misconfigured stream); the batch is dropped
+    #[error("Audit batch rejected: {0}")]
+    Rejected(String),
+}
+
+/// External destination for audit entries (or other queued items)
+#[async_trait]
+pub trait AuditSink<T: Sync = AuditEntry>: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Deliver one batch
+    async fn send(&self, batch: &[T]) -> Result<(), AuditExportError>;
+}
+
+/// Batching, retrying exporter for one sink
+pub struct AuditExporter<T: Sync = AuditEntry> {
+    sink: Arc<dyn AuditSink<T>>,
+    retry: RetryPolicy,
+    batch_size: usize,
+    max_queued: usize,
+    queue: Mutex<VecDeque<T>>,
+    dropped: AtomicU64,
+}
+
+impl<T: Send + Sync> AuditExporter<T> {
+    pub fn new(sink: Arc<dyn AuditSink<T>>, retry: RetryPolicy) -> Self {
+        Self {
+            sink,
+            retry,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_queued: MAX_QUEUED_ENTRIES,
+            queue: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+        }
+    }
+    
+    /// Hold at most `max_queued` entries (default [`MAX_QUEUED_ENTRIES`])
+    pub fn with_max_queued(mut self, max_queued: usize) -> Self {
+        self.max_queued = max_queued.max(1);
+        self
+    }
+    
+    /// Queue an entry for the next flush
+    pub fn enqueue(&self, entry: T) {
+        let Ok(mut queue) = self.queue.lock() else { return };
+        if queue.len() >= self.max_queued {
+            queue.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        queue.push_back(entry);
+    }
+    
+    /// Entries waiting to be sent
+    pub fn pending(&self) -> usize {
+        self.queue.lock().map(|q| q.len()).unwrap_or(0)
+    }
+    
+    /// Entries dropped because the queue was full or the sink rejected them
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+    
+    /// Send batches until the queue is empty; returns the number of entries delivered
+    ///
+    /// Stops at the first batch that still fails after all retries and puts
+    /// it back at the front of the queue.
+    pub async fn flush(&self) -> Result<usize, AuditExportError> {
+        let mut delivered = 0;
+        loop {
+            let batch: Vec<T> = match self.queue.lock() {
+                Ok(mut queue) => {
+                    let n = queue.len().min(self.batch_size);
+                    queue.drain(..n).collect()
+                }
+                Err(_) => return Ok(delivered),
+            };
+            if batch.is_empty() {
+                return Ok(delivered);
+            }
+            
+            match self.send_with_retry(&batch).await {
+                Ok(()) => delivered += batch.len(),
+                Err(AuditExportError::Rejected(reason)) => {
+                    tracing::warn!("Audit sink {} rejected {} entries: {}", self.sink.name(), batch.len(), reason);
+                    self.dropped.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    if let Ok(mut queue) = self.queue.lock() {
+                        for entry in batch.into_iter().rev() {
+                            queue.push_front(entry);
+                        }
+                        while queue.len() > self.max_queued {
+                            queue.pop_front();
+                            self.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+    
+    async fn send_with_retry(&self, batch: &[T]) -> Result<(), AuditExportError> {
+        let mut delay = self.retry.base_delay;
+        let mut attempt = 1;
+        loop {
+            match self.sink.send(batch).await {
+                Err(AuditExportError::Unavailable(e)) if attempt < self.retry.max_attempts => {
+                    tracing::debug!("Audit export via {} failed (attempt {}): {}", self.sink.name(), attempt, e);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    
+    /// Flush periodically; spawn once at startup
+    pub async fn run(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(e) = self.flush().await {
+                tracing::warn!("Audit export via {} failed, {} entries pending: {}", self.sink.name(), self.pending(), e);
+            }
+        }
+    }
+}
+
+/// Audit table that also queues every entry for export
+pub struct ExportingAuditStorage {
+    inner: Arc<dyn AuditStorage>,
+    exporter: Arc<AuditExporter>,
+}
+
+impl ExportingAuditStorage {
+    pub fn new(inner: Arc<dyn AuditStorage>, exporter: Arc<AuditExporter>) -> Self {
+        Self { inner, exporter }
+    }
+}
+
+#[async_trait]
+impl AuditStorage for ExportingAuditStorage {
+    async fn record(&self, entry: AuditEntry) -> Result<(), StorageError> {
+        self.inner.record(entry.clone()).await?;
+        self.exporter.enqueue(entry);
+        Ok(())
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<AuditEntry>, StorageError> {
+        self.inner.list(organization_id, options).await
+    }
+    
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        self.inner.delete_all(organization_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory_storage::MemoryAuditStorage;
+    use std::sync::atomic::AtomicUsize;
+    
+    /// Fails the first `failures` calls, then records batch sizes
+    struct FlakySink {
+        failures: AtomicUsize,
+        batches: Mutex<Vec<usize>>,
+    }
+    
+    #[async_trait]
+    impl AuditSink for FlakySink {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+        
+        async fn send(&self, batch: &[AuditEntry]) -> Result<(), AuditExportError> {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(AuditExportError::Unavailable("503".to_string()));
+            }
+            self.batches.lock().unwrap().push(batch.len());
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_export_batches_and_retries() {
+        let sink = Arc::new(FlakySink { failures: AtomicUsize::new(3), batches: Mutex::new(Vec::new()) });
+        let retry = RetryPolicy { max_attempts: 2, base_delay: Duration::from_millis(1) };
+        let exporter = Arc::new(AuditExporter::new(sink.clone(), retry));
+        let storage = ExportingAuditStorage::new(Arc::new(MemoryAuditStorage::new()), exporter.clone());
+        
+        for i in 0..(DEFAULT_BATCH_SIZE + 1) {
+            storage.record(AuditEntry::new("org-1", "share.accessed", None, Some(&i.to_string()))).await.unwrap();
+        }
+        assert_eq!(storage.list("org-1", QueryOptions::default()).await.unwrap().items.len(), DEFAULT_BATCH_SIZE + 1);
+        
+        // Two failed attempts: the batch goes back on the queue
+        assert!(exporter.flush().await.is_err());
+        assert_eq!(exporter.pending(), DEFAULT_BATCH_SIZE + 1);
+        
+        // One more failure, then success
+        assert_eq!(exporter.flush().await.unwrap(), DEFAULT_BATCH_SIZE + 1);
+        assert_eq!(*sink.batches.lock().unwrap(), vec![DEFAULT_BATCH_SIZE, 1]);
+        assert_eq!(exporter.pending(), 0);
+        assert_eq!(exporter.dropped(), 0);
+    }
+}
@@ -6,13 +6,16 @@
 //!
 //! 1. **Always validate tokens server-side** - Never trust client claims
 //! 2. **Verify signature** - Use Azure AD public keys
-//! 3. **Check audience** - Ensure token is for our app
+//! 3. **Check audience** - Ensure token is for our app. Teams SSO tokens
+//!    carry `api://{clientId}` or the bare client ID depending on the app
+//!    registration, so both are accepted (see [`TokenValidatorConfig::accepted_audiences`])
 //! 4. **Check issuer** - Ensure token is from Azure AD
 //! 5. **Check expiration** - Reject expired tokens
 
 use jsonwebtoken::{decode, decode_header, DecodingKey, Validation, Algorithm};
+use crate::authorization::ADMIN_ROLE;
+use crate::teams_context::TeamsContext;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
 use thiserror::Error;
 
 /// Authentication errors
@@ -84,6 +87,10 @@ pub struct TokenClaims {
     #[serde(default)]
     pub roles: Vec<String>,
     
+    /// Security group object IDs (omitted when not configured or on group overage)
+    #[serde(default)]
+    pub groups: Option<Vec<String>>,
+    
     /// Scope (for delegated permissions)
     #[serde(default)]
     pub scp: Option<String>,
@@ -109,6 +116,15 @@ pub struct UserContext {
     
     /// All roles
     pub roles: Vec<String>,
+    
+    /// Group IDs from the token; None means membership must be checked in the directory
+    pub groups: Option<Vec<String>>,
+    
+    /// Delegated scopes (`scp`); None for app-only tokens (see [`crate::authorization`])
+    pub scopes: Option<Vec<String>>,
+    
+    /// Teams team the request came from, once membership is verified (see [`crate::teams_context`])
+    pub team: Option<TeamsContext>,
 }
 
 impl From<TokenClaims> for UserContext {
@@ -118,8 +134,11 @@ impl From<TokenClaims> for UserContext {
             organization_id: claims.tid,
             display_name: claims.name,
             email: claims.preferred_username.or(claims.upn),
-            is_admin: claims.roles.contains(&"admin.write".to_string()),
+            is_admin: claims.roles.iter().any(|r| r == ADMIN_ROLE),
             roles: claims.roles,
+            groups: claims.groups,
+            scopes: claims.scp.map(|scp| scp.split_whitespace().map(str::to_string).collect()),
+            team: None,
         }
     }
 }
@@ -130,6 +149,9 @@ pub struct TokenValidatorConfig {
     /// Expected audience (our app client ID)
     pub audience: String,
     
+    /// Further accepted audiences, such as an app ID URI `api://{domain}/{clientId}`
+    pub additional_audiences: Vec<String>,
+    
     /// Expected issuer pattern (Azure AD)
     pub issuer_pattern: String,
     
@@ -150,6 +172,7 @@ impl Default for TokenValidatorConfig {
         Self {
             // These should come from environment variables
             audience: std::env::var("AZURE_CLIENT_ID").unwrap_or_default(),
+            additional_audiences: Vec::new(),
             issuer_pattern: "https://login.microsoftonline.com/".to_string(),
             admin_role: "admin.write".to_string(),
             // Only skip signature validation in development mode
@@ -158,6 +181,32 @@ impl Default for TokenValidatorConfig {
     }
 }
 
+impl TokenValidatorConfig {
+    /// Every audience a token may carry: each configured one as given, plus
+    /// its `api://` form when bare, or its bare form when `api://{id}`
+    pub fn accepted_audiences(&self) -> Vec<String> {
+        let mut accepted = Vec::new();
+        for audience in std::iter::once(&self.audience).chain(&self.additional_audiences) {
+            let audience = audience.trim();
+            if audience.is_empty() {
+                continue;
+            }
+            let variant = match audience.strip_prefix("api://") {
+                Some(id) if !id.contains('/') => id.to_string(),
+                Some(_) => audience.to_string(),
+                None if !audience.contains("://") => format!("api://{}", audience),
+                None => audience.to_string(),
+            };
+            for candidate in [audience.to_string(), variant] {
+                if !accepted.contains(&candidate) {
+                    accepted.push(candidate);
+                }
+            }
+        }
+        accepted
+    }
+}
+
 /// Token validator
 pub struct TokenValidator {
     config: TokenValidatorConfig,
@@ -168,6 +217,11 @@ impl TokenValidator {
         Self { config }
     }
     
+    /// Expected audience (the API's client ID)
+    pub fn audience(&self) -> &str {
+        &self.config.audience
+    }
+    
     /// Validate a bearer token from Authorization header
     pub async fn validate(&self, auth_header: &str) -> Result<UserContext, AuthError> {
         // Extract token from "Bearer <token>"
@@ -192,10 +246,8 @@ impl TokenValidator {
         validation.validate_exp = true;
         validation.validate_nbf = true;
         
-        // Set expected audience
-        let mut audiences = HashSet::new();
-        audiences.insert(self.config.audience.clone());
-        validation.aud = Some(audiences);
+        // Any accepted audience will do
+        validation.set_audience(&self.config.accepted_audiences());
         
         // ⚠️ SECURITY WARNING: Signature validation should ALWAYS be enabled in production!
         // Only skip in development mode when RUST_ENV=development
@@ -215,7 +267,11 @@ impl TokenValidator {
             token,
             &DecodingKey::from_secret(&[]), // Dummy key when sig validation disabled
             &validation,
-        ).map_err(|e| AuthError::ValidationFailed(e.to_string()))?;
+        ).map_err(|e| match e.kind() {
+            jsonwebtoken::errors::ErrorKind::InvalidAudience => AuthError::InvalidAudience,
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::Expired,
+            _ => AuthError::ValidationFailed(e.to_string()),
+        })?;
         
         let claims = token_data.claims;
         
@@ -226,16 +282,6 @@ impl TokenValidator {
         
         Ok(UserContext::from(claims))
     }
-    
-    /// Check if user has admin role
-    pub fn require_admin(&self, user: &UserContext) -> Result<(), AuthError> {
-        if !user.is_admin {
-            return Err(AuthError::InsufficientPermissions(
-                "Admin role required".to_string()
-            ));
-        }
-        Ok(())
-    }
 }
 
 /// Extract user context from HTTP request headers
@@ -271,6 +317,7 @@ mod tests {
             preferred_username: None,
             name: Some("Test User".to_string()),
             roles: vec!["admin.write".to_string()],
+            groups: Some(vec!["group-1".to_string()]),
             scp: None,
         };
         
@@ -278,5 +325,31 @@ mod tests {
         assert_eq!(context.user_id, "user-oid");
         assert_eq!(context.organization_id, "tenant-id");
         assert!(context.is_admin);
+        assert_eq!(context.groups, Some(vec!["group-1".to_string()]));
+    }
+    
+    #[tokio::test]
+    async fn test_accepted_audiences() {
+        let config = TokenValidatorConfig {
+            audience: "client-id".to_string(),
+            additional_audiences: vec!["api://wheel.example.com/client-id".to_string(), "api://client-id".to_string()],
+            skip_signature_validation: true,
+            ..Default::default()
+        };
+        assert_eq!(config.accepted_audiences(), ["client-id", "api://client-id", "api://wheel.example.com/client-id"]);
+        
+        let validator = TokenValidator::new(config);
+        let token = |aud: &str| jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({
+                "sub": "user-sub", "oid": "user-oid", "tid": "tenant-id", "aud": aud,
+                "iss": "https://login.microsoftonline.com/tenant-id/v2.0", "exp": 9999999999i64, "iat": 1000000000,
+            }),
+            &jsonwebtoken::EncodingKey::from_secret(b"test"),
+        ).unwrap();
+        for aud in ["client-id", "api://client-id", "api://wheel.example.com/client-id"] {
+            assert_eq!(validator.validate_token(&token(aud)).await.unwrap().user_id, "user-oid");
+        }
+        assert!(matches!(validator.validate_token(&token("other-app")).await, Err(AuthError::InvalidAudience)));
     }
 }
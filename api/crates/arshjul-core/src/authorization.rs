@@ -0,0 +1,161 @@
+//! # Authorization Policy
+//!
+//! Which tokens may call which endpoints, in one place. Endpoints fall into
+//! families; each family names the delegated scope (`scp`) a user token must
+//! carry and the app role the user must hold, if any:
+//!
+//! | Family | Endpoints | Delegated scope | App role |
+//! |--------|-----------|-----------------|----------|
+//! | [`EndpointFamily::Shares`] | `/api/shares/...` | `Shares.ReadWrite` | - |
+//! | [`EndpointFamily::Activities`] | `/api/activities/...`, approvals, layers, activity types, delta | `Activities.ReadWrite` | - |
+//! | [`EndpointFamily::Admin`] | `/api/admin/...` and admin-only actions | `Admin.ReadWrite` | `admin.write` |
+//! | [`EndpointFamily::Reports`] | planning and Power BI analytics, activity export, activity type usage | `Reports.Read` | `reports.read` or `admin.write` |
+//!
+//! Scopes only narrow what a delegated client may do on the user's behalf;
+//! the role still decides what the user may do. [`FULL_ACCESS_SCOPE`], the
+//! scope the Teams app signs in with, grants every family, and admins may
+//! call report endpoints with `Admin.ReadWrite`. Tokens without `scp`
+//! (app-only) are checked by role alone.
+//!
+//! [`REPORTS_ROLE`] is meant for BI service principals: a token holding it
+//! without `admin.write` is [reporting only](is_reporting_only) and refused
+//! by every other endpoint, including those outside any family (see
+//! [`authorize_personal`]). Report endpoints give such callers no free text
+//! or identities: exports are redacted of descriptions, links and people.
+
+use crate::auth::UserContext;
+use thiserror::Error;
+
+/// Scope of the Teams app's own SSO token; grants every family
+pub const FULL_ACCESS_SCOPE: &str = "access_as_user";
+
+/// App role of organization admins
+pub const ADMIN_ROLE: &str = "admin.write";
+
+/// App role of reporting service principals
+pub const REPORTS_ROLE: &str = "reports.read";
+
+/// Authorization failures; the message names what is missing
+#[derive(Debug, Error, PartialEq)]
+pub enum AuthorizationError {
+    #[error("Missing scope: {0}")]
+    MissingScope(&'static str),
+    
+    #[error("Missing role: {0}")]
+    MissingRole(&'static str),
+    
+    #[error("Role {0} only grants report endpoints")]
+    ReportingOnly(&'static str),
+}
+
+/// Group of endpoints sharing one policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointFamily {
+    Shares,
+    Activities,
+    Admin,
+    Reports,
+}
+
+impl EndpointFamily {
+    /// Delegated scope a user token needs
+    pub fn scope(&self) -> &'static str {
+        match self {
+            Self::Shares => "Shares.ReadWrite",
+            Self::Activities => "Activities.ReadWrite",
+            Self::Admin => "Admin.ReadWrite",
+            Self::Reports => "Reports.Read",
+        }
+    }
+    
+    /// Whether a delegated token with `scope` may call the family on behalf of `user`
+    fn granted_by(&self, scope: &str, user: &UserContext) -> bool {
+        scope == self.scope()
+            || scope == FULL_ACCESS_SCOPE
+            || (*self == Self::Reports && user.is_admin && scope == Self::Admin.scope())
+    }
+}
+
+/// Whether `user` holds the reporting role and nothing that grants more
+pub fn is_reporting_only(user: &UserContext) -> bool {
+    !user.is_admin && user.roles.iter().any(|r| r == REPORTS_ROLE)
+}
+
+/// Check that `user`'s token may call endpoints of `family`
+pub fn authorize(user: &UserContext, family: EndpointFamily) -> Result<(), AuthorizationError> {
+    match family {
+        EndpointFamily::Admin if !user.is_admin => return Err(AuthorizationError::MissingRole(ADMIN_ROLE)),
+        EndpointFamily::Reports if !user.is_admin && !is_reporting_only(user) => {
+            return Err(AuthorizationError::MissingRole(REPORTS_ROLE))
+        }
+        EndpointFamily::Shares | EndpointFamily::Activities => authorize_personal(user)?,
+        _ => {}
+    }
+    match &user.scopes {
+        Some(scopes) if !scopes.iter().any(|s| family.granted_by(s, user)) => {
+            Err(AuthorizationError::MissingScope(family.scope()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check that `user` may call endpoints of no family, such as their own settings or the directory
+pub fn authorize_personal(user: &UserContext) -> Result<(), AuthorizationError> {
+    if is_reporting_only(user) {
+        return Err(AuthorizationError::ReportingOnly(REPORTS_ROLE));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn user(roles: &[&str], scopes: Option<&str>) -> UserContext {
+        UserContext {
+            user_id: "user-1".to_string(),
+            organization_id: "org-1".to_string(),
+            display_name: None,
+            email: None,
+            is_admin: roles.contains(&ADMIN_ROLE),
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            groups: None,
+            scopes: scopes.map(|s| s.split_whitespace().map(str::to_string).collect()),
+            team: None,
+        }
+    }
+    
+    #[test]
+    fn test_authorize() {
+        use EndpointFamily::*;
+        
+        let delegated = user(&[], Some("Shares.ReadWrite"));
+        assert_eq!(authorize(&delegated, Shares), Ok(()));
+        assert_eq!(authorize(&delegated, Activities), Err(AuthorizationError::MissingScope("Activities.ReadWrite")));
+        assert_eq!(authorize(&user(&[], Some(FULL_ACCESS_SCOPE)), Activities), Ok(()));
+        
+        // Admin endpoints need the role, and the scope when delegated
+        assert_eq!(authorize(&user(&[], Some("Admin.ReadWrite")), Admin), Err(AuthorizationError::MissingRole(ADMIN_ROLE)));
+        assert_eq!(authorize(&user(&[ADMIN_ROLE], Some("Shares.ReadWrite")), Admin), Err(AuthorizationError::MissingScope("Admin.ReadWrite")));
+        assert_eq!(authorize(&user(&[ADMIN_ROLE], Some("Admin.ReadWrite")), Admin), Ok(()));
+        assert_eq!(authorize(&user(&[ADMIN_ROLE], None), Admin), Ok(()));
+    }
+    
+    #[test]
+    fn test_reporting_role() {
+        use EndpointFamily::*;
+        
+        let reporting = user(&[REPORTS_ROLE], None);
+        assert!(is_reporting_only(&reporting));
+        assert_eq!(authorize(&reporting, Reports), Ok(()));
+        assert_eq!(authorize(&reporting, Activities), Err(AuthorizationError::ReportingOnly(REPORTS_ROLE)));
+        assert_eq!(authorize(&reporting, Admin), Err(AuthorizationError::MissingRole(ADMIN_ROLE)));
+        assert_eq!(authorize_personal(&reporting), Err(AuthorizationError::ReportingOnly(REPORTS_ROLE)));
+        
+        // Admins keep the report endpoints, also through their admin scope
+        assert_eq!(authorize(&user(&[], None), Reports), Err(AuthorizationError::MissingRole(REPORTS_ROLE)));
+        assert_eq!(authorize(&user(&[ADMIN_ROLE], Some("Admin.ReadWrite")), Reports), Ok(()));
+        assert_eq!(authorize(&user(&[REPORTS_ROLE], Some("Admin.ReadWrite")), Reports), Err(AuthorizationError::MissingScope("Reports.Read")));
+        assert!(!is_reporting_only(&user(&[ADMIN_ROLE, REPORTS_ROLE], None)));
+    }
+}
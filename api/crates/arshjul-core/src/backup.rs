@@ -0,0 +1,288 @@
+//! # Organization Backups
+//!
+//! `GET /api/admin/export` returns everything an organization has - its
+//! policy, activity types, layers, activities, shares and user settings -
+//! as one [`BackupArchive`]. `POST /api/admin/import` writes an archive
+//! back, into the same organization or another one; every entity is moved
+//! to the importing organization first ([`rehome`]).
+//!
+//! `?on_conflict=` picks what happens to entities that are already stored
+//! ([`ConflictStrategy`]):
+//!
+//! | Strategy | Stored entity | Archive entity |
+//! |----------|---------------|----------------|
+//! | `skip` (default) | kept | dropped |
+//! | `overwrite` | replaced | written |
+//! | `new-ids` | kept | written under a new ID ([`assign_new_ids`]) |
+//!
+//! With `new-ids`, shares also get a new key and short code, so their old
+//! links keep pointing at the stored shares. Activity types, user settings
+//! and the policy are keyed by name, user and organization, so `new-ids`
+//! skips them.
+//!
+//! Share keys are exported in plaintext, even when the backend seals them
+//! at rest, so an archive opens the same links wherever it is imported.
+//! Treat a downloaded archive like a credential.
+//!
+//! Items in the recycle bin and the audit log are left out of exports.
+//! Imports check entities against the rules of the create handlers (title
+//! and name lengths, dates, tags, links, layers) before writing anything,
+//! then write them one by one (see [`crate::storage::import_layers`] and
+//! its siblings), publishing a change event for each. An import may stop
+//! part way; importing the same archive again with `skip` completes it.
+
+use crate::crypto;
+use crate::models::{BackupArchive, LayerType};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Archive format written by this version of the API
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Entities one archive may hold
+pub const MAX_ENTITIES: usize = 100_000;
+
+/// Archives that can't be imported
+#[derive(Debug, Error, PartialEq)]
+pub enum BackupError {
+    #[error("Unsupported archive format version: {0} (expected {FORMAT_VERSION})")]
+    UnsupportedVersion(u32),
+
+    #[error("Archive too large: {0} entities (max {MAX_ENTITIES})")]
+    TooLarge(usize),
+}
+
+/// Check an archive before importing it
+pub fn validate(archive: &BackupArchive) -> Result<(), BackupError> {
+    if archive.format_version != FORMAT_VERSION {
+        return Err(BackupError::UnsupportedVersion(archive.format_version));
+    }
+    let entities = archive.activity_types.len()
+        + archive.layers.len()
+        + archive.activities.len()
+        + archive.shares.len()
+        + archive.user_settings.len();
+    if entities > MAX_ENTITIES {
+        return Err(BackupError::TooLarge(entities));
+    }
+    Ok(())
+}
+
+/// File name of an organization's archive
+pub fn file_name(organization_id: &str, exported_at: DateTime<Utc>) -> String {
+    format!("arshjul-backup-{}-{}.json", organization_id, exported_at.format("%Y%m%d-%H%M%S"))
+}
+
+/// Move every entity of the archive to `organization_id`
+///
+/// Federation grants stay behind when the organization changes: their
+/// tokens name the exporting organization as the grantor.
+pub fn rehome(archive: &mut BackupArchive, organization_id: &str) {
+    if archive.organization_id != organization_id {
+        if let Some(ref mut policy) = archive.policy {
+            policy.federation_grants.clear();
+        }
+    }
+    archive.organization_id = organization_id.to_string();
+
+    if let Some(ref mut policy) = archive.policy {
+        policy.organization_id = organization_id.to_string();
+    }
+    for config in &mut archive.activity_types {
+        config.organization_id = organization_id.to_string();
+    }
+    for layer in &mut archive.layers {
+        layer.organization_id = organization_id.to_string();
+    }
+    for activity in &mut archive.activities {
+        activity.organization_id = organization_id.to_string();
+    }
+    for share in &mut archive.shares {
+        share.organization_id = organization_id.to_string();
+    }
+    for settings in &mut archive.user_settings {
+        settings.organization_id = organization_id.to_string();
+    }
+}
+
+/// Give shares, layers and activities new IDs, and shares new keys and
+/// short codes, updating every reference between them
+///
+/// The terms layer keeps its fixed ID (see [`crate::terms`]); references to
+/// entities outside the archive are left as they are.
+pub fn assign_new_ids(archive: &mut BackupArchive) {
+    let new_id = || uuid::Uuid::new_v4().to_string();
+
+    let layer_ids: HashMap<String, String> = archive.layers.iter()
+        .filter(|l| l.layer_type != LayerType::Terms)
+        .map(|l| (l.id.clone(), new_id()))
+        .collect();
+    let activity_ids: HashMap<String, String> = archive.activities.iter().map(|a| (a.id.clone(), new_id())).collect();
+    let share_ids: HashMap<String, String> = archive.shares.iter().map(|s| (s.id.clone(), new_id())).collect();
+
+    let remap = |ids: &HashMap<String, String>, id: &mut String| {
+        if let Some(new) = ids.get(id.as_str()) {
+            *id = new.clone();
+        }
+    };
+    let remap_keys = |ids: &HashMap<String, String>, map: &mut HashMap<String, bool>| {
+        *map = map.drain().map(|(id, v)| (ids.get(&id).cloned().unwrap_or(id), v)).collect();
+    };
+
+    for layer in &mut archive.layers {
+        remap(&layer_ids, &mut layer.id);
+    }
+    for activity in &mut archive.activities {
+        remap(&activity_ids, &mut activity.id);
+        remap(&layer_ids, &mut activity.scope);
+        remap(&layer_ids, &mut activity.scope_id);
+        if let Some(ref mut id) = activity.split_from {
+            remap(&activity_ids, id);
+        }
+        for id in &mut activity.merged_from {
+            remap(&activity_ids, id);
+        }
+    }
+    for share in &mut archive.shares {
+        remap(&share_ids, &mut share.id);
+        share.share_key = crypto::generate_share_key();
+        share.short_code = crypto::generate_short_code();
+        for id in &mut share.layer_config.layer_ids {
+            remap(&layer_ids, id);
+        }
+        if let Some(ref mut visibility) = share.layer_config.layer_visibility {
+            remap_keys(&layer_ids, visibility);
+        }
+    }
+    for settings in &mut archive.user_settings {
+        for id in settings.layer_order.iter_mut().flatten() {
+            remap(&layer_ids, id);
+        }
+        if let Some(ref mut visibility) = settings.layer_visibility {
+            remap_keys(&layer_ids, visibility);
+        }
+    }
+    if let Some(ref mut policy) = archive.policy {
+        for grant in &mut policy.federation_grants {
+            for id in &mut grant.layer_ids {
+                remap(&layer_ids, id);
+            }
+        }
+        if let Some(ref mut rate_plan) = policy.rate_plan {
+            rate_plan.api_keys = std::mem::take(&mut rate_plan.api_keys).into_iter()
+                .map(|(id, limit)| (share_ids.get(&id).cloned().unwrap_or(id), limit))
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::*;
+    use crate::terms::TERMS_LAYER_ID;
+
+    fn layer(id: &str, layer_type: LayerType) -> Layer {
+        Layer {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            layer_type,
+            color: "#000000".to_string(),
+            ring_index: 0,
+            is_visible: true,
+            requires_approval: false,
+            visible_to_groups: Vec::new(),
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            organization_id: "org-a".to_string(),
+            created_by: "admin".to_string(),
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+
+    fn archive() -> BackupArchive {
+        let mut activity: Activity = serde_json::from_value(serde_json::json!({
+            "id": "activity-1",
+            "title": "Budget",
+            "startDate": "2025-03-01T00:00:00Z",
+            "endDate": "2025-03-02T00:00:00Z",
+            "type": "meeting",
+            "color": "#000000",
+            "highlightColor": "#000000",
+            "scope": "layer-1",
+            "scopeId": "layer-1",
+            "organizationId": "org-a",
+        })).unwrap();
+        activity.merged_from = vec!["gone".to_string()];
+
+        let mut settings = UserSettings::new("user-1".to_string(), "org-a".to_string());
+        settings.layer_order = Some(vec!["layer-1".to_string(), TERMS_LAYER_ID.to_string()]);
+
+        BackupArchive {
+            format_version: FORMAT_VERSION,
+            organization_id: "org-a".to_string(),
+            exported_at: Utc::now(),
+            policy: Some(OrganizationPolicy::new("org-a")),
+            activity_types: Vec::new(),
+            layers: vec![layer("layer-1", LayerType::Custom), layer(TERMS_LAYER_ID, LayerType::Terms)],
+            activities: vec![activity],
+            shares: Vec::new(),
+            user_settings: vec![settings],
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut archive = archive();
+        assert_eq!(validate(&archive), Ok(()));
+
+        archive.format_version = FORMAT_VERSION + 1;
+        assert_eq!(validate(&archive), Err(BackupError::UnsupportedVersion(FORMAT_VERSION + 1)));
+    }
+
+    #[test]
+    fn test_rehome() {
+        let mut archive = archive();
+        archive.policy.as_mut().unwrap().federation_grants.push(FederationGrant {
+            id: "grant-1".to_string(),
+            partner_organization_id: "org-c".to_string(),
+            layer_ids: vec!["layer-1".to_string()],
+            created_by: "admin".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+            include_descriptions: false,
+        });
+
+        rehome(&mut archive, "org-a");
+        assert_eq!(archive.policy.as_ref().unwrap().federation_grants.len(), 1);
+
+        rehome(&mut archive, "org-b");
+        let policy = archive.policy.as_ref().unwrap();
+        assert_eq!(policy.organization_id, "org-b");
+        assert!(policy.federation_grants.is_empty());
+        assert!(archive.layers.iter().all(|l| l.organization_id == "org-b"));
+        assert!(archive.activities.iter().all(|a| a.organization_id == "org-b"));
+        assert!(archive.user_settings.iter().all(|s| s.organization_id == "org-b"));
+    }
+
+    #[test]
+    fn test_assign_new_ids() {
+        let mut archive = archive();
+        assign_new_ids(&mut archive);
+
+        let new_layer = &archive.layers[0].id;
+        assert_ne!(new_layer, "layer-1");
+        assert_eq!(archive.layers[1].id, TERMS_LAYER_ID);
+
+        let activity = &archive.activities[0];
+        assert_ne!(activity.id, "activity-1");
+        assert_eq!(&activity.scope, new_layer);
+        assert_eq!(&activity.scope_id, new_layer);
+        assert_eq!(activity.merged_from, ["gone"]);
+
+        assert_eq!(archive.user_settings[0].layer_order, Some(vec![new_layer.clone(), TERMS_LAYER_ID.to_string()]));
+    }
+}
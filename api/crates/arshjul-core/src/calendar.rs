@@ -0,0 +1,156 @@
+//! # Calendar Conventions
+//!
+//! Nordic organizations plan by week number, others by month. Each
+//! organization sets its conventions once in its policy
+//! (`PUT /api/admin/policy/calendar`), and server-rendered outputs follow
+//! them: exports carry [`CalendarMetadata`] and week numbers, and planning
+//! analytics count activities per week.
+//!
+//! | Numbering | Weeks start | Week 1 |
+//! |-----------|-------------|--------|
+//! | `iso` (default) | Monday | holds the year's first Thursday; late December can be week 1 of the next year, early January week 52/53 of the last |
+//! | `simple` | the first day of week | holds 1 January; the days of a year are always numbered in that year |
+
+use crate::export::{ExportError, ExportLocale};
+use crate::models::{CalendarMetadata, CalendarSettings, FirstDayOfWeek, WeekNumber, WeekNumbering, WeekSpan};
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use thiserror::Error;
+
+/// Invalid calendar settings
+#[derive(Debug, Error, PartialEq)]
+pub enum CalendarError {
+    #[error(transparent)]
+    Locale(#[from] ExportError),
+    
+    #[error("ISO weeks start on Monday")]
+    IsoWeekStart,
+}
+
+/// Check settings before they are saved
+pub fn validate(settings: &CalendarSettings) -> Result<(), CalendarError> {
+    ExportLocale::parse(&settings.locale)?;
+    if settings.week_numbering == WeekNumbering::Iso && settings.first_day_of_week != FirstDayOfWeek::Monday {
+        return Err(CalendarError::IsoWeekStart);
+    }
+    Ok(())
+}
+
+fn weekday(first_day: FirstDayOfWeek) -> Weekday {
+    match first_day {
+        FirstDayOfWeek::Monday => Weekday::Mon,
+        FirstDayOfWeek::Sunday => Weekday::Sun,
+        FirstDayOfWeek::Saturday => Weekday::Sat,
+    }
+}
+
+/// First day of the week holding `date`
+pub fn week_start(date: NaiveDate, first_day: FirstDayOfWeek) -> NaiveDate {
+    date.week(weekday(first_day)).first_day()
+}
+
+/// Number of the week holding `date`
+pub fn week_number(date: NaiveDate, settings: &CalendarSettings) -> WeekNumber {
+    match settings.week_numbering {
+        WeekNumbering::Iso => {
+            let week = date.iso_week();
+            WeekNumber { year: week.year(), week: week.week() }
+        }
+        WeekNumbering::Simple => {
+            let first_week = week_start(date.with_ordinal(1).unwrap_or(date), settings.first_day_of_week);
+            WeekNumber { year: date.year(), week: ((date - first_week).num_days() / 7 + 1) as u32 }
+        }
+    }
+}
+
+/// Week in ISO notation, e.g. `2025-W09`
+pub fn format_week(week: WeekNumber) -> String {
+    format!("{}-W{:02}", week.year, week.week)
+}
+
+/// Weeks overlapping `year`, each with the number it has in that year
+pub fn weeks(year: i32, settings: &CalendarSettings) -> Vec<WeekSpan> {
+    let (Some(first), Some(last)) = (NaiveDate::from_ymd_opt(year, 1, 1), NaiveDate::from_ymd_opt(year, 12, 31)) else {
+        return Vec::new();
+    };
+    let first_day = match settings.week_numbering {
+        WeekNumbering::Iso => FirstDayOfWeek::Monday,
+        WeekNumbering::Simple => settings.first_day_of_week,
+    };
+    
+    let mut weeks = Vec::new();
+    let mut start = week_start(first, first_day);
+    while start <= last {
+        let end = start + Days::new(6);
+        weeks.push(WeekSpan {
+            number: week_number(start.max(first), settings),
+            start,
+            end,
+        });
+        start = start + Days::new(7);
+    }
+    weeks
+}
+
+/// Conventions and weeks of `year` for rendered outputs
+pub fn metadata(year: i32, locale: ExportLocale, settings: &CalendarSettings) -> CalendarMetadata {
+    let mut weekday_names = locale.weekday_names().map(str::to_string).to_vec();
+    weekday_names.rotate_left(weekday(settings.first_day_of_week).num_days_from_monday() as usize);
+    
+    CalendarMetadata {
+        locale: locale.tag().to_string(),
+        first_day_of_week: settings.first_day_of_week,
+        week_numbering: settings.week_numbering,
+        month_names: locale.month_names().map(str::to_string).to_vec(),
+        weekday_names,
+        weeks: weeks(year, settings),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+    
+    fn simple(first_day_of_week: FirstDayOfWeek) -> CalendarSettings {
+        CalendarSettings { first_day_of_week, week_numbering: WeekNumbering::Simple, ..Default::default() }
+    }
+    
+    #[test]
+    fn test_week_numbers() {
+        let iso = CalendarSettings::default();
+        // 2021 starts on a Friday, so its first days are in 2020's week 53
+        assert_eq!(week_number(date("2021-01-01"), &iso), WeekNumber { year: 2020, week: 53 });
+        assert_eq!(week_number(date("2021-01-04"), &iso), WeekNumber { year: 2021, week: 1 });
+        assert_eq!(week_number(date("2024-12-30"), &iso), WeekNumber { year: 2025, week: 1 });
+        assert_eq!(format_week(week_number(date("2025-03-01"), &iso)), "2025-W09");
+        
+        let sunday = simple(FirstDayOfWeek::Sunday);
+        assert_eq!(week_number(date("2021-01-01"), &sunday), WeekNumber { year: 2021, week: 1 });
+        assert_eq!(week_number(date("2021-01-03"), &sunday), WeekNumber { year: 2021, week: 2 });
+        assert_eq!(week_number(date("2024-12-31"), &sunday), WeekNumber { year: 2024, week: 53 });
+        
+        assert_eq!(validate(&simple(FirstDayOfWeek::Saturday)), Ok(()));
+        assert_eq!(validate(&CalendarSettings { first_day_of_week: FirstDayOfWeek::Sunday, ..Default::default() }), Err(CalendarError::IsoWeekStart));
+        assert!(validate(&CalendarSettings { locale: "de".to_string(), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_weeks_and_metadata() {
+        let weeks = weeks(2021, &CalendarSettings::default());
+        assert_eq!(weeks.len(), 53);
+        assert_eq!((weeks[0].number, weeks[0].start), (WeekNumber { year: 2020, week: 53 }, date("2020-12-28")));
+        assert_eq!(weeks[1].number, WeekNumber { year: 2021, week: 1 });
+        assert_eq!(weeks[52].number, WeekNumber { year: 2021, week: 52 });
+        
+        let metadata = metadata(2025, ExportLocale::Norwegian, &simple(FirstDayOfWeek::Sunday));
+        assert_eq!(metadata.locale, "nb");
+        assert_eq!(metadata.weekday_names[..2], ["søn", "man"]);
+        assert_eq!(metadata.month_names[4], "mai");
+        assert_eq!(serde_json::to_value(&metadata.weeks[0]).unwrap(), serde_json::json!({
+            "year": 2025, "week": 1, "start": "2024-12-29", "end": "2025-01-04",
+        }));
+    }
+}
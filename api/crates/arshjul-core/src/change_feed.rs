@@ -0,0 +1,218 @@
+//! # Change Feed Processing
+//!
+//! Caches are purged by the event bus of the instance that made a change
+//! (see [`crate::invalidation`]). With several instances, the others keep
+//! serving their in-process share cache until it expires, and writes made
+//! directly in the database (restores, migrations) purge nothing at all.
+//!
+//! [`ChangeFeedProcessor`] closes that gap where the backend has a
+//! [`ChangeFeed`] (Cosmos DB). Every instance polls it and hands each write
+//! to its own cache invalidation, as if it had been made locally:
+//!
+//! | Write | Handling |
+//! |-------|----------|
+//! | Share | short code index entry restored if missing, then the share's caches purged |
+//! | Activity | caches of every share of the organization purged, once per organization and poll |
+//!
+//! Polling starts at the time the processor is created; the checkpoint is
+//! kept in memory. Writes of this instance come back through the feed and
+//! are purged a second time, which is harmless. Deletions don't show in the
+//! feed; the deleting instance purges shared caches, and in-process caches
+//! of the others expire with their TTL.
+
+use crate::clock::Clock;
+use crate::events::{ChangeKind, DomainEvent, EntityChange, EntityKind, EventSubscriber};
+use crate::storage::{ChangeFeed, ChangedDocument, StorageError};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Position in the feed: the latest write second seen, and the documents
+/// written in it that were handled already (the feed's clock has seconds)
+#[derive(Debug, Default)]
+struct Checkpoint {
+    timestamp: i64,
+    seen: HashSet<(String, String)>,
+}
+
+impl Checkpoint {
+    fn starting_at(timestamp: i64) -> Self {
+        Self { timestamp, seen: HashSet::new() }
+    }
+
+    /// Changes not handled yet, moving the checkpoint past them
+    fn advance(&mut self, changes: Vec<ChangedDocument>) -> Vec<ChangedDocument> {
+        let key = |c: &ChangedDocument| (c.organization_id.clone(), c.id.clone());
+        let new: Vec<ChangedDocument> = changes.into_iter()
+            .filter(|c| c.timestamp > self.timestamp || (c.timestamp == self.timestamp && !self.seen.contains(&key(c))))
+            .collect();
+        if let Some(latest) = new.iter().map(|c| c.timestamp).max() {
+            if latest > self.timestamp {
+                self.timestamp = latest;
+                self.seen.clear();
+            }
+            self.seen.extend(new.iter().filter(|c| c.timestamp == latest).map(key));
+        }
+        new
+    }
+}
+
+/// Polls a [`ChangeFeed`] and invalidates caches for what it reports
+pub struct ChangeFeedProcessor {
+    feed: Arc<dyn ChangeFeed>,
+    invalidation: Arc<dyn EventSubscriber>,
+    shares: Mutex<Checkpoint>,
+    activities: Mutex<Checkpoint>,
+}
+
+impl ChangeFeedProcessor {
+    /// Process writes from now on, handing them to `invalidation`
+    /// (a [`CacheInvalidation`](crate::invalidation::CacheInvalidation))
+    pub fn new(feed: Arc<dyn ChangeFeed>, invalidation: Arc<dyn EventSubscriber>, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now().timestamp();
+        Self {
+            feed,
+            invalidation,
+            shares: Mutex::new(Checkpoint::starting_at(now)),
+            activities: Mutex::new(Checkpoint::starting_at(now)),
+        }
+    }
+
+    /// Handle the writes since the last poll; returns how many there were
+    pub async fn run_once(&self) -> Result<usize, StorageError> {
+        let since = self.shares.lock().unwrap_or_else(|e| e.into_inner()).timestamp;
+        let changed = self.feed.shares_changed_since(since).await?;
+        let shares = self.shares.lock().unwrap_or_else(|e| e.into_inner()).advance(changed);
+        for share in &shares {
+            match self.feed.repair_short_code(share).await {
+                Ok(true) => tracing::info!(organization_id = %share.organization_id, share_id = %share.id, "Restored missing short code index entry"),
+                Ok(false) => {}
+                Err(e) => tracing::warn!(share_id = %share.id, error = %e, "Failed to check short code index entry"),
+            }
+            let mut change = EntityChange::new(&share.organization_id, EntityKind::Share, &share.id, ChangeKind::Updated, None);
+            if let Some(ref code) = share.short_code {
+                change = change.with_key(code);
+            }
+            self.invalidate(change).await;
+        }
+
+        let since = self.activities.lock().unwrap_or_else(|e| e.into_inner()).timestamp;
+        let changed = self.feed.activities_changed_since(since).await?;
+        let activities = self.activities.lock().unwrap_or_else(|e| e.into_inner()).advance(changed);
+        // Every share of the organization is affected either way; one event each
+        let by_organization: BTreeMap<&str, &str> = activities.iter()
+            .map(|a| (a.organization_id.as_str(), a.id.as_str()))
+            .collect();
+        for (organization_id, activity_id) in by_organization {
+            self.invalidate(EntityChange::new(organization_id, EntityKind::Activity, activity_id, ChangeKind::Updated, None)).await;
+        }
+
+        Ok(shares.len() + activities.len())
+    }
+
+    async fn invalidate(&self, change: EntityChange) {
+        if let Err(e) = self.invalidation.handle(&DomainEvent::EntityChanged(change)).await {
+            tracing::warn!(backend = self.feed.name(), error = %e, "Cache invalidation from the change feed failed");
+        }
+    }
+
+    /// Poll every `interval` until the task is dropped
+    pub async fn run(self: Arc<Self>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.run_once().await {
+                Ok(0) => {}
+                Ok(changes) => tracing::debug!(backend = self.feed.name(), changes, "Processed change feed"),
+                Err(e) => tracing::warn!(backend = self.feed.name(), error = %e, "Change feed poll failed"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::events::EventError;
+    use async_trait::async_trait;
+
+    fn doc(organization_id: &str, id: &str, timestamp: i64) -> ChangedDocument {
+        ChangedDocument {
+            organization_id: organization_id.to_string(),
+            id: id.to_string(),
+            short_code: Some(format!("code-{}", id)),
+            timestamp,
+        }
+    }
+
+    /// Returns every document written at or after `since`, as a real feed would
+    #[derive(Default)]
+    struct FakeFeed {
+        shares: Mutex<Vec<ChangedDocument>>,
+        activities: Mutex<Vec<ChangedDocument>>,
+        repaired: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ChangeFeed for FakeFeed {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn shares_changed_since(&self, since: i64) -> Result<Vec<ChangedDocument>, StorageError> {
+            Ok(self.shares.lock().unwrap().iter().filter(|d| d.timestamp >= since).cloned().collect())
+        }
+
+        async fn activities_changed_since(&self, since: i64) -> Result<Vec<ChangedDocument>, StorageError> {
+            Ok(self.activities.lock().unwrap().iter().filter(|d| d.timestamp >= since).cloned().collect())
+        }
+
+        async fn repair_short_code(&self, share: &ChangedDocument) -> Result<bool, StorageError> {
+            self.repaired.lock().unwrap().push(share.id.clone());
+            Ok(share.id == "s-missing")
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(EntityKind, String, Option<String>)>>);
+
+    #[async_trait]
+    impl EventSubscriber for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn handle(&self, event: &DomainEvent) -> Result<(), EventError> {
+            if let DomainEvent::EntityChanged(change) = event {
+                self.0.lock().unwrap().push((change.entity, change.organization_id.clone(), change.key.clone()));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_once() {
+        let clock = Arc::new(ManualClock::new("2026-01-01T00:00:00Z".parse().unwrap()));
+        let start = clock.now().timestamp();
+        let feed = Arc::new(FakeFeed::default());
+        let recorder = Arc::new(Recorder::default());
+        let processor = ChangeFeedProcessor::new(feed.clone(), recorder.clone(), clock);
+
+        feed.shares.lock().unwrap().extend([doc("org-1", "s-old", start - 1), doc("org-1", "s-missing", start)]);
+        feed.activities.lock().unwrap().extend([doc("org-1", "a-1", start), doc("org-1", "a-2", start + 1), doc("org-2", "a-3", start + 1)]);
+        assert_eq!(processor.run_once().await.unwrap(), 4);
+        assert_eq!(*feed.repaired.lock().unwrap(), ["s-missing"]);
+        assert_eq!(*recorder.0.lock().unwrap(), [
+            (EntityKind::Share, "org-1".to_string(), Some("code-s-missing".to_string())),
+            (EntityKind::Activity, "org-1".to_string(), None),
+            (EntityKind::Activity, "org-2".to_string(), None),
+        ]);
+
+        // Writes in the second already seen are not handled twice; later ones in it are
+        recorder.0.lock().unwrap().clear();
+        feed.shares.lock().unwrap().push(doc("org-2", "s-2", start));
+        assert_eq!(processor.run_once().await.unwrap(), 1);
+        assert_eq!(*recorder.0.lock().unwrap(), [(EntityKind::Share, "org-2".to_string(), Some("code-s-2".to_string()))]);
+        assert_eq!(processor.run_once().await.unwrap(), 0);
+    }
+}
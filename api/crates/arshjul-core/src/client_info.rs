@@ -0,0 +1,314 @@
+//! # Client Identity
+//!
+//! Extracts the real client IP and user agent for rate limiting, analytics
+//! and IP allow-lists. The API runs behind the Functions front end and
+//! optionally Azure Front Door, so the TCP peer is never the client.
+//!
+//! ## Resolution Order
+//!
+//! 1. `X-Azure-ClientIP` - only when Front Door is trusted, and (if
+//!    configured) `X-Azure-FDID` matches our Front Door profile ID
+//! 2. `X-Forwarded-For` - walked right to left, skipping trusted proxies;
+//!    the first untrusted hop is the client. When every hop is trusted, the
+//!    rightmost one is used: hops further left are whatever the client sent
+//! 3. The TCP peer address, if the runtime provides one
+//!
+//! Headers set by untrusted parties are never taken at face value: without
+//! trusted proxies configured, only the rightmost `X-Forwarded-For` hop
+//! (appended by the platform itself) is used.
+//!
+//! The client's country comes from `X-Client-Country` (ISO 3166-1 alpha-2),
+//! which a Front Door rule must overwrite from the edge's geo lookup. It is
+//! only taken from our own Front Door profile (`X-Azure-FDID` configured and
+//! matching): other proxies pass whatever the client sent through.
+
+use serde::Serialize;
+use std::net::{IpAddr, Ipv6Addr};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Maximum stored user agent length
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Client info errors
+#[derive(Debug, Error)]
+pub enum ClientInfoError {
+    #[error("Invalid CIDR range: {0}")]
+    InvalidCidr(String),
+}
+
+/// IP network in CIDR notation (e.g., "10.0.0.0/8", "2001:db8::/32")
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    /// Check if an address belongs to this network
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = ClientInfoError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || ClientInfoError::InvalidCidr(s.to_string());
+        
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let network: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix {
+            Some(p) => p.parse::<u8>().map_err(|_| invalid())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+        
+        Ok(Self { network, prefix_len })
+    }
+}
+
+/// Which proxy headers to trust
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxyConfig {
+    /// Proxies allowed to append to `X-Forwarded-For`
+    pub trusted_proxies: Vec<IpCidr>,
+    
+    /// Trust `X-Azure-ClientIP` set by Azure Front Door
+    pub trust_front_door: bool,
+    
+    /// Expected `X-Azure-FDID` (Front Door profile ID); rejects other profiles
+    pub front_door_id: Option<String>,
+}
+
+impl TrustedProxyConfig {
+    /// Parse a comma-separated list of CIDR ranges
+    pub fn parse_proxies(list: &str) -> Result<Vec<IpCidr>, ClientInfoError> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(IpCidr::from_str)
+            .collect()
+    }
+    
+    fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+/// Resolved client identity for a request
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientInfo {
+    /// Best-effort client IP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<IpAddr>,
+    
+    /// User agent (truncated)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    
+    /// Country code reported by a trusted edge (uppercase)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+impl ClientInfo {
+    /// Extract client info from request headers
+    pub fn from_headers(
+        headers: &[(String, String)],
+        peer_addr: Option<IpAddr>,
+        config: &TrustedProxyConfig,
+    ) -> Self {
+        let ip = front_door_client_ip(headers, config)
+            .or_else(|| forwarded_for_client_ip(headers, config))
+            .or(peer_addr);
+        
+        let user_agent = header(headers, "user-agent")
+            .map(|ua| ua.trim())
+            .filter(|ua| !ua.is_empty())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        
+        let country = Some(())
+            .filter(|_| config.front_door_id.is_some() && front_door_verified(headers, config))
+            .and_then(|_| header(headers, "x-client-country"))
+            .map(str::trim)
+            .filter(|c| c.len() == 2 && c.chars().all(|ch| ch.is_ascii_alphabetic()))
+            .map(str::to_ascii_uppercase);
+        
+        Self { ip, user_agent, country }
+    }
+    
+    /// The client's network, for per-client limits: the IPv4 address, or
+    /// the /64 of an IPv6 one, since one subscriber usually holds a whole
+    /// /64; None when the IP is unknown
+    pub fn network(&self) -> Option<String> {
+        match self.ip? {
+            IpAddr::V4(ip) => Some(ip.to_string()),
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => Some(ip.to_string()),
+                None => Some(format!("{}/64", Ipv6Addr::from(u128::from(ip) & !(u64::MAX as u128)))),
+            },
+        }
+    }
+}
+
+/// Find a header value (case-insensitive)
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Parse a forwarded address, tolerating ports ("1.2.3.4:443", "[::1]:443")
+fn parse_forwarded_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    if let Ok(ip) = value.parse() {
+        return Some(ip);
+    }
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split_once(']').and_then(|(ip, _)| ip.parse().ok());
+    }
+    value.rsplit_once(':').and_then(|(ip, _)| ip.parse().ok())
+}
+
+/// Whether the request came through our trusted Front Door profile
+fn front_door_verified(headers: &[(String, String)], config: &TrustedProxyConfig) -> bool {
+    config.trust_front_door && config.front_door_id.as_ref()
+        .is_none_or(|expected| header(headers, "x-azure-fdid").map(str::trim) == Some(expected.as_str()))
+}
+
+fn front_door_client_ip(headers: &[(String, String)], config: &TrustedProxyConfig) -> Option<IpAddr> {
+    if !front_door_verified(headers, config) {
+        return None;
+    }
+    header(headers, "x-azure-clientip").and_then(parse_forwarded_ip)
+}
+
+fn forwarded_for_client_ip(headers: &[(String, String)], config: &TrustedProxyConfig) -> Option<IpAddr> {
+    // Multiple headers are equivalent to one comma-joined list
+    let hops: Vec<IpAddr> = headers.iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("x-forwarded-for"))
+        .flat_map(|(_, v)| v.split(','))
+        .filter_map(parse_forwarded_ip)
+        .collect();
+    
+    hops.iter()
+        .rev()
+        .find(|ip| !config.is_trusted(ip))
+        .or(hops.last())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+    
+    #[test]
+    fn test_cidr_contains() {
+        let v4: IpCidr = "10.0.0.0/8".parse().unwrap();
+        assert!(v4.contains(&"10.20.30.40".parse().unwrap()));
+        assert!(!v4.contains(&"11.0.0.1".parse().unwrap()));
+        
+        let v6: IpCidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(&"2001:db8::1".parse().unwrap()));
+        assert!(!v6.contains(&"10.0.0.1".parse().unwrap()));
+        
+        let single: IpCidr = "192.168.1.1".parse().unwrap();
+        assert!(single.contains(&"192.168.1.1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("0.0.0.0/0".parse::<IpCidr>().unwrap().contains(&"8.8.8.8".parse().unwrap()));
+    }
+    
+    #[test]
+    fn test_forwarded_for_skips_trusted_proxies() {
+        let config = TrustedProxyConfig {
+            trusted_proxies: TrustedProxyConfig::parse_proxies("10.0.0.0/8, 172.16.0.1").unwrap(),
+            ..Default::default()
+        };
+        let h = headers(&[
+            ("X-Forwarded-For", "1.1.1.1, 203.0.113.7:51234, 172.16.0.1"),
+            ("X-Forwarded-For", "10.1.2.3"),
+            ("User-Agent", "Mozilla/5.0"),
+        ]);
+        
+        let info = ClientInfo::from_headers(&h, None, &config);
+        assert_eq!(info.ip, Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(info.user_agent.as_deref(), Some("Mozilla/5.0"));
+        
+        // Without trusted proxies only the platform-appended hop counts
+        let info = ClientInfo::from_headers(&h, None, &TrustedProxyConfig::default());
+        assert_eq!(info.ip, Some("10.1.2.3".parse().unwrap()));
+        
+        // A client posing as a trusted proxy doesn't get its leftmost entry taken
+        let h = headers(&[("X-Forwarded-For", "10.9.9.9, 172.16.0.1, 10.1.2.3")]);
+        let info = ClientInfo::from_headers(&h, None, &config);
+        assert_eq!(info.ip, Some("10.1.2.3".parse().unwrap()));
+    }
+    
+    #[test]
+    fn test_front_door_header() {
+        let config = TrustedProxyConfig {
+            trust_front_door: true,
+            front_door_id: Some("fd-123".to_string()),
+            ..Default::default()
+        };
+        
+        let h = headers(&[("X-Azure-ClientIP", "[2001:db8::5]:443"), ("X-Azure-FDID", "fd-123"), ("X-Forwarded-For", "9.9.9.9")]);
+        assert_eq!(ClientInfo::from_headers(&h, None, &config).ip, Some("2001:db8::5".parse().unwrap()));
+        
+        // Wrong Front Door profile falls back to X-Forwarded-For
+        let h = headers(&[("X-Azure-ClientIP", "1.2.3.4"), ("X-Azure-FDID", "other"), ("X-Forwarded-For", "9.9.9.9")]);
+        assert_eq!(ClientInfo::from_headers(&h, None, &config).ip, Some("9.9.9.9".parse().unwrap()));
+        
+        // Peer address is the last resort
+        let peer = "192.0.2.1".parse().ok();
+        assert_eq!(ClientInfo::from_headers(&[], peer, &config).ip, peer);
+        
+        // Country only from the trusted edge
+        let h = headers(&[("X-Azure-FDID", "fd-123"), ("X-Client-Country", "no")]);
+        assert_eq!(ClientInfo::from_headers(&h, None, &config).country.as_deref(), Some("NO"));
+        let h = headers(&[("X-Client-Country", "NO")]);
+        assert_eq!(ClientInfo::from_headers(&h, None, &config).country, None);
+        assert_eq!(ClientInfo::from_headers(&h, None, &TrustedProxyConfig::default()).country, None);
+        
+        // Proxies and profiles we can't tell apart from any other pass client headers through
+        let h = headers(&[("X-Forwarded-For", "9.9.9.9, 10.0.0.1"), ("X-Client-Country", "NO")]);
+        let proxies = TrustedProxyConfig { trusted_proxies: TrustedProxyConfig::parse_proxies("10.0.0.0/8").unwrap(), ..Default::default() };
+        assert_eq!(ClientInfo::from_headers(&h, None, &proxies).country, None);
+        let any_profile = TrustedProxyConfig { trust_front_door: true, ..Default::default() };
+        assert_eq!(ClientInfo::from_headers(&h, None, &any_profile).country, None);
+    }
+    
+    #[test]
+    fn test_network() {
+        let network = |ip: &str| ClientInfo { ip: ip.parse().ok(), ..Default::default() }.network();
+        assert_eq!(network("192.0.2.7").as_deref(), Some("192.0.2.7"));
+        assert_eq!(network("::ffff:192.0.2.7").as_deref(), Some("192.0.2.7"));
+        assert_eq!(network("2001:db8:1:2:aaaa::1").as_deref(), Some("2001:db8:1:2::/64"));
+        assert_eq!(network("2001:db8:1:2:bbbb::9"), network("2001:db8:1:2:aaaa::1"));
+        assert_eq!(network("unknown"), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! # Clock
+//!
+//! Source of the current time for anything time-dependent (expiry, renewal,
+//! TTL, locks, analytics buckets), so tests can control time instead of
+//! racing `Utc::now()`.
+//!
+//! Production code gets a [`SystemClock`] via `HandlerContext` and storage
+//! constructors; tests inject a [`ManualClock`] and move it explicitly.
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::sync::Mutex;
+
+/// Current time provider
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+    
+    /// Current UTC date
+    fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
+
+/// Wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock that only moves when told to
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(now) }
+    }
+    
+    pub fn set(&self, now: DateTime<Utc>) {
+        if let Ok(mut current) = self.now.lock() {
+            *current = now;
+        }
+    }
+    
+    pub fn advance(&self, by: Duration) {
+        if let Ok(mut current) = self.now.lock() {
+            *current += by;
+        }
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now.lock().map(|n| *n).unwrap_or_else(|e| *e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_manual_clock() {
+        let start = DateTime::parse_from_rfc3339("2025-03-01T23:30:00Z").unwrap().with_timezone(&Utc);
+        let clock = ManualClock::new(start);
+        assert_eq!(clock.now(), start);
+        
+        clock.advance(Duration::hours(1));
+        assert_eq!(clock.now(), start + Duration::hours(1));
+        assert_eq!(clock.today(), NaiveDate::from_ymd_opt(2025, 3, 2).unwrap());
+        
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+}
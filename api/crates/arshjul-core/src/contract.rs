@@ -0,0 +1,396 @@
+//! # Contract Recording
+//!
+//! Guards the Teams frontend against accidental API breaks.
+//!
+//! [`ExchangeRecorder`] is opt-in middleware for the HTTP bindings: it writes
+//! each handler's request and response to `{dir}/{operation}.json` after
+//! sanitizing them. [`Replay`] runs handlers against recorded fixtures and
+//! reports responses that are no longer compatible with them.
+//!
+//! ## Sanitizing
+//!
+//! - Secrets and random access values (`shareKey`, share/embed/calendar URLs,
+//!   tokens, `shortCode`, emails) become `"<redacted>"`
+//! - In responses, UUIDs become `"<uuid>"` and RFC 3339 timestamps
+//!   `"<timestamp>"`, so re-recording only shows real changes; requests keep
+//!   them so they can be replayed
+//!
+//! ## Compatibility
+//!
+//! A response stays compatible with a fixture when the status is the same and
+//! every value in the fixture is still there with the same JSON type and
+//! value. Added fields are fine; `null` in the fixture matches anything, and
+//! error messages are only checked to be strings.
+//!
+//! Fixtures live in `fixtures/contracts`; after an intended API change,
+//! re-record them with
+//! `RECORD_CONTRACTS_DIR=fixtures/contracts cargo test -p arshjul-core contract`,
+//! the variable the server records with too.
+
+use crate::handlers::HttpResponse;
+use crate::models::ApiError;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Placeholder for removed secrets
+pub const REDACTED: &str = "<redacted>";
+
+/// Fields whose values are replaced by [`REDACTED`]
+const REDACTED_FIELDS: &[&str] = &[
+    "shareKey", "shareUrl", "embedCode", "calendarUrl", "shortCode",
+    "token", "nonce", "syncToken", "accessToken", "confirmationToken", "email",
+];
+
+/// Fields compared by type only (wording may change)
+const TYPE_ONLY_FIELDS: &[&str] = &["message", "error"];
+
+/// One sanitized request/response pair
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Exchange {
+    /// Handler name, e.g. `create_share`
+    pub operation: String,
+    
+    /// Request body (or query) the handler was called with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<Value>,
+    
+    pub status: u16,
+    pub response: Value,
+}
+
+impl Exchange {
+    /// Sanitized exchange for a handler result
+    pub fn capture<T: Serialize>(
+        operation: &str,
+        request: Option<Value>,
+        result: &Result<HttpResponse<T>, HttpResponse<ApiError>>,
+    ) -> Self {
+        let (status, response) = match result {
+            Ok(response) => (response.status, serde_json::to_value(&response.body)),
+            Err(response) => (response.status, serde_json::to_value(&response.body)),
+        };
+        
+        let mut exchange = Self {
+            operation: operation.to_string(),
+            request,
+            status,
+            response: response.unwrap_or(Value::Null),
+        };
+        if let Some(ref mut request) = exchange.request {
+            redact(request);
+        }
+        sanitize(&mut exchange.response);
+        exchange
+    }
+}
+
+/// Remove secrets in place
+pub fn redact(value: &mut Value) {
+    walk(value, false);
+}
+
+/// Remove secrets and volatile values in place
+pub fn sanitize(value: &mut Value) {
+    walk(value, true);
+}
+
+fn walk(value: &mut Value, normalize: bool) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    walk(value, normalize);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| walk(item, normalize)),
+        Value::String(s) if normalize => {
+            if uuid::Uuid::parse_str(s).is_ok() {
+                *s = "<uuid>".to_string();
+            } else if DateTime::parse_from_rfc3339(s).is_ok() {
+                *s = "<timestamp>".to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Differences that would break a client built against `expected`
+pub fn check_compatible(expected: &Exchange, actual: &Exchange) -> Result<(), Vec<String>> {
+    let mut problems = Vec::new();
+    if expected.status != actual.status {
+        problems.push(format!("{}: status {} -> {}", expected.operation, expected.status, actual.status));
+    }
+    compare(&expected.response, &actual.response, &expected.operation, false, &mut problems);
+    
+    if problems.is_empty() { Ok(()) } else { Err(problems) }
+}
+
+fn compare(expected: &Value, actual: &Value, path: &str, type_only: bool, problems: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Null, _) => {}
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let path = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual) => compare(value, actual, &path, TYPE_ONLY_FIELDS.contains(&key.as_str()), problems),
+                    None if value.is_null() => {}
+                    None => problems.push(format!("{}: missing", path)),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if actual.len() < expected.len() {
+                problems.push(format!("{}: {} items -> {}", path, expected.len(), actual.len()));
+            }
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                compare(expected, actual, &format!("{}[{}]", path, i), false, problems);
+            }
+        }
+        (Value::String(_), Value::String(_)) if type_only => {}
+        (expected, actual) if std::mem::discriminant(expected) != std::mem::discriminant(actual) => {
+            problems.push(format!("{}: {} -> {}", path, type_name(expected), type_name(actual)));
+        }
+        (expected, actual) if !type_only && expected != actual => {
+            problems.push(format!("{}: {} -> {}", path, expected, actual));
+        }
+        _ => {}
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Writes sanitized exchanges as fixtures
+pub struct ExchangeRecorder {
+    dir: PathBuf,
+}
+
+impl ExchangeRecorder {
+    /// Record into `dir`, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+    
+    /// Write `{operation}.json`, replacing the previous recording
+    pub fn record(&self, exchange: &Exchange) -> std::io::Result<()> {
+        let file_name: String = exchange.operation.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .collect();
+        let mut json = serde_json::to_string_pretty(exchange)?;
+        json.push('\n');
+        std::fs::write(self.dir.join(format!("{}.json", file_name)), json)
+    }
+    
+    /// Record a handler result and pass it through unchanged
+    pub fn capture<T: Serialize>(
+        &self,
+        operation: &str,
+        request: Option<Value>,
+        result: Result<HttpResponse<T>, HttpResponse<ApiError>>,
+    ) -> Result<HttpResponse<T>, HttpResponse<ApiError>> {
+        if let Err(e) = self.record(&Exchange::capture(operation, request, &result)) {
+            tracing::warn!("Failed to record {} exchange: {}", operation, e);
+        }
+        result
+    }
+}
+
+/// Load every `*.json` fixture in a directory, by operation
+pub fn load_fixtures(dir: &Path) -> std::io::Result<BTreeMap<String, Exchange>> {
+    let mut fixtures = BTreeMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "json") {
+            let exchange: Exchange = serde_json::from_slice(&std::fs::read(&path)?)?;
+            fixtures.insert(exchange.operation.clone(), exchange);
+        }
+    }
+    Ok(fixtures)
+}
+
+/// Replay runner: checks handler results against fixtures, or re-records them
+///
+/// Steps call [`Replay::request`] for the request body (the recorded one when
+/// replaying), run the handler, and pass the result to [`Replay::check`].
+pub struct Replay {
+    fixtures: BTreeMap<String, Exchange>,
+    recorder: Option<ExchangeRecorder>,
+    problems: Vec<String>,
+}
+
+impl Replay {
+    /// Compare against the fixtures in `dir`
+    pub fn load(dir: &Path) -> std::io::Result<Self> {
+        Ok(Self { fixtures: load_fixtures(dir)?, recorder: None, problems: Vec::new() })
+    }
+    
+    /// Overwrite the fixtures in `dir` with current responses
+    pub fn record(dir: &Path) -> std::io::Result<Self> {
+        Ok(Self { fixtures: BTreeMap::new(), recorder: Some(ExchangeRecorder::new(dir)?), problems: Vec::new() })
+    }
+    
+    /// Request body for a step: the recorded one, or `default` when recording
+    pub fn request(&self, operation: &str, default: Value) -> Value {
+        self.fixtures.get(operation)
+            .and_then(|f| f.request.clone())
+            .unwrap_or(default)
+    }
+    
+    /// Record or check one step
+    pub fn check<T: Serialize>(
+        &mut self,
+        operation: &str,
+        request: Option<Value>,
+        result: &Result<HttpResponse<T>, HttpResponse<ApiError>>,
+    ) {
+        let actual = Exchange::capture(operation, request, result);
+        if let Some(ref recorder) = self.recorder {
+            if let Err(e) = recorder.record(&actual) {
+                self.problems.push(format!("{}: {}", operation, e));
+            }
+            return;
+        }
+        
+        match self.fixtures.get(operation) {
+            Some(expected) => {
+                if let Err(problems) = check_compatible(expected, &actual) {
+                    self.problems.extend(problems);
+                }
+            }
+            None => self.problems.push(format!("{}: no fixture recorded", operation)),
+        }
+    }
+    
+    /// Incompatibilities found so far
+    pub fn finish(self) -> Result<(), Vec<String>> {
+        if self.problems.is_empty() { Ok(()) } else { Err(self.problems) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client_info::ClientInfo;
+    use crate::handlers::{self, tests::{admin, context}};
+    use crate::models::*;
+    use serde_json::json;
+    
+    fn parse<T: serde::de::DeserializeOwned>(request: &Value) -> T {
+        serde_json::from_value(request.clone()).unwrap()
+    }
+    
+    /// Set `RECORD_CONTRACTS_DIR` to re-record after an intended API change
+    #[tokio::test]
+    async fn test_contract_fixtures() {
+        let mut replay = match std::env::var_os("RECORD_CONTRACTS_DIR").filter(|d| !d.is_empty()) {
+            Some(dir) => Replay::record(Path::new(&dir)).unwrap(),
+            None => Replay::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/contracts")).unwrap(),
+        };
+        
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(Layer {
+            id: "layer-1".to_string(),
+            name: "Planning".to_string(),
+            description: None,
+            layer_type: LayerType::Custom,
+            color: "#3b82f6".to_string(),
+            ring_index: 0,
+            is_visible: true,
+            requires_approval: false,
+            visible_to_groups: Vec::new(),
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            organization_id: "org-1".to_string(),
+            created_by: "user-1".to_string(),
+            created_at: ctx.clock.now(),
+            updated_at: None,
+        }).await.unwrap();
+        
+        let request = replay.request("create_share", json!({
+            "visibility": "public", "name": "Q1 plan", "layerConfig": { "layerIds": ["layer-1"] },
+        }));
+        let result = handlers::create_share(&ctx, &user, parse(&request)).await;
+        replay.check("create_share", Some(request), &result);
+        let share = result.unwrap().body.share;
+        
+        let request = replay.request("list_shares", json!({}));
+        let result = handlers::list_shares(&ctx, &user, parse(&request)).await;
+        replay.check("list_shares", Some(request), &result);
+        
+        let request = replay.request("create_activity", json!({
+            "title": "Budget review", "startDate": "2025-03-10T00:00:00Z", "endDate": "2025-03-12T00:00:00Z",
+            "type": "review", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+            "description": "**Q2** numbers", "descriptionFormat": "markdown", "tags": ["finance"],
+        }));
+        let result = handlers::create_activity(&ctx, &user, parse(&request)).await;
+        replay.check("create_activity", Some(request), &result);
+        
+        let request = replay.request("list_activities", json!({}));
+        let result = handlers::list_activities(&ctx, &user, parse(&request)).await;
+        replay.check("list_activities", Some(request), &result);
+        
+        let result = handlers::list_layers(&ctx, &user).await;
+        replay.check("list_layers", None, &result);
+        
+        let result = handlers::get_notification_preferences(&ctx, &user).await;
+        replay.check("get_notification_preferences", None, &result);
+        
+        let result = handlers::access_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await;
+        replay.check("access_public_share", None, &result);
+        
+        let result = handlers::access_public_share(&ctx, &ClientInfo::default(), &share.short_code, &"0".repeat(64)).await;
+        replay.check("access_public_share_wrong_key", None, &result);
+        
+        let result = handlers::issue_public_nonce(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await;
+        replay.check("issue_public_nonce", None, &result);
+        
+        let nonce = result.unwrap().body.nonce;
+        let request = replay.request("report_public_share", json!({ "reason": "spam" }));
+        let result = handlers::report_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key, Some(&nonce), parse(&request)).await;
+        replay.check("report_public_share", Some(request), &result);
+        
+        let request = replay.request("get_delta", json!({}));
+        let result = handlers::get_delta(&ctx, &user, parse(&request)).await;
+        replay.check("get_delta", Some(request), &result);
+        
+        if let Err(problems) = replay.finish() {
+            panic!("API responses changed incompatibly:\n{}", problems.join("\n"));
+        }
+    }
+    
+    #[test]
+    fn test_compatibility_rules() {
+        let exchange = |status, response: Value| Exchange { operation: "op".to_string(), request: None, status, response };
+        let expected = exchange(200, json!({ "id": "<uuid>", "items": [{ "n": 1 }], "gone": null, "message": "Saved" }));
+        
+        // Additions and reworded messages are fine
+        assert!(check_compatible(&expected, &exchange(200, json!({
+            "id": "<uuid>", "items": [{ "n": 1, "extra": true }], "message": "Stored", "new": 1,
+        }))).is_ok());
+        
+        let problems = check_compatible(&expected, &exchange(201, json!({ "items": [{ "n": "1" }], "message": "Saved" }))).unwrap_err();
+        assert_eq!(problems, vec!["op: status 200 -> 201", "op.id: missing", "op.items[0].n: number -> string"]);
+        
+        let mut secret = json!({ "shareKey": "abc", "createdAt": "2025-03-01T12:00:00Z", "shareUrl": null });
+        sanitize(&mut secret);
+        assert_eq!(secret, json!({ "shareKey": REDACTED, "createdAt": "<timestamp>", "shareUrl": null }));
+    }
+}
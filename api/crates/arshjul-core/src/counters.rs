@@ -0,0 +1,262 @@
+//! # Counters
+//!
+//! `GET /api/shares` reports `totalCount`, which Table Storage and Cosmos DB
+//! can only answer by reading the whole partition. [`CountedShares`], put in front of
+//! the share backend by [`Storage::with_counters`](crate::storage::Storage::with_counters),
+//! answers it from a per-organization counter in a [`CounterStorage`]
+//! instead:
+//!
+//! | Call | Counter |
+//! |------|---------|
+//! | `create` | +1 |
+//! | `update` moving a share to the recycle bin | -1 |
+//! | `update` restoring a share from the recycle bin | +1 |
+//! | `delete` of a share not in the recycle bin | -1 |
+//! | `list` without filter | read; counted in full when missing or older than [`RECOUNT_AFTER_HOURS`] |
+//!
+//! Telling a restore from any other update takes a point read before the
+//! update, as does a delete; both are rare next to lists.
+//!
+//! Counts are of shares not in the recycle bin. Expired shares, and writes
+//! that bypass the API (cleanups, restores of backups), are only reflected
+//! at the next full count, as are changes racing with one. Filtered lists
+//! keep whatever total the backend reports: counters can't know how many
+//! shares match. Where that is none, `GET /api/shares` counts the matching
+//! shares in full.
+//!
+//! Backends that count exactly (memory, SQLite) need no counters; their
+//! totals are passed through.
+
+use crate::clock::{Clock, SystemClock};
+use crate::models::*;
+use crate::storage::*;
+use async_trait::async_trait;
+use chrono::Duration;
+use std::sync::Arc;
+
+/// Counter of the shares of an organization
+pub const SHARES_COUNTER: &str = "shares";
+
+/// Hours a counter is trusted before it is counted in full again
+pub const RECOUNT_AFTER_HOURS: i64 = 24;
+
+/// Share storage reporting totals from counters
+pub struct CountedShares<S: ?Sized> {
+    inner: Arc<S>,
+    counters: Arc<dyn CounterStorage>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: ShareStorage + ?Sized> CountedShares<S> {
+    pub fn new(inner: Arc<S>, counters: Arc<dyn CounterStorage>) -> Self {
+        Self { inner, counters, clock: Arc::new(SystemClock) }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Counters are advisory: a failed update is logged, not returned
+    async fn add(&self, organization_id: &str, delta: i64) {
+        if let Err(e) = self.counters.add(organization_id, SHARES_COUNTER, delta).await {
+            tracing::warn!(organization_id, error = %e, "Failed to update share counter");
+        }
+    }
+
+    /// Shares of the organization, from the counter or a full count
+    async fn total(&self, organization_id: &str) -> Result<u64, StorageError> {
+        let now = self.clock.now();
+        if let Some(counter) = self.counters.get(organization_id, SHARES_COUNTER).await? {
+            if now - counter.counted_at < Duration::hours(RECOUNT_AFTER_HOURS) {
+                return Ok(counter.value);
+            }
+        }
+
+        let value = list_all_shares(self.inner.as_ref(), organization_id).await?.len() as u64;
+        self.counters.set(organization_id, SHARES_COUNTER, Counter { value, counted_at: now }).await?;
+        tracing::debug!(organization_id, value, "Counted shares");
+        Ok(value)
+    }
+
+    /// Whether an update brings the share back from the recycle bin
+    ///
+    /// Shares in the recycle bin can't be fetched for an update, so one
+    /// with `deletedAt` set is moving there, and one without may be coming back
+    async fn restoring(&self, share: &ShareLink) -> bool {
+        share.deleted_at.is_none()
+            && self.inner.get_deleted(&share.organization_id, &share.id).await.is_ok()
+    }
+
+    /// Count a written share that moved to or from the recycle bin
+    async fn moved(&self, share: &ShareLink, restored: bool) {
+        if share.deleted_at.is_some() {
+            self.add(&share.organization_id, -1).await;
+        } else if restored {
+            self.add(&share.organization_id, 1).await;
+        }
+    }
+}
+
+#[async_trait]
+impl<S: ShareStorage + ?Sized> ShareStorage for CountedShares<S> {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let share = self.inner.create(share).await?;
+        self.add(&share.organization_id, 1).await;
+        Ok(share)
+    }
+
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.inner.get(organization_id, share_id).await
+    }
+
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        self.inner.get_by_short_code(short_code).await
+    }
+
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        // Shares in the recycle bin can't be fetched for an update, so one
+        // with `deletedAt` set is moving there, and one without may be coming back
+        let restored = self.restoring(&share).await;
+        let share = self.inner.update(share).await?;
+        self.moved(&share, restored).await;
+        Ok(share)
+    }
+
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        self.inner.get_tagged(organization_id, share_id).await
+    }
+
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        let restored = self.restoring(&share).await;
+        let replaced = self.inner.replace(share, etag).await?;
+        if let Some(ref share) = replaced {
+            self.moved(share, restored).await;
+        }
+        Ok(replaced)
+    }
+
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        let counted = self.inner.get(organization_id, share_id).await.is_ok();
+        self.inner.delete(organization_id, share_id).await?;
+        if counted {
+            self.add(organization_id, -1).await;
+        }
+        Ok(())
+    }
+
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        self.inner.get_tombstone(short_code).await
+    }
+
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        let filtered = options.filter.is_some();
+        let mut result = self.inner.list(organization_id, options).await?;
+        if result.total_count.is_none() && !filtered {
+            result.total_count = Some(self.total(organization_id).await?);
+        }
+        Ok(result)
+    }
+
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.increment_views(organization_id, share_id).await
+    }
+
+    async fn get_deleted(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.inner.get_deleted(organization_id, share_id).await
+    }
+
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        self.inner.list_deleted(organization_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::recycle_bin::RecycleBin;
+    use crate::storage::memory_storage::{MemoryCounterStorage, MemoryShareStorage};
+
+    /// A backend that can't count, like Table Storage
+    struct Uncounted(MemoryShareStorage);
+
+    #[async_trait]
+    impl ShareStorage for Uncounted {
+        async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+            self.0.create(share).await
+        }
+
+        async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+            self.0.get(organization_id, share_id).await
+        }
+
+        async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+            self.0.get_by_short_code(short_code).await
+        }
+
+        async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+            self.0.update(share).await
+        }
+
+        async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+            self.0.delete(organization_id, share_id).await
+        }
+
+        async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+            self.0.get_tombstone(short_code).await
+        }
+
+        async fn list(&self, organization_id: &str, options: QueryOptions) -> Result<QueryResult<ShareLink>, StorageError> {
+            Ok(QueryResult { total_count: None, ..self.0.list(organization_id, options).await? })
+        }
+
+        async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+            self.0.increment_views(organization_id, share_id).await
+        }
+    }
+
+    fn share(id: &str) -> ShareLink {
+        crate::storage_tests::share("org-1", id, &format!("Code{id}"), "public")
+    }
+
+    #[tokio::test]
+    async fn test_totals_follow_writes() {
+        let clock = Arc::new(ManualClock::new("2026-01-01T00:00:00Z".parse().unwrap()));
+        let backend = Arc::new(RecycleBin::new(Arc::new(Uncounted(MemoryShareStorage::new()))));
+        let counters = Arc::new(MemoryCounterStorage::new());
+        let shares = CountedShares::new(backend.clone(), counters.clone()).with_clock(clock.clone());
+        let total = || async { shares.list("org-1", QueryOptions { page_size: Some(1), ..Default::default() }).await.unwrap().total_count };
+
+        // Shares written before counting are found by the first full count
+        backend.create(share("s-1")).await.unwrap();
+        assert_eq!(counters.get("org-1", SHARES_COUNTER).await.unwrap(), None);
+        assert_eq!(total().await, Some(1));
+
+        shares.create(share("s-2")).await.unwrap();
+        shares.create(share("s-3")).await.unwrap();
+        assert_eq!(total().await, Some(3));
+
+        let mut binned = shares.get("org-1", "s-2").await.unwrap();
+        binned.deleted_at = Some(clock.now());
+        let binned = shares.update(binned).await.unwrap();
+        assert_eq!(total().await, Some(2));
+        shares.update(ShareLink { deleted_at: None, ..binned }).await.unwrap();
+        assert_eq!(total().await, Some(3));
+        shares.update(shares.get("org-1", "s-2").await.unwrap()).await.unwrap();
+        shares.delete("org-1", "s-3").await.unwrap();
+        assert_eq!(total().await, Some(2));
+
+        // Filtered lists get no total; writes bypassing the counters show after a day
+        let filtered = QueryOptions { filter: FilterBuilder::new().eq(FilterField::Visibility, "public").build(), ..Default::default() };
+        assert_eq!(shares.list("org-1", filtered).await.unwrap().total_count, None);
+        backend.delete("org-1", "s-1").await.unwrap();
+        assert_eq!(total().await, Some(2));
+        clock.advance(Duration::hours(RECOUNT_AFTER_HOURS));
+        assert_eq!(total().await, Some(1));
+    }
+}
@@ -4,10 +4,16 @@
 
 use rand::Rng;
 
+/// Short code alphabet; excludes confusing characters: 0, O, I, l, 1
+const SHORT_CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghjkmnpqrstuvwxyz23456789";
+
 /// Generate a secure random key (64 hex characters = 256 bits)
 /// Matches frontend: `generateShareKey()` in sharing.ts
 pub fn generate_share_key() -> String {
-    let mut rng = rand::thread_rng();
+    share_key_with(&mut rand::thread_rng())
+}
+
+fn share_key_with<R: Rng + ?Sized>(rng: &mut R) -> String {
     let bytes: [u8; 32] = rng.gen();
     hex::encode(bytes)
 }
@@ -16,13 +22,14 @@ pub fn generate_share_key() -> String {
 /// Matches frontend: `generateShortCode()` in sharing.ts
 /// Excludes confusing characters: 0, O, I, l, 1
 pub fn generate_short_code() -> String {
-    const CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghjkmnpqrstuvwxyz23456789";
-    let mut rng = rand::thread_rng();
-    
+    short_code_with(&mut rand::thread_rng())
+}
+
+fn short_code_with<R: Rng + ?Sized>(rng: &mut R) -> String {
     (0..8)
         .map(|_| {
-            let idx = rng.gen_range(0..CHARS.len());
-            CHARS[idx] as char
+            let idx = rng.gen_range(0..SHORT_CODE_CHARS.len());
+            SHORT_CODE_CHARS[idx] as char
         })
         .collect()
 }
@@ -92,4 +99,51 @@ mod tests {
         assert!(!is_valid_short_code("AbCd123")); // too short
         assert!(!is_valid_short_code("AbCd1234!")); // invalid char
     }
+    
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        
+        proptest! {
+            #[test]
+            fn short_codes_avoid_confusing_characters(seed in any::<u64>()) {
+                let code = short_code_with(&mut StdRng::seed_from_u64(seed));
+                prop_assert!(is_valid_short_code(&code));
+                prop_assert!(code.bytes().all(|c| SHORT_CODE_CHARS.contains(&c)));
+                prop_assert!(!code.contains(['0', 'O', 'I', 'l', '1']));
+            }
+            
+            #[test]
+            fn short_code_alphabet_always_validates(code in "[A-HJ-NP-Za-km-z2-9]{8}") {
+                prop_assert!(is_valid_short_code(&code));
+            }
+            
+            #[test]
+            fn share_keys_round_trip_hex(seed in any::<u64>()) {
+                let key = share_key_with(&mut StdRng::seed_from_u64(seed));
+                prop_assert!(is_valid_share_key(&key));
+                let bytes = hex::decode(&key).unwrap();
+                prop_assert_eq!(bytes.len(), 32);
+                prop_assert_eq!(hex::encode(bytes), key);
+            }
+            
+            #[test]
+            fn secure_compare_matches_equality(a in ".{0,80}", b in ".{0,80}") {
+                prop_assert_eq!(secure_compare(&a, &b), a == b);
+                prop_assert!(secure_compare(&a, &a.clone()));
+            }
+            
+            /// A difference at any position is caught (no early exit on a prefix)
+            #[test]
+            fn secure_compare_checks_every_byte(key in "[0-9a-f]{64}", position in 0usize..64) {
+                let mut other = key.clone().into_bytes();
+                other[position] = if other[position] == b'0' { b'1' } else { b'0' };
+                let other = String::from_utf8(other).unwrap();
+                prop_assert!(!secure_compare(&key, &other));
+                prop_assert!(!secure_compare(&other, &key));
+            }
+        }
+    }
 }
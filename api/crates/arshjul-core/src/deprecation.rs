@@ -0,0 +1,240 @@
+//! # Deprecations
+//!
+//! Routes that are being replaced (by `v2` endpoints, renamed fields or
+//! removed behavior) are listed once in [`DEPRECATED_ROUTES`]. Their handlers
+//! pass every response, errors included, through [`Deprecations::apply`],
+//! which adds
+//!
+//! | Header | Value |
+//! |--------|-------|
+//! | `Deprecation` | `@{unix seconds}` of the deprecation date (RFC 9745) |
+//! | `Sunset` | HTTP date after which the route may be removed (RFC 8594), if planned |
+//! | `Link` | `<{guide}>; rel="deprecation"`, if there is a migration guide |
+//!
+//! and counts the call against the caller's organization. The first call of
+//! an organization is logged as a warning, later ones at `debug`.
+//! `GET /api/metrics/deprecations` reports which organizations still call
+//! each route, so they can be contacted before the sunset;
+//! `GET /api/admin/deprecations` shows an organization's admins their own
+//! calls.
+//!
+//! Counts live in process memory: each instance reports the callers it has
+//! served since it started, and a restart starts over. The warning on an
+//! organization's first call is the durable record, in the logs of every
+//! instance it reached.
+//!
+//! Removing a route after its sunset is a code change; this module only
+//! announces it.
+
+use crate::clock::{Clock, SystemClock};
+use crate::handlers::HttpResponse;
+use crate::models::{DeprecatedRouteUse, DeprecationReport};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// A deprecated route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedRoute {
+    pub method: &'static str,
+    /// Route pattern; `{name}` matches one path segment
+    pub path: &'static str,
+    pub deprecated_at: NaiveDate,
+    /// Planned removal
+    pub sunset: Option<NaiveDate>,
+    /// Migration guide
+    pub link: Option<&'static str>,
+}
+
+impl DeprecatedRoute {
+    /// Whether a request is for this route
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        let path = path.split('?').next().unwrap_or(path);
+        let mut pattern = self.path.trim_matches('/').split('/');
+        let mut segments = path.trim_matches('/').split('/');
+        self.method.eq_ignore_ascii_case(method) && loop {
+            match (pattern.next(), segments.next()) {
+                (None, None) => break true,
+                (Some(p), Some(s)) if p == s || (p.starts_with('{') && p.ends_with('}') && !s.is_empty()) => continue,
+                _ => break false,
+            }
+        }
+    }
+
+    /// Headers announcing the deprecation
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = vec![("Deprecation".to_string(), format!("@{}", midnight(self.deprecated_at).timestamp()))];
+        if let Some(sunset) = self.sunset {
+            headers.push(("Sunset".to_string(), midnight(sunset).format("%a, %d %b %Y %H:%M:%S GMT").to_string()));
+        }
+        if let Some(link) = self.link {
+            headers.push(("Link".to_string(), format!("<{}>; rel=\"deprecation\"", link)));
+        }
+        headers
+    }
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+}
+
+/// Routes currently deprecated
+///
+/// Add an entry when a replacement ships, and have the route's handler apply
+/// [`Deprecations`] to its responses; keep it until the route is removed.
+pub const DEPRECATED_ROUTES: &[DeprecatedRoute] = &[
+    // The archive can outgrow a request; the background export returns the same one
+    DeprecatedRoute {
+        method: "GET",
+        path: "/api/admin/export",
+        deprecated_at: NaiveDate::from_ymd_opt(2026, 10, 17).expect("valid date"),
+        sunset: None,
+        link: None,
+    },
+];
+
+#[derive(Debug, Clone)]
+struct Usage {
+    requests: u64,
+    first_used_at: DateTime<Utc>,
+    last_used_at: DateTime<Utc>,
+}
+
+/// Deprecated routes and their callers per organization
+pub struct Deprecations {
+    routes: Vec<DeprecatedRoute>,
+    /// By route index, then organization
+    usage: RwLock<HashMap<(usize, String), Usage>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for Deprecations {
+    fn default() -> Self {
+        Self::new(DEPRECATED_ROUTES.to_vec())
+    }
+}
+
+impl Deprecations {
+    pub fn new(routes: Vec<DeprecatedRoute>) -> Self {
+        Self::with_clock(routes, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(routes: Vec<DeprecatedRoute>, clock: Arc<dyn Clock>) -> Self {
+        Self { routes, usage: RwLock::new(HashMap::new()), clock }
+    }
+
+    /// Announce the deprecation of the request's route, if it is deprecated,
+    /// and count the call for the caller's organization
+    ///
+    /// `organization_id` is None for anonymous requests, which are not counted.
+    pub fn apply<T: Serialize>(
+        &self,
+        method: &str,
+        path: &str,
+        organization_id: Option<&str>,
+        mut response: HttpResponse<T>,
+    ) -> HttpResponse<T> {
+        let Some(index) = self.routes.iter().position(|r| r.matches(method, path)) else {
+            return response;
+        };
+        if let Some(organization_id) = organization_id {
+            self.record(index, organization_id);
+        }
+        response.headers.extend(self.routes[index].headers());
+        response
+    }
+
+    fn record(&self, index: usize, organization_id: &str) {
+        let route = &self.routes[index];
+        let now = self.clock.now();
+        let mut usage = self.usage.write().unwrap_or_else(|e| e.into_inner());
+        match usage.get_mut(&(index, organization_id.to_string())) {
+            Some(u) => {
+                u.requests += 1;
+                u.last_used_at = now;
+                tracing::debug!(organization_id, method = route.method, path = route.path, "Deprecated route called");
+            }
+            None => {
+                usage.insert((index, organization_id.to_string()), Usage { requests: 1, first_used_at: now, last_used_at: now });
+                tracing::warn!(organization_id, method = route.method, path = route.path, sunset = ?route.sunset, "Deprecated route called");
+            }
+        }
+    }
+
+    /// Callers of each deprecated route
+    pub fn report(&self) -> Vec<DeprecationReport> {
+        self.report_matching(|_| true)
+    }
+
+    /// Each deprecated route, with the organization's calls if it made any
+    pub fn report_for(&self, organization_id: &str) -> Vec<DeprecationReport> {
+        self.report_matching(|caller| caller == organization_id)
+    }
+
+    fn report_matching(&self, caller: impl Fn(&str) -> bool) -> Vec<DeprecationReport> {
+        let usage = self.usage.read().unwrap_or_else(|e| e.into_inner());
+        self.routes.iter().enumerate()
+            .map(|(index, route)| {
+                let mut organizations: Vec<DeprecatedRouteUse> = usage.iter()
+                    .filter(|((i, organization_id), _)| *i == index && caller(organization_id))
+                    .map(|((_, organization_id), u)| DeprecatedRouteUse {
+                        organization_id: organization_id.clone(),
+                        requests: u.requests,
+                        first_used_at: u.first_used_at,
+                        last_used_at: u.last_used_at,
+                    })
+                    .collect();
+                organizations.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at).then_with(|| a.organization_id.cmp(&b.organization_id)));
+                DeprecationReport {
+                    method: route.method.to_string(),
+                    path: route.path.to_string(),
+                    deprecated_at: route.deprecated_at,
+                    sunset: route.sunset,
+                    organizations,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn header<'a, T: Serialize>(response: &'a HttpResponse<T>, name: &str) -> Option<&'a str> {
+        response.headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn test_headers_and_report() {
+        let clock = Arc::new(ManualClock::new("2026-03-01T12:00:00Z".parse().unwrap()));
+        let deprecations = Deprecations::with_clock(vec![DeprecatedRoute {
+            method: "POST",
+            path: "/api/shares/{id}/renew",
+            deprecated_at: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+            sunset: NaiveDate::from_ymd_opt(2026, 7, 1),
+            link: Some("https://example.com/migrate"),
+        }], clock);
+
+        let response = deprecations.apply("POST", "/api/shares/s-1/renew?x=1", Some("org-1"), HttpResponse::ok(()));
+        assert_eq!(header(&response, "Deprecation"), Some("@1767225600"));
+        assert_eq!(header(&response, "Sunset"), Some("Wed, 01 Jul 2026 00:00:00 GMT"));
+        assert_eq!(header(&response, "Link"), Some("<https://example.com/migrate>; rel=\"deprecation\""));
+        deprecations.apply("post", "/api/shares/s-2/renew", Some("org-1"), HttpResponse::ok(()));
+        deprecations.apply("POST", "/api/shares/s-1/renew", None, HttpResponse::ok(()));
+
+        // Other routes are untouched and not counted
+        for (method, path) in [("GET", "/api/shares/s-1/renew"), ("POST", "/api/shares/renew"), ("POST", "/api/shares/s-1/renew/x")] {
+            assert!(deprecations.apply(method, path, Some("org-2"), HttpResponse::ok(())).headers.is_empty());
+        }
+
+        let report = deprecations.report();
+        assert_eq!(report.len(), 1);
+        let callers: Vec<_> = report[0].organizations.iter().map(|u| (u.organization_id.as_str(), u.requests)).collect();
+        assert_eq!(callers, [("org-1", 2)]);
+        assert_eq!(deprecations.report_for("org-1"), report);
+        assert!(deprecations.report_for("org-2")[0].organizations.is_empty());
+    }
+}
@@ -0,0 +1,343 @@
+//! # Directory
+//!
+//! Activities and shares only store user object IDs. A [`DirectoryService`]
+//! (Microsoft Graph in `arshjul-azure`, `graph` feature) resolves them to
+//! display names so responses can carry `createdByName` and the frontend
+//! needs no Graph permissions of its own.
+//!
+//! The same service backs the people picker (`GET /api/directory/search`),
+//! always scoped to the caller's own tenant.
+//!
+//! It also answers group membership checks for layers restricted to Azure AD
+//! groups, when the token carries no `groups` claim (see [`crate::layer_access`]).
+//!
+//! [`DirectoryCache`] wraps a service with a per-user TTL cache and looks up
+//! all misses of a response in one batch. Users the directory doesn't know
+//! (deleted accounts) are cached too, so they aren't looked up repeatedly.
+//! Membership results are cached per user and group for the same TTL.
+
+use crate::models::DirectoryEntry;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// How long resolved names are cached
+pub const DEFAULT_CACHE_TTL_MINUTES: i64 = 60;
+
+/// Results per kind when `top` is not given
+pub const DEFAULT_SEARCH_RESULTS: usize = 10;
+
+/// Upper bound for `top`
+pub const MAX_SEARCH_RESULTS: usize = 25;
+
+/// Shortest accepted search text
+pub const MIN_QUERY_LEN: usize = 2;
+
+/// Longest accepted search text
+pub const MAX_QUERY_LEN: usize = 100;
+
+/// Graph application permissions the directory features need, and what for
+pub const GRAPH_PERMISSIONS: &[(&str, &str)] = &[
+    ("User.Read.All", "Creator display names and the people picker"),
+    ("GroupMember.Read.All", "Group search and group-restricted layers"),
+];
+
+/// Admin consent page granting the app's permissions for a tenant
+pub fn admin_consent_url(tenant_id: &str, client_id: &str) -> String {
+    format!("https://login.microsoftonline.com/{}/adminconsent?client_id={}", tenant_id, client_id)
+}
+
+/// Reference documentation of a Graph permission
+pub fn permission_docs_url(permission: &str) -> String {
+    format!(
+        "https://learn.microsoft.com/graph/permissions-reference#{}",
+        permission.replace('.', "").to_lowercase()
+    )
+}
+
+/// Cache entries kept before expired ones are evicted
+const MAX_CACHE_ENTRIES: usize = 10_000;
+
+/// Directory errors
+#[derive(Debug, Error)]
+pub enum DirectoryError {
+    #[error("Directory unavailable: {0}")]
+    Unavailable(String),
+    
+    #[error("Invalid directory response: {0}")]
+    InvalidResponse(String),
+    
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    
+    #[error("Admin consent required: {0}")]
+    ConsentRequired(String),
+}
+
+/// Resolves user object IDs within an organization (tenant)
+#[async_trait]
+pub trait DirectoryService: Send + Sync {
+    /// Display names by user ID; unknown users are left out
+    async fn display_names(
+        &self,
+        organization_id: &str,
+        user_ids: &[String],
+    ) -> Result<HashMap<String, String>, DirectoryError>;
+    
+    /// Users and groups whose name or email starts with `query`
+    async fn search(
+        &self,
+        organization_id: &str,
+        query: &str,
+        top: usize,
+    ) -> Result<Vec<DirectoryEntry>, DirectoryError>;
+    
+    /// The subset of `group_ids` the user is a (transitive) member of
+    async fn member_groups(
+        &self,
+        organization_id: &str,
+        user_id: &str,
+        group_ids: &[String],
+    ) -> Result<HashSet<String>, DirectoryError>;
+    
+    /// Probe whether the tenant granted a permission from [`GRAPH_PERMISSIONS`]
+    async fn permission_granted(&self, organization_id: &str, permission: &str) -> Result<bool, DirectoryError>;
+}
+
+/// (organization, user, group)
+type MembershipKey = (String, String, String);
+
+struct CacheEntry {
+    display_name: Option<String>,
+    expires_at: DateTime<Utc>,
+}
+
+/// TTL cache in front of a directory service
+pub struct DirectoryCache {
+    inner: Arc<dyn DirectoryService>,
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), CacheEntry>>,
+    /// Membership and when it expires
+    memberships: Mutex<HashMap<MembershipKey, (bool, DateTime<Utc>)>>,
+}
+
+impl DirectoryCache {
+    pub fn new(inner: Arc<dyn DirectoryService>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            memberships: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Drop every cached entry of an organization (e.g. on offboarding)
+    pub fn evict_organization(&self, organization_id: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|(org, _), _| org != organization_id);
+        }
+        if let Ok(mut memberships) = self.memberships.lock() {
+            memberships.retain(|(org, _, _), _| org != organization_id);
+        }
+    }
+}
+
+#[async_trait]
+impl DirectoryService for DirectoryCache {
+    async fn display_names(
+        &self,
+        organization_id: &str,
+        user_ids: &[String],
+    ) -> Result<HashMap<String, String>, DirectoryError> {
+        let now = Utc::now();
+        let mut names = HashMap::new();
+        let mut misses = Vec::new();
+        
+        {
+            let entries = self.entries.lock()
+                .map_err(|_| DirectoryError::Unavailable("cache lock poisoned".to_string()))?;
+            let mut seen = HashSet::new();
+            for user_id in user_ids.iter().filter(|id| seen.insert(id.as_str())) {
+                match entries.get(&(organization_id.to_string(), user_id.clone())) {
+                    Some(entry) if entry.expires_at > now => {
+                        if let Some(ref name) = entry.display_name {
+                            names.insert(user_id.clone(), name.clone());
+                        }
+                    }
+                    _ => misses.push(user_id.clone()),
+                }
+            }
+        }
+        
+        if misses.is_empty() {
+            return Ok(names);
+        }
+        
+        let resolved = self.inner.display_names(organization_id, &misses).await?;
+        
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() + misses.len() > MAX_CACHE_ENTRIES {
+                entries.retain(|_, entry| entry.expires_at > now);
+            }
+            let expires_at = now + self.ttl;
+            for user_id in misses {
+                let display_name = resolved.get(&user_id).cloned();
+                entries.insert((organization_id.to_string(), user_id), CacheEntry { display_name, expires_at });
+            }
+        }
+        
+        names.extend(resolved);
+        Ok(names)
+    }
+    
+    async fn search(
+        &self,
+        organization_id: &str,
+        query: &str,
+        top: usize,
+    ) -> Result<Vec<DirectoryEntry>, DirectoryError> {
+        self.inner.search(organization_id, query, top).await
+    }
+    
+    async fn member_groups(
+        &self,
+        organization_id: &str,
+        user_id: &str,
+        group_ids: &[String],
+    ) -> Result<HashSet<String>, DirectoryError> {
+        let now = Utc::now();
+        let key = |group_id: &str| (organization_id.to_string(), user_id.to_string(), group_id.to_string());
+        let mut member_of = HashSet::new();
+        let mut misses = Vec::new();
+        
+        {
+            let memberships = self.memberships.lock()
+                .map_err(|_| DirectoryError::Unavailable("cache lock poisoned".to_string()))?;
+            for group_id in group_ids {
+                match memberships.get(&key(group_id)) {
+                    Some((is_member, expires_at)) if *expires_at > now => {
+                        if *is_member {
+                            member_of.insert(group_id.clone());
+                        }
+                    }
+                    _ => misses.push(group_id.clone()),
+                }
+            }
+        }
+        
+        if misses.is_empty() {
+            return Ok(member_of);
+        }
+        
+        let resolved = self.inner.member_groups(organization_id, user_id, &misses).await?;
+        
+        if let Ok(mut memberships) = self.memberships.lock() {
+            if memberships.len() + misses.len() > MAX_CACHE_ENTRIES {
+                memberships.retain(|_, (_, expires_at)| *expires_at > now);
+            }
+            for group_id in &misses {
+                memberships.insert(key(group_id), (resolved.contains(group_id), now + self.ttl));
+            }
+        }
+        
+        member_of.extend(resolved);
+        Ok(member_of)
+    }
+    
+    async fn permission_granted(&self, organization_id: &str, permission: &str) -> Result<bool, DirectoryError> {
+        self.inner.permission_granted(organization_id, permission).await
+    }
+}
+
+/// Validate a people picker query, returning the trimmed text and result count
+pub fn validate_search(q: &str, top: Option<usize>) -> Result<(&str, usize), String> {
+    let q = q.trim();
+    let len = q.chars().count();
+    if !(MIN_QUERY_LEN..=MAX_QUERY_LEN).contains(&len) {
+        return Err(format!("Search text must be {} to {} characters", MIN_QUERY_LEN, MAX_QUERY_LEN));
+    }
+    
+    match top {
+        None => Ok((q, DEFAULT_SEARCH_RESULTS)),
+        Some(top) if (1..=MAX_SEARCH_RESULTS).contains(&top) => Ok((q, top)),
+        Some(_) => Err(format!("top must be between 1 and {}", MAX_SEARCH_RESULTS)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// Knows one user and counts lookups
+    #[derive(Default)]
+    struct CountingDirectory {
+        lookups: AtomicUsize,
+    }
+    
+    #[async_trait]
+    impl DirectoryService for CountingDirectory {
+        async fn display_names(
+            &self,
+            _organization_id: &str,
+            user_ids: &[String],
+        ) -> Result<HashMap<String, String>, DirectoryError> {
+            self.lookups.fetch_add(user_ids.len(), Ordering::SeqCst);
+            Ok(user_ids.iter()
+                .filter(|id| id.as_str() == "user-1")
+                .map(|id| (id.clone(), "Kari Nordmann".to_string()))
+                .collect())
+        }
+        
+        async fn search(
+            &self,
+            _organization_id: &str,
+            _query: &str,
+            _top: usize,
+        ) -> Result<Vec<DirectoryEntry>, DirectoryError> {
+            Ok(Vec::new())
+        }
+        
+        async fn member_groups(
+            &self,
+            _organization_id: &str,
+            _user_id: &str,
+            _group_ids: &[String],
+        ) -> Result<HashSet<String>, DirectoryError> {
+            Ok(HashSet::new())
+        }
+        
+        async fn permission_granted(&self, _organization_id: &str, _permission: &str) -> Result<bool, DirectoryError> {
+            Ok(true)
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_cache_batches_and_remembers_unknown_users() {
+        let directory = Arc::new(CountingDirectory::default());
+        let cache = DirectoryCache::new(directory.clone(), Duration::minutes(DEFAULT_CACHE_TTL_MINUTES));
+        let ids = vec!["user-1".to_string(), "deleted".to_string(), "user-1".to_string()];
+        
+        let names = cache.display_names("org-1", &ids).await.unwrap();
+        assert_eq!(names.get("user-1").map(String::as_str), Some("Kari Nordmann"));
+        assert!(!names.contains_key("deleted"));
+        assert_eq!(directory.lookups.load(Ordering::SeqCst), 2);
+        
+        cache.display_names("org-1", &ids).await.unwrap();
+        assert_eq!(directory.lookups.load(Ordering::SeqCst), 2);
+        
+        cache.display_names("org-2", &ids[..1]).await.unwrap();
+        assert_eq!(directory.lookups.load(Ordering::SeqCst), 3);
+    }
+    
+    #[test]
+    fn test_remediation_links() {
+        assert_eq!(
+            admin_consent_url("tenant-1", "app-1"),
+            "https://login.microsoftonline.com/tenant-1/adminconsent?client_id=app-1"
+        );
+        assert!(permission_docs_url("GroupMember.Read.All").ends_with("#groupmemberreadall"));
+    }
+}
@@ -0,0 +1,89 @@
+//! # Dry Runs
+//!
+//! Mutating admin operations accept `?dry_run=true` (e.g.
+//! `DELETE /api/admin/organization?dry_run=true`). They first collect every
+//! write they would make into an [`ExecutionPlan`], without writing:
+//!
+//! - **Dry run** - the plan's [`DryRunReport`], with counts and IDs per step
+//! - **Real run** - the handler carries the plan out step by step
+//!
+//! Both paths share the plan, so the report is exactly what a real run would
+//! change at that moment. Dry runs skip confirmation tokens; they still
+//! require the same role as the operation.
+
+use crate::models::{DryRunReport, PlannedAction, PlannedChange, PlannedEntity};
+
+/// Writes an operation would make, in order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionPlan {
+    changes: Vec<PlannedChange>,
+}
+
+impl ExecutionPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a step; steps without IDs are left out
+    pub fn add<I, S>(&mut self, entity: PlannedEntity, action: PlannedAction, ids: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let ids: Vec<String> = ids.into_iter().map(Into::into).collect();
+        if !ids.is_empty() {
+            self.changes.push(PlannedChange { entity, action, count: ids.len() as u64, ids });
+        }
+    }
+    
+    pub fn steps(&self) -> &[PlannedChange] {
+        &self.changes
+    }
+    
+    /// IDs planned for a write, over all steps
+    pub fn ids(&self, entity: PlannedEntity, action: PlannedAction) -> impl Iterator<Item = &str> {
+        self.changes.iter()
+            .filter(move |c| c.entity == entity && c.action == action)
+            .flat_map(|c| c.ids.iter().map(String::as_str))
+    }
+    
+    /// Number of writes of one kind
+    pub fn count(&self, entity: PlannedEntity, action: PlannedAction) -> u64 {
+        self.ids(entity, action).count() as u64
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+    
+    pub fn report(&self) -> DryRunReport {
+        DryRunReport {
+            dry_run: true,
+            total: self.changes.iter().map(|c| c.count).sum(),
+            changes: self.changes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_plan_report() {
+        let mut plan = ExecutionPlan::new();
+        plan.add(PlannedEntity::Share, PlannedAction::Revoke, ["share-1"]);
+        plan.add(PlannedEntity::Share, PlannedAction::Delete, ["share-1", "share-2"]);
+        plan.add(PlannedEntity::Layer, PlannedAction::Delete, Vec::<String>::new());
+        
+        assert_eq!(plan.steps().len(), 2);
+        assert_eq!(plan.count(PlannedEntity::Share, PlannedAction::Delete), 2);
+        assert_eq!(plan.count(PlannedEntity::Layer, PlannedAction::Delete), 0);
+        
+        let report = plan.report();
+        assert_eq!(report.total, 3);
+        assert_eq!(serde_json::to_value(&report.changes[0]).unwrap(), serde_json::json!({
+            "entity": "share", "action": "revoke", "count": 1, "ids": ["share-1"],
+        }));
+    }
+}
@@ -0,0 +1,475 @@
+//! # Dual-Write Migration
+//!
+//! Moving an entity from one backend to another (Table Storage to Cosmos DB)
+//! without downtime takes a period where both are kept current.
+//! [`DualWriteStorage`] wraps a primary and a secondary backend:
+//!
+//! - **Reads** go to the primary
+//! - **Writes** go to the primary first; what it stored is then written to
+//!   the secondary. The caller gets the primary's result, and a failed
+//!   secondary write doesn't fail the request
+//! - **Mirroring** heals gaps: an update or view count the secondary misses
+//!   because it lacks the entity creates it there, and a create it already
+//!   has overwrites it
+//! - **Divergence** is logged at `warn` with `entity`, `operation`, `id` and
+//!   both backend names: failed secondary writes, and point reads (`get`)
+//!   whose secondary copy is missing or differs from the primary's. Only one
+//!   in [`DEFAULT_COMPARE_EVERY`] point reads is compared (see
+//!   [`DualWriteStorage::with_compare_every`]), so the migration doesn't
+//!   double the cost of every read
+//!
+//! Entities written before dual-writing started reach the secondary only
+//! when next written; copy the rest over (e.g. export and import) before
+//! switching the primary.
+//!
+//! Wire it per entity type (`DUAL_WRITE`, see the server's storage wiring):
+//! start with the old backend as primary, switch the primary once the
+//! secondary has caught up and divergence stopped, then drop dual-writing.
+
+use crate::models::*;
+use crate::storage::*;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Point reads per comparison with the secondary
+pub const DEFAULT_COMPARE_EVERY: u64 = 100;
+
+/// A named backend of a dual-write pair
+pub struct Backend<S: ?Sized> {
+    pub name: &'static str,
+    pub storage: Arc<S>,
+}
+
+impl<S: ?Sized> Backend<S> {
+    pub fn new(name: &'static str, storage: Arc<S>) -> Self {
+        Self { name, storage }
+    }
+}
+
+/// Storage writing to two backends and reading from the primary
+pub struct DualWriteStorage<S: ?Sized> {
+    entity: &'static str,
+    primary: Backend<S>,
+    secondary: Backend<S>,
+    compare_every: u64,
+    reads: AtomicU64,
+}
+
+impl<S: ?Sized> DualWriteStorage<S> {
+    pub fn new(entity: &'static str, primary: Backend<S>, secondary: Backend<S>) -> Self {
+        Self {
+            entity,
+            primary,
+            secondary,
+            compare_every: DEFAULT_COMPARE_EVERY,
+            reads: AtomicU64::new(0),
+        }
+    }
+
+    /// Compare one in `every` point reads with the secondary (1 compares all)
+    pub fn with_compare_every(mut self, every: u64) -> Self {
+        self.compare_every = every.max(1);
+        self
+    }
+
+    /// Whether this point read is compared with the secondary
+    fn sampled(&self) -> bool {
+        self.reads.fetch_add(1, Ordering::Relaxed).is_multiple_of(self.compare_every)
+    }
+
+    fn diverged(&self, operation: &'static str, id: &str, reason: &str) {
+        tracing::warn!(
+            entity = self.entity,
+            operation,
+            id,
+            primary = self.primary.name,
+            secondary = self.secondary.name,
+            "Dual-write divergence: {}",
+            reason,
+        );
+    }
+
+    /// Log a failed secondary write
+    fn mirrored<T>(&self, operation: &'static str, id: &str, result: Result<T, StorageError>) {
+        if let Err(e) = result {
+            self.diverged(operation, id, &format!("secondary write failed: {}", e));
+        }
+    }
+
+    /// Compare a point read of the primary with the secondary's copy
+    fn compare<T: Serialize>(&self, operation: &'static str, id: &str, primary: &T, secondary: Result<T, StorageError>) {
+        match secondary {
+            Ok(copy) if serde_json::to_value(&copy).ok() == serde_json::to_value(primary).ok() => {}
+            Ok(_) => self.diverged(operation, id, "secondary copy differs"),
+            Err(StorageError::NotFound(_)) => self.diverged(operation, id, "missing from secondary"),
+            Err(e) => self.diverged(operation, id, &format!("secondary read failed: {}", e)),
+        }
+    }
+}
+
+/// Deleting what the secondary never had is no divergence
+fn deleted(result: Result<(), StorageError>) -> Result<(), StorageError> {
+    match result {
+        Err(StorageError::NotFound(_)) => Ok(()),
+        result => result,
+    }
+}
+
+#[async_trait]
+impl<S: ShareStorage + ?Sized> ShareStorage for DualWriteStorage<S> {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let created = self.primary.storage.create(share).await?;
+        let result = match self.secondary.storage.create(created.clone()).await {
+            Err(StorageError::AlreadyExists(_)) => self.secondary.storage.update(created.clone()).await,
+            result => result,
+        };
+        self.mirrored("create", &created.id, result);
+        Ok(created)
+    }
+
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        let share = self.primary.storage.get(organization_id, share_id).await?;
+        if self.sampled() {
+            self.compare("get", share_id, &share, self.secondary.storage.get(organization_id, share_id).await);
+        }
+        Ok(share)
+    }
+
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        self.primary.storage.get_by_short_code(short_code).await
+    }
+
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let updated = self.primary.storage.update(share).await?;
+        let result = match self.secondary.storage.update(updated.clone()).await {
+            Err(StorageError::NotFound(_)) => self.secondary.storage.create(updated.clone()).await,
+            result => result,
+        };
+        self.mirrored("update", &updated.id, result);
+        Ok(updated)
+    }
+
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        self.primary.storage.get_tagged(organization_id, share_id).await
+    }
+
+    /// The ETag is the primary's; the secondary gets what the primary stored
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        let Some(replaced) = self.primary.storage.replace(share, etag).await? else {
+            return Ok(None);
+        };
+        let result = match self.secondary.storage.update(replaced.clone()).await {
+            Err(StorageError::NotFound(_)) => self.secondary.storage.create(replaced.clone()).await,
+            result => result,
+        };
+        self.mirrored("replace", &replaced.id, result);
+        Ok(Some(replaced))
+    }
+
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.primary.storage.delete(organization_id, share_id).await?;
+        self.mirrored("delete", share_id, deleted(self.secondary.storage.delete(organization_id, share_id).await));
+        Ok(())
+    }
+
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        self.primary.storage.get_tombstone(short_code).await
+    }
+
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        self.primary.storage.list(organization_id, options).await
+    }
+
+    async fn list_by_layer(&self, organization_id: &str, layer_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        self.primary.storage.list_by_layer(organization_id, layer_id).await
+    }
+
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.primary.storage.increment_views(organization_id, share_id).await?;
+        let result = match self.secondary.storage.increment_views(organization_id, share_id).await {
+            Err(StorageError::NotFound(_)) => match self.primary.storage.get(organization_id, share_id).await {
+                Ok(share) => self.secondary.storage.create(share).await.map(|_| ()),
+                Err(e) => Err(e),
+            },
+            result => result,
+        };
+        self.mirrored("increment_views", share_id, result);
+        Ok(())
+    }
+
+    async fn get_deleted(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.primary.storage.get_deleted(organization_id, share_id).await
+    }
+
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        self.primary.storage.list_deleted(organization_id).await
+    }
+}
+
+impl<S: ActivityStorage + ?Sized> DualWriteStorage<S> {
+    /// Write an activity the primary stored to the secondary
+    async fn mirror_activity(&self, operation: &'static str, activity: &Activity, created: bool) {
+        let secondary = &self.secondary.storage;
+        let result = if created {
+            match secondary.create(activity.clone()).await {
+                Err(StorageError::AlreadyExists(_)) => secondary.update(activity.clone()).await,
+                result => result,
+            }
+        } else {
+            match secondary.update(activity.clone()).await {
+                Err(StorageError::NotFound(_)) => secondary.create(activity.clone()).await,
+                result => result,
+            }
+        };
+        self.mirrored(operation, &activity.id, result);
+    }
+}
+
+#[async_trait]
+impl<S: ActivityStorage + ?Sized> ActivityStorage for DualWriteStorage<S> {
+    async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+        let created = self.primary.storage.create(activity).await?;
+        self.mirror_activity("create", &created, true).await;
+        Ok(created)
+    }
+
+    async fn create_many(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let created = self.primary.storage.create_many(activities).await?;
+        if let Err(e) = self.secondary.storage.create_many(created.clone()).await {
+            // Batches may be partly written; mirror the rest one by one
+            tracing::debug!(entity = self.entity, error = %e, "Secondary batch create failed, mirroring one by one");
+            for activity in &created {
+                self.mirror_activity("create_many", activity, true).await;
+            }
+        }
+        Ok(created)
+    }
+
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        let activity = self.primary.storage.get(organization_id, activity_id).await?;
+        if self.sampled() {
+            self.compare("get", activity_id, &activity, self.secondary.storage.get(organization_id, activity_id).await);
+        }
+        Ok(activity)
+    }
+
+    async fn get_tagged(&self, organization_id: &str, activity_id: &str) -> Result<(Activity, String), StorageError> {
+        self.primary.storage.get_tagged(organization_id, activity_id).await
+    }
+
+    async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+        let updated = self.primary.storage.update(activity).await?;
+        self.mirror_activity("update", &updated, false).await;
+        Ok(updated)
+    }
+
+    async fn update_batch(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let updated = self.primary.storage.update_batch(activities).await?;
+        if self.secondary.storage.update_batch(updated.clone()).await.is_err() {
+            for activity in &updated {
+                self.mirror_activity("update_batch", activity, false).await;
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+        self.primary.storage.delete(organization_id, activity_id).await?;
+        self.mirrored("delete", activity_id, deleted(self.secondary.storage.delete(organization_id, activity_id).await));
+        Ok(())
+    }
+
+    /// The ETags are the primary's; the secondary gets the changes unconditionally
+    async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+        self.primary.storage.apply_changes(organization_id, changes.clone()).await?;
+        let mirrored = ActivityChanges { etags: Default::default(), ..changes.clone() };
+        if self.secondary.storage.apply_changes(organization_id, mirrored).await.is_err() {
+            for activity in &changes.create {
+                self.mirror_activity("apply_changes", activity, true).await;
+            }
+            for activity in &changes.update {
+                self.mirror_activity("apply_changes", activity, false).await;
+            }
+            for activity_id in &changes.delete {
+                self.mirrored("apply_changes", activity_id, deleted(self.secondary.storage.delete(organization_id, activity_id).await));
+            }
+        }
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<Activity>, StorageError> {
+        self.primary.storage.list(organization_id, options).await
+    }
+
+    async fn list_by_layers(
+        &self,
+        organization_id: &str,
+        layer_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<Activity>, StorageError> {
+        self.primary.storage.list_by_layers(organization_id, layer_ids, year).await
+    }
+
+    async fn get_deleted(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        self.primary.storage.get_deleted(organization_id, activity_id).await
+    }
+
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<Activity>, StorageError> {
+        self.primary.storage.list_deleted(organization_id).await
+    }
+}
+
+#[async_trait]
+impl<S: LayerStorage + ?Sized> LayerStorage for DualWriteStorage<S> {
+    async fn create(&self, layer: Layer) -> Result<Layer, StorageError> {
+        let created = self.primary.storage.create(layer).await?;
+        let result = match self.secondary.storage.create(created.clone()).await {
+            Err(StorageError::AlreadyExists(_)) => self.secondary.storage.update(created.clone()).await,
+            result => result,
+        };
+        self.mirrored("create", &created.id, result);
+        Ok(created)
+    }
+
+    async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError> {
+        let layer = self.primary.storage.get(organization_id, layer_id).await?;
+        if self.sampled() {
+            self.compare("get", layer_id, &layer, self.secondary.storage.get(organization_id, layer_id).await);
+        }
+        Ok(layer)
+    }
+
+    async fn update(&self, layer: Layer) -> Result<Layer, StorageError> {
+        let updated = self.primary.storage.update(layer).await?;
+        let result = match self.secondary.storage.update(updated.clone()).await {
+            Err(StorageError::NotFound(_)) => self.secondary.storage.create(updated.clone()).await,
+            result => result,
+        };
+        self.mirrored("update", &updated.id, result);
+        Ok(updated)
+    }
+
+    async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError> {
+        self.primary.storage.delete(organization_id, layer_id).await?;
+        self.mirrored("delete", layer_id, deleted(self.secondary.storage.delete(organization_id, layer_id).await));
+        Ok(())
+    }
+
+    async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError> {
+        self.primary.storage.list(organization_id).await
+    }
+}
+
+#[async_trait]
+impl<S: ActivityTypeStorage + ?Sized> ActivityTypeStorage for DualWriteStorage<S> {
+    async fn upsert(&self, config: ActivityTypeConfig) -> Result<ActivityTypeConfig, StorageError> {
+        let stored = self.primary.storage.upsert(config).await?;
+        self.mirrored("upsert", &stored.key, self.secondary.storage.upsert(stored.clone()).await);
+        Ok(stored)
+    }
+
+    async fn get(&self, organization_id: &str, key: &str) -> Result<ActivityTypeConfig, StorageError> {
+        let config = self.primary.storage.get(organization_id, key).await?;
+        if self.sampled() {
+            self.compare("get", key, &config, self.secondary.storage.get(organization_id, key).await);
+        }
+        Ok(config)
+    }
+
+    async fn delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.primary.storage.delete(organization_id, key).await?;
+        self.mirrored("delete", key, deleted(self.secondary.storage.delete(organization_id, key).await));
+        Ok(())
+    }
+
+    async fn force_delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.primary.storage.force_delete(organization_id, key).await?;
+        self.mirrored("force_delete", key, deleted(self.secondary.storage.force_delete(organization_id, key).await));
+        Ok(())
+    }
+
+    async fn list(&self, organization_id: &str) -> Result<Vec<ActivityTypeConfig>, StorageError> {
+        self.primary.storage.list(organization_id).await
+    }
+}
+
+#[async_trait]
+impl<S: UserSettingsStorage + ?Sized> UserSettingsStorage for DualWriteStorage<S> {
+    async fn get(&self, organization_id: &str, user_id: &str) -> Result<UserSettings, StorageError> {
+        // Both return defaults for users who never saved settings, so no comparison
+        self.primary.storage.get(organization_id, user_id).await
+    }
+
+    async fn upsert(&self, settings: UserSettings) -> Result<UserSettings, StorageError> {
+        let stored = self.primary.storage.upsert(settings).await?;
+        self.mirrored("upsert", &stored.user_id, self.secondary.storage.upsert(stored.clone()).await);
+        Ok(stored)
+    }
+
+    async fn delete(&self, organization_id: &str, user_id: &str) -> Result<(), StorageError> {
+        self.primary.storage.delete(organization_id, user_id).await?;
+        self.mirrored("delete", user_id, deleted(self.secondary.storage.delete(organization_id, user_id).await));
+        Ok(())
+    }
+
+    async fn list(&self, organization_id: &str) -> Result<Vec<UserSettings>, StorageError> {
+        self.primary.storage.list(organization_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory_storage::MemoryShareStorage;
+
+    fn share(id: &str) -> ShareLink {
+        crate::storage_tests::share("org-1", id, &format!("Code{id}"), "public")
+    }
+
+    #[tokio::test]
+    async fn test_writes_reach_both_and_heal_gaps() {
+        let table = Arc::new(MemoryShareStorage::new());
+        let cosmos = Arc::new(MemoryShareStorage::new());
+        let dual = DualWriteStorage::new("share", Backend::new("table", table.clone()), Backend::new("cosmosdb", cosmos.clone()));
+
+        dual.create(share("s-1")).await.unwrap();
+        assert_eq!(cosmos.get("org-1", "s-1").await.unwrap().short_code, "Codes-1");
+
+        // Written before dual-writing: the next update copies it over
+        table.create(share("s-2")).await.unwrap();
+        let mut renamed = share("s-2");
+        renamed.name = Some("Board".to_string());
+        dual.update(renamed).await.unwrap();
+        assert_eq!(cosmos.get("org-1", "s-2").await.unwrap().name.as_deref(), Some("Board"));
+
+        dual.increment_views("org-1", "s-2").await.unwrap();
+        assert_eq!(cosmos.get("org-1", "s-2").await.unwrap().stats.view_count, table.get("org-1", "s-2").await.unwrap().stats.view_count);
+
+        // The secondary missing a share fails neither reads nor deletes
+        cosmos.delete("org-1", "s-1").await.unwrap();
+        assert_eq!(dual.get("org-1", "s-1").await.unwrap().id, "s-1");
+        dual.delete("org-1", "s-1").await.unwrap();
+        assert!(matches!(table.get("org-1", "s-1").await, Err(StorageError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_compare_sampling() {
+        let backend = || Backend::new("memory", Arc::new(MemoryShareStorage::new()));
+        let dual = DualWriteStorage::new("share", backend(), backend()).with_compare_every(3);
+        let sampled: Vec<bool> = (0..6).map(|_| dual.sampled()).collect();
+        assert_eq!(sampled, [true, false, false, true, false, false]);
+
+        let every = DualWriteStorage::new("share", backend(), backend()).with_compare_every(0);
+        assert!(every.sampled() && every.sampled());
+    }
+}
@@ -0,0 +1,249 @@
+//! # Domain Events
+//!
+//! In-process event bus for entity changes and edit lock notifications.
+//!
+//! Handlers publish an event after a write has been persisted; subscribers
+//! (live updates, cache invalidation, search indexing, ...) react to it.
+//! Subscriber failures are logged and never fail the originating request.
+
+use crate::models::{EditLock, ShareReport, ShareTrafficAlert};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Event delivery errors
+#[derive(Debug, Error)]
+pub enum EventError {
+    #[error("Delivery failed: {0}")]
+    Delivery(String),
+    
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
+
+/// Kind of entity that changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityKind {
+    Share,
+    Activity,
+    Layer,
+    ActivityType,
+}
+
+/// What happened to the entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A persisted change to a single entity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityChange {
+    pub organization_id: String,
+    pub entity: EntityKind,
+    pub entity_id: String,
+    pub change: ChangeKind,
+    
+    /// Secondary key the entity is addressed by (a share's short code)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    
+    /// Layers a changed activity is on, and was on if it moved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layer_ids: Vec<String>,
+    
+    /// User who made the change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<String>,
+    
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl EntityChange {
+    pub fn new(
+        organization_id: &str,
+        entity: EntityKind,
+        entity_id: &str,
+        change: ChangeKind,
+        changed_by: Option<&str>,
+    ) -> Self {
+        Self {
+            organization_id: organization_id.to_string(),
+            entity,
+            entity_id: entity_id.to_string(),
+            change,
+            key: None,
+            layer_ids: Vec::new(),
+            changed_by: changed_by.map(str::to_string),
+            occurred_at: Utc::now(),
+        }
+    }
+    
+    /// Attach the secondary key
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+    
+    /// Attach the layers of a changed activity
+    pub fn with_layers(mut self, layer_ids: &[&str]) -> Self {
+        for id in layer_ids {
+            if !self.layer_ids.iter().any(|known| known == id) {
+                self.layer_ids.push(id.to_string());
+            }
+        }
+        self
+    }
+}
+
+/// Events published on the bus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DomainEvent {
+    /// An entity was created, updated or deleted
+    EntityChanged(EntityChange),
+    
+    /// An edit lock was acquired or renewed
+    ActivityLocked(EditLock),
+    
+    /// An edit lock was released
+    ActivityUnlocked(EditLock),
+    
+    /// A public share was reported as abusive or misconfigured
+    ShareReported(ShareReport),
+    
+    /// The traffic monitor flagged a share for review
+    ShareTrafficAnomaly(ShareTrafficAlert),
+}
+
+impl DomainEvent {
+    /// Organization the event belongs to
+    pub fn organization_id(&self) -> &str {
+        match self {
+            DomainEvent::EntityChanged(change) => &change.organization_id,
+            DomainEvent::ActivityLocked(lock) | DomainEvent::ActivityUnlocked(lock) => &lock.organization_id,
+            DomainEvent::ShareReported(report) => &report.organization_id,
+            DomainEvent::ShareTrafficAnomaly(alert) => &alert.organization_id,
+        }
+    }
+}
+
+/// Connection info for a live update client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegotiateResponse {
+    pub url: String,
+    pub access_token: String,
+}
+
+/// Real-time push service that clients connect to (e.g., Azure SignalR)
+#[async_trait]
+pub trait LiveUpdateService: Send + Sync {
+    /// Connection info for a user, subscribed to their organization's broadcasts
+    async fn connect(&self, organization_id: &str, user_id: &str) -> Result<NegotiateResponse, EventError>;
+}
+
+/// Receives events from the bus
+#[async_trait]
+pub trait EventSubscriber: Send + Sync {
+    /// Subscriber name for logging
+    fn name(&self) -> &'static str;
+    
+    /// Handle a single event
+    async fn handle(&self, event: &DomainEvent) -> Result<(), EventError>;
+}
+
+/// Fan-out event bus
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Arc<dyn EventSubscriber>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register a subscriber
+    pub fn subscribe(&mut self, subscriber: Arc<dyn EventSubscriber>) {
+        tracing::info!("Event subscriber registered: {}", subscriber.name());
+        self.subscribers.push(subscriber);
+    }
+    
+    /// Deliver an event to every subscriber
+    pub async fn publish(&self, event: DomainEvent) {
+        for subscriber in &self.subscribers {
+            if let Err(e) = subscriber.handle(&event).await {
+                tracing::warn!("Event subscriber {} failed: {}", subscriber.name(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Mutex;
+    
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<String>>,
+    }
+    
+    #[async_trait]
+    impl EventSubscriber for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+        
+        async fn handle(&self, event: &DomainEvent) -> Result<(), EventError> {
+            self.seen.lock().await.push(event.organization_id().to_string());
+            Ok(())
+        }
+    }
+    
+    struct Failing;
+    
+    #[async_trait]
+    impl EventSubscriber for Failing {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+        
+        async fn handle(&self, _event: &DomainEvent) -> Result<(), EventError> {
+            Err(EventError::Delivery("boom".to_string()))
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_publish_reaches_all_subscribers() {
+        let recorder = Arc::new(Recorder::default());
+        let mut bus = EventBus::new();
+        bus.subscribe(Arc::new(Failing));
+        bus.subscribe(recorder.clone());
+        
+        bus.publish(DomainEvent::EntityChanged(EntityChange::new(
+            "org-1", EntityKind::Share, "share-1", ChangeKind::Created, Some("user-1"),
+        ))).await;
+        
+        assert_eq!(*recorder.seen.lock().await, vec!["org-1".to_string()]);
+    }
+    
+    #[test]
+    fn test_event_serialization() {
+        let event = DomainEvent::EntityChanged(EntityChange::new(
+            "org-1", EntityKind::ActivityType, "meeting", ChangeKind::Deleted, None,
+        ));
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "entityChanged");
+        assert_eq!(json["entity"], "activityType");
+        assert_eq!(json["change"], "deleted");
+    }
+}
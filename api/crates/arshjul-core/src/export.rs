@@ -0,0 +1,809 @@
+//! # Exports
+//!
+//! `GET /api/shares/{id}/export` and `GET /api/activities/export` pick the
+//! format from the `Accept` header:
+//!
+//! | Media type | Exporter | Content |
+//! |------------|----------|---------|
+//! | `application/json` | [`JsonExporter`] | the [`ExportDocument`] (default without `Accept`) |
+//! | `text/csv` | [`CsvExporter`] | one row per activity |
+//! | `application/x-ndjson` | [`NdjsonExporter`] | one activity per line |
+//! | `text/calendar` | [`IcsExporter`] | one event per activity (see [`crate::ics`]) |
+//! | `image/svg+xml` | [`SvgExporter`] | the wheel: one ring per layer, months clockwise from the top |
+//! | `application/pdf` | [`PdfExporter`] | activity list on A4 pages |
+//!
+//! Formats plug in through [`ExporterRegistry::register`]. Negotiation
+//! follows `q` values and `type/*` and `*/*` ranges; the first registered
+//! exporter wins ties. Requests no exporter can satisfy get 406.
+//!
+//! [`render`] does what all formats share before an exporter writes the document:
+//!
+//! - **Redaction** - `redact=descriptions,links,people` blanks those fields
+//! - **Pagination** - row formats ([`Exporter::paginated`]) return `pageSize`
+//!   activities in `id` order after `continuationToken`; the token for the
+//!   next page comes back in `X-Continuation-Token`
+//! - **Localization** - `locale=nb` switches month names, dates and the
+//!   default title to Norwegian ([`ExportLocale`]); without it, the
+//!   user's locale applies, then the organization's (see [`crate::i18n`])
+//! - **Weeks** - week numbers follow the organization's calendar settings
+//!   (see [`crate::calendar`]); the document carries them as
+//!   [`CalendarMetadata`](crate::models::CalendarMetadata)
+
+use crate::calendar;
+use crate::ics;
+use crate::recurrence;
+use crate::models::{Activity, CalendarSettings, ExportDocument, ExportQuery, Layer};
+use crate::storage;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::Serialize;
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::io;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Rows per page of row formats unless the client asks for fewer or more
+pub const DEFAULT_PAGE_SIZE: u32 = 1000;
+
+/// Upper bound for `pageSize`
+pub const MAX_PAGE_SIZE: u32 = 5000;
+
+/// Response header carrying the next page's continuation token
+pub const CONTINUATION_HEADER: &str = "X-Continuation-Token";
+
+/// Export errors
+#[derive(Debug, Error, PartialEq)]
+pub enum ExportError {
+    #[error("Not acceptable; available formats: {0}")]
+    NotAcceptable(String),
+    
+    #[error("Unknown locale: {0}")]
+    UnknownLocale(String),
+    
+    #[error("Unknown field to redact: {0}")]
+    UnknownRedaction(String),
+    
+    #[error("Export failed: {0}")]
+    Render(String),
+}
+
+/// Language of month names, dates and default titles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportLocale {
+    #[default]
+    English,
+    Norwegian,
+}
+
+impl ExportLocale {
+    /// `en`, or `nb`/`no`/`nn` (region suffixes such as `nb-NO` are ignored)
+    pub fn parse(value: &str) -> Result<Self, ExportError> {
+        let language = value.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Ok(Self::English),
+            "nb" | "no" | "nn" => Ok(Self::Norwegian),
+            _ => Err(ExportError::UnknownLocale(value.to_string())),
+        }
+    }
+    
+    /// Language tag, e.g. `nb`
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::Norwegian => "nb",
+        }
+    }
+    
+    pub fn month_names(&self) -> [&'static str; 12] {
+        match self {
+            Self::English => ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"],
+            Self::Norwegian => ["jan", "feb", "mar", "apr", "mai", "jun", "jul", "aug", "sep", "okt", "nov", "des"],
+        }
+    }
+    
+    /// Monday first
+    pub fn weekday_names(&self) -> [&'static str; 7] {
+        match self {
+            Self::English => ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+            Self::Norwegian => ["man", "tir", "ons", "tor", "fre", "lør", "søn"],
+        }
+    }
+    
+    /// e.g. `Week 9` or `Uke 9`
+    pub fn week_label(&self, week: u32) -> String {
+        match self {
+            Self::English => format!("Week {}", week),
+            Self::Norwegian => format!("Uke {}", week),
+        }
+    }
+    
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        match self {
+            Self::English => date.format("%Y-%m-%d").to_string(),
+            Self::Norwegian => date.format("%d.%m.%Y").to_string(),
+        }
+    }
+    
+    /// Date for running text, e.g. `Mon Mar 3, 2025` or `man 3. mar 2025`
+    pub fn format_long_date(&self, date: NaiveDate) -> String {
+        let weekday = self.weekday_names()[date.weekday().num_days_from_monday() as usize];
+        let month = self.month_names()[date.month0() as usize];
+        match self {
+            Self::English => format!("{} {} {}, {}", weekday, month, date.day(), date.year()),
+            Self::Norwegian => format!("{} {}. {} {}", weekday, date.day(), month, date.year()),
+        }
+    }
+    
+    /// Title of exports without one of their own
+    pub fn default_title(&self) -> &'static str {
+        match self {
+            Self::English => "Annual Wheel",
+            Self::Norwegian => "Årshjul",
+        }
+    }
+}
+
+/// Activity fields left out of an export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Redaction {
+    pub descriptions: bool,
+    pub links: bool,
+    /// `createdBy` of activities and layers, its display name and the approval
+    /// review, which name people
+    pub people: bool,
+}
+
+impl Redaction {
+    /// Comma-separated field names, e.g. `descriptions,people`
+    pub fn parse(value: &str) -> Result<Self, ExportError> {
+        let mut redaction = Self::default();
+        for field in value.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            match field {
+                "descriptions" => redaction.descriptions = true,
+                "links" => redaction.links = true,
+                "people" => redaction.people = true,
+                other => return Err(ExportError::UnknownRedaction(other.to_string())),
+            }
+        }
+        Ok(redaction)
+    }
+    
+    /// Fields redacted by either
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            descriptions: self.descriptions || other.descriptions,
+            links: self.links || other.links,
+            people: self.people || other.people,
+        }
+    }
+    
+    pub fn apply(&self, activity: &mut Activity) {
+        if self.descriptions {
+            activity.description = None;
+        }
+        if self.links {
+            activity.links.clear();
+        }
+        if self.people {
+            activity.created_by = None;
+            activity.created_by_name = None;
+            activity.approval_review = None;
+        }
+    }
+    
+    /// Layers carry a description and their creator too
+    pub fn apply_to_layer(&self, layer: &mut Layer) {
+        if self.descriptions {
+            layer.description = None;
+        }
+        if self.people {
+            layer.created_by.clear();
+        }
+    }
+}
+
+/// Options shared by every format
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportSettings {
+    pub locale: ExportLocale,
+    pub redaction: Redaction,
+    pub page_size: Option<u32>,
+    pub continuation_token: Option<String>,
+    /// Week numbering of the organization
+    pub calendar: CalendarSettings,
+}
+
+impl ExportSettings {
+    /// Settings from the query string; the locale defaults to the organization's
+    pub fn from_query(query: &ExportQuery, calendar: &CalendarSettings) -> Result<Self, ExportError> {
+        Ok(Self {
+            locale: ExportLocale::parse(query.locale.as_deref().unwrap_or(&calendar.locale))?,
+            redaction: query.redact.as_deref().map(Redaction::parse).transpose()?.unwrap_or_default(),
+            page_size: query.page_size,
+            continuation_token: query.continuation_token.clone(),
+            calendar: calendar.clone(),
+        })
+    }
+}
+
+/// Renders an export document in one format
+pub trait Exporter: Send + Sync {
+    /// Media type produced, e.g. `text/csv`
+    fn media_type(&self) -> &'static str;
+    
+    /// File extension of downloads
+    fn extension(&self) -> &'static str;
+    
+    /// Whether the format lists rows that can be fetched a page at a time;
+    /// documents such as the wheel always hold every activity
+    fn paginated(&self) -> bool {
+        false
+    }
+    
+    /// Whether recurring activities are drawn as their occurrences in the
+    /// document's year; data formats and calendars keep the series
+    fn occurrences(&self) -> bool {
+        false
+    }
+    
+    /// Write the document; [`render`] has already redacted and paginated it
+    fn render(&self, document: &ExportDocument, settings: &ExportSettings, out: &mut dyn io::Write) -> io::Result<()>;
+    
+    /// `Content-Type` header value
+    fn content_type(&self) -> String {
+        match self.media_type() {
+            text if text.starts_with("text/") || text.ends_with("+xml") => format!("{}; charset=utf-8", text),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// Exporters by media type, in order of preference
+#[derive(Clone)]
+pub struct ExporterRegistry {
+    exporters: Vec<Arc<dyn Exporter>>,
+}
+
+impl Default for ExporterRegistry {
+    /// All built-in formats, JSON first
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Arc::new(JsonExporter));
+        registry.register(Arc::new(CsvExporter));
+        registry.register(Arc::new(NdjsonExporter));
+        registry.register(Arc::new(IcsExporter));
+        registry.register(Arc::new(SvgExporter));
+        registry.register(Arc::new(PdfExporter));
+        registry
+    }
+}
+
+impl ExporterRegistry {
+    pub fn empty() -> Self {
+        Self { exporters: Vec::new() }
+    }
+    
+    /// Add a format; replaces the exporter registered for the same media type
+    pub fn register(&mut self, exporter: Arc<dyn Exporter>) {
+        match self.exporters.iter_mut().find(|e| e.media_type() == exporter.media_type()) {
+            Some(existing) => *existing = exporter,
+            None => self.exporters.push(exporter),
+        }
+    }
+    
+    pub fn media_types(&self) -> Vec<&'static str> {
+        self.exporters.iter().map(|e| e.media_type()).collect()
+    }
+    
+    /// Exporter for an `Accept` header; the first one when the header is missing
+    pub fn negotiate(&self, accept: Option<&str>) -> Result<&dyn Exporter, ExportError> {
+        let not_acceptable = || ExportError::NotAcceptable(self.media_types().join(", "));
+        let Some(accept) = accept.map(str::trim).filter(|a| !a.is_empty()) else {
+            return self.exporters.first().map(|e| e.as_ref()).ok_or_else(not_acceptable);
+        };
+        
+        let mut ranges: Vec<(f64, String)> = accept.split(',').filter_map(|part| {
+            let mut params = part.split(';');
+            let range = params.next()?.trim().to_ascii_lowercase();
+            let q = params
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f64>().ok())
+                .unwrap_or(1.0);
+            (!range.is_empty()).then_some((q, range))
+        }).collect();
+        // Stable, so equal weights keep the client's order
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+        
+        // `q=0` on an exact type rules it out even when a wildcard matches
+        let refused = |media_type: &str| ranges.iter().any(|(q, range)| *q <= 0.0 && range == media_type);
+        ranges.iter()
+            .filter(|(q, _)| *q > 0.0)
+            .find_map(|(_, range)| self.exporters.iter().find(|e| {
+                media_range_matches(range, e.media_type()) && !refused(e.media_type())
+            }))
+            .map(|e| e.as_ref())
+            .ok_or_else(not_acceptable)
+    }
+}
+
+fn media_range_matches(range: &str, media_type: &str) -> bool {
+    match range.strip_suffix("/*") {
+        Some("*") => true,
+        Some(kind) => media_type.split('/').next() == Some(kind),
+        None => range == media_type,
+    }
+}
+
+/// Serialized name of a unit enum variant
+fn variant_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+fn layer_name<'a>(layers: &'a [Layer], layer_id: &str) -> Option<&'a str> {
+    layers.iter().find(|l| l.id == layer_id).map(|l| l.name.as_str())
+}
+
+/// The export document as JSON
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn media_type(&self) -> &'static str {
+        "application/json"
+    }
+    
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+    
+    fn paginated(&self) -> bool {
+        true
+    }
+    
+    fn render(&self, document: &ExportDocument, _settings: &ExportSettings, out: &mut dyn io::Write) -> io::Result<()> {
+        serde_json::to_writer(out, document).map_err(io::Error::other)
+    }
+}
+
+/// RFC 4180 CSV with a header row
+pub struct CsvExporter;
+
+impl CsvExporter {
+    const COLUMNS: [&'static str; 10] = ["id", "title", "type", "layer", "startDate", "endDate", "week", "tags", "approvalStatus", "description"];
+    
+    /// Quoted field; cells that spreadsheets would run as formulas are prefixed with `'`
+    fn field(value: &str) -> String {
+        let value = match value.chars().next() {
+            Some('=' | '+' | '-' | '@' | '\t' | '\r') => format!("'{}", value),
+            _ => value.to_string(),
+        };
+        format!("\"{}\"", value.replace('"', "\"\""))
+    }
+}
+
+impl Exporter for CsvExporter {
+    fn media_type(&self) -> &'static str {
+        "text/csv"
+    }
+    
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+    
+    fn paginated(&self) -> bool {
+        true
+    }
+    
+    fn render(&self, document: &ExportDocument, settings: &ExportSettings, out: &mut dyn io::Write) -> io::Result<()> {
+        write!(out, "{}\r\n", Self::COLUMNS.join(","))?;
+        for activity in &document.activities {
+            let row = [
+                activity.id.clone(),
+                activity.title.clone(),
+                variant_name(&activity.activity_type),
+                layer_name(&document.layers, &activity.scope).unwrap_or(&activity.scope).to_string(),
+                activity.start_date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                activity.end_date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                calendar::format_week(calendar::week_number(activity.start_date.date_naive(), &settings.calendar)),
+                activity.tags.join(";"),
+                variant_name(&activity.approval_status),
+                activity.description.clone().unwrap_or_default(),
+            ];
+            write!(out, "{}\r\n", row.iter().map(|v| Self::field(v)).collect::<Vec<_>>().join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Newline-delimited JSON, one activity per line
+pub struct NdjsonExporter;
+
+impl Exporter for NdjsonExporter {
+    fn media_type(&self) -> &'static str {
+        "application/x-ndjson"
+    }
+    
+    fn extension(&self) -> &'static str {
+        "ndjson"
+    }
+    
+    fn paginated(&self) -> bool {
+        true
+    }
+    
+    fn render(&self, document: &ExportDocument, _settings: &ExportSettings, out: &mut dyn io::Write) -> io::Result<()> {
+        for activity in &document.activities {
+            serde_json::to_writer(&mut *out, activity).map_err(io::Error::other)?;
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// iCalendar, as served by calendar feeds
+pub struct IcsExporter;
+
+impl Exporter for IcsExporter {
+    fn media_type(&self) -> &'static str {
+        "text/calendar"
+    }
+    
+    fn extension(&self) -> &'static str {
+        "ics"
+    }
+    
+    fn render(&self, document: &ExportDocument, settings: &ExportSettings, out: &mut dyn io::Write) -> io::Result<()> {
+        out.write_all(ics::render_calendar(&document.title, &document.activities, document.generated_at, settings.locale).as_bytes())
+    }
+}
+
+/// Escape text for XML content and attributes
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// `#rgb` or `#rrggbb`, else a neutral grey; colors end up in attributes
+fn safe_color(color: &str) -> &str {
+    let hex = color.strip_prefix('#').unwrap_or("");
+    if matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) { color } else { "#9e9e9e" }
+}
+
+/// The wheel as SVG: layers as rings from the inside out, activities as arcs
+pub struct SvgExporter;
+
+impl SvgExporter {
+    const SIZE: f64 = 800.0;
+    const INNER_RADIUS: f64 = 120.0;
+    const OUTER_RADIUS: f64 = 360.0;
+    
+    /// Point at `radius` and `angle` (radians clockwise from the top)
+    fn point(radius: f64, angle: f64) -> (f64, f64) {
+        let center = Self::SIZE / 2.0;
+        (center + radius * angle.sin(), center - radius * angle.cos())
+    }
+    
+    /// Ring segment between two angles
+    fn segment(inner: f64, outer: f64, from: f64, to: f64) -> String {
+        let large = if to - from > PI { 1 } else { 0 };
+        let (x0, y0) = Self::point(outer, from);
+        let (x1, y1) = Self::point(outer, to);
+        let (x2, y2) = Self::point(inner, to);
+        let (x3, y3) = Self::point(inner, from);
+        format!(
+            "M{:.1} {:.1} A{:.1} {:.1} 0 {} 1 {:.1} {:.1} L{:.1} {:.1} A{:.1} {:.1} 0 {} 0 {:.1} {:.1} Z",
+            x0, y0, outer, outer, large, x1, y1, x2, y2, inner, inner, large, x3, y3,
+        )
+    }
+}
+
+impl Exporter for SvgExporter {
+    fn media_type(&self) -> &'static str {
+        "image/svg+xml"
+    }
+    
+    fn extension(&self) -> &'static str {
+        "svg"
+    }
+    
+    fn occurrences(&self) -> bool {
+        true
+    }
+    
+    fn render(&self, document: &ExportDocument, settings: &ExportSettings, writer: &mut dyn io::Write) -> io::Result<()> {
+        let year = document.year.unwrap_or_else(|| document.generated_at.year());
+        let (Some(year_start), Some(next_year)) = (NaiveDate::from_ymd_opt(year, 1, 1), NaiveDate::from_ymd_opt(year + 1, 1, 1)) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Year out of range: {}", year)));
+        };
+        let days = (next_year - year_start).num_days() as f64;
+        let angle = |date: NaiveDate| (date - year_start).num_days().clamp(0, days as i64) as f64 / days * 2.0 * PI;
+        
+        let mut layers: Vec<&Layer> = document.layers.iter().collect();
+        layers.sort_by_key(|l| l.ring_index);
+        let ring_width = (Self::OUTER_RADIUS - Self::INNER_RADIUS) / layers.len().max(1) as f64;
+        
+        let mut out = String::new();
+        let _ = write!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {0} {0}" width="{0}" height="{0}" font-family="sans-serif">"#, Self::SIZE);
+        let _ = write!(out, "<title>{}</title>", escape_xml(&document.title));
+        
+        for (ring, layer) in layers.iter().enumerate() {
+            let inner = Self::INNER_RADIUS + ring as f64 * ring_width;
+            let _ = write!(out, r##"<circle cx="{0}" cy="{0}" r="{1:.1}" fill="none" stroke="#e0e0e0"/>"##, Self::SIZE / 2.0, inner + ring_width);
+            
+            for activity in document.activities.iter().filter(|a| a.scope == layer.id) {
+                // Clipped to the year; a day-long activity still gets a visible sliver
+                let from = angle(activity.start_date.date_naive());
+                let to = angle(activity.end_date.date_naive().succ_opt().unwrap_or(next_year)).max(from + 0.01).min(2.0 * PI - 0.0001);
+                if from >= to {
+                    continue;
+                }
+                let _ = write!(
+                    out,
+                    r#"<path d="{}" fill="{}" stroke="white" stroke-width="0.5"><title>{}</title></path>"#,
+                    Self::segment(inner + 2.0, inner + ring_width - 2.0, from, to),
+                    safe_color(&activity.color),
+                    escape_xml(&activity.title),
+                );
+            }
+        }
+        
+        for (month, name) in settings.locale.month_names().iter().enumerate() {
+            let Some(first) = NaiveDate::from_ymd_opt(year, month as u32 + 1, 1) else { continue };
+            let start = angle(first);
+            let (x0, y0) = Self::point(Self::INNER_RADIUS, start);
+            let (x1, y1) = Self::point(Self::OUTER_RADIUS + 10.0, start);
+            let _ = write!(out, r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#bdbdbd"/>"##, x0, y0, x1, y1);
+            let (x, y) = Self::point(Self::OUTER_RADIUS + 25.0, start + PI / 12.0);
+            let _ = write!(out, r#"<text x="{:.1}" y="{:.1}" font-size="14" text-anchor="middle" dominant-baseline="middle">{}</text>"#, x, y, name);
+        }
+        
+        let center = Self::SIZE / 2.0;
+        let _ = write!(out, r#"<text x="{0}" y="{1}" font-size="20" text-anchor="middle">{2}</text>"#, center, center - 6.0, escape_xml(&document.title));
+        let _ = write!(out, r#"<text x="{0}" y="{1}" font-size="16" text-anchor="middle">{2}</text>"#, center, center + 18.0, year);
+        out.push_str("</svg>");
+        writer.write_all(out.as_bytes())
+    }
+}
+
+/// Activity list as PDF: Helvetica on A4 pages, without external dependencies
+pub struct PdfExporter;
+
+impl PdfExporter {
+    const PAGE_WIDTH: u32 = 595;
+    const PAGE_HEIGHT: u32 = 842;
+    const MARGIN: u32 = 50;
+    const LINE_HEIGHT: u32 = 14;
+    const LINES_PER_PAGE: usize = 50;
+    
+    /// PDF string literal in WinAnsi (Latin-1) encoding; other characters become `?`
+    fn literal(text: &str) -> String {
+        let mut out = String::from("(");
+        for c in text.chars() {
+            match c {
+                '(' | ')' | '\\' => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                ' '..='~' => out.push(c),
+                c if (c as u32) >= 0xA0 && (c as u32) <= 0xFF => {
+                    let _ = write!(out, "\\{:03o}", c as u32);
+                }
+                _ => out.push('?'),
+            }
+        }
+        out.push(')');
+        out
+    }
+    
+    fn line(activity: &Activity, layers: &[Layer], settings: &ExportSettings) -> String {
+        let locale = settings.locale;
+        let (start, end) = (activity.start_date.date_naive(), activity.end_date.date_naive());
+        let week = locale.week_label(calendar::week_number(start, &settings.calendar).week);
+        let mut line = format!("{}  {}  {}", week, locale.format_date(start), activity.title);
+        if end != start {
+            line = format!("{}  {} - {}  {}", week, locale.format_date(start), locale.format_date(end), activity.title);
+        }
+        if let Some(layer) = layer_name(layers, &activity.scope) {
+            let _ = write!(line, " ({})", layer);
+        }
+        line
+    }
+}
+
+impl Exporter for PdfExporter {
+    fn media_type(&self) -> &'static str {
+        "application/pdf"
+    }
+    
+    fn extension(&self) -> &'static str {
+        "pdf"
+    }
+    
+    fn occurrences(&self) -> bool {
+        true
+    }
+    
+    fn render(&self, document: &ExportDocument, settings: &ExportSettings, writer: &mut dyn io::Write) -> io::Result<()> {
+        let mut activities: Vec<&Activity> = document.activities.iter().collect();
+        activities.sort_by_key(|a| (a.start_date, a.end_date));
+        let mut lines = vec![document.title.clone(), String::new()];
+        lines.extend(activities.iter().map(|a| Self::line(a, &document.layers, settings)));
+        let pages: Vec<&[String]> = lines.chunks(Self::LINES_PER_PAGE).collect();
+        
+        // Objects: 1 catalog, 2 page tree, 3 font, then a page and its content stream per page
+        let page_ids: Vec<usize> = (0..pages.len()).map(|i| 4 + 2 * i).collect();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+                pages.len(),
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+        ];
+        for (page, id) in pages.iter().zip(&page_ids) {
+            let mut content = format!("BT /F1 10 Tf {} TL {} {} Td", Self::LINE_HEIGHT, Self::MARGIN, Self::PAGE_HEIGHT - Self::MARGIN);
+            for line in page.iter() {
+                let _ = write!(content, " {} Tj T*", Self::literal(line));
+            }
+            content.push_str(" ET");
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                Self::PAGE_WIDTH, Self::PAGE_HEIGHT, id + 1,
+            ));
+            objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content));
+        }
+        
+        let mut out = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            let _ = write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+        let xref = out.len();
+        let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(out, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(out, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref);
+        writer.write_all(out.as_bytes())
+    }
+}
+
+/// Download file name, e.g. `activities-2025.csv`
+pub fn file_name(stem: &str, exporter: &dyn Exporter) -> String {
+    let stem: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' }).collect();
+    format!("{}.{}", stem, exporter.extension())
+}
+
+/// Export document generated at `now`
+pub fn document(title: &str, year: Option<i32>, layers: Vec<Layer>, activities: Vec<Activity>, now: DateTime<Utc>) -> ExportDocument {
+    ExportDocument {
+        title: title.to_string(),
+        year,
+        generated_at: now,
+        layers,
+        activities,
+        continuation_token: None,
+        calendar: None,
+    }
+}
+
+/// Redact and paginate the document as the settings say, then render it;
+/// returns the bytes and the continuation token of the next page
+pub fn render(exporter: &dyn Exporter, mut document: ExportDocument, settings: &ExportSettings) -> Result<(Vec<u8>, Option<String>), ExportError> {
+    if exporter.occurrences() {
+        document.activities = recurrence::expand(std::mem::take(&mut document.activities), document.year);
+    }
+    for activity in &mut document.activities {
+        settings.redaction.apply(activity);
+    }
+    for layer in &mut document.layers {
+        settings.redaction.apply_to_layer(layer);
+    }
+    
+    if exporter.paginated() {
+        let page_size = settings.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+        let (page, next) = storage::paginate(document.activities, |a| a.id.clone(), page_size, settings.continuation_token.as_deref());
+        document.activities = page;
+        document.continuation_token = next;
+    }
+    
+    let year = document.year.unwrap_or_else(|| document.generated_at.year());
+    document.calendar = Some(calendar::metadata(year, settings.locale, &settings.calendar));
+    
+    let mut bytes = Vec::new();
+    exporter.render(&document, settings, &mut bytes).map_err(|e| ExportError::Render(e.to_string()))?;
+    Ok((bytes, document.continuation_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sample() -> ExportDocument {
+        let layer: Layer = serde_json::from_value(serde_json::json!({
+            "id": "layer-1", "name": "Styre", "type": "custom", "color": "#336699", "ringIndex": 0,
+            "isVisible": true, "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+        })).unwrap();
+        let activity: Activity = serde_json::from_value(serde_json::json!({
+            "id": "activity-1", "title": "=SUM(A1) \"Årsmøte\"", "startDate": "2025-03-10T09:00:00Z", "endDate": "2025-03-12T09:00:00Z",
+            "type": "meeting", "color": "#336699\" onload=\"x", "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1",
+            "organizationId": "org-1",
+        })).unwrap();
+        document("Board (2025)", Some(2025), vec![layer], vec![activity], "2025-06-01T00:00:00Z".parse().unwrap())
+    }
+    
+    #[test]
+    fn test_negotiate() {
+        let registry = ExporterRegistry::default();
+        let media_type = |accept: Option<&str>| registry.negotiate(accept).map(|e| e.media_type());
+        
+        assert_eq!(media_type(None), Ok("application/json"));
+        assert_eq!(media_type(Some("text/csv")), Ok("text/csv"));
+        assert_eq!(media_type(Some("application/x-ndjson")), Ok("application/x-ndjson"));
+        assert_eq!(media_type(Some("text/html, application/pdf;q=0.9, */*;q=0.1")), Ok("application/pdf"));
+        assert_eq!(media_type(Some("text/*;q=0.5, image/svg+xml")), Ok("image/svg+xml"));
+        assert_eq!(media_type(Some("text/*, text/csv;q=0")), Ok("text/calendar"));
+        assert!(matches!(registry.negotiate(Some("text/html")), Err(ExportError::NotAcceptable(_))));
+    }
+    
+    fn rendered(exporter: &dyn Exporter, document: &ExportDocument, settings: &ExportSettings) -> String {
+        String::from_utf8(render(exporter, document.clone(), settings).unwrap().0).unwrap()
+    }
+    
+    #[test]
+    fn test_exporters() {
+        let document = sample();
+        let settings = ExportSettings::default();
+        
+        let csv = rendered(&CsvExporter, &document, &settings);
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.starts_with("\"activity-1\",\"'=SUM(A1) \"\"Årsmøte\"\"\",\"meeting\",\"Styre\""));
+        assert!(row.contains(",\"2025-W11\","));
+        
+        let svg = rendered(&SvgExporter, &document, &settings);
+        assert!(svg.contains("<path") && svg.contains(r##"fill="#9e9e9e""##));
+        assert!(!svg.contains("onload=\""));
+        
+        let pdf = rendered(&PdfExporter, &document, &settings);
+        assert!(pdf.starts_with("%PDF-1.4") && pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains(r"(Board \(2025\))"));
+        assert!(pdf.contains(r"\305rsm\370te"));
+        
+        assert_eq!(file_name("share abc/1", &CsvExporter), "share-abc-1.csv");
+    }
+    
+    #[test]
+    fn test_shared_settings() {
+        let mut document = sample();
+        let mut second = document.activities[0].clone();
+        second.id = "activity-2".to_string();
+        document.activities[0].description = Some("Secret".to_string());
+        document.activities[0].created_by = Some("user-1".to_string());
+        document.activities.push(second);
+        
+        let query = ExportQuery {
+            locale: Some("nb-NO".to_string()),
+            redact: Some("descriptions, people".to_string()),
+            page_size: Some(1),
+            continuation_token: None,
+        };
+        let settings = ExportSettings::from_query(&query, &CalendarSettings::default()).unwrap();
+        assert_eq!(settings.locale, ExportLocale::Norwegian);
+        
+        let (bytes, next) = render(&NdjsonExporter, document.clone(), &settings).unwrap();
+        let ndjson = String::from_utf8(bytes).unwrap();
+        assert_eq!(next.as_deref(), Some("activity-1"));
+        assert_eq!(ndjson.lines().count(), 1);
+        assert!(!ndjson.contains("Secret") && !ndjson.contains("user-1"));
+        
+        let (_, next) = render(&NdjsonExporter, document.clone(), &ExportSettings { continuation_token: next, ..settings.clone() }).unwrap();
+        assert_eq!(next, None);
+        
+        // The wheel is never paginated
+        assert!(rendered(&SvgExporter, &document, &settings).contains(">mai<"));
+        assert!(rendered(&PdfExporter, &document, &settings).contains("Uke 11  10.03.2025 - 12.03.2025"));
+        
+        assert!(matches!(Redaction::parse("descriptions,emails"), Err(ExportError::UnknownRedaction(_))));
+        assert!(matches!(ExportLocale::parse("de"), Err(ExportError::UnknownLocale(_))));
+    }
+}
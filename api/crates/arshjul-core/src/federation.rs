@@ -0,0 +1,233 @@
+//! # Partner Federation
+//!
+//! Municipal collaborations plan on one wheel across two tenants. An admin
+//! of the granting organization gives a partner organization read access to
+//! selected layers: `POST /api/admin/federation/grants` records a
+//! [`FederationGrant`] in the organization policy and returns a signed
+//! federation token, which the grantor hands to the partner out of band.
+//!
+//! The partner attaches the token when creating a share
+//! (`federationTokens`), which makes it a combined share: its public view
+//! merges the granted layers' approved activities with its own at access
+//! time, stacked by the partner's activity order. Tokens are:
+//!
+//! - **Signed** - by the [`KeyRing`] for this purpose only, so they can't be
+//!   forged or widened to other layers. Instances verify each other's tokens
+//!   when they share signing keys (`SIGNING_KEY_VAULT_URL`)
+//! - **Bound** - to the partner organization; no other tenant can use them
+//! - **Revocable** - every access checks that the grant is still in the
+//!   grantor's policy and unexpired, so `DELETE /api/admin/federation/grants/{id}`
+//!   takes effect at once, and only layers still in the grant are shown.
+//!   Revoking also republishes the partner's CDN snapshots (`crate::snapshots`)
+//!
+//! The grantor decides what the partner shows: activities carry titles,
+//! dates and colors only, unless the grant sets `includeDescriptions`,
+//! whatever the partner share's view settings say.
+//!
+//! Calendar feeds and preview images show the share's own layers only.
+
+use crate::models::{Activity, FederationGrant, OrganizationPolicy, PartnerSource};
+use crate::signing_keys::{KeyRing, SigningKeyError};
+use crate::storage::{ActivityStorage, StorageError};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Signing purpose of federation tokens
+const TOKEN_PURPOSE: &str = "federation";
+
+/// Grants an organization may have at a time
+pub const MAX_GRANTS: usize = 50;
+
+/// Partner organizations one combined share may draw on
+pub const MAX_PARTNERS: usize = 5;
+
+/// Federation token errors
+#[derive(Debug, Error, PartialEq)]
+pub enum FederationError {
+    #[error("Invalid federation token")]
+    InvalidToken,
+
+    #[error("Federation token is for another organization")]
+    WrongPartner,
+
+    #[error("Federation grant has expired or was revoked")]
+    Revoked,
+
+    #[error("Signing failed: {0}")]
+    Signing(String),
+}
+
+impl From<SigningKeyError> for FederationError {
+    fn from(e: SigningKeyError) -> Self {
+        match e {
+            SigningKeyError::InvalidSignature => Self::InvalidToken,
+            other => Self::Signing(other.to_string()),
+        }
+    }
+}
+
+/// Claims of a federation token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FederationToken {
+    pub grantor_organization_id: String,
+    pub grant_id: String,
+    pub partner_organization_id: String,
+    pub layer_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl FederationToken {
+    pub fn new(grantor_organization_id: &str, grant: &FederationGrant) -> Self {
+        Self {
+            grantor_organization_id: grantor_organization_id.to_string(),
+            grant_id: grant.id.clone(),
+            partner_organization_id: grant.partner_organization_id.clone(),
+            layer_ids: grant.layer_ids.clone(),
+            expires_at: grant.expires_at,
+        }
+    }
+
+    /// `{base64url(claims)}.{signature}`
+    pub fn sign(&self, keys: &KeyRing) -> Result<String, FederationError> {
+        let claims = serde_json::to_vec(self).map_err(|e| FederationError::Signing(e.to_string()))?;
+        let claims = URL_SAFE_NO_PAD.encode(claims);
+        let signature = keys.sign(TOKEN_PURPOSE, claims.as_bytes())?;
+        Ok(format!("{}.{}", claims, signature))
+    }
+
+    /// Check a token's signature, partner and expiry
+    pub fn verify(keys: &KeyRing, token: &str, partner_organization_id: &str, now: DateTime<Utc>) -> Result<Self, FederationError> {
+        let (claims, signature) = token.trim().split_once('.').ok_or(FederationError::InvalidToken)?;
+        keys.verify(TOKEN_PURPOSE, claims.as_bytes(), signature)?;
+        let claims = URL_SAFE_NO_PAD.decode(claims).map_err(|_| FederationError::InvalidToken)?;
+        let token: Self = serde_json::from_slice(&claims).map_err(|_| FederationError::InvalidToken)?;
+
+        if token.partner_organization_id != partner_organization_id {
+            return Err(FederationError::WrongPartner);
+        }
+        if token.expires_at.is_some_and(|at| at <= now) {
+            return Err(FederationError::Revoked);
+        }
+        Ok(token)
+    }
+
+    /// Source of a combined share drawing on the token's grant
+    pub fn source(self) -> PartnerSource {
+        PartnerSource {
+            organization_id: self.grantor_organization_id,
+            grant_id: self.grant_id,
+            layer_ids: self.layer_ids,
+        }
+    }
+}
+
+/// The grant behind `source` in the grantor's policy, if it still lets `partner_organization_id` in
+pub fn active_grant<'a>(
+    policy: &'a OrganizationPolicy,
+    source: &PartnerSource,
+    partner_organization_id: &str,
+    now: DateTime<Utc>,
+) -> Option<&'a FederationGrant> {
+    policy.federation_grants.iter()
+        .filter(|g| g.id == source.grant_id && g.partner_organization_id == partner_organization_id)
+        .find(|g| g.expires_at.is_none_or(|at| at > now))
+}
+
+/// Layers of `source` the grant still covers
+pub fn granted_layers(grant: &FederationGrant, source: &PartnerSource) -> Vec<String> {
+    source.layer_ids.iter()
+        .filter(|id| grant.layer_ids.contains(id))
+        .cloned()
+        .collect()
+}
+
+/// Activities of the grantor's layers the grant still covers, redacted as the grant says,
+/// with the IDs of those layers
+pub async fn granted_activities(
+    activities: &dyn ActivityStorage,
+    grant: &FederationGrant,
+    source: &PartnerSource,
+    year: i32,
+) -> Result<(Vec<String>, Vec<Activity>), StorageError> {
+    let layer_ids = granted_layers(grant, source);
+    let mut granted = activities.list_by_layers(&source.organization_id, &layer_ids, Some(year)).await?;
+    if !grant.include_descriptions {
+        for activity in &mut granted {
+            redact(activity);
+        }
+    }
+    Ok((layer_ids, granted))
+}
+
+/// Strip what a grant without descriptions leaves out
+fn redact(activity: &mut Activity) {
+    activity.description = None;
+    activity.links.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::signing_keys::{InMemoryKeyStore, RotationPolicy};
+    use chrono::Duration;
+    use std::sync::Arc;
+
+    fn grant(expires_at: Option<DateTime<Utc>>) -> FederationGrant {
+        FederationGrant {
+            id: "grant-1".to_string(),
+            partner_organization_id: "org-b".to_string(),
+            layer_ids: vec!["layer-1".to_string(), "layer-2".to_string()],
+            created_by: "admin".to_string(),
+            created_at: "2025-01-01T00:00:00Z".parse().unwrap(),
+            expires_at,
+            include_descriptions: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_roundtrip() {
+        let now: DateTime<Utc> = "2025-03-01T00:00:00Z".parse().unwrap();
+        let keys = KeyRing::new(Arc::new(InMemoryKeyStore::new()), RotationPolicy::default(), Arc::new(ManualClock::new(now)));
+        keys.rotate().await.unwrap();
+
+        let token = FederationToken::new("org-a", &grant(Some(now + Duration::days(30)))).sign(&keys).unwrap();
+        let verified = FederationToken::verify(&keys, &token, "org-b", now).unwrap();
+        assert_eq!(verified.grantor_organization_id, "org-a");
+        assert_eq!(verified.layer_ids, ["layer-1", "layer-2"]);
+
+        assert_eq!(FederationToken::verify(&keys, &token, "org-c", now), Err(FederationError::WrongPartner));
+        assert_eq!(FederationToken::verify(&keys, &token, "org-b", now + Duration::days(30)), Err(FederationError::Revoked));
+
+        // Widening the claims breaks the signature
+        let (_, signature) = token.split_once('.').unwrap();
+        let mut widened = FederationToken::new("org-a", &grant(None));
+        widened.layer_ids.push("layer-3".to_string());
+        let forged = format!("{}.{}", URL_SAFE_NO_PAD.encode(serde_json::to_vec(&widened).unwrap()), signature);
+        assert_eq!(FederationToken::verify(&keys, &forged, "org-b", now), Err(FederationError::InvalidToken));
+    }
+
+    #[test]
+    fn test_active_grant() {
+        let now: DateTime<Utc> = "2025-03-01T00:00:00Z".parse().unwrap();
+        let mut policy = OrganizationPolicy::new("org-a");
+        policy.federation_grants.push(grant(Some(now + Duration::days(1))));
+        let source = PartnerSource {
+            organization_id: "org-a".to_string(),
+            grant_id: "grant-1".to_string(),
+            layer_ids: vec!["layer-2".to_string(), "layer-9".to_string()],
+        };
+
+        let active = active_grant(&policy, &source, "org-b", now).unwrap();
+        assert_eq!(granted_layers(active, &source), ["layer-2"]);
+        assert!(active_grant(&policy, &source, "org-c", now).is_none());
+        assert!(active_grant(&policy, &source, "org-b", now + Duration::days(1)).is_none());
+
+        policy.federation_grants.clear();
+        assert!(active_grant(&policy, &source, "org-b", now).is_none());
+    }
+}
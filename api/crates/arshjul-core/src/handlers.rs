@@ -0,0 +1,5401 @@
+//! HTTP Handlers for Azure Functions
+//!
+//! Each handler corresponds to an HTTP-triggered Azure Function.
+
+use crate::auth::{AuthError, TokenValidator, UserContext};
+use crate::authorization::{self, EndpointFamily};
+use crate::clock::Clock;
+use crate::crypto::{generate_share_key, generate_short_code, is_valid_share_key, is_valid_short_code, secure_compare};
+use crate::models::*;
+use crate::storage::{self, ShareStorage, ActivityChanges, ActivityStorage, LayerStorage, ActivityTypeStorage, UserSettingsStorage, AuditStorage, PolicyStorage, FilterBuilder, FilterField, QueryOptions, StorageError};
+use crate::storage::memory_storage::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::sync::{compute_delta, SyncToken};
+use crate::events::{ChangeKind, DomainEvent, EntityChange, EntityKind, EventBus, LiveUpdateService, NegotiateResponse};
+use crate::locks::{ensure_not_locked_by_other, new_lock, LockError, LockStore};
+use crate::offboarding::{ConfirmationSigner, ConfirmationToken, Purger, AUDIT_ACTION_PURGED};
+use crate::dry_run::ExecutionPlan;
+use crate::client_info::{ClientInfo, TrustedProxyConfig};
+use crate::pseudonym::{self, Pseudonymizer};
+use crate::public_access::{self, PublicAccessError};
+use crate::snapshots;
+use crate::search::{self, ActivitySearchIndex};
+use crate::directory::{self, DirectoryError, DirectoryService};
+use crate::rate_limit::{self, RateLimitStatus, RateLimited, RateLimiter};
+use crate::layer_access;
+use crate::teams_context::{self, TeamsContext, TeamsContextError};
+use crate::markdown;
+use crate::links;
+use crate::share_urls::ShareUrls;
+use crate::share_traffic::ShareTrafficStore;
+use crate::deprecation::Deprecations;
+use crate::log_overrides::{LogOverrides, DEFAULT_OVERRIDE_MINUTES, MAX_OVERRIDE_MINUTES};
+use crate::slo::SloTracker;
+use crate::planning::{self, Cancellation, AUDIT_ACTION_ACTIVITY_CANCELLED};
+use crate::powerbi::{self, RefreshRange};
+use crate::period_lock;
+use crate::share_renewal::{RenewalLinkSigner, RenewalTokenError};
+use crate::ics;
+use crate::i18n;
+use crate::export::{self, ExportSettings, Exporter, ExporterRegistry, Redaction};
+use crate::access_log::{self, AccessLogForwarder};
+use crate::activity_types;
+use crate::reassign;
+use crate::split_merge;
+use crate::ordering;
+use crate::recurrence;
+use crate::calendar;
+use crate::terms::{self, TermPopulator};
+use crate::indexing;
+use crate::preview::{self, PreviewSigner};
+use crate::signing_keys::KeyRing;
+use crate::federation::{self, FederationError, FederationToken};
+use crate::backup;
+use crate::health::HealthChecker;
+use crate::partition_monitor::PartitionMonitor;
+use crate::storage_metrics::StorageMetrics;
+use crate::storage_budget::{RequestBudget, StorageBudget};
+use crate::org_snapshots::{self, OrganizationSnapshotStore};
+use crate::operations::{self, BulkExecutor, OperationInput};
+use crate::nonce::{self, NonceError, NonceStore, RequestNonce};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Short code draws before giving up on share creation
+const MAX_SHORT_CODE_ATTEMPTS: u32 = 5;
+
+/// Handler context with shared dependencies
+pub struct HandlerContext {
+    pub share_storage: Arc<dyn ShareStorage>,
+    pub activity_storage: Arc<dyn ActivityStorage>,
+    pub layer_storage: Arc<dyn LayerStorage>,
+    pub activity_type_storage: Arc<dyn ActivityTypeStorage>,
+    pub user_settings_storage: Arc<dyn UserSettingsStorage>,
+    pub audit_storage: Arc<dyn AuditStorage>,
+    pub policy_storage: Arc<dyn PolicyStorage>,
+    pub token_validator: TokenValidator,
+    pub base_url: String,
+    /// Reports after which a public share is deactivated automatically (0 = never)
+    pub share_report_threshold: u32,
+    /// Proxy headers trusted for client IP resolution
+    pub trusted_proxies: TrustedProxyConfig,
+    /// Event bus for entity change notifications
+    pub events: Arc<EventBus>,
+    /// Live update service (None when not configured)
+    pub live_updates: Option<Arc<dyn LiveUpdateService>>,
+    /// Advisory edit locks for activities
+    pub locks: Arc<dyn LockStore>,
+    /// Hashes user IDs written to audit/analytics records
+    pub pseudonymizer: Arc<dyn Pseudonymizer>,
+    /// CDN base URL of published share snapshots (None disables redirects)
+    pub snapshot_base_url: Option<String>,
+    /// External activity search index (None searches storage)
+    pub activity_search: Option<Arc<dyn ActivitySearchIndex>>,
+    /// Resolves creator display names (None leaves `createdByName` unset)
+    pub directory: Option<Arc<dyn DirectoryService>>,
+    /// Per-user limit on directory searches
+    pub directory_search_limiter: Arc<RateLimiter>,
+    /// Per-minute windows of public share endpoints; limits come from each organization's rate plan
+    pub public_api_limiter: Arc<RateLimiter>,
+    /// Export formats, picked by the `Accept` header
+    pub exporters: Arc<ExporterRegistry>,
+    /// Domains activity links may not point to (subdomains included)
+    pub link_domain_denylist: Vec<String>,
+    /// Daily public share views for anomaly detection (not recorded when unset)
+    pub share_traffic: Option<Arc<dyn ShareTrafficStore>>,
+    /// Forwards public share access to organizations' SIEMs (not forwarded when unset)
+    pub access_log: Option<Arc<AccessLogForwarder>>,
+    /// Temporary per-organization log levels
+    pub log_overrides: Arc<LogOverrides>,
+    /// Service level indicators for `GET /api/metrics`
+    pub slo: Arc<SloTracker>,
+    /// Bearer token required by `GET /api/metrics` (None disables the endpoint)
+    pub metrics_token: Option<String>,
+    /// Deprecated routes and the organizations calling them
+    pub deprecations: Arc<Deprecations>,
+    /// Partition sizes of `GET /api/metrics/partitions`
+    pub partitions: Arc<PartitionMonitor>,
+    /// Storage call counters and latency of `GET /api/metrics` (see [`crate::storage_metrics`])
+    pub storage_metrics: Arc<StorageMetrics>,
+    /// Storage calls and time of one share view before it degrades
+    pub storage_budget: StorageBudget,
+    /// Point-in-time copies of organizations (None disables `/api/admin/snapshots`)
+    pub org_snapshots: Option<Arc<dyn OrganizationSnapshotStore>>,
+    /// Runs bulk imports and reassignments in the background (None disables `/api/operations`)
+    pub operations: Option<Arc<BulkExecutor>>,
+    /// Storage probes of `GET /api/health`
+    pub health: Arc<HealthChecker>,
+    /// Signs share renewal links in reminder emails (None disables renew-by-token)
+    pub renewal_links: Option<Arc<RenewalLinkSigner>>,
+    /// Signs share preview image links (None issues no preview URLs)
+    pub preview_links: Option<Arc<PreviewSigner>>,
+    /// Versioned signing keys, published at `GET /.well-known/jwks.json` (None disables it)
+    pub signing_keys: Option<Arc<KeyRing>>,
+    /// Organizations whose indexable shares `GET /sitemap.xml` lists (empty disables it)
+    pub sitemap_organizations: Vec<String>,
+    /// Rendered `GET /sitemap.xml`, reused until it expires
+    pub sitemap_cache: Arc<indexing::SitemapCache>,
+    /// Pending single-use nonces of public POST endpoints
+    pub nonces: Arc<dyn NonceStore>,
+    /// Time source for expiry, renewal and timestamps
+    pub clock: Arc<dyn Clock>,
+}
+
+impl HandlerContext {
+    /// Validate the caller's bearer token, counting the outcome for the `auth_success` SLI
+    pub async fn authenticate(&self, auth_header: Option<&str>) -> Result<UserContext, HttpResponse<ApiError>> {
+        let header = auth_header.ok_or_else(|| HttpResponse::unauthorized(&AuthError::MissingHeader.to_string()))?;
+        let result = self.token_validator.validate(header).await;
+        self.slo.record_auth(result.is_ok());
+        result.map_err(|e| HttpResponse::unauthorized(&e.to_string()))
+    }
+    
+    /// Attach the Teams team the request came from (see [`crate::teams_context`]) once the user is verified as a member
+    pub async fn with_teams_context(&self, mut user: UserContext, headers: &[(String, String)]) -> Result<UserContext, HttpResponse<ApiError>> {
+        let team = TeamsContext::from_headers(headers)
+            .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+        if let Some(team) = team {
+            teams_context::verify_membership(&team, &user, self.directory.as_deref()).await
+                .map_err(|e| match e {
+                    TeamsContextError::Directory(_) => {
+                        tracing::warn!("Team membership check failed: {}", e);
+                        HttpResponse::service_unavailable("Team membership could not be verified")
+                    }
+                    _ => HttpResponse::forbidden(&e.to_string()),
+                })?;
+            user.team = Some(team);
+        }
+        Ok(user)
+    }
+    
+    /// Resolve client IP and user agent; the single entry point for rate limiting, analytics and allow-lists
+    pub fn client_info(&self, headers: &[(String, String)], peer_addr: Option<std::net::IpAddr>) -> ClientInfo {
+        ClientInfo::from_headers(headers, peer_addr, &self.trusted_proxies)
+    }
+    
+    /// Identifier to store for a user in audit/analytics records
+    pub fn pseudonymize(&self, organization_id: &str, user_id: &str) -> String {
+        self.pseudonymizer.pseudonymize(organization_id, user_id)
+    }
+    
+    /// Display names of the given users; best effort, empty when unavailable
+    async fn display_names<'a>(&self, organization_id: &str, user_ids: impl Iterator<Item = &'a str>) -> HashMap<String, String> {
+        let Some(ref directory) = self.directory else {
+            return HashMap::new();
+        };
+        let user_ids: Vec<String> = user_ids.map(str::to_string).collect();
+        if user_ids.is_empty() {
+            return HashMap::new();
+        }
+        
+        directory.display_names(organization_id, &user_ids).await.unwrap_or_else(|e| {
+            tracing::warn!("Display name lookup failed: {}", e);
+            HashMap::new()
+        })
+    }
+    
+    /// Fill in `created_by_name` on activities
+    async fn with_creator_names(&self, organization_id: &str, mut activities: Vec<Activity>) -> Vec<Activity> {
+        let names = self.display_names(organization_id, activities.iter().filter_map(|a| a.created_by.as_deref())).await;
+        for activity in &mut activities {
+            activity.created_by_name = activity.created_by.as_ref().and_then(|id| names.get(id).cloned());
+        }
+        activities
+    }
+    
+    /// Show shares that follow the organization with its current default view settings
+    async fn with_shown_view_settings(&self, organization_id: &str, mut shares: Vec<ShareLink>) -> Result<Vec<ShareLink>, HttpResponse<ApiError>> {
+        if shares.iter().any(|s| s.inherit_view_settings) {
+            let policy = self.policy_storage.get(organization_id).await
+                .map_err(HttpResponse::from)?;
+            for share in &mut shares {
+                share.view_settings = public_access::view_settings(share, policy.share_view_defaults.as_ref());
+            }
+        }
+        Ok(shares)
+    }
+    
+    /// Fill in `created_by_name` on shares
+    async fn with_share_creator_names(&self, organization_id: &str, mut shares: Vec<ShareLink>) -> Vec<ShareLink> {
+        let names = self.display_names(organization_id, shares.iter().map(|s| s.created_by.as_str())).await;
+        for share in &mut shares {
+            share.created_by_name = names.get(&share.created_by).cloned();
+        }
+        shares
+    }
+    
+    /// Layers of the organization the user may see (see [`crate::layer_access`])
+    async fn visible_layers(&self, user: &UserContext) -> Result<Vec<Layer>, HttpResponse<ApiError>> {
+        let layers = self.layer_storage.list(&user.organization_id).await
+            .map_err(HttpResponse::from)?;
+        let member_of = layer_access::member_groups(&layers, user, self.directory.as_deref()).await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Group membership check failed, hiding restricted layers: {}", e);
+                HashSet::new()
+            });
+        Ok(layer_access::filter_visible(layers, user.is_admin, &member_of)
+            .into_iter()
+            .filter(|l| teams_context::on_team_wheel(l, user.team.as_ref()))
+            .collect())
+    }
+    
+    /// IDs of the layers the user may see
+    async fn visible_layer_ids(&self, user: &UserContext) -> Result<HashSet<String>, HttpResponse<ApiError>> {
+        Ok(self.visible_layers(user).await?.into_iter().map(|l| l.id).collect())
+    }
+    
+    /// Purge routine over this context's storage, publishing on its bus
+    fn purger(&self) -> Purger {
+        Purger {
+            shares: self.share_storage.clone(),
+            activities: self.activity_storage.clone(),
+            layers: self.layer_storage.clone(),
+            activity_types: self.activity_type_storage.clone(),
+            user_settings: self.user_settings_storage.clone(),
+            policies: self.policy_storage.clone(),
+            audit: self.audit_storage.clone(),
+            org_snapshots: self.org_snapshots.clone(),
+            events: Some(self.events.clone()),
+        }
+    }
+    
+    /// Publish a share change, including the short code for cache invalidation
+    async fn publish_share_change(&self, user: &UserContext, share: &ShareLink, change: ChangeKind) {
+        self.events.publish(DomainEvent::EntityChanged(EntityChange::new(
+            &user.organization_id,
+            EntityKind::Share,
+            &share.id,
+            change,
+            Some(&user.user_id),
+        ).with_key(&share.short_code))).await;
+    }
+    
+    /// Publish an entity change after a successful write
+    async fn publish_change(&self, user: &UserContext, entity: EntityKind, entity_id: &str, change: ChangeKind) {
+        self.events.publish(DomainEvent::EntityChanged(EntityChange::new(
+            &user.organization_id,
+            entity,
+            entity_id,
+            change,
+            Some(&user.user_id),
+        ))).await;
+    }
+    
+    /// Publish a change to an activity on `layer_ids`
+    async fn publish_activity_change(&self, user: &UserContext, activity_id: &str, layer_ids: &[&str], change: ChangeKind) {
+        self.events.publish(DomainEvent::EntityChanged(EntityChange::new(
+            &user.organization_id,
+            EntityKind::Activity,
+            activity_id,
+            change,
+            Some(&user.user_id),
+        ).with_layers(layer_ids))).await;
+    }
+}
+
+/// HTTP Response wrapper
+///
+/// Bodies are JSON unless a non-JSON `Content-Type` header is set, in which
+/// case a `String` body is written as-is (e.g. `text/calendar` feeds). Byte
+/// bodies (`Vec<u8>`, from exports) are always written as-is.
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpResponse<T: Serialize> {
+    pub status: u16,
+    pub body: T,
+    /// Response headers (cache policy, ETag); see [`crate::http_cache`]
+    pub headers: Vec<(String, String)>,
+}
+
+impl<T: Serialize> HttpResponse<T> {
+    pub fn ok(body: T) -> Self {
+        Self { status: 200, body, headers: Vec::new() }
+    }
+    
+    pub fn created(body: T) -> Self {
+        Self { status: 201, body, headers: Vec::new() }
+    }
+    
+    /// 202: the request was queued and is still being worked on
+    pub fn accepted(body: T) -> Self {
+        Self { status: 202, body, headers: Vec::new() }
+    }
+    
+    /// 302 redirect; the body is kept for clients that don't follow redirects
+    pub fn found(location: &str, body: T) -> Self {
+        Self { status: 302, body, headers: Vec::new() }.with_header("Location", location)
+    }
+    
+    /// Add or replace a header
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+    
+    /// Add the `X-RateLimit-*` headers (see [`crate::rate_limit`])
+    pub fn with_rate_limit(self, status: &RateLimitStatus) -> Self {
+        status.headers().iter().fold(self, |response, (name, value)| response.with_header(name, value))
+    }
+}
+
+impl HttpResponse<ApiError> {
+    pub fn bad_request(message: &str) -> Self {
+        Self { status: 400, body: ApiError::bad_request(message), headers: Vec::new() }
+    }
+    
+    pub fn unauthorized(message: &str) -> Self {
+        Self { status: 401, body: ApiError::unauthorized(message), headers: Vec::new() }
+    }
+    
+    pub fn forbidden(message: &str) -> Self {
+        Self { status: 403, body: ApiError::forbidden(message), headers: Vec::new() }
+    }
+    
+    pub fn not_found(message: &str) -> Self {
+        Self { status: 404, body: ApiError::not_found(message), headers: Vec::new() }
+    }
+    
+    pub fn gone(message: &str) -> Self {
+        Self { status: 410, body: ApiError::expired(message), headers: Vec::new() }
+    }
+    
+    pub fn conflict(message: &str) -> Self {
+        Self { status: 409, body: ApiError::conflict(message), headers: Vec::new() }
+    }
+    
+    pub fn internal_error(message: &str) -> Self {
+        Self { status: 500, body: ApiError::internal(message), headers: Vec::new() }
+    }
+    
+    pub fn service_unavailable(message: &str) -> Self {
+        Self { status: 503, body: ApiError::service_unavailable(message), headers: Vec::new() }
+    }
+    
+    pub fn not_acceptable(message: &str) -> Self {
+        Self { status: 406, body: ApiError::not_acceptable(message), headers: Vec::new() }
+    }
+    
+    pub fn too_many_requests(message: &str, retry_after_seconds: i64) -> Self {
+        Self { status: 429, body: ApiError::too_many_requests(message), headers: Vec::new() }
+            .with_header("Retry-After", &retry_after_seconds.to_string())
+    }
+}
+
+/// Seconds clients are asked to wait after storage throttled them
+const STORAGE_RETRY_AFTER_SECONDS: u32 = 5;
+
+/// Storage failures a handler doesn't handle itself: throttling that outlasted
+/// the retries ([`crate::storage_retry`]) is 503 with `Retry-After`, the rest 500
+impl From<StorageError> for HttpResponse<ApiError> {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::Transient(_) => HttpResponse::service_unavailable("Storage is busy, try again later")
+                .with_header("Retry-After", &STORAGE_RETRY_AFTER_SECONDS.to_string()),
+            StorageError::Validation(message) => HttpResponse::bad_request(&message),
+            StorageError::Conflict(_) => HttpResponse::conflict("Changed by someone else since it was read; reload and try again"),
+            e => HttpResponse::internal_error(&e.to_string()),
+        }
+    }
+}
+
+// ============================================
+// Share Handlers
+// ============================================
+
+/// Longest share name, in characters
+const MAX_SHARE_NAME_LEN: usize = 200;
+
+/// Longest share description, in characters
+const MAX_SHARE_DESCRIPTION_LEN: usize = 2000;
+
+/// Check the name and description of a new or updated share
+fn validate_share_text(name: Option<&str>, description: Option<&str>) -> Result<(), HttpResponse<ApiError>> {
+    if name.is_some_and(|n| n.chars().count() > MAX_SHARE_NAME_LEN) {
+        return Err(HttpResponse::bad_request(&format!("Name too long (max {} characters)", MAX_SHARE_NAME_LEN)));
+    }
+    if description.is_some_and(|d| d.chars().count() > MAX_SHARE_DESCRIPTION_LEN) {
+        return Err(HttpResponse::bad_request(&format!("Description too long (max {} characters)", MAX_SHARE_DESCRIPTION_LEN)));
+    }
+    Ok(())
+}
+
+/// POST /api/shares - Create a new share
+pub async fn create_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: CreateShareRequest,
+) -> Result<HttpResponse<CreateShareResponse>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    
+    // Validate request
+    if request.layer_config.layer_ids.is_empty() {
+        return Err(HttpResponse::bad_request("At least one layer must be selected"));
+    }
+    
+    // Validate layer_ids count (prevent abuse)
+    if request.layer_config.layer_ids.len() > 100 {
+        return Err(HttpResponse::bad_request("Too many layers selected (max 100)"));
+    }
+    
+    // Restricted layers can only be shared by those who can see them
+    let visible = ctx.visible_layer_ids(user).await?;
+    if !request.layer_config.layer_ids.iter().all(|id| visible.contains(id)) {
+        return Err(HttpResponse::bad_request("Unknown layer selected"));
+    }
+    let partner_sources = partner_sources(ctx, user, &request.federation_tokens).await?;
+    
+    validate_share_text(request.name.as_deref(), request.description.as_deref())?;
+    
+    // Shown with the organization's defaults, under the fields the request sets
+    let view_overrides = request.view_settings.unwrap_or_default();
+    let defaults = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?
+        .share_view_defaults
+        .unwrap_or_default();
+    
+    // Create share
+    let now = ctx.clock.now();
+    let expires_at = now + Duration::days(365); // 1 year TTL
+    
+    let mut share = ShareLink {
+        id: uuid::Uuid::new_v4().to_string(),
+        share_key: generate_share_key(),
+        short_code: generate_short_code(),
+        visibility: request.visibility,
+        organization_id: user.organization_id.clone(),
+        created_by: user.user_id.clone(),
+        created_at: now,
+        expires_at,
+        renewed_at: None,
+        name: request.name,
+        description: request.description,
+        layer_config: request.layer_config,
+        view_settings: view_overrides.merge(&defaults),
+        inherit_view_settings: true,
+        view_overrides,
+        stats: ShareStats::default(),
+        is_active: true,
+        ttl: Some((expires_at - now).num_seconds()),
+        report_count: 0,
+        reporters: Vec::new(),
+        publish_snapshot: request.publish_snapshot,
+        indexable: None,
+        created_by_name: None,
+        review: None,
+        team_ids: Vec::new(),
+        channel_ids: Vec::new(),
+        deleted_at: None,
+        partner_sources,
+    };
+    (share.team_ids, share.channel_ids) = teams_context::scope_of(user.team.as_ref());
+    
+    // Save to storage, drawing a new short code if it is taken or retired
+    let mut attempts = 0;
+    let saved = loop {
+        match ctx.share_storage.create(share.clone()).await {
+            Ok(saved) => break saved,
+            Err(StorageError::AlreadyExists(_)) if attempts < MAX_SHORT_CODE_ATTEMPTS => {
+                attempts += 1;
+                share.short_code = generate_short_code();
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    
+    ctx.publish_share_change(user, &saved, ChangeKind::Created).await;
+    
+    let mut urls = ShareUrls::new(&ctx.base_url, &saved);
+    if let Some(signer) = &ctx.preview_links {
+        urls = urls.with_preview(signer, ctx.clock.now());
+    }
+    Ok(HttpResponse::created(urls.response()))
+}
+
+/// Partner layers of a new combined share, from the federation tokens attached to it
+async fn partner_sources(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    tokens: &[String],
+) -> Result<Vec<PartnerSource>, HttpResponse<ApiError>> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+    if tokens.len() > federation::MAX_PARTNERS {
+        return Err(HttpResponse::bad_request(&format!("Too many federation tokens (max {})", federation::MAX_PARTNERS)));
+    }
+    let keys = ctx.signing_keys.as_ref()
+        .ok_or_else(|| HttpResponse::service_unavailable("Federation is not configured"))?;
+    
+    let now = ctx.clock.now();
+    let mut sources: Vec<PartnerSource> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let source = FederationToken::verify(keys, token, &user.organization_id, now)
+            .map_err(|e| match e {
+                FederationError::Signing(_) => HttpResponse::internal_error(&e.to_string()),
+                _ => HttpResponse::bad_request(&e.to_string()),
+            })?
+            .source();
+        let policy = ctx.policy_storage.get(&source.organization_id).await
+            .map_err(HttpResponse::from)?;
+        if federation::active_grant(&policy, &source, &user.organization_id, now).is_none() {
+            return Err(HttpResponse::bad_request(&FederationError::Revoked.to_string()));
+        }
+        if !sources.iter().any(|s| s.organization_id == source.organization_id && s.grant_id == source.grant_id) {
+            sources.push(source);
+        }
+    }
+    Ok(sources)
+}
+
+/// Add the layers and activities partner organizations still grant to a combined share
+///
+/// Partner activities are redacted by the grant, not the share's view settings.
+/// A partner that can't be read is left out rather than failing the view;
+/// the returned flag is set when one was left out for the storage budget.
+async fn with_partner_layers(
+    ctx: &HandlerContext,
+    budget: &RequestBudget,
+    mut share: ShareLink,
+    mut activities: Vec<Activity>,
+    year: i32,
+) -> (ShareLink, Vec<Activity>, bool) {
+    let now = ctx.clock.now();
+    let mut partial = false;
+    for source in std::mem::take(&mut share.partner_sources) {
+        let policy = match budget.call(public_policy(ctx, &source.organization_id)).await {
+            Some(Some(policy)) => policy,
+            Some(None) => continue,
+            None => {
+                partial = true;
+                continue;
+            }
+        };
+        let Some(grant) = federation::active_grant(&policy, &source, &share.organization_id, now) else {
+            continue;
+        };
+        match budget.call(federation::granted_activities(ctx.activity_storage.as_ref(), grant, &source, year)).await {
+            Some(Ok((layer_ids, partner_activities))) => {
+                activities.extend(partner_activities);
+                share.layer_config.layer_ids.extend(layer_ids);
+            }
+            Some(Err(e)) => tracing::warn!(error = %e, partner = %source.organization_id, "Failed to load partner activities"),
+            None => partial = true,
+        }
+    }
+    (share, activities, partial)
+}
+
+/// GET /api/shares - List shares for organization
+pub async fn list_shares(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: ListSharesRequest,
+) -> Result<HttpResponse<ListSharesResponse>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    
+    // Filtered in storage so pages and counts match the filter
+    let mut filter = FilterBuilder::new();
+    if let Some(visibility) = request.visibility {
+        let visibility = serde_json::to_value(visibility).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+        filter = filter.eq(FilterField::Visibility, visibility);
+    }
+    if let Some(is_active) = request.is_active {
+        filter = filter.eq(FilterField::IsActive, is_active);
+    }
+    let filter = filter.build();
+    
+    // Teams scope is no storage filter, so listings in a team or channel page in memory
+    let (shares, continuation_token, total_count) = match user.team {
+        Some(ref team) => {
+            let scoped: Vec<ShareLink> = storage::list_all_shares_matching(ctx.share_storage.as_ref(), &user.organization_id, filter).await
+                .map_err(HttpResponse::from)?
+                .into_iter()
+                .filter(|s| teams_context::share_in_scope(s, Some(team)))
+                .collect();
+            let total = scoped.len() as u64;
+            let page_size = request.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+            let (page, next) = storage::paginate(scoped, |s| s.id.clone(), page_size, request.continuation_token.as_deref());
+            (page, next, total)
+        }
+        None => {
+            let options = QueryOptions {
+                page_size: request.page_size,
+                continuation_token: request.continuation_token,
+                filter: filter.clone(),
+            };
+            // Backends with opaque tokens reject ones they did not issue
+            let result = ctx.share_storage.list(&user.organization_id, options).await
+                .map_err(|e| match e {
+                    StorageError::Validation(message) => HttpResponse::bad_request(&message),
+                    e => HttpResponse::from(e),
+                })?;
+            // Backends that can't count (Table Storage without counters, filtered lists) are counted in full
+            let total = match result.total_count {
+                Some(total) => total,
+                None => storage::list_all_shares_matching(ctx.share_storage.as_ref(), &user.organization_id, filter).await
+                    .map_err(HttpResponse::from)?
+                    .len() as u64,
+            };
+            (result.items, result.continuation_token, total)
+        }
+    };
+    
+    let shares = ctx.with_shown_view_settings(&user.organization_id, shares).await?;
+    Ok(HttpResponse::ok(ListSharesResponse {
+        shares: ctx.with_share_creator_names(&user.organization_id, shares).await,
+        continuation_token,
+        total_count,
+    }))
+}
+
+/// GET /api/shares/{id} - Get share by ID
+pub async fn get_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    let share = ctx.share_storage.get(&user.organization_id, share_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Share not found"),
+            _ => HttpResponse::from(e),
+        })?;
+    let shares = ctx.with_shown_view_settings(&user.organization_id, vec![share]).await?;
+    let share = ctx.with_share_creator_names(&user.organization_id, shares).await.remove(0);
+    
+    Ok(HttpResponse::ok(share))
+}
+
+/// PATCH /api/shares/{id} - Rename a share or change how it is shown
+pub async fn update_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+    request: UpdateShareRequest,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    validate_share_text(request.name.as_deref(), request.description.as_deref())?;
+    let mut share = get_writable_share(ctx, user, share_id).await?;
+    
+    if let Some(name) = request.name {
+        share.name = Some(name).filter(|n| !n.is_empty());
+    }
+    if let Some(description) = request.description {
+        share.description = Some(description).filter(|d| !d.is_empty());
+    }
+    if request.view_settings.is_some() || request.inherit_view_settings.is_some() {
+        let defaults = ctx.policy_storage.get(&user.organization_id).await
+            .map_err(HttpResponse::from)?
+            .share_view_defaults;
+        match request.inherit_view_settings {
+            Some(true) => {
+                share.inherit_view_settings = true;
+                share.view_overrides = ShareViewSettingsPatch::default();
+            }
+            // What viewers see now becomes the share's own settings
+            Some(false) => {
+                share.view_settings = public_access::view_settings(&share, defaults.as_ref());
+                share.inherit_view_settings = false;
+                share.view_overrides = ShareViewSettingsPatch::default();
+            }
+            None => {}
+        }
+        // Shares following the defaults keep following them for fields the patch omits
+        if let Some(patch) = request.view_settings {
+            if share.inherit_view_settings {
+                share.view_overrides = share.view_overrides.then(&patch);
+            } else {
+                share.view_settings = patch.merge(&share.view_settings);
+            }
+        }
+        share.view_settings = public_access::view_settings(&share, defaults.as_ref());
+    }
+    
+    let updated = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    // Snapshots and cached public responses show the old settings
+    ctx.publish_share_change(user, &updated, ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+/// DELETE /api/shares/{id} - Move a share to the recycle bin (see [`crate::recycle_bin`])
+pub async fn delete_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+) -> Result<HttpResponse<()>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    
+    // Get share first to verify ownership
+    let mut share = ctx.share_storage.get(&user.organization_id, share_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Share not found"),
+            _ => HttpResponse::from(e),
+        })?;
+    
+    // Links stop working right away; the cleanup job deletes it after the retention window
+    share.deleted_at = Some(ctx.clock.now());
+    let share = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_share_change(user, &share, ChangeKind::Deleted).await;
+    
+    Ok(HttpResponse::ok(()))
+}
+
+/// POST /api/shares/{id}/restore - Take a share out of the recycle bin, links and key unchanged
+pub async fn restore_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    
+    let mut share = ctx.share_storage.get_deleted(&user.organization_id, share_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Share not in the recycle bin"),
+            _ => HttpResponse::from(e),
+        })?;
+    
+    share.deleted_at = None;
+    let restored = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_share_change(user, &restored, ChangeKind::Created).await;
+    
+    Ok(HttpResponse::ok(restored))
+}
+
+/// Reads of a share a renewal makes before giving up on concurrent writes
+const RENEWAL_ATTEMPTS: usize = 3;
+
+/// Extend a share by a year from now, conditional on the ETag it was read
+/// at, so two renewals (or a renewal and an edit) can't overwrite each
+/// other; `check` sees every read and may refuse. The share before and after.
+async fn renew_conditionally(
+    ctx: &HandlerContext,
+    organization_id: &str,
+    share_id: &str,
+    check: impl Fn(&ShareLink) -> Result<(), HttpResponse<ApiError>>,
+) -> Result<(ShareLink, ShareLink), HttpResponse<ApiError>> {
+    for _ in 0..RENEWAL_ATTEMPTS {
+        let (share, etag) = ctx.share_storage.get_tagged(organization_id, share_id).await
+            .map_err(|e| match e {
+                StorageError::NotFound(_) => HttpResponse::not_found("Share not found"),
+                _ => HttpResponse::from(e),
+            })?;
+        check(&share)?;
+        
+        let now = ctx.clock.now();
+        let mut renewed = share.clone();
+        renewed.expires_at = now + Duration::days(365);
+        renewed.renewed_at = Some(now);
+        renewed.ttl = Some((renewed.expires_at - now).num_seconds());
+        
+        if let Some(renewed) = ctx.share_storage.replace(renewed, &etag).await.map_err(HttpResponse::from)? {
+            return Ok((share, renewed));
+        }
+    }
+    Err(HttpResponse::conflict("Share changed while renewing it, try again"))
+}
+
+/// POST /api/shares/{id}/renew - Renew share TTL
+pub async fn renew_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    
+    // Extend expiration by 1 year from now
+    let now = ctx.clock.now();
+    let (_, updated) = renew_conditionally(ctx, &user.organization_id, share_id, |share| match share.is_expired(now) {
+        true => Err(HttpResponse::gone("Share has expired")),
+        false => Ok(()),
+    }).await?;
+    
+    ctx.publish_share_change(user, &updated, ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+/// Audit action for shares renewed from a reminder email link
+const AUDIT_ACTION_SHARE_RENEWED_BY_LINK: &str = "share.renewed_by_link";
+
+/// POST /api/shares/renew-by-token - Renew a share from an expiry reminder link, without sign-in
+pub async fn renew_share_by_token(
+    ctx: &HandlerContext,
+    request: RenewByTokenRequest,
+) -> Result<HttpResponse<RenewByTokenResponse>, HttpResponse<ApiError>> {
+    let signer = ctx.renewal_links.as_ref()
+        .ok_or_else(|| HttpResponse::service_unavailable("Renewal links are not configured"))?;
+    let now = ctx.clock.now();
+    let token = signer.verify(&request.token, now)
+        .map_err(|e| match e {
+            RenewalTokenError::Expired => HttpResponse::gone(&e.to_string()),
+            _ => HttpResponse::bad_request(&e.to_string()),
+        })?;
+    
+    let org = &token.organization_id;
+    let (previous, updated) = renew_conditionally(ctx, org, &token.share_id, |share| {
+        if share.is_expired(now) {
+            return Err(HttpResponse::gone("Share has expired"));
+        }
+        if !share.is_active {
+            return Err(HttpResponse::gone("Share has been deactivated"));
+        }
+        // Renewing moves the expiry, so each link works once
+        if !token.is_current(share) {
+            return Err(HttpResponse::conflict(&RenewalTokenError::AlreadyUsed.to_string()));
+        }
+        Ok(())
+    }).await?;
+    let previous_expires_at = previous.expires_at;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_SHARE_RENEWED_BY_LINK, Some(&ctx.pseudonymize(org, &updated.created_by)), Some(&updated.id))
+        .with_details(serde_json::json!({
+            "previousExpiresAt": previous_expires_at,
+            "expiresAt": updated.expires_at,
+            "nonce": token.nonce,
+        }));
+    ctx.audit_storage.record(entry).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.events.publish(DomainEvent::EntityChanged(EntityChange::new(
+        org,
+        EntityKind::Share,
+        &updated.id,
+        ChangeKind::Updated,
+        Some(&updated.created_by),
+    ).with_key(&updated.short_code))).await;
+    
+    Ok(HttpResponse::ok(RenewByTokenResponse {
+        share_id: updated.id,
+        share_name: updated.name,
+        expires_at: updated.expires_at,
+    }))
+}
+
+/// POST /api/shares/{id}/regenerate-key - Regenerate share key
+pub async fn regenerate_share_key(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+) -> Result<HttpResponse<CreateShareResponse>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    let mut share = get_writable_share(ctx, user, share_id).await?;
+    
+    // Generate new key
+    share.share_key = generate_share_key();
+    
+    let updated = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_share_change(user, &updated, ChangeKind::Updated).await;
+    
+    // Preview links signed with the old key stop working
+    let mut urls = ShareUrls::new(&ctx.base_url, &updated);
+    if let Some(signer) = &ctx.preview_links {
+        urls = urls.with_preview(signer, ctx.clock.now());
+    }
+    Ok(HttpResponse::ok(urls.response()))
+}
+
+/// PUT /api/shares/{id}/snapshot - Serve a share from a static CDN snapshot
+pub async fn set_share_snapshot(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+    request: SetShareSnapshotRequest,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    if request.enabled && ctx.snapshot_base_url.is_none() {
+        return Err(HttpResponse::service_unavailable("Snapshot publishing is not configured"));
+    }
+    
+    let mut share = get_writable_share(ctx, user, share_id).await?;
+    
+    share.publish_snapshot = request.enabled;
+    
+    let updated = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    // The snapshot publisher renders or removes the blob
+    ctx.publish_share_change(user, &updated, ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+/// PUT /api/shares/{id}/indexing - Allow or forbid search engine indexing, or follow the organization default
+pub async fn set_share_indexing(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+    request: SetShareIndexingRequest,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    let mut share = get_writable_share(ctx, user, share_id).await?;
+    
+    share.indexable = request.indexable;
+    
+    let updated = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    // Cached public responses carry the old robots header
+    ctx.publish_share_change(user, &updated, ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+/// Audit action recorded when a share's teams change
+const AUDIT_ACTION_SHARE_TEAMS: &str = "share.teams_updated";
+
+/// PUT /api/shares/{id}/teams - List the share only in the given Teams teams and channels (admin only)
+pub async fn set_share_teams(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+    request: SetTeamsScopeRequest,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let (team_ids, channel_ids) = teams_context::validate_scope(&request.team_ids, &request.channel_ids)
+        .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    
+    let org = &user.organization_id;
+    let mut share = get_writable_share(ctx, user, share_id).await?;
+    share.team_ids = team_ids;
+    share.channel_ids = channel_ids;
+    
+    let updated = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_SHARE_TEAMS, Some(&ctx.pseudonymize(org, &user.user_id)), Some(share_id))
+        .with_details(serde_json::json!({ "teamIds": updated.team_ids, "channelIds": updated.channel_ids }));
+    ctx.audit_storage.record(entry).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_share_change(user, &updated, ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+/// DELETE /api/shares/{id}/review - Clear a share's review flag after checking its traffic (admin only)
+pub async fn clear_share_review(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+) -> Result<HttpResponse<ShareLink>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let mut share = get_writable_share(ctx, user, share_id).await?;
+    
+    if share.review.take().is_none() {
+        return Ok(HttpResponse::ok(share));
+    }
+    
+    let updated = ctx.share_storage.update(share).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_share_change(user, &updated, ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+/// GET /api/shares/s/{shortCode} - View a Users-visibility share within the organization
+pub async fn access_user_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    short_code: &str,
+) -> Result<HttpResponse<AccessShareResponse>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    let denied = |e: PublicAccessError| Ok(HttpResponse::ok(public_access::denied(&e)));
+    
+    if !is_valid_short_code(short_code) {
+        return denied(PublicAccessError::InvalidCode);
+    }
+    
+    // Shares of other organizations and public shares are indistinguishable from missing ones
+    let mut share = match ctx.share_storage.get_by_short_code(short_code).await {
+        Ok(s) if s.organization_id == user.organization_id && s.visibility == ShareVisibility::Users => s,
+        Ok(_) | Err(StorageError::NotFound(_)) => return denied(PublicAccessError::NotFound),
+        Err(e) => return Err(e.into()),
+    };
+    
+    let now = ctx.clock.now();
+    if let Err(e) = public_access::check_live(&share, now) {
+        return denied(e);
+    }
+    
+    let _ = ctx.share_storage.increment_views(&share.organization_id, &share.id).await;
+    
+    // Viewers only see the shared layers they could see in the app
+    let visible = ctx.visible_layer_ids(user).await?;
+    share.layer_config.layer_ids.retain(|id| visible.contains(id));
+    let policy = public_policy(ctx, &share.organization_id).await;
+    share.view_settings = public_access::view_settings(&share, policy.as_ref().and_then(|p| p.share_view_defaults.as_ref()));
+    
+    let year = public_access::share_year(&share, now);
+    let activities = ctx.activity_storage.list_by_layers(&share.organization_id, &share.layer_config.layer_ids, Some(year))
+        .await
+        .map_err(HttpResponse::from)?;
+    let (share, activities, partial) = with_partner_layers(ctx, &ctx.storage_budget.start(), share, activities, year).await;
+    
+    let mut response = public_access::project(&share, activities, year, activity_order(policy.as_ref()));
+    response.partial = partial;
+    Ok(HttpResponse::ok(response))
+}
+
+// ============================================
+// Activity Handlers
+// ============================================
+
+/// POST /api/activities - Create an activity
+pub async fn create_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: CreateActivityRequest,
+) -> Result<HttpResponse<Activity>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    validate_activity_fields(&request.title, request.start_date, request.end_date, request.description.as_deref(), request.description_format, request.priority)?;
+    links::validate(&request.links, &ctx.link_domain_denylist).map_err(|e| HttpResponse::bad_request(&e))?;
+    let tags = normalize_tags(request.tags)?;
+    ensure_layer_writable(&request.scope)?;
+    let layer = get_layer_for_activity(ctx, user, &request.scope).await?;
+    ensure_period_open(ctx, user, &[request.end_date]).await?;
+    
+    let now = ctx.clock.now();
+    let activity = Activity {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: request.title,
+        start_date: request.start_date,
+        end_date: request.end_date,
+        activity_type: request.activity_type,
+        color: request.color,
+        highlight_color: request.highlight_color,
+        description: request.description,
+        description_format: request.description_format,
+        links: request.links,
+        tags,
+        priority: request.priority,
+        recurrence: request.recurrence,
+        scope_id: request.scope.clone(),
+        scope: request.scope,
+        organization_id: user.organization_id.clone(),
+        created_by: Some(user.user_id.clone()),
+        created_at: Some(now),
+        updated_at: Some(now),
+        edit_count: 0,
+        approval_status: submission_status(&layer, user),
+        approval_review: None,
+        split_from: None,
+        merged_from: Vec::new(),
+        deleted_at: None,
+        created_by_name: None,
+    };
+    recurrence::validate(&activity).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    
+    let saved = ctx.activity_storage.create(activity).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_activity_change(user, &saved.id, &[&saved.scope], ChangeKind::Created).await;
+    
+    Ok(HttpResponse::created(saved))
+}
+
+/// GET /api/activities - List activities (optionally by layers and year)
+pub async fn list_activities(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: ListActivitiesRequest,
+) -> Result<HttpResponse<Vec<Activity>>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let activities = visible_activities(ctx, user, request).await?;
+    Ok(HttpResponse::ok(ctx.with_creator_names(&user.organization_id, activities).await))
+}
+
+/// Activities matching a list request on the layers the user may see
+async fn visible_activities(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: ListActivitiesRequest,
+) -> Result<Vec<Activity>, HttpResponse<ApiError>> {
+    let activities = match request.layer_ids {
+        Some(ref layer_ids) => ctx.activity_storage.list_by_layers(&user.organization_id, layer_ids, request.year).await,
+        None => list_all_activities(ctx, &user.organization_id).await
+            .map(|all| all.into_iter()
+                .filter(|a| request.year.is_none_or(|y| a.start_date.year() <= y && a.end_date.year() >= y))
+                .collect()),
+    }.map_err(HttpResponse::from)?;
+    
+    let visible = ctx.visible_layer_ids(user).await?;
+    Ok(activities.into_iter().filter(|a| visible.contains(&a.scope)).collect())
+}
+
+/// GET /api/activities/search - Full-text activity search
+pub async fn search_activities(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SearchActivitiesRequest,
+) -> Result<HttpResponse<SearchActivitiesResponse>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let top = search::validate_request(&request).map_err(|e| HttpResponse::bad_request(&e))?;
+    let visible = ctx.visible_layer_ids(user).await?;
+    
+    if let Some(ref index) = ctx.activity_search {
+        match index.search(&user.organization_id, &request, top).await {
+            Ok(ids) => {
+                // Re-read hits so deleted or changed activities never come from a lagging index
+                let mut items = Vec::with_capacity(ids.len());
+                for id in ids {
+                    match ctx.activity_storage.get(&user.organization_id, &id).await {
+                        Ok(activity) if search::matches_filters(&activity, &request) && visible.contains(&activity.scope) => {
+                            items.push(activity)
+                        }
+                        Ok(_) | Err(StorageError::NotFound(_)) => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                let items = ctx.with_creator_names(&user.organization_id, items).await;
+                return Ok(HttpResponse::ok(SearchActivitiesResponse { items, source: SearchSource::Index }));
+            }
+            Err(e) => tracing::warn!("Activity search via {} failed, searching storage: {}", index.name(), e),
+        }
+    }
+    
+    let activities = match request.layer_ids {
+        Some(ref layer_ids) => ctx.activity_storage.list_by_layers(&user.organization_id, layer_ids, request.year).await,
+        None => list_all_activities(ctx, &user.organization_id).await,
+    }.map_err(HttpResponse::from)?;
+    let activities = activities.into_iter().filter(|a| visible.contains(&a.scope)).collect();
+    
+    let items = search::search_in_memory(activities, &request, top);
+    Ok(HttpResponse::ok(SearchActivitiesResponse {
+        items: ctx.with_creator_names(&user.organization_id, items).await,
+        source: SearchSource::Storage,
+    }))
+}
+
+/// PUT /api/activities/{id} - Update an activity
+pub async fn update_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+    request: UpdateActivityRequest,
+) -> Result<HttpResponse<Activity>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let mut activity = get_activity_or_404(ctx, user, activity_id).await?;
+    ensure_layer_writable(&activity.scope)?;
+    ensure_activity_unlocked(ctx, user, activity_id).await?;
+    let original_end = activity.end_date;
+    let original_scope = activity.scope.clone();
+    
+    if let Some(title) = request.title {
+        activity.title = title;
+    }
+    if let Some(start_date) = request.start_date {
+        activity.start_date = start_date;
+    }
+    if let Some(end_date) = request.end_date {
+        activity.end_date = end_date;
+    }
+    if let Some(activity_type) = request.activity_type {
+        activity.activity_type = activity_type;
+    }
+    if let Some(color) = request.color {
+        activity.color = color;
+    }
+    if let Some(highlight_color) = request.highlight_color {
+        activity.highlight_color = highlight_color;
+    }
+    if let Some(description) = request.description {
+        activity.description = Some(description).filter(|d| !d.is_empty());
+    }
+    if let Some(description_format) = request.description_format {
+        activity.description_format = description_format;
+    }
+    if let Some(activity_links) = request.links {
+        links::validate(&activity_links, &ctx.link_domain_denylist).map_err(|e| HttpResponse::bad_request(&e))?;
+        activity.links = activity_links;
+    }
+    if let Some(tags) = request.tags {
+        activity.tags = normalize_tags(tags)?;
+    }
+    if let Some(priority) = request.priority {
+        activity.priority = priority;
+    }
+    if let Some(recurrence) = request.recurrence {
+        activity.recurrence = recurrence;
+    }
+    if let Some(scope) = request.scope {
+        ensure_layer_writable(&scope)?;
+        activity.scope_id = scope.clone();
+        activity.scope = scope;
+    }
+    
+    validate_activity_fields(&activity.title, activity.start_date, activity.end_date, activity.description.as_deref(), activity.description_format, activity.priority)?;
+    recurrence::validate(&activity).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    ensure_period_open(ctx, user, &[original_end, activity.end_date]).await?;
+    
+    // Non-admin edits on controlled layers go back through review
+    let layer = get_layer_for_activity(ctx, user, &activity.scope).await?;
+    if submission_status(&layer, user) == ApprovalStatus::PendingApproval {
+        activity.approval_status = ApprovalStatus::PendingApproval;
+    }
+    activity.updated_at = Some(ctx.clock.now());
+    activity.edit_count = activity.edit_count.saturating_add(1);
+    
+    let updated = ctx.activity_storage.update(activity).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_activity_change(user, &updated.id, &[&original_scope, &updated.scope], ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+/// DELETE /api/activities/{id} - Move an activity to the recycle bin (see [`crate::recycle_bin`])
+pub async fn delete_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+) -> Result<HttpResponse<()>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let mut activity = get_activity_or_404(ctx, user, activity_id).await?;
+    ensure_layer_writable(&activity.scope)?;
+    ensure_activity_unlocked(ctx, user, activity_id).await?;
+    ensure_period_open(ctx, user, &[activity.end_date]).await?;
+    
+    activity.deleted_at = Some(ctx.clock.now());
+    let activity = ctx.activity_storage.update(activity).await
+        .map_err(HttpResponse::from)?;
+    
+    // Deleting before the start counts as a cancellation in planning analytics
+    if activity.start_date > ctx.clock.now() {
+        let org = &user.organization_id;
+        let entry = AuditEntry::new(org, AUDIT_ACTION_ACTIVITY_CANCELLED, Some(&ctx.pseudonymize(org, &user.user_id)), Some(activity_id))
+            .with_details(Cancellation::details(&activity));
+        if let Err(e) = ctx.audit_storage.record(entry).await {
+            tracing::warn!(error = %e, "Failed to record activity cancellation");
+        }
+    }
+    
+    ctx.publish_activity_change(user, activity_id, &[&activity.scope], ChangeKind::Deleted).await;
+    
+    Ok(HttpResponse::ok(()))
+}
+
+/// POST /api/activities/{id}/restore - Take an activity out of the recycle bin
+///
+/// Checked like a new activity on its layer: the layer must still exist and
+/// the period be open; non-admins' restores on controlled layers go back
+/// through review.
+pub async fn restore_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+) -> Result<HttpResponse<Activity>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let mut activity = ctx.activity_storage.get_deleted(&user.organization_id, activity_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Activity not in the recycle bin"),
+            _ => HttpResponse::from(e),
+        })?;
+    ensure_layer_writable(&activity.scope)?;
+    let layer = get_layer_for_activity(ctx, user, &activity.scope).await?;
+    ensure_period_open(ctx, user, &[activity.end_date]).await?;
+    
+    activity.deleted_at = None;
+    activity.updated_at = Some(ctx.clock.now());
+    if submission_status(&layer, user) == ApprovalStatus::PendingApproval {
+        activity.approval_status = ApprovalStatus::PendingApproval;
+    }
+    let restored = ctx.activity_storage.update(activity).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_activity_change(user, &restored.id, &[&restored.scope], ChangeKind::Created).await;
+    
+    Ok(HttpResponse::ok(restored))
+}
+
+/// POST /api/activities/{id}/split - Split an activity into two linked activities at a date
+pub async fn split_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+    request: SplitActivityRequest,
+) -> Result<HttpResponse<SplitActivityResponse>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let (activity, etag) = get_tagged_activity_or_404(ctx, user, activity_id).await?;
+    ensure_layer_writable(&activity.scope)?;
+    ensure_activity_unlocked(ctx, user, activity_id).await?;
+    
+    let (mut first, mut second) = split_merge::split(&activity, request.at, request.title, uuid::Uuid::new_v4().to_string(), ctx.clock.now())
+        .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    validate_activity_fields(&second.title, second.start_date, second.end_date, second.description.as_deref(), second.description_format, second.priority)?;
+    ensure_period_open(ctx, user, &[activity.end_date, first.end_date]).await?;
+    
+    // Both parts go back through review like any edit on a controlled layer
+    let layer = get_layer_for_activity(ctx, user, &activity.scope).await?;
+    if submission_status(&layer, user) == ApprovalStatus::PendingApproval {
+        first.approval_status = ApprovalStatus::PendingApproval;
+        second.approval_status = ApprovalStatus::PendingApproval;
+    }
+    second.created_by = Some(user.user_id.clone());
+    
+    let org = &user.organization_id;
+    let changes = ActivityChanges {
+        create: vec![second.clone()],
+        update: vec![first.clone()],
+        etags: HashMap::from([(first.id.clone(), etag)]),
+        ..Default::default()
+    };
+    ctx.activity_storage.apply_changes(org, changes).await
+        .map_err(HttpResponse::from)?;
+    
+    let entry = AuditEntry::new(org, split_merge::AUDIT_ACTION_SPLIT, Some(&ctx.pseudonymize(org, &user.user_id)), Some(activity_id))
+        .with_details(serde_json::json!({ "at": request.at, "before": activity, "created": second.id }));
+    if let Err(e) = ctx.audit_storage.record(entry).await {
+        tracing::warn!(error = %e, "Failed to record activity split");
+    }
+    
+    ctx.publish_activity_change(user, &first.id, &[&first.scope], ChangeKind::Updated).await;
+    ctx.publish_activity_change(user, &second.id, &[&second.scope], ChangeKind::Created).await;
+    
+    Ok(HttpResponse::created(SplitActivityResponse { first, second }))
+}
+
+/// POST /api/activities/merge - Merge one activity into another and delete it
+pub async fn merge_activities(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: MergeActivitiesRequest,
+) -> Result<HttpResponse<Activity>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let (target, target_etag) = get_tagged_activity_or_404(ctx, user, &request.target_id).await?;
+    let (source, source_etag) = get_tagged_activity_or_404(ctx, user, &request.source_id).await?;
+    ensure_layer_writable(&target.scope)?;
+    ensure_layer_writable(&source.scope)?;
+    
+    let mut merged = split_merge::merge(&target, &source, ctx.clock.now())
+        .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    for id in [&target.id, &source.id] {
+        ensure_activity_unlocked(ctx, user, id).await?;
+    }
+    merged.tags = normalize_tags(std::mem::take(&mut merged.tags))?;
+    links::validate(&merged.links, &ctx.link_domain_denylist).map_err(|e| HttpResponse::bad_request(&e))?;
+    validate_activity_fields(&merged.title, merged.start_date, merged.end_date, merged.description.as_deref(), merged.description_format, merged.priority)?;
+    ensure_period_open(ctx, user, &[target.end_date, source.end_date, merged.end_date]).await?;
+    
+    let layer = get_layer_for_activity(ctx, user, &merged.scope).await?;
+    if submission_status(&layer, user) == ApprovalStatus::PendingApproval {
+        merged.approval_status = ApprovalStatus::PendingApproval;
+    }
+    
+    let org = &user.organization_id;
+    let changes = ActivityChanges {
+        update: vec![merged.clone()],
+        delete: vec![source.id.clone()],
+        etags: HashMap::from([(target.id.clone(), target_etag), (source.id.clone(), source_etag)]),
+        ..Default::default()
+    };
+    ctx.activity_storage.apply_changes(org, changes).await
+        .map_err(HttpResponse::from)?;
+    
+    // The source survives only here
+    let entry = AuditEntry::new(org, split_merge::AUDIT_ACTION_MERGED, Some(&ctx.pseudonymize(org, &user.user_id)), Some(&target.id))
+        .with_details(serde_json::json!({ "target": target, "source": source }));
+    if let Err(e) = ctx.audit_storage.record(entry).await {
+        tracing::warn!(error = %e, "Failed to record activity merge");
+    }
+    
+    ctx.publish_activity_change(user, &merged.id, &[&target.scope, &merged.scope], ChangeKind::Updated).await;
+    ctx.publish_activity_change(user, &source.id, &[&source.scope], ChangeKind::Deleted).await;
+    
+    Ok(HttpResponse::ok(merged))
+}
+
+/// POST /api/activities/{id}/lock - Acquire or renew an edit lock
+pub async fn lock_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+) -> Result<HttpResponse<EditLock>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    get_activity_or_404(ctx, user, activity_id).await?;
+    
+    let lock = new_lock(&user.organization_id, activity_id, &user.user_id, user.display_name.as_deref(), ctx.clock.now());
+    let lock = ctx.locks.acquire(lock).await
+        .map_err(lock_error_response)?;
+    
+    ctx.events.publish(DomainEvent::ActivityLocked(lock.clone())).await;
+    
+    Ok(HttpResponse::ok(lock))
+}
+
+/// DELETE /api/activities/{id}/lock - Release an edit lock held by the caller
+pub async fn unlock_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+) -> Result<HttpResponse<()>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let lock = ctx.locks.get(&user.organization_id, activity_id).await
+        .map_err(lock_error_response)?;
+    
+    let released = ctx.locks.release(&user.organization_id, activity_id, &user.user_id).await
+        .map_err(lock_error_response)?;
+    
+    if let (true, Some(lock)) = (released, lock) {
+        ctx.events.publish(DomainEvent::ActivityUnlocked(lock)).await;
+    }
+    
+    Ok(HttpResponse::ok(()))
+}
+
+// ============================================
+// Approval Handlers
+// ============================================
+
+/// GET /api/approvals - Activities waiting for review (admin only)
+pub async fn list_pending_approvals(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<Vec<Activity>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let mut pending: Vec<Activity> = list_all_activities(ctx, &user.organization_id).await
+        .map_err(HttpResponse::from)?
+        .into_iter()
+        .filter(|a| a.approval_status == ApprovalStatus::PendingApproval)
+        .collect();
+    
+    // Oldest submissions first
+    pending.sort_by_key(|a| a.updated_at.or(a.created_at));
+    
+    Ok(HttpResponse::ok(pending))
+}
+
+/// POST /api/approvals/{id}/approve - Approve a pending activity (admin only)
+pub async fn approve_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+    request: ApprovalDecisionRequest,
+) -> Result<HttpResponse<Activity>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    review_activity(ctx, user, activity_id, ApprovalStatus::Approved, request.comment).await
+}
+
+/// POST /api/approvals/{id}/reject - Reject a pending activity (admin only)
+pub async fn reject_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+    request: ApprovalDecisionRequest,
+) -> Result<HttpResponse<Activity>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    review_activity(ctx, user, activity_id, ApprovalStatus::Rejected, request.comment).await
+}
+
+/// Record a review decision on a pending activity
+async fn review_activity(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    activity_id: &str,
+    decision: ApprovalStatus,
+    comment: Option<String>,
+) -> Result<HttpResponse<Activity>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    if comment.as_ref().is_some_and(|c| c.len() > 2000) {
+        return Err(HttpResponse::bad_request("Comment too long (max 2000 characters)"));
+    }
+    
+    let mut activity = get_activity_or_404(ctx, user, activity_id).await?;
+    if activity.approval_status != ApprovalStatus::PendingApproval {
+        return Err(HttpResponse::conflict("Activity is not pending approval"));
+    }
+    
+    let now = ctx.clock.now();
+    activity.approval_status = decision;
+    activity.approval_review = Some(ApprovalReview {
+        reviewed_by: user.user_id.clone(),
+        reviewed_at: now,
+        comment: comment.filter(|c| !c.trim().is_empty()),
+    });
+    activity.updated_at = Some(now);
+    
+    let updated = ctx.activity_storage.update(activity).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_activity_change(user, &updated.id, &[&updated.scope], ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+// ============================================
+// Layer Handlers
+// ============================================
+
+/// GET /api/layers - Layers visible to the caller
+pub async fn list_layers(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<Vec<Layer>>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    let mut layers = ctx.visible_layers(user).await?;
+    layers.sort_by_key(|l| l.ring_index);
+    
+    Ok(HttpResponse::ok(layers))
+}
+
+/// Audit action recorded when a layer's teams change
+const AUDIT_ACTION_LAYER_TEAMS: &str = "layer.teams_updated";
+
+/// PUT /api/layers/{id}/teams - Show the layer only in the given Teams teams and channels (admin only)
+pub async fn set_layer_teams(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    layer_id: &str,
+    request: SetTeamsScopeRequest,
+) -> Result<HttpResponse<Layer>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    ensure_layer_writable(layer_id)?;
+    let (team_ids, channel_ids) = teams_context::validate_scope(&request.team_ids, &request.channel_ids)
+        .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    
+    let org = &user.organization_id;
+    let mut layer = ctx.layer_storage.get(org, layer_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Layer not found"),
+            _ => HttpResponse::from(e),
+        })?;
+    layer.team_ids = team_ids;
+    layer.channel_ids = channel_ids;
+    layer.updated_at = Some(ctx.clock.now());
+    
+    let updated = ctx.layer_storage.update(layer).await
+        .map_err(HttpResponse::from)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_LAYER_TEAMS, Some(&ctx.pseudonymize(org, &user.user_id)), Some(layer_id))
+        .with_details(serde_json::json!({ "teamIds": updated.team_ids, "channelIds": updated.channel_ids }));
+    ctx.audit_storage.record(entry).await
+        .map_err(HttpResponse::from)?;
+    
+    ctx.publish_change(user, EntityKind::Layer, layer_id, ChangeKind::Updated).await;
+    
+    Ok(HttpResponse::ok(updated))
+}
+
+// ============================================
+// Activity Type Handlers
+// ============================================
+
+/// Audit action recorded when an activity type is deleted
+const AUDIT_ACTION_ACTIVITY_TYPE_DELETED: &str = "activity_type.deleted";
+
+/// GET /api/activity-types/{key}/usage - Activities using a type, per year and layer (admin or reporting role)
+pub async fn activity_type_usage(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    key: &str,
+) -> Result<HttpResponse<ActivityTypeUsage>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Reports)?;
+    
+    let org = &user.organization_id;
+    // Built-in types are usable before the organization customizes them
+    match ctx.activity_type_storage.get(org, key).await {
+        Ok(_) => {}
+        Err(StorageError::NotFound(_)) if activity_types::parse_key(key).is_some() => {}
+        Err(StorageError::NotFound(_)) => return Err(HttpResponse::not_found("Activity type not found")),
+        Err(e) => return Err(e.into()),
+    }
+    
+    let activities = list_all_activities(ctx, org).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(activity_types::usage(key, &activities)))
+}
+
+/// DELETE /api/activity-types/{key} - Delete an activity type (admin only)
+///
+/// Refused while activities use the type, unless `?reassignTo=` names a
+/// built-in type to move them to. If a write fails, activities already moved
+/// are put back and the type is kept. With `?dry_run=true`, returns the plan.
+pub async fn delete_activity_type(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    key: &str,
+    query: DeleteActivityTypeQuery,
+) -> Result<HttpResponse<DryRunOr<ActivityTypeDeletion>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    
+    let config = ctx.activity_type_storage.get(org, key).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Activity type not found"),
+            _ => to_error(e),
+        })?;
+    storage::ensure_deletable(&config)
+        .map_err(|_| HttpResponse::conflict("System activity types can't be deleted"))?;
+    
+    let activities = list_all_activities(ctx, org).await.map_err(to_error)?;
+    let used = activity_types::using(key, &activities);
+    let target = match &query.reassign_to {
+        Some(target) => Some(activity_types::parse_key(target)
+            .filter(|_| target != key)
+            .ok_or_else(|| HttpResponse::bad_request("reassignTo must be another built-in activity type"))?),
+        None if !used.is_empty() => {
+            return Err(HttpResponse::conflict(&format!(
+                "Activity type is used by {} activities; reassign them with reassignTo", used.len()
+            )));
+        }
+        None => None,
+    };
+    
+    let mut plan = ExecutionPlan::new();
+    plan.add(PlannedEntity::Activity, PlannedAction::Update, used.iter().map(|a| a.id.as_str()));
+    plan.add(PlannedEntity::ActivityType, PlannedAction::Delete, [key]);
+    if query.dry_run {
+        return Ok(HttpResponse::ok(DryRunOr::DryRun(plan.report())));
+    }
+    
+    let now = ctx.clock.now();
+    let mut moved: Vec<&Activity> = Vec::new();
+    let mut result = Ok(());
+    if let Some(activity_type) = &target {
+        for original in &used {
+            let mut activity = (*original).clone();
+            activity.activity_type = activity_type.clone();
+            activity.updated_at = Some(now);
+            match ctx.activity_storage.update(activity).await {
+                Ok(_) => moved.push(original),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+    }
+    if result.is_ok() {
+        result = ctx.activity_type_storage.delete(org, key).await;
+    }
+    if let Err(e) = result {
+        for original in moved {
+            if let Err(undo) = ctx.activity_storage.update(original.clone()).await {
+                tracing::error!("Failed to restore type of activity {} after deleting {} failed: {}", original.id, key, undo);
+            }
+        }
+        return Err(to_error(e));
+    }
+    
+    let deletion = ActivityTypeDeletion {
+        key: key.to_string(),
+        reassigned_to: query.reassign_to.filter(|_| !moved.is_empty()),
+        reassigned: moved.len() as u64,
+    };
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_ACTIVITY_TYPE_DELETED, Some(&ctx.pseudonymize(org, &user.user_id)), Some(key))
+        .with_details(serde_json::json!({ "reassignedTo": deletion.reassigned_to, "reassigned": deletion.reassigned }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    for activity in moved {
+        ctx.publish_activity_change(user, &activity.id, &[&activity.scope], ChangeKind::Updated).await;
+    }
+    ctx.publish_change(user, EntityKind::ActivityType, key, ChangeKind::Deleted).await;
+    
+    Ok(HttpResponse::ok(DryRunOr::Executed(deletion)))
+}
+
+// ============================================
+// User Settings Handlers
+// ============================================
+
+/// Maximum number of reminder lead times per user
+const MAX_REMINDER_LEAD_TIMES: usize = 5;
+
+/// GET /api/user-settings/notifications - Get the caller's notification preferences
+pub async fn get_notification_preferences(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<NotificationPreferences>, HttpResponse<ApiError>> {
+    authorize_personal(user)?;
+    let settings = ctx.user_settings_storage.get(&user.organization_id, &user.user_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(settings.notifications))
+}
+
+/// PUT /api/user-settings/notifications - Replace the caller's notification preferences
+pub async fn update_notification_preferences(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    mut request: NotificationPreferences,
+) -> Result<HttpResponse<NotificationPreferences>, HttpResponse<ApiError>> {
+    authorize_personal(user)?;
+    if request.reminder_lead_days.len() > MAX_REMINDER_LEAD_TIMES {
+        return Err(HttpResponse::bad_request("Too many reminder lead times (max 5)"));
+    }
+    if request.reminder_lead_days.iter().any(|&d| d > 365) {
+        return Err(HttpResponse::bad_request("Reminder lead time must be at most 365 days"));
+    }
+    if request.quiet_hours.as_ref().is_some_and(|q| q.utc_offset_minutes.abs() > 14 * 60) {
+        return Err(HttpResponse::bad_request("Invalid UTC offset for quiet hours"));
+    }
+    
+    request.reminder_lead_days.sort_unstable();
+    request.reminder_lead_days.dedup();
+    let mut channels = Vec::with_capacity(request.channels.len());
+    for channel in request.channels.drain(..) {
+        if !channels.contains(&channel) {
+            channels.push(channel);
+        }
+    }
+    request.channels = channels;
+    
+    let mut settings = ctx.user_settings_storage.get(&user.organization_id, &user.user_id).await
+        .map_err(HttpResponse::from)?;
+    settings.notifications = request;
+    settings.updated_at = ctx.clock.now();
+    
+    let saved = ctx.user_settings_storage.upsert(settings).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(saved.notifications))
+}
+
+// ============================================
+// Directory Handlers
+// ============================================
+
+/// Directory searches per user and minute
+pub const DIRECTORY_SEARCHES_PER_MINUTE: u32 = 30;
+
+/// GET /api/directory/search - People/group picker for the caller's tenant
+pub async fn search_directory(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: DirectorySearchRequest,
+) -> Result<HttpResponse<Vec<DirectoryEntry>>, HttpResponse<ApiError>> {
+    authorize_personal(user)?;
+    let Some(ref directory) = ctx.directory else {
+        return Err(HttpResponse::service_unavailable("Directory search is not configured"));
+    };
+    let (query, top) = directory::validate_search(&request.q, request.top)
+        .map_err(|e| HttpResponse::bad_request(&e))?;
+    
+    let rate = ctx.directory_search_limiter.check_at(&format!("{}:{}", user.organization_id, user.user_id), ctx.clock.now())
+        .map_err(|limited| HttpResponse::too_many_requests("Too many directory searches", limited.retry_after_seconds)
+            .with_rate_limit(&limited.status))?;
+    
+    // The organization ID is the tenant from the validated token, never client input
+    let entries = directory.search(&user.organization_id, query, top).await
+        .map_err(|e| {
+            tracing::warn!("Directory search failed: {}", e);
+            HttpResponse::service_unavailable("Directory search is temporarily unavailable")
+        })?;
+    
+    Ok(HttpResponse::ok(entries).with_rate_limit(&rate))
+}
+
+/// GET /api/admin/integrations/graph/status - Probe each Graph permission (admin only)
+pub async fn graph_integration_status(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<GraphIntegrationStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let mut permissions = Vec::with_capacity(directory::GRAPH_PERMISSIONS.len());
+    for (permission, used_for) in directory::GRAPH_PERMISSIONS {
+        let (state, detail) = match ctx.directory {
+            None => (PermissionState::Unknown, Some("Graph is not configured on the server".to_string())),
+            Some(ref directory) => match directory.permission_granted(&user.organization_id, permission).await {
+                Ok(true) => (PermissionState::Granted, None),
+                Ok(false) => (PermissionState::Missing, Some("Grant admin consent for this permission".to_string())),
+                Err(e @ DirectoryError::ConsentRequired(_)) => (PermissionState::Missing, Some(e.to_string())),
+                Err(e) => (PermissionState::Unknown, Some(e.to_string())),
+            },
+        };
+        permissions.push(GraphPermissionStatus {
+            permission: permission.to_string(),
+            used_for: used_for.to_string(),
+            state,
+            detail,
+            docs_url: directory::permission_docs_url(permission),
+        });
+    }
+    
+    Ok(HttpResponse::ok(GraphIntegrationStatus {
+        configured: ctx.directory.is_some(),
+        healthy: permissions.iter().all(|p| p.state == PermissionState::Granted),
+        permissions,
+        admin_consent_url: directory::admin_consent_url(&user.organization_id, ctx.token_validator.audience()),
+    }))
+}
+
+// ============================================
+// Exports
+// ============================================
+
+/// Export settings from the query, on top of redactions the endpoint always applies
+///
+/// Without `locale` in the query, the caller's own locale applies before the organization's.
+async fn export_settings(ctx: &HandlerContext, user: &UserContext, query: &ExportQuery, redaction: Redaction) -> Result<ExportSettings, HttpResponse<ApiError>> {
+    let calendar = calendar_settings(ctx, &user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    let mut settings = ExportSettings::from_query(query, &calendar).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    if query.locale.is_none() {
+        let user_settings = ctx.user_settings_storage.get(&user.organization_id, &user.user_id).await
+            .map_err(HttpResponse::from)?;
+        settings.locale = i18n::resolve(Some(&user_settings), &calendar);
+    }
+    settings.redaction = settings.redaction.union(redaction);
+    Ok(settings)
+}
+
+/// Rendered export with download headers
+fn export_response(exporter: &dyn Exporter, document: ExportDocument, settings: &ExportSettings, file_stem: &str) -> Result<HttpResponse<Vec<u8>>, HttpResponse<ApiError>> {
+    let (bytes, continuation_token) = export::render(exporter, document, settings)
+        .map_err(|e| HttpResponse::internal_error(&e.to_string()))?;
+    
+    let mut response = HttpResponse::ok(bytes)
+        .with_header("Content-Type", &exporter.content_type())
+        .with_header("Content-Disposition", &format!("attachment; filename=\"{}\"", export::file_name(file_stem, exporter)))
+        .with_header("Vary", "Accept");
+    if let Some(token) = continuation_token {
+        response = response.with_header(export::CONTINUATION_HEADER, &token);
+    }
+    Ok(response)
+}
+
+/// GET /api/shares/{id}/export - The share's wheel in the format picked by the `Accept` header
+///
+/// Contains what viewers of the share see: approved activities on the shared
+/// layers, narrowed to the layers the caller can see. People are always
+/// redacted, as on the public page.
+pub async fn export_share(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    share_id: &str,
+    query: ExportQuery,
+    accept: Option<&str>,
+) -> Result<HttpResponse<Vec<u8>>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Shares)?;
+    let exporter = ctx.exporters.negotiate(accept).map_err(|e| HttpResponse::not_acceptable(&e.to_string()))?;
+    let settings = export_settings(ctx, user, &query, Redaction { people: true, ..Default::default() }).await?;
+    
+    let share = ctx.share_storage.get(&user.organization_id, share_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Share not found"),
+            _ => HttpResponse::from(e),
+        })?;
+    
+    let layers: Vec<Layer> = ctx.visible_layers(user).await?.into_iter()
+        .filter(|l| share.layer_config.layer_ids.contains(&l.id))
+        .collect();
+    let layer_ids: Vec<String> = layers.iter().map(|l| l.id.clone()).collect();
+    
+    let now = ctx.clock.now();
+    let year = public_access::share_year(&share, now);
+    let activities = ctx.activity_storage.list_by_layers(&share.organization_id, &layer_ids, Some(year)).await
+        .map_err(HttpResponse::from)?
+        .into_iter()
+        .filter(|a| a.approval_status == ApprovalStatus::Approved)
+        .collect();
+    
+    let document = export::document(&i18n::share_title(settings.locale, &share), Some(year), layers, activities, now);
+    export_response(exporter, document, &settings, &format!("share-{}", share.short_code))
+}
+
+/// GET /api/activities/export - Activities, filtered like `GET /api/activities`, in the format picked by the `Accept` header
+pub async fn export_activities(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: ListActivitiesRequest,
+    query: ExportQuery,
+    accept: Option<&str>,
+) -> Result<HttpResponse<Vec<u8>>, HttpResponse<ApiError>> {
+    // Reporting service principals get the export without free text or people
+    let reporting = authorization::is_reporting_only(user);
+    let redaction = if reporting {
+        Redaction { descriptions: true, links: true, people: true }
+    } else {
+        Redaction::default()
+    };
+    authorize(user, if reporting { EndpointFamily::Reports } else { EndpointFamily::Activities })?;
+    let exporter = ctx.exporters.negotiate(accept).map_err(|e| HttpResponse::not_acceptable(&e.to_string()))?;
+    let settings = export_settings(ctx, user, &query, redaction).await?;
+    
+    let year = request.year;
+    let activities = visible_activities(ctx, user, request).await?;
+    let activities = ctx.with_creator_names(&user.organization_id, activities).await;
+    let layers = ctx.visible_layers(user).await?;
+    
+    let now = ctx.clock.now();
+    let file_stem = year.map_or_else(|| "activities".to_string(), |y| format!("activities-{}", y));
+    let document = export::document(settings.locale.default_title(), year, layers, activities, now);
+    export_response(exporter, document, &settings, &file_stem)
+}
+
+// ============================================
+// Organization Off-boarding
+// ============================================
+
+/// Every write a purge makes, in order
+async fn purge_plan(ctx: &HandlerContext, org: &str) -> Result<ExecutionPlan, StorageError> {
+    let mut plan = ExecutionPlan::new();
+    
+    // Revoke shares first so public links stop working even if a later step fails
+    let shares = list_all_shares(ctx, org).await?;
+    plan.add(PlannedEntity::Share, PlannedAction::Revoke, shares.iter().filter(|s| s.is_active).map(|s| s.id.clone()));
+    // Items in the recycle bin go too
+    let binned_shares = ctx.share_storage.list_deleted(org).await?;
+    plan.add(PlannedEntity::Share, PlannedAction::Delete, shares.into_iter().chain(binned_shares).map(|s| s.id));
+    
+    let binned_activities = ctx.activity_storage.list_deleted(org).await?;
+    plan.add(PlannedEntity::Activity, PlannedAction::Delete, list_all_activities(ctx, org).await?.into_iter().chain(binned_activities).map(|a| a.id));
+    plan.add(PlannedEntity::Layer, PlannedAction::Delete, ctx.layer_storage.list(org).await?.into_iter().map(|l| l.id));
+    plan.add(PlannedEntity::ActivityType, PlannedAction::Delete, ctx.activity_type_storage.list(org).await?.into_iter().map(|t| t.key));
+    plan.add(PlannedEntity::UserSettings, PlannedAction::Delete, ctx.user_settings_storage.list(org).await?.into_iter().map(|s| s.user_id));
+    
+    // The default policy is never stored
+    let policy = ctx.policy_storage.get(org).await?;
+    plan.add(PlannedEntity::Policy, PlannedAction::Delete, policy.updated_at.map(|_| org.to_string()));
+    
+    if let Some(snapshots) = &ctx.org_snapshots {
+        plan.add(PlannedEntity::OrganizationSnapshot, PlannedAction::Delete, snapshots.list(org).await?.into_iter().map(|s| s.id));
+    }
+    
+    let mut audit_ids = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let page = ctx.audit_storage.list(org, QueryOptions {
+            continuation_token,
+            ..Default::default()
+        }).await?;
+        
+        audit_ids.extend(page.items.into_iter().map(|e| e.id));
+        
+        match page.continuation_token {
+            Some(token) => continuation_token = Some(token),
+            None => break,
+        }
+    }
+    plan.add(PlannedEntity::AuditEntry, PlannedAction::Delete, audit_ids);
+    
+    Ok(plan)
+}
+
+/// Entities a purge plan deletes, per type
+fn purge_summary(plan: &ExecutionPlan) -> PurgeSummary {
+    let deleted = |entity| plan.count(entity, PlannedAction::Delete);
+    PurgeSummary {
+        shares: deleted(PlannedEntity::Share),
+        activities: deleted(PlannedEntity::Activity),
+        layers: deleted(PlannedEntity::Layer),
+        activity_types: deleted(PlannedEntity::ActivityType),
+        user_settings: deleted(PlannedEntity::UserSettings),
+        audit_entries: deleted(PlannedEntity::AuditEntry),
+        organization_snapshots: deleted(PlannedEntity::OrganizationSnapshot),
+    }
+}
+
+/// Signer of purge confirmation tokens, from the key ring
+fn confirmation_signer(ctx: &HandlerContext) -> Result<ConfirmationSigner, HttpResponse<ApiError>> {
+    let keys = ctx.signing_keys.as_ref()
+        .ok_or_else(|| HttpResponse::service_unavailable("Purge confirmation is not configured"))?;
+    Ok(ConfirmationSigner::new(keys.clone()))
+}
+
+/// POST /api/admin/organization/purge-confirmation - Issue a purge confirmation token (admin only)
+///
+/// The token is signed and only confirms the plan previewed here (see [`crate::offboarding`]).
+pub async fn request_purge_confirmation(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<PurgeConfirmation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let signer = confirmation_signer(ctx)?;
+    
+    let org = &user.organization_id;
+    let plan = purge_plan(ctx, org).await.map_err(HttpResponse::from)?;
+    
+    let token = ConfirmationToken::new(org, &user.user_id, &plan, ctx.clock.now());
+    let signed = signer.sign(&token).map_err(|e| HttpResponse::internal_error(&e.to_string()))?;
+    
+    Ok(HttpResponse::ok(PurgeConfirmation {
+        confirmation_token: signed,
+        expires_at: token.expires_at,
+        pending: purge_summary(&plan),
+    }))
+}
+
+/// DELETE /api/admin/organization - Revoke all shares and delete every entity for the tenant (admin only)
+///
+/// With `?dry_run=true`, returns the plan instead and needs no confirmation token.
+pub async fn purge_organization(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: PurgeOrganizationRequest,
+    query: DryRunQuery,
+) -> Result<HttpResponse<DryRunOr<DeletionCertificate>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    
+    if query.dry_run {
+        let plan = purge_plan(ctx, org).await.map_err(to_error)?;
+        return Ok(HttpResponse::ok(DryRunOr::DryRun(plan.report())));
+    }
+    
+    let signer = confirmation_signer(ctx)?;
+    let plan = purge_plan(ctx, org).await.map_err(to_error)?;
+    signer.verify(&request.confirmation_token, org, &user.user_id, &plan, ctx.clock.now())
+        .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    
+    let started_at = ctx.clock.now();
+    
+    tracing::warn!("Purging all data for organization {} (requested by {})", org, user.user_id);
+    
+    let mut certificate = DeletionCertificate {
+        id: uuid::Uuid::new_v4().to_string(),
+        organization_id: org.clone(),
+        requested_by: ctx.pseudonymize(org, &user.user_id),
+        started_at,
+        completed_at: started_at,
+        shares_revoked: 0,
+        deleted: PurgeSummary::default(),
+    };
+    let purger = ctx.purger();
+    for step in plan.steps() {
+        purger.run(org, step.entity, step.action, &step.ids, &user.user_id, &mut certificate).await.map_err(to_error)?;
+    }
+    // Background exports and imports hold the organization's data too
+    if let Some(operations) = &ctx.operations {
+        operations.delete_organization(org, None).await.map_err(to_error)?;
+    }
+    certificate.completed_at = ctx.clock.now();
+    
+    // The certificate is the only record kept for the organization
+    let entry = AuditEntry::new(org, AUDIT_ACTION_PURGED, Some(&certificate.requested_by), Some(&certificate.id))
+        .with_details(serde_json::to_value(&certificate).unwrap_or_default());
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    tracing::warn!("Organization {} purged: {:?}", org, certificate.deleted);
+    
+    Ok(HttpResponse::ok(DryRunOr::Executed(certificate)))
+}
+
+// ============================================
+// Organization Backups
+// ============================================
+
+/// Audit action recorded when an organization is exported
+pub(crate) const AUDIT_ACTION_EXPORTED: &str = "organization.exported";
+
+/// Audit action recorded when an archive is imported
+pub(crate) const AUDIT_ACTION_IMPORTED: &str = "organization.imported";
+
+/// GET /api/admin/export - Everything the organization has, as one JSON archive (admin only)
+///
+/// Deprecated in favor of `POST /api/admin/export/background`, which returns the same archive.
+pub async fn export_organization(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<BackupArchive>, HttpResponse<ApiError>> {
+    let result = serve_export_organization(ctx, user).await;
+    deprecated(ctx, "GET", "/api/admin/export", user, result)
+}
+
+async fn serve_export_organization(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<BackupArchive>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+
+    let archive = organization_archive(ctx, org).await.map_err(to_error)?;
+
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let entry = AuditEntry::new(org, AUDIT_ACTION_EXPORTED, Some(&actor), None)
+        .with_details(serde_json::json!({
+            "layers": archive.layers.len(),
+            "activities": archive.activities.len(),
+            "shares": archive.shares.len(),
+        }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+
+    let file_name = backup::file_name(org, archive.exported_at);
+    Ok(HttpResponse::ok(archive)
+        .with_header("Content-Disposition", &format!("attachment; filename=\"{}\"", file_name)))
+}
+
+/// POST /api/admin/import - Write an archive into the organization (admin only)
+///
+/// `?on_conflict=skip|overwrite|new-ids` decides what happens to entities already stored.
+/// Entities must pass the rules of the create handlers, and every one written
+/// is published as a change, like an edit in the app.
+pub async fn import_organization(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    mut archive: BackupArchive,
+    query: ImportQuery,
+) -> Result<HttpResponse<ImportSummary>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    backup::validate(&archive).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let strategy = query.on_conflict;
+
+    let source = archive.organization_id.clone();
+    backup::rehome(&mut archive, org);
+    if strategy == ConflictStrategy::NewIds {
+        backup::assign_new_ids(&mut archive);
+    }
+    validate_import(ctx, org, &mut archive).await?;
+
+    tracing::info!("Importing archive of organization {} into {} ({:?})", source, org, strategy);
+    let summary = write_archive(ctx, user, archive, strategy).await.map_err(to_error)?;
+
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let entry = AuditEntry::new(org, AUDIT_ACTION_IMPORTED, Some(&actor), None)
+        .with_details(serde_json::json!({
+            "sourceOrganizationId": source,
+            "summary": summary,
+        }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+
+    Ok(HttpResponse::ok(summary))
+}
+
+/// Everything the organization has, as an archive
+async fn organization_archive(ctx: &HandlerContext, org: &str) -> Result<BackupArchive, StorageError> {
+    let policy = ctx.policy_storage.get(org).await?;
+    Ok(BackupArchive {
+        format_version: backup::FORMAT_VERSION,
+        organization_id: org.to_string(),
+        exported_at: ctx.clock.now(),
+        // The default policy is never stored
+        policy: policy.updated_at.is_some().then_some(policy),
+        activity_types: ctx.activity_type_storage.list(org).await?,
+        layers: ctx.layer_storage.list(org).await?,
+        activities: list_all_activities(ctx, org).await?,
+        shares: list_all_shares(ctx, org).await?,
+        user_settings: ctx.user_settings_storage.list(org).await?,
+    })
+}
+
+/// Hold a rehomed archive to the rules of the create handlers
+///
+/// Activities and shares must refer to layers in the archive or already
+/// stored. Tags are normalized as on create; the first entity breaking a
+/// rule fails the import before anything is written.
+async fn validate_import(ctx: &HandlerContext, org: &str, archive: &mut BackupArchive) -> Result<(), HttpResponse<ApiError>> {
+    let mut layer_ids: HashSet<String> = ctx.layer_storage.list(org).await
+        .map_err(HttpResponse::from)?
+        .into_iter()
+        .map(|l| l.id)
+        .collect();
+    layer_ids.extend(archive.layers.iter().map(|l| l.id.clone()));
+
+    for activity in &mut archive.activities {
+        let invalid = |message: &str| HttpResponse::bad_request(&format!("Activity {}: {}", activity.id, message));
+        validate_activity_fields(&activity.title, activity.start_date, activity.end_date, activity.description.as_deref(), activity.description_format, activity.priority)
+            .map_err(|e| invalid(&e.body.message))?;
+        links::validate(&activity.links, &ctx.link_domain_denylist).map_err(|e| invalid(&e))?;
+        if !layer_ids.contains(&activity.scope) {
+            return Err(invalid("Layer not found"));
+        }
+        let tags = normalize_tags(std::mem::take(&mut activity.tags))
+            .map_err(|e| HttpResponse::bad_request(&format!("Activity {}: {}", activity.id, e.body.message)))?;
+        activity.tags = tags;
+    }
+    for share in &archive.shares {
+        let invalid = |message: &str| HttpResponse::bad_request(&format!("Share {}: {}", share.id, message));
+        validate_share_text(share.name.as_deref(), share.description.as_deref()).map_err(|e| invalid(&e.body.message))?;
+        let layers = &share.layer_config.layer_ids;
+        if layers.is_empty() || layers.len() > 100 {
+            return Err(invalid("Between 1 and 100 layers must be selected"));
+        }
+        if !layers.iter().all(|id| layer_ids.contains(id)) {
+            return Err(invalid("Unknown layer selected"));
+        }
+        if share.partner_sources.len() > federation::MAX_PARTNERS {
+            return Err(invalid(&format!("Too many partner organizations (max {})", federation::MAX_PARTNERS)));
+        }
+    }
+    Ok(())
+}
+
+/// Write a rehomed archive into the organization, publishing a change for every entity written
+async fn write_archive(ctx: &HandlerContext, user: &UserContext, archive: BackupArchive, strategy: ConflictStrategy) -> Result<ImportSummary, StorageError> {
+    let org = &user.organization_id;
+
+    // Layers before the activities and shares that refer to them; row by row, so each write is known
+    let mut summary = ImportSummary { on_conflict: strategy, ..Default::default() };
+    if let Some(policy) = archive.policy {
+        summary.policy = storage::import_policy(ctx.policy_storage.as_ref(), policy, strategy).await?;
+    }
+    for config in archive.activity_types {
+        let key = config.key.clone();
+        let count = storage::import_activity_types(ctx.activity_type_storage.as_ref(), org, vec![config], strategy).await?;
+        if let Some(change) = operations::tally(&mut summary.activity_types, count) {
+            ctx.publish_change(user, EntityKind::ActivityType, &key, change).await;
+        }
+    }
+    for layer in archive.layers {
+        let id = layer.id.clone();
+        let count = storage::import_layers(ctx.layer_storage.as_ref(), vec![layer], strategy).await?;
+        if let Some(change) = operations::tally(&mut summary.layers, count) {
+            ctx.publish_change(user, EntityKind::Layer, &id, change).await;
+        }
+    }
+    for activity in archive.activities {
+        let (id, scope) = (activity.id.clone(), activity.scope.clone());
+        let count = storage::import_activities(ctx.activity_storage.as_ref(), vec![activity], strategy).await?;
+        if let Some(change) = operations::tally(&mut summary.activities, count) {
+            ctx.publish_activity_change(user, &id, &[&scope], change).await;
+        }
+    }
+    for share in archive.shares {
+        let count = storage::import_shares(ctx.share_storage.as_ref(), vec![share.clone()], strategy).await?;
+        if let Some(change) = operations::tally(&mut summary.shares, count) {
+            ctx.publish_share_change(user, &share, change).await;
+        }
+    }
+    summary.user_settings = storage::import_user_settings(ctx.user_settings_storage.as_ref(), org, archive.user_settings, strategy).await?;
+    Ok(summary)
+}
+
+// ============================================
+// Organization Snapshots
+// ============================================
+
+/// Audit action recorded when a snapshot is taken
+const AUDIT_ACTION_SNAPSHOT_TAKEN: &str = "organization.snapshot_taken";
+
+/// Audit action recorded when a snapshot is restored
+const AUDIT_ACTION_SNAPSHOT_RESTORED: &str = "organization.snapshot_restored";
+
+fn snapshot_store(ctx: &HandlerContext) -> Result<&dyn OrganizationSnapshotStore, HttpResponse<ApiError>> {
+    ctx.org_snapshots.as_deref()
+        .ok_or_else(|| HttpResponse::service_unavailable("Organization snapshots are not configured"))
+}
+
+/// POST /api/admin/snapshots - Store a point-in-time copy of the organization (admin only)
+pub async fn take_organization_snapshot(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<OrganizationSnapshot>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let store = snapshot_store(ctx)?;
+
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+
+    let archive = organization_archive(ctx, org).await.map_err(to_error)?;
+    let snapshot = org_snapshots::take(store, &archive).await.map_err(to_error)?;
+
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let entry = AuditEntry::new(org, AUDIT_ACTION_SNAPSHOT_TAKEN, Some(&actor), Some(&snapshot.id))
+        .with_details(serde_json::json!({
+            "layers": archive.layers.len(),
+            "activities": archive.activities.len(),
+            "shares": archive.shares.len(),
+            "bytes": snapshot.bytes,
+        }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+
+    Ok(HttpResponse::created(snapshot))
+}
+
+/// GET /api/admin/snapshots - Snapshots of the organization, newest first (admin only)
+pub async fn list_organization_snapshots(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<Vec<OrganizationSnapshot>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let store = snapshot_store(ctx)?;
+
+    let snapshots = org_snapshots::list(store, &user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    Ok(HttpResponse::ok(snapshots))
+}
+
+/// POST /api/admin/snapshots/{id}/restore - Write a snapshot back over the organization's data (admin only)
+///
+/// Entities deleted or changed since come back as they were; ones created since stay.
+pub async fn restore_organization_snapshot(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    snapshot_id: &str,
+) -> Result<HttpResponse<ImportSummary>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let store = snapshot_store(ctx)?;
+
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+
+    let archive = org_snapshots::load(store, org, snapshot_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Snapshot not found"),
+            _ => HttpResponse::from(e),
+        })?;
+    backup::validate(&archive).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+
+    tracing::warn!("Restoring snapshot {} of organization {}", snapshot_id, org);
+    let summary = write_archive(ctx, user, archive, ConflictStrategy::Overwrite).await.map_err(to_error)?;
+
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let entry = AuditEntry::new(org, AUDIT_ACTION_SNAPSHOT_RESTORED, Some(&actor), Some(snapshot_id))
+        .with_details(serde_json::json!({ "summary": summary }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+
+    Ok(HttpResponse::ok(summary))
+}
+
+// ============================================
+// Bulk Reassignment
+// ============================================
+
+/// Audit action recorded when activities are reassigned in bulk
+pub(crate) const AUDIT_ACTION_REASSIGNED: &str = "activities.reassigned";
+
+/// POST /api/admin/reassign - Move all activities of a layer or type to another (admin only)
+///
+/// With `?dry_run=true`, returns the plan instead.
+pub async fn reassign_activities(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: ReassignRequest,
+    query: DryRunQuery,
+) -> Result<HttpResponse<DryRunOr<ReassignResult>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    reassign::validate(&request).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    
+    if let Reassignment::Layer { to, .. } = &request.reassignment {
+        ctx.layer_storage.get(org, to).await
+            .map_err(|e| match e {
+                StorageError::NotFound(_) => HttpResponse::bad_request("Target layer not found"),
+                _ => to_error(e),
+            })?;
+    }
+    
+    let matching: Vec<Activity> = list_all_activities(ctx, org).await.map_err(to_error)?
+        .into_iter()
+        .filter(|a| reassign::matches(&request, a))
+        .collect();
+    
+    let mut plan = ExecutionPlan::new();
+    plan.add(PlannedEntity::Activity, PlannedAction::Update, matching.iter().map(|a| a.id.as_str()));
+    if query.dry_run {
+        return Ok(HttpResponse::ok(DryRunOr::DryRun(plan.report())));
+    }
+    
+    let now = ctx.clock.now();
+    let update = |activities: Vec<Activity>| ActivityChanges { update: activities, ..Default::default() };
+    let mut written = 0;
+    for batch in matching.chunks(reassign::BATCH_SIZE) {
+        let moved = batch.iter().cloned().map(|mut activity| {
+            reassign::apply(&request.reassignment, &mut activity);
+            activity.updated_at = Some(now);
+            activity
+        }).collect();
+        if let Err(e) = ctx.activity_storage.apply_changes(org, update(moved)).await {
+            // A failed batch wrote nothing; put back earlier batches, each in one transaction
+            for original in matching[..written].chunks(reassign::BATCH_SIZE) {
+                if let Err(undo) = ctx.activity_storage.apply_changes(org, update(original.to_vec())).await {
+                    tracing::error!("Failed to undo reassignment of {} activities in {}: {}", original.len(), org, undo);
+                }
+            }
+            return Err(to_error(e));
+        }
+        written += batch.len();
+    }
+    
+    let result = ReassignResult { reassigned: written as u64 };
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_REASSIGNED, Some(&ctx.pseudonymize(org, &user.user_id)), None)
+        .with_details(serde_json::json!({ "request": request, "reassigned": result.reassigned }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    for activity in &matching {
+        let mut moved = activity.clone();
+        reassign::apply(&request.reassignment, &mut moved);
+        ctx.publish_activity_change(user, &activity.id, &[&activity.scope, &moved.scope], ChangeKind::Updated).await;
+    }
+    
+    Ok(HttpResponse::ok(DryRunOr::Executed(result)))
+}
+
+// ============================================
+// Background Operations
+// ============================================
+
+fn bulk_executor(ctx: &HandlerContext) -> Result<&Arc<BulkExecutor>, HttpResponse<ApiError>> {
+    ctx.operations.as_ref()
+        .ok_or_else(|| HttpResponse::service_unavailable("Background operations are not configured"))
+}
+
+/// Seconds clients are asked to wait before polling an operation again
+const OPERATION_RETRY_AFTER_SECONDS: u32 = 5;
+
+/// 202 pointing at `GET /api/operations/{id}`
+fn operation_accepted(operation: BulkOperation) -> HttpResponse<BulkOperation> {
+    let location = format!("/api/operations/{}", operation.id);
+    HttpResponse::accepted(operation)
+        .with_header("Location", &location)
+        .with_header("Retry-After", &OPERATION_RETRY_AFTER_SECONDS.to_string())
+}
+
+/// POST /api/admin/import/background - Import an archive in the background (admin only)
+///
+/// Takes the same archive and `?on_conflict=` as `POST /api/admin/import`; audited when it succeeds.
+pub async fn start_import_operation(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    mut archive: BackupArchive,
+    query: ImportQuery,
+) -> Result<HttpResponse<BulkOperation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let executor = bulk_executor(ctx)?;
+    backup::validate(&archive).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+
+    let org = &user.organization_id;
+    let strategy = query.on_conflict;
+
+    let source = archive.organization_id.clone();
+    backup::rehome(&mut archive, org);
+    if strategy == ConflictStrategy::NewIds {
+        backup::assign_new_ids(&mut archive);
+    }
+    validate_import(ctx, org, &mut archive).await?;
+
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let operation = executor.start(org, &actor, OperationInput::Import { archive: Box::new(archive), strategy }).await
+        .map_err(HttpResponse::from)?;
+    tracing::info!("Importing archive of organization {} into {} in operation {}", source, org, operation.id);
+
+    Ok(operation_accepted(operation))
+}
+
+/// POST /api/admin/reassign/background - Reassign activities in the background (admin only)
+///
+/// Takes the same request as `POST /api/admin/reassign`; audited when it succeeds.
+pub async fn start_reassign_operation(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: ReassignRequest,
+) -> Result<HttpResponse<BulkOperation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let executor = bulk_executor(ctx)?;
+    reassign::validate(&request).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+
+    if let Reassignment::Layer { to, .. } = &request.reassignment {
+        ctx.layer_storage.get(org, to).await
+            .map_err(|e| match e {
+                StorageError::NotFound(_) => HttpResponse::bad_request("Target layer not found"),
+                _ => to_error(e),
+            })?;
+    }
+
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let operation = executor.start(org, &actor, OperationInput::Reassign { request }).await.map_err(to_error)?;
+    Ok(operation_accepted(operation))
+}
+
+/// POST /api/admin/export/background - Export the organization in the background (admin only)
+///
+/// The finished operation's result is the archive `GET /api/admin/export` returns; audited when it succeeds.
+pub async fn start_export_operation(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<BulkOperation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let executor = bulk_executor(ctx)?;
+
+    let org = &user.organization_id;
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let operation = executor.start(org, &actor, OperationInput::Export).await
+        .map_err(HttpResponse::from)?;
+    Ok(operation_accepted(operation))
+}
+
+/// DELETE /api/admin/organization/background - Purge the organization in the background (admin only)
+///
+/// Takes the same confirmation token as `DELETE /api/admin/organization`; the finished
+/// operation's result is the deletion certificate.
+pub async fn start_purge_operation(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: PurgeOrganizationRequest,
+) -> Result<HttpResponse<BulkOperation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let executor = bulk_executor(ctx)?;
+
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+
+    let signer = confirmation_signer(ctx)?;
+    let plan = purge_plan(ctx, org).await.map_err(to_error)?;
+    signer.verify(&request.confirmation_token, org, &user.user_id, &plan, ctx.clock.now())
+        .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+
+    let started_at = ctx.clock.now();
+
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let input = OperationInput::Purge { changes: plan.steps().to_vec(), started_at };
+    let operation = executor.start(org, &actor, input).await.map_err(to_error)?;
+    tracing::warn!("Purging all data for organization {} in operation {} (requested by {})", org, operation.id, user.user_id);
+
+    Ok(operation_accepted(operation))
+}
+
+/// POST /api/admin/schema/rewrite - Save the organization's rows again at the current schema version (admin only)
+///
+/// Runs in the background (see [`crate::schema`]); audited when it succeeds.
+pub async fn start_schema_rewrite(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<BulkOperation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let executor = bulk_executor(ctx)?;
+
+    let org = &user.organization_id;
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let operation = executor.start(org, &actor, OperationInput::SchemaRewrite).await
+        .map_err(HttpResponse::from)?;
+    Ok(operation_accepted(operation))
+}
+
+/// POST /api/admin/search/reindex - Push every activity into the search index again (admin only)
+///
+/// Runs in the background (see [`crate::operations`]); audited when it succeeds.
+pub async fn start_search_reindex(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<BulkOperation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let executor = bulk_executor(ctx)?;
+    if !executor.has_search_index() {
+        return Err(HttpResponse::not_found("Search index is not configured"));
+    }
+
+    let org = &user.organization_id;
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let operation = executor.start(org, &actor, OperationInput::Reindex).await
+        .map_err(HttpResponse::from)?;
+    Ok(operation_accepted(operation))
+}
+
+/// GET /api/operations/{id} - Status and progress of a background operation (admin only)
+///
+/// 404 once a finished operation is past its `expiresAt`.
+pub async fn get_operation(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    operation_id: &str,
+) -> Result<HttpResponse<BulkOperation>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let executor = bulk_executor(ctx)?;
+
+    let operation = executor.get(&user.organization_id, operation_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Operation not found"),
+            _ => HttpResponse::from(e),
+        })?;
+    if operation.status.is_unfinished() {
+        return Ok(HttpResponse::ok(operation).with_header("Retry-After", &OPERATION_RETRY_AFTER_SECONDS.to_string()));
+    }
+    Ok(HttpResponse::ok(operation))
+}
+
+// ============================================
+// Privacy Administration
+// ============================================
+
+/// Audit action recorded for every pseudonym resolution
+const AUDIT_ACTION_PSEUDONYMS_RESOLVED: &str = "pseudonyms.resolved";
+
+/// POST /api/admin/pseudonyms/resolve - Re-identify audit pseudonyms (admin only)
+///
+/// Pseudonyms are matched against every user ID known to the organization.
+pub async fn resolve_pseudonyms(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: ResolvePseudonymsRequest,
+) -> Result<HttpResponse<Vec<PseudonymResolution>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    if request.legal_basis.trim().is_empty() {
+        return Err(HttpResponse::bad_request("A legal basis is required"));
+    }
+    if request.pseudonyms.is_empty() || request.pseudonyms.len() > 100 {
+        return Err(HttpResponse::bad_request("Between 1 and 100 pseudonyms required"));
+    }
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    
+    let mut candidates: Vec<String> = ctx.user_settings_storage.list(org).await.map_err(to_error)?
+        .into_iter().map(|s| s.user_id).collect();
+    candidates.extend(list_all_shares(ctx, org).await.map_err(to_error)?.into_iter().map(|s| s.created_by));
+    candidates.extend(list_all_activities(ctx, org).await.map_err(to_error)?.into_iter().filter_map(|a| a.created_by));
+    candidates.extend(ctx.layer_storage.list(org).await.map_err(to_error)?.into_iter().map(|l| l.created_by));
+    candidates.sort();
+    candidates.dedup();
+    
+    let resolved: Vec<PseudonymResolution> = pseudonym::resolve(ctx.pseudonymizer.as_ref(), org, &request.pseudonyms, &candidates)
+        .into_iter()
+        .map(|(pseudonym, user_id)| PseudonymResolution { pseudonym, user_id })
+        .collect();
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_PSEUDONYMS_RESOLVED, Some(&ctx.pseudonymize(org, &user.user_id)), None)
+        .with_details(serde_json::json!({
+            "legalBasis": request.legal_basis,
+            "pseudonyms": request.pseudonyms,
+            "resolved": resolved.iter().filter(|r| r.user_id.is_some()).count(),
+        }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(resolved))
+}
+
+// ============================================
+// Admin Analytics
+// ============================================
+
+/// GET /api/admin/analytics/planning?year={year} - Lead time, edit churn and cancellations (admin or reporting role)
+pub async fn planning_analytics(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: PlanningAnalyticsRequest,
+) -> Result<HttpResponse<PlanningAnalytics>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Reports)?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let year = request.year.unwrap_or_else(|| ctx.clock.now().year());
+    
+    let activities = list_all_activities(ctx, org).await.map_err(to_error)?;
+    let layers = ctx.layer_storage.list(org).await.map_err(to_error)?;
+    
+    let mut cancellations = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let page = ctx.audit_storage.list(org, QueryOptions {
+            continuation_token,
+            filter: FilterBuilder::new().eq(FilterField::Action, AUDIT_ACTION_ACTIVITY_CANCELLED).build(),
+            ..Default::default()
+        }).await.map_err(to_error)?;
+        
+        cancellations.extend(page.items.iter().filter_map(Cancellation::from_entry));
+        
+        match page.continuation_token {
+            Some(token) => continuation_token = Some(token),
+            None => break,
+        }
+    }
+    
+    let calendar = calendar_settings(ctx, org).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(planning::compute(year, &activities, &layers, &cancellations, &calendar)))
+}
+
+/// GET /api/admin/analytics/powerbi?table={table} - One page of the Power BI dataset (admin or reporting role)
+pub async fn powerbi_dataset(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: PowerBiRequest,
+) -> Result<HttpResponse<PowerBiPage>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Reports)?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let range = RefreshRange::new(request.range_start, request.range_end)
+        .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    let page_size = request.page_size;
+    let token = request.continuation_token.as_deref();
+    
+    let (rows, continuation_token) = match request.table {
+        PowerBiTable::Activities => {
+            let facts = powerbi::activity_facts(&list_all_activities(ctx, org).await.map_err(to_error)?, &range);
+            let (rows, next) = powerbi::paginate(facts, |f| f.activity_id.clone(), page_size, token);
+            (PowerBiRows::Activities(rows), next)
+        }
+        PowerBiTable::Layers => {
+            let dimensions = powerbi::layer_dimensions(&ctx.layer_storage.list(org).await.map_err(to_error)?);
+            let (rows, next) = powerbi::paginate(dimensions, |d| d.layer_id.clone(), page_size, token);
+            (PowerBiRows::Layers(rows), next)
+        }
+        PowerBiTable::ActivityTypes => {
+            let dimensions = powerbi::activity_type_dimensions(&ctx.activity_type_storage.list(org).await.map_err(to_error)?);
+            let (rows, next) = powerbi::paginate(dimensions, |d| d.activity_type.clone(), page_size, token);
+            (PowerBiRows::ActivityTypes(rows), next)
+        }
+        PowerBiTable::ShareViews => {
+            let (from, to) = range.days(ctx.clock.today())
+                .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+            
+            // Without traffic storage no views were recorded
+            let mut facts = Vec::new();
+            if let Some(ref traffic) = ctx.share_traffic {
+                for share in list_all_shares(ctx, org).await.map_err(to_error)? {
+                    let history = traffic.history(org, &share.id, from, to).await.map_err(to_error)?;
+                    facts.extend(powerbi::share_view_facts(&share.id, share.name.as_deref(), &history));
+                }
+            }
+            let (rows, next) = powerbi::paginate(facts, powerbi::share_view_key, page_size, token);
+            (PowerBiRows::ShareViews(rows), next)
+        }
+    };
+    
+    Ok(HttpResponse::ok(PowerBiPage {
+        table: request.table,
+        rows,
+        continuation_token,
+    }))
+}
+
+// ============================================
+// Organization Policy
+// ============================================
+
+/// Audit action recorded when the period lock changes
+const AUDIT_ACTION_PERIOD_LOCK: &str = "policy.period_lock";
+
+fn period_lock_status(ctx: &HandlerContext, policy: &OrganizationPolicy) -> PeriodLockStatus {
+    PeriodLockStatus {
+        rule: policy.period_lock,
+        locked_before: policy.period_lock.map(|rule| period_lock::cutoff(&rule, ctx.clock.today())),
+        updated_at: policy.updated_at,
+    }
+}
+
+/// GET /api/admin/policy/period-lock - Past period lock and today's cutoff (admin only)
+pub async fn get_period_lock(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<PeriodLockStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(period_lock_status(ctx, &policy)))
+}
+
+/// PUT /api/admin/policy/period-lock - Make past activities read-only for non-admins, or lift the lock (admin only)
+pub async fn set_period_lock(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetPeriodLockRequest,
+) -> Result<HttpResponse<PeriodLockStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    if let Some(ref rule) = request.rule {
+        period_lock::validate(rule, ctx.clock.today()).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    }
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.period_lock = request.rule;
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_PERIOD_LOCK, Some(&actor), None)
+        .with_details(serde_json::json!({ "rule": policy.period_lock }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(period_lock_status(ctx, &policy)))
+}
+
+const AUDIT_ACTION_RATE_PLAN: &str = "policy.rate_plan";
+
+fn rate_plan_status(policy: &OrganizationPolicy) -> RatePlanStatus {
+    let plan = policy.rate_plan.clone().unwrap_or_default();
+    RatePlanStatus {
+        organization_per_minute: plan.organization_per_minute.unwrap_or(rate_limit::DEFAULT_ORGANIZATION_PER_MINUTE),
+        default_api_key_per_minute: rate_limit::DEFAULT_API_KEY_PER_MINUTE,
+        plan,
+        updated_at: policy.updated_at,
+    }
+}
+
+/// GET /api/admin/policy/rate-plan - Public API limits in effect (admin only)
+pub async fn get_rate_plan(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<RatePlanStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(rate_plan_status(&policy)))
+}
+
+/// PUT /api/admin/policy/rate-plan - Set public API limits for the organization and individual share keys (admin only)
+pub async fn set_rate_plan(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: RatePlan,
+) -> Result<HttpResponse<RatePlanStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    rate_limit::validate_plan(&request).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.rate_plan = (request != RatePlan::default()).then_some(request);
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_RATE_PLAN, Some(&actor), None)
+        .with_details(serde_json::json!({ "plan": policy.rate_plan }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(rate_plan_status(&policy)))
+}
+
+const AUDIT_ACTION_ACCESS_LOG: &str = "policy.access_log";
+
+fn access_log_status(ctx: &HandlerContext, policy: &OrganizationPolicy) -> AccessLogForwardingStatus {
+    AccessLogForwardingStatus {
+        forwarding: policy.access_log.clone(),
+        available: ctx.access_log.is_some(),
+        updated_at: policy.updated_at,
+    }
+}
+
+/// GET /api/admin/policy/access-log - SIEM forwarding of public share access (admin only)
+pub async fn get_access_log_forwarding(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<AccessLogForwardingStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(access_log_status(ctx, &policy)))
+}
+
+/// PUT /api/admin/policy/access-log - Forward sampled public share access to a webhook or Event Hub, or stop (admin only)
+pub async fn set_access_log_forwarding(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetAccessLogForwardingRequest,
+) -> Result<HttpResponse<AccessLogForwardingStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    if let Some(ref forwarding) = request.forwarding {
+        access_log::validate(forwarding).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    }
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.access_log = request.forwarding;
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_ACCESS_LOG, Some(&actor), None)
+        .with_details(serde_json::json!({ "forwarding": policy.access_log }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(access_log_status(ctx, &policy)))
+}
+
+/// Audit action recorded when the calendar settings change
+const AUDIT_ACTION_CALENDAR: &str = "policy.calendar";
+
+/// Calendar conventions of an organization, defaults unless it set its own
+async fn calendar_settings(ctx: &HandlerContext, organization_id: &str) -> Result<CalendarSettings, StorageError> {
+    Ok(ctx.policy_storage.get(organization_id).await?.calendar.unwrap_or_default())
+}
+
+fn calendar_status(policy: &OrganizationPolicy) -> CalendarSettingsStatus {
+    CalendarSettingsStatus {
+        calendar: policy.calendar.clone().unwrap_or_default(),
+        customized: policy.calendar.is_some(),
+        updated_at: policy.updated_at,
+    }
+}
+
+/// GET /api/admin/policy/calendar - Locale and week numbering of rendered outputs (admin only)
+pub async fn get_calendar_settings(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<CalendarSettingsStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(calendar_status(&policy)))
+}
+
+/// PUT /api/admin/policy/calendar - Set locale, first day of week and week numbering, or restore the defaults (admin only)
+pub async fn set_calendar_settings(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetCalendarSettingsRequest,
+) -> Result<HttpResponse<CalendarSettingsStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    if let Some(ref settings) = request.calendar {
+        calendar::validate(settings).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    }
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.calendar = request.calendar;
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_CALENDAR, Some(&actor), None)
+        .with_details(serde_json::json!({ "calendar": policy.calendar }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(calendar_status(&policy)))
+}
+
+/// Audit action recorded when the term structure changes
+const AUDIT_ACTION_TERMS: &str = "policy.terms";
+
+fn term_status(policy: &OrganizationPolicy, sync: Option<TermSyncResult>) -> TermStructureStatus {
+    TermStructureStatus {
+        terms: policy.terms.clone(),
+        layer_id: policy.terms.as_ref().map(|_| terms::TERMS_LAYER_ID.to_string()),
+        updated_at: policy.updated_at,
+        sync,
+    }
+}
+
+/// GET /api/admin/policy/terms - School year terms, holidays and exam periods (admin only)
+pub async fn get_term_structure(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<TermStructureStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(term_status(&policy, None)))
+}
+
+/// PUT /api/admin/policy/terms - Set the term structure and regenerate its layer, or remove both (admin only)
+pub async fn set_term_structure(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetTermStructureRequest,
+) -> Result<HttpResponse<TermStructureStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    if let Some(ref structure) = request.terms {
+        terms::validate(structure).map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+    }
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let now = ctx.clock.now();
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.terms = request.terms;
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(now);
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    // A failed run leaves the saved structure; the next run catches up
+    let populator = TermPopulator::new(ctx.layer_storage.clone(), ctx.activity_storage.clone(), ctx.policy_storage.clone());
+    let sync = populator.run(org, now).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_TERMS, Some(&actor), None)
+        .with_details(serde_json::json!({
+            "periods": policy.terms.as_ref().map(|t| t.periods.len()),
+            "created": sync.created.len(),
+            "updated": sync.updated.len(),
+            "deleted": sync.deleted.len(),
+        }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    let layer_change = if policy.terms.is_some() { ChangeKind::Updated } else { ChangeKind::Deleted };
+    ctx.publish_change(user, EntityKind::Layer, terms::TERMS_LAYER_ID, layer_change).await;
+    for (ids, change) in [(&sync.created, ChangeKind::Created), (&sync.updated, ChangeKind::Updated), (&sync.deleted, ChangeKind::Deleted)] {
+        for id in ids {
+            ctx.publish_activity_change(user, id, &[terms::TERMS_LAYER_ID], change).await;
+        }
+    }
+    
+    Ok(HttpResponse::ok(term_status(&policy, Some(sync))))
+}
+
+/// Audit action recorded when the indexing default changes
+const AUDIT_ACTION_INDEXING: &str = "policy.indexing";
+
+fn indexing_status(ctx: &HandlerContext, policy: &OrganizationPolicy) -> IndexingPolicyStatus {
+    IndexingPolicyStatus {
+        indexable: policy.indexable,
+        in_sitemap: ctx.sitemap_organizations.contains(&policy.organization_id),
+        updated_at: policy.updated_at,
+    }
+}
+
+/// GET /api/admin/policy/indexing - Search engine indexing default of public shares (admin only)
+pub async fn get_indexing_policy(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<IndexingPolicyStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(indexing_status(ctx, &policy)))
+}
+
+/// PUT /api/admin/policy/indexing - Let search engines index public shares by default, or not (admin only)
+pub async fn set_indexing_policy(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetIndexingPolicyRequest,
+) -> Result<HttpResponse<IndexingPolicyStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.indexable = request.indexable;
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_INDEXING, Some(&actor), None)
+        .with_details(serde_json::json!({ "indexable": policy.indexable }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(indexing_status(ctx, &policy)))
+}
+
+/// Audit action recorded when the default view settings of new shares change
+const AUDIT_ACTION_SHARE_VIEW_DEFAULTS: &str = "policy.share_view_defaults";
+
+fn share_view_defaults_status(policy: &OrganizationPolicy) -> ShareViewDefaultsStatus {
+    ShareViewDefaultsStatus {
+        view_settings: policy.share_view_defaults.clone().unwrap_or_default(),
+        customized: policy.share_view_defaults.is_some(),
+        updated_at: policy.updated_at,
+    }
+}
+
+/// GET /api/admin/policy/share-view-defaults - Default view settings of shares, under the fields each share sets itself (admin only)
+pub async fn get_share_view_defaults(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<ShareViewDefaultsStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(share_view_defaults_status(&policy)))
+}
+
+/// PUT /api/admin/policy/share-view-defaults - Set theme, legend, rotation and other defaults of shares, or restore the built-in ones (admin only)
+///
+/// Shares that follow the defaults show the new ones right away.
+pub async fn set_share_view_defaults(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetShareViewDefaultsRequest,
+) -> Result<HttpResponse<ShareViewDefaultsStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    if request.view_settings.as_ref().is_some_and(|s| s.custom_title.is_some()) {
+        return Err(HttpResponse::bad_request("Custom titles are set per share"));
+    }
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.share_view_defaults = request.view_settings;
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_SHARE_VIEW_DEFAULTS, Some(&actor), None)
+        .with_details(serde_json::json!({ "viewSettings": policy.share_view_defaults }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(share_view_defaults_status(&policy)))
+}
+
+/// PATCH /api/admin/policy/share-view-defaults - Change some of the defaults of shares, keeping the rest (admin only)
+pub async fn update_share_view_defaults(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: UpdateShareViewDefaultsRequest,
+) -> Result<HttpResponse<ShareViewDefaultsStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    let view_settings = request.view_settings.merge(&policy.share_view_defaults.unwrap_or_default());
+    set_share_view_defaults(ctx, user, SetShareViewDefaultsRequest { view_settings: Some(view_settings) }).await
+}
+
+/// Audit action recorded when the draw order of overlapping activities changes
+const AUDIT_ACTION_ACTIVITY_ORDER: &str = "policy.activity_order";
+
+fn activity_order_status(policy: &OrganizationPolicy) -> ActivityOrderStatus {
+    ActivityOrderStatus {
+        order: activity_order(Some(policy)),
+        updated_at: policy.updated_at,
+    }
+}
+
+/// GET /api/admin/policy/activity-order - How overlapping activities in a ring are stacked (admin only)
+pub async fn get_activity_order(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<ActivityOrderStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(activity_order_status(&policy)))
+}
+
+/// PUT /api/admin/policy/activity-order - Stack overlapping activities by duration, priority or type (admin only)
+pub async fn set_activity_order(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetActivityOrderRequest,
+) -> Result<HttpResponse<ActivityOrderStatus>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.activity_order = request.order;
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    let policy = ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_ACTIVITY_ORDER, Some(&actor), None)
+        .with_details(serde_json::json!({ "order": policy.activity_order }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::ok(activity_order_status(&policy)))
+}
+
+/// Audit action recorded when a partner organization is granted layers
+const AUDIT_ACTION_FEDERATION_GRANTED: &str = "federation.granted";
+
+/// Audit action recorded when a partner organization's grant is revoked
+const AUDIT_ACTION_FEDERATION_REVOKED: &str = "federation.revoked";
+
+/// GET /api/admin/federation/grants - Layers shared with partner organizations (admin only)
+pub async fn list_federation_grants(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<Vec<FederationGrant>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(policy.federation_grants))
+}
+
+/// POST /api/admin/federation/grants - Grant a partner organization read access to layers (admin only)
+///
+/// Returns the signed federation token the partner attaches to a combined share.
+pub async fn create_federation_grant(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: CreateFederationGrantRequest,
+) -> Result<HttpResponse<FederationGrantResponse>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    let keys = ctx.signing_keys.as_ref()
+        .ok_or_else(|| HttpResponse::service_unavailable("Federation is not configured"))?;
+    
+    let org = &user.organization_id;
+    let partner = request.partner_organization_id.trim();
+    if partner.is_empty() || partner == org {
+        return Err(HttpResponse::bad_request("Partner must be another organization"));
+    }
+    if request.layer_ids.is_empty() {
+        return Err(HttpResponse::bad_request("At least one layer must be selected"));
+    }
+    if request.layer_ids.len() > 100 {
+        return Err(HttpResponse::bad_request("Too many layers selected (max 100)"));
+    }
+    let visible = ctx.visible_layer_ids(user).await?;
+    if !request.layer_ids.iter().all(|id| visible.contains(id)) {
+        return Err(HttpResponse::bad_request("Unknown layer selected"));
+    }
+    let now = ctx.clock.now();
+    if request.expires_at.is_some_and(|at| at <= now) {
+        return Err(HttpResponse::bad_request("Expiry must be in the future"));
+    }
+    
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    policy.federation_grants.retain(|g| g.expires_at.is_none_or(|at| at > now));
+    if policy.federation_grants.len() >= federation::MAX_GRANTS {
+        return Err(HttpResponse::bad_request(&format!("Too many federation grants (max {})", federation::MAX_GRANTS)));
+    }
+    
+    let grant = FederationGrant {
+        id: uuid::Uuid::new_v4().to_string(),
+        partner_organization_id: partner.to_string(),
+        layer_ids: request.layer_ids,
+        created_by: actor.clone(),
+        created_at: now,
+        expires_at: request.expires_at,
+        include_descriptions: request.include_descriptions,
+    };
+    let token = FederationToken::new(org, &grant).sign(keys)
+        .map_err(|e| HttpResponse::internal_error(&e.to_string()))?;
+    
+    policy.federation_grants.push(grant.clone());
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(now);
+    ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_FEDERATION_GRANTED, Some(&actor), Some(&grant.id))
+        .with_details(serde_json::json!({
+            "partnerOrganizationId": grant.partner_organization_id,
+            "layerIds": grant.layer_ids,
+            "expiresAt": grant.expires_at,
+            "includeDescriptions": grant.include_descriptions,
+        }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    Ok(HttpResponse::created(FederationGrantResponse { grant, token }))
+}
+
+/// DELETE /api/admin/federation/grants/{id} - Revoke a partner organization's grant (admin only)
+///
+/// Combined shares drawing on it stop showing the layers on their next view;
+/// the partner's caches and snapshots are refreshed through layer change events.
+pub async fn revoke_federation_grant(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    grant_id: &str,
+) -> Result<HttpResponse<()>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let org = &user.organization_id;
+    let to_error = |e: StorageError| HttpResponse::from(e);
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    
+    let mut policy = ctx.policy_storage.get(org).await.map_err(to_error)?;
+    let Some(index) = policy.federation_grants.iter().position(|g| g.id == grant_id) else {
+        return Err(HttpResponse::not_found("Federation grant not found"));
+    };
+    let grant = policy.federation_grants.remove(index);
+    policy.updated_by = Some(actor.clone());
+    policy.updated_at = Some(ctx.clock.now());
+    ctx.policy_storage.upsert(policy).await.map_err(to_error)?;
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_FEDERATION_REVOKED, Some(&actor), Some(&grant.id))
+        .with_details(serde_json::json!({ "partnerOrganizationId": grant.partner_organization_id }));
+    ctx.audit_storage.record(entry).await.map_err(to_error)?;
+    
+    for layer_id in &grant.layer_ids {
+        ctx.events.publish(DomainEvent::EntityChanged(EntityChange::new(
+            &grant.partner_organization_id,
+            EntityKind::Layer,
+            layer_id,
+            ChangeKind::Updated,
+            Some(&actor),
+        ))).await;
+    }
+    
+    Ok(HttpResponse::ok(()))
+}
+
+/// GET /sitemap.xml - Public shares of the configured organizations that opted in to indexing
+pub async fn sitemap(ctx: &HandlerContext) -> Result<HttpResponse<String>, HttpResponse<ApiError>> {
+    if ctx.sitemap_organizations.is_empty() {
+        return Err(HttpResponse::not_found("Sitemap is not enabled"));
+    }
+    
+    let now = ctx.clock.now();
+    let xml = match ctx.sitemap_cache.get(now) {
+        Some(xml) => xml,
+        None => {
+            let mut urls = Vec::new();
+            for org in &ctx.sitemap_organizations {
+                for share in list_all_shares(ctx, org).await.map_err(HttpResponse::from)? {
+                    if indexing::is_listable(&share) {
+                        urls.push((ShareUrls::new(&ctx.base_url, &share).share_url(), share.renewed_at.unwrap_or(share.created_at)));
+                    }
+                }
+            }
+            let xml = indexing::sitemap(urls);
+            ctx.sitemap_cache.put(now, &xml);
+            xml
+        }
+    };
+    
+    Ok(HttpResponse::ok(xml)
+        .with_header("Content-Type", "application/xml; charset=utf-8")
+        .with_header("Cache-Control", &format!("public, max-age={}", indexing::SITEMAP_CACHE_SECONDS)))
+}
+
+/// GET /.well-known/jwks.json - Public keys verifying artifacts this API signs
+pub async fn jwks(ctx: &HandlerContext) -> Result<HttpResponse<Jwks>, HttpResponse<ApiError>> {
+    let keys = ctx.signing_keys.as_ref()
+        .ok_or_else(|| HttpResponse::not_found("Signing keys are not configured"))?;
+    let jwks = keys.jwks().map_err(|e| HttpResponse::internal_error(&e.to_string()))?;
+    
+    // Keys are published a day before they sign, so an hour of caching is safe
+    Ok(HttpResponse::ok(jwks).with_header("Cache-Control", "public, max-age=3600"))
+}
+
+// ============================================
+// Diagnostics
+// ============================================
+
+/// Audit action recorded when verbose logging is switched on or off
+const AUDIT_ACTION_LOG_OVERRIDE: &str = "logging.override";
+
+/// GET /api/admin/logging - Current verbose logging override (admin only)
+pub async fn get_log_override(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<Option<LogOverride>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    Ok(HttpResponse::ok(ctx.log_overrides.get(&user.organization_id)))
+}
+
+/// PUT /api/admin/logging - Log the organization at debug/trace level until the override expires (admin only)
+pub async fn set_log_override(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: SetLogOverrideRequest,
+) -> Result<HttpResponse<LogOverride>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let minutes = request.duration_minutes.unwrap_or(DEFAULT_OVERRIDE_MINUTES);
+    if !(1..=MAX_OVERRIDE_MINUTES).contains(&minutes) {
+        return Err(HttpResponse::bad_request(&format!("Duration must be between 1 and {} minutes", MAX_OVERRIDE_MINUTES)));
+    }
+    
+    let org = &user.organization_id;
+    let actor = ctx.pseudonymize(org, &user.user_id);
+    let log_override = ctx.log_overrides.set(org, request.level, Duration::minutes(minutes), &actor);
+    
+    let entry = AuditEntry::new(org, AUDIT_ACTION_LOG_OVERRIDE, Some(&actor), None)
+        .with_details(serde_json::to_value(&log_override).unwrap_or_default());
+    ctx.audit_storage.record(entry).await
+        .map_err(HttpResponse::from)?;
+    
+    tracing::warn!("Verbose logging ({:?}) enabled for organization {} until {}", request.level, org, log_override.expires_at);
+    
+    Ok(HttpResponse::ok(log_override))
+}
+
+/// DELETE /api/admin/logging - Return the organization to the global log level (admin only)
+pub async fn clear_log_override(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<()>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    
+    let org = &user.organization_id;
+    if ctx.log_overrides.clear(org).is_some() {
+        let actor = ctx.pseudonymize(org, &user.user_id);
+        let entry = AuditEntry::new(org, AUDIT_ACTION_LOG_OVERRIDE, Some(&actor), None)
+            .with_details(serde_json::json!({ "cleared": true }));
+        ctx.audit_storage.record(entry).await
+            .map_err(HttpResponse::from)?;
+        tracing::info!("Verbose logging disabled for organization {}", org);
+    }
+    
+    Ok(HttpResponse::ok(()))
+}
+
+/// GET /api/metrics - Service level indicators and storage call metrics in the Prometheus text format (bearer `METRICS_TOKEN`)
+pub async fn metrics(
+    ctx: &HandlerContext,
+    auth_header: Option<&str>,
+) -> Result<HttpResponse<String>, HttpResponse<ApiError>> {
+    check_metrics_token(ctx, auth_header)?;
+    
+    Ok(HttpResponse::ok(ctx.slo.render_prometheus() + &ctx.partitions.render_prometheus() + &ctx.storage_metrics.render_prometheus())
+        .with_header("Content-Type", "text/plain; version=0.0.4; charset=utf-8"))
+}
+
+/// GET /api/metrics/deprecations - Organizations still calling deprecated routes (bearer `METRICS_TOKEN`)
+///
+/// Covers the calls this instance has served; see [`crate::deprecation`].
+pub async fn deprecation_report(
+    ctx: &HandlerContext,
+    auth_header: Option<&str>,
+) -> Result<HttpResponse<Vec<DeprecationReport>>, HttpResponse<ApiError>> {
+    check_metrics_token(ctx, auth_header)?;
+    Ok(HttpResponse::ok(ctx.deprecations.report()))
+}
+
+/// GET /api/admin/deprecations - Deprecated routes and the organization's calls to them (admin only)
+///
+/// Covers the calls this instance has served; see [`crate::deprecation`].
+pub async fn organization_deprecations(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<Vec<DeprecationReport>>, HttpResponse<ApiError>> {
+    require_admin(user)?;
+    Ok(HttpResponse::ok(ctx.deprecations.report_for(&user.organization_id)))
+}
+
+/// GET /api/metrics/partitions - Latest size and growth of every organization partition (bearer `METRICS_TOKEN`)
+///
+/// Empty until the first sample, and on backends without partition sampling;
+/// see [`crate::partition_monitor`].
+pub async fn partition_report(
+    ctx: &HandlerContext,
+    auth_header: Option<&str>,
+) -> Result<HttpResponse<Vec<PartitionReport>>, HttpResponse<ApiError>> {
+    check_metrics_token(ctx, auth_header)?;
+    Ok(HttpResponse::ok(ctx.partitions.report()))
+}
+
+fn check_metrics_token(ctx: &HandlerContext, auth_header: Option<&str>) -> Result<(), HttpResponse<ApiError>> {
+    let Some(ref expected) = ctx.metrics_token else {
+        return Err(HttpResponse::not_found("Metrics are not enabled"));
+    };
+    let token = auth_header.and_then(|h| h.strip_prefix("Bearer ")).unwrap_or_default();
+    if !secure_compare(token, expected) {
+        return Err(HttpResponse::unauthorized("Invalid metrics token"));
+    }
+    Ok(())
+}
+
+/// GET /api/health - Whether storage answers, per table or container (no auth)
+///
+/// 503 when a check fails, so load balancers take the instance out of rotation.
+pub async fn health(ctx: &HandlerContext) -> Result<HttpResponse<HealthReport>, HttpResponse<ApiError>> {
+    let report = ctx.health.check().await;
+    let status = if report.status == HealthStatus::Unhealthy { 503 } else { 200 };
+    
+    Ok(HttpResponse { status, body: report, headers: Vec::new() }
+        .with_header("Cache-Control", "no-store"))
+}
+
+// ============================================
+// Public Share Access
+// ============================================
+
+/// GET /api/public/s/{shortCode}?k={key} - Access public share
+pub async fn access_public_share(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+) -> Result<HttpResponse<AccessShareResponse>, HttpResponse<ApiError>> {
+    let started = Instant::now();
+    let result = serve_public_share(ctx, client, short_code, key).await;
+    ctx.slo.record_public_access(status_of(&result), started.elapsed());
+    result
+}
+
+async fn serve_public_share(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+) -> Result<HttpResponse<AccessShareResponse>, HttpResponse<ApiError>> {
+    let denied = |e: PublicAccessError| Ok(HttpResponse::ok(public_access::denied(&e))
+        .with_header(indexing::ROBOTS_HEADER, indexing::robots(false)));
+    
+    // Validate input format
+    if let Err(e) = public_access::validate_request(short_code, key) {
+        return denied(e);
+    }
+    
+    // Look up share by short code
+    let budget = ctx.storage_budget.start();
+    budget.charge();
+    let mut share = match ctx.share_storage.get_by_short_code(short_code).await {
+        Ok(s) => s,
+        Err(StorageError::NotFound(_)) => {
+            let retired = ctx.share_storage.get_tombstone(short_code).await
+                .map_err(HttpResponse::from)?
+                .is_some();
+            return denied(if retired { PublicAccessError::Removed } else { PublicAccessError::NotFound });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    
+    // Verify key (constant time), active flag and expiration
+    let now = ctx.clock.now();
+    budget.charge();
+    let policy = public_policy(ctx, &share.organization_id).await;
+    let log = |result: PublicAccessResult, reason: Option<String>| {
+        log_public_access(ctx, policy.as_ref(), access_log::event(&share, PublicAccessEndpoint::Share, result, reason, client.country.as_deref(), now));
+    };
+    if let Err(e) = public_access::authorize(&share, key, now) {
+        log(PublicAccessResult::Denied, Some(e.to_string()));
+        return denied(e);
+    }
+    let rate = check_public_rate(ctx, client, &share, policy.as_ref()).inspect_err(|_| log(PublicAccessResult::RateLimited, None))?;
+    log(PublicAccessResult::Granted, None);
+    let indexable = indexing::is_indexable(&share, policy.as_ref().is_some_and(|p| p.indexable));
+    share.view_settings = public_access::view_settings(&share, policy.as_ref().and_then(|p| p.share_view_defaults.as_ref()));
+    
+    // Increment view count (fire and forget), if the activity reads still fit after it
+    let snapshot_url = snapshots::snapshot_url(&share, ctx.snapshot_base_url.as_deref());
+    let view_calls = if snapshot_url.is_some() { 0 } else { 1 + 2 * share.partner_sources.len() as u32 };
+    if budget.fits(view_calls + 2) {
+        let _ = budget.call(ctx.share_storage.increment_views(&share.organization_id, &share.id)).await;
+        if let Some(ref traffic) = ctx.share_traffic {
+            let _ = budget.call(traffic.record_view(&share.organization_id, &share.id, now.date_naive(), client.country.as_deref())).await;
+        }
+    } else {
+        tracing::info!(share_id = %share.id, "Storage budget spent, view not counted");
+    }
+    
+    // High-traffic shares are served from the CDN
+    if let Some(url) = snapshot_url {
+        return Ok(HttpResponse::found(&url, AccessShareResponse {
+            success: true,
+            error: None,
+            config: None,
+            activities: None,
+            partial: false,
+        }).with_header(indexing::ROBOTS_HEADER, indexing::robots(indexable)).with_rate_limit(&rate));
+    }
+    
+    // Fetch activities for the shared layers, and those partners grant a combined share
+    let year = public_access::share_year(&share, now);
+    let (activities, skipped) = match budget.call(ctx.activity_storage.list_by_layers(&share.organization_id, &share.layer_config.layer_ids, Some(year))).await {
+        Some(result) => (result.unwrap_or_default(), false),
+        None => (Vec::new(), true),
+    };
+    let (share, activities, partial) = with_partner_layers(ctx, &budget, share, activities, year).await;
+    if skipped || partial {
+        tracing::warn!(share_id = %share.id, "Storage budget spent, serving partial activities");
+    }
+    
+    let mut response = public_access::project(&share, activities, year, activity_order(policy.as_ref()));
+    response.partial = skipped || partial;
+    if let Some(ref mut config) = response.config {
+        config.indexable = indexable;
+    }
+    Ok(HttpResponse::ok(response)
+        .with_header(indexing::ROBOTS_HEADER, indexing::robots(indexable))
+        .with_rate_limit(&rate))
+}
+
+/// Policy of the share's organization for public requests
+///
+/// Public access keeps working on the default limits, without forwarding,
+/// when the policy can't be read.
+async fn public_policy(ctx: &HandlerContext, organization_id: &str) -> Option<OrganizationPolicy> {
+    match ctx.policy_storage.get(organization_id).await {
+        Ok(policy) => Some(policy),
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to load organization policy");
+            None
+        }
+    }
+}
+
+/// Draw order of public views, by duration when the policy is unavailable
+fn activity_order(policy: Option<&OrganizationPolicy>) -> ActivityOrder {
+    policy.and_then(|p| p.activity_order).unwrap_or_default()
+}
+
+/// Queue a public access event for the organization's SIEM, if it forwards access
+fn log_public_access(ctx: &HandlerContext, policy: Option<&OrganizationPolicy>, event: ShareAccessEvent) {
+    if let Some(ref forwarder) = ctx.access_log {
+        access_log::forward(forwarder, policy.and_then(|p| p.access_log.as_ref()), event);
+    }
+}
+
+/// Count a public API request against the client's, the organization's and the share key's limits
+fn check_public_rate(ctx: &HandlerContext, client: &ClientInfo, share: &ShareLink, policy: Option<&OrganizationPolicy>) -> Result<RateLimitStatus, HttpResponse<ApiError>> {
+    let plan = policy.and_then(|p| p.rate_plan.as_ref());
+    let (organization_limit, key_limit) = rate_limit::plan_limits(plan, &share.id);
+    
+    let now = ctx.clock.now();
+    let limited = |limited: RateLimited| HttpResponse::too_many_requests("Rate limit exceeded", limited.retry_after_seconds)
+        .with_rate_limit(&limited.status);
+    // Checked first, so one client's flood doesn't use up everyone else's share of the limits
+    let client = client.network()
+        .map(|network| ctx.public_api_limiter.check_limit_at(&format!("client:{}:{}", share.organization_id, network), rate_limit::DEFAULT_CLIENT_PER_MINUTE, now))
+        .transpose()
+        .map_err(limited)?;
+    let organization = ctx.public_api_limiter.check_limit_at(&format!("org:{}", share.organization_id), organization_limit, now)
+        .map_err(limited)?;
+    let key = ctx.public_api_limiter.check_limit_at(&format!("share:{}", share.id), key_limit, now)
+        .map_err(limited)?;
+    let status = organization.tighter(key);
+    Ok(client.map_or(status, |client| status.tighter(client)))
+}
+
+/// GET /api/public/s/{shortCode}/calendar.ics?k={key} - Subscribable calendar for a public share
+///
+/// Narrowed by `layers`, `types`, `tags` and `from`/`to` (see [`CalendarFeedQuery`]).
+/// Denials are plain 404s; calendar clients have no way to show a JSON error.
+pub async fn public_share_calendar(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+    query: CalendarFeedQuery,
+) -> Result<HttpResponse<String>, HttpResponse<ApiError>> {
+    let started = Instant::now();
+    let result = serve_public_calendar(ctx, client, short_code, key, query).await;
+    ctx.slo.record_public_access(status_of(&result), started.elapsed());
+    result
+}
+
+async fn serve_public_calendar(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+    query: CalendarFeedQuery,
+) -> Result<HttpResponse<String>, HttpResponse<ApiError>> {
+    let not_found = || HttpResponse::not_found("Calendar not found");
+    
+    public_access::validate_request(short_code, key).map_err(|_| not_found())?;
+    let share = ctx.share_storage.get_by_short_code(short_code).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => not_found(),
+            _ => HttpResponse::from(e),
+        })?;
+    let now = ctx.clock.now();
+    let policy = public_policy(ctx, &share.organization_id).await;
+    let log = |result: PublicAccessResult, reason: Option<String>| {
+        log_public_access(ctx, policy.as_ref(), access_log::event(&share, PublicAccessEndpoint::Calendar, result, reason, client.country.as_deref(), now));
+    };
+    public_access::authorize(&share, key, now).map_err(|e| {
+        log(PublicAccessResult::Denied, Some(e.to_string()));
+        not_found()
+    })?;
+    let rate = check_public_rate(ctx, client, &share, policy.as_ref()).inspect_err(|_| log(PublicAccessResult::RateLimited, None))?;
+    log(PublicAccessResult::Granted, None);
+    
+    let filter = ics::CalendarFilter::parse(&query).map_err(|e| HttpResponse::bad_request(&e))?;
+    let shared = &share.layer_config.layer_ids;
+    if filter.layer_ids.as_ref().is_some_and(|ids| ids.iter().any(|id| !shared.contains(id))) {
+        return Err(HttpResponse::bad_request("Layer is not part of this share"));
+    }
+    
+    // Without an explicit window the feed mirrors the shared wheel's year
+    let year = (!filter.has_window()).then(|| public_access::share_year(&share, now));
+    let activities: Vec<Activity> = ctx.activity_storage
+        .list_by_layers(&share.organization_id, filter.layer_ids.as_ref().unwrap_or(shared), year)
+        .await
+        .map_err(HttpResponse::from)?
+        .into_iter()
+        .filter(|a| a.approval_status == ApprovalStatus::Approved && shared.contains(&a.scope))
+        .filter(|a| filter.matches(a))
+        .collect();
+    
+    let layer_names: Vec<String> = match &filter.layer_ids {
+        Some(ids) => ctx.layer_storage.list(&share.organization_id).await
+            .map_err(HttpResponse::from)?
+            .into_iter()
+            .filter(|l| ids.contains(&l.id))
+            .map(|l| l.name)
+            .collect(),
+        None => Vec::new(),
+    };
+    // Subscribers are anonymous: feeds follow the organization's locale
+    let locale = i18n::resolve(None, &policy.as_ref().and_then(|p| p.calendar.clone()).unwrap_or_default());
+    let name = ics::calendar_name(&i18n::share_title(locale, &share), &layer_names);
+    
+    let indexable = indexing::is_indexable(&share, policy.as_ref().is_some_and(|p| p.indexable));
+    Ok(HttpResponse::ok(ics::render_calendar(&name, &activities, now, locale))
+        .with_header("Content-Type", "text/calendar; charset=utf-8")
+        .with_header(indexing::ROBOTS_HEADER, indexing::robots(indexable))
+        .with_rate_limit(&rate))
+}
+
+/// GET /api/public/s/{shortCode}/upcoming?limit={n}&k={key} - Next activities of a public share
+///
+/// A small list for signage and intranet widgets, without the wheel
+/// configuration or descriptions. Shares without a fixed year look into the
+/// next year when this one has too few. Denials are plain 404s.
+pub async fn public_share_upcoming(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+    query: UpcomingQuery,
+) -> Result<HttpResponse<UpcomingResponse>, HttpResponse<ApiError>> {
+    let started = Instant::now();
+    let result = serve_public_upcoming(ctx, client, short_code, key, query).await;
+    ctx.slo.record_public_access(status_of(&result), started.elapsed());
+    result
+}
+
+async fn serve_public_upcoming(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+    query: UpcomingQuery,
+) -> Result<HttpResponse<UpcomingResponse>, HttpResponse<ApiError>> {
+    let not_found = || HttpResponse::not_found("Share not found");
+    
+    let limit = match query.limit {
+        None => public_access::DEFAULT_UPCOMING,
+        Some(n) if (1..=public_access::MAX_UPCOMING as u32).contains(&n) => n as usize,
+        Some(_) => return Err(HttpResponse::bad_request(&format!("limit must be between 1 and {}", public_access::MAX_UPCOMING))),
+    };
+    
+    public_access::validate_request(short_code, key).map_err(|_| not_found())?;
+    let budget = ctx.storage_budget.start();
+    budget.charge();
+    let share = ctx.share_storage.get_by_short_code(short_code).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => not_found(),
+            _ => HttpResponse::from(e),
+        })?;
+    let now = ctx.clock.now();
+    budget.charge();
+    let policy = public_policy(ctx, &share.organization_id).await;
+    let log = |result: PublicAccessResult, reason: Option<String>| {
+        log_public_access(ctx, policy.as_ref(), access_log::event(&share, PublicAccessEndpoint::Upcoming, result, reason, client.country.as_deref(), now));
+    };
+    public_access::authorize(&share, key, now).map_err(|e| {
+        log(PublicAccessResult::Denied, Some(e.to_string()));
+        not_found()
+    })?;
+    let rate = check_public_rate(ctx, client, &share, policy.as_ref()).inspect_err(|_| log(PublicAccessResult::RateLimited, None))?;
+    log(PublicAccessResult::Granted, None);
+    
+    // Partner layers are added per year, so each year starts from the stored share
+    let year = public_access::share_year(&share, now);
+    let years = if share.layer_config.year.is_some() { year..=year } else { year..=year + 1 };
+    let mut shown = share.clone();
+    let mut activities = Vec::new();
+    let mut partial = false;
+    for year in years {
+        let own = match budget.call(ctx.activity_storage.list_by_layers(&share.organization_id, &share.layer_config.layer_ids, Some(year))).await {
+            Some(result) => result.map_err(HttpResponse::from)?,
+            None => {
+                partial = true;
+                Vec::new()
+            }
+        };
+        let (combined, year_activities, partners_left_out) = with_partner_layers(ctx, &budget, share.clone(), own, year).await;
+        shown = combined;
+        activities.extend(year_activities);
+        partial |= partners_left_out;
+        // An incomplete year can't tell whether the next one is needed
+        if partial || public_access::upcoming(&shown, activities.clone(), now, limit).len() == limit {
+            break;
+        }
+    }
+    if partial {
+        tracing::warn!(share_id = %share.id, "Storage budget spent, serving partial upcoming activities");
+    }
+    let upcoming = public_access::upcoming(&shown, activities, now, limit);
+    
+    let indexable = indexing::is_indexable(&share, policy.as_ref().is_some_and(|p| p.indexable));
+    Ok(HttpResponse::ok(UpcomingResponse { title: public_access::share_title(&share), activities: upcoming, partial })
+        .with_header(indexing::ROBOTS_HEADER, indexing::robots(indexable))
+        .with_rate_limit(&rate))
+}
+
+/// GET /api/public/s/{shortCode}/preview.png?sig={sig} - Thumbnail of a public share for link unfurling
+///
+/// Signed instead of keyed (see [`preview`]), so the unfurling service never
+/// sees the share key. Denials are plain 404s.
+pub async fn public_share_preview(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    sig: &str,
+) -> Result<HttpResponse<Vec<u8>>, HttpResponse<ApiError>> {
+    let started = Instant::now();
+    let result = serve_public_preview(ctx, client, short_code, sig).await;
+    ctx.slo.record_public_access(status_of(&result), started.elapsed());
+    result
+}
+
+async fn serve_public_preview(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    sig: &str,
+) -> Result<HttpResponse<Vec<u8>>, HttpResponse<ApiError>> {
+    let not_found = || HttpResponse::not_found("Preview not found");
+    
+    let signer = ctx.preview_links.as_ref().ok_or_else(not_found)?;
+    if !is_valid_short_code(short_code) {
+        return Err(not_found());
+    }
+    let share = ctx.share_storage.get_by_short_code(short_code).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => not_found(),
+            _ => HttpResponse::from(e),
+        })?;
+    let now = ctx.clock.now();
+    let policy = public_policy(ctx, &share.organization_id).await;
+    let log = |result: PublicAccessResult, reason: Option<String>| {
+        log_public_access(ctx, policy.as_ref(), access_log::event(&share, PublicAccessEndpoint::Preview, result, reason, client.country.as_deref(), now));
+    };
+    let authorized = match share.visibility {
+        ShareVisibility::Public => signer.verify(&share, sig, now).map_err(|e| e.to_string())
+            .and_then(|_| public_access::check_live(&share, now).map_err(|e| e.to_string())),
+        ShareVisibility::Users => Err(PublicAccessError::InvalidKey.to_string()),
+    };
+    authorized.map_err(|reason| {
+        log(PublicAccessResult::Denied, Some(reason));
+        not_found()
+    })?;
+    let rate = check_public_rate(ctx, client, &share, policy.as_ref()).inspect_err(|_| log(PublicAccessResult::RateLimited, None))?;
+    log(PublicAccessResult::Granted, None);
+    
+    let shared = &share.layer_config.layer_ids;
+    let year = public_access::share_year(&share, now);
+    let activities: Vec<Activity> = ctx.activity_storage
+        .list_by_layers(&share.organization_id, shared, Some(year))
+        .await
+        .map_err(HttpResponse::from)?
+        .into_iter()
+        .filter(|a| a.approval_status == ApprovalStatus::Approved && shared.contains(&a.scope))
+        .collect();
+    let layers: Vec<Layer> = ctx.layer_storage.list(&share.organization_id).await
+        .map_err(HttpResponse::from)?
+        .into_iter()
+        .filter(|l| shared.contains(&l.id))
+        .collect();
+    
+    Ok(HttpResponse::ok(preview::render(&layers, &activities, year, activity_order(policy.as_ref())))
+        .with_header("Content-Type", "image/png")
+        .with_header("Cache-Control", "public, max-age=3600")
+        .with_header(indexing::ROBOTS_HEADER, indexing::robots(false))
+        .with_rate_limit(&rate))
+}
+
+/// POST /api/public/s/{shortCode}/nonce?k={key} - Issue a single-use nonce for the share's public POSTs
+pub async fn issue_public_nonce(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+) -> Result<HttpResponse<RequestNonce>, HttpResponse<ApiError>> {
+    let not_found = || HttpResponse::not_found("Share not found");
+    
+    if !is_valid_short_code(short_code) || !is_valid_share_key(key) {
+        return Err(not_found());
+    }
+    let share = ctx.share_storage.get_by_short_code(short_code).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => not_found(),
+            _ => HttpResponse::from(e),
+        })?;
+    if !secure_compare(&share.share_key, key) {
+        return Err(not_found());
+    }
+    
+    let policy = public_policy(ctx, &share.organization_id).await;
+    let rate = check_public_rate(ctx, client, &share, policy.as_ref())?;
+    
+    let issued = nonce::issue(ctx.nonces.as_ref(), short_code, ctx.clock.now()).await
+        .map_err(|e| {
+            tracing::error!(store = ctx.nonces.name(), "Failed to issue nonce: {}", e);
+            HttpResponse::service_unavailable("Try again later")
+        })?;
+    Ok(HttpResponse::ok(issued)
+        .with_header("Cache-Control", "no-store")
+        .with_rate_limit(&rate))
+}
+
+/// Audit action recorded for share reports
+const AUDIT_ACTION_SHARE_REPORTED: &str = "share.reported";
+
+/// Reads of a share a report makes before giving up on concurrent writes
+const REPORT_ATTEMPTS: usize = 3;
+
+/// POST /api/public/s/{shortCode}/report?k={key} - Report an abusive or misconfigured public share
+///
+/// Requires a nonce from [`issue_public_nonce`] in the `X-Request-Nonce` header.
+/// Reports that can't be taken (bad link, unknown share, missing or used
+/// nonce) answer 200 with `success: false`, like denied share access.
+///
+/// Each reporter (client network, see [`ClientInfo::network`]) counts once
+/// per [`REPORTER_WINDOW_HOURS`]; repeats are answered alike but not kept.
+pub async fn report_public_share(
+    ctx: &HandlerContext,
+    client: &ClientInfo,
+    short_code: &str,
+    key: &str,
+    request_nonce: Option<&str>,
+    request: ReportShareRequest,
+) -> Result<HttpResponse<ReportShareResponse>, HttpResponse<ApiError>> {
+    let rejected = |error: &str| Ok(HttpResponse::ok(ReportShareResponse {
+        success: false,
+        error: Some(error.to_string()),
+    }));
+    
+    if request.details.as_ref().is_some_and(|d| d.len() > 2000) {
+        return Err(HttpResponse::bad_request("Details too long (max 2000 characters)"));
+    }
+    if request.reporter_contact.as_ref().is_some_and(|c| c.len() > 200) {
+        return Err(HttpResponse::bad_request("Contact too long (max 200 characters)"));
+    }
+    
+    // Requiring the key keeps anyone guessing short codes from deactivating shares
+    if !is_valid_short_code(short_code) || !is_valid_share_key(key) {
+        return rejected("Invalid share link");
+    }
+    
+    match nonce::redeem(ctx.nonces.as_ref(), short_code, request_nonce).await {
+        Ok(()) => {}
+        Err(e @ NonceError::Storage(_)) => {
+            tracing::error!(store = ctx.nonces.name(), "Nonce check failed: {}", e);
+            return Err(HttpResponse::service_unavailable("Try again later"));
+        }
+        Err(e) => return rejected(&e.to_string()),
+    }
+    
+    let share = match ctx.share_storage.get_by_short_code(short_code).await {
+        Ok(s) => s,
+        Err(StorageError::NotFound(_)) => return rejected("Share not found"),
+        Err(e) => return Err(e.into()),
+    };
+    
+    if !secure_compare(&share.share_key, key) {
+        return rejected("Invalid share link");
+    }
+    
+    // Clients without a known IP share one reporter, so they can't add up to the threshold
+    let reporter = ctx.pseudonymize(&share.organization_id, &client.network().unwrap_or_default());
+    let mut reported = None;
+    for _ in 0..REPORT_ATTEMPTS {
+        let (mut share, etag) = match ctx.share_storage.get_tagged(&share.organization_id, &share.id).await {
+            Ok(tagged) => tagged,
+            Err(StorageError::NotFound(_)) => return rejected("Share not found"),
+            Err(e) => return Err(e.into()),
+        };
+        
+        let now = ctx.clock.now();
+        share.reporters.retain(|r| now - r.reported_at < Duration::hours(REPORTER_WINDOW_HOURS));
+        if share.reporters.iter().any(|r| r.reporter == reporter) {
+            return Ok(HttpResponse::ok(ReportShareResponse { success: true, error: None }));
+        }
+        if share.reporters.len() >= MAX_SHARE_REPORTERS {
+            share.reporters.remove(0);
+        }
+        share.reporters.push(ShareReporter { reporter: reporter.clone(), reported_at: now });
+        
+        share.report_count += 1;
+        let auto_deactivated = share.is_active
+            && ctx.share_report_threshold > 0
+            && share.report_count >= ctx.share_report_threshold;
+        share.is_active &= !auto_deactivated;
+        
+        if let Some(share) = ctx.share_storage.replace(share, &etag).await.map_err(HttpResponse::from)? {
+            reported = Some((share, auto_deactivated));
+            break;
+        }
+    }
+    let Some((share, auto_deactivated)) = reported else {
+        return Err(HttpResponse::conflict("Share changed while reporting it, try again"));
+    };
+    if auto_deactivated {
+        tracing::warn!("Share {} deactivated after {} reports", share.id, share.report_count);
+    }
+    
+    let report = ShareReport {
+        id: uuid::Uuid::new_v4().to_string(),
+        share_id: share.id.clone(),
+        organization_id: share.organization_id.clone(),
+        short_code: share.short_code.clone(),
+        reason: request.reason,
+        details: request.details,
+        reporter_contact: request.reporter_contact,
+        reported_at: ctx.clock.now(),
+        auto_deactivated,
+    };
+    
+    let entry = AuditEntry::new(&share.organization_id, AUDIT_ACTION_SHARE_REPORTED, None, Some(&share.id))
+        .with_details(serde_json::to_value(&report).unwrap_or_default());
+    if let Err(e) = ctx.audit_storage.record(entry).await {
+        tracing::error!("Failed to record share report for {}: {}", share.id, e);
+    }
+    
+    // Org admins are notified through the event subscribers (live updates, notifications)
+    ctx.events.publish(DomainEvent::ShareReported(report)).await;
+    
+    Ok(HttpResponse::ok(ReportShareResponse {
+        success: true,
+        error: None,
+    }))
+}
+
+// ============================================
+// Delta Sync
+// ============================================
+
+/// GET /api/delta?token={syncToken} - Incremental sync of activities, layers and activity types
+pub async fn get_delta(
+    ctx: &HandlerContext,
+    user: &UserContext,
+    request: DeltaRequest,
+) -> Result<HttpResponse<DeltaResponse>, HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Activities)?;
+    
+    // Capture the new token's timestamp before reading so concurrent writes land in the next delta
+    let now = ctx.clock.now();
+    
+    let since = match request.token.as_deref() {
+        Some(token) => {
+            let token = SyncToken::decode(token)
+                .map_err(|e| HttpResponse::bad_request(&e.to_string()))?;
+            
+            // Tokens from another tenant or too old fall back to a full resync
+            if token.organization_id != user.organization_id || token.is_stale(now) {
+                None
+            } else {
+                Some(token.changed_since())
+            }
+        }
+        None => None,
+    };
+    
+    // Layers hidden from the user drop out of `ids`, so clients remove them
+    let layers = ctx.visible_layers(user).await?;
+    let visible: HashSet<&str> = layers.iter().map(|l| l.id.as_str()).collect();
+    let activities: Vec<Activity> = list_all_activities(ctx, &user.organization_id).await
+        .map_err(HttpResponse::from)?
+        .into_iter()
+        .filter(|a| visible.contains(a.scope.as_str()))
+        .collect();
+    let activity_types = ctx.activity_type_storage.list(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    
+    Ok(HttpResponse::ok(DeltaResponse {
+        activities: compute_delta(activities, since),
+        layers: compute_delta(layers, since),
+        activity_types: compute_delta(activity_types, since),
+        sync_token: SyncToken::new(&user.organization_id, now).encode(),
+        full_sync: since.is_none(),
+    }))
+}
+
+// ============================================
+// Live Updates
+// ============================================
+
+/// POST /api/signalr/negotiate - Connection info for the live update hub
+pub async fn negotiate_live_updates(
+    ctx: &HandlerContext,
+    user: &UserContext,
+) -> Result<HttpResponse<NegotiateResponse>, HttpResponse<ApiError>> {
+    authorize_personal(user)?;
+    let live_updates = ctx.live_updates.as_ref()
+        .ok_or_else(|| HttpResponse::service_unavailable("Live updates are not configured"))?;
+    
+    let response = live_updates.connect(&user.organization_id, &user.user_id).await
+        .map_err(|e| HttpResponse::internal_error(&e.to_string()))?;
+    
+    Ok(HttpResponse::ok(response))
+}
+
+// ============================================
+// Helper Functions
+// ============================================
+
+/// HTTP status of a handler result
+/// Announce a deprecated route on its response, errors included, and count the caller
+fn deprecated<T: Serialize>(
+    ctx: &HandlerContext,
+    method: &str,
+    path: &str,
+    user: &UserContext,
+    result: Result<HttpResponse<T>, HttpResponse<ApiError>>,
+) -> Result<HttpResponse<T>, HttpResponse<ApiError>> {
+    let organization_id = Some(user.organization_id.as_str());
+    match result {
+        Ok(response) => Ok(ctx.deprecations.apply(method, path, organization_id, response)),
+        Err(response) => Err(ctx.deprecations.apply(method, path, organization_id, response)),
+    }
+}
+
+fn status_of<T: Serialize>(result: &Result<HttpResponse<T>, HttpResponse<ApiError>>) -> u16 {
+    match result {
+        Ok(response) => response.status,
+        Err(response) => response.status,
+    }
+}
+
+/// Validate user-editable activity fields
+fn validate_activity_fields(
+    title: &str,
+    start_date: chrono::DateTime<Utc>,
+    end_date: chrono::DateTime<Utc>,
+    description: Option<&str>,
+    description_format: DescriptionFormat,
+    priority: i32,
+) -> Result<(), HttpResponse<ApiError>> {
+    if title.trim().is_empty() {
+        return Err(HttpResponse::bad_request("Title is required"));
+    }
+    if title.len() > 200 {
+        return Err(HttpResponse::bad_request("Title too long (max 200 characters)"));
+    }
+    if end_date < start_date {
+        return Err(HttpResponse::bad_request("End date must be on or after start date"));
+    }
+    let max_description_len = markdown::max_len(description_format);
+    if description.is_some_and(|d| d.chars().count() > max_description_len) {
+        return Err(HttpResponse::bad_request(&format!("Description too long (max {} characters)", max_description_len)));
+    }
+    if priority.abs() > ordering::MAX_PRIORITY {
+        return Err(HttpResponse::bad_request(&format!("Priority must be between -{0} and {0}", ordering::MAX_PRIORITY)));
+    }
+    Ok(())
+}
+
+/// Maximum tags per activity
+const MAX_TAGS: usize = 10;
+
+/// Maximum tag length in characters
+const MAX_TAG_LEN: usize = 50;
+
+/// Trim, lowercase and de-duplicate tags
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>, HttpResponse<ApiError>> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LEN || tag.contains(',') {
+            return Err(HttpResponse::bad_request(&format!("Invalid tag (max {} characters, no commas)", MAX_TAG_LEN)));
+        }
+        normalized.push(tag);
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(HttpResponse::bad_request(&format!("Too many tags (max {})", MAX_TAGS)));
+    }
+    Ok(normalized)
+}
+
+/// Load the layer an activity is assigned to, rejecting layers outside the caller's organization
+async fn get_layer_for_activity(ctx: &HandlerContext, user: &UserContext, layer_id: &str) -> Result<Layer, HttpResponse<ApiError>> {
+    ctx.layer_storage.get(&user.organization_id, layer_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::bad_request("Layer not found"),
+            _ => HttpResponse::from(e),
+        })
+}
+
+/// Reject writes to a layer generated from the term structure, or to its activities (see [`terms`])
+fn ensure_layer_writable(layer_id: &str) -> Result<(), HttpResponse<ApiError>> {
+    if terms::is_system_layer(layer_id) {
+        return Err(HttpResponse::forbidden("The terms layer is generated from the term structure"));
+    }
+    Ok(())
+}
+
+/// Load a share for a write; expired shares are gone even where storage doesn't drop them
+async fn get_writable_share(ctx: &HandlerContext, user: &UserContext, share_id: &str) -> Result<ShareLink, HttpResponse<ApiError>> {
+    let share = ctx.share_storage.get(&user.organization_id, share_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Share not found"),
+            _ => HttpResponse::from(e),
+        })?;
+    if share.is_expired(ctx.clock.now()) {
+        return Err(HttpResponse::gone("Share has expired"));
+    }
+    Ok(share)
+}
+
+/// Reject non-admin writes to activities ending in the locked past period (see [`crate::period_lock`])
+async fn ensure_period_open(ctx: &HandlerContext, user: &UserContext, end_dates: &[DateTime<Utc>]) -> Result<(), HttpResponse<ApiError>> {
+    if user.is_admin {
+        return Ok(());
+    }
+    let policy = ctx.policy_storage.get(&user.organization_id).await
+        .map_err(HttpResponse::from)?;
+    let Some(rule) = policy.period_lock else {
+        return Ok(());
+    };
+    
+    let cutoff = period_lock::cutoff(&rule, ctx.clock.today());
+    if end_dates.iter().any(|end| period_lock::is_locked(*end, cutoff)) {
+        return Err(HttpResponse::forbidden(&format!("Activities ending before {} are locked", cutoff)));
+    }
+    Ok(())
+}
+
+/// Approval status for a submission by this user to this layer
+fn submission_status(layer: &Layer, user: &UserContext) -> ApprovalStatus {
+    if layer.requires_approval && !user.is_admin {
+        ApprovalStatus::PendingApproval
+    } else {
+        ApprovalStatus::Approved
+    }
+}
+
+/// Reject callers without the admin role
+fn require_admin(user: &UserContext) -> Result<(), HttpResponse<ApiError>> {
+    authorize(user, EndpointFamily::Admin)
+}
+
+/// Check the caller's scopes and roles for an endpoint family (see [`authorization`](crate::authorization))
+fn authorize(user: &UserContext, family: EndpointFamily) -> Result<(), HttpResponse<ApiError>> {
+    authorization::authorize(user, family)
+        .map_err(|e| HttpResponse::forbidden(&e.to_string()))
+}
+
+/// Check that the caller may use endpoints outside the families (see [`authorization::authorize_personal`])
+fn authorize_personal(user: &UserContext) -> Result<(), HttpResponse<ApiError>> {
+    authorization::authorize_personal(user)
+        .map_err(|e| HttpResponse::forbidden(&e.to_string()))
+}
+
+/// Load an activity in the caller's organization
+async fn get_activity_or_404(ctx: &HandlerContext, user: &UserContext, activity_id: &str) -> Result<Activity, HttpResponse<ApiError>> {
+    ctx.activity_storage.get(&user.organization_id, activity_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Activity not found"),
+            _ => HttpResponse::from(e),
+        })
+}
+
+/// Load an activity in the caller's organization with the ETag it was read at
+async fn get_tagged_activity_or_404(ctx: &HandlerContext, user: &UserContext, activity_id: &str) -> Result<(Activity, String), HttpResponse<ApiError>> {
+    ctx.activity_storage.get_tagged(&user.organization_id, activity_id).await
+        .map_err(|e| match e {
+            StorageError::NotFound(_) => HttpResponse::not_found("Activity not found"),
+            _ => HttpResponse::from(e),
+        })
+}
+
+/// Reject writes while another user holds the edit lock
+async fn ensure_activity_unlocked(ctx: &HandlerContext, user: &UserContext, activity_id: &str) -> Result<(), HttpResponse<ApiError>> {
+    let lock = ctx.locks.get(&user.organization_id, activity_id).await
+        .map_err(lock_error_response)?;
+    ensure_not_locked_by_other(lock, &user.user_id, ctx.clock.now())
+        .map_err(lock_error_response)
+}
+
+/// Map lock errors to HTTP responses
+fn lock_error_response(e: LockError) -> HttpResponse<ApiError> {
+    match e {
+        LockError::Conflict(ref lock) => {
+            let mut response = HttpResponse::conflict(&e.to_string());
+            response.body.details = serde_json::to_value(lock).ok();
+            response
+        }
+        LockError::Storage(_) => HttpResponse::internal_error(&e.to_string()),
+    }
+}
+
+/// List every share for an organization, following continuation tokens
+async fn list_all_shares(ctx: &HandlerContext, organization_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+    crate::storage::list_all_shares(ctx.share_storage.as_ref(), organization_id).await
+}
+
+/// List every activity for an organization, following continuation tokens
+async fn list_all_activities(ctx: &HandlerContext, organization_id: &str) -> Result<Vec<Activity>, StorageError> {
+    crate::storage::list_all_activities(ctx.activity_storage.as_ref(), organization_id).await
+}
+
+use chrono::Datelike;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::auth::TokenValidatorConfig;
+    use crate::clock::ManualClock;
+    use crate::locks::MemoryLockStore;
+    use crate::pseudonym::PlainIdentifiers;
+    use crate::storage::Storage;
+    
+    /// Handlers on in-memory storage, at 2025-03-01 12:00 UTC
+    pub(crate) fn context() -> HandlerContext {
+        let now = DateTime::parse_from_rfc3339("2025-03-01T12:00:00Z").unwrap().to_utc();
+        let storage = Storage::in_memory();
+        HandlerContext {
+            share_storage: storage.shares,
+            activity_storage: storage.activities,
+            layer_storage: storage.layers,
+            activity_type_storage: storage.activity_types,
+            user_settings_storage: storage.user_settings,
+            audit_storage: storage.audit,
+            policy_storage: storage.policies,
+            token_validator: TokenValidator::new(TokenValidatorConfig::default()),
+            base_url: "https://wheel.example.com".to_string(),
+            share_report_threshold: 3,
+            trusted_proxies: Default::default(),
+            events: Arc::new(EventBus::new()),
+            live_updates: None,
+            locks: Arc::new(MemoryLockStore::new()),
+            pseudonymizer: Arc::new(PlainIdentifiers),
+            snapshot_base_url: None,
+            activity_search: None,
+            directory: None,
+            directory_search_limiter: Arc::new(RateLimiter::new(30, chrono::Duration::minutes(1))),
+            exporters: Arc::new(crate::export::ExporterRegistry::default()),
+            public_api_limiter: Arc::new(RateLimiter::new(crate::rate_limit::DEFAULT_API_KEY_PER_MINUTE, chrono::Duration::minutes(1))),
+            link_domain_denylist: Vec::new(),
+            share_traffic: None,
+            access_log: None,
+            log_overrides: Arc::new(Default::default()),
+            slo: Arc::new(Default::default()),
+            metrics_token: None,
+            deprecations: Arc::new(crate::deprecation::Deprecations::default()),
+            partitions: Arc::new(crate::partition_monitor::PartitionMonitor::new(None, Default::default())),
+            storage_metrics: storage.metrics.clone(),
+            storage_budget: Default::default(),
+            org_snapshots: None,
+            operations: None,
+            health: Arc::new(crate::health::HealthChecker::new(None)),
+            renewal_links: None,
+            preview_links: None,
+            signing_keys: None,
+            sitemap_organizations: Vec::new(),
+            sitemap_cache: Arc::new(Default::default()),
+            nonces: Arc::new(crate::nonce::InProcessNonceStore::new()),
+            clock: Arc::new(ManualClock::new(now)),
+        }
+    }
+    
+    pub(crate) fn admin() -> UserContext {
+        UserContext {
+            user_id: "user-1".to_string(),
+            organization_id: "org-1".to_string(),
+            display_name: Some("Planner".to_string()),
+            email: Some("planner@example.com".to_string()),
+            is_admin: true,
+            roles: vec!["admin.write".to_string()],
+            groups: Some(Vec::new()),
+            scopes: None,
+            team: None,
+        }
+    }
+    
+    /// Custom layer of `org-1` without approval
+    pub(crate) fn layer(id: &str) -> Layer {
+        Layer {
+            id: id.to_string(),
+            name: "Planning".to_string(),
+            description: None,
+            layer_type: LayerType::Custom,
+            color: "#3b82f6".to_string(),
+            ring_index: 0,
+            is_visible: true,
+            requires_approval: false,
+            visible_to_groups: Vec::new(),
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            organization_id: "org-1".to_string(),
+            created_by: "user-1".to_string(),
+            created_at: Utc::now(),
+            updated_at: None,
+        }
+    }
+    
+    /// Layer storage that stays throttled
+    struct BusyLayers;
+    
+    #[async_trait::async_trait]
+    impl LayerStorage for BusyLayers {
+        async fn create(&self, _: Layer) -> Result<Layer, StorageError> {
+            Err(StorageError::Transient("429".to_string()))
+        }
+        
+        async fn get(&self, _: &str, _: &str) -> Result<Layer, StorageError> {
+            Err(StorageError::Transient("429".to_string()))
+        }
+        
+        async fn update(&self, _: Layer) -> Result<Layer, StorageError> {
+            Err(StorageError::Transient("429".to_string()))
+        }
+        
+        async fn delete(&self, _: &str, _: &str) -> Result<(), StorageError> {
+            Err(StorageError::Transient("429".to_string()))
+        }
+        
+        async fn list(&self, _: &str) -> Result<Vec<Layer>, StorageError> {
+            Err(StorageError::Transient("429".to_string()))
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_throttled_storage_is_503() {
+        let mut ctx = context();
+        ctx.layer_storage = Arc::new(BusyLayers);
+        let response = list_layers(&ctx, &admin()).await.unwrap_err();
+        assert_eq!(response.status, 503);
+        assert_eq!(response.body.code, "SERVICE_UNAVAILABLE");
+        assert!(response.headers.contains(&("Retry-After".to_string(), STORAGE_RETRY_AFTER_SECONDS.to_string())));
+        
+        let response = HttpResponse::from(StorageError::Serialization("bad row".to_string()));
+        assert_eq!(response.status, 500);
+        assert!(response.headers.is_empty());
+    }
+    
+    /// Share storage that reports no totals, like Table Storage
+    struct UncountedShares(Arc<dyn ShareStorage>);
+    
+    #[async_trait::async_trait]
+    impl ShareStorage for UncountedShares {
+        async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+            self.0.create(share).await
+        }
+        
+        async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+            self.0.get(organization_id, share_id).await
+        }
+        
+        async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+            self.0.get_by_short_code(short_code).await
+        }
+        
+        async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+            self.0.update(share).await
+        }
+        
+        async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+            self.0.delete(organization_id, share_id).await
+        }
+        
+        async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+            self.0.get_tombstone(short_code).await
+        }
+        
+        async fn list(&self, organization_id: &str, options: QueryOptions) -> Result<storage::QueryResult<ShareLink>, StorageError> {
+            Ok(storage::QueryResult { total_count: None, ..self.0.list(organization_id, options).await? })
+        }
+        
+        async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+            self.0.increment_views(organization_id, share_id).await
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_list_shares_total_without_backend_count() {
+        let mut ctx = context();
+        ctx.share_storage = Arc::new(UncountedShares(ctx.share_storage.clone()));
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        for visibility in ["public", "public", "users"] {
+            create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+                "visibility": visibility, "layerConfig": { "layerIds": ["layer-1"] },
+            })).unwrap()).await.unwrap();
+        }
+        
+        let request = |body: serde_json::Value| serde_json::from_value::<ListSharesRequest>(body).unwrap();
+        let page = list_shares(&ctx, &user, request(serde_json::json!({ "pageSize": 1 }))).await.unwrap().body;
+        assert_eq!((page.shares.len(), page.total_count), (1, 3));
+        let page = list_shares(&ctx, &user, request(serde_json::json!({ "visibility": "public" }))).await.unwrap().body;
+        assert_eq!(page.total_count, 2);
+    }
+    
+    /// Snapshot container in memory
+    #[derive(Default)]
+    struct MemorySnapshots(std::sync::Mutex<HashMap<String, Vec<u8>>>);
+    
+    #[async_trait::async_trait]
+    impl snapshots::SnapshotStore for MemorySnapshots {
+        async fn put(&self, path: &str, _: &str, _: &str, body: Vec<u8>) -> Result<(), crate::events::EventError> {
+            self.0.lock().unwrap().insert(path.to_string(), body);
+            Ok(())
+        }
+        
+        async fn delete_prefix(&self, prefix: &str) -> Result<(), crate::events::EventError> {
+            self.0.lock().unwrap().retain(|path, _| !path.starts_with(prefix));
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_federation_grant_combined_view() {
+        let mut ctx = context();
+        ctx.signing_keys = Some(crate::signing_keys::tests::key_ring_at(ctx.clock.now()).await);
+        let snapshot_store = Arc::new(MemorySnapshots::default());
+        let mut events = EventBus::new();
+        events.subscribe(Arc::new(snapshots::SnapshotPublisher::new(
+            snapshot_store.clone(),
+            ctx.share_storage.clone(),
+            ctx.activity_storage.clone(),
+            ctx.policy_storage.clone(),
+        ).with_clock(ctx.clock.clone())));
+        ctx.events = Arc::new(events);
+        
+        let grantor = admin();
+        let partner = UserContext { user_id: "user-2".to_string(), organization_id: "org-2".to_string(), ..admin() };
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        ctx.layer_storage.create(Layer { organization_id: "org-2".to_string(), ..layer("layer-2") }).await.unwrap();
+        for (user, scope) in [(&grantor, "layer-1"), (&partner, "layer-2")] {
+            create_activity(&ctx, user, serde_json::from_value(serde_json::json!({
+                "title": format!("Meeting on {}", scope), "startDate": "2025-03-03T09:00:00Z", "endDate": "2025-03-03T10:00:00Z",
+                "type": "meeting", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": scope,
+                "description": "Budget details", "links": [{ "title": "Agenda", "url": "https://example.com/agenda" }],
+            })).unwrap()).await.unwrap();
+        }
+        
+        let request = |body: serde_json::Value| serde_json::from_value::<CreateFederationGrantRequest>(body).unwrap();
+        let self_grant = request(serde_json::json!({ "partnerOrganizationId": "org-1", "layerIds": ["layer-1"] }));
+        assert_eq!(create_federation_grant(&ctx, &grantor, self_grant).await.unwrap_err().status, 400);
+        let unknown = request(serde_json::json!({ "partnerOrganizationId": "org-2", "layerIds": ["layer-2"] }));
+        assert_eq!(create_federation_grant(&ctx, &grantor, unknown).await.unwrap_err().status, 400);
+        let created = create_federation_grant(&ctx, &grantor, request(serde_json::json!({
+            "partnerOrganizationId": "org-2", "layerIds": ["layer-1"],
+        }))).await.unwrap();
+        assert_eq!(created.status, 201);
+        let FederationGrantResponse { grant, token } = created.body;
+        assert!(!grant.include_descriptions);
+        assert_eq!(list_federation_grants(&ctx, &grantor).await.unwrap().body, std::slice::from_ref(&grant));
+        
+        // The grantor's token only works for the partner
+        let share_request = |tokens: &[&str]| serde_json::from_value::<CreateShareRequest>(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-2"] },
+            "federationTokens": tokens, "publishSnapshot": true,
+        })).unwrap();
+        let other = UserContext { organization_id: "org-3".to_string(), ..partner.clone() };
+        ctx.layer_storage.create(Layer { organization_id: "org-3".to_string(), ..layer("layer-2") }).await.unwrap();
+        assert_eq!(create_share(&ctx, &other, share_request(&[&token])).await.unwrap_err().status, 400);
+        let share = create_share(&ctx, &partner, share_request(&[&token])).await.unwrap().body.share;
+        
+        // Partner activities are shown without what the grant leaves out
+        let view = access_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await.unwrap().body;
+        let activities = view.activities.unwrap();
+        assert_eq!(activities.len(), 2);
+        let own = activities.iter().find(|a| a.layer_id == "layer-2").unwrap();
+        assert_eq!(own.description.as_deref(), Some("Budget details"));
+        let granted = activities.iter().find(|a| a.layer_id == "layer-1").unwrap();
+        assert_eq!((granted.description.as_ref(), granted.description_html.as_ref(), granted.links.len()), (None, None, 0));
+        assert!(view.config.unwrap().layers.layer_ids.contains(&"layer-1".to_string()));
+        
+        // The snapshot shows the combined view
+        let snapshot = |store: &MemorySnapshots| -> AccessShareResponse {
+            let snapshots = store.0.lock().unwrap();
+            serde_json::from_slice(&snapshots[&snapshots::snapshot_path(&share)]).unwrap()
+        };
+        assert_eq!(snapshot(&snapshot_store).activities.unwrap().len(), 2);
+        
+        assert_eq!(revoke_federation_grant(&ctx, &grantor, "missing").await.unwrap_err().status, 404);
+        revoke_federation_grant(&ctx, &grantor, &grant.id).await.unwrap();
+        assert!(list_federation_grants(&ctx, &grantor).await.unwrap().body.is_empty());
+        let view = access_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await.unwrap().body;
+        assert_eq!(view.activities.unwrap().len(), 1);
+        let republished = snapshot(&snapshot_store).activities.unwrap();
+        assert!(republished.iter().all(|a| a.layer_id == "layer-2"));
+        
+        // Descriptions are the grantor's to include
+        let FederationGrantResponse { token, .. } = create_federation_grant(&ctx, &grantor, request(serde_json::json!({
+            "partnerOrganizationId": "org-2", "layerIds": ["layer-1"], "includeDescriptions": true,
+        }))).await.unwrap().body;
+        let share = create_share(&ctx, &partner, share_request(&[&token])).await.unwrap().body.share;
+        let view = access_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await.unwrap().body;
+        let granted = view.activities.unwrap().into_iter().find(|a| a.layer_id == "layer-1").unwrap();
+        assert_eq!(granted.description.as_deref(), Some("Budget details"));
+        assert_eq!(granted.links.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_public_views_past_storage_budget() {
+        let mut ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        create_activity(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "title": "Planning day", "startDate": "2025-03-03T09:00:00Z", "endDate": "2025-03-03T10:00:00Z",
+            "type": "meeting", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+        })).unwrap()).await.unwrap();
+        let share = create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+        })).unwrap()).await.unwrap().body.share;
+        let views = |ctx: &HandlerContext| {
+            let (shares, id) = (ctx.share_storage.clone(), share.id.clone());
+            async move { shares.get("org-1", &id).await.unwrap().stats.view_count }
+        };
+        
+        // The lookup and policy spend the whole budget
+        ctx.storage_budget = StorageBudget { max_calls: 2, ..Default::default() };
+        let view = access_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await.unwrap().body;
+        assert!(view.partial);
+        assert!(view.activities.unwrap().is_empty());
+        assert_eq!(views(&ctx).await, 0);
+        let upcoming = public_share_upcoming(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key, UpcomingQuery::default()).await.unwrap().body;
+        assert!(upcoming.partial);
+        assert!(upcoming.activities.is_empty());
+        
+        ctx.storage_budget = StorageBudget::default();
+        let view = access_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await.unwrap().body;
+        assert!(!view.partial);
+        assert_eq!(view.activities.unwrap().len(), 1);
+        assert_eq!(views(&ctx).await, 1);
+        let upcoming = public_share_upcoming(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key, UpcomingQuery::default()).await.unwrap().body;
+        assert!(!upcoming.partial);
+        assert_eq!(upcoming.activities.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_deprecated_route_headers() {
+        let ctx = context();
+        let header = |response: &HttpResponse<BackupArchive>, name: &str| response.headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+        let response = export_organization(&ctx, &admin()).await.unwrap();
+        assert_eq!(header(&response, "Deprecation").as_deref(), Some("@1792195200"));
+        
+        // Refused calls are announced and counted too
+        let member = UserContext { is_admin: false, roles: vec![], ..admin() };
+        let refused = export_organization(&ctx, &member).await.unwrap_err();
+        assert!(refused.headers.iter().any(|(k, _)| k == "Deprecation"));
+        
+        let report = organization_deprecations(&ctx, &admin()).await.unwrap().body;
+        let route = report.iter().find(|r| r.path == "/api/admin/export").unwrap();
+        assert_eq!(route.organizations.iter().map(|u| u.requests).collect::<Vec<_>>(), [2]);
+        let other = UserContext { organization_id: "org-2".to_string(), ..admin() };
+        assert!(organization_deprecations(&ctx, &other).await.unwrap().body.iter().all(|r| r.organizations.is_empty()));
+        assert_eq!(organization_deprecations(&ctx, &member).await.unwrap_err().status, 403);
+    }
+    
+    #[tokio::test]
+    async fn test_review_decisions_need_activities_scope() {
+        let ctx = context();
+        let scopes = |scopes: &[&str]| Some(scopes.iter().map(|s| s.to_string()).collect());
+        let scoped = UserContext { scopes: scopes(&["Admin.ReadWrite"]), ..admin() };
+        let decision = || ApprovalDecisionRequest { comment: None };
+        assert_eq!(approve_activity(&ctx, &scoped, "a-1", decision()).await.unwrap_err().status, 403);
+        assert_eq!(reject_activity(&ctx, &scoped, "a-1", decision()).await.unwrap_err().status, 403);
+        
+        // Past the scope check, the missing activity is what fails
+        let scoped = UserContext { scopes: scopes(&["Admin.ReadWrite", "Activities.ReadWrite"]), ..admin() };
+        assert_eq!(reject_activity(&ctx, &scoped, "a-1", decision()).await.unwrap_err().status, 404);
+    }
+    
+    #[tokio::test]
+    async fn test_purge_confirmation() {
+        let mut ctx = context();
+        let user = admin();
+        let purge = |token: &str| PurgeOrganizationRequest { confirmation_token: token.to_string() };
+        assert_eq!(request_purge_confirmation(&ctx, &user).await.unwrap_err().status, 503);
+        
+        ctx.signing_keys = Some(crate::signing_keys::tests::key_ring_at(ctx.clock.now()).await);
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let confirmation = request_purge_confirmation(&ctx, &user).await.unwrap().body;
+        assert_eq!(confirmation.pending.layers, 1);
+        
+        // A token minted by the caller is refused
+        let minted = ConfirmationToken::new("org-1", "user-1", &crate::dry_run::ExecutionPlan::new(), ctx.clock.now());
+        let minted = base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, serde_json::to_vec(&minted).unwrap());
+        assert_eq!(purge_organization(&ctx, &user, purge(&minted), DryRunQuery::default()).await.unwrap_err().status, 400);
+        
+        // So is one for a plan that has changed since
+        create_activity(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "title": "Added after the preview", "startDate": "2025-03-03T09:00:00Z", "endDate": "2025-03-03T10:00:00Z",
+            "type": "meeting", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+        })).unwrap()).await.unwrap();
+        let stale = purge_organization(&ctx, &user, purge(&confirmation.confirmation_token), DryRunQuery::default()).await.unwrap_err();
+        assert_eq!(stale.status, 400);
+        assert_eq!(ctx.activity_storage.list_by_layers("org-1", &["layer-1".to_string()], None).await.unwrap().len(), 1);
+        
+        // Point-in-time copies go with the data
+        let snapshots = Arc::new(crate::org_snapshots::MemoryOrganizationSnapshotStore::new());
+        ctx.org_snapshots = Some(snapshots.clone());
+        take_organization_snapshot(&ctx, &user).await.unwrap();
+        
+        let confirmation = request_purge_confirmation(&ctx, &user).await.unwrap().body;
+        assert_eq!(confirmation.pending.organization_snapshots, 1);
+        let DryRunOr::Executed(certificate) = purge_organization(&ctx, &user, purge(&confirmation.confirmation_token), DryRunQuery::default()).await.unwrap().body else {
+            panic!("expected a purge");
+        };
+        assert_eq!((certificate.deleted.layers, certificate.deleted.activities, certificate.deleted.organization_snapshots), (1, 1, 1));
+        assert!(snapshots.list("org-1").await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_sitemap_lists_opted_in_shares_and_caches() {
+        let mut ctx = context();
+        let clock = Arc::new(ManualClock::new(ctx.clock.now()));
+        ctx.clock = clock.clone();
+        ctx.sitemap_organizations = vec!["org-1".to_string()];
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        set_indexing_policy(&ctx, &user, serde_json::from_value(serde_json::json!({ "indexable": true })).unwrap()).await.unwrap();
+        let mut shares = Vec::new();
+        for _ in 0..2 {
+            shares.push(create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+                "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+            })).unwrap()).await.unwrap().body.share);
+        }
+        set_share_indexing(&ctx, &user, &shares[0].id, SetShareIndexingRequest { indexable: Some(true) }).await.unwrap();
+        
+        // The organization default does not publish keyed URLs
+        let response = sitemap(&ctx).await.unwrap();
+        assert!(response.headers.iter().any(|(k, v)| k == "Cache-Control" && v == "public, max-age=3600"));
+        assert!(response.body.contains(&shares[0].share_key));
+        assert!(!response.body.contains(&shares[1].share_key));
+        
+        // Later opt-ins show up once the cached sitemap expires
+        set_share_indexing(&ctx, &user, &shares[1].id, SetShareIndexingRequest { indexable: Some(true) }).await.unwrap();
+        assert!(!sitemap(&ctx).await.unwrap().body.contains(&shares[1].share_key));
+        clock.advance(Duration::seconds(indexing::SITEMAP_CACHE_SECONDS));
+        assert!(sitemap(&ctx).await.unwrap().body.contains(&shares[1].share_key));
+    }
+    
+    #[tokio::test]
+    async fn test_renew_by_token() {
+        let mut ctx = context();
+        let clock = Arc::new(ManualClock::new(ctx.clock.now()));
+        ctx.clock = clock.clone();
+        let now = ctx.clock.now();
+        let signer = Arc::new(RenewalLinkSigner::new(crate::signing_keys::tests::key_ring_at(now).await));
+        ctx.renewal_links = Some(signer.clone());
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let mut shares = Vec::new();
+        for _ in 0..2 {
+            shares.push(create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+                "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+            })).unwrap()).await.unwrap().body.share);
+        }
+        let link = |share: &ShareLink| RenewByTokenRequest { token: signer.sign(&crate::share_renewal::RenewalToken::new(share, now)).unwrap() };
+        
+        let request = link(&shares[0]);
+        clock.advance(Duration::days(1));
+        let renewed = renew_share_by_token(&ctx, RenewByTokenRequest { token: request.token.clone() }).await.unwrap().body;
+        assert_eq!(renewed.expires_at, now + Duration::days(366));
+        assert_eq!(renew_share_by_token(&ctx, request).await.unwrap_err().status, 409);
+        
+        // Deactivated shares stay deactivated
+        let mut deactivated = shares[1].clone();
+        deactivated.is_active = false;
+        ctx.share_storage.update(deactivated.clone()).await.unwrap();
+        assert_eq!(renew_share_by_token(&ctx, link(&deactivated)).await.unwrap_err().status, 410);
+        assert_eq!(ctx.share_storage.get("org-1", &deactivated.id).await.unwrap().expires_at, shares[1].expires_at);
+    }
+    
+    #[tokio::test]
+    async fn test_terms_layer_is_read_only() {
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        set_term_structure(&ctx, &user, serde_json::from_value(serde_json::json!({ "terms": {
+            "layerName": "School year", "periods": [{ "name": "Autumn term", "kind": "term", "start": "2025-08-18", "end": "2025-12-19" }],
+        }})).unwrap()).await.unwrap();
+        let generated = "term-term-2025-08-18";
+        assert_eq!(ctx.layer_storage.get("org-1", terms::TERMS_LAYER_ID).await.unwrap().ring_index, terms::TERMS_RING_INDEX);
+        
+        let request = serde_json::from_value(serde_json::json!({
+            "title": "Parents' evening", "startDate": "2025-09-03T17:00:00Z", "endDate": "2025-09-03T19:00:00Z",
+            "type": "meeting", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": terms::TERMS_LAYER_ID,
+        })).unwrap();
+        assert_eq!(create_activity(&ctx, &user, request).await.unwrap_err().status, 403);
+        let rename = serde_json::from_value(serde_json::json!({ "title": "Renamed" })).unwrap();
+        assert_eq!(update_activity(&ctx, &user, generated, rename).await.unwrap_err().status, 403);
+        let moved = serde_json::from_value(serde_json::json!({ "scope": "layer-1" })).unwrap();
+        assert_eq!(update_activity(&ctx, &user, generated, moved).await.unwrap_err().status, 403);
+        assert_eq!(delete_activity(&ctx, &user, generated).await.unwrap_err().status, 403);
+        let teams = serde_json::from_value(serde_json::json!({ "teamIds": [] })).unwrap();
+        assert_eq!(set_layer_teams(&ctx, &user, terms::TERMS_LAYER_ID, teams).await.unwrap_err().status, 403);
+        assert_eq!(ctx.activity_storage.get("org-1", generated).await.unwrap().title, "Autumn term");
+    }
+    
+    #[tokio::test]
+    async fn test_rejected_reports_answer_alike() {
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let share = create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+        })).unwrap()).await.unwrap().body.share;
+        let report = || serde_json::from_value::<ReportShareRequest>(serde_json::json!({ "reason": "spam" })).unwrap();
+        let rejection = |response: HttpResponse<ReportShareResponse>| {
+            assert_eq!(response.status, 200);
+            assert!(!response.body.success);
+            response.body.error.unwrap()
+        };
+        
+        let nonce = issue_public_nonce(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await.unwrap().body.nonce;
+        let wrong_key = "0".repeat(64);
+        let response = report_public_share(&ctx, &ClientInfo::default(), &share.short_code, &wrong_key, Some(&nonce), report()).await.unwrap();
+        assert_eq!(rejection(response), "Invalid share link");
+        let response = report_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key, None, report()).await.unwrap();
+        assert_eq!(rejection(response), NonceError::Missing.to_string());
+        
+        // The nonce is used up by the first report, taken or not
+        let nonce = issue_public_nonce(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key).await.unwrap().body.nonce;
+        let response = report_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key, Some(&nonce), report()).await.unwrap();
+        assert!(response.body.success);
+        let replayed = report_public_share(&ctx, &ClientInfo::default(), &share.short_code, &share.share_key, Some(&nonce), report()).await.unwrap();
+        assert_eq!(rejection(replayed), NonceError::Invalid.to_string());
+        assert_eq!(ctx.share_storage.get("org-1", &share.id).await.unwrap().report_count, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_reports_count_once_per_reporter() {
+        let mut ctx = context();
+        let clock = Arc::new(ManualClock::new(ctx.clock.now()));
+        ctx.clock = clock.clone();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let share = create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+        })).unwrap()).await.unwrap().body.share;
+        let report_from = |ip: &str| {
+            let client = ClientInfo { ip: ip.parse().ok(), ..Default::default() };
+            let ctx = &ctx;
+            let share = &share;
+            async move {
+                let nonce = issue_public_nonce(ctx, &client, &share.short_code, &share.share_key).await.unwrap().body.nonce;
+                let request = serde_json::from_value(serde_json::json!({ "reason": "spam" })).unwrap();
+                let response = report_public_share(ctx, &client, &share.short_code, &share.share_key, Some(&nonce), request).await.unwrap();
+                assert!(response.body.success);
+                ctx.share_storage.get("org-1", &share.id).await.unwrap()
+            }
+        };
+        
+        // One link holder reporting over and over, from anywhere in their /64
+        for ip in ["192.0.2.1", "192.0.2.1", "192.0.2.1"] {
+            assert_eq!(report_from(ip).await.report_count, 1);
+        }
+        report_from("2001:db8::1").await;
+        let reported = report_from("2001:db8::2").await;
+        assert_eq!(reported.report_count, 2);
+        assert!(reported.is_active);
+        
+        // The same reporter counts again once the window has passed
+        clock.advance(Duration::hours(REPORTER_WINDOW_HOURS));
+        let reported = report_from("192.0.2.1").await;
+        assert_eq!(reported.report_count, 3);
+        assert!(!reported.is_active);
+        assert_eq!(reported.reporters.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_public_rate_limit_per_client() {
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let share = create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+        })).unwrap()).await.unwrap().body.share;
+        let client = |ip: &str| ClientInfo { ip: ip.parse().ok(), ..Default::default() };
+        
+        let flooding = client("192.0.2.1");
+        for _ in 0..rate_limit::DEFAULT_CLIENT_PER_MINUTE {
+            issue_public_nonce(&ctx, &flooding, &share.short_code, &share.share_key).await.unwrap();
+        }
+        let limited = issue_public_nonce(&ctx, &flooding, &share.short_code, &share.share_key).await.unwrap_err();
+        assert_eq!(limited.status, 429);
+        
+        let response = issue_public_nonce(&ctx, &client("192.0.2.2"), &share.short_code, &share.share_key).await.unwrap();
+        let remaining = response.headers.iter().find(|(name, _)| name == rate_limit::REMAINING_HEADER).unwrap();
+        assert_eq!(remaining.1, (rate_limit::DEFAULT_CLIENT_PER_MINUTE - 1).to_string());
+    }
+    
+    /// Activity storage where someone edits each updated activity just
+    /// before a batch of changes is applied
+    struct Interleaved(Arc<dyn ActivityStorage>);
+    
+    #[async_trait::async_trait]
+    impl ActivityStorage for Interleaved {
+        async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+            self.0.create(activity).await
+        }
+        
+        async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+            self.0.get(organization_id, activity_id).await
+        }
+        
+        async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+            self.0.update(activity).await
+        }
+        
+        async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+            self.0.delete(organization_id, activity_id).await
+        }
+        
+        async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+            for activity in &changes.update {
+                let mut edited = self.0.get(organization_id, &activity.id).await?;
+                edited.title = "Edited meanwhile".to_string();
+                self.0.update(edited).await?;
+            }
+            self.0.apply_changes(organization_id, changes).await
+        }
+        
+        async fn list(&self, organization_id: &str, options: QueryOptions) -> Result<storage::QueryResult<Activity>, StorageError> {
+            self.0.list(organization_id, options).await
+        }
+        
+        async fn list_by_layers(&self, organization_id: &str, layer_ids: &[String], year: Option<i32>) -> Result<Vec<Activity>, StorageError> {
+            self.0.list_by_layers(organization_id, layer_ids, year).await
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_split_and_merge() {
+        let mut ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let activity = create_activity(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "title": "Exams", "startDate": "2025-05-01T00:00:00Z", "endDate": "2025-06-30T00:00:00Z",
+            "type": "event", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+        })).unwrap()).await.unwrap().body;
+        let at = |date: &str| serde_json::from_value::<SplitActivityRequest>(serde_json::json!({ "at": date })).unwrap();
+        
+        // A split outside the activity is refused
+        assert_eq!(split_activity(&ctx, &user, &activity.id, at("2025-08-01T00:00:00Z")).await.unwrap_err().status, 400);
+        
+        let split = split_activity(&ctx, &user, &activity.id, at("2025-06-01T00:00:00Z")).await.unwrap();
+        assert_eq!(split.status, 201);
+        let SplitActivityResponse { first, second } = split.body;
+        assert_eq!(ctx.activity_storage.get("org-1", &first.id).await.unwrap().end_date, second.start_date);
+        assert!(ctx.activity_storage.get("org-1", &second.id).await.is_ok());
+        
+        // Changes to either part since they were read make the merge fail whole
+        let merge = || MergeActivitiesRequest { target_id: first.id.clone(), source_id: second.id.clone() };
+        let activities = ctx.activity_storage.clone();
+        ctx.activity_storage = Arc::new(Interleaved(activities.clone()));
+        assert_eq!(merge_activities(&ctx, &user, merge()).await.unwrap_err().status, 409);
+        assert!(activities.get("org-1", &second.id).await.is_ok());
+        ctx.activity_storage = activities;
+        
+        let merged = merge_activities(&ctx, &user, merge()).await.unwrap().body;
+        assert_eq!((merged.start_date, merged.end_date), (activity.start_date, activity.end_date));
+        assert!(ctx.activity_storage.get("org-1", &second.id).await.is_err());
+        
+        // Writes the backend refuses as invalid are the client's fault
+        assert_eq!(HttpResponse::from(StorageError::Validation("Activity a-1 belongs to another organization".to_string())).status, 400);
+        assert_eq!(HttpResponse::from(StorageError::Conflict("a-1".to_string())).status, 409);
+    }
+    
+    /// Activity storage that refuses the nth batch of changes
+    struct FailingBatch {
+        inner: Arc<dyn ActivityStorage>,
+        batches: std::sync::atomic::AtomicUsize,
+        fail_at: usize,
+    }
+    
+    #[async_trait::async_trait]
+    impl ActivityStorage for FailingBatch {
+        async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+            self.inner.create(activity).await
+        }
+        
+        async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+            self.inner.get(organization_id, activity_id).await
+        }
+        
+        async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+            self.inner.update(activity).await
+        }
+        
+        async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+            self.inner.delete(organization_id, activity_id).await
+        }
+        
+        async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+            if self.batches.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1 == self.fail_at {
+                return Err(StorageError::Transient("Batch refused".to_string()));
+            }
+            self.inner.apply_changes(organization_id, changes).await
+        }
+        
+        async fn list(&self, organization_id: &str, options: QueryOptions) -> Result<storage::QueryResult<Activity>, StorageError> {
+            self.inner.list(organization_id, options).await
+        }
+        
+        async fn list_by_layers(&self, organization_id: &str, layer_ids: &[String], year: Option<i32>) -> Result<Vec<Activity>, StorageError> {
+            self.inner.list_by_layers(organization_id, layer_ids, year).await
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_failed_reassignment_puts_back_earlier_batches() {
+        let mut ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        ctx.layer_storage.create(layer("layer-2")).await.unwrap();
+        for i in 0..reassign::BATCH_SIZE + 10 {
+            create_activity(&ctx, &user, serde_json::from_value(serde_json::json!({
+                "title": format!("Activity {}", i), "startDate": "2025-05-01T00:00:00Z", "endDate": "2025-05-02T00:00:00Z",
+                "type": "event", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+            })).unwrap()).await.unwrap();
+        }
+        let request = || serde_json::from_value::<ReassignRequest>(serde_json::json!({
+            "kind": "layer", "from": "layer-1", "to": "layer-2",
+        })).unwrap();
+        
+        // The second batch fails after the first was written
+        let activities = ctx.activity_storage.clone();
+        ctx.activity_storage = Arc::new(FailingBatch { inner: activities.clone(), batches: Default::default(), fail_at: 2 });
+        assert_eq!(reassign_activities(&ctx, &user, request(), DryRunQuery::default()).await.unwrap_err().status, 503);
+        let all = list_all_activities(&ctx, "org-1").await.unwrap();
+        assert_eq!(all.len(), reassign::BATCH_SIZE + 10);
+        assert!(all.iter().all(|a| a.scope == "layer-1"));
+        
+        ctx.activity_storage = activities;
+        let result = reassign_activities(&ctx, &user, request(), DryRunQuery::default()).await.unwrap().body;
+        assert!(matches!(result, DryRunOr::Executed(ReassignResult { reassigned }) if reassigned == reassign::BATCH_SIZE as u64 + 10));
+        assert!(list_all_activities(&ctx, "org-1").await.unwrap().iter().all(|a| a.scope == "layer-2"));
+    }
+    
+    #[tokio::test]
+    async fn test_reporting_export_leaves_out_descriptions_and_people() {
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(Layer { description: Some("Finance team plans".to_string()), ..layer("layer-1") }).await.unwrap();
+        create_activity(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "title": "Budget review", "description": "Bring the draft figures", "startDate": "2025-05-01T00:00:00Z",
+            "endDate": "2025-05-02T00:00:00Z", "type": "event", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+        })).unwrap()).await.unwrap();
+        let reporter = UserContext {
+            user_id: "reporting-app".to_string(),
+            display_name: None,
+            email: None,
+            is_admin: false,
+            roles: vec![authorization::REPORTS_ROLE.to_string()],
+            ..admin()
+        };
+        let export = |user: UserContext| {
+            let ctx = &ctx;
+            async move {
+                let request = serde_json::from_value(serde_json::json!({})).unwrap();
+                let response = export_activities(ctx, &user, request, ExportQuery::default(), Some("application/json")).await.unwrap();
+                String::from_utf8(response.body).unwrap()
+            }
+        };
+        
+        // Members see everything
+        let full = export(admin()).await;
+        assert!(full.contains("Bring the draft figures"));
+        assert!(full.contains("Finance team plans"));
+        assert!(full.contains("user-1"));
+        
+        // The reporting role gets the activities without free text or who made them
+        let redacted = export(reporter).await;
+        assert!(redacted.contains("Budget review"));
+        assert!(!redacted.contains("Bring the draft figures"));
+        assert!(!redacted.contains("Finance team plans"));
+        assert!(!redacted.contains("user-1"));
+        assert!(!redacted.contains("Planner"));
+    }
+    
+    /// Entity changes published on the bus
+    #[derive(Default)]
+    struct Changes(std::sync::Mutex<Vec<(EntityKind, ChangeKind)>>);
+    
+    #[async_trait::async_trait]
+    impl crate::events::EventSubscriber for Changes {
+        fn name(&self) -> &'static str {
+            "changes"
+        }
+        
+        async fn handle(&self, event: &DomainEvent) -> Result<(), crate::events::EventError> {
+            if let DomainEvent::EntityChanged(change) = event {
+                self.0.lock().unwrap().push((change.entity, change.change));
+            }
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_purge_publishes_deletions() {
+        let mut ctx = context();
+        let changes = Arc::new(Changes::default());
+        let mut events = EventBus::new();
+        events.subscribe(changes.clone());
+        ctx.events = Arc::new(events);
+        ctx.signing_keys = Some(crate::signing_keys::tests::key_ring_at(ctx.clock.now()).await);
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        create_activity(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "title": "Budget", "startDate": "2025-03-03T09:00:00Z", "endDate": "2025-03-03T10:00:00Z",
+            "type": "meeting", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+        })).unwrap()).await.unwrap();
+        create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+        })).unwrap()).await.unwrap();
+        changes.0.lock().unwrap().clear();
+        
+        let confirmation = request_purge_confirmation(&ctx, &user).await.unwrap().body;
+        let request = PurgeOrganizationRequest { confirmation_token: confirmation.confirmation_token };
+        purge_organization(&ctx, &user, request, DryRunQuery::default()).await.unwrap();
+        
+        // Caches, snapshots and live clients hear of every share, activity and layer
+        let published = changes.0.lock().unwrap().clone();
+        for change in [
+            (EntityKind::Share, ChangeKind::Updated),
+            (EntityKind::Share, ChangeKind::Deleted),
+            (EntityKind::Activity, ChangeKind::Deleted),
+            (EntityKind::Layer, ChangeKind::Deleted),
+        ] {
+            assert!(published.contains(&change), "{:?} missing from {:?}", change, published);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_export_import_roundtrip() {
+        let mut ctx = context();
+        let changes = Arc::new(Changes::default());
+        let mut events = EventBus::new();
+        events.subscribe(changes.clone());
+        ctx.events = Arc::new(events);
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        create_activity(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "title": "Budget", "startDate": "2025-03-03T09:00:00Z", "endDate": "2025-03-03T10:00:00Z",
+            "type": "meeting", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+        })).unwrap()).await.unwrap();
+        create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+        })).unwrap()).await.unwrap();
+        let archive = export_organization(&ctx, &user).await.unwrap().body;
+        let import = |archive: BackupArchive, on_conflict: ConflictStrategy| {
+            import_organization(&ctx, &user, archive, ImportQuery { on_conflict })
+        };
+        let published = |kind: ChangeKind| -> Vec<EntityKind> {
+            std::mem::take(&mut *changes.0.lock().unwrap()).into_iter().filter(|(_, c)| *c == kind).map(|(e, _)| e).collect()
+        };
+        published(ChangeKind::Created);
+        
+        // Skip keeps what is stored and changes nothing
+        let mut renamed = archive.clone();
+        renamed.activities[0].title = "Budget review".to_string();
+        let summary = import(renamed.clone(), ConflictStrategy::Skip).await.unwrap().body;
+        assert_eq!((summary.layers.skipped, summary.activities.skipped, summary.shares.skipped), (1, 1, 1));
+        assert_eq!(list_all_activities(&ctx, "org-1").await.unwrap()[0].title, "Budget");
+        assert!(changes.0.lock().unwrap().is_empty());
+        
+        // Overwrite replaces it and publishes the updates
+        let summary = import(renamed, ConflictStrategy::Overwrite).await.unwrap().body;
+        assert_eq!((summary.layers.overwritten, summary.activities.overwritten, summary.shares.overwritten), (1, 1, 1));
+        assert_eq!(list_all_activities(&ctx, "org-1").await.unwrap()[0].title, "Budget review");
+        assert_eq!(published(ChangeKind::Updated), [EntityKind::Layer, EntityKind::Activity, EntityKind::Share]);
+        
+        // New IDs import next to the stored entities, linked to each other
+        let summary = import(archive.clone(), ConflictStrategy::NewIds).await.unwrap().body;
+        assert_eq!((summary.layers.created, summary.activities.created, summary.shares.created), (1, 1, 1));
+        assert_eq!(published(ChangeKind::Created), [EntityKind::Layer, EntityKind::Activity, EntityKind::Share]);
+        let layers = ctx.layer_storage.list("org-1").await.unwrap();
+        assert_eq!(layers.len(), 2);
+        let copy = list_all_activities(&ctx, "org-1").await.unwrap().into_iter().find(|a| a.title == "Budget").unwrap();
+        assert_ne!(copy.scope, "layer-1");
+        assert!(layers.iter().any(|l| l.id == copy.scope));
+        assert_eq!(list_all_shares(&ctx, "org-1").await.unwrap().len(), 2);
+        
+        // Entities break the create rules before anything is written
+        let mut broken = archive.clone();
+        broken.activities[0].end_date = broken.activities[0].start_date - Duration::days(1);
+        assert_eq!(import(broken, ConflictStrategy::NewIds).await.unwrap_err().status, 400);
+        let mut unknown_layer = archive.clone();
+        unknown_layer.activities[0].scope = "layer-9".to_string();
+        assert_eq!(import(unknown_layer, ConflictStrategy::NewIds).await.unwrap_err().status, 400);
+        let mut long_name = archive;
+        long_name.shares[0].name = Some("x".repeat(MAX_SHARE_NAME_LEN + 1));
+        assert_eq!(import(long_name, ConflictStrategy::Overwrite).await.unwrap_err().status, 400);
+        assert_eq!(list_all_activities(&ctx, "org-1").await.unwrap().len(), 2);
+        assert!(changes.0.lock().unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_update_share_view_settings() {
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        set_share_view_defaults(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "viewSettings": { "theme": "dark", "showLegend": false },
+        })).unwrap()).await.unwrap();
+        let created = create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] },
+        })).unwrap()).await.unwrap().body.share;
+        assert!(created.inherit_view_settings);
+        let update = |body: serde_json::Value| serde_json::from_value::<UpdateShareRequest>(body).unwrap();
+        
+        // Patches override single fields; the share follows the defaults for the others
+        let share = update_share(&ctx, &user, &created.id, update(serde_json::json!({
+            "viewSettings": { "showTitle": false },
+        }))).await.unwrap().body;
+        assert!(share.inherit_view_settings);
+        assert_eq!(share.view_settings.theme, ShareTheme::Dark);
+        assert!(!share.view_settings.show_legend);
+        assert!(!share.view_settings.show_title);
+        assert!(share.view_settings.allow_interaction);
+        
+        // An explicit false is applied, an explicit true undoes a default
+        let share = update_share(&ctx, &user, &created.id, update(serde_json::json!({
+            "viewSettings": { "allowInteraction": false, "showLegend": true },
+        }))).await.unwrap().body;
+        assert!(!share.view_settings.allow_interaction);
+        assert!(share.view_settings.show_legend);
+        assert!(!share.view_settings.show_title);
+        
+        // Later changes to the defaults show in the app, under the overrides
+        update_share_view_defaults(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "viewSettings": { "theme": "light", "rotateToCurrentMonth": false },
+        })).unwrap()).await.unwrap();
+        let shown = get_share(&ctx, &user, &created.id).await.unwrap().body.view_settings;
+        assert_eq!(shown.theme, ShareTheme::Light);
+        assert!(!shown.rotate_to_current_month);
+        assert!(shown.show_legend);
+        assert!(!shown.show_title);
+        let listed = list_shares(&ctx, &user, serde_json::from_value(serde_json::json!({})).unwrap()).await.unwrap().body.shares;
+        assert_eq!(listed[0].view_settings, shown);
+        
+        // Following the defaults again drops the overrides
+        let share = update_share(&ctx, &user, &created.id, update(serde_json::json!({ "inheritViewSettings": true }))).await.unwrap().body;
+        assert!(share.inherit_view_settings);
+        assert!(share.view_overrides.is_empty());
+        assert!(!share.view_settings.show_legend);
+        assert!(share.view_settings.allow_interaction);
+        
+        // Shares with their own settings no longer follow the defaults
+        let share = update_share(&ctx, &user, &created.id, update(serde_json::json!({
+            "viewSettings": { "showTitle": false }, "inheritViewSettings": false,
+        }))).await.unwrap().body;
+        assert!(!share.inherit_view_settings);
+        update_share_view_defaults(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "viewSettings": { "theme": "dark" },
+        })).unwrap()).await.unwrap();
+        let shown = get_share(&ctx, &user, &created.id).await.unwrap().body.view_settings;
+        assert_eq!((shown.theme, shown.show_title, shown.show_legend), (ShareTheme::Light, false, false));
+        
+        // New shares start from the defaults under the fields they set
+        let created = create_share(&ctx, &user, serde_json::from_value(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] }, "viewSettings": { "showLegend": true },
+        })).unwrap()).await.unwrap().body.share;
+        assert!(created.inherit_view_settings);
+        assert_eq!((created.view_settings.theme, created.view_settings.show_legend), (ShareTheme::Dark, true));
+    }
+    
+    #[tokio::test]
+    async fn test_share_text_limits() {
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let create = |name: String| serde_json::from_value::<CreateShareRequest>(serde_json::json!({
+            "visibility": "public", "layerConfig": { "layerIds": ["layer-1"] }, "name": name,
+        })).unwrap();
+        assert_eq!(create_share(&ctx, &user, create("x".repeat(201))).await.unwrap_err().status, 400);
+        // Counted in characters, not bytes
+        let share = create_share(&ctx, &user, create("å".repeat(200))).await.unwrap().body.share;
+        
+        let update = |body: serde_json::Value| serde_json::from_value::<UpdateShareRequest>(body).unwrap();
+        let long_name = update(serde_json::json!({ "name": "x".repeat(201) }));
+        assert_eq!(update_share(&ctx, &user, &share.id, long_name).await.unwrap_err().status, 400);
+        let long_description = update(serde_json::json!({ "description": "x".repeat(2001) }));
+        assert_eq!(update_share(&ctx, &user, &share.id, long_description).await.unwrap_err().status, 400);
+        let renamed = update(serde_json::json!({ "name": "Plan", "description": "x".repeat(2000) }));
+        assert_eq!(update_share(&ctx, &user, &share.id, renamed).await.unwrap().body.name.as_deref(), Some("Plan"));
+    }
+    
+    #[tokio::test]
+    async fn test_recurring_activity() {
+        let ctx = context();
+        let user = admin();
+        ctx.layer_storage.create(layer("layer-1")).await.unwrap();
+        let request = |recurrence: serde_json::Value| serde_json::from_value::<CreateActivityRequest>(serde_json::json!({
+            "title": "Staff meeting", "startDate": "2025-03-03T09:00:00Z", "endDate": "2025-03-31T23:59:59Z",
+            "type": "meeting", "color": "#3b82f6", "highlightColor": "#1d4ed8", "scope": "layer-1",
+            "recurrence": recurrence,
+        })).unwrap();
+        
+        // Exceptions must name a scheduled occurrence
+        let unknown = request(serde_json::json!({ "frequency": "weekly", "exceptions": [{ "date": "2025-03-11", "skip": true }] }));
+        assert_eq!(create_activity(&ctx, &user, unknown).await.unwrap_err().status, 400);
+        
+        let created = create_activity(&ctx, &user, request(serde_json::json!({
+            "frequency": "weekly", "exceptions": [{ "date": "2025-03-10", "skip": true }],
+        }))).await.unwrap().body;
+        assert_eq!(created.recurrence.as_ref().unwrap().exceptions.len(), 1);
+        
+        let moved_out: UpdateActivityRequest = serde_json::from_value(serde_json::json!({
+            "recurrence": { "frequency": "weekly", "exceptions": [{ "date": "2025-03-17", "startDate": "2025-04-02T09:00:00Z" }] },
+        })).unwrap();
+        assert_eq!(update_activity(&ctx, &user, &created.id, moved_out).await.unwrap_err().status, 400);
+        
+        // Omitted keeps the series, null ends it
+        let renamed: UpdateActivityRequest = serde_json::from_value(serde_json::json!({ "title": "Team meeting" })).unwrap();
+        assert!(update_activity(&ctx, &user, &created.id, renamed).await.unwrap().body.recurrence.is_some());
+        let single: UpdateActivityRequest = serde_json::from_value(serde_json::json!({ "recurrence": null })).unwrap();
+        assert!(update_activity(&ctx, &user, &created.id, single).await.unwrap().body.recurrence.is_none());
+    }
+    
+    #[test]
+    fn test_submission_status() {
+        let mut layer = Layer {
+            id: "layer-1".to_string(),
+            name: "Leadership".to_string(),
+            description: None,
+            layer_type: LayerType::Organization,
+            color: "#000000".to_string(),
+            ring_index: 0,
+            is_visible: true,
+            requires_approval: true,
+            visible_to_groups: Vec::new(),
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            organization_id: "org".to_string(),
+            created_by: "admin".to_string(),
+            created_at: Utc::now(),
+            updated_at: None,
+        };
+        let mut user = UserContext {
+            user_id: "user".to_string(),
+            organization_id: "org".to_string(),
+            display_name: None,
+            email: None,
+            is_admin: false,
+            roles: vec![],
+            groups: None,
+            scopes: None,
+            team: None,
+        };
+        
+        assert_eq!(submission_status(&layer, &user), ApprovalStatus::PendingApproval);
+        
+        user.is_admin = true;
+        assert_eq!(submission_status(&layer, &user), ApprovalStatus::Approved);
+        
+        user.is_admin = false;
+        layer.requires_approval = false;
+        assert_eq!(submission_status(&layer, &user), ApprovalStatus::Approved);
+    }
+}
@@ -0,0 +1,221 @@
+//! # Health Checks
+//!
+//! `GET /api/health` tells Azure Front Door and monitoring whether this
+//! instance can reach its storage, so a wrong account name, missing role
+//! assignment or deleted container shows up before users hit it. The
+//! backend's [`StorageProbe`] checks each table or container:
+//!
+//! | Backend | Probe |
+//! |---------|-------|
+//! | Table Storage | query of an empty partition of every table |
+//! | Cosmos DB | read of every container's properties |
+//! | SQLite | read of a row of every table |
+//! | memory | none; always healthy |
+//!
+//! Probes only read, since `GET /api/health` needs no sign-in. They run
+//! concurrently, so a report takes as long as the slowest check rather than
+//! their sum and stays inside Front Door's health probe timeout.
+//!
+//! Each check gets [`PROBE_TIMEOUT`] and is reported with its latency; one
+//! slower than [`SLOW_PROBE`] is `degraded`. The response is 503 when any
+//! check is `unhealthy` and 200 otherwise. Failures are reported by kind
+//! only; the error itself goes to the log.
+//!
+//! The report is reused for [`CACHE_FOR`], so frequent probes from many
+//! Front Door edges make a few storage requests, not one per edge.
+
+use crate::models::{HealthCheck, HealthReport, HealthStatus};
+use crate::storage::{StorageError, StorageProbe};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Longest wait for one check
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Checks slower than this are degraded
+pub const SLOW_PROBE: Duration = Duration::from_millis(500);
+
+/// How long a report is served before storage is probed again
+pub const CACHE_FOR: Duration = Duration::from_secs(10);
+
+/// Probes storage and keeps the latest report
+pub struct HealthChecker {
+    probe: Option<Arc<dyn StorageProbe>>,
+    timeout: Duration,
+    last: Mutex<Option<(Instant, HealthReport)>>,
+}
+
+impl HealthChecker {
+    pub fn new(probe: Option<Arc<dyn StorageProbe>>) -> Self {
+        Self { probe, timeout: PROBE_TIMEOUT, last: Mutex::new(None) }
+    }
+
+    /// Wait `timeout` for each check instead of [`PROBE_TIMEOUT`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The latest report, probing storage again once it is older than [`CACHE_FOR`]
+    ///
+    /// Concurrent callers wait for one probe rather than each starting their own.
+    pub async fn check(&self) -> HealthReport {
+        let mut last = self.last.lock().await;
+        if let Some((at, ref report)) = *last {
+            if at.elapsed() < CACHE_FOR {
+                return report.clone();
+            }
+        }
+        let report = self.probe_all().await;
+        *last = Some((Instant::now(), report.clone()));
+        report
+    }
+
+    async fn probe_all(&self) -> HealthReport {
+        let Some(ref probe) = self.probe else {
+            return HealthReport { status: HealthStatus::Healthy, backend: None, checked_at: Utc::now(), checks: Vec::new() };
+        };
+
+        let mut probes = tokio::task::JoinSet::new();
+        for (index, target) in probe.targets().into_iter().enumerate() {
+            let (probe, timeout) = (probe.clone(), self.timeout);
+            probes.spawn(async move {
+                let started = Instant::now();
+                let result = tokio::time::timeout(timeout, probe.probe(target)).await;
+                (index, target, result, started.elapsed())
+            });
+        }
+        let mut results = probes.join_all().await;
+        results.sort_by_key(|(index, ..)| *index);
+
+        let mut checks = Vec::new();
+        for (_, target, result, elapsed) in results {
+            let (status, error) = match result {
+                Ok(Ok(())) if elapsed > SLOW_PROBE => (HealthStatus::Degraded, None),
+                Ok(Ok(())) => (HealthStatus::Healthy, None),
+                Ok(Err(e)) => {
+                    tracing::warn!("Health probe of {} {} failed: {}", probe.name(), target, e);
+                    (HealthStatus::Unhealthy, Some(error_kind(&e)))
+                }
+                Err(_) => {
+                    tracing::warn!("Health probe of {} {} timed out after {:?}", probe.name(), target, self.timeout);
+                    (HealthStatus::Unhealthy, Some("timeout"))
+                }
+            };
+            checks.push(HealthCheck {
+                target: target.to_string(),
+                status,
+                latency_ms: elapsed.as_millis() as u64,
+                error: error.map(str::to_string),
+            });
+        }
+
+        HealthReport {
+            status: checks.iter().map(|c| c.status).max().unwrap_or(HealthStatus::Healthy),
+            backend: Some(probe.name().to_string()),
+            checked_at: Utc::now(),
+            checks,
+        }
+    }
+}
+
+/// What to report of a failed check without revealing account details
+fn error_kind(e: &StorageError) -> &'static str {
+    match e {
+        StorageError::Transient(_) => "throttled",
+        StorageError::Unauthorized(_) => "unauthorized",
+        _ => "error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// `ok` answers, `denied` is refused, `stuck` never answers in time
+    struct FakeProbe {
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl StorageProbe for FakeProbe {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn targets(&self) -> Vec<&'static str> {
+            vec!["ok", "denied", "stuck"]
+        }
+
+        async fn probe(&self, target: &str) -> Result<(), StorageError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match target {
+                "denied" => Err(StorageError::Unauthorized("account key for https://secret.example".to_string())),
+                "stuck" => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report() {
+        let probe = Arc::new(FakeProbe { calls: AtomicU32::new(0) });
+        let checker = HealthChecker::new(Some(probe.clone())).with_timeout(Duration::from_millis(50));
+
+        let report = checker.check().await;
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert_eq!(report.backend.as_deref(), Some("fake"));
+        let outcome: Vec<_> = report.checks.iter().map(|c| (c.target.as_str(), c.status, c.error.as_deref())).collect();
+        assert_eq!(outcome, [
+            ("ok", HealthStatus::Healthy, None),
+            ("denied", HealthStatus::Unhealthy, Some("unauthorized")),
+            ("stuck", HealthStatus::Unhealthy, Some("timeout")),
+        ]);
+
+        // Served from the cache
+        assert_eq!(checker.check().await, report);
+        assert_eq!(probe.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_probes_run_concurrently() {
+        struct SlowProbe;
+
+        #[async_trait]
+        impl StorageProbe for SlowProbe {
+            fn name(&self) -> &'static str {
+                "slow"
+            }
+
+            fn targets(&self) -> Vec<&'static str> {
+                vec!["a", "b", "c", "d"]
+            }
+
+            async fn probe(&self, _: &str) -> Result<(), StorageError> {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok(())
+            }
+        }
+
+        let started = Instant::now();
+        let report = HealthChecker::new(Some(Arc::new(SlowProbe))).check().await;
+        assert!(started.elapsed() < Duration::from_millis(350));
+        let targets: Vec<_> = report.checks.iter().map(|c| c.target.as_str()).collect();
+        assert_eq!(targets, ["a", "b", "c", "d"]);
+        assert_eq!(report.status, HealthStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_without_probe() {
+        let report = HealthChecker::new(None).check().await;
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert!(report.checks.is_empty());
+    }
+}
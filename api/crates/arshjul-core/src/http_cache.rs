@@ -0,0 +1,151 @@
+//! # HTTP Caching
+//!
+//! Central `Cache-Control` / `ETag` policy for every endpoint, applied once in
+//! the HTTP layer instead of ad hoc in individual handlers.
+//!
+//! | Route | Policy |
+//! |-------|--------|
+//! | Exports (`.svg`, `.png`, `/api/exports/*`) | `public, max-age=31536000, immutable` - URLs are content-hash keyed |
+//! | `GET /api/public/*` | `public, max-age=60, must-revalidate` + `ETag` / `If-None-Match` |
+//! | Everything else (authenticated) | `no-store` |
+//!
+//! Error responses are always `no-store`, so a transient 404/500 never gets
+//! pinned in a browser or CDN cache.
+
+#[cfg(feature = "server")]
+use crate::handlers::HttpResponse;
+#[cfg(feature = "server")]
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Max-age for public share data
+pub const PUBLIC_MAX_AGE_SECONDS: u32 = 60;
+
+/// Max-age for content-addressed exports (one year)
+pub const IMMUTABLE_MAX_AGE_SECONDS: u32 = 31_536_000;
+
+/// Cache policy for a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Never store (authenticated data, errors, mutations)
+    NoStore,
+    
+    /// Short shared caching with revalidation via ETag
+    Revalidate { max_age: u32 },
+    
+    /// Cache forever; the URL changes whenever the content does
+    Immutable,
+}
+
+impl CachePolicy {
+    /// Select the policy for a request
+    pub fn for_request(method: &str, path: &str) -> Self {
+        if !method.eq_ignore_ascii_case("GET") && !method.eq_ignore_ascii_case("HEAD") {
+            return CachePolicy::NoStore;
+        }
+        
+        let path = path.split('?').next().unwrap_or(path);
+        if path.starts_with("/api/exports/") || path.ends_with(".svg") || path.ends_with(".png") {
+            CachePolicy::Immutable
+        } else if path.starts_with("/api/public/") {
+            CachePolicy::Revalidate { max_age: PUBLIC_MAX_AGE_SECONDS }
+        } else {
+            CachePolicy::NoStore
+        }
+    }
+    
+    /// `Cache-Control` header value
+    pub fn header_value(&self) -> String {
+        match self {
+            CachePolicy::NoStore => "no-store".to_string(),
+            CachePolicy::Revalidate { max_age } => format!("public, max-age={}, must-revalidate", max_age),
+            CachePolicy::Immutable => format!("public, max-age={}, immutable", IMMUTABLE_MAX_AGE_SECONDS),
+        }
+    }
+    
+    /// Whether responses carry an ETag
+    #[cfg(feature = "server")]
+    fn uses_etag(&self) -> bool {
+        !matches!(self, CachePolicy::NoStore)
+    }
+}
+
+/// Hex SHA-256 content hash (first 16 bytes), used for ETags and export URLs
+pub fn content_hash(bytes: &[u8]) -> String {
+    hex::encode(&Sha256::digest(bytes)[..16])
+}
+
+/// Check an `If-None-Match` header against an ETag
+#[cfg(feature = "server")]
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',')
+        .map(|t| t.trim().trim_start_matches("W/"))
+        .any(|t| t == "*" || t == etag)
+}
+
+/// Apply the route's cache policy to a successful response
+///
+/// Returns status 304 when the client's `If-None-Match` matches; the body
+/// must then be omitted by the runtime.
+#[cfg(feature = "server")]
+pub fn apply_cache_policy<T: Serialize>(
+    method: &str,
+    path: &str,
+    if_none_match: Option<&str>,
+    response: HttpResponse<T>,
+) -> HttpResponse<T> {
+    let policy = if response.status >= 400 {
+        CachePolicy::NoStore
+    } else {
+        CachePolicy::for_request(method, path)
+    };
+    
+    let mut response = response.with_header("Cache-Control", &policy.header_value());
+    
+    if policy.uses_etag() && response.status == 200 {
+        let body = serde_json::to_vec(&response.body).unwrap_or_default();
+        let etag = format!("\"{}\"", content_hash(&body));
+        
+        if if_none_match.is_some_and(|h| etag_matches(h, &etag)) {
+            response.status = 304;
+        }
+        response = response.with_header("ETag", &etag);
+    }
+    
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[cfg(feature = "server")]
+    fn header<'a, T: Serialize>(response: &'a HttpResponse<T>, name: &str) -> Option<&'a str> {
+        response.headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+    
+    #[test]
+    fn test_policy_per_route() {
+        assert_eq!(CachePolicy::for_request("GET", "/api/shares"), CachePolicy::NoStore);
+        assert_eq!(CachePolicy::for_request("POST", "/api/public/s/abc/report"), CachePolicy::NoStore);
+        assert_eq!(CachePolicy::for_request("GET", "/api/public/s/abc?k=key"), CachePolicy::Revalidate { max_age: PUBLIC_MAX_AGE_SECONDS });
+        assert_eq!(CachePolicy::for_request("GET", "/api/exports/3f2a.svg"), CachePolicy::Immutable);
+    }
+    
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_etag_and_not_modified() {
+        let response = apply_cache_policy("GET", "/api/public/s/abc", None, HttpResponse::ok("wheel"));
+        assert_eq!(response.status, 200);
+        assert_eq!(header(&response, "Cache-Control"), Some("public, max-age=60, must-revalidate"));
+        let etag = header(&response, "ETag").unwrap().to_string();
+        
+        let cached = apply_cache_policy("GET", "/api/public/s/abc", Some(&format!("W/{}", etag)), HttpResponse::ok("wheel"));
+        assert_eq!(cached.status, 304);
+        
+        let private = apply_cache_policy("GET", "/api/activities", Some(&etag), HttpResponse::ok("wheel"));
+        assert_eq!(private.status, 200);
+        assert_eq!(header(&private, "Cache-Control"), Some("no-store"));
+        assert!(header(&private, "ETag").is_none());
+    }
+}
@@ -0,0 +1,224 @@
+//! # Localized Texts
+//!
+//! Text written for people rather than clients, in the languages of
+//! [`ExportLocale`]:
+//!
+//! - **Calendars** - event `SUMMARY` prefixes by activity type
+//!   (`Deadline: Budget`, `Frist: Budsjett`) and the calendar name of
+//!   untitled shares
+//! - **Digest emails** - subject and activity list of the periodic digest
+//!   ([`DigestFrequency`])
+//! - **Reminders** - activity start reminders and share expiry reminders
+//!   with their renewal link
+//!
+//! Dates in running text use [`ExportLocale::format_long_date`]
+//! (`Mon Mar 3, 2025`, `man 3. mar 2025`).
+//!
+//! ## Locale
+//!
+//! [`resolve`] picks the recipient's language: their own
+//! ([`UserSettings::locale`]) when set and known, else the organization's
+//! ([`CalendarSettings::locale`]), else English. Anonymous readers, such as
+//! calendar feed subscribers, get the organization's.
+
+use crate::export::ExportLocale;
+use crate::models::{Activity, ActivityType, CalendarSettings, DigestFrequency, ShareLink, ShareRenewalReminder, UserSettings};
+use chrono::NaiveDate;
+
+/// Subject and plain-text body of an email
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalizedEmail {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Locale of a recipient: theirs, else the organization's, else English
+pub fn resolve(user: Option<&UserSettings>, calendar: &CalendarSettings) -> ExportLocale {
+    user.and_then(|u| u.locale.as_deref())
+        .and_then(|l| ExportLocale::parse(l).ok())
+        .or_else(|| ExportLocale::parse(&calendar.locale).ok())
+        .unwrap_or_default()
+}
+
+pub fn activity_type_label(locale: ExportLocale, activity_type: &ActivityType) -> &'static str {
+    match (locale, activity_type) {
+        (ExportLocale::English, ActivityType::Meeting) => "Meeting",
+        (ExportLocale::English, ActivityType::Deadline) => "Deadline",
+        (ExportLocale::English, ActivityType::Event) => "Event",
+        (ExportLocale::English, ActivityType::Planning) => "Planning",
+        (ExportLocale::English, ActivityType::Review) => "Review",
+        (ExportLocale::English, ActivityType::Training) => "Training",
+        (ExportLocale::English, ActivityType::Holiday) => "Holiday",
+        (ExportLocale::English, ActivityType::Other) => "Other",
+        (ExportLocale::Norwegian, ActivityType::Meeting) => "Møte",
+        (ExportLocale::Norwegian, ActivityType::Deadline) => "Frist",
+        (ExportLocale::Norwegian, ActivityType::Event) => "Arrangement",
+        (ExportLocale::Norwegian, ActivityType::Planning) => "Planlegging",
+        (ExportLocale::Norwegian, ActivityType::Review) => "Gjennomgang",
+        (ExportLocale::Norwegian, ActivityType::Training) => "Opplæring",
+        (ExportLocale::Norwegian, ActivityType::Holiday) => "Ferie",
+        (ExportLocale::Norwegian, ActivityType::Other) => "Annet",
+    }
+}
+
+/// Event title: the activity title behind its type, except for `other`
+pub fn event_summary(locale: ExportLocale, activity: &Activity) -> String {
+    match &activity.activity_type {
+        ActivityType::Other => activity.title.clone(),
+        activity_type => format!("{}: {}", activity_type_label(locale, activity_type), activity.title),
+    }
+}
+
+/// Title of a share in calendars and exports; untitled shares get the locale's default
+pub fn share_title(locale: ExportLocale, share: &ShareLink) -> String {
+    share.view_settings.custom_title.clone()
+        .or(share.name.clone())
+        .unwrap_or_else(|| locale.default_title().to_string())
+}
+
+/// One date, or a range of dates
+pub fn date_range(locale: ExportLocale, start: NaiveDate, end: NaiveDate) -> String {
+    if end <= start {
+        locale.format_long_date(start)
+    } else {
+        format!("{} – {}", locale.format_long_date(start), locale.format_long_date(end))
+    }
+}
+
+/// `today`, `tomorrow` or `in 3 days`
+fn days_away(locale: ExportLocale, days: i64) -> String {
+    match (locale, days) {
+        (ExportLocale::English, ..=0) => "today".to_string(),
+        (ExportLocale::English, 1) => "tomorrow".to_string(),
+        (ExportLocale::English, n) => format!("in {} days", n),
+        (ExportLocale::Norwegian, ..=0) => "i dag".to_string(),
+        (ExportLocale::Norwegian, 1) => "i morgen".to_string(),
+        (ExportLocale::Norwegian, n) => format!("om {} dager", n),
+    }
+}
+
+/// Activity lines of digests: dates, then the event summary
+fn activity_lines(locale: ExportLocale, activities: &[Activity]) -> String {
+    activities.iter()
+        .map(|a| format!("- {}: {}", date_range(locale, a.start_date.date_naive(), a.end_date.date_naive()), event_summary(locale, a)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Periodic digest of upcoming activities
+pub fn digest_email(locale: ExportLocale, frequency: DigestFrequency, activities: &[Activity]) -> LocalizedEmail {
+    let subject = match (locale, frequency) {
+        (ExportLocale::English, DigestFrequency::Daily) => "Your daily Annual Wheel digest",
+        (ExportLocale::English, DigestFrequency::Weekly) => "Your weekly Annual Wheel digest",
+        (ExportLocale::English, DigestFrequency::Monthly) => "Your monthly Annual Wheel digest",
+        (ExportLocale::Norwegian, DigestFrequency::Daily) => "Din daglige oversikt fra årshjulet",
+        (ExportLocale::Norwegian, DigestFrequency::Weekly) => "Din ukentlige oversikt fra årshjulet",
+        (ExportLocale::Norwegian, DigestFrequency::Monthly) => "Din månedlige oversikt fra årshjulet",
+    };
+    let intro = match (locale, activities.len()) {
+        (ExportLocale::English, 0) => "No upcoming activities.".to_string(),
+        (ExportLocale::English, 1) => "1 upcoming activity:".to_string(),
+        (ExportLocale::English, n) => format!("{} upcoming activities:", n),
+        (ExportLocale::Norwegian, 0) => "Ingen kommende aktiviteter.".to_string(),
+        (ExportLocale::Norwegian, 1) => "1 kommende aktivitet:".to_string(),
+        (ExportLocale::Norwegian, n) => format!("{} kommende aktiviteter:", n),
+    };
+
+    let mut body = intro;
+    if !activities.is_empty() {
+        body.push_str("\n\n");
+        body.push_str(&activity_lines(locale, activities));
+    }
+    LocalizedEmail { subject: subject.to_string(), body }
+}
+
+/// Reminder of an activity starting in `days_until` days
+pub fn activity_reminder(locale: ExportLocale, activity: &Activity, days_until: i64) -> LocalizedEmail {
+    let when = days_away(locale, days_until);
+    let subject = match locale {
+        ExportLocale::English => format!("Reminder: {} starts {}", activity.title, when),
+        ExportLocale::Norwegian => format!("Påminnelse: {} starter {}", activity.title, when),
+    };
+
+    let mut body = format!(
+        "{}\n{}",
+        event_summary(locale, activity),
+        date_range(locale, activity.start_date.date_naive(), activity.end_date.date_naive()),
+    );
+    if let Some(description) = activity.description.as_deref().filter(|d| !d.is_empty()) {
+        body.push_str("\n\n");
+        body.push_str(description);
+    }
+    LocalizedEmail { subject, body }
+}
+
+/// Expiry reminder for a share owner, with the renewal link
+pub fn share_expiry_reminder(locale: ExportLocale, reminder: &ShareRenewalReminder) -> LocalizedEmail {
+    let when = days_away(locale, reminder.days_left);
+    let expires = locale.format_long_date(reminder.expires_at.date_naive());
+    let link_expires = locale.format_long_date(reminder.link_expires_at.date_naive());
+    let name = reminder.share_name.as_deref();
+    let (subject, body) = match locale {
+        ExportLocale::English => (
+            match name {
+                Some(name) => format!("Your share \"{}\" expires {}", name, when),
+                None => format!("Your share expires {}", when),
+            },
+            format!(
+                "The share expires on {}. Renew it without signing in:\n{}\n\nThe link works once, until {}.",
+                expires, reminder.renewal_url, link_expires,
+            ),
+        ),
+        ExportLocale::Norwegian => (
+            match name {
+                Some(name) => format!("Delingen «{}» utløper {}", name, when),
+                None => format!("Delingen din utløper {}", when),
+            },
+            format!(
+                "Delingen utløper {}. Forny den uten å logge inn:\n{}\n\nLenken kan brukes én gang, frem til {}.",
+                expires, reminder.renewal_url, link_expires,
+            ),
+        ),
+    };
+    LocalizedEmail { subject, body }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity() -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Budsjett", "startDate": "2025-03-03T00:00:00Z",
+            "endDate": "2025-03-04T00:00:00Z", "type": "deadline", "color": "#000000",
+            "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_user_locale_wins() {
+        let calendar = CalendarSettings { locale: "nb".to_string(), ..Default::default() };
+        let mut user = UserSettings::new("user-1".to_string(), "org-1".to_string());
+        assert_eq!(resolve(Some(&user), &calendar), ExportLocale::Norwegian);
+        user.locale = Some("en-GB".to_string());
+        assert_eq!(resolve(Some(&user), &calendar), ExportLocale::English);
+        // Unknown locales fall through to the organization's
+        user.locale = Some("de".to_string());
+        assert_eq!(resolve(Some(&user), &calendar), ExportLocale::Norwegian);
+        assert_eq!(resolve(None, &CalendarSettings { locale: "fr".to_string(), ..Default::default() }), ExportLocale::English);
+    }
+
+    #[test]
+    fn test_texts() {
+        let activity = activity();
+        assert_eq!(event_summary(ExportLocale::Norwegian, &activity), "Frist: Budsjett");
+        assert_eq!(ExportLocale::English.format_long_date(NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()), "Mon Mar 3, 2025");
+
+        let digest = digest_email(ExportLocale::Norwegian, DigestFrequency::Weekly, std::slice::from_ref(&activity));
+        assert_eq!(digest.subject, "Din ukentlige oversikt fra årshjulet");
+        assert_eq!(digest.body, "1 kommende aktivitet:\n\n- man 3. mar 2025 – tir 4. mar 2025: Frist: Budsjett");
+
+        let reminder = activity_reminder(ExportLocale::English, &activity, 1);
+        assert_eq!(reminder.subject, "Reminder: Budsjett starts tomorrow");
+    }
+}
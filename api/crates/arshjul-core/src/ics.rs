@@ -0,0 +1,297 @@
+//! # iCalendar Export
+//!
+//! Renders activities as an RFC 5545 calendar. Activities are whole-day
+//! events; the first link becomes the event `URL` and every link is listed
+//! in the description, since `URL` can only appear once per event.
+//!
+//! Feeds can be narrowed with [`CalendarFilter`] so subscribers can overlay
+//! just the layers, types, tags or dates they care about.
+//!
+//! A recurring activity is one event with an `RRULE` ending on its last
+//! scheduled occurrence. Skipped occurrences are listed in `EXDATE`; moved
+//! and renamed ones follow as events with the same `UID` and the original
+//! date in `RECURRENCE-ID` (see [`crate::recurrence`]).
+
+use crate::export::ExportLocale;
+use crate::i18n;
+use crate::models::{Activity, ActivityType, CalendarFeedQuery, RecurrenceFrequency};
+use crate::recurrence;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// Product identifier
+const PRODID: &str = "-//Annual Wheel//Arshjul//EN";
+
+/// Widest `from`..`to` window a feed may request
+pub const MAX_WINDOW_DAYS: i64 = 3 * 366;
+
+// ============================================
+// Feed Filters
+// ============================================
+
+/// Parsed feed filters; `None` means unrestricted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarFilter {
+    pub layer_ids: Option<Vec<String>>,
+    pub types: Option<Vec<ActivityType>>,
+    pub tags: Option<Vec<String>>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Split a comma-separated query value, dropping empty entries
+fn split_list(value: Option<&str>) -> Option<Vec<String>> {
+    let items: Vec<String> = value?.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    Some(items).filter(|i| !i.is_empty())
+}
+
+impl CalendarFilter {
+    /// Parse and validate query parameters
+    pub fn parse(query: &CalendarFeedQuery) -> Result<Self, String> {
+        let types = split_list(query.types.as_deref())
+            .map(|names| names.iter()
+                .map(|n| serde_json::from_value(serde_json::Value::String(n.to_lowercase()))
+                    .map_err(|_| format!("Unknown activity type: {}", n)))
+                .collect::<Result<Vec<ActivityType>, _>>())
+            .transpose()?;
+        
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            if to < from {
+                return Err("`to` must be on or after `from`".to_string());
+            }
+            if (to - from).num_days() > MAX_WINDOW_DAYS {
+                return Err(format!("Date window too wide (max {} days)", MAX_WINDOW_DAYS));
+            }
+        }
+        
+        Ok(Self {
+            layer_ids: split_list(query.layers.as_deref()),
+            types,
+            tags: split_list(query.tags.as_deref())
+                .map(|tags| tags.iter().map(|t| t.to_lowercase()).collect()),
+            from: query.from,
+            to: query.to,
+        })
+    }
+    
+    /// Whether a date window was requested (otherwise feeds cover the share's year)
+    pub fn has_window(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+    
+    /// Whether an activity passes every filter; windows match on overlap
+    pub fn matches(&self, activity: &Activity) -> bool {
+        self.layer_ids.as_ref().is_none_or(|ids| ids.contains(&activity.scope))
+            && self.types.as_ref().is_none_or(|types| types.contains(&activity.activity_type))
+            && self.tags.as_ref().is_none_or(|tags| activity.tags.iter().any(|t| tags.contains(t)))
+            && self.from.is_none_or(|from| activity.end_date.date_naive() >= from)
+            && self.to.is_none_or(|to| activity.start_date.date_naive() <= to)
+    }
+}
+
+/// `X-WR-CALNAME` for a feed: the share title, plus the layer names when
+/// the feed is narrowed to some layers
+pub fn calendar_name(title: &str, layer_names: &[String]) -> String {
+    if layer_names.is_empty() {
+        title.to_string()
+    } else {
+        format!("{} ({})", title, layer_names.join(", "))
+    }
+}
+
+// ============================================
+// Rendering
+// ============================================
+
+/// Escape a TEXT value
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Append a content line, folded at 75 octets
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// VEVENT lines for an activity, with `rule` lines after its dates
+fn push_event(out: &mut String, activity: &Activity, stamp: &str, locale: ExportLocale, rule: &[String]) {
+    push_line(out, "BEGIN:VEVENT");
+    push_line(out, &format!("UID:{}@arshjul", activity.id));
+    push_line(out, &format!("DTSTAMP:{}", stamp));
+    push_line(out, &format!("DTSTART;VALUE=DATE:{}", activity.start_date.format("%Y%m%d")));
+    // DTEND is exclusive for whole-day events
+    push_line(out, &format!("DTEND;VALUE=DATE:{}", (activity.end_date + Duration::days(1)).format("%Y%m%d")));
+    for line in rule {
+        push_line(out, line);
+    }
+    push_line(out, &format!("SUMMARY:{}", escape_text(&i18n::event_summary(locale, activity))));
+    
+    let mut description = activity.description.clone().unwrap_or_default();
+    for link in &activity.links {
+        if !description.is_empty() {
+            description.push('\n');
+        }
+        description.push_str(&format!("{}: {}", link.title, link.url));
+    }
+    if !description.is_empty() {
+        push_line(out, &format!("DESCRIPTION:{}", escape_text(&description)));
+    }
+    if let Some(link) = activity.links.first() {
+        push_line(out, &format!("URL:{}", link.url));
+    }
+    
+    push_line(out, "END:VEVENT");
+}
+
+/// The series event of a recurring activity, then one event per moved or
+/// renamed occurrence
+fn push_series(out: &mut String, activity: &Activity, stamp: &str, locale: ExportLocale) {
+    let (Some(rule), Some(last)) = (&activity.recurrence, recurrence::scheduled_dates(activity).last().copied()) else {
+        return;
+    };
+    let frequency = match rule.frequency {
+        RecurrenceFrequency::Daily => "DAILY",
+        RecurrenceFrequency::Weekly => "WEEKLY",
+        RecurrenceFrequency::Monthly => "MONTHLY",
+        RecurrenceFrequency::Yearly => "YEARLY",
+    };
+    let mut lines = vec![format!("RRULE:FREQ={};INTERVAL={};UNTIL={}", frequency, rule.interval, last.format("%Y%m%d"))];
+    let skipped: Vec<String> = rule.exceptions.iter()
+        .filter(|e| e.skip)
+        .map(|e| e.date.format("%Y%m%d").to_string())
+        .collect();
+    if !skipped.is_empty() {
+        lines.push(format!("EXDATE;VALUE=DATE:{}", skipped.join(",")));
+    }
+    
+    let first = Activity {
+        end_date: activity.start_date + Duration::days(rule.duration_days.into()),
+        ..activity.clone()
+    };
+    push_event(out, &first, stamp, locale, &lines);
+    
+    for occurrence in recurrence::occurrences(activity) {
+        if !rule.exceptions.iter().any(|e| e.date == occurrence.date) {
+            continue;
+        }
+        let changed = Activity {
+            title: occurrence.title,
+            start_date: occurrence.start_date,
+            end_date: occurrence.end_date,
+            ..first.clone()
+        };
+        push_event(out, &changed, stamp, locale, &[format!("RECURRENCE-ID;VALUE=DATE:{}", occurrence.date.format("%Y%m%d"))]);
+    }
+}
+
+/// A calendar with one event per activity, summaries prefixed with the type in `locale`
+pub fn render_calendar(name: &str, activities: &[Activity], now: DateTime<Utc>, locale: ExportLocale) -> String {
+    let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut out = String::new();
+    
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, &format!("PRODID:{}", PRODID));
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    push_line(&mut out, &format!("X-WR-CALNAME:{}", escape_text(name)));
+    for activity in activities {
+        if activity.recurrence.is_some() {
+            push_series(&mut out, activity, &stamp, locale);
+        } else {
+            push_event(&mut out, activity, &stamp, locale, &[]);
+        }
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_event_with_links() {
+        let activity: Activity = serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Budget; final", "startDate": "2025-03-03T00:00:00Z",
+            "endDate": "2025-03-04T00:00:00Z", "type": "deadline", "color": "#000000",
+            "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+            "links": [{ "title": "Agenda", "url": "https://contoso.sharepoint.com/agenda" }],
+        })).unwrap();
+        
+        let ics = render_calendar("Leadership", &[activity], Utc::now(), ExportLocale::English);
+        assert!(ics.contains("SUMMARY:Deadline: Budget\\; final\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20250305\r\n"));
+        assert!(ics.contains("URL:https://contoso.sharepoint.com/agenda\r\n"));
+        assert!(ics.lines().all(|l| l.trim_end_matches('\r').len() <= 75));
+    }
+    
+    #[test]
+    fn test_recurring_event() {
+        let activity: Activity = serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Staff meeting", "startDate": "2025-01-06T09:00:00Z",
+            "endDate": "2025-03-31T00:00:00Z", "type": "meeting", "color": "#000000",
+            "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+            "recurrence": {
+                "frequency": "weekly", "interval": 2,
+                "exceptions": [
+                    { "date": "2025-01-20", "skip": true },
+                    { "date": "2025-02-03", "skip": true },
+                    { "date": "2025-02-17", "startDate": "2025-02-18T09:00:00Z", "title": "Staff meeting (moved)" },
+                ],
+            },
+        })).unwrap();
+        
+        let ics = render_calendar("Staff", &[activity], Utc::now(), ExportLocale::English);
+        let events: Vec<&str> = ics.split("BEGIN:VEVENT").skip(1).collect();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains("DTSTART;VALUE=DATE:20250106\r\nDTEND;VALUE=DATE:20250107\r\n"));
+        assert!(events[0].contains("RRULE:FREQ=WEEKLY;INTERVAL=2;UNTIL=20250317\r\n"));
+        assert!(events[0].contains("EXDATE;VALUE=DATE:20250120,20250203\r\n"));
+        assert!(events[1].contains("UID:a-1@arshjul\r\n"));
+        assert!(events[1].contains("RECURRENCE-ID;VALUE=DATE:20250217\r\n"));
+        assert!(events[1].contains("DTSTART;VALUE=DATE:20250218\r\n"));
+        assert!(events[1].contains("SUMMARY:Meeting: Staff meeting (moved)\r\n"));
+    }
+    
+    #[test]
+    fn test_filter() {
+        let activity: Activity = serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Board meeting", "startDate": "2025-03-03T00:00:00Z",
+            "endDate": "2025-03-04T00:00:00Z", "type": "meeting", "color": "#000000",
+            "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+            "tags": ["board"],
+        })).unwrap();
+        let parse = |q: serde_json::Value| CalendarFilter::parse(&serde_json::from_value(q).unwrap());
+        
+        let filter = parse(serde_json::json!({ "layers": "layer-1, layer-2", "types": "Meeting", "tags": "Board,hr", "from": "2025-03-04" })).unwrap();
+        assert!(filter.matches(&activity));
+        assert!(!parse(serde_json::json!({ "types": "deadline" })).unwrap().matches(&activity));
+        assert!(!parse(serde_json::json!({ "to": "2025-03-02" })).unwrap().matches(&activity));
+        assert!(parse(serde_json::json!({ "types": "party" })).is_err());
+        assert!(parse(serde_json::json!({ "from": "2025-03-04", "to": "2025-03-01" })).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! # Search Engine Indexing
+//!
+//! Public shares stay out of search engines unless published on purpose.
+//! Organizations choose the default (`PUT /api/admin/policy/indexing`),
+//! and owners can override it per share (`PUT /api/shares/{id}/indexing`).
+//! Users shares are never indexable.
+//!
+//! The choice reaches crawlers two ways: public share and calendar
+//! responses carry an `X-Robots-Tag` header, and the share configuration
+//! carries `indexable` for the share page's robots meta tag. Snapshots only
+//! know the share's own flag.
+//!
+//! Deployments that publish wheels list the organizations to include in
+//! `GET /sitemap.xml` (`SITEMAP_ORGANIZATIONS`); it is disabled otherwise.
+//! Sitemap URLs carry the share key, so only shares whose owner turned
+//! indexing on themselves are listed - the organization default lets
+//! crawlers index a page they found, but never publishes its link. The
+//! rendered sitemap is cached for [`SITEMAP_CACHE_SECONDS`].
+
+use crate::markdown::escape_html;
+use crate::models::{ShareLink, ShareVisibility};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::RwLock;
+
+/// Header carrying the robots directives of API responses
+pub const ROBOTS_HEADER: &str = "X-Robots-Tag";
+
+/// Most URLs in one sitemap file (sitemaps.org limit)
+pub const MAX_SITEMAP_URLS: usize = 50_000;
+
+/// How long a rendered sitemap is served before shares are listed again
+pub const SITEMAP_CACHE_SECONDS: i64 = 3600;
+
+/// Whether search engines may index the share; `organization_default`
+/// applies unless the share sets its own flag
+pub fn is_indexable(share: &ShareLink, organization_default: bool) -> bool {
+    share.visibility == ShareVisibility::Public
+        && share.is_active
+        && share.indexable.unwrap_or(organization_default)
+}
+
+/// Whether `GET /sitemap.xml` may publish the share's keyed URL; the
+/// share itself must opt in, the organization default is not enough
+pub fn is_listable(share: &ShareLink) -> bool {
+    is_indexable(share, false) && share.indexable == Some(true)
+}
+
+/// Robots directives for a response
+pub fn robots(indexable: bool) -> &'static str {
+    if indexable { "index, follow" } else { "noindex, nofollow" }
+}
+
+/// Sitemap of share page URLs with their last modification, sorted by URL
+pub fn sitemap(mut urls: Vec<(String, DateTime<Utc>)>) -> String {
+    urls.sort();
+    urls.truncate(MAX_SITEMAP_URLS);
+    
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (url, modified) in urls {
+        xml.push_str(&format!(
+            "  <url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+            escape_html(&url), modified.format("%Y-%m-%d")
+        ));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// Last rendered sitemap, shared by every request of the instance
+#[derive(Default)]
+pub struct SitemapCache {
+    rendered: RwLock<Option<(DateTime<Utc>, String)>>,
+}
+
+impl SitemapCache {
+    /// The cached sitemap unless it is older than [`SITEMAP_CACHE_SECONDS`]
+    pub fn get(&self, now: DateTime<Utc>) -> Option<String> {
+        let rendered = self.rendered.read().ok()?;
+        rendered.as_ref()
+            .filter(|(at, _)| now - *at < Duration::seconds(SITEMAP_CACHE_SECONDS))
+            .map(|(_, xml)| xml.clone())
+    }
+    
+    /// Cache a sitemap rendered at `now`
+    pub fn put(&self, now: DateTime<Utc>, xml: &str) {
+        if let Ok(mut rendered) = self.rendered.write() {
+            *rendered = Some((now, xml.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn share(visibility: &str, indexable: Option<bool>) -> ShareLink {
+        serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "key", "shortCode": "abc12345", "visibility": visibility,
+            "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+            "expiresAt": "2099-01-01T00:00:00Z", "layerConfig": { "layerIds": [] }, "viewSettings": {},
+            "isActive": true, "indexable": indexable,
+        })).unwrap()
+    }
+    
+    #[test]
+    fn test_is_indexable() {
+        assert!(is_indexable(&share("public", None), true));
+        assert!(!is_indexable(&share("public", None), false));
+        assert!(is_indexable(&share("public", Some(true)), false));
+        assert!(!is_indexable(&share("public", Some(false)), true));
+        assert!(!is_indexable(&share("users", Some(true)), true));
+        assert_eq!(robots(false), "noindex, nofollow");
+    }
+    
+    #[test]
+    fn test_is_listable() {
+        assert!(is_listable(&share("public", Some(true))));
+        assert!(!is_listable(&share("public", None)));
+        assert!(!is_listable(&share("users", Some(true))));
+    }
+    
+    #[test]
+    fn test_sitemap_cache() {
+        let cache = SitemapCache::default();
+        let now: DateTime<Utc> = "2025-03-01T10:00:00Z".parse().unwrap();
+        assert_eq!(cache.get(now), None);
+        cache.put(now, "<urlset/>");
+        assert_eq!(cache.get(now + Duration::seconds(SITEMAP_CACHE_SECONDS - 1)).as_deref(), Some("<urlset/>"));
+        assert_eq!(cache.get(now + Duration::seconds(SITEMAP_CACHE_SECONDS)), None);
+    }
+    
+    #[test]
+    fn test_sitemap() {
+        let modified: DateTime<Utc> = "2025-03-01T10:00:00Z".parse().unwrap();
+        let xml = sitemap(vec![
+            ("https://wheel.example/s/B?k=2&x".to_string(), modified),
+            ("https://wheel.example/s/A?k=1".to_string(), modified),
+        ]);
+        assert!(xml.starts_with("<?xml"));
+        let a = xml.find("<loc>https://wheel.example/s/A?k=1</loc><lastmod>2025-03-01</lastmod>").unwrap();
+        let b = xml.find("<loc>https://wheel.example/s/B?k=2&amp;x</loc>").unwrap();
+        assert!(a < b);
+    }
+}
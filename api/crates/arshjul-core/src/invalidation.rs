@@ -0,0 +1,374 @@
+//! # Cache Invalidation
+//!
+//! Purges cached public share responses when the underlying data changes,
+//! whatever the cache: CDN (Front Door), Redis, or in-process.
+//!
+//! [`CacheInvalidation`] is an event bus subscriber. It maps each change to
+//! the affected short codes and hands them to every registered
+//! [`CacheInvalidator`] once. Codes an invalidator fails on go to its outbox
+//! (an [`AuditExporter`] queue), which [`CacheInvalidation::run`] flushes in
+//! the background with exponential backoff, so a slow CDN never holds up
+//! the event bus:
+//!
+//! | Event | Short codes |
+//! |-------|-------------|
+//! | Share created/updated/deleted | the share's own |
+//! | Activity changed | the shares of its layers ([`ShareStorage::list_by_layer`]) |
+//! | Layer changed | the shares of the layer |
+//! | Activity type changed, or an activity whose layers aren't known | every share of the organization |
+//! | Share auto-deactivated after reports | the reported share's |
+
+use crate::audit_export::{AuditExportError, AuditExporter, AuditSink};
+use crate::events::{ChangeKind, DomainEvent, EntityKind, EventError, EventSubscriber};
+use crate::share_cache::ShareCache;
+use crate::storage::{list_all_shares, ShareStorage, StorageError};
+use async_trait::async_trait;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Short codes held in an invalidator's outbox while it is unreachable
+pub const MAX_QUEUED_SHORT_CODES: usize = 10_000;
+
+/// Default outbox flush interval
+pub const DEFAULT_RETRY_INTERVAL_SECONDS: u64 = 10;
+
+/// Public route a short code is served under
+pub fn public_share_path(short_code: &str) -> String {
+    format!("/api/public/s/{}", short_code)
+}
+
+/// A cache that can drop entries for specific shares
+#[async_trait]
+pub trait CacheInvalidator: Send + Sync {
+    /// Name for logs and metrics
+    fn name(&self) -> &'static str;
+    
+    /// Drop cached responses for the given short codes
+    async fn invalidate(&self, short_codes: &[String]) -> Result<(), EventError>;
+}
+
+/// In-process cache invalidation through a callback
+pub struct CallbackInvalidator<F> {
+    callback: F,
+}
+
+impl<F: Fn(&[String]) + Send + Sync> CallbackInvalidator<F> {
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+#[async_trait]
+impl<F: Fn(&[String]) + Send + Sync> CacheInvalidator for CallbackInvalidator<F> {
+    fn name(&self) -> &'static str {
+        "in-process"
+    }
+    
+    async fn invalidate(&self, short_codes: &[String]) -> Result<(), EventError> {
+        (self.callback)(short_codes);
+        Ok(())
+    }
+}
+
+/// Drops shares from a [`ShareCache`], for changes other instances made
+pub struct ShareCacheInvalidator {
+    cache: Arc<dyn ShareCache>,
+}
+
+impl ShareCacheInvalidator {
+    pub fn new(cache: Arc<dyn ShareCache>) -> Self {
+        Self { cache }
+    }
+}
+
+#[async_trait]
+impl CacheInvalidator for ShareCacheInvalidator {
+    fn name(&self) -> &'static str {
+        "share-cache"
+    }
+    
+    async fn invalidate(&self, short_codes: &[String]) -> Result<(), EventError> {
+        for code in short_codes {
+            self.cache.remove(code).await.map_err(|e| EventError::Delivery(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Outbox delivery through an invalidator
+struct InvalidatorSink(Arc<dyn CacheInvalidator>);
+
+#[async_trait]
+impl AuditSink<String> for InvalidatorSink {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+    
+    async fn send(&self, short_codes: &[String]) -> Result<(), AuditExportError> {
+        self.0.invalidate(short_codes).await.map_err(|e| AuditExportError::Unavailable(e.to_string()))
+    }
+}
+
+/// Retry behavior for failed invalidations
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each further retry
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+        }
+    }
+}
+
+/// Counters per invalidator
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvalidationStats {
+    /// Short codes submitted for invalidation
+    pub keys: u64,
+    pub succeeded: u64,
+    /// Outbox flushes that failed after all retries (the codes stay queued)
+    pub failed: u64,
+    /// Invalidations that failed and were queued for retry
+    pub retries: u64,
+}
+
+/// An invalidator and its outbox
+struct Registered {
+    invalidator: Arc<dyn CacheInvalidator>,
+    outbox: AuditExporter<String>,
+}
+
+/// Event subscriber fanning out invalidations
+pub struct CacheInvalidation {
+    invalidators: Vec<Registered>,
+    shares: Arc<dyn ShareStorage>,
+    retry: RetryPolicy,
+    stats: Mutex<HashMap<&'static str, InvalidationStats>>,
+}
+
+impl CacheInvalidation {
+    pub fn new(shares: Arc<dyn ShareStorage>, retry: RetryPolicy) -> Self {
+        Self {
+            invalidators: Vec::new(),
+            shares,
+            retry,
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Register an invalidator
+    pub fn register(&mut self, invalidator: Arc<dyn CacheInvalidator>) {
+        tracing::info!("Cache invalidator registered: {}", invalidator.name());
+        let outbox = AuditExporter::new(Arc::new(InvalidatorSink(invalidator.clone())), self.retry)
+            .with_max_queued(MAX_QUEUED_SHORT_CODES);
+        self.invalidators.push(Registered { invalidator, outbox });
+    }
+    
+    /// Short codes waiting for a retry, across invalidators
+    pub fn pending(&self) -> usize {
+        self.invalidators.iter().map(|r| r.outbox.pending()).sum()
+    }
+    
+    /// Current counters, by invalidator name
+    pub fn stats(&self) -> HashMap<&'static str, InvalidationStats> {
+        self.stats.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+    
+    fn record(&self, name: &'static str, update: impl FnOnce(&mut InvalidationStats)) {
+        if let Ok(mut stats) = self.stats.lock() {
+            update(stats.entry(name).or_default());
+        }
+    }
+    
+    /// Short codes affected by an event
+    async fn affected_short_codes(&self, event: &DomainEvent) -> Result<Vec<String>, StorageError> {
+        let (organization_id, layer_ids) = match event {
+            DomainEvent::EntityChanged(change) => match change.entity {
+                EntityKind::Share => return Ok(change.key.iter().cloned().collect()),
+                EntityKind::Activity if !change.layer_ids.is_empty() => (&change.organization_id, change.layer_ids.clone()),
+                EntityKind::Layer => (&change.organization_id, vec![change.entity_id.clone()]),
+                EntityKind::Activity | EntityKind::ActivityType => {
+                    let shares = list_all_shares(self.shares.as_ref(), &change.organization_id).await?;
+                    return Ok(shares.into_iter().map(|s| s.short_code).collect());
+                }
+            },
+            DomainEvent::ShareReported(report) if report.auto_deactivated => return Ok(vec![report.short_code.clone()]),
+            _ => return Ok(Vec::new()),
+        };
+        
+        let mut short_codes = BTreeSet::new();
+        for layer_id in &layer_ids {
+            let shares = self.shares.list_by_layer(organization_id, layer_id).await?;
+            short_codes.extend(shares.into_iter().map(|s| s.short_code));
+        }
+        Ok(short_codes.into_iter().collect())
+    }
+    
+    /// Retry every outbox once; returns the short codes delivered
+    pub async fn flush(&self) -> usize {
+        let mut delivered = 0;
+        for registered in &self.invalidators {
+            let name = registered.invalidator.name();
+            match registered.outbox.flush().await {
+                Ok(0) => {}
+                Ok(n) => {
+                    self.record(name, |s| s.succeeded += 1);
+                    delivered += n;
+                }
+                Err(e) => {
+                    tracing::warn!("Cache invalidation via {} failed, {} short codes pending: {}", name, registered.outbox.pending(), e);
+                    self.record(name, |s| s.failed += 1);
+                }
+            }
+        }
+        delivered
+    }
+    
+    /// Flush the outboxes periodically; spawn once at startup
+    pub async fn run(self: Arc<Self>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            self.flush().await;
+        }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for CacheInvalidation {
+    fn name(&self) -> &'static str {
+        "cache-invalidation"
+    }
+    
+    async fn handle(&self, event: &DomainEvent) -> Result<(), EventError> {
+        // Creations can't be cached yet
+        if let DomainEvent::EntityChanged(change) = event {
+            if change.entity == EntityKind::Share && change.change == ChangeKind::Created {
+                return Ok(());
+            }
+        }
+        
+        if self.invalidators.is_empty() {
+            return Ok(());
+        }
+        let short_codes = self.affected_short_codes(event).await
+            .map_err(|e| EventError::Delivery(e.to_string()))?;
+        if short_codes.is_empty() {
+            return Ok(());
+        }
+        
+        for Registered { invalidator, outbox } in &self.invalidators {
+            let name = invalidator.name();
+            self.record(name, |s| s.keys += short_codes.len() as u64);
+            match invalidator.invalidate(&short_codes).await {
+                Ok(()) => self.record(name, |s| s.succeeded += 1),
+                Err(e) => {
+                    tracing::debug!("Cache invalidation via {} failed, queued for retry: {}", name, e);
+                    self.record(name, |s| s.retries += 1);
+                    for code in &short_codes {
+                        outbox.enqueue(code.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EntityChange;
+    use crate::storage::memory_storage::MemoryShareStorage;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    
+    /// Fails the first call, then succeeds
+    #[derive(Default)]
+    struct Flaky {
+        calls: AtomicU32,
+    }
+    
+    #[async_trait]
+    impl CacheInvalidator for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+        
+        async fn invalidate(&self, _short_codes: &[String]) -> Result<(), EventError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(EventError::Delivery("timeout".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_share_change_invalidates_with_retry() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_by_callback = seen.clone();
+        
+        let mut invalidation = CacheInvalidation::new(
+            Arc::new(MemoryShareStorage::new()),
+            RetryPolicy { max_attempts: 3, base_delay: Duration::ZERO },
+        );
+        invalidation.register(Arc::new(CallbackInvalidator::new(move |codes: &[String]| {
+            seen_by_callback.lock().unwrap().extend_from_slice(codes);
+        })));
+        invalidation.register(Arc::new(Flaky::default()));
+        
+        let event = DomainEvent::EntityChanged(EntityChange::new(
+            "org-1", EntityKind::Share, "share-1", ChangeKind::Updated, Some("user-1"),
+        ).with_key("AbCd1234"));
+        invalidation.handle(&event).await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["AbCd1234".to_string()]);
+        assert_eq!(invalidation.pending(), 1);
+        
+        // The failed purge waits in the outbox for the next flush
+        assert_eq!(invalidation.flush().await, 1);
+        assert_eq!(invalidation.pending(), 0);
+        let stats = invalidation.stats();
+        assert_eq!(stats["flaky"], InvalidationStats { keys: 1, succeeded: 1, failed: 0, retries: 1 });
+        assert_eq!(stats["in-process"].succeeded, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_activity_change_purges_the_shares_of_its_layers() {
+        let shares = Arc::new(MemoryShareStorage::new());
+        for (id, code, layer) in [("s-1", "Code0001", "layer-1"), ("s-2", "Code0002", "layer-2")] {
+            let mut share = crate::storage_tests::share("org-1", id, code, "public");
+            share.layer_config.layer_ids = vec![layer.to_string()];
+            shares.create(share).await.unwrap();
+        }
+        let seen = Arc::new(Mutex::new(BTreeSet::new()));
+        let seen_by_callback = seen.clone();
+        let mut invalidation = CacheInvalidation::new(shares, RetryPolicy::default());
+        invalidation.register(Arc::new(CallbackInvalidator::new(move |codes: &[String]| {
+            seen_by_callback.lock().unwrap().extend(codes.iter().cloned());
+        })));
+        let purged = |event: EntityChange| {
+            seen.lock().unwrap().clear();
+            let invalidation = &invalidation;
+            let seen = seen.clone();
+            async move {
+                invalidation.handle(&DomainEvent::EntityChanged(event)).await.unwrap();
+                let codes: Vec<String> = seen.lock().unwrap().iter().cloned().collect();
+                codes
+            }
+        };
+        
+        let moved = EntityChange::new("org-1", EntityKind::Activity, "a-1", ChangeKind::Updated, None);
+        assert_eq!(purged(moved.clone().with_layers(&["layer-1"])).await, ["Code0001"]);
+        assert_eq!(purged(moved.clone().with_layers(&["layer-1", "layer-2"])).await, ["Code0001", "Code0002"]);
+        assert_eq!(purged(EntityChange::new("org-1", EntityKind::Layer, "layer-2", ChangeKind::Updated, None)).await, ["Code0002"]);
+        // Without its layers, an activity change purges the organization
+        assert_eq!(purged(moved).await, ["Code0001", "Code0002"]);
+    }
+}
@@ -0,0 +1,100 @@
+//! # Layer Access
+//!
+//! Layers can be restricted to Azure AD groups (`Layer::visible_to_groups`),
+//! e.g. a "Leadership" layer only its members see. Restricted layers and
+//! their activities are left out of list endpoints and `Users` shares.
+//!
+//! Membership comes from the token's `groups` claim (enable with
+//! `"groupMembershipClaims": "SecurityGroup"` in the app manifest). Tokens without it
+//! (claim not configured, or more groups than fit in a token) are checked
+//! against the directory, which caches the answers. Without a directory,
+//! or when it fails, restricted layers stay hidden. Admins see every layer.
+
+use crate::auth::UserContext;
+use crate::directory::{DirectoryError, DirectoryService};
+use crate::models::Layer;
+use std::collections::HashSet;
+
+/// Whether the layer is limited to some groups
+pub fn is_restricted(layer: &Layer) -> bool {
+    !layer.visible_to_groups.is_empty()
+}
+
+/// Whether a user in `member_of` may see the layer
+pub fn can_see(layer: &Layer, is_admin: bool, member_of: &HashSet<String>) -> bool {
+    is_admin || !is_restricted(layer) || layer.visible_to_groups.iter().any(|g| member_of.contains(g))
+}
+
+/// Groups the user belongs to, among those the given layers are restricted to
+pub async fn member_groups(
+    layers: &[Layer],
+    user: &UserContext,
+    directory: Option<&dyn DirectoryService>,
+) -> Result<HashSet<String>, DirectoryError> {
+    let mut required: Vec<String> = layers.iter()
+        .flat_map(|l| l.visible_to_groups.iter().cloned())
+        .collect();
+    required.sort();
+    required.dedup();
+    
+    if required.is_empty() || user.is_admin {
+        return Ok(HashSet::new());
+    }
+    
+    if let Some(ref groups) = user.groups {
+        return Ok(required.into_iter().filter(|g| groups.contains(g)).collect());
+    }
+    
+    match directory {
+        Some(directory) => directory.member_groups(&user.organization_id, &user.user_id, &required).await,
+        None => Ok(HashSet::new()),
+    }
+}
+
+/// The layers the user may see
+pub fn filter_visible(layers: Vec<Layer>, is_admin: bool, member_of: &HashSet<String>) -> Vec<Layer> {
+    layers.into_iter().filter(|l| can_see(l, is_admin, member_of)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn layer(id: &str, groups: &[&str]) -> Layer {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "name": id, "type": "organization", "color": "#000000", "ringIndex": 0,
+            "organizationId": "org-1", "createdBy": "admin", "createdAt": "2025-01-01T00:00:00Z",
+            "visibleToGroups": groups,
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_groups_claim_limits_restricted_layers() {
+        let layers = vec![layer("everyone", &[]), layer("leadership", &["group-lead"])];
+        let mut user = UserContext {
+            user_id: "user-1".to_string(),
+            organization_id: "org-1".to_string(),
+            display_name: None,
+            email: None,
+            is_admin: false,
+            roles: vec![],
+            groups: Some(vec!["group-other".to_string()]),
+            scopes: None,
+            team: None,
+        };
+        
+        let member_of = member_groups(&layers, &user, None).await.unwrap();
+        let ids: Vec<String> = filter_visible(layers.clone(), false, &member_of).into_iter().map(|l| l.id).collect();
+        assert_eq!(ids, vec!["everyone"]);
+        
+        user.groups = Some(vec!["group-lead".to_string()]);
+        let member_of = member_groups(&layers, &user, None).await.unwrap();
+        assert_eq!(filter_visible(layers.clone(), false, &member_of).len(), 2);
+        
+        // No claim and no directory: fail closed
+        user.groups = None;
+        let member_of = member_groups(&layers, &user, None).await.unwrap();
+        assert_eq!(filter_visible(layers.clone(), false, &member_of).len(), 1);
+        assert_eq!(filter_visible(layers, true, &member_of).len(), 2);
+    }
+}
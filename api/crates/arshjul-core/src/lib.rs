@@ -0,0 +1,250 @@
+//! # Annual Wheel (Årshjul) API - Core
+//!
+//! Domain models, storage traits and HTTP handlers for the Annual Wheel Teams app.
+//!
+//! ## Architecture
+//!
+//! - **Storage**: Traits here; Azure Table Storage / Cosmos DB in `arshjul-azure`; instances follow each other's writes through the Cosmos DB change feed (see [`change_feed`])
+//! - **Auth**: Azure AD / Teams SSO token validation; delegated scopes and app roles per endpoint family, including the `reports.read` role of BI service principals, in [`authorization`]
+//! - **API**: RESTful HTTP endpoints
+//!
+//! ## Crates
+//!
+//! - `arshjul-core` - Models, traits, handlers (this crate, no cloud SDKs)
+//! - `arshjul-azure` - Azure adapters (Table Storage, Cosmos DB, SignalR)
+//! - `arshjul-server` - Configuration and the `arshjul-api` binary
+//! - `arshjul-edge` - Read-only public share endpoint for wasm32-wasi / Workers
+//!
+//! The `server` feature (default) enables authentication and the HTTP
+//! handlers; without it the crate has no native-only dependencies.
+//!
+//! ## Endpoints
+//!
+//! ### Shares
+//! - `POST /api/shares` - Create share (authenticated; scoped to the caller's Teams team/channel; partner federation tokens make it a combined share)
+//! - `GET /api/shares` - List shares for org (authenticated; in Teams, those scoped to the team/channel)
+//! - `GET /api/shares/{id}` - Get share details (authenticated)
+//! - `PATCH /api/shares/{id}` - Change name, description and view settings; omitted fields are kept (authenticated)
+//! - `DELETE /api/shares/{id}` - Move share to the recycle bin (authenticated)
+//! - `POST /api/shares/{id}/restore` - Restore share from the recycle bin (authenticated)
+//! - `POST /api/shares/{id}/renew` - Renew share TTL (authenticated)
+//! - `POST /api/shares/renew-by-token` - Renew share TTL from an expiry reminder link (signed single-use token, audited)
+//! - `POST /api/shares/{id}/regenerate-key` - Regenerate share key (authenticated)
+//! - `PUT /api/shares/{id}/snapshot` - Serve share from a CDN snapshot (authenticated)
+//! - `PUT /api/shares/{id}/indexing` - Allow or forbid search engine indexing, or follow the organization default (authenticated)
+//! - `PUT /api/shares/{id}/teams` - List the share only in these Teams teams and channels (admin only, audited)
+//! - `DELETE /api/shares/{id}/review` - Clear a share flagged for unusual traffic (admin only)
+//! - `GET /api/shares/{id}/export` - Export the share as JSON, CSV, NDJSON, iCalendar, SVG or PDF by `Accept` header, with `locale`, `redact` and paging parameters (authenticated)
+//! - `GET /api/shares/s/{shortCode}` - View a Users-visibility share (authenticated, same organization)
+//!
+//! ### Public Share Access
+//! - `GET /api/public/s/{shortCode}` - Access public share (with key in query; 302 to the CDN for snapshot shares)
+//! - `GET /api/public/s/{shortCode}/calendar.ics` - iCalendar feed (with key; filter by `layers`, `types`, `tags`, `from`, `to`)
+//! - `GET /api/public/s/{shortCode}/upcoming` - Next activities as a small JSON list for signage and widgets (with key; `limit` 1-50, default 10)
+//! - `GET /api/public/s/{shortCode}/preview.png` - Low-resolution thumbnail for link unfurling (with signed `sig` from the share response, see [`preview`])
+//! - `POST /api/public/s/{shortCode}/nonce` - Single-use nonce for the public POSTs below (with key in query, see [`nonce`])
+//! - `POST /api/public/s/{shortCode}/report` - Report abuse or misconfiguration (with key in query and a nonce in `X-Request-Nonce`)
+//! - `GET /sitemap.xml` - Public shares opted in to indexing of the organizations in `SITEMAP_ORGANIZATIONS` (see [`indexing`])
+//!
+//! ### Activities
+//! - `POST /api/activities` - Create activity, optionally recurring with per-occurrence exceptions (authenticated; see [`recurrence`])
+//! - `GET /api/activities` - List activities (authenticated)
+//! - `GET /api/activities/search` - Full-text search (authenticated; Azure AI Search when configured)
+//! - `GET /api/activities/export` - Export activities by `Accept` header (authenticated, or reporting role without descriptions, links and people; see [`export`])
+//! - `PUT /api/activities/{id}` - Update activity (authenticated)
+//! - `DELETE /api/activities/{id}` - Move activity to the recycle bin (authenticated)
+//! - `POST /api/activities/{id}/restore` - Restore activity from the recycle bin (authenticated)
+//! - `POST /api/activities/{id}/split` - Split into two linked activities at a date (authenticated, audited; see [`split_merge`])
+//! - `POST /api/activities/merge` - Merge one activity into another and delete it (authenticated, audited)
+//! - `POST /api/activities/{id}/lock` - Acquire/renew advisory edit lock (authenticated)
+//! - `DELETE /api/activities/{id}/lock` - Release edit lock (authenticated)
+//!
+//! ### Approvals
+//! - `GET /api/approvals` - Activities pending approval on controlled layers (admin only)
+//! - `POST /api/approvals/{id}/approve` - Approve with optional comment (admin only)
+//! - `POST /api/approvals/{id}/reject` - Reject with optional comment (admin only)
+//!
+//! ### Layers
+//! - `POST /api/layers` - Create layer (admin only)
+//! - `GET /api/layers` - List layers visible to the caller (authenticated; group-restricted layers filtered)
+//! - `PUT /api/layers/{id}` - Update layer (admin only)
+//! - `DELETE /api/layers/{id}` - Delete layer (admin only)
+//! - `PUT /api/layers/{id}/teams` - Show the layer only in these Teams teams and channels (admin only, audited)
+//!
+//! ### Activity Types
+//! - `GET /api/activity-types` - List activity types (authenticated)
+//! - `PUT /api/activity-types/{key}` - Update activity type (admin only)
+//! - `GET /api/activity-types/{key}/usage` - Activities using the type, per year and layer (admin or reporting role)
+//! - `DELETE /api/activity-types/{key}?reassignTo=` - Delete a type; refused while in use unless its activities are reassigned (admin only, audited, supports `dry_run`)
+//!
+//! ### User Settings
+//! - `GET /api/user-settings/notifications` - Get notification preferences (authenticated)
+//! - `PUT /api/user-settings/notifications` - Update notification preferences (authenticated)
+//!
+//! ### Directory
+//! - `GET /api/directory/search?q=` - People/group picker in the caller's tenant (authenticated, rate limited)
+//!
+//! ### Organization Administration
+//! - `POST /api/admin/organization/purge-confirmation` - Issue a signed purge confirmation token for the plan it previews (admin only; see [`offboarding`])
+//! - `GET /api/admin/integrations/graph/status` - Graph permission and consent self-check (admin only)
+//! - `POST /api/admin/pseudonyms/resolve` - Re-identify audit pseudonyms (admin only, audited)
+//! - `DELETE /api/admin/organization` - Revoke shares and delete all tenant data (admin only; `?dry_run=true` lists what would change)
+//! - `GET /api/admin/export` - Everything the organization has (policy, activity types, layers, activities, shares, user settings) as one JSON archive (admin only, audited; deprecated, use `POST /api/admin/export/background`)
+//! - `POST /api/admin/import` - Write an archive back; `?on_conflict=skip|overwrite|new-ids` decides what happens to entities already stored (admin only, audited; see [`backup`])
+//! - `POST /api/admin/snapshots` - Store the organization's archive in Blob Storage as a point-in-time snapshot (admin only, audited; see [`org_snapshots`])
+//! - `GET /api/admin/snapshots` - Snapshots of the organization, newest first (admin only)
+//! - `POST /api/admin/snapshots/{id}/restore` - Write a snapshot back: deleted and changed entities return as they were, new ones stay (admin only, audited)
+//! - `POST /api/admin/reassign` - Move all activities of a layer or type to another, optionally for one year (admin only, audited, supports `dry_run`; see [`reassign`])
+//! - `POST /api/admin/import/background` - Import an archive in the background; 202 with the operation and its `Location` (admin only, audited on success; see [`operations`])
+//! - `POST /api/admin/reassign/background` - Reassign activities in the background; 202 with the operation and its `Location` (admin only, audited on success)
+//! - `POST /api/admin/export/background` - Export the organization in the background; the result is the archive (admin only, audited on success)
+//! - `DELETE /api/admin/organization/background` - Purge the organization in the background; the result is the deletion certificate (admin only, requires confirmation token)
+//! - `POST /api/admin/schema/rewrite` - Save every row of the organization again at the current schema version, in the background (admin only, audited on success; see [`schema`])
+//! - `POST /api/admin/search/reindex` - Push every activity into the Azure AI Search index again, in the background (admin only, audited on success; 404 without an index)
+//! - `GET /api/operations/{id}` - Status, progress and result of a background operation, with `Retry-After` until it finishes; 404 after `expiresAt` (admin only)
+//! - `GET /api/admin/analytics/planning` - Lead time, edit churn and cancellations per year (admin or reporting role)
+//! - `GET /api/admin/analytics/powerbi` - Paginated Power BI tables: activity and share view facts, layer and type dimensions (admin or reporting role)
+//! - `GET /api/admin/policy/period-lock` - Past period lock and today's cutoff (admin only)
+//! - `PUT /api/admin/policy/period-lock` - Make activities before a date or quarter read-only for non-admins (admin only, audited)
+//! - `GET /api/admin/policy/rate-plan` - Public API limits in effect (admin only)
+//! - `PUT /api/admin/policy/rate-plan` - Requests per minute for the organization and individual share keys (admin only, audited)
+//! - `GET /api/admin/policy/access-log` - SIEM forwarding of public share access (admin only)
+//! - `PUT /api/admin/policy/access-log` - Forward sampled public share access to a webhook or Event Hub, or stop (admin only, audited; see [`access_log`])
+//! - `GET /api/admin/policy/calendar` - Locale, first day of week and week numbering of rendered outputs (admin only)
+//! - `PUT /api/admin/policy/calendar` - Change them, or restore the ISO 8601 defaults (admin only, audited; see [`calendar`])
+//! - `GET /api/admin/policy/terms` - School year terms, holidays and exam periods (admin only)
+//! - `PUT /api/admin/policy/terms` - Set them and regenerate the terms layer, or remove both (admin only, audited; see [`terms`])
+//! - `GET /api/admin/policy/indexing` - Search engine indexing default of public shares (admin only)
+//! - `PUT /api/admin/policy/indexing` - Let search engines index public shares by default, or not (admin only, audited)
+//! - `GET /api/admin/policy/share-view-defaults` - Default view settings of shares, under the fields each share sets itself (admin only)
+//! - `PUT /api/admin/policy/share-view-defaults` - Change them, or restore the built-in defaults; shares that follow them change too (admin only, audited)
+//! - `PATCH /api/admin/policy/share-view-defaults` - Change some of them, keeping the rest (admin only, audited)
+//! - `GET /api/admin/policy/activity-order` - How overlapping activities in a ring are stacked (admin only)
+//! - `PUT /api/admin/policy/activity-order` - Stack them by duration, priority or type; public views carry the resolved `zIndex` (admin only, audited)
+//! - `GET /api/admin/federation/grants` - Layers shared with partner organizations (admin only)
+//! - `POST /api/admin/federation/grants` - Grant a partner organization read access to layers, returning a signed federation token; `includeDescriptions` lets the partner show descriptions and links (admin only, audited; see [`federation`])
+//! - `DELETE /api/admin/federation/grants/{id}` - Revoke a grant; combined shares drop the layers at once (admin only, audited)
+//! - `GET /api/admin/deprecations` - Deprecated routes, their sunset and the organization's calls to them (admin only; see [`deprecation`])
+//! - `GET /api/admin/logging` - Current verbose logging override (admin only)
+//! - `PUT /api/admin/logging` - Log the organization at `debug`/`trace` level for a while (admin only, audited)
+//! - `DELETE /api/admin/logging` - End the override (admin only)
+//!
+//! ### Delta Sync
+//! - `GET /api/delta` - Changed activities, layers and activity types since a sync token (authenticated)
+//!
+//! ### Metrics
+//! - `GET /api/metrics` - SLIs, error budget burn rates and storage call counts and latency per backend, Prometheus text format (bearer `METRICS_TOKEN`; see [`storage_metrics`])
+//! - `GET /api/metrics/deprecations` - Organizations still calling deprecated routes, which answer with `Deprecation`/`Sunset` headers (bearer `METRICS_TOKEN`; see [`deprecation`])
+//! - `GET /api/metrics/partitions` - Entity count, size and growth of every organization's Table Storage partition, largest first (bearer `METRICS_TOKEN`; see [`partition_monitor`])
+//! - `GET /api/health` - Storage probe per table or container with latency; 503 when one fails (see [`health`])
+//!
+//! ### Signing Keys
+//! - `GET /.well-known/jwks.json` - Public halves of the current and upcoming signing keys (see [`signing_keys`])
+//!
+//! ### Live Updates
+//! - `POST /api/signalr/negotiate` - Live update connection info (authenticated)
+
+pub mod models;
+pub mod clock;
+pub mod storage;
+pub mod traced_storage;
+pub mod storage_metrics;
+pub mod recycle_bin;
+pub mod dual_write;
+pub mod schema;
+pub mod counters;
+#[cfg(any(test, feature = "test-util"))]
+pub mod storage_tests;
+pub mod share_cache;
+#[cfg(feature = "server")]
+pub mod handlers;
+#[cfg(feature = "server")]
+pub mod auth;
+#[cfg(feature = "server")]
+pub mod authorization;
+pub mod crypto;
+pub mod sync;
+pub mod events;
+pub mod locks;
+#[cfg(feature = "server")]
+pub mod offboarding;
+pub mod dry_run;
+pub mod client_info;
+pub mod http_cache;
+pub mod pseudonym;
+pub mod public_access;
+#[cfg(feature = "server")]
+pub mod snapshots;
+pub mod search;
+pub mod directory;
+pub mod rate_limit;
+pub mod markdown;
+pub mod links;
+pub mod ics;
+#[cfg(feature = "server")]
+pub mod share_urls;
+pub mod share_traffic;
+pub mod log_overrides;
+pub mod planning;
+pub mod powerbi;
+pub mod period_lock;
+#[cfg(feature = "server")]
+pub mod share_renewal;
+pub mod export;
+pub mod activity_types;
+pub mod reassign;
+pub mod split_merge;
+pub mod ordering;
+pub mod recurrence;
+pub mod calendar;
+pub mod i18n;
+pub mod terms;
+pub mod indexing;
+#[cfg(feature = "server")]
+pub mod preview;
+pub mod nonce;
+#[cfg(feature = "server")]
+pub mod invalidation;
+#[cfg(feature = "server")]
+pub mod layer_access;
+#[cfg(feature = "server")]
+pub mod teams_context;
+#[cfg(feature = "server")]
+pub mod audit_export;
+#[cfg(feature = "server")]
+pub mod access_log;
+#[cfg(feature = "server")]
+pub mod contract;
+#[cfg(feature = "server")]
+pub mod slo;
+#[cfg(feature = "server")]
+pub mod signing_keys;
+#[cfg(feature = "server")]
+pub mod federation;
+#[cfg(feature = "server")]
+pub mod backup;
+#[cfg(feature = "server")]
+pub mod share_cleanup;
+#[cfg(feature = "server")]
+pub mod storage_retry;
+#[cfg(feature = "server")]
+pub mod health;
+#[cfg(feature = "server")]
+pub mod deprecation;
+#[cfg(feature = "server")]
+pub mod change_feed;
+#[cfg(feature = "server")]
+pub mod partition_monitor;
+#[cfg(feature = "server")]
+pub mod storage_budget;
+#[cfg(feature = "server")]
+pub mod org_snapshots;
+#[cfg(feature = "server")]
+pub mod operations;
+#[cfg(feature = "server")]
+pub mod share_key_cipher;
+#[cfg(feature = "server")]
+pub mod notifications;
+
+pub use models::*;
+pub use storage::*;
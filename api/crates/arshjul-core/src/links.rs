@@ -0,0 +1,91 @@
+//! # Activity Links
+//!
+//! Activities can carry links to agendas, documents or meeting pages
+//! (`Activity::links`). Links are shown in shared views and exported as the
+//! ICS `URL` property, so they are validated strictly: https only, no
+//! credentials in the URL, and optionally not to denied domains
+//! (`LINK_DOMAIN_DENYLIST`, matching the domain and its subdomains).
+
+use crate::models::ActivityLink;
+
+/// Links per activity
+pub const MAX_LINKS: usize = 10;
+
+/// Longest link title
+pub const MAX_TITLE_LEN: usize = 200;
+
+/// Longest URL
+pub const MAX_URL_LEN: usize = 2048;
+
+/// Host of an https URL, lowercased and without port
+pub fn https_host(url: &str) -> Option<String> {
+    let rest = url.get(..8).filter(|scheme| scheme.eq_ignore_ascii_case("https://")).map(|_| &url[8..])?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    
+    // Userinfo makes links misleading (https://trusted.com@evil.com)
+    if authority.contains('@') {
+        return None;
+    }
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => authority,
+    };
+    
+    let valid = !host.is_empty()
+        && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    valid.then(|| host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// Whether the host is a denied domain or one of its subdomains
+pub fn is_denied(host: &str, denylist: &[String]) -> bool {
+    denylist.iter().any(|domain| {
+        let domain = domain.trim().trim_start_matches('.').to_ascii_lowercase();
+        !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+    })
+}
+
+/// Validate the links of an activity
+pub fn validate(links: &[ActivityLink], denylist: &[String]) -> Result<(), String> {
+    if links.len() > MAX_LINKS {
+        return Err(format!("Too many links (max {})", MAX_LINKS));
+    }
+    
+    for link in links {
+        if link.title.trim().is_empty() || link.title.chars().count() > MAX_TITLE_LEN {
+            return Err(format!("Link title must be 1 to {} characters", MAX_TITLE_LEN));
+        }
+        if link.url.len() > MAX_URL_LEN || link.url.chars().any(char::is_whitespace) {
+            return Err(format!("Invalid link URL: {}", link.title));
+        }
+        let host = https_host(&link.url)
+            .ok_or_else(|| format!("Link URL must be https: {}", link.title))?;
+        if is_denied(&host, denylist) {
+            return Err(format!("Links to {} are not allowed", host));
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn link(url: &str) -> ActivityLink {
+        ActivityLink { title: "Agenda".to_string(), url: url.to_string() }
+    }
+    
+    #[test]
+    fn test_validate_links() {
+        let denylist = vec!["example.org".to_string()];
+        
+        assert!(validate(&[link("https://contoso.sharepoint.com/sites/x?y=1")], &denylist).is_ok());
+        assert_eq!(https_host("HTTPS://Teams.Microsoft.com:443/l/meetup"), Some("teams.microsoft.com".to_string()));
+        
+        assert!(validate(&[link("http://contoso.com")], &denylist).is_err());
+        assert!(validate(&[link("javascript:alert(1)")], &denylist).is_err());
+        assert!(validate(&[link("https://contoso.com@evil.test/")], &denylist).is_err());
+        assert!(validate(&[link("https://files.example.org/a")], &denylist).is_err());
+        assert!(validate(&[link("https://notexample.org/a")], &denylist).is_ok());
+    }
+}
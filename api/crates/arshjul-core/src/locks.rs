@@ -0,0 +1,191 @@
+//! # Collaborative Edit Locks
+//!
+//! Lightweight advisory locks so two planners don't silently overwrite the
+//! same activity during planning workshops.
+//!
+//! - `POST /api/activities/{id}/lock` acquires (or renews) a lock for
+//!   [`LOCK_TTL_SECONDS`]; the editor re-posts while it stays open.
+//! - Locks expire on their own, so a closed tab never blocks anyone for long.
+//! - Acquire/release is broadcast on the live update channel.
+//! - Update and delete handlers reject writes while another user holds the lock.
+//!
+//! Locks live in a [`LockStore`]:
+//!
+//! - [`MemoryLockStore`] - per instance; enough for one instance
+//! - `RedisLockStore` - shared by all instances (`arshjul-azure`, `redis` feature)
+
+use crate::clock::{Clock, SystemClock};
+use crate::models::EditLock;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+/// Lock lifetime without renewal
+pub const LOCK_TTL_SECONDS: i64 = 120;
+
+/// Lock errors
+#[derive(Debug, Error)]
+pub enum LockError {
+    #[error("Activity is locked by {}", .0.locked_by_name.as_deref().unwrap_or(&.0.locked_by))]
+    Conflict(Box<EditLock>),
+    
+    #[error("Lock storage error: {0}")]
+    Storage(String),
+}
+
+/// Lock store for edit locks
+#[async_trait]
+pub trait LockStore: Send + Sync {
+    /// Acquire or renew a lock; fails if another user holds an unexpired lock
+    async fn acquire(&self, lock: EditLock) -> Result<EditLock, LockError>;
+    
+    /// Release a lock held by the given user (no-op if not held)
+    async fn release(&self, organization_id: &str, activity_id: &str, user_id: &str) -> Result<bool, LockError>;
+    
+    /// Get the current unexpired lock, if any
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Option<EditLock>, LockError>;
+}
+
+/// Build a new lock for a user, acquired at `now`
+pub fn new_lock(organization_id: &str, activity_id: &str, user_id: &str, user_name: Option<&str>, now: DateTime<Utc>) -> EditLock {
+    EditLock {
+        activity_id: activity_id.to_string(),
+        organization_id: organization_id.to_string(),
+        locked_by: user_id.to_string(),
+        locked_by_name: user_name.map(str::to_string),
+        acquired_at: now,
+        expires_at: now + Duration::seconds(LOCK_TTL_SECONDS),
+    }
+}
+
+/// Check whether a write by `user_id` is allowed under the current lock
+pub fn ensure_not_locked_by_other(lock: Option<EditLock>, user_id: &str, now: DateTime<Utc>) -> Result<(), LockError> {
+    match lock {
+        Some(lock) if lock.locked_by != user_id && !lock.is_expired(now) => {
+            Err(LockError::Conflict(Box::new(lock)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// In-memory lock store (single instance deployments and tests)
+pub struct MemoryLockStore {
+    locks: RwLock<HashMap<String, EditLock>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for MemoryLockStore {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl MemoryLockStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Expire locks by another time source (tests)
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self { locks: RwLock::new(HashMap::new()), clock }
+    }
+    
+    fn key(organization_id: &str, activity_id: &str) -> String {
+        format!("{}:{}", organization_id, activity_id)
+    }
+}
+
+#[async_trait]
+impl LockStore for MemoryLockStore {
+    async fn acquire(&self, lock: EditLock) -> Result<EditLock, LockError> {
+        let key = Self::key(&lock.organization_id, &lock.activity_id);
+        let mut locks = self.locks.write().await;
+        
+        // Drop expired locks opportunistically
+        let now = self.clock.now();
+        locks.retain(|_, l| !l.is_expired(now));
+        
+        let lock = match locks.get(&key) {
+            Some(existing) if existing.locked_by != lock.locked_by => {
+                return Err(LockError::Conflict(Box::new(existing.clone())));
+            }
+            // Renewal keeps the original acquisition time
+            Some(existing) => EditLock {
+                acquired_at: existing.acquired_at,
+                ..lock
+            },
+            None => lock,
+        };
+        
+        locks.insert(key, lock.clone());
+        Ok(lock)
+    }
+    
+    async fn release(&self, organization_id: &str, activity_id: &str, user_id: &str) -> Result<bool, LockError> {
+        let key = Self::key(organization_id, activity_id);
+        let mut locks = self.locks.write().await;
+        
+        match locks.get(&key) {
+            Some(existing) if existing.locked_by == user_id => {
+                locks.remove(&key);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+    
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Option<EditLock>, LockError> {
+        let locks = self.locks.read().await;
+        Ok(locks.get(&Self::key(organization_id, activity_id))
+            .filter(|l| !l.is_expired(self.clock.now()))
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    
+    #[tokio::test]
+    async fn test_lock_conflict_and_release() {
+        let store = MemoryLockStore::new();
+        let now = Utc::now();
+        
+        let lock = store.acquire(new_lock("org", "a1", "alice", Some("Alice"), now)).await.unwrap();
+        assert_eq!(lock.locked_by, "alice");
+        
+        // Renewal by the holder succeeds
+        assert!(store.acquire(new_lock("org", "a1", "alice", None, now)).await.is_ok());
+        
+        // Another user is rejected
+        match store.acquire(new_lock("org", "a1", "bob", None, now)).await {
+            Err(LockError::Conflict(held)) => assert_eq!(held.locked_by, "alice"),
+            other => panic!("expected conflict, got {:?}", other),
+        }
+        assert!(ensure_not_locked_by_other(store.get("org", "a1").await.unwrap(), "bob", now).is_err());
+        assert!(ensure_not_locked_by_other(store.get("org", "a1").await.unwrap(), "alice", now).is_ok());
+        
+        // Only the holder can release
+        assert!(!store.release("org", "a1", "bob").await.unwrap());
+        assert!(store.release("org", "a1", "alice").await.unwrap());
+        assert!(store.acquire(new_lock("org", "a1", "bob", None, now)).await.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_expired_lock_is_ignored() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let store = MemoryLockStore::with_clock(clock.clone());
+        store.acquire(new_lock("org", "a1", "alice", None, clock.now())).await.unwrap();
+        
+        clock.advance(Duration::seconds(LOCK_TTL_SECONDS - 1));
+        assert!(store.get("org", "a1").await.unwrap().is_some());
+        
+        clock.advance(Duration::seconds(2));
+        assert!(store.get("org", "a1").await.unwrap().is_none());
+        assert!(store.acquire(new_lock("org", "a1", "bob", None, clock.now())).await.is_ok());
+    }
+}
@@ -0,0 +1,112 @@
+//! # Log Level Overrides
+//!
+//! Debugging one tenant should not require raising the global log level.
+//! An admin switches their organization to `debug` or `trace` for a limited
+//! time (`PUT /api/admin/logging`); the server's tracing filter asks
+//! [`LogOverrides::level`] whether an event inside that organization's spans
+//! should be logged.
+//!
+//! Overrides live in process memory and expire on their own after at most
+//! [`MAX_OVERRIDE_MINUTES`]; a restart clears them.
+
+use crate::clock::{Clock, SystemClock};
+use crate::models::{LogOverride, VerboseLevel};
+use chrono::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Override lifetime when the request does not say
+pub const DEFAULT_OVERRIDE_MINUTES: i64 = 30;
+
+/// Longest an override may stay active
+pub const MAX_OVERRIDE_MINUTES: i64 = 240;
+
+/// Active overrides by organization
+pub struct LogOverrides {
+    overrides: RwLock<HashMap<String, LogOverride>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for LogOverrides {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl LogOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self { overrides: RwLock::new(HashMap::new()), clock }
+    }
+    
+    /// Set or replace the override of an organization
+    pub fn set(&self, organization_id: &str, level: VerboseLevel, duration: Duration, set_by: &str) -> LogOverride {
+        let now = self.clock.now();
+        let log_override = LogOverride {
+            organization_id: organization_id.to_string(),
+            level,
+            set_by: set_by.to_string(),
+            expires_at: now + duration,
+        };
+        
+        let mut overrides = self.overrides.write().unwrap_or_else(|e| e.into_inner());
+        overrides.retain(|_, o| o.expires_at > now);
+        overrides.insert(organization_id.to_string(), log_override.clone());
+        log_override
+    }
+    
+    /// Remove the override of an organization, returning it if it was active
+    pub fn clear(&self, organization_id: &str) -> Option<LogOverride> {
+        let now = self.clock.now();
+        self.overrides.write().unwrap_or_else(|e| e.into_inner())
+            .remove(organization_id)
+            .filter(|o| o.expires_at > now)
+    }
+    
+    /// Active override of an organization
+    pub fn get(&self, organization_id: &str) -> Option<LogOverride> {
+        let now = self.clock.now();
+        self.overrides.read().unwrap_or_else(|e| e.into_inner())
+            .get(organization_id)
+            .filter(|o| o.expires_at > now)
+            .cloned()
+    }
+    
+    /// Most verbose level to log for an organization, if overridden
+    pub fn level(&self, organization_id: &str) -> Option<tracing::Level> {
+        let overrides = self.overrides.read().unwrap_or_else(|e| e.into_inner());
+        let log_override = overrides.get(organization_id)?;
+        if log_override.expires_at <= self.clock.now() {
+            return None;
+        }
+        Some(match log_override.level {
+            VerboseLevel::Debug => tracing::Level::DEBUG,
+            VerboseLevel::Trace => tracing::Level::TRACE,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::Utc;
+    
+    #[test]
+    fn test_override_expires() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let overrides = LogOverrides::with_clock(clock.clone());
+        overrides.set("org-1", VerboseLevel::Debug, Duration::minutes(30), "admin");
+        
+        assert_eq!(overrides.level("org-1"), Some(tracing::Level::DEBUG));
+        assert_eq!(overrides.level("org-2"), None);
+        
+        clock.advance(Duration::minutes(30));
+        assert_eq!(overrides.level("org-1"), None);
+        assert!(overrides.get("org-1").is_none());
+        assert!(overrides.clear("org-1").is_none());
+    }
+}
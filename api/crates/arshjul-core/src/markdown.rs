@@ -0,0 +1,269 @@
+//! # Description Markup
+//!
+//! Activity descriptions are plain text or a small Markdown subset
+//! (`Activity::description_format`). Records written before Markdown
+//! support have no format and stay plain text, so their `*` and `_` never
+//! turn into formatting; editors opt in per activity by saving with
+//! `descriptionFormat: "markdown"`.
+//!
+//! [`render_html`] produces the HTML used in share responses and
+//! notification emails. It escapes all input and only emits the tags below,
+//! so no HTML from a description ever reaches a viewer:
+//!
+//! | Markdown | HTML |
+//! |----------|------|
+//! | blank line / line break | `<p>` / `<br>` |
+//! | `#`, `##`, `###` | `<h3>`, `<h4>`, `<h5>` |
+//! | `- item`, `1. item` | `<ul>`, `<ol>` |
+//! | `**bold**`, `*italic*`, `` `code` `` | `<strong>`, `<em>`, `<code>` |
+//! | `[text](https://...)` | `<a rel="noopener noreferrer">` (http, https, mailto only) |
+
+use crate::models::DescriptionFormat;
+
+/// Longest plain text description
+pub const MAX_PLAIN_LEN: usize = 2000;
+
+/// Longest Markdown description (room for link targets and markup)
+pub const MAX_MARKDOWN_LEN: usize = 4000;
+
+/// Length limit for a description format
+pub fn max_len(format: DescriptionFormat) -> usize {
+    match format {
+        DescriptionFormat::Plain => MAX_PLAIN_LEN,
+        DescriptionFormat::Markdown => MAX_MARKDOWN_LEN,
+    }
+}
+
+/// Escape text for HTML content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Safe HTML for a description
+pub fn render_html(text: &str, format: DescriptionFormat) -> String {
+    match format {
+        DescriptionFormat::Plain => paragraphs(text)
+            .map(|lines| format!("<p>{}</p>", lines.iter().map(|l| escape_html(l)).collect::<Vec<_>>().join("<br>")))
+            .collect(),
+        DescriptionFormat::Markdown => render_markdown(text),
+    }
+}
+
+/// Groups of consecutive non-blank lines
+fn paragraphs(text: &str) -> impl Iterator<Item = Vec<&str>> {
+    let mut blocks = Vec::new();
+    let mut current = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(line.trim_end());
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+    blocks.into_iter()
+}
+
+#[derive(PartialEq)]
+enum ListKind {
+    Bullet,
+    Ordered,
+}
+
+/// List item content, if the line starts a list item
+fn list_item(line: &str) -> Option<(ListKind, &str)> {
+    let line = line.trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return Some((ListKind::Bullet, rest));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && digits <= 9 {
+        if let Some(rest) = line[digits..].strip_prefix(". ") {
+            return Some((ListKind::Ordered, rest));
+        }
+    }
+    None
+}
+
+fn render_markdown(text: &str) -> String {
+    let mut html = String::new();
+    
+    for block in paragraphs(text) {
+        let mut paragraph: Vec<String> = Vec::new();
+        let mut list: Option<ListKind> = None;
+        
+        let close_paragraph = |html: &mut String, paragraph: &mut Vec<String>| {
+            if !paragraph.is_empty() {
+                html.push_str(&format!("<p>{}</p>", paragraph.join("<br>")));
+                paragraph.clear();
+            }
+        };
+        let close_list = |html: &mut String, list: &mut Option<ListKind>| {
+            match list.take() {
+                Some(ListKind::Bullet) => html.push_str("</ul>"),
+                Some(ListKind::Ordered) => html.push_str("</ol>"),
+                None => {}
+            }
+        };
+        
+        for line in block {
+            if let Some((kind, content)) = list_item(line) {
+                close_paragraph(&mut html, &mut paragraph);
+                if list.as_ref() != Some(&kind) {
+                    close_list(&mut html, &mut list);
+                    html.push_str(if kind == ListKind::Bullet { "<ul>" } else { "<ol>" });
+                    list = Some(kind);
+                }
+                html.push_str(&format!("<li>{}</li>", render_inline(content)));
+                continue;
+            }
+            
+            close_list(&mut html, &mut list);
+            let heading = ["### ", "## ", "# "].iter().position(|p| line.starts_with(p));
+            match heading {
+                Some(i) => {
+                    close_paragraph(&mut html, &mut paragraph);
+                    let level = 5 - i;
+                    let content = line[3 - i..].trim();
+                    html.push_str(&format!("<h{}>{}</h{}>", level, render_inline(content), level));
+                }
+                None => paragraph.push(render_inline(line.trim())),
+            }
+        }
+        
+        close_list(&mut html, &mut list);
+        close_paragraph(&mut html, &mut paragraph);
+    }
+    
+    html
+}
+
+/// Link targets allowed in descriptions
+fn is_safe_url(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    ["https://", "http://", "mailto:"].iter().any(|scheme| lower.starts_with(scheme))
+}
+
+/// Inline markup; everything else is escaped
+fn render_inline(text: &str) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    
+    while let Some(c) = rest.chars().next() {
+        let after = &rest[c.len_utf8()..];
+        
+        // Backslash escapes the next character
+        if c == '\\' {
+            if let Some(next) = after.chars().next() {
+                html.push_str(&escape_html(&next.to_string()));
+                rest = &after[next.len_utf8()..];
+                continue;
+            }
+        }
+        
+        if c == '`' {
+            if let Some(end) = after.find('`') {
+                html.push_str(&format!("<code>{}</code>", escape_html(&after[..end])));
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+        
+        if let Some(inner) = rest.strip_prefix("**") {
+            if let Some(end) = inner.find("**").filter(|&end| end > 0) {
+                html.push_str(&format!("<strong>{}</strong>", render_inline(&inner[..end])));
+                rest = &inner[end + 2..];
+                continue;
+            }
+        }
+        
+        if c == '*' {
+            if let Some(end) = after.find(c).filter(|&end| end > 0 && !after.starts_with(' ')) {
+                html.push_str(&format!("<em>{}</em>", render_inline(&after[..end])));
+                rest = &after[end + 1..];
+                continue;
+            }
+        }
+        
+        if c == '[' {
+            if let Some((label, url, remaining)) = parse_link(after) {
+                if is_safe_url(url) {
+                    html.push_str(&format!(
+                        "<a href=\"{}\" rel=\"noopener noreferrer\" target=\"_blank\">{}</a>",
+                        escape_html(url), render_inline(label)
+                    ));
+                } else {
+                    html.push_str(&render_inline(label));
+                }
+                rest = remaining;
+                continue;
+            }
+        }
+        
+        html.push_str(&escape_html(&c.to_string()));
+        rest = after;
+    }
+    
+    html
+}
+
+/// `label](url)rest` after an opening bracket
+fn parse_link(text: &str) -> Option<(&str, &str, &str)> {
+    let label_end = text.find("](")?;
+    let after_label = &text[label_end + 2..];
+    let url_end = after_label.find(')')?;
+    let url = after_label[..url_end].trim();
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((&text[..label_end], url, &after_label[url_end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_markdown_subset() {
+        let html = render_html(
+            "## Agenda\n- **Budget** review\n- see [notes](https://example.com/a?b=1&c=2)\n\nBring *laptops* and `code`",
+            DescriptionFormat::Markdown,
+        );
+        assert_eq!(
+            html,
+            "<h4>Agenda</h4><ul><li><strong>Budget</strong> review</li>\
+             <li>see <a href=\"https://example.com/a?b=1&amp;c=2\" rel=\"noopener noreferrer\" target=\"_blank\">notes</a></li></ul>\
+             <p>Bring <em>laptops</em> and <code>code</code></p>"
+        );
+    }
+    
+    #[test]
+    fn test_html_and_script_urls_are_neutralized() {
+        let html = render_html(
+            "<script>alert(1)</script> [x](javascript:alert(1)) <img src=x onerror=y>",
+            DescriptionFormat::Markdown,
+        );
+        assert!(!html.contains("<script") && !html.contains("<img") && !html.contains("href"));
+        assert!(html.contains("&lt;script&gt;"));
+        
+        // Plain text keeps its asterisks
+        assert_eq!(render_html("a *b*\nc", DescriptionFormat::Plain), "<p>a *b*<br>c</p>");
+    }
+}
@@ -0,0 +1,3607 @@
+//! # Annual Wheel (Årshjul) API
+//!
+//! Data models for the Annual Wheel Teams app.
+//! Designed for Azure Table Storage with easy migration path to Cosmos DB.
+//!
+//! ## Table Design
+//!
+//! ### Table: `shares`
+//! - PartitionKey: `organizationId`
+//! - RowKey: `id` (UUID)
+//! - Allows efficient queries by organization
+//!
+//! ### Table: `activities`
+//! - PartitionKey: `organizationId`
+//! - RowKey: `id` (UUID)
+//! - Allows efficient queries by organization
+//!
+//! ### Table: `layers`
+//! - PartitionKey: `organizationId`
+//! - RowKey: `id` (UUID)
+//!
+//! ### Table: `activitytypes`
+//! - PartitionKey: `organizationId`
+//! - RowKey: `key` (type key like "meeting", "holiday")
+//!
+//! ### Table: `shortcodes`
+//! - PartitionKey: first character of `shortCode`
+//! - RowKey: `shortCode`
+//! - Points at the owning share; after deletion the row is kept as a
+//!   tombstone (`retiredUntil`) so the code is never re-issued while old
+//!   links may still circulate
+//!
+//! ### Table: `audit`
+//! - PartitionKey: `organizationId`
+//! - RowKey: time-ordered `id`
+//!
+//! ## Cosmos DB Migration
+//!
+//! When migrating to Cosmos DB:
+//! 1. PartitionKey stays the same (`organizationId`)
+//! 2. RowKey becomes `id` field
+//! 3. Add `ttl` field for automatic expiration (shares)
+//! 4. Use `/organizationId` as partition key path
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================
+// Share Models
+// ============================================
+
+/// Sharing visibility mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareVisibility {
+    /// Requires authentication, respects user roles
+    Users,
+    /// Uses a secure key, no authentication required
+    Public,
+}
+
+/// Theme for shared view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareTheme {
+    #[default]
+    Light,
+    Dark,
+    Auto,
+}
+
+/// Layer configuration for a share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLayerConfig {
+    /// Layer IDs to include in this share
+    pub layer_ids: Vec<String>,
+    
+    /// Optional: Override layer visibility (key = layerId)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_visibility: Option<std::collections::HashMap<String, bool>>,
+    
+    /// Year to display (defaults to current year)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+}
+
+/// View settings for a share
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareViewSettings {
+    /// Theme: light, dark, or auto
+    #[serde(default)]
+    pub theme: ShareTheme,
+    
+    /// Show legend at bottom
+    #[serde(default = "default_true")]
+    pub show_legend: bool,
+    
+    /// Show title header
+    #[serde(default = "default_true")]
+    pub show_title: bool,
+    
+    /// Custom title (overrides default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_title: Option<String>,
+    
+    /// Allow interaction (hover tooltips)
+    #[serde(default = "default_true")]
+    pub allow_interaction: bool,
+    
+    /// Auto-rotate to current month
+    #[serde(default = "default_true")]
+    pub rotate_to_current_month: bool,
+    
+    /// Include rendered HTML descriptions (`descriptionHtml`) in the shared view
+    #[serde(default = "default_true")]
+    pub description_html: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ShareViewSettings {
+    fn default() -> Self {
+        Self {
+            theme: ShareTheme::Light,
+            show_legend: true,
+            show_title: true,
+            custom_title: None,
+            allow_interaction: true,
+            rotate_to_current_month: true,
+            description_html: true,
+        }
+    }
+}
+
+/// Changes to [`ShareViewSettings`]; omitted fields keep their value
+///
+/// An empty `customTitle` removes the custom title.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareViewSettingsPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ShareTheme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_legend: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_title: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_interaction: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotate_to_current_month: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<bool>,
+}
+
+impl ShareViewSettingsPatch {
+    /// Whether the patch sets no field
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    /// This patch followed by `later`; fields both set take `later`'s value
+    pub fn then(&self, later: &ShareViewSettingsPatch) -> ShareViewSettingsPatch {
+        ShareViewSettingsPatch {
+            theme: later.theme.or(self.theme),
+            show_legend: later.show_legend.or(self.show_legend),
+            show_title: later.show_title.or(self.show_title),
+            custom_title: later.custom_title.clone().or_else(|| self.custom_title.clone()),
+            allow_interaction: later.allow_interaction.or(self.allow_interaction),
+            rotate_to_current_month: later.rotate_to_current_month.or(self.rotate_to_current_month),
+            description_html: later.description_html.or(self.description_html),
+        }
+    }
+    
+    /// `settings` with the fields the patch sets replaced
+    pub fn merge(&self, settings: &ShareViewSettings) -> ShareViewSettings {
+        ShareViewSettings {
+            theme: self.theme.unwrap_or(settings.theme),
+            show_legend: self.show_legend.unwrap_or(settings.show_legend),
+            show_title: self.show_title.unwrap_or(settings.show_title),
+            custom_title: match self.custom_title {
+                Some(ref title) => Some(title.clone()).filter(|t| !t.is_empty()),
+                None => settings.custom_title.clone(),
+            },
+            allow_interaction: self.allow_interaction.unwrap_or(settings.allow_interaction),
+            rotate_to_current_month: self.rotate_to_current_month.unwrap_or(settings.rotate_to_current_month),
+            description_html: self.description_html.unwrap_or(settings.description_html),
+        }
+    }
+}
+
+/// Access statistics for a share
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareStats {
+    /// Total view count
+    #[serde(default)]
+    pub view_count: u64,
+    
+    /// Last accessed timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_accessed_at: Option<DateTime<Utc>>,
+    
+    /// Unique visitors (approximate)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_visitors: Option<u64>,
+}
+
+/// Share link - stored in Table Storage
+///
+/// Table: `shares`
+/// - PartitionKey: `organization_id`
+/// - RowKey: `id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    /// Unique identifier (RowKey in Table Storage, id in Cosmos DB)
+    pub id: String,
+    
+    /// Secure random key for public access (64 chars hex = 256 bits)
+    pub share_key: String,
+    
+    /// Short code for URL (8 chars, alphanumeric)
+    pub short_code: String,
+    
+    /// Visibility mode
+    pub visibility: ShareVisibility,
+    
+    /// Organization that created this share (PartitionKey)
+    pub organization_id: String,
+    
+    /// User who created the share
+    pub created_by: String,
+    
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+    
+    /// Expiration timestamp
+    pub expires_at: DateTime<Utc>,
+    
+    /// Last renewed timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renewed_at: Option<DateTime<Utc>>,
+    
+    /// Optional friendly name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    
+    /// Optional description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    
+    /// Layer configuration (stored as JSON string in Table Storage)
+    pub layer_config: ShareLayerConfig,
+    
+    /// View settings
+    pub view_settings: ShareViewSettings,
+    
+    /// Shown with the organization's default view settings under
+    /// `view_overrides` instead of `view_settings` (see
+    /// [`crate::public_access::view_settings`])
+    #[serde(default)]
+    pub inherit_view_settings: bool,
+    
+    /// Fields a share following the organization's defaults sets itself
+    #[serde(default, skip_serializing_if = "ShareViewSettingsPatch::is_empty")]
+    pub view_overrides: ShareViewSettingsPatch,
+    
+    /// Access statistics
+    #[serde(default)]
+    pub stats: ShareStats,
+    
+    /// Whether the share is active
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+    
+    /// TTL in seconds (for Cosmos DB, calculated from expires_at)
+    /// In Table Storage, we check expires_at manually
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<i64>,
+    
+    /// Number of abuse/misconfiguration reports received, one per reporter
+    /// within [`REPORTER_WINDOW_HOURS`]
+    #[serde(default)]
+    pub report_count: u32,
+    
+    /// Reporters seen within [`REPORTER_WINDOW_HOURS`], oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reporters: Vec<ShareReporter>,
+    
+    /// Serve from a static snapshot on the CDN instead of the API
+    #[serde(default)]
+    pub publish_snapshot: bool,
+    
+    /// Allow search engines to index the share (None = organization default, see [`crate::indexing`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indexable: Option<bool>,
+    
+    /// Display name of `created_by`, resolved from the directory in responses only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by_name: Option<String>,
+    
+    /// Set when the share needs an admin's attention (e.g., unusual traffic)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<ShareReview>,
+    
+    /// Teams (group IDs) whose tabs list the share (see [`crate::teams_context`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub team_ids: Vec<String>,
+    
+    /// Teams channels whose tabs list the share; with `team_ids` empty, every team and channel does
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channel_ids: Vec<String>,
+    
+    /// Set while the share is in the recycle bin (see [`crate::recycle_bin`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    
+    /// Layers of partner organizations merged into the share; non-empty for
+    /// combined shares (see [`crate::federation`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partner_sources: Vec<PartnerSource>,
+}
+
+/// Layers a partner organization granted to a combined share
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartnerSource {
+    /// Granting organization
+    pub organization_id: String,
+    /// [`FederationGrant`] in the granting organization's policy
+    pub grant_id: String,
+    /// Granted layers, as of the token; narrowed to the grant's at access time
+    pub layer_ids: Vec<String>,
+}
+
+impl ShareLink {
+    /// Calculate TTL in seconds from expiration date
+    pub fn calculate_ttl(&self, now: DateTime<Utc>) -> i64 {
+        let diff = self.expires_at.signed_duration_since(now);
+        diff.num_seconds().max(0)
+    }
+    
+    /// Check if share is expired
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now > self.expires_at
+    }
+    
+    /// Check if share needs renewal (within 30 days of expiry)
+    pub fn needs_renewal(&self, now: DateTime<Utc>) -> bool {
+        let thirty_days = chrono::Duration::days(30);
+        self.expires_at - now < thirty_days
+    }
+}
+
+/// Minimum time a deleted share's short code stays retired
+pub const SHORT_CODE_RETIREMENT_DAYS: i64 = 90;
+
+/// Retired short code of a deleted share
+///
+/// Deliberately carries no tenant data, so it survives an organization purge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortCodeTombstone {
+    pub short_code: String,
+    pub retired_at: DateTime<Utc>,
+    /// Code may be re-issued after this point
+    pub retired_until: DateTime<Utc>,
+}
+
+impl ShortCodeTombstone {
+    /// Tombstone for a share being deleted, if its links may still be in use
+    ///
+    /// The code stays retired until [`SHORT_CODE_RETIREMENT_DAYS`] after the
+    /// later of now and the share's expiry. Shares that expired longer ago
+    /// than that have dead links already and need no tombstone.
+    pub fn for_deleted_share(share: &ShareLink, now: DateTime<Utc>) -> Option<Self> {
+        let retired_until = share.expires_at.max(now) + Duration::days(SHORT_CODE_RETIREMENT_DAYS);
+        if share.expires_at + Duration::days(SHORT_CODE_RETIREMENT_DAYS) < now {
+            return None;
+        }
+        
+        Some(Self {
+            short_code: share.short_code.clone(),
+            retired_at: now,
+            retired_until,
+        })
+    }
+    
+    /// Check if the code is still retired
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        now < self.retired_until
+    }
+}
+
+// ============================================
+// Activity Models
+// ============================================
+
+/// Link attached to an activity (see [`crate::links`])
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityLink {
+    pub title: String,
+    /// https URL
+    pub url: String,
+}
+
+/// Markup of an activity description (see [`crate::markdown`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DescriptionFormat {
+    /// Plain text; the format of descriptions written before Markdown support
+    #[default]
+    Plain,
+    Markdown,
+}
+
+/// Activity type category
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityType {
+    Meeting,
+    Deadline,
+    Event,
+    Planning,
+    Review,
+    Training,
+    Holiday,
+    #[default]
+    Other,
+}
+
+/// Approval state of an activity on a controlled layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ApprovalStatus {
+    /// Visible everywhere (default, and for layers without approval mode)
+    #[default]
+    Approved,
+    /// Submitted by a non-admin, waiting for review
+    PendingApproval,
+    /// Rejected by an admin
+    Rejected,
+}
+
+/// Review decision recorded on an activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalReview {
+    pub reviewed_by: String,
+    pub reviewed_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// How often a recurring activity repeats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecurrenceFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Repetition of an activity (see [`crate::recurrence`])
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recurrence {
+    pub frequency: RecurrenceFrequency,
+    /// Every `interval` days, weeks, months or years (default: 1)
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    /// Days each occurrence lasts after the day it starts (default: 0)
+    #[serde(default)]
+    pub duration_days: u32,
+    /// Changes to single occurrences
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<OccurrenceException>,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+/// Change to one occurrence of a recurring activity, keyed by the date it
+/// would start on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OccurrenceException {
+    pub date: NaiveDate,
+    /// Drop the occurrence; the other fields are ignored
+    #[serde(default)]
+    pub skip: bool,
+    /// Moved start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<DateTime<Utc>>,
+    /// Moved end (default: the series' duration after the start)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<DateTime<Utc>>,
+    /// Title of this occurrence only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Activity - a planned event in the annual wheel
+///
+/// Table: `activities`
+/// - PartitionKey: `organization_id`
+/// - RowKey: `id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Activity {
+    /// Unique identifier
+    pub id: String,
+    
+    /// Activity title
+    pub title: String,
+    
+    /// Start date
+    pub start_date: DateTime<Utc>,
+    
+    /// End date
+    pub end_date: DateTime<Utc>,
+    
+    /// Activity type
+    #[serde(rename = "type")]
+    pub activity_type: ActivityType,
+    
+    /// Display color (hex)
+    pub color: String,
+    
+    /// Highlight color for borders/hover (darker)
+    pub highlight_color: String,
+    
+    /// Optional description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    
+    /// Markup of `description`
+    #[serde(default)]
+    pub description_format: DescriptionFormat,
+    
+    /// Related documents and pages
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<ActivityLink>,
+    
+    /// Free-form labels (lowercase), used for filtering calendar feeds
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    
+    /// Drawn above activities of lower priority where they overlap (see [`crate::ordering`])
+    #[serde(default)]
+    pub priority: i32,
+    
+    /// Scope - Layer ID this activity belongs to
+    pub scope: String,
+    
+    /// Scope ID (for backward compat, same as scope)
+    pub scope_id: String,
+    
+    /// Organization ID (PartitionKey)
+    pub organization_id: String,
+    
+    /// User who created the activity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    
+    /// Creation timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    
+    /// Last modified timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+    
+    /// Updates since creation (see [`crate::planning`])
+    #[serde(default)]
+    pub edit_count: u32,
+    
+    /// Approval state (always approved unless the layer requires approval)
+    #[serde(default)]
+    pub approval_status: ApprovalStatus,
+    
+    /// Latest review decision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_review: Option<ApprovalReview>,
+    
+    /// Activity this one was split off from (see [`crate::split_merge`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_from: Option<String>,
+    
+    /// Activities merged into this one, which no longer exist
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merged_from: Vec<String>,
+    
+    /// Set while the activity is in the recycle bin (see [`crate::recycle_bin`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    
+    /// Makes the activity a series: `start_date` is the start of the first
+    /// occurrence and `end_date` the end of the series
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    
+    /// Display name of `created_by`, resolved from the directory in responses only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by_name: Option<String>,
+}
+
+// ============================================
+// Layer Models
+// ============================================
+
+/// Layer type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LayerType {
+    Holidays,
+    Organization,
+    /// School year generated from the term structure (see [`crate::terms`])
+    Terms,
+    #[default]
+    Custom,
+}
+
+/// Layer - admin-configurable ring in the wheel
+///
+/// Table: `layers`
+/// - PartitionKey: `organization_id`
+/// - RowKey: `id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Layer {
+    /// Unique identifier
+    pub id: String,
+    
+    /// Layer name
+    pub name: String,
+    
+    /// Optional description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    
+    /// Layer type
+    #[serde(rename = "type")]
+    pub layer_type: LayerType,
+    
+    /// Display color (hex)
+    pub color: String,
+    
+    /// Position on the wheel (0 = innermost)
+    pub ring_index: i32,
+    
+    /// Default visibility for users
+    #[serde(default = "default_true")]
+    pub is_visible: bool,
+    
+    /// Non-admin submissions require admin approval before they are shared
+    #[serde(default)]
+    pub requires_approval: bool,
+    
+    /// Azure AD groups whose members may see the layer (empty = everyone)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub visible_to_groups: Vec<String>,
+    
+    /// Teams (group IDs) whose tabs show the layer (see [`crate::teams_context`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub team_ids: Vec<String>,
+    
+    /// Teams channels whose tabs show the layer; with `team_ids` empty, every team and channel does
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channel_ids: Vec<String>,
+    
+    /// Organization ID (PartitionKey)
+    pub organization_id: String,
+    
+    /// User who created the layer
+    pub created_by: String,
+    
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+    
+    /// Last modified timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+// ============================================
+// Activity Type Configuration
+// ============================================
+
+/// Activity type configuration - admin customizable
+///
+/// Table: `activitytypes`
+/// - PartitionKey: `organization_id`
+/// - RowKey: `key` (e.g., "meeting", "holiday")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityTypeConfig {
+    /// Type key (RowKey)
+    pub key: String,
+    
+    /// Display label
+    pub label: String,
+    
+    /// Icon identifier
+    pub icon: String,
+    
+    /// Default color (hex)
+    pub color: String,
+    
+    /// Highlight color (hex)
+    pub highlight_color: String,
+    
+    /// Description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    
+    /// Organization ID (PartitionKey)
+    pub organization_id: String,
+    
+    /// Whether this is a system default (can't be deleted)
+    #[serde(default)]
+    pub is_system: bool,
+    
+    /// Sort order
+    #[serde(default)]
+    pub sort_order: i32,
+    
+    /// Last modified timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Activities of a type in one year
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageByYear {
+    pub year: i32,
+    pub count: u64,
+}
+
+/// Activities of a type in one layer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageByLayer {
+    pub layer_id: String,
+    pub count: u64,
+}
+
+/// Response for `GET /api/activity-types/{key}/usage`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityTypeUsage {
+    pub key: String,
+    /// Activities using the type
+    pub total: u64,
+    pub by_year: Vec<UsageByYear>,
+    pub by_layer: Vec<UsageByLayer>,
+}
+
+/// Query parameters of `DELETE /api/activity-types/{key}`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteActivityTypeQuery {
+    /// Built-in type to move the type's activities to; without it, deleting a type in use is refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reassign_to: Option<String>,
+    
+    /// Report what would change without writing
+    #[serde(default, rename = "dry_run", alias = "dryRun")]
+    pub dry_run: bool,
+}
+
+/// Response for `DELETE /api/activity-types/{key}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityTypeDeletion {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reassigned_to: Option<String>,
+    /// Activities moved to `reassigned_to`
+    pub reassigned: u64,
+}
+
+/// What a bulk reassignment moves activities between
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Reassignment {
+    /// Layer IDs
+    Layer { from: String, to: String },
+    /// Built-in activity type keys
+    #[serde(rename = "type")]
+    ActivityType { from: String, to: String },
+}
+
+/// Request body for `POST /api/admin/reassign`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReassignRequest {
+    #[serde(flatten)]
+    pub reassignment: Reassignment,
+    
+    /// Only activities starting in this year
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+}
+
+/// Request for `POST /api/activities/{id}/split`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitActivityRequest {
+    /// Where the activity is split: the first part ends and the second starts here
+    pub at: DateTime<Utc>,
+    /// Title of the second part (defaults to the original title)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Response for `POST /api/activities/{id}/split`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitActivityResponse {
+    /// The original activity, shortened to end at the split
+    pub first: Activity,
+    /// The new activity from the split on, linked by `splitFrom`
+    pub second: Activity,
+}
+
+/// Request for `POST /api/activities/merge`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeActivitiesRequest {
+    /// Activity that is kept
+    pub target_id: String,
+    /// Activity merged into the target and deleted
+    pub source_id: String,
+}
+
+/// Response for `POST /api/admin/reassign`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReassignResult {
+    /// Activities moved
+    pub reassigned: u64,
+}
+
+// ============================================
+// API Request/Response Models
+// ============================================
+
+/// Request to create a share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateShareRequest {
+    pub visibility: ShareVisibility,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub layer_config: ShareLayerConfig,
+    /// Fields set here override the organization's default view settings;
+    /// the share follows it for the others
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_settings: Option<ShareViewSettingsPatch>,
+    /// Publish a static snapshot (for high-traffic shares)
+    #[serde(default)]
+    pub publish_snapshot: bool,
+    /// Federation tokens of partner organizations whose layers the share
+    /// combines with its own (see [`crate::federation`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub federation_tokens: Vec<String>,
+}
+
+/// Request for `PATCH /api/shares/{id}`; omitted fields are left alone
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateShareRequest {
+    /// An empty name removes it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// An empty description removes it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Applied on top of the settings the share is shown with; the share
+    /// stops following the organization defaults unless
+    /// `inheritViewSettings` says otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_settings: Option<ShareViewSettingsPatch>,
+    /// Follow the organization's default view settings again (`true`), or
+    /// keep the share's current ones (`false`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherit_view_settings: Option<bool>,
+}
+
+/// Request for `PUT /api/shares/{id}/snapshot`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetShareSnapshotRequest {
+    pub enabled: bool,
+}
+
+/// Request for `PUT /api/shares/{id}/indexing`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetShareIndexingRequest {
+    /// None follows the organization default
+    #[serde(default)]
+    pub indexable: Option<bool>,
+}
+
+/// Response when creating a share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateShareResponse {
+    pub share: ShareLink,
+    pub share_url: String,
+    pub embed_code: String,
+    /// `webcal://` subscription to the share's calendar feed; public shares
+    /// only, since calendar clients can't sign in for Users shares
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calendar_url: Option<String>,
+    /// Signed, short-lived thumbnail for link unfurling; public shares only,
+    /// when preview links are configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview_url: Option<String>,
+}
+
+/// Request to access a public share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessShareRequest {
+    pub short_code: String,
+    pub key: String,
+}
+
+/// Share access config returned to clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareAccessConfig {
+    pub layers: ShareLayerConfig,
+    pub view_settings: ShareViewSettings,
+    pub organization_name: String,
+    pub title: String,
+    /// Whether the share page may be indexed (robots meta tag)
+    #[serde(default)]
+    pub indexable: bool,
+}
+
+/// Activity for share access (simplified)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareActivity {
+    pub id: String,
+    pub title: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub color: String,
+    pub highlight_color: String,
+    pub layer_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Sanitized HTML rendering of `description`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<ActivityLink>,
+    /// Position in the ring's draw order, from 0 at the bottom; clients draw
+    /// overlapping activities of a ring in ascending order
+    #[serde(default)]
+    pub z_index: u32,
+}
+
+/// Calendar feed filters (`GET /api/public/s/{shortCode}/calendar.ics`)
+///
+/// List values are comma-separated so feed URLs stay readable in calendar
+/// clients; an activity matches a list when it matches any entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarFeedQuery {
+    /// Layer IDs (must be shared layers)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layers: Option<String>,
+    /// Activity types, e.g. `meeting,deadline`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub types: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+    /// First day of the window (inclusive); default: the share's year
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<NaiveDate>,
+    /// Last day of the window (inclusive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<NaiveDate>,
+}
+
+/// Response when accessing a share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessShareResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<ShareAccessConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activities: Option<Vec<ShareActivity>>,
+    /// Activities were left out to answer in time (see `storage_budget`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+/// Query of `GET /api/public/s/{shortCode}/upcoming`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingQuery {
+    /// Number of activities, default 10, at most 50
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Activity in the upcoming list, for signage and intranet widgets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingActivity {
+    pub id: String,
+    pub title: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub color: String,
+    pub layer_id: String,
+}
+
+/// Next activities of a public share, without the wheel configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingResponse {
+    pub title: String,
+    pub activities: Vec<UpcomingActivity>,
+    /// Activities were left out to answer in time (see `storage_budget`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+/// Request to renew a share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenewShareRequest {
+    pub share_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_expires_at: Option<DateTime<Utc>>,
+}
+
+/// List shares request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListSharesRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<ShareVisibility>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_active: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+}
+
+/// List shares response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListSharesResponse {
+    pub shares: Vec<ShareLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+    /// Shares on all pages matching the filter
+    pub total_count: u64,
+}
+
+/// Request to create an activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateActivityRequest {
+    pub title: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    #[serde(rename = "type", default)]
+    pub activity_type: ActivityType,
+    pub color: String,
+    pub highlight_color: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Markup of `description` (default: plain text)
+    #[serde(default)]
+    pub description_format: DescriptionFormat,
+    #[serde(default)]
+    pub links: Vec<ActivityLink>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Draw order among overlapping activities (default: 0)
+    #[serde(default)]
+    pub priority: i32,
+    /// Repeat the activity until `end_date`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    /// Layer ID
+    pub scope: String,
+}
+
+/// Request to update an activity (omitted fields are left unchanged)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateActivityRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<DateTime<Utc>>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub activity_type: Option<ActivityType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight_color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_format: Option<DescriptionFormat>,
+    /// Replaces all links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<ActivityLink>>,
+    /// Replaces all tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    /// Replaces the recurrence; `null` makes the activity a single event
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Option<Recurrence>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// A field that is present, even as `null`, so `Option<Option<T>>` can
+/// tell a removal from an omitted field
+fn present<'de, T: Deserialize<'de>, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+/// List activities request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListActivitiesRequest {
+    /// Restrict to these layers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_ids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+}
+
+/// Activity search request (`GET /api/activities/search`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchActivitiesRequest {
+    /// Search text; every word must match the title or description
+    pub q: String,
+    /// Restrict to these layers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_ids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    /// Maximum results (default 25, max 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top: Option<usize>,
+}
+
+/// Where search results came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSource {
+    /// External search index
+    Index,
+    /// Scan of activity storage (no index configured, or the index failed)
+    Storage,
+}
+
+/// Activity search response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchActivitiesResponse {
+    pub items: Vec<Activity>,
+    pub source: SearchSource,
+}
+
+/// Approve/reject request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalDecisionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Advisory edit lock on an activity
+///
+/// Locks are short-lived and renewed by re-acquiring while the editor is open.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditLock {
+    pub activity_id: String,
+    pub organization_id: String,
+    
+    /// User holding the lock
+    pub locked_by: String,
+    
+    /// Display name of the holder (shown to other planners)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked_by_name: Option<String>,
+    
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl EditLock {
+    /// Check if the lock has expired
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now > self.expires_at
+    }
+}
+
+// ============================================
+// Directory Models
+// ============================================
+
+/// People picker request (`GET /api/directory/search`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectorySearchRequest {
+    /// Start of a name or email address
+    pub q: String,
+    /// Maximum results per kind (default 10, max 25)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top: Option<usize>,
+}
+
+/// Kind of directory object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectoryEntryKind {
+    User,
+    Group,
+}
+
+/// User or group found in the organization's directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryEntry {
+    /// Object ID
+    pub id: String,
+    pub kind: DirectoryEntryKind,
+    pub display_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_principal_name: Option<String>,
+}
+
+/// Outcome of a Graph permission probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionState {
+    Granted,
+    Missing,
+    /// The probe failed for another reason (network, throttling)
+    Unknown,
+}
+
+/// One Graph application permission and whether the tenant granted it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphPermissionStatus {
+    pub permission: String,
+    /// Features that need the permission
+    pub used_for: String,
+    pub state: PermissionState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Permission reference documentation
+    pub docs_url: String,
+}
+
+/// Response for `GET /api/admin/integrations/graph/status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphIntegrationStatus {
+    /// Graph credentials are configured on the server
+    pub configured: bool,
+    /// Every permission is granted
+    pub healthy: bool,
+    pub permissions: Vec<GraphPermissionStatus>,
+    /// Where a tenant admin grants consent for the whole organization
+    pub admin_consent_url: String,
+}
+
+// ============================================
+// Share Report Models
+// ============================================
+
+/// Why a public share was reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShareReportReason {
+    /// Exposes data that should not be public
+    SensitiveData,
+    /// Offensive or inappropriate content
+    Inappropriate,
+    /// Spam or phishing
+    Spam,
+    Other,
+}
+
+/// Request body for `POST /api/public/s/{shortCode}/report`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportShareRequest {
+    pub reason: ShareReportReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    /// Optional email or phone so the admin can follow up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reporter_contact: Option<String>,
+}
+
+/// A recorded report against a share
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareReport {
+    pub id: String,
+    pub share_id: String,
+    pub organization_id: String,
+    pub short_code: String,
+    pub reason: ShareReportReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reporter_contact: Option<String>,
+    pub reported_at: DateTime<Utc>,
+    /// Whether this report pushed the share over the auto-deactivation threshold
+    pub auto_deactivated: bool,
+}
+
+/// How long a report keeps further ones from the same reporter from counting
+pub const REPORTER_WINDOW_HOURS: i64 = 24;
+
+/// Reporters a share remembers; the oldest are forgotten first
+pub const MAX_SHARE_REPORTERS: usize = 100;
+
+/// Someone who reported a share, keyed by their pseudonymized network
+/// (see [`crate::client_info::ClientInfo::network`])
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareReporter {
+    pub reporter: String,
+    pub reported_at: DateTime<Utc>,
+}
+
+/// Response for share reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportShareResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// ============================================
+// Share Traffic Models
+// ============================================
+
+/// Views of one share on one day (UTC)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyShareTraffic {
+    pub organization_id: String,
+    pub share_id: String,
+    pub date: NaiveDate,
+    pub views: u64,
+    /// Views by ISO 3166-1 alpha-2 country code, when the edge provides one
+    #[serde(default)]
+    pub countries: std::collections::HashMap<String, u64>,
+}
+
+/// Unusual traffic on a share
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum TrafficAnomaly {
+    /// Daily views far above the share's baseline
+    ViewSpike { views: u64, baseline: f64 },
+    /// Views from a country never seen in the baseline period
+    UnusualCountry { country: String, views: u64 },
+}
+
+/// Admin alert raised by the traffic monitor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareTrafficAlert {
+    pub share_id: String,
+    pub organization_id: String,
+    pub short_code: String,
+    pub date: NaiveDate,
+    pub anomalies: Vec<TrafficAnomaly>,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Review flag on a share
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareReview {
+    pub anomalies: Vec<TrafficAnomaly>,
+    pub flagged_at: DateTime<Utc>,
+}
+
+// ============================================
+// Share Renewal Models
+// ============================================
+
+/// Expiry reminder for a share owner, with a one-click renewal link
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareRenewalReminder {
+    pub organization_id: String,
+    pub share_id: String,
+    pub share_name: Option<String>,
+    /// Recipient: the user who created the share
+    pub owner_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub days_left: i64,
+    pub renewal_url: String,
+    pub link_expires_at: DateTime<Utc>,
+    /// Language of the email: the owner's, else the organization's (see [`crate::i18n`])
+    #[serde(default = "default_calendar_locale")]
+    pub locale: String,
+}
+
+/// Request for `POST /api/shares/renew-by-token`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenewByTokenRequest {
+    pub token: String,
+}
+
+/// Response of `POST /api/shares/renew-by-token`; the link holder gets no share key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenewByTokenResponse {
+    pub share_id: String,
+    pub share_name: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// ============================================
+// Delta Sync Models
+// ============================================
+
+/// Delta query request (`GET /api/delta?token=`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaRequest {
+    /// Sync token from a previous response (omit for initial sync)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// Changes for one entity type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityDelta<T> {
+    /// Entities created or modified since the sync token
+    pub changed: Vec<T>,
+    
+    /// IDs of all live entities (clients drop cached entries not in this list)
+    pub ids: Vec<String>,
+}
+
+/// Delta query response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaResponse {
+    pub activities: EntityDelta<Activity>,
+    pub layers: EntityDelta<Layer>,
+    pub activity_types: EntityDelta<ActivityTypeConfig>,
+    
+    /// Token to pass on the next delta request
+    pub sync_token: String,
+    
+    /// True when the response contains every entity (initial or forced resync)
+    pub full_sync: bool,
+}
+
+// ============================================
+// User Settings Models
+// ============================================
+
+/// User theme preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UserTheme {
+    Light,
+    Dark,
+    #[default]
+    System,
+}
+
+/// Notification delivery channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationChannel {
+    Email,
+    Teams,
+}
+
+/// How often the activity digest is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFrequency {
+    Daily,
+    #[default]
+    Weekly,
+    Monthly,
+}
+
+/// Window in which no notifications are delivered (local time)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    /// Start of the quiet window (e.g., "22:00:00")
+    pub start: NaiveTime,
+    
+    /// End of the quiet window; may be earlier than start (spans midnight)
+    pub end: NaiveTime,
+    
+    /// User's offset from UTC in minutes
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+impl QuietHours {
+    /// Check if a point in time falls inside the quiet window
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let local = (at + Duration::minutes(self.utc_offset_minutes as i64)).time();
+        if self.start <= self.end {
+            local >= self.start && local < self.end
+        } else {
+            local >= self.start || local < self.end
+        }
+    }
+}
+
+/// Per-user notification preferences, consulted by the digest and reminder jobs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPreferences {
+    /// Receive the periodic activity digest
+    #[serde(default = "default_true")]
+    pub digest_enabled: bool,
+    
+    #[serde(default)]
+    pub digest_frequency: DigestFrequency,
+    
+    /// Send reminders this many days before an activity starts (empty = no reminders)
+    #[serde(default = "default_reminder_lead_days")]
+    pub reminder_lead_days: Vec<u32>,
+    
+    /// Enabled delivery channels
+    #[serde(default = "default_notification_channels")]
+    pub channels: Vec<NotificationChannel>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+fn default_reminder_lead_days() -> Vec<u32> {
+    vec![1]
+}
+
+fn default_notification_channels() -> Vec<NotificationChannel> {
+    vec![NotificationChannel::Teams]
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            digest_enabled: true,
+            digest_frequency: DigestFrequency::default(),
+            reminder_lead_days: default_reminder_lead_days(),
+            channels: default_notification_channels(),
+            quiet_hours: None,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// Check if a channel is enabled
+    pub fn allows_channel(&self, channel: NotificationChannel) -> bool {
+        self.channels.contains(&channel)
+    }
+    
+    /// Check if the digest should be sent on this channel
+    pub fn wants_digest(&self, channel: NotificationChannel) -> bool {
+        self.digest_enabled && self.allows_channel(channel)
+    }
+    
+    /// Check if a reminder is due for an activity starting on `start_date`
+    ///
+    /// Reminder jobs run daily; a reminder is due when the activity starts
+    /// exactly one of the configured lead times from `today`.
+    pub fn reminder_due(&self, today: chrono::NaiveDate, start_date: chrono::NaiveDate) -> bool {
+        let days_until = (start_date - today).num_days();
+        self.reminder_lead_days.iter().any(|&d| d as i64 == days_until)
+    }
+    
+    /// Check if notifications may be delivered at this time
+    pub fn can_deliver_at(&self, at: DateTime<Utc>) -> bool {
+        !self.quiet_hours.as_ref().is_some_and(|q| q.contains(at))
+    }
+}
+
+/// User-specific settings
+/// 
+/// Table: `usersettings`
+/// - PartitionKey: `organizationId`
+/// - RowKey: `userId`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSettings {
+    /// User ID (from Teams/Azure AD)
+    pub user_id: String,
+    
+    /// Organization ID
+    pub organization_id: String,
+    
+    /// User's preferred layer order (array of layer IDs, inner to outer)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_order: Option<Vec<String>>,
+    
+    /// Layer visibility overrides (layerId -> visible)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_visibility: Option<std::collections::HashMap<String, bool>>,
+    
+    /// User theme preference
+    #[serde(default)]
+    pub theme: UserTheme,
+    
+    /// Notification preferences
+    #[serde(default)]
+    pub notifications: NotificationPreferences,
+    
+    /// Language of digests, reminders and exports (`en`, `nb`); None follows the organization (see [`crate::i18n`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    
+    /// Last updated timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UserSettings {
+    /// Create new user settings with defaults
+    pub fn new(user_id: String, organization_id: String) -> Self {
+        Self {
+            user_id,
+            organization_id,
+            layer_order: None,
+            layer_visibility: None,
+            theme: UserTheme::default(),
+            notifications: NotificationPreferences::default(),
+            locale: None,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// Request to update user settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUserSettingsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_order: Option<Vec<String>>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_visibility: Option<std::collections::HashMap<String, bool>>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<UserTheme>,
+    
+    /// Empty to follow the organization again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+// ============================================
+// Audit Models
+// ============================================
+
+/// Audit log entry
+///
+/// Table: `audit`
+/// - PartitionKey: `organizationId`
+/// - RowKey: `id` (time-ordered)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: String,
+    pub organization_id: String,
+    
+    /// Dotted action name (e.g., "organization.purged")
+    pub action: String,
+    
+    /// Pseudonymized user or service principal that performed the action (see [`crate::pseudonym`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<String>,
+    
+    /// Affected entity ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<String>,
+    
+    pub occurred_at: DateTime<Utc>,
+    
+    /// Action-specific payload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl AuditEntry {
+    /// Create a new entry with a time-ordered ID
+    pub fn new(organization_id: &str, action: &str, actor_id: Option<&str>, target_id: Option<&str>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: format!("{:020}-{}", now.timestamp_micros(), uuid::Uuid::new_v4().simple()),
+            organization_id: organization_id.to_string(),
+            action: action.to_string(),
+            actor_id: actor_id.map(str::to_string),
+            target_id: target_id.map(str::to_string),
+            occurred_at: now,
+            details: None,
+        }
+    }
+    
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+// ============================================
+// Organization Policy Models
+// ============================================
+
+/// Which past activities are read-only for non-admins (see [`crate::period_lock`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PeriodLockRule {
+    /// Activities ending before a fixed date
+    Before { date: NaiveDate },
+    /// Activities ending before the quarter `quarters` back from the current one
+    Quarters { quarters: u32 },
+}
+
+/// Public API requests per minute (see [`crate::rate_limit`]); unset limits use the defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatePlan {
+    /// Across all public shares of the organization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization_per_minute: Option<u32>,
+    
+    /// Per share key, by share ID
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub api_keys: std::collections::BTreeMap<String, u32>,
+}
+
+/// Where an organization's public share access events go (see [`crate::access_log`])
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AccessLogDestination {
+    /// HTTPS endpoint receiving JSON arrays of events
+    #[serde(rename_all = "camelCase")]
+    Webhook { url: String },
+    /// Azure Event Hub, written to with the API's managed identity
+    #[serde(rename_all = "camelCase")]
+    EventHub { namespace: String, hub: String },
+}
+
+/// Forwarding of public share access events to a SIEM
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogForwarding {
+    pub destination: AccessLogDestination,
+    
+    /// Share of access events forwarded, in `(0, 1]`
+    #[serde(default = "AccessLogForwarding::default_sample_rate")]
+    pub sample_rate: f64,
+}
+
+impl AccessLogForwarding {
+    fn default_sample_rate() -> f64 {
+        1.0
+    }
+}
+
+/// Organization-wide policies, set by admins
+///
+/// Table: `policies`
+/// - PartitionKey: `organization_id`
+/// - RowKey: `policy`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationPolicy {
+    pub organization_id: String,
+    
+    /// Past period lock (None = everything editable)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_lock: Option<PeriodLockRule>,
+    
+    /// Public API rate limits (None = defaults)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_plan: Option<RatePlan>,
+    
+    /// SIEM forwarding of public share access (None = off)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_log: Option<AccessLogForwarding>,
+    
+    /// Week numbering and locale of rendered outputs (None = defaults)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<CalendarSettings>,
+    
+    /// School year terms, holidays and exams (None = no terms layer)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terms: Option<TermStructure>,
+    
+    /// Public shares may be indexed by search engines unless they opt out
+    #[serde(default)]
+    pub indexable: bool,
+    
+    /// Default view settings of shares, under the fields each share sets itself (None = built-in defaults)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_view_defaults: Option<ShareViewSettings>,
+    
+    /// Draw order of overlapping activities in a ring (None = by duration)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity_order: Option<ActivityOrder>,
+    
+    /// Layers shared with partner organizations (see [`crate::federation`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub federation_grants: Vec<FederationGrant>,
+    
+    /// Pseudonymized admin who last changed the policy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl OrganizationPolicy {
+    /// Policy of an organization that never set one
+    pub fn new(organization_id: &str) -> Self {
+        Self {
+            organization_id: organization_id.to_string(),
+            period_lock: None,
+            rate_plan: None,
+            access_log: None,
+            calendar: None,
+            terms: None,
+            indexable: false,
+            share_view_defaults: None,
+            activity_order: None,
+            federation_grants: Vec::new(),
+            updated_by: None,
+            updated_at: None,
+        }
+    }
+}
+
+/// Read access to selected layers, granted to a partner organization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FederationGrant {
+    pub id: String,
+    /// Organization (tenant ID) whose combined shares may show the layers
+    pub partner_organization_id: String,
+    pub layer_ids: Vec<String>,
+    /// Pseudonymized admin who granted access
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// None = until revoked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Show descriptions and links of the layers' activities; titles and dates only otherwise
+    #[serde(default)]
+    pub include_descriptions: bool,
+}
+
+/// Request for `POST /api/admin/federation/grants`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateFederationGrantRequest {
+    pub partner_organization_id: String,
+    pub layer_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub include_descriptions: bool,
+}
+
+/// Response of `POST /api/admin/federation/grants`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FederationGrantResponse {
+    pub grant: FederationGrant,
+    /// Signed token to hand to the partner, who attaches it to a new share
+    pub token: String,
+}
+
+/// Request for `PUT /api/admin/policy/period-lock`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetPeriodLockRequest {
+    /// None lifts the lock
+    #[serde(default)]
+    pub rule: Option<PeriodLockRule>,
+}
+
+/// Current period lock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodLockStatus {
+    pub rule: Option<PeriodLockRule>,
+    /// First open day today; activities ending earlier are read-only for non-admins
+    pub locked_before: Option<NaiveDate>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Current rate plan with the limits in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatePlanStatus {
+    pub plan: RatePlan,
+    /// Organization-wide requests per minute in effect
+    pub organization_per_minute: u32,
+    /// Requests per minute of share keys without their own limit
+    pub default_api_key_per_minute: u32,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Request for `PUT /api/admin/policy/access-log`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetAccessLogForwardingRequest {
+    /// None stops forwarding
+    #[serde(default)]
+    pub forwarding: Option<AccessLogForwarding>,
+}
+
+/// Current access log forwarding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogForwardingStatus {
+    pub forwarding: Option<AccessLogForwarding>,
+    /// Whether this deployment forwards at all; settings are kept either way
+    pub available: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Day calendar weeks start on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FirstDayOfWeek {
+    #[default]
+    Monday,
+    Sunday,
+    Saturday,
+}
+
+/// How weeks are numbered (see [`crate::calendar`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WeekNumbering {
+    /// ISO 8601: weeks start on Monday, week 1 holds the year's first Thursday
+    #[default]
+    Iso,
+    /// Week 1 holds 1 January; weeks start on the first day of week
+    Simple,
+}
+
+/// Local calendar conventions of an organization's rendered outputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarSettings {
+    /// Month and weekday names, dates and titles: `en` or `nb`
+    #[serde(default = "default_calendar_locale")]
+    pub locale: String,
+    #[serde(default)]
+    pub first_day_of_week: FirstDayOfWeek,
+    #[serde(default)]
+    pub week_numbering: WeekNumbering,
+}
+
+fn default_calendar_locale() -> String {
+    "en".to_string()
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        Self {
+            locale: default_calendar_locale(),
+            first_day_of_week: FirstDayOfWeek::default(),
+            week_numbering: WeekNumbering::default(),
+        }
+    }
+}
+
+/// Request for `PUT /api/admin/policy/calendar`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCalendarSettingsRequest {
+    /// None restores the defaults
+    #[serde(default)]
+    pub calendar: Option<CalendarSettings>,
+}
+
+/// Calendar conventions in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarSettingsStatus {
+    pub calendar: CalendarSettings,
+    /// Whether the organization changed the defaults
+    pub customized: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Week of a week-numbering year
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekNumber {
+    /// Year the week is numbered in; ISO weeks around New Year can belong to the other year
+    pub year: i32,
+    pub week: u32,
+}
+
+/// A numbered week and its days
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekSpan {
+    #[serde(flatten)]
+    pub number: WeekNumber,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+/// Calendar conventions rendered outputs follow, so clients can match them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarMetadata {
+    pub locale: String,
+    pub first_day_of_week: FirstDayOfWeek,
+    pub week_numbering: WeekNumbering,
+    /// January first
+    pub month_names: Vec<String>,
+    /// Starting with the first day of week
+    pub weekday_names: Vec<String>,
+    /// Weeks overlapping the year, in order
+    pub weeks: Vec<WeekSpan>,
+}
+
+/// Request for `PUT /api/admin/policy/indexing`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetIndexingPolicyRequest {
+    pub indexable: bool,
+}
+
+/// Search engine indexing in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexingPolicyStatus {
+    /// Default of public shares without their own flag
+    pub indexable: bool,
+    /// Whether `GET /sitemap.xml` lists the organization's shares that opted in to indexing
+    pub in_sitemap: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Request for `PUT /api/admin/policy/share-view-defaults`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetShareViewDefaultsRequest {
+    /// None restores the built-in defaults
+    #[serde(default)]
+    pub view_settings: Option<ShareViewSettings>,
+}
+
+/// Request for `PATCH /api/admin/policy/share-view-defaults`, applied on
+/// top of the defaults in effect
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateShareViewDefaultsRequest {
+    pub view_settings: ShareViewSettingsPatch,
+}
+
+/// Default view settings of new shares in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareViewDefaultsStatus {
+    pub view_settings: ShareViewSettings,
+    /// Whether the organization changed the built-in defaults
+    pub customized: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// How overlapping activities in a ring are stacked (see [`crate::ordering`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ActivityOrder {
+    /// Longer activities below shorter ones
+    #[default]
+    Duration,
+    /// Higher priority on top, then by duration
+    Priority,
+    /// By type (deadlines on top, holidays at the bottom), then by duration
+    Type,
+}
+
+/// Request for `PUT /api/admin/policy/activity-order`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetActivityOrderRequest {
+    /// None restores ordering by duration
+    #[serde(default)]
+    pub order: Option<ActivityOrder>,
+}
+
+/// Draw order in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityOrderStatus {
+    pub order: ActivityOrder,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Kind of a school year period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TermPeriodKind {
+    /// Semester or term
+    Term,
+    /// School holiday
+    Holiday,
+    /// Exam period
+    Exam,
+}
+
+/// Period of the school year, whole days
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermPeriod {
+    pub name: String,
+    pub kind: TermPeriodKind,
+    pub start: NaiveDate,
+    /// Last day, inclusive
+    pub end: NaiveDate,
+}
+
+/// Term structure of a school or municipality
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermStructure {
+    /// Name of the generated layer
+    #[serde(default = "default_terms_layer_name")]
+    pub layer_name: String,
+    pub periods: Vec<TermPeriod>,
+}
+
+fn default_terms_layer_name() -> String {
+    "School year".to_string()
+}
+
+/// Request for `PUT /api/admin/policy/terms`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetTermStructureRequest {
+    /// None removes the terms layer and its activities
+    #[serde(default)]
+    pub terms: Option<TermStructure>,
+}
+
+/// Changes a term generator run made
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermSyncResult {
+    /// Activity IDs, by change
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// Term structure in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermStructureStatus {
+    pub terms: Option<TermStructure>,
+    /// Generated layer, while there is a term structure
+    pub layer_id: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// What the generator changed, after a `PUT`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync: Option<TermSyncResult>,
+}
+
+/// Public endpoint a share was accessed through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PublicAccessEndpoint {
+    Share,
+    Calendar,
+    Preview,
+    Upcoming,
+}
+
+/// Outcome of a public share access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PublicAccessResult {
+    Granted,
+    Denied,
+    RateLimited,
+}
+
+/// One public share access, as forwarded to a SIEM
+///
+/// Carries no IP address or user agent; the country is the only location.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareAccessEvent {
+    pub id: String,
+    pub organization_id: String,
+    pub share_id: String,
+    pub occurred_at: DateTime<Utc>,
+    pub endpoint: PublicAccessEndpoint,
+    pub result: PublicAccessResult,
+    /// Why access was denied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// ISO 3166-1 alpha-2 country of the client, when the edge provides it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+// ============================================
+// Dry Run Models
+// ============================================
+
+/// Query parameters of mutating admin operations
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunQuery {
+    /// Report what would change without writing
+    #[serde(default, rename = "dry_run", alias = "dryRun")]
+    pub dry_run: bool,
+}
+
+/// Kind of entity a planned change touches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlannedEntity {
+    Share,
+    Activity,
+    Layer,
+    ActivityType,
+    UserSettings,
+    Policy,
+    OrganizationSnapshot,
+    AuditEntry,
+}
+
+/// Write a planned change makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlannedAction {
+    Create,
+    Update,
+    /// Deactivate without deleting (e.g. shares)
+    Revoke,
+    Delete,
+}
+
+/// One step of an execution plan: the same write to a set of entities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedChange {
+    pub entity: PlannedEntity,
+    pub action: PlannedAction,
+    pub count: u64,
+    pub ids: Vec<String>,
+}
+
+/// What an operation would change, returned by `?dry_run=true`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunReport {
+    pub dry_run: bool,
+    /// Writes in total
+    pub total: u64,
+    /// Steps in the order they would run
+    pub changes: Vec<PlannedChange>,
+}
+
+/// Response of an operation that supports dry runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DryRunOr<T> {
+    DryRun(DryRunReport),
+    Executed(T),
+}
+
+// ============================================
+// Organization Off-boarding Models
+// ============================================
+
+/// Number of entities removed per type
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeSummary {
+    pub shares: u64,
+    pub activities: u64,
+    pub layers: u64,
+    pub activity_types: u64,
+    pub user_settings: u64,
+    pub audit_entries: u64,
+    #[serde(default)]
+    pub organization_snapshots: u64,
+}
+
+/// Response for `POST /api/admin/organization/purge-confirmation`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeConfirmation {
+    /// Token to pass to `DELETE /api/admin/organization`
+    pub confirmation_token: String,
+    pub expires_at: DateTime<Utc>,
+    
+    /// What will be deleted
+    pub pending: PurgeSummary,
+}
+
+/// Request body for `DELETE /api/admin/organization`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeOrganizationRequest {
+    /// Not needed for dry runs
+    #[serde(default)]
+    pub confirmation_token: String,
+}
+
+/// Proof of deletion, kept after the organization's data is gone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletionCertificate {
+    pub id: String,
+    pub organization_id: String,
+    pub requested_by: String,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    
+    /// Shares deactivated before deletion
+    pub shares_revoked: u64,
+    pub deleted: PurgeSummary,
+}
+
+// ============================================
+// Organization Backup Models
+// ============================================
+
+/// Everything an organization has, as returned by `GET /api/admin/export`
+/// and taken by `POST /api/admin/import` (see [`crate::backup`])
+///
+/// Items in the recycle bin and the audit log are not included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupArchive {
+    /// [`crate::backup::FORMAT_VERSION`] of the exporting API
+    pub format_version: u32,
+    
+    /// Organization the archive was exported from
+    pub organization_id: String,
+    pub exported_at: DateTime<Utc>,
+    
+    /// None when the organization never set one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<OrganizationPolicy>,
+    
+    #[serde(default)]
+    pub activity_types: Vec<ActivityTypeConfig>,
+    #[serde(default)]
+    pub layers: Vec<Layer>,
+    #[serde(default)]
+    pub activities: Vec<Activity>,
+    #[serde(default)]
+    pub shares: Vec<ShareLink>,
+    #[serde(default)]
+    pub user_settings: Vec<UserSettings>,
+}
+
+/// How an import treats entities that already exist
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictStrategy {
+    /// Keep the stored entity
+    #[default]
+    Skip,
+    /// Replace the stored entity with the archive's
+    Overwrite,
+    /// Import shares, layers and activities under new IDs, next to the stored
+    /// ones; activity types, user settings and the policy are skipped
+    NewIds,
+}
+
+/// Query parameters of `POST /api/admin/import`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ImportQuery {
+    #[serde(default, alias = "onConflict")]
+    pub on_conflict: ConflictStrategy,
+}
+
+/// What an import did with entities of one type
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCount {
+    pub created: u64,
+    pub overwritten: u64,
+    /// Already stored, kept as they were
+    pub skipped: u64,
+}
+
+/// Response of `POST /api/admin/import`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub on_conflict: ConflictStrategy,
+    pub policy: ImportCount,
+    pub activity_types: ImportCount,
+    pub layers: ImportCount,
+    pub activities: ImportCount,
+    pub shares: ImportCount,
+    pub user_settings: ImportCount,
+}
+
+/// Point-in-time copy of an organization (see [`crate::org_snapshots`])
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationSnapshot {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    /// Size of the stored archive
+    pub bytes: u64,
+}
+
+// ============================================
+// Background Operation Models
+// ============================================
+
+/// What a background operation does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationKind {
+    /// `POST /api/admin/import/background`
+    Import,
+    /// `POST /api/admin/reassign/background`
+    Reassign,
+    /// `POST /api/admin/export/background`
+    Export,
+    /// `DELETE /api/admin/organization/background`
+    Purge,
+    /// `POST /api/admin/schema/rewrite`
+    SchemaRewrite,
+    /// `POST /api/admin/search/reindex`
+    Reindex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl OperationStatus {
+    /// Queued or running, i.e. to be resumed after a restart
+    pub fn is_unfinished(self) -> bool {
+        matches!(self, Self::Queued | Self::Running)
+    }
+}
+
+/// Bulk operation run in the background (see [`crate::operations`]);
+/// response of `GET /api/operations/{id}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkOperation {
+    pub id: String,
+    pub organization_id: String,
+    pub kind: OperationKind,
+    pub status: OperationStatus,
+    /// Rows written so far
+    pub completed: u64,
+    /// Rows to write
+    pub total: u64,
+    /// Pseudonymized user who started it
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Why the operation failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// [`ImportSummary`], [`ReassignResult`], [`DeletionCertificate`] or
+    /// [`SchemaRewriteSummary`] of the rows written so far, or the
+    /// [`BackupArchive`] of an export
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// When a finished operation and its result are deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Instance running the operation (see [`crate::operations`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_owner: Option<String>,
+    /// Another instance may take the operation over after this, unless the
+    /// owner renews the lease
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_expires_at: Option<DateTime<Utc>>,
+}
+
+/// Rows saved again at the current schema version (see [`crate::schema`]), per type
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaRewriteSummary {
+    pub activity_types: u64,
+    pub layers: u64,
+    pub activities: u64,
+    pub shares: u64,
+    pub user_settings: u64,
+}
+
+// ============================================
+// Privacy Models
+// ============================================
+
+/// Request for `POST /api/admin/pseudonyms/resolve`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvePseudonymsRequest {
+    pub pseudonyms: Vec<String>,
+    
+    /// Legal basis for re-identification (recorded in the audit log)
+    pub legal_basis: String,
+}
+
+/// A pseudonym and the user it belongs to, if known
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PseudonymResolution {
+    pub pseudonym: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+// ============================================
+// Teams Models
+// ============================================
+
+/// Request to scope a layer or share to Teams teams and channels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetTeamsScopeRequest {
+    /// Group IDs of the teams
+    #[serde(default)]
+    pub team_ids: Vec<String>,
+    /// Channel IDs; with both lists empty, every team and channel lists it
+    #[serde(default)]
+    pub channel_ids: Vec<String>,
+}
+
+// ============================================
+// Planning Analytics Models
+// ============================================
+
+/// Query for `GET /api/admin/analytics/planning`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanningAnalyticsRequest {
+    /// Year activities start in (default: current year)
+    #[serde(default)]
+    pub year: Option<i32>,
+}
+
+/// Planning discipline of an organization over one year (see [`crate::planning`])
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanningAnalytics {
+    pub year: i32,
+    pub activity_count: u64,
+    pub cancelled_count: u64,
+    /// Cancelled share of all planned activities (None without any)
+    pub cancellation_rate: Option<f64>,
+    pub lead_time: LeadTimeSummary,
+    /// By month the activities start in (1-12)
+    pub months: Vec<MonthlyPlanning>,
+    /// By week the activities start in, numbered as the organization does
+    pub weeks: Vec<WeeklyPlanning>,
+    pub layers: Vec<LayerPlanning>,
+}
+
+/// How far ahead activities were entered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeadTimeSummary {
+    /// Activities with a known creation time
+    pub measured: u64,
+    pub median_days: Option<f64>,
+    pub average_days: Option<f64>,
+    /// Entered after they had already started
+    pub created_after_start: u64,
+    pub buckets: Vec<LeadTimeBucket>,
+}
+
+/// Activities per lead time range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeadTimeBucket {
+    pub label: String,
+    pub count: u64,
+}
+
+/// Activities starting in one month
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyPlanning {
+    pub month: u32,
+    pub activities: u64,
+    pub median_lead_days: Option<f64>,
+}
+
+/// Activities starting in one week
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyPlanning {
+    #[serde(flatten)]
+    pub week: WeekNumber,
+    pub activities: u64,
+}
+
+/// Edit churn and cancellations on one layer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerPlanning {
+    pub layer_id: String,
+    /// None for layers that no longer exist
+    pub layer_name: Option<String>,
+    pub activities: u64,
+    pub edits: u64,
+    pub edits_per_activity: Option<f64>,
+    pub cancelled: u64,
+    pub cancellation_rate: Option<f64>,
+}
+
+// ============================================
+// Power BI Models
+// ============================================
+
+/// Table of the Power BI dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerBiTable {
+    /// Fact: one row per activity
+    Activities,
+    /// Dimension: layers
+    Layers,
+    /// Dimension: configured activity types
+    ActivityTypes,
+    /// Fact: public share views per share, day and country
+    ShareViews,
+}
+
+/// Query for `GET /api/admin/analytics/powerbi`
+///
+/// `rangeStart`/`rangeEnd` map to the Power BI incremental refresh
+/// parameters and filter facts by `modifiedAt` (activities) or `date`
+/// (share views) as `rangeStart <= x < rangeEnd`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerBiRequest {
+    pub table: PowerBiTable,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range_start: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range_end: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+}
+
+/// One page of a Power BI table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerBiPage {
+    pub table: PowerBiTable,
+    pub rows: PowerBiRows,
+    /// Pass back to get the next page; absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+}
+
+/// Rows of a [`PowerBiPage`], shaped by its table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PowerBiRows {
+    Activities(Vec<ActivityFact>),
+    Layers(Vec<LayerDimension>),
+    ActivityTypes(Vec<ActivityTypeDimension>),
+    ShareViews(Vec<ShareViewFact>),
+}
+
+/// Flattened activity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityFact {
+    pub activity_id: String,
+    pub title: String,
+    /// Key into [`LayerDimension`]
+    pub layer_id: String,
+    /// Key into [`ActivityTypeDimension`]
+    pub activity_type: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub start_day: NaiveDate,
+    pub duration_days: i64,
+    /// Tags joined with `;`
+    pub tags: String,
+    pub approval_status: ApprovalStatus,
+    pub edit_count: u32,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Last update, or creation for never edited activities (incremental refresh column)
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// Flattened layer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerDimension {
+    pub layer_id: String,
+    pub name: String,
+    pub layer_type: LayerType,
+    pub color: String,
+    pub ring_index: i32,
+    pub is_visible: bool,
+    pub requires_approval: bool,
+}
+
+/// Flattened activity type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityTypeDimension {
+    pub activity_type: String,
+    pub label: String,
+    pub color: String,
+    pub is_system: bool,
+    pub sort_order: i32,
+}
+
+/// Public share views on one day from one country
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareViewFact {
+    pub share_id: String,
+    pub share_name: Option<String>,
+    pub date: NaiveDate,
+    /// ISO 3166-1 alpha-2 code; None for views without a known country
+    pub country: Option<String>,
+    pub views: u64,
+}
+
+// ============================================
+// Export Models
+// ============================================
+
+/// Content of `/api/shares/{id}/export` and `/api/activities/export`, rendered per format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportDocument {
+    pub title: String,
+    /// Year shown, for formats that draw one (the wheel)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    pub generated_at: DateTime<Utc>,
+    pub layers: Vec<Layer>,
+    pub activities: Vec<Activity>,
+    /// Token of the next page, for paginated formats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation_token: Option<String>,
+    /// Locale and weeks of the year shown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<CalendarMetadata>,
+}
+
+/// Query parameters of export endpoints, shared by every format
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportQuery {
+    /// `en` (default) or `nb`
+    pub locale: Option<String>,
+    /// Comma-separated fields to leave out: `descriptions`, `links`, `people`
+    pub redact: Option<String>,
+    /// Activities per page of row formats (JSON, CSV, NDJSON)
+    pub page_size: Option<u32>,
+    pub continuation_token: Option<String>,
+}
+
+// ============================================
+// Diagnostics Models
+// ============================================
+
+/// Log level an organization can be switched to temporarily
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerboseLevel {
+    Debug,
+    Trace,
+}
+
+/// Temporary log level of one organization (see [`crate::log_overrides`])
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogOverride {
+    pub organization_id: String,
+    pub level: VerboseLevel,
+    /// Pseudonymized admin who set it
+    pub set_by: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request body for `PUT /api/admin/logging`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLogOverrideRequest {
+    pub level: VerboseLevel,
+    /// Minutes until the override expires (default 30, max 240)
+    #[serde(default)]
+    pub duration_minutes: Option<i64>,
+}
+
+/// Outcome of a storage probe, or of all of them (see [`crate::health`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    /// Answered, but slowly
+    Degraded,
+    /// Failed or timed out
+    Unhealthy,
+}
+
+/// Probe of one table or container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    pub target: String,
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    /// Kind of failure (`timeout`, `throttled`, `unauthorized`, `error`); details are logged only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response of `GET /api/health`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    /// Worst status of the checks
+    pub status: HealthStatus,
+    /// Storage backend probed; None when it has no probe (in memory)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    pub checked_at: DateTime<Utc>,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// Use of a deprecated route by one organization, on this instance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeprecatedRouteUse {
+    pub organization_id: String,
+    pub requests: u64,
+    pub first_used_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// A deprecated route and the organizations still calling it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeprecationReport {
+    pub method: String,
+    pub path: String,
+    pub deprecated_at: NaiveDate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<NaiveDate>,
+    /// Most recent use first
+    pub organizations: Vec<DeprecatedRouteUse>,
+}
+
+/// How close a partition is to its limits (see [`crate::partition_monitor`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionLevel {
+    Warning,
+    Critical,
+}
+
+/// Latest size and growth of one organization's partition in one table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionReport {
+    pub table: String,
+    pub organization_id: String,
+    pub entities: u64,
+    /// Approximate stored size
+    pub bytes: u64,
+    pub sampled_at: DateTime<Utc>,
+    /// Growth over the kept samples; None until there are two
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entities_per_day: Option<f64>,
+    /// Days until a limit is reached at that growth; None when not growing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days_to_limit: Option<f64>,
+    /// None while well below the limits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<PartitionLevel>,
+}
+
+/// Samples of one organization's partition in one table, oldest first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionHistory {
+    pub table: String,
+    pub organization_id: String,
+    pub samples: Vec<PartitionSize>,
+}
+
+/// Size of a partition when it was sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionSize {
+    pub at: DateTime<Utc>,
+    pub entities: u64,
+    /// Approximate stored size
+    pub bytes: u64,
+}
+
+// ============================================
+// Signing Key Models
+// ============================================
+
+/// Public half of a signing key (RFC 8037 Ed25519 JWK)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwk {
+    /// Always `OKP`
+    pub kty: String,
+    /// Always `Ed25519`
+    pub crv: String,
+    /// Public key, base64url
+    pub x: String,
+    pub kid: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    pub alg: String,
+}
+
+/// Body of `GET /.well-known/jwks.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+// ============================================
+// Error Types
+// ============================================
+
+/// API error response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn not_found(message: &str) -> Self {
+        Self {
+            code: "NOT_FOUND".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn unauthorized(message: &str) -> Self {
+        Self {
+            code: "UNAUTHORIZED".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn bad_request(message: &str) -> Self {
+        Self {
+            code: "BAD_REQUEST".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn internal(message: &str) -> Self {
+        Self {
+            code: "INTERNAL_ERROR".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn expired(message: &str) -> Self {
+        Self {
+            code: "EXPIRED".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn conflict(message: &str) -> Self {
+        Self {
+            code: "CONFLICT".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn forbidden(message: &str) -> Self {
+        Self {
+            code: "FORBIDDEN".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn service_unavailable(message: &str) -> Self {
+        Self {
+            code: "SERVICE_UNAVAILABLE".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn not_acceptable(message: &str) -> Self {
+        Self {
+            code: "NOT_ACCEPTABLE".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+    
+    pub fn too_many_requests(message: &str) -> Self {
+        Self {
+            code: "TOO_MANY_REQUESTS".to_string(),
+            message: message.to_string(),
+            details: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_share_link_serialization() {
+        let share = ShareLink {
+            id: "test-id".to_string(),
+            share_key: "a".repeat(64),
+            short_code: "AbCd1234".to_string(),
+            visibility: ShareVisibility::Public,
+            organization_id: "org-123".to_string(),
+            created_by: "user-123".to_string(),
+            created_at: Utc::now(),
+            expires_at: Utc::now() + chrono::Duration::days(365),
+            renewed_at: None,
+            name: Some("Test Share".to_string()),
+            description: None,
+            layer_config: ShareLayerConfig {
+                layer_ids: vec!["layer-1".to_string()],
+                layer_visibility: None,
+                year: Some(2025),
+            },
+            view_settings: ShareViewSettings::default(),
+            inherit_view_settings: false,
+            view_overrides: ShareViewSettingsPatch::default(),
+            stats: ShareStats::default(),
+            is_active: true,
+            ttl: None,
+            report_count: 0,
+            reporters: Vec::new(),
+            publish_snapshot: false,
+            indexable: None,
+            created_by_name: None,
+            review: None,
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            deleted_at: None,
+            partner_sources: Vec::new(),
+        };
+        
+        let json = serde_json::to_string_pretty(&share).unwrap();
+        println!("{}", json);
+        
+        let deserialized: ShareLink = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.id, share.id);
+        assert_eq!(deserialized.visibility, ShareVisibility::Public);
+    }
+    
+    #[test]
+    fn test_notification_preferences() {
+        let prefs: NotificationPreferences = serde_json::from_str("{}").unwrap();
+        assert_eq!(prefs, NotificationPreferences::default());
+        assert!(prefs.wants_digest(NotificationChannel::Teams));
+        assert!(!prefs.wants_digest(NotificationChannel::Email));
+        
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        assert!(prefs.reminder_due(today, chrono::NaiveDate::from_ymd_opt(2025, 3, 11).unwrap()));
+        assert!(!prefs.reminder_due(today, chrono::NaiveDate::from_ymd_opt(2025, 3, 12).unwrap()));
+        
+        // Quiet hours spanning midnight in UTC+1
+        let quiet = QuietHours {
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            utc_offset_minutes: 60,
+        };
+        let at = |h, m| chrono::NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()
+            .and_hms_opt(h, m, 0).unwrap().and_utc();
+        assert!(quiet.contains(at(21, 30)));
+        assert!(quiet.contains(at(5, 0)));
+        assert!(!quiet.contains(at(6, 0)));
+        assert!(!quiet.contains(at(12, 0)));
+    }
+    
+    #[test]
+    fn test_view_settings_patch() {
+        let settings = ShareViewSettings {
+            theme: ShareTheme::Dark,
+            custom_title: Some("Plan 2026".to_string()),
+            ..Default::default()
+        };
+        
+        // Omitted fields keep their value, explicit ones replace it, false included
+        let patch: ShareViewSettingsPatch = serde_json::from_str(r#"{"showLegend":false}"#).unwrap();
+        assert_eq!(patch, ShareViewSettingsPatch { show_legend: Some(false), ..Default::default() });
+        assert_eq!(patch.merge(&settings), ShareViewSettings { show_legend: false, ..settings.clone() });
+        
+        let empty: ShareViewSettingsPatch = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty.merge(&settings), settings);
+        assert_eq!(serde_json::to_string(&empty).unwrap(), "{}");
+        
+        let patch: ShareViewSettingsPatch = serde_json::from_str(r#"{"theme":"auto","customTitle":"","descriptionHtml":true}"#).unwrap();
+        let merged = patch.merge(&settings);
+        assert_eq!((merged.theme, merged.custom_title, merged.description_html), (ShareTheme::Auto, None, true));
+        assert_eq!(serde_json::to_value(&patch).unwrap(), serde_json::json!({ "theme": "auto", "customTitle": "", "descriptionHtml": true }));
+    }
+    
+    #[test]
+    fn test_dry_run_queries_agree() {
+        for query in [r#"{"dry_run":true}"#, r#"{"dryRun":true}"#] {
+            assert!(serde_json::from_str::<DryRunQuery>(query).unwrap().dry_run);
+            assert!(serde_json::from_str::<DeleteActivityTypeQuery>(query).unwrap().dry_run);
+        }
+        assert!(!serde_json::from_str::<DryRunQuery>("{}").unwrap().dry_run);
+        assert_eq!(
+            serde_json::to_value(DryRunQuery { dry_run: true }).unwrap(),
+            serde_json::to_value(DeleteActivityTypeQuery { dry_run: true, ..Default::default() }).unwrap(),
+        );
+    }
+    
+    #[test]
+    fn test_share_expiry() {
+        let mut share = ShareLink {
+            id: "test".to_string(),
+            share_key: "a".repeat(64),
+            short_code: "AbCd1234".to_string(),
+            visibility: ShareVisibility::Public,
+            organization_id: "org".to_string(),
+            created_by: "user".to_string(),
+            created_at: Utc::now(),
+            expires_at: Utc::now() - chrono::Duration::days(1), // Expired
+            renewed_at: None,
+            name: None,
+            description: None,
+            layer_config: ShareLayerConfig {
+                layer_ids: vec![],
+                layer_visibility: None,
+                year: None,
+            },
+            view_settings: ShareViewSettings::default(),
+            inherit_view_settings: false,
+            view_overrides: ShareViewSettingsPatch::default(),
+            stats: ShareStats::default(),
+            is_active: true,
+            ttl: None,
+            report_count: 0,
+            reporters: Vec::new(),
+            publish_snapshot: false,
+            indexable: None,
+            created_by_name: None,
+            review: None,
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            deleted_at: None,
+            partner_sources: Vec::new(),
+        };
+        
+        assert!(share.is_expired(Utc::now()));
+        
+        share.expires_at = Utc::now() + chrono::Duration::days(365);
+        assert!(!share.is_expired(Utc::now()));
+        assert!(!share.needs_renewal(Utc::now()));
+        
+        share.expires_at = Utc::now() + chrono::Duration::days(10);
+        assert!(share.needs_renewal(Utc::now()));
+        
+        // Deleted shares retire their short code past the original expiry
+        let now = Utc::now();
+        let tombstone = ShortCodeTombstone::for_deleted_share(&share, now).unwrap();
+        assert!(tombstone.is_active(now + Duration::days(SHORT_CODE_RETIREMENT_DAYS)));
+        assert!(!tombstone.is_active(now + Duration::days(SHORT_CODE_RETIREMENT_DAYS + 11)));
+        
+        share.expires_at = now - Duration::days(SHORT_CODE_RETIREMENT_DAYS + 1);
+        assert!(ShortCodeTombstone::for_deleted_share(&share, now).is_none());
+    }
+    
+    /// Serialize -> deserialize -> serialize must be stable for stored and API models
+    mod properties {
+        use super::*;
+        use proptest::collection::{hash_map, vec};
+        use proptest::prelude::*;
+        use serde::de::DeserializeOwned;
+        use std::collections::HashMap;
+        
+        fn assert_stable<T: Serialize + DeserializeOwned>(value: &T) -> Result<(), TestCaseError> {
+            let json = serde_json::to_string(value).unwrap();
+            let decoded: T = serde_json::from_str(&json)
+                .map_err(|e| TestCaseError::fail(format!("{}: {}", e, json)))?;
+            // Compared as values: map field order is not stable
+            prop_assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::from_str::<serde_json::Value>(&json).unwrap());
+            Ok(())
+        }
+        
+        fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+            // 1970..2100, sub-second precision included
+            (0i64..4_102_444_800, 0u32..1_000_000_000)
+                .prop_map(|(secs, nanos)| DateTime::from_timestamp(secs, nanos).unwrap())
+        }
+        
+        fn date() -> impl Strategy<Value = NaiveDate> {
+            timestamp().prop_map(|t| t.date_naive())
+        }
+        
+        fn text() -> impl Strategy<Value = String> {
+            any::<String>()
+        }
+        
+        fn visibility_map() -> impl Strategy<Value = Option<HashMap<String, bool>>> {
+            proptest::option::of(hash_map(text(), any::<bool>(), 0..4))
+        }
+        
+        fn anomaly() -> impl Strategy<Value = TrafficAnomaly> {
+            prop_oneof![
+                (any::<u64>(), 0.0f64..1e9).prop_map(|(views, baseline)| TrafficAnomaly::ViewSpike { views, baseline }),
+                ("[A-Z]{2}", any::<u64>()).prop_map(|(country, views)| TrafficAnomaly::UnusualCountry { country, views }),
+            ]
+        }
+        
+        prop_compose! {
+            fn share_settings()(
+                layer_ids in vec(text(), 0..4),
+                layer_visibility in visibility_map(),
+                year in proptest::option::of(any::<i32>()),
+                theme in prop_oneof![Just(ShareTheme::Light), Just(ShareTheme::Dark), Just(ShareTheme::Auto)],
+                flags in any::<[bool; 5]>(),
+                custom_title in proptest::option::of(text()),
+            ) -> (ShareLayerConfig, ShareViewSettings) {
+                (
+                    ShareLayerConfig { layer_ids, layer_visibility, year },
+                    ShareViewSettings {
+                        theme,
+                        show_legend: flags[0],
+                        show_title: flags[1],
+                        custom_title,
+                        allow_interaction: flags[2],
+                        rotate_to_current_month: flags[3],
+                        description_html: flags[4],
+                    },
+                )
+            }
+        }
+        
+        prop_compose! {
+            fn share_state()(
+                view_count in any::<u64>(),
+                last_accessed_at in proptest::option::of(timestamp()),
+                unique_visitors in proptest::option::of(any::<u64>()),
+                is_active in any::<bool>(),
+                ttl in proptest::option::of(any::<i64>()),
+                report_count in any::<u32>(),
+                publish_snapshot in any::<bool>(),
+                review in proptest::option::of((vec(anomaly(), 0..3), timestamp())),
+            ) -> (ShareStats, bool, Option<i64>, u32, bool, Option<ShareReview>) {
+                (
+                    ShareStats { view_count, last_accessed_at, unique_visitors },
+                    is_active,
+                    ttl,
+                    report_count,
+                    publish_snapshot,
+                    review.map(|(anomalies, flagged_at)| ShareReview { anomalies, flagged_at }),
+                )
+            }
+        }
+        
+        prop_compose! {
+            fn share_link()(
+                ids in any::<[String; 5]>(),
+                public in any::<bool>(),
+                times in (timestamp(), timestamp(), proptest::option::of(timestamp()), proptest::option::of(timestamp())),
+                texts in any::<[Option<String>; 3]>(),
+                (layer_config, view_settings) in share_settings(),
+                (inherit_view_settings, overrides) in (any::<bool>(), any::<[Option<bool>; 2]>()),
+                (stats, is_active, ttl, report_count, publish_snapshot, review) in share_state(),
+                scope in (vec(text(), 0..3), vec(text(), 0..3)),
+                partners in vec((text(), text(), vec(text(), 0..3)), 0..2),
+            ) -> ShareLink {
+                let [id, share_key, short_code, organization_id, created_by] = ids;
+                let [name, description, created_by_name] = texts;
+                ShareLink {
+                    id,
+                    share_key,
+                    short_code,
+                    visibility: if public { ShareVisibility::Public } else { ShareVisibility::Users },
+                    organization_id,
+                    created_by,
+                    created_at: times.0,
+                    expires_at: times.1,
+                    renewed_at: times.2,
+                    name,
+                    description,
+                    layer_config,
+                    view_settings,
+                    inherit_view_settings,
+                    view_overrides: ShareViewSettingsPatch { show_legend: overrides[0], description_html: overrides[1], ..Default::default() },
+                    stats,
+                    is_active,
+                    ttl,
+                    report_count,
+                    reporters: Vec::new(),
+                    publish_snapshot,
+                    created_by_name,
+                    review,
+                    indexable: None,
+                    team_ids: scope.0,
+                    channel_ids: scope.1,
+                    deleted_at: times.3,
+                    partner_sources: partners.into_iter()
+                        .map(|(organization_id, grant_id, layer_ids)| PartnerSource { organization_id, grant_id, layer_ids })
+                        .collect(),
+                }
+            }
+        }
+        
+        prop_compose! {
+            fn activity()(
+                ids in any::<[String; 7]>(),
+                times in (timestamp(), timestamp(), proptest::option::of(timestamp()), proptest::option::of(timestamp())),
+                activity_type in prop_oneof![
+                    Just(ActivityType::Meeting), Just(ActivityType::Deadline), Just(ActivityType::Event),
+                    Just(ActivityType::Planning), Just(ActivityType::Review), Just(ActivityType::Training),
+                    Just(ActivityType::Holiday), Just(ActivityType::Other),
+                ],
+                texts in any::<[Option<String>; 3]>(),
+                markdown in any::<bool>(),
+                links in vec((text(), text()).prop_map(|(title, url)| ActivityLink { title, url }), 0..3),
+                tags in vec(text(), 0..3),
+                approval_status in prop_oneof![
+                    Just(ApprovalStatus::Approved), Just(ApprovalStatus::PendingApproval), Just(ApprovalStatus::Rejected),
+                ],
+                review in proptest::option::of((text(), timestamp(), proptest::option::of(text()))),
+                edit_count in any::<u32>(),
+                priority in any::<i32>(),
+                lineage in (proptest::option::of(text()), vec(text(), 0..3), proptest::option::of(timestamp())),
+            ) -> Activity {
+                let [id, title, color, highlight_color, scope, scope_id, organization_id] = ids;
+                let [description, created_by, created_by_name] = texts;
+                Activity {
+                    id,
+                    title,
+                    start_date: times.0,
+                    end_date: times.1,
+                    activity_type,
+                    color,
+                    highlight_color,
+                    description,
+                    description_format: if markdown { DescriptionFormat::Markdown } else { DescriptionFormat::Plain },
+                    links,
+                    tags,
+                    priority,
+                    scope,
+                    scope_id,
+                    organization_id,
+                    created_by,
+                    created_at: times.2,
+                    updated_at: times.3,
+                    edit_count,
+                    approval_status,
+                    approval_review: review.map(|(reviewed_by, reviewed_at, comment)| ApprovalReview { reviewed_by, reviewed_at, comment }),
+                    split_from: lineage.0,
+                    merged_from: lineage.1,
+                    deleted_at: lineage.2,
+                    recurrence: None,
+                    created_by_name,
+                }
+            }
+        }
+        
+        prop_compose! {
+            fn layer()(
+                ids in any::<[String; 5]>(),
+                description in proptest::option::of(text()),
+                layer_type in prop_oneof![Just(LayerType::Holidays), Just(LayerType::Organization), Just(LayerType::Custom)],
+                ring_index in any::<i32>(),
+                flags in any::<[bool; 2]>(),
+                visible_to_groups in vec(text(), 0..3),
+                scope in (vec(text(), 0..3), vec(text(), 0..3)),
+                created_at in timestamp(),
+                updated_at in proptest::option::of(timestamp()),
+            ) -> Layer {
+                let [id, name, color, organization_id, created_by] = ids;
+                Layer {
+                    id,
+                    name,
+                    description,
+                    layer_type,
+                    color,
+                    ring_index,
+                    is_visible: flags[0],
+                    requires_approval: flags[1],
+                    visible_to_groups,
+                    team_ids: scope.0,
+                    channel_ids: scope.1,
+                    organization_id,
+                    created_by,
+                    created_at,
+                    updated_at,
+                }
+            }
+        }
+        
+        prop_compose! {
+            fn user_settings()(
+                ids in any::<[String; 2]>(),
+                layer_order in proptest::option::of(vec(text(), 0..4)),
+                layer_visibility in visibility_map(),
+                theme in prop_oneof![Just(UserTheme::Light), Just(UserTheme::Dark), Just(UserTheme::System)],
+                digest_enabled in any::<bool>(),
+                digest_frequency in prop_oneof![Just(DigestFrequency::Daily), Just(DigestFrequency::Weekly), Just(DigestFrequency::Monthly)],
+                reminder_lead_days in vec(any::<u32>(), 0..4),
+                channels in vec(prop_oneof![Just(NotificationChannel::Email), Just(NotificationChannel::Teams)], 0..3),
+                quiet_hours in proptest::option::of((timestamp(), timestamp(), -840i32..=840)),
+                locale in proptest::option::of(text()),
+                updated_at in timestamp(),
+            ) -> UserSettings {
+                let [user_id, organization_id] = ids;
+                UserSettings {
+                    user_id,
+                    organization_id,
+                    layer_order,
+                    layer_visibility,
+                    theme,
+                    notifications: NotificationPreferences {
+                        digest_enabled,
+                        digest_frequency,
+                        reminder_lead_days,
+                        channels,
+                        quiet_hours: quiet_hours.map(|(start, end, utc_offset_minutes)| QuietHours {
+                            start: start.time(),
+                            end: end.time(),
+                            utc_offset_minutes,
+                        }),
+                    },
+                    locale,
+                    updated_at,
+                }
+            }
+        }
+        
+        prop_compose! {
+            fn audit_entry()(
+                ids in any::<[String; 3]>(),
+                actor_id in proptest::option::of(text()),
+                target_id in proptest::option::of(text()),
+                occurred_at in timestamp(),
+                details in proptest::option::of(hash_map(text(), text(), 0..4)),
+            ) -> AuditEntry {
+                let [id, organization_id, action] = ids;
+                AuditEntry {
+                    id,
+                    organization_id,
+                    action,
+                    actor_id,
+                    target_id,
+                    occurred_at,
+                    details: details.map(|d| serde_json::json!(d)),
+                }
+            }
+        }
+        
+        proptest! {
+            #[test]
+            fn share_link_is_stable(share in share_link()) {
+                assert_stable(&share)?;
+            }
+            
+            #[test]
+            fn activity_is_stable(activity in activity()) {
+                assert_stable(&activity)?;
+            }
+            
+            #[test]
+            fn layer_is_stable(layer in layer()) {
+                assert_stable(&layer)?;
+            }
+            
+            #[test]
+            fn user_settings_are_stable(settings in user_settings()) {
+                assert_stable(&settings)?;
+            }
+            
+            #[test]
+            fn audit_entry_is_stable(entry in audit_entry()) {
+                assert_stable(&entry)?;
+            }
+            
+            #[test]
+            fn small_models_are_stable(
+                ids in any::<[String; 4]>(),
+                times in (timestamp(), timestamp()),
+                day in date(),
+                countries in hash_map("[A-Z]{2}", any::<u64>(), 0..4),
+                anomalies in vec(anomaly(), 0..3),
+                locked_by_name in proptest::option::of(text()),
+            ) {
+                let [a, b, c, d] = ids;
+                assert_stable(&EditLock {
+                    activity_id: a.clone(),
+                    organization_id: b.clone(),
+                    locked_by: c.clone(),
+                    locked_by_name,
+                    acquired_at: times.0,
+                    expires_at: times.1,
+                })?;
+                assert_stable(&ShortCodeTombstone { short_code: d.clone(), retired_at: times.0, retired_until: times.1 })?;
+                assert_stable(&DailyShareTraffic {
+                    organization_id: a.clone(),
+                    share_id: b.clone(),
+                    date: day,
+                    views: countries.values().fold(0u64, |sum, v| sum.saturating_add(*v)),
+                    countries,
+                })?;
+                assert_stable(&ShareTrafficAlert {
+                    share_id: a,
+                    organization_id: b,
+                    short_code: d,
+                    date: day,
+                    anomalies,
+                    detected_at: times.0,
+                })?;
+            }
+        }
+    }
+}
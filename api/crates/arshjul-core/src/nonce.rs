@@ -0,0 +1,236 @@
+//! # Request Nonces
+//!
+//! Public POST endpoints carry no user token, so a captured request could be
+//! replayed by a script. They require a server-issued nonce instead:
+//!
+//! 1. `POST /api/public/s/{shortCode}/nonce?k={key}` issues one for the
+//!    share, valid for [`NONCE_TTL_SECONDS`]
+//! 2. The public POST sends it back in the [`NONCE_HEADER`] header
+//! 3. [`redeem`] accepts it once, for the share it was issued for
+//!
+//! Issuing is rate limited like other public share endpoints, which also
+//! bounds how fast a script can post. Pending nonces live in a [`NonceStore`]:
+//!
+//! - [`InProcessNonceStore`] - per instance, bounded; enough for one instance.
+//!   Each share holds at most [`MAX_PENDING_NONCES_PER_SCOPE`] and issuing
+//!   more drops its oldest, so a client issuing in a loop only uses up its
+//!   own share's nonces; when the store is full, the nonces closest to
+//!   expiry go
+//! - `RedisNonceStore` - shared by all instances (`arshjul-azure`, `redis` feature)
+//!
+//! Store failures reject the request rather than skip the check.
+
+use crate::clock::{Clock, SystemClock};
+use crate::crypto::generate_share_key;
+use crate::storage::StorageError;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
+
+/// How long an issued nonce can be redeemed
+pub const NONCE_TTL_SECONDS: i64 = 300;
+
+/// Request header carrying the nonce
+pub const NONCE_HEADER: &str = "X-Request-Nonce";
+
+/// Nonces pending in an in-process store
+pub const MAX_PENDING_NONCES: usize = 100_000;
+
+/// Nonces pending for one scope (share) in an in-process store
+pub const MAX_PENDING_NONCES_PER_SCOPE: usize = 1_000;
+
+/// Nonce check failures
+#[derive(Debug, Error)]
+pub enum NonceError {
+    #[error("Missing request nonce")]
+    Missing,
+    
+    #[error("Invalid, expired or already used request nonce")]
+    Invalid,
+    
+    #[error("Nonce store unavailable: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// A nonce handed to a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestNonce {
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Pending nonces and the scope (short code) each was issued for
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Keep `nonce` for `scope` until `ttl` has passed
+    async fn put(&self, nonce: &str, scope: &str, ttl: Duration) -> Result<(), StorageError>;
+    
+    /// Remove a pending nonce, returning its scope; None when unknown or expired
+    async fn take(&self, nonce: &str) -> Result<Option<String>, StorageError>;
+}
+
+/// Issue a nonce for `scope`
+pub async fn issue(store: &dyn NonceStore, scope: &str, now: DateTime<Utc>) -> Result<RequestNonce, StorageError> {
+    let ttl = Duration::seconds(NONCE_TTL_SECONDS);
+    let nonce = generate_share_key();
+    store.put(&nonce, scope, ttl).await?;
+    Ok(RequestNonce { nonce, expires_at: now + ttl })
+}
+
+/// Accept `nonce` once, if it was issued for `scope`
+pub async fn redeem(store: &dyn NonceStore, scope: &str, nonce: Option<&str>) -> Result<(), NonceError> {
+    let nonce = nonce.ok_or(NonceError::Missing)?;
+    if nonce.len() != 64 || !nonce.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(NonceError::Invalid);
+    }
+    match store.take(nonce).await? {
+        Some(issued_for) if issued_for == scope => Ok(()),
+        _ => Err(NonceError::Invalid),
+    }
+}
+
+/// Pending nonces with their scope and expiry
+#[derive(Default)]
+struct Pending {
+    nonces: HashMap<String, (String, DateTime<Utc>)>,
+    /// Nonces of each scope, oldest first
+    by_scope: HashMap<String, VecDeque<String>>,
+}
+
+impl Pending {
+    fn remove(&mut self, nonce: &str) -> Option<(String, DateTime<Utc>)> {
+        let (scope, expires) = self.nonces.remove(nonce)?;
+        if let Some(queue) = self.by_scope.get_mut(&scope) {
+            queue.retain(|n| n != nonce);
+            if queue.is_empty() {
+                self.by_scope.remove(&scope);
+            }
+        }
+        Some((scope, expires))
+    }
+    
+    /// Make room for one more nonce of `scope`
+    fn make_room(&mut self, scope: &str, now: DateTime<Utc>) {
+        let oldest = self.by_scope.get(scope)
+            .filter(|queue| queue.len() >= MAX_PENDING_NONCES_PER_SCOPE)
+            .and_then(|queue| queue.front().cloned());
+        if let Some(nonce) = oldest {
+            self.remove(&nonce);
+        }
+        if self.nonces.len() < MAX_PENDING_NONCES {
+            return;
+        }
+        let expired: Vec<String> = self.nonces.iter()
+            .filter(|(_, (_, expires))| *expires <= now)
+            .map(|(nonce, _)| nonce.clone())
+            .collect();
+        for nonce in expired {
+            self.remove(&nonce);
+        }
+        if self.nonces.len() >= MAX_PENDING_NONCES {
+            let closest = self.nonces.iter().min_by_key(|(_, (_, expires))| *expires).map(|(nonce, _)| nonce.clone());
+            if let Some(nonce) = closest {
+                self.remove(&nonce);
+            }
+        }
+    }
+}
+
+/// Per-instance nonce store
+pub struct InProcessNonceStore {
+    pending: Mutex<Pending>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for InProcessNonceStore {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl InProcessNonceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Expire nonces by another time source (tests)
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self { pending: Mutex::new(Pending::default()), clock }
+    }
+    
+    fn lock(&self) -> Result<MutexGuard<'_, Pending>, StorageError> {
+        self.pending.lock().map_err(|e| StorageError::Storage(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl NonceStore for InProcessNonceStore {
+    fn name(&self) -> &'static str {
+        "in-process"
+    }
+    
+    async fn put(&self, nonce: &str, scope: &str, ttl: Duration) -> Result<(), StorageError> {
+        let now = self.clock.now();
+        let mut pending = self.lock()?;
+        pending.make_room(scope, now);
+        pending.nonces.insert(nonce.to_string(), (scope.to_string(), now + ttl));
+        pending.by_scope.entry(scope.to_string()).or_default().push_back(nonce.to_string());
+        Ok(())
+    }
+    
+    async fn take(&self, nonce: &str) -> Result<Option<String>, StorageError> {
+        let now = self.clock.now();
+        Ok(self.lock()?.remove(nonce)
+            .filter(|(_, expires)| *expires > now)
+            .map(|(scope, _)| scope))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    
+    #[tokio::test]
+    async fn test_nonce_is_single_use() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let store = InProcessNonceStore::with_clock(clock.clone());
+        let issued = issue(&store, "Code0001", clock.now()).await.unwrap();
+        
+        assert!(matches!(redeem(&store, "Code0001", None).await, Err(NonceError::Missing)));
+        assert!(matches!(redeem(&store, "Code0002", Some(&issued.nonce)).await, Err(NonceError::Invalid)));
+        // Presented for the wrong share, it is used up all the same
+        assert!(matches!(redeem(&store, "Code0001", Some(&issued.nonce)).await, Err(NonceError::Invalid)));
+        
+        let issued = issue(&store, "Code0001", clock.now()).await.unwrap();
+        redeem(&store, "Code0001", Some(&issued.nonce)).await.unwrap();
+        assert!(matches!(redeem(&store, "Code0001", Some(&issued.nonce)).await, Err(NonceError::Invalid)));
+        
+        let issued = issue(&store, "Code0001", clock.now()).await.unwrap();
+        clock.advance(Duration::seconds(NONCE_TTL_SECONDS + 1));
+        assert!(matches!(redeem(&store, "Code0001", Some(&issued.nonce)).await, Err(NonceError::Invalid)));
+    }
+    
+    #[tokio::test]
+    async fn test_issuing_in_a_loop_only_uses_up_its_own_scope() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let store = InProcessNonceStore::with_clock(clock.clone());
+        let other = issue(&store, "Code0002", clock.now()).await.unwrap();
+        let first = issue(&store, "Code0001", clock.now()).await.unwrap();
+        for _ in 0..MAX_PENDING_NONCES_PER_SCOPE {
+            issue(&store, "Code0001", clock.now()).await.unwrap();
+        }
+        
+        assert_eq!(store.lock().unwrap().nonces.len(), MAX_PENDING_NONCES_PER_SCOPE + 1);
+        assert!(matches!(redeem(&store, "Code0001", Some(&first.nonce)).await, Err(NonceError::Invalid)));
+        redeem(&store, "Code0002", Some(&other.nonce)).await.unwrap();
+        assert!(!store.lock().unwrap().by_scope.contains_key("Code0002"));
+    }
+}
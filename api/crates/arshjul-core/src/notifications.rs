@@ -0,0 +1,315 @@
+//! # Digest and Reminder Emails
+//!
+//! The daily notification run of an organization ([`NotificationJobs::run_once`])
+//! sends each user, in their language ([`crate::i18n::resolve`]):
+//!
+//! - **Digest** - upcoming activities, rendered by [`i18n::digest_email`];
+//!   daily ones every day, weekly ones on Mondays (the next 7 days) and
+//!   monthly ones on the 1st (the next 31 days)
+//! - **Activity reminders** - [`i18n::activity_reminder`] for activities
+//!   starting one of the user's `reminderLeadDays` from today
+//! - **Share expiry reminders** - [`ShareExpiryReminders`] with renewal
+//!   links, when configured ([`NotificationJobs::with_share_reminders`])
+//!
+//! Only users with settings, the email channel enabled and outside their
+//! quiet hours at run time get mail. Recurring activities count once per
+//! occurrence. Activities on restricted layers ([`crate::layer_access`])
+//! reach only the groups' members, checked against the directory; without
+//! one they are left out.
+//!
+//! Each pass sends everything due that day, so it must run once a day on a
+//! single instance. Delivery goes through a [`Mailer`]; one undeliverable
+//! email does not hold back the rest.
+
+use crate::clock::Clock;
+use crate::directory::{DirectoryError, DirectoryService};
+use crate::export::ExportLocale;
+use crate::i18n::{self, LocalizedEmail};
+use crate::models::{Activity, CalendarSettings, DigestFrequency, Layer, NotificationChannel, ShareRenewalReminder, UserSettings};
+use crate::recurrence;
+use crate::share_renewal::{ReminderError, ReminderMailer, ShareExpiryReminders};
+use crate::storage::{list_all_activities, ActivityStorage, LayerStorage, PolicyStorage, StorageError, UserSettingsStorage};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// An email to one user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailMessage {
+    pub organization_id: String,
+    /// Recipient's user ID; the mailer resolves the address
+    pub recipient_id: String,
+    pub locale: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl EmailMessage {
+    fn new(organization_id: &str, recipient_id: &str, locale: ExportLocale, email: LocalizedEmail) -> Self {
+        Self {
+            organization_id: organization_id.to_string(),
+            recipient_id: recipient_id.to_string(),
+            locale: locale.tag().to_string(),
+            subject: email.subject,
+            body: email.body,
+        }
+    }
+}
+
+/// Delivers rendered emails (e.g. through a mail webhook)
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<(), ReminderError>;
+}
+
+/// Renders share expiry reminders in their locale and hands them to a [`Mailer`]
+pub struct LocalizedReminderMailer {
+    mailer: Arc<dyn Mailer>,
+}
+
+impl LocalizedReminderMailer {
+    pub fn new(mailer: Arc<dyn Mailer>) -> Self {
+        Self { mailer }
+    }
+}
+
+#[async_trait]
+impl ReminderMailer for LocalizedReminderMailer {
+    async fn send(&self, reminder: &ShareRenewalReminder) -> Result<(), ReminderError> {
+        let locale = ExportLocale::parse(&reminder.locale).unwrap_or_default();
+        let email = i18n::share_expiry_reminder(locale, reminder);
+        self.mailer.send(&EmailMessage::new(&reminder.organization_id, &reminder.owner_id, locale, email)).await
+    }
+}
+
+/// Days of upcoming activities in a digest sent today, if one is due
+pub fn digest_window(frequency: DigestFrequency, today: NaiveDate) -> Option<i64> {
+    match frequency {
+        DigestFrequency::Daily => Some(1),
+        DigestFrequency::Weekly => (today.weekday() == Weekday::Mon).then_some(7),
+        DigestFrequency::Monthly => (today.day() == 1).then_some(31),
+    }
+}
+
+/// Daily digest and reminder job
+pub struct NotificationJobs {
+    activities: Arc<dyn ActivityStorage>,
+    layers: Arc<dyn LayerStorage>,
+    user_settings: Arc<dyn UserSettingsStorage>,
+    policies: Arc<dyn PolicyStorage>,
+    mailer: Arc<dyn Mailer>,
+    clock: Arc<dyn Clock>,
+    directory: Option<Arc<dyn DirectoryService>>,
+    share_reminders: Option<ShareExpiryReminders>,
+}
+
+impl NotificationJobs {
+    pub fn new(
+        activities: Arc<dyn ActivityStorage>,
+        layers: Arc<dyn LayerStorage>,
+        user_settings: Arc<dyn UserSettingsStorage>,
+        policies: Arc<dyn PolicyStorage>,
+        mailer: Arc<dyn Mailer>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self { activities, layers, user_settings, policies, mailer, clock, directory: None, share_reminders: None }
+    }
+    
+    /// Check restricted layers' groups against the directory
+    pub fn with_directory(mut self, directory: Arc<dyn DirectoryService>) -> Self {
+        self.directory = Some(directory);
+        self
+    }
+    
+    /// Also send share expiry reminders
+    pub fn with_share_reminders(mut self, reminders: ShareExpiryReminders) -> Self {
+        self.share_reminders = Some(reminders);
+        self
+    }
+    
+    /// Send the organization's emails due today; returns the digests and activity reminders delivered
+    pub async fn run_once(&self, organization_id: &str) -> Result<Vec<EmailMessage>, StorageError> {
+        let now = self.clock.now();
+        let calendar = self.policies.get(organization_id).await?.calendar.unwrap_or_default();
+        let mut sent = Vec::new();
+        
+        let users: Vec<UserSettings> = self.user_settings.list(organization_id).await?.into_iter()
+            .filter(|u| u.notifications.allows_channel(NotificationChannel::Email) && u.notifications.can_deliver_at(now))
+            .collect();
+        if !users.is_empty() {
+            let layers = self.layers.list(organization_id).await?;
+            let activities: Vec<Activity> = recurrence::expand(list_all_activities(self.activities.as_ref(), organization_id).await?, None)
+                .into_iter()
+                .filter(|a| a.deleted_at.is_none() && a.end_date >= now)
+                .collect();
+            
+            for user in &users {
+                let visible = match self.visible_layer_ids(organization_id, user, &layers).await {
+                    Ok(visible) => visible,
+                    Err(e) => {
+                        tracing::warn!(user_id = %user.user_id, error = %e, "Skipping notifications: layer access check failed");
+                        continue;
+                    }
+                };
+                let upcoming: Vec<&Activity> = activities.iter().filter(|a| visible.contains(&a.scope)).collect();
+                for message in self.render(organization_id, user, &calendar, &upcoming, now) {
+                    match self.mailer.send(&message).await {
+                        Ok(()) => sent.push(message),
+                        Err(e) => tracing::warn!(user_id = %user.user_id, error = %e, "Failed to send notification"),
+                    }
+                }
+            }
+        }
+        
+        if let Some(ref reminders) = self.share_reminders {
+            reminders.run(organization_id, now).await?;
+        }
+        Ok(sent)
+    }
+    
+    /// Run for the organizations every `interval` until the task is dropped
+    pub async fn run(self: Arc<Self>, organization_ids: Vec<String>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for organization_id in &organization_ids {
+                if let Err(e) = self.run_once(organization_id).await {
+                    tracing::warn!(organization_id = %organization_id, error = %e, "Notification run failed");
+                }
+            }
+        }
+    }
+    
+    /// The user's digest and reminders due at `now`
+    fn render(
+        &self,
+        organization_id: &str,
+        user: &UserSettings,
+        calendar: &CalendarSettings,
+        upcoming: &[&Activity],
+        now: DateTime<Utc>,
+    ) -> Vec<EmailMessage> {
+        let locale = i18n::resolve(Some(user), calendar);
+        let today = now.date_naive();
+        let mut messages = Vec::new();
+        
+        let preferences = &user.notifications;
+        if let Some(days) = digest_window(preferences.digest_frequency, today).filter(|_| preferences.wants_digest(NotificationChannel::Email)) {
+            let until = now + Duration::days(days);
+            let mut digest: Vec<Activity> = upcoming.iter()
+                .filter(|a| a.start_date < until)
+                .map(|a| (*a).clone())
+                .collect();
+            digest.sort_by_key(|a| a.start_date);
+            let email = i18n::digest_email(locale, preferences.digest_frequency, &digest);
+            messages.push(EmailMessage::new(organization_id, &user.user_id, locale, email));
+        }
+        
+        for activity in upcoming {
+            let start = activity.start_date.date_naive();
+            if preferences.reminder_due(today, start) {
+                let email = i18n::activity_reminder(locale, activity, (start - today).num_days());
+                messages.push(EmailMessage::new(organization_id, &user.user_id, locale, email));
+            }
+        }
+        messages
+    }
+    
+    async fn visible_layer_ids(
+        &self,
+        organization_id: &str,
+        user: &UserSettings,
+        layers: &[Layer],
+    ) -> Result<HashSet<String>, DirectoryError> {
+        let mut required: Vec<String> = layers.iter().flat_map(|l| l.visible_to_groups.iter().cloned()).collect();
+        required.sort();
+        required.dedup();
+        let member_of = match &self.directory {
+            Some(directory) if !required.is_empty() => directory.member_groups(organization_id, &user.user_id, &required).await?,
+            _ => HashSet::new(),
+        };
+        Ok(layers.iter()
+            .filter(|l| l.visible_to_groups.is_empty() || l.visible_to_groups.iter().any(|g| member_of.contains(g)))
+            .map(|l| l.id.clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::models::OrganizationPolicy;
+    use crate::storage::memory_storage::{MemoryActivityStorage, MemoryLayerStorage, MemoryPolicyStorage, MemoryUserSettingsStorage};
+    use std::sync::Mutex;
+    
+    #[derive(Default)]
+    struct RecordingMailer {
+        sent: Mutex<Vec<EmailMessage>>,
+    }
+    
+    #[async_trait]
+    impl Mailer for RecordingMailer {
+        async fn send(&self, message: &EmailMessage) -> Result<(), ReminderError> {
+            self.sent.lock().unwrap().push(message.clone());
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_user_locale_overrides_organization() {
+        // Monday
+        let now = "2025-03-03T07:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let activities = Arc::new(MemoryActivityStorage::new());
+        let layers = Arc::new(MemoryLayerStorage::new());
+        let user_settings = Arc::new(MemoryUserSettingsStorage::new());
+        let policies = Arc::new(MemoryPolicyStorage::new());
+        let mailer = Arc::new(RecordingMailer::default());
+        
+        let layer: Layer = serde_json::from_value(serde_json::json!({
+            "id": "layer-1", "name": "Layer", "type": "organization", "color": "#000000", "ringIndex": 0,
+            "organizationId": "org-1", "createdBy": "admin", "createdAt": "2025-01-01T00:00:00Z",
+        })).unwrap();
+        layers.create(layer).await.unwrap();
+        activities.create(serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Budsjett", "startDate": "2025-03-04T00:00:00Z",
+            "endDate": "2025-03-05T00:00:00Z", "type": "deadline", "color": "#000000",
+            "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+        })).unwrap()).await.unwrap();
+        
+        let mut policy = OrganizationPolicy::new("org-1");
+        policy.calendar = Some(CalendarSettings { locale: "en".to_string(), ..Default::default() });
+        policies.upsert(policy).await.unwrap();
+        
+        let mut norwegian = UserSettings::new("user-nb".to_string(), "org-1".to_string());
+        norwegian.locale = Some("nb".to_string());
+        norwegian.notifications.channels = vec![NotificationChannel::Email];
+        user_settings.upsert(norwegian).await.unwrap();
+        let mut english = UserSettings::new("user-en".to_string(), "org-1".to_string());
+        english.notifications.channels = vec![NotificationChannel::Email];
+        user_settings.upsert(english).await.unwrap();
+        
+        let jobs = NotificationJobs::new(activities, layers, user_settings, policies, mailer.clone(), Arc::new(ManualClock::new(now)));
+        let sent = jobs.run_once("org-1").await.unwrap();
+        assert_eq!(sent.len(), 4);
+        assert_eq!(*mailer.sent.lock().unwrap(), sent);
+        
+        let subjects = |user: &str| sent.iter().filter(|m| m.recipient_id == user).map(|m| m.subject.as_str()).collect::<Vec<_>>();
+        assert_eq!(subjects("user-nb"), ["Din ukentlige oversikt fra årshjulet", "Påminnelse: Budsjett starter i morgen"]);
+        assert_eq!(subjects("user-en"), ["Your weekly Annual Wheel digest", "Reminder: Budsjett starts tomorrow"]);
+        assert!(sent.iter().filter(|m| m.recipient_id == "user-nb").all(|m| m.locale == "nb"));
+    }
+    
+    #[test]
+    fn test_digest_window() {
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        assert_eq!(digest_window(DigestFrequency::Weekly, monday), Some(7));
+        assert_eq!(digest_window(DigestFrequency::Weekly, monday + Duration::days(1)), None);
+        assert_eq!(digest_window(DigestFrequency::Monthly, monday), None);
+        assert_eq!(digest_window(DigestFrequency::Monthly, NaiveDate::from_ymd_opt(2025, 4, 1).unwrap()), Some(31));
+        assert_eq!(digest_window(DigestFrequency::Daily, monday), Some(1));
+    }
+}
@@ -0,0 +1,320 @@
+//! # Organization Off-boarding
+//!
+//! Support for `DELETE /api/admin/organization`, required when a customer
+//! uninstalls the Teams app.
+//!
+//! Purging is a two-step operation so it can't be triggered by accident:
+//! 1. `POST /api/admin/organization/purge-confirmation` returns a short-lived
+//!    confirmation token bound to the organization, the requesting admin and
+//!    the plan it previewed, together with the number of entities that will
+//!    be deleted.
+//! 2. `DELETE /api/admin/organization` with that token revokes every share,
+//!    deletes all tenant data and records a [`DeletionCertificate`].
+//!
+//! Tokens are signed with the [`KeyRing`] (purpose `purge-confirmation`), so
+//! a token can't be minted without the server and the preview can't be
+//! skipped. They carry a digest of the plan ([`plan_digest`]); when shares,
+//! activities or other entities were added or removed since the preview,
+//! the purge is refused and the admin confirms again.
+//!
+//! `DELETE /api/admin/organization?dry_run=true` lists every share, activity
+//! and other entity the purge would touch (see [`crate::dry_run`]).
+//!
+//! The purge in the request and the background one (see
+//! [`crate::operations`]) carry out the plan through the same [`Purger`].
+//! It publishes a change event for every share, activity, layer and
+//! activity type it revokes or deletes, so live clients, share caches,
+//! published snapshots and the search index let go of them as they would
+//! after a delete.
+//!
+//! [`DeletionCertificate`]: crate::models::DeletionCertificate
+
+use crate::dry_run::ExecutionPlan;
+use crate::events::{ChangeKind, DomainEvent, EntityChange, EntityKind, EventBus};
+use crate::models::{DeletionCertificate, PlannedAction, PlannedEntity};
+use crate::org_snapshots::OrganizationSnapshotStore;
+use crate::signing_keys::{KeyRing, SigningKeyError};
+use crate::storage::{
+    ActivityStorage, ActivityTypeStorage, AuditStorage, LayerStorage, PolicyStorage, ShareStorage, StorageError,
+    UserSettingsStorage,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// [`KeyRing`] purpose of confirmation tokens
+const SIGNING_PURPOSE: &str = "purge-confirmation";
+
+/// How long a confirmation token stays valid
+pub const CONFIRMATION_TTL_MINUTES: i64 = 10;
+
+/// Audit action recorded when an organization is purged
+pub const AUDIT_ACTION_PURGED: &str = "organization.purged";
+
+/// Confirmation errors
+#[derive(Debug, Error, PartialEq)]
+pub enum ConfirmationError {
+    #[error("Invalid confirmation token")]
+    Invalid,
+    
+    #[error("Confirmation token has expired")]
+    Expired,
+    
+    #[error("Confirmation token was issued for another organization or user")]
+    Mismatch,
+    
+    #[error("The organization's data changed since the confirmation; request a new token")]
+    PlanChanged,
+}
+
+/// Purge confirmation token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfirmationToken {
+    #[serde(rename = "o")]
+    pub organization_id: String,
+    
+    #[serde(rename = "u")]
+    pub user_id: String,
+    
+    #[serde(rename = "e")]
+    pub expires_at: DateTime<Utc>,
+    
+    /// [`plan_digest`] of the previewed plan
+    #[serde(rename = "p")]
+    pub plan: String,
+    
+    /// Random nonce so tokens for the same plan differ
+    #[serde(rename = "n")]
+    pub nonce: String,
+}
+
+impl ConfirmationToken {
+    /// Token for a plan, issued at `now`
+    pub fn new(organization_id: &str, user_id: &str, plan: &ExecutionPlan, now: DateTime<Utc>) -> Self {
+        Self {
+            organization_id: organization_id.to_string(),
+            user_id: user_id.to_string(),
+            expires_at: now + Duration::minutes(CONFIRMATION_TTL_MINUTES),
+            plan: plan_digest(plan),
+            nonce: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+}
+
+/// Digest of what a purge plan deletes and revokes
+///
+/// Audit entries are left out: the purge deletes all of them, and new ones
+/// are written all the time.
+pub fn plan_digest(plan: &ExecutionPlan) -> String {
+    let mut hasher = Sha256::new();
+    for step in plan.steps().iter().filter(|s| s.entity != PlannedEntity::AuditEntry) {
+        hasher.update(format!("{:?} {:?} {}\n", step.action, step.entity, step.ids.len()));
+        for id in &step.ids {
+            hasher.update(id.as_bytes());
+            hasher.update(b"\n");
+        }
+    }
+    hex::encode(&hasher.finalize()[..16])
+}
+
+/// Signs and verifies confirmation tokens (`base64url(claims).{kid}.base64url(signature)`)
+pub struct ConfirmationSigner {
+    keys: Arc<KeyRing>,
+}
+
+impl ConfirmationSigner {
+    pub fn new(keys: Arc<KeyRing>) -> Self {
+        Self { keys }
+    }
+    
+    pub fn sign(&self, token: &ConfirmationToken) -> Result<String, SigningKeyError> {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(token).map_err(|e| SigningKeyError::Serialization(e.to_string()))?);
+        let signature = self.keys.sign(SIGNING_PURPOSE, payload.as_bytes())?;
+        Ok(format!("{}.{}", payload, signature))
+    }
+    
+    /// Check the signature, the caller, the lifetime and the plan about to be carried out
+    pub fn verify(
+        &self,
+        token: &str,
+        organization_id: &str,
+        user_id: &str,
+        plan: &ExecutionPlan,
+        now: DateTime<Utc>,
+    ) -> Result<ConfirmationToken, ConfirmationError> {
+        let (payload, signature) = token.trim().split_once('.').ok_or(ConfirmationError::Invalid)?;
+        self.keys.verify(SIGNING_PURPOSE, payload.as_bytes(), signature)
+            .map_err(|_| ConfirmationError::Invalid)?;
+        
+        let bytes = URL_SAFE_NO_PAD.decode(payload).map_err(|_| ConfirmationError::Invalid)?;
+        let token: ConfirmationToken = serde_json::from_slice(&bytes).map_err(|_| ConfirmationError::Invalid)?;
+        if token.organization_id != organization_id || token.user_id != user_id {
+            return Err(ConfirmationError::Mismatch);
+        }
+        if now > token.expires_at {
+            return Err(ConfirmationError::Expired);
+        }
+        if token.plan != plan_digest(plan) {
+            return Err(ConfirmationError::PlanChanged);
+        }
+        
+        Ok(token)
+    }
+}
+
+/// Carries out the steps of a purge plan
+pub struct Purger {
+    pub shares: Arc<dyn ShareStorage>,
+    pub activities: Arc<dyn ActivityStorage>,
+    pub layers: Arc<dyn LayerStorage>,
+    pub activity_types: Arc<dyn ActivityTypeStorage>,
+    pub user_settings: Arc<dyn UserSettingsStorage>,
+    pub policies: Arc<dyn PolicyStorage>,
+    pub audit: Arc<dyn AuditStorage>,
+    /// Point-in-time copies of the organization (None when not configured)
+    pub org_snapshots: Option<Arc<dyn OrganizationSnapshotStore>>,
+    /// Where revokes and deletes are announced (None publishes nothing)
+    pub events: Option<Arc<EventBus>>,
+}
+
+impl Purger {
+    /// Revoke or delete `ids` of one plan step, counting them in `certificate`
+    ///
+    /// Entities already gone are skipped, so a step can run again. Audit
+    /// entries and organization snapshots go all at once, with those
+    /// written since the plan was made.
+    pub async fn run(
+        &self,
+        organization_id: &str,
+        entity: PlannedEntity,
+        action: PlannedAction,
+        ids: &[String],
+        changed_by: &str,
+        certificate: &mut DeletionCertificate,
+    ) -> Result<(), StorageError> {
+        let org = organization_id;
+        let count = ids.len() as u64;
+        
+        match (entity, action) {
+            (PlannedEntity::Share, PlannedAction::Revoke) => {
+                for id in ids {
+                    let mut share = match self.shares.get(org, id).await {
+                        Ok(share) => share,
+                        Err(StorageError::NotFound(_)) => continue,
+                        Err(e) => return Err(e),
+                    };
+                    share.is_active = false;
+                    let share = self.shares.update(share).await?;
+                    self.publish(org, EntityKind::Share, id, Some(&share.short_code), ChangeKind::Updated, changed_by).await;
+                }
+                certificate.shares_revoked += count;
+            }
+            (PlannedEntity::Share, PlannedAction::Delete) => {
+                for id in ids {
+                    // The short code, for caches; binned shares may not read back
+                    let short_code = self.shares.get(org, id).await.ok().map(|s| s.short_code);
+                    gone(self.shares.delete(org, id).await)?;
+                    self.publish(org, EntityKind::Share, id, short_code.as_deref(), ChangeKind::Deleted, changed_by).await;
+                }
+                certificate.deleted.shares += count;
+            }
+            (PlannedEntity::Activity, PlannedAction::Delete) => {
+                for id in ids {
+                    gone(self.activities.delete(org, id).await)?;
+                    self.publish(org, EntityKind::Activity, id, None, ChangeKind::Deleted, changed_by).await;
+                }
+                certificate.deleted.activities += count;
+            }
+            (PlannedEntity::Layer, PlannedAction::Delete) => {
+                for id in ids {
+                    gone(self.layers.delete(org, id).await)?;
+                    self.publish(org, EntityKind::Layer, id, None, ChangeKind::Deleted, changed_by).await;
+                }
+                certificate.deleted.layers += count;
+            }
+            (PlannedEntity::ActivityType, PlannedAction::Delete) => {
+                for key in ids {
+                    gone(self.activity_types.force_delete(org, key).await)?;
+                    self.publish(org, EntityKind::ActivityType, key, None, ChangeKind::Deleted, changed_by).await;
+                }
+                certificate.deleted.activity_types += count;
+            }
+            (PlannedEntity::UserSettings, PlannedAction::Delete) => {
+                for user_id in ids {
+                    gone(self.user_settings.delete(org, user_id).await)?;
+                }
+                certificate.deleted.user_settings += count;
+            }
+            (PlannedEntity::Policy, PlannedAction::Delete) => gone(self.policies.delete(org).await)?,
+            (PlannedEntity::OrganizationSnapshot, PlannedAction::Delete) => {
+                let store = self.org_snapshots.as_ref()
+                    .ok_or_else(|| StorageError::Storage("Organization snapshots are not configured".to_string()))?;
+                certificate.deleted.organization_snapshots = store.delete_all(org).await?;
+            }
+            (PlannedEntity::AuditEntry, PlannedAction::Delete) => {
+                certificate.deleted.audit_entries = self.audit.delete_all(org).await?;
+            }
+            (entity, action) => return Err(StorageError::Storage(format!("Unexpected purge step: {:?} {:?}", action, entity))),
+        }
+        Ok(())
+    }
+    
+    async fn publish(&self, organization_id: &str, entity: EntityKind, entity_id: &str, key: Option<&str>, change: ChangeKind, changed_by: &str) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        let mut event = EntityChange::new(organization_id, entity, entity_id, change, Some(changed_by));
+        if let Some(key) = key {
+            event = event.with_key(key);
+        }
+        events.publish(DomainEvent::EntityChanged(event)).await;
+    }
+}
+
+/// A delete of something already gone succeeded
+fn gone(result: Result<(), StorageError>) -> Result<(), StorageError> {
+    match result {
+        Err(StorageError::NotFound(_)) => Ok(()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_confirmation_token() {
+        let now = Utc::now();
+        let signer = ConfirmationSigner::new(crate::signing_keys::tests::key_ring_at(now).await);
+        let mut plan = ExecutionPlan::new();
+        plan.add(PlannedEntity::Share, PlannedAction::Delete, ["share-1"]);
+        plan.add(PlannedEntity::AuditEntry, PlannedAction::Delete, ["audit-1"]);
+        let token = ConfirmationToken::new("org", "admin", &plan, now);
+        let signed = signer.sign(&token).unwrap();
+        
+        assert_eq!(signer.verify(&signed, "org", "admin", &plan, now).unwrap(), token);
+        assert_eq!(signer.verify(&signed, "other-org", "admin", &plan, now), Err(ConfirmationError::Mismatch));
+        assert_eq!(signer.verify(&signed, "org", "someone", &plan, now), Err(ConfirmationError::Mismatch));
+        assert_eq!(signer.verify("garbage", "org", "admin", &plan, now), Err(ConfirmationError::Invalid));
+        
+        // Minted without the key ring, or by another one
+        let unsigned = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&token).unwrap());
+        assert_eq!(signer.verify(&unsigned, "org", "admin", &plan, now), Err(ConfirmationError::Invalid));
+        let other = ConfirmationSigner::new(crate::signing_keys::tests::key_ring_at(now).await);
+        assert_eq!(signer.verify(&other.sign(&token).unwrap(), "org", "admin", &plan, now), Err(ConfirmationError::Invalid));
+        
+        let later = now + Duration::minutes(CONFIRMATION_TTL_MINUTES) + Duration::seconds(1);
+        assert_eq!(signer.verify(&signed, "org", "admin", &plan, later), Err(ConfirmationError::Expired));
+        
+        // New audit entries don't matter; new data does
+        let mut audited = plan.clone();
+        audited.add(PlannedEntity::AuditEntry, PlannedAction::Delete, ["audit-2"]);
+        assert!(signer.verify(&signed, "org", "admin", &audited, now).is_ok());
+        plan.add(PlannedEntity::Activity, PlannedAction::Delete, ["activity-1"]);
+        assert_eq!(signer.verify(&signed, "org", "admin", &plan, now), Err(ConfirmationError::PlanChanged));
+    }
+}
@@ -0,0 +1,1198 @@
+//! # Background Operations
+//!
+//! Bulk imports, reassignments, exports, purges and schema rewrites touch
+//! thousands of rows.
+//! Run inside the request, they outlast the Functions HTTP timeout and hold
+//! the host's storage throughput while interactive requests on the same
+//! instance queue behind them. Their `/background` endpoints hand them to the
+//! [`BulkExecutor`] instead:
+//!
+//! 1. The handler validates the request, queues the work and answers
+//!    `202 Accepted` with the [`BulkOperation`], a `Location` of
+//!    `/api/operations/{id}` and a `Retry-After`
+//! 2. `GET /api/operations/{id}` reports status and progress, with
+//!    `Retry-After` until the operation finishes
+//! 3. The finished operation carries its result (an import summary, the
+//!    archive, a deletion certificate...) until `expiresAt`, after which it
+//!    is gone and [`BulkExecutor::purge_expired`] deletes it from the store
+//!
+//! ## Scheduling
+//!
+//! - At most [`BulkLimits::max_running`] operations run on an instance
+//! - At most [`BulkLimits::max_per_org`] of them belong to one organization
+//! - Free slots go to the waiting organizations in turn, so one queueing
+//!   imports doesn't hold back another's reassignment
+//! - Operations write [`STEP_SIZE`] rows per step and yield between steps
+//!
+//! Both limits are per instance: each instance only counts the operations
+//! it runs itself, so with several instances an organization can have up to
+//! `max_per_org` running on each.
+//!
+//! Copying a year is out of scope: the API has no copy-year endpoint, in
+//! the request or in the background. `OperationInput` has the operations
+//! that exist.
+//!
+//! ## Resuming
+//!
+//! An operation's input (the prepared archive, or the reassignment) is
+//! written to the [`OperationStore`] once, and its record (status, progress
+//! and the result so far) after every step. [`BulkExecutor::resume`] queues
+//! operations a recycled host left queued or running again, from their last
+//! recorded step.
+//!
+//! Each instance claims an operation before running it with a lease on the
+//! record: `leaseOwner` and `leaseExpiresAt`, written conditional on the
+//! ETag read ([`OperationStore::replace`]). Every recorded step renews the
+//! lease, as does a heartbeat while a long step runs. Other instances only
+//! take over operations whose lease has expired, and an owner whose
+//! conditional write fails has lost the operation and stops. Queued
+//! operations carry no lease, so whichever instance claims one first runs
+//! it. Steps are safe to repeat: imports find the rows already
+//! written and keep (`skip`, `new-ids`) or rewrite (`overwrite`) them, and
+//! reassignments look up the activities still to move. A repeated step may
+//! count its rows twice in the import summary. Imports publish a change
+//! event for every entity they write ([`BulkExecutor::with_events`]), so
+//! caches, the search index and snapshots follow as they would an edit.
+//!
+//! Unlike `POST /api/admin/reassign`, a failed background reassignment puts
+//! nothing back: the operation fails with the rows written so far, and
+//! starting it again moves the rest. Purges likewise run the plan confirmed
+//! when they were started, skipping entities already gone, and publish
+//! their deletes like `DELETE /api/admin/organization` (see
+//! [`crate::offboarding::Purger`]). Once done, a purge deletes the
+//! organization's other operations, whose inputs and results hold its data.
+//!
+//! Export results and import inputs hold share keys; with
+//! `SHARE_KEY_ENCRYPTION_KEYS` set the store is wrapped in a
+//! [`SealedOperationStore`](crate::share_key_cipher::SealedOperationStore).
+//!
+//! ## Schema rewrites
+//!
+//! `POST /api/admin/schema/rewrite` saves every row of the organization
+//! again as it reads, so each is stored at the current schema version (see
+//! [`crate::schema`]). Shares, activities and layers are updated, and rows
+//! deleted in the meantime are skipped; activity types and user settings
+//! have only upserts. Progress is a position in each type's rows in key
+//! order, so rows deleted during the rewrite can leave a few others for the
+//! next one; those are still upgraded when read.
+//!
+//! ## Search reindex
+//!
+//! `POST /api/admin/search/reindex` pushes every activity of the
+//! organization into the configured [`ActivitySearchIndex`] again, in `id`
+//! order, e.g. after the index was recreated or missed events. Activities
+//! deleted meanwhile stay in the index until their delete event arrives;
+//! search re-reads hits from storage, so they never show.
+
+use crate::clock::{Clock, SystemClock};
+use crate::backup;
+use crate::events::{ChangeKind, DomainEvent, EntityChange, EntityKind, EventBus};
+use crate::handlers::{AUDIT_ACTION_EXPORTED, AUDIT_ACTION_IMPORTED, AUDIT_ACTION_REASSIGNED};
+use crate::models::{
+    AuditEntry, BackupArchive, BulkOperation, ConflictStrategy, DeletionCertificate, ImportCount,
+    ImportSummary, OperationKind, OperationStatus, PlannedChange, PlannedEntity,
+    PurgeSummary, ReassignRequest, ReassignResult, SchemaRewriteSummary,
+};
+use crate::offboarding::{Purger, AUDIT_ACTION_PURGED};
+use crate::org_snapshots::OrganizationSnapshotStore;
+use crate::reassign;
+use crate::search::ActivitySearchIndex;
+use crate::storage::{
+    self, list_all_activities, list_all_shares, ActivityStorage, ActivityTypeStorage, AuditStorage,
+    LayerStorage, PolicyStorage, ShareStorage, Storage, StorageError, UserSettingsStorage,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+/// Rows written per step
+pub const STEP_SIZE: usize = reassign::BATCH_SIZE;
+
+/// Default operations running at once on an instance
+pub const DEFAULT_MAX_RUNNING: usize = 2;
+
+/// Default operations of one organization running at once
+pub const DEFAULT_MAX_PER_ORG: usize = 1;
+
+/// Default days a finished operation and its result are kept
+pub const DEFAULT_RESULT_TTL_DAYS: i64 = 7;
+
+/// Default seconds an instance holds an operation without renewing its lease
+pub const DEFAULT_LEASE_SECONDS: i64 = 300;
+
+/// Audit action recorded when an organization's rows were rewritten
+pub const AUDIT_ACTION_SCHEMA_REWRITTEN: &str = "schema.rewritten";
+
+/// Audit action recorded when an organization's activities were indexed again
+pub const AUDIT_ACTION_REINDEXED: &str = "search.reindexed";
+
+/// How many operations run at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkLimits {
+    pub max_running: usize,
+    pub max_per_org: usize,
+}
+
+impl Default for BulkLimits {
+    fn default() -> Self {
+        Self { max_running: DEFAULT_MAX_RUNNING, max_per_org: DEFAULT_MAX_PER_ORG }
+    }
+}
+
+/// What an operation writes, stored once so it can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationInput {
+    /// An archive already rehomed (and given new IDs) for the organization
+    Import { archive: Box<BackupArchive>, strategy: ConflictStrategy },
+    Reassign { request: ReassignRequest },
+    Export,
+    /// The purge plan confirmed when the operation was started
+    Purge { changes: Vec<PlannedChange>, started_at: DateTime<Utc> },
+    SchemaRewrite,
+    Reindex,
+}
+
+impl OperationInput {
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            Self::Import { .. } => OperationKind::Import,
+            Self::Reassign { .. } => OperationKind::Reassign,
+            Self::Export => OperationKind::Export,
+            Self::Purge { .. } => OperationKind::Purge,
+            Self::SchemaRewrite => OperationKind::SchemaRewrite,
+            Self::Reindex => OperationKind::Reindex,
+        }
+    }
+}
+
+/// Keeps operation records and inputs across host recycles
+#[async_trait]
+pub trait OperationStore: Send + Sync {
+    /// Create or replace an operation record
+    async fn put(&self, operation: &BulkOperation) -> Result<(), StorageError>;
+
+    /// `NotFound` if the organization has no operation with this ID
+    async fn get(&self, organization_id: &str, operation_id: &str) -> Result<BulkOperation, StorageError>;
+
+    /// An operation record and the ETag it was read at
+    async fn get_tagged(&self, organization_id: &str, operation_id: &str) -> Result<(BulkOperation, String), StorageError>;
+
+    /// Replace a record that still has `etag`; the new ETag, or None if
+    /// someone wrote the record since
+    async fn replace(&self, operation: &BulkOperation, etag: &str) -> Result<Option<String>, StorageError>;
+
+    /// Operations of every organization
+    async fn list(&self) -> Result<Vec<BulkOperation>, StorageError>;
+
+    /// Delete an operation record and its input; missing ones are fine
+    async fn delete(&self, organization_id: &str, operation_id: &str) -> Result<(), StorageError>;
+
+    async fn put_input(&self, organization_id: &str, operation_id: &str, body: Vec<u8>) -> Result<(), StorageError>;
+
+    async fn get_input(&self, organization_id: &str, operation_id: &str) -> Result<Vec<u8>, StorageError>;
+}
+
+/// Object path of an operation record
+pub fn operation_path(organization_id: &str, operation_id: &str) -> String {
+    format!("{}/{}.json", organization_id, operation_id)
+}
+
+/// Object path of an operation's input
+pub fn input_path(organization_id: &str, operation_id: &str) -> String {
+    format!("{}/{}.input.json", organization_id, operation_id)
+}
+
+/// Operations waiting for and holding slots
+#[derive(Debug, Default)]
+struct Queue {
+    waiting: BTreeMap<String, VecDeque<String>>,
+    running: HashMap<String, usize>,
+    /// Organization that got the last slot
+    last_org: Option<String>,
+}
+
+impl Queue {
+    fn push(&mut self, organization_id: &str, operation_id: &str) {
+        self.waiting.entry(organization_id.to_string()).or_default().push_back(operation_id.to_string());
+    }
+
+    /// Next operation to run, if a slot is free: the first waiting
+    /// organization after the one served last that is below its limit
+    fn take(&mut self, limits: BulkLimits) -> Option<(String, String)> {
+        if self.running.values().sum::<usize>() >= limits.max_running {
+            return None;
+        }
+        let eligible = |org: &String| self.running.get(org).copied().unwrap_or(0) < limits.max_per_org;
+        let org = self.waiting.keys()
+            .filter(|org| eligible(org))
+            .find(|org| self.last_org.as_ref().is_some_and(|last| *org > last))
+            .or_else(|| self.waiting.keys().find(|org| eligible(org)))?
+            .clone();
+
+        let queued = self.waiting.get_mut(&org)?;
+        let operation_id = queued.pop_front()?;
+        if queued.is_empty() {
+            self.waiting.remove(&org);
+        }
+        *self.running.entry(org.clone()).or_default() += 1;
+        self.last_org = Some(org.clone());
+        Some((org, operation_id))
+    }
+
+    fn finish(&mut self, organization_id: &str) {
+        if let Some(running) = self.running.get_mut(organization_id) {
+            *running -= 1;
+            if *running == 0 {
+                self.running.remove(organization_id);
+            }
+        }
+    }
+}
+
+fn add(total: &mut ImportCount, count: ImportCount) {
+    total.created += count.created;
+    total.overwritten += count.overwritten;
+    total.skipped += count.skipped;
+}
+
+/// Add the count of a one-row import to the total; the change it made, if any
+pub(crate) fn tally(total: &mut ImportCount, count: ImportCount) -> Option<ChangeKind> {
+    let change = if count.created > 0 {
+        Some(ChangeKind::Created)
+    } else if count.overwritten > 0 {
+        Some(ChangeKind::Updated)
+    } else {
+        None
+    };
+    add(total, count);
+    change
+}
+
+/// Rows after the first `done` in key order, a step's worth
+fn next_batch<T>(mut rows: Vec<T>, key: impl Fn(&T) -> String, done: u64) -> Vec<T> {
+    rows.sort_by_cached_key(|r| key(r));
+    rows.into_iter().skip(done as usize).take(STEP_SIZE).collect()
+}
+
+/// Whether an instance holds a lease on the operation at `now`
+fn leased(operation: &BulkOperation, now: DateTime<Utc>) -> bool {
+    operation.lease_expires_at.is_some_and(|at| at > now)
+}
+
+/// `NotFound` counts as done, so repeated rewrite steps pass
+fn gone(result: Result<(), StorageError>) -> Result<(), StorageError> {
+    match result {
+        Err(StorageError::NotFound(_)) => Ok(()),
+        other => other,
+    }
+}
+
+/// Runs bulk operations in the background, a few at a time
+pub struct BulkExecutor {
+    store: Arc<dyn OperationStore>,
+    policies: Arc<dyn PolicyStorage>,
+    activity_types: Arc<dyn ActivityTypeStorage>,
+    layers: Arc<dyn LayerStorage>,
+    activities: Arc<dyn ActivityStorage>,
+    shares: Arc<dyn ShareStorage>,
+    user_settings: Arc<dyn UserSettingsStorage>,
+    audit: Arc<dyn AuditStorage>,
+    org_snapshots: Option<Arc<dyn OrganizationSnapshotStore>>,
+    search: Option<Arc<dyn ActivitySearchIndex>>,
+    limits: BulkLimits,
+    result_ttl: Duration,
+    /// Owner written on the leases of this instance
+    instance_id: String,
+    lease: Duration,
+    clock: Arc<dyn Clock>,
+    events: Option<Arc<EventBus>>,
+    queue: Mutex<Queue>,
+}
+
+impl BulkExecutor {
+    pub fn new(store: Arc<dyn OperationStore>, storage: &Storage, limits: BulkLimits) -> Self {
+        Self {
+            store,
+            policies: storage.policies.clone(),
+            activity_types: storage.activity_types.clone(),
+            layers: storage.layers.clone(),
+            activities: storage.activities.clone(),
+            shares: storage.shares.clone(),
+            user_settings: storage.user_settings.clone(),
+            audit: storage.audit.clone(),
+            org_snapshots: None,
+            search: None,
+            limits,
+            result_ttl: Duration::days(DEFAULT_RESULT_TTL_DAYS),
+            instance_id: uuid::Uuid::new_v4().to_string(),
+            lease: Duration::seconds(DEFAULT_LEASE_SECONDS),
+            clock: Arc::new(SystemClock),
+            events: None,
+            queue: Mutex::new(Queue::default()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How long finished operations are kept
+    pub fn with_result_ttl(mut self, ttl: Duration) -> Self {
+        self.result_ttl = ttl;
+        self
+    }
+
+    /// How long a lease lasts unless renewed; another instance takes over
+    /// operations this one stopped renewing after at most this long
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Publish the entities imports write
+    pub fn with_events(mut self, events: Arc<EventBus>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Organization snapshots purges delete
+    pub fn with_org_snapshots(mut self, store: Arc<dyn OrganizationSnapshotStore>) -> Self {
+        self.org_snapshots = Some(store);
+        self
+    }
+
+    /// Index reindex operations write to
+    pub fn with_search_index(mut self, index: Arc<dyn ActivitySearchIndex>) -> Self {
+        self.search = Some(index);
+        self
+    }
+
+    /// Whether reindex operations have an index to write to
+    pub fn has_search_index(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// Record an operation and queue it
+    pub async fn start(self: &Arc<Self>, organization_id: &str, created_by: &str, input: OperationInput) -> Result<BulkOperation, StorageError> {
+        let total = match &input {
+            OperationInput::Import { archive, .. } => {
+                (archive.policy.is_some() as usize + archive.activity_types.len() + archive.layers.len()
+                    + archive.activities.len() + archive.shares.len() + archive.user_settings.len()) as u64
+            }
+            OperationInput::Reassign { request } => list_all_activities(self.activities.as_ref(), organization_id).await?
+                .iter()
+                .filter(|a| reassign::matches(request, a))
+                .count() as u64,
+            OperationInput::Export => 1,
+            OperationInput::Purge { changes, .. } => changes.iter().map(|c| c.ids.len() as u64).sum(),
+            OperationInput::SchemaRewrite => {
+                (self.activity_types.list(organization_id).await?.len()
+                    + self.layers.list(organization_id).await?.len()
+                    + list_all_activities(self.activities.as_ref(), organization_id).await?.len()
+                    + list_all_shares(self.shares.as_ref(), organization_id).await?.len()
+                    + self.user_settings.list(organization_id).await?.len()) as u64
+            }
+            OperationInput::Reindex => list_all_activities(self.activities.as_ref(), organization_id).await?.len() as u64,
+        };
+        let now = self.clock.now();
+        let operation = BulkOperation {
+            id: uuid::Uuid::new_v4().to_string(),
+            organization_id: organization_id.to_string(),
+            kind: input.kind(),
+            status: OperationStatus::Queued,
+            completed: 0,
+            total,
+            created_by: created_by.to_string(),
+            created_at: now,
+            updated_at: now,
+            error: None,
+            result: None,
+            expires_at: None,
+            lease_owner: None,
+            lease_expires_at: None,
+        };
+
+        let body = serde_json::to_vec(&input).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        self.store.put_input(organization_id, &operation.id, body).await?;
+        self.store.put(&operation).await?;
+        self.enqueue(&operation);
+        Ok(operation)
+    }
+
+    /// `NotFound` once the operation expired
+    pub async fn get(&self, organization_id: &str, operation_id: &str) -> Result<BulkOperation, StorageError> {
+        let operation = self.store.get(organization_id, operation_id).await?;
+        if operation.expires_at.is_some_and(|at| at <= self.clock.now()) {
+            return Err(StorageError::NotFound(operation_id.to_string()));
+        }
+        Ok(operation)
+    }
+
+    /// Queue unfinished operations no instance holds a lease on, oldest
+    /// first; returns how many
+    pub async fn resume(self: &Arc<Self>) -> Result<usize, StorageError> {
+        let now = self.clock.now();
+        let mut unfinished: Vec<_> = self.store.list().await?.into_iter()
+            .filter(|o| o.status.is_unfinished() && !leased(o, now))
+            .collect();
+        unfinished.sort_by_key(|o| o.created_at);
+        for operation in &unfinished {
+            self.enqueue(operation);
+        }
+        Ok(unfinished.len())
+    }
+
+    /// Delete expired operations; returns how many
+    pub async fn purge_expired(&self) -> Result<u64, StorageError> {
+        let now = self.clock.now();
+        let mut deleted = 0;
+        for operation in self.store.list().await? {
+            if operation.expires_at.is_some_and(|at| at <= now) {
+                self.store.delete(&operation.organization_id, &operation.id).await?;
+                deleted += 1;
+            }
+        }
+        if deleted > 0 {
+            tracing::info!(deleted, "Deleted expired background operations");
+        }
+        Ok(deleted)
+    }
+
+    /// Delete the organization's operations, records and inputs, but `except`
+    /// (a purge running as an operation); returns how many were deleted
+    pub async fn delete_organization(&self, organization_id: &str, except: Option<&str>) -> Result<u64, StorageError> {
+        let mut deleted = 0;
+        for operation in self.store.list().await? {
+            if operation.organization_id == organization_id && except != Some(operation.id.as_str()) {
+                self.store.delete(&operation.organization_id, &operation.id).await?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Take over operations whose lease expired every `interval`, starting
+    /// with those left by an earlier host, until the task is dropped
+    pub async fn run_resume(self: Arc<Self>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.resume().await {
+                Ok(0) => {}
+                Ok(resumed) => tracing::info!(resumed, "Resumed background operations"),
+                Err(e) => tracing::warn!(error = %e, "Failed to resume background operations"),
+            }
+        }
+    }
+
+    /// Delete expired operations every `interval` until the task is dropped
+    pub async fn run_cleanup(self: Arc<Self>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.purge_expired().await {
+                tracing::warn!(error = %e, "Expired operation cleanup failed");
+            }
+        }
+    }
+
+    fn enqueue(self: &Arc<Self>, operation: &BulkOperation) {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).push(&operation.organization_id, &operation.id);
+        self.dispatch();
+    }
+
+    /// Start queued operations while slots are free
+    fn dispatch(self: &Arc<Self>) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        while let Some((organization_id, operation_id)) = queue.take(self.limits) {
+            tokio::spawn(self.clone().run(organization_id, operation_id));
+        }
+    }
+
+    async fn run(self: Arc<Self>, organization_id: String, operation_id: String) {
+        if let Err(e) = self.execute(&organization_id, &operation_id).await {
+            tracing::error!(operation_id = %operation_id, error = %e, "Failed to run background operation");
+        }
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).finish(&organization_id);
+        self.dispatch();
+    }
+
+    /// Claim the operation and run it step by step while holding the lease
+    async fn execute(&self, organization_id: &str, operation_id: &str) -> Result<(), StorageError> {
+        let (mut operation, mut etag) = self.store.get_tagged(organization_id, operation_id).await?;
+        if !operation.status.is_unfinished() || leased(&operation, self.clock.now()) {
+            return Ok(());
+        }
+        let body = self.store.get_input(organization_id, operation_id).await?;
+        let input: OperationInput = serde_json::from_slice(&body).map_err(|e| StorageError::Serialization(e.to_string()))?;
+
+        operation.status = OperationStatus::Running;
+        if !self.record(&mut operation, &mut etag).await? {
+            return Ok(());
+        }
+
+        loop {
+            let before = operation.clone();
+            let step = {
+                let step = std::pin::pin!(self.step(&input, &mut operation));
+                match self.with_heartbeat(step, before, &mut etag).await? {
+                    Some(step) => step,
+                    None => return Ok(()),
+                }
+            };
+            match step {
+                Ok(false) => if !self.record(&mut operation, &mut etag).await? {
+                    return Ok(());
+                },
+                Ok(true) => {
+                    operation.status = OperationStatus::Succeeded;
+                    if self.record(&mut operation, &mut etag).await? {
+                        self.record_audit(&input, &operation).await;
+                    }
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::warn!(operation_id = %operation.id, error = %e, "Background operation failed");
+                    operation.status = OperationStatus::Failed;
+                    operation.error = Some(e.to_string());
+                    return self.record(&mut operation, &mut etag).await.map(drop);
+                }
+            }
+            // Let interactive requests on this instance through between steps
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Write the operation if this instance still holds it, renewing the
+    /// lease, or releasing it once finished; false when another instance
+    /// took the operation over
+    async fn record(&self, operation: &mut BulkOperation, etag: &mut String) -> Result<bool, StorageError> {
+        operation.updated_at = self.clock.now();
+        if operation.status.is_unfinished() {
+            operation.lease_owner = Some(self.instance_id.clone());
+            operation.lease_expires_at = Some(operation.updated_at + self.lease);
+        } else {
+            operation.lease_owner = None;
+            operation.lease_expires_at = None;
+            operation.expires_at = Some(operation.updated_at + self.result_ttl);
+        }
+        match self.store.replace(operation, etag).await? {
+            Some(new_etag) => {
+                *etag = new_etag;
+                Ok(true)
+            }
+            None => {
+                tracing::warn!(operation_id = %operation.id, "Lost the lease on a background operation to another instance");
+                Ok(false)
+            }
+        }
+    }
+
+    /// Run a step, renewing the lease on the record as it was before the
+    /// step every third of the lease; None when the lease was lost meanwhile
+    async fn with_heartbeat<F, T>(&self, mut step: std::pin::Pin<&mut F>, mut before: BulkOperation, etag: &mut String) -> Result<Option<T>, StorageError>
+    where
+        F: std::future::Future<Output = T>,
+    {
+        let interval = (self.lease / 3).to_std().unwrap_or(std::time::Duration::from_secs(1));
+        loop {
+            match tokio::time::timeout(interval, step.as_mut()).await {
+                Ok(output) => return Ok(Some(output)),
+                Err(_) => if !self.record(&mut before, etag).await? {
+                    return Ok(None);
+                },
+            }
+        }
+    }
+
+    /// Write the next rows; true when nothing is left
+    async fn step(&self, input: &OperationInput, operation: &mut BulkOperation) -> Result<bool, StorageError> {
+        match input {
+            OperationInput::Import { archive, strategy } => self.import_step(archive, *strategy, operation).await,
+            OperationInput::Reassign { request } => self.reassign_step(request, operation).await,
+            OperationInput::Export => self.export_step(operation).await,
+            OperationInput::Purge { changes, started_at } => self.purge_step(changes, *started_at, operation).await,
+            OperationInput::SchemaRewrite => self.rewrite_step(operation).await,
+            OperationInput::Reindex => self.reindex_step(operation).await,
+        }
+    }
+
+    async fn import_step(&self, archive: &BackupArchive, strategy: ConflictStrategy, operation: &mut BulkOperation) -> Result<bool, StorageError> {
+        let org = &operation.organization_id;
+        let mut summary: ImportSummary = operation.result.clone()
+            .and_then(|r| serde_json::from_value(r).ok())
+            .unwrap_or(ImportSummary { on_conflict: strategy, ..Default::default() });
+
+        // Layers before the activities and shares that refer to them, as in `POST /api/admin/import`
+        let lengths = [
+            archive.policy.is_some() as usize,
+            archive.activity_types.len(),
+            archive.layers.len(),
+            archive.activities.len(),
+            archive.shares.len(),
+            archive.user_settings.len(),
+        ];
+        let mut offset = operation.completed as usize;
+        let Some(phase) = lengths.iter().position(|&len| {
+            let inside = offset < len;
+            if !inside {
+                offset -= len;
+            }
+            inside
+        }) else {
+            return Ok(true);
+        };
+        let end = (offset + STEP_SIZE).min(lengths[phase]);
+
+        match phase {
+            0 => if let Some(policy) = archive.policy.clone() {
+                add(&mut summary.policy, storage::import_policy(self.policies.as_ref(), policy, strategy).await?);
+            },
+            // Row by row, so each write is known
+            1 => for config in &archive.activity_types[offset..end] {
+                let count = storage::import_activity_types(self.activity_types.as_ref(), org, vec![config.clone()], strategy).await?;
+                self.publish(operation, EntityKind::ActivityType, &config.key, None, tally(&mut summary.activity_types, count)).await;
+            },
+            2 => for layer in &archive.layers[offset..end] {
+                let count = storage::import_layers(self.layers.as_ref(), vec![layer.clone()], strategy).await?;
+                self.publish(operation, EntityKind::Layer, &layer.id, None, tally(&mut summary.layers, count)).await;
+            },
+            3 => for activity in &archive.activities[offset..end] {
+                let count = storage::import_activities(self.activities.as_ref(), vec![activity.clone()], strategy).await?;
+                self.publish(operation, EntityKind::Activity, &activity.id, None, tally(&mut summary.activities, count)).await;
+            },
+            4 => for share in &archive.shares[offset..end] {
+                let count = storage::import_shares(self.shares.as_ref(), vec![share.clone()], strategy).await?;
+                self.publish(operation, EntityKind::Share, &share.id, Some(&share.short_code), tally(&mut summary.shares, count)).await;
+            },
+            _ => add(&mut summary.user_settings, storage::import_user_settings(self.user_settings.as_ref(), org, archive.user_settings[offset..end].to_vec(), strategy).await?),
+        }
+
+        operation.completed += (end - offset) as u64;
+        operation.result = serde_json::to_value(&summary).ok();
+        Ok(operation.completed >= operation.total)
+    }
+
+    /// Purge routine over the same storage, publishing on the same bus
+    fn purger(&self) -> Purger {
+        Purger {
+            shares: self.shares.clone(),
+            activities: self.activities.clone(),
+            layers: self.layers.clone(),
+            activity_types: self.activity_types.clone(),
+            user_settings: self.user_settings.clone(),
+            policies: self.policies.clone(),
+            audit: self.audit.clone(),
+            org_snapshots: self.org_snapshots.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    /// Publish a change an import step made, if any
+    async fn publish(&self, operation: &BulkOperation, entity: EntityKind, entity_id: &str, short_code: Option<&str>, change: Option<ChangeKind>) {
+        let (Some(events), Some(change)) = (&self.events, change) else {
+            return;
+        };
+        let mut event = EntityChange::new(&operation.organization_id, entity, entity_id, change, Some(&operation.created_by));
+        if let Some(short_code) = short_code {
+            event = event.with_key(short_code);
+        }
+        events.publish(DomainEvent::EntityChanged(event)).await;
+    }
+
+    async fn reassign_step(&self, request: &ReassignRequest, operation: &mut BulkOperation) -> Result<bool, StorageError> {
+        let now = self.clock.now();
+        let moved: Vec<_> = list_all_activities(self.activities.as_ref(), &operation.organization_id).await?
+            .into_iter()
+            .filter(|a| reassign::matches(request, a))
+            .take(STEP_SIZE)
+            .map(|mut activity| {
+                reassign::apply(&request.reassignment, &mut activity);
+                activity.updated_at = Some(now);
+                activity
+            })
+            .collect();
+
+        let count = moved.len();
+        if count > 0 {
+            self.activities.update_batch(moved).await?;
+        }
+        operation.completed += count as u64;
+        // Activities created in the meantime are moved too
+        operation.total = operation.total.max(operation.completed);
+        operation.result = serde_json::to_value(ReassignResult { reassigned: operation.completed }).ok();
+        Ok(count < STEP_SIZE)
+    }
+
+    /// The whole archive in one step; reads are cheap next to the request timeout
+    async fn export_step(&self, operation: &mut BulkOperation) -> Result<bool, StorageError> {
+        let org = &operation.organization_id;
+        let policy = self.policies.get(org).await?;
+        let archive = BackupArchive {
+            format_version: backup::FORMAT_VERSION,
+            organization_id: org.clone(),
+            exported_at: self.clock.now(),
+            // The default policy is never stored
+            policy: policy.updated_at.is_some().then_some(policy),
+            activity_types: self.activity_types.list(org).await?,
+            layers: self.layers.list(org).await?,
+            activities: list_all_activities(self.activities.as_ref(), org).await?,
+            shares: list_all_shares(self.shares.as_ref(), org).await?,
+            user_settings: self.user_settings.list(org).await?,
+        };
+        operation.result = Some(serde_json::to_value(&archive).map_err(|e| StorageError::Serialization(e.to_string()))?);
+        operation.completed = 1;
+        Ok(true)
+    }
+
+    /// The next IDs of the plan, in the order of `DELETE /api/admin/organization`
+    async fn purge_step(&self, changes: &[PlannedChange], started_at: DateTime<Utc>, operation: &mut BulkOperation) -> Result<bool, StorageError> {
+        let org = operation.organization_id.clone();
+        let mut certificate: DeletionCertificate = match operation.result.clone().and_then(|r| serde_json::from_value(r).ok()) {
+            Some(certificate) => certificate,
+            None => DeletionCertificate {
+                id: uuid::Uuid::new_v4().to_string(),
+                organization_id: org.clone(),
+                requested_by: operation.created_by.clone(),
+                started_at,
+                completed_at: started_at,
+                shares_revoked: 0,
+                deleted: PurgeSummary::default(),
+            },
+        };
+
+        let mut offset = operation.completed as usize;
+        let Some(change) = changes.iter().find(|c| {
+            let inside = offset < c.ids.len();
+            if !inside {
+                offset -= c.ids.len();
+            }
+            inside
+        }) else {
+            // Other operations of the organization go last; their results hold its data
+            self.delete_organization(&org, Some(&operation.id)).await?;
+            return Ok(true);
+        };
+        // Audit entries and snapshots go all at once, with those written since the plan was made
+        let end = match change.entity {
+            PlannedEntity::AuditEntry | PlannedEntity::OrganizationSnapshot => change.ids.len(),
+            _ => (offset + STEP_SIZE).min(change.ids.len()),
+        };
+        let ids = &change.ids[offset..end];
+        self.purger().run(&org, change.entity, change.action, ids, &operation.created_by, &mut certificate).await?;
+
+        operation.completed += ids.len() as u64;
+        certificate.completed_at = self.clock.now();
+        operation.result = Some(serde_json::to_value(&certificate).map_err(|e| StorageError::Serialization(e.to_string()))?);
+        let done = operation.completed >= operation.total;
+        if done {
+            self.delete_organization(&org, Some(&operation.id)).await?;
+        }
+        Ok(done)
+    }
+
+    /// Save the next rows of the first type with rows left; a type's count in
+    /// the summary is how far it got
+    async fn rewrite_step(&self, operation: &mut BulkOperation) -> Result<bool, StorageError> {
+        let org = operation.organization_id.clone();
+        let mut summary: SchemaRewriteSummary = operation.result.clone()
+            .and_then(|r| serde_json::from_value(r).ok())
+            .unwrap_or_default();
+
+        let written = 'step: {
+            let batch = next_batch(self.activity_types.list(&org).await?, |t| t.key.clone(), summary.activity_types);
+            if !batch.is_empty() {
+                summary.activity_types += batch.len() as u64;
+                for config in batch {
+                    self.activity_types.upsert(config).await?;
+                }
+                break 'step true;
+            }
+            let batch = next_batch(self.layers.list(&org).await?, |l| l.id.clone(), summary.layers);
+            if !batch.is_empty() {
+                summary.layers += batch.len() as u64;
+                for layer in batch {
+                    gone(self.layers.update(layer).await.map(drop))?;
+                }
+                break 'step true;
+            }
+            let batch = next_batch(list_all_activities(self.activities.as_ref(), &org).await?, |a| a.id.clone(), summary.activities);
+            if !batch.is_empty() {
+                summary.activities += batch.len() as u64;
+                for activity in batch {
+                    gone(self.activities.update(activity).await.map(drop))?;
+                }
+                break 'step true;
+            }
+            let batch = next_batch(list_all_shares(self.shares.as_ref(), &org).await?, |s| s.id.clone(), summary.shares);
+            if !batch.is_empty() {
+                summary.shares += batch.len() as u64;
+                for share in batch {
+                    gone(self.shares.update(share).await.map(drop))?;
+                }
+                break 'step true;
+            }
+            let batch = next_batch(self.user_settings.list(&org).await?, |s| s.user_id.clone(), summary.user_settings);
+            if !batch.is_empty() {
+                summary.user_settings += batch.len() as u64;
+                for settings in batch {
+                    self.user_settings.upsert(settings).await?;
+                }
+                break 'step true;
+            }
+            false
+        };
+
+        operation.completed = summary.activity_types + summary.layers + summary.activities + summary.shares + summary.user_settings;
+        // Rows created in the meantime are saved too
+        operation.total = operation.total.max(operation.completed);
+        operation.result = serde_json::to_value(&summary).ok();
+        Ok(!written)
+    }
+
+    /// Push the next activities in `id` order into the search index
+    async fn reindex_step(&self, operation: &mut BulkOperation) -> Result<bool, StorageError> {
+        let index = self.search.as_ref()
+            .ok_or_else(|| StorageError::Storage("Search index is not configured".to_string()))?;
+        let activities = list_all_activities(self.activities.as_ref(), &operation.organization_id).await?;
+        let batch = next_batch(activities, |a| a.id.clone(), operation.completed);
+        if batch.is_empty() {
+            operation.total = operation.completed;
+            return Ok(true);
+        }
+
+        for activity in &batch {
+            index.upsert(activity).await.map_err(|e| StorageError::Storage(e.to_string()))?;
+        }
+        operation.completed += batch.len() as u64;
+        // Activities created in the meantime are indexed too
+        operation.total = operation.total.max(operation.completed);
+        operation.result = Some(serde_json::json!({ "indexed": operation.completed }));
+        Ok(false)
+    }
+
+    async fn record_audit(&self, input: &OperationInput, operation: &BulkOperation) {
+        let (action, details) = match input {
+            OperationInput::Import { .. } => (AUDIT_ACTION_IMPORTED, serde_json::json!({ "summary": operation.result })),
+            OperationInput::Reassign { request } => (AUDIT_ACTION_REASSIGNED, serde_json::json!({ "request": request, "reassigned": operation.completed })),
+            OperationInput::Export => {
+                let archive = operation.result.as_ref();
+                let len = |key: &str| archive.and_then(|a| a[key].as_array()).map_or(0, Vec::len);
+                (AUDIT_ACTION_EXPORTED, serde_json::json!({
+                    "layers": len("layers"),
+                    "activities": len("activities"),
+                    "shares": len("shares"),
+                }))
+            }
+            // The certificate is the only record kept for the organization
+            OperationInput::Purge { .. } => (AUDIT_ACTION_PURGED, operation.result.clone().unwrap_or_default()),
+            OperationInput::SchemaRewrite => (AUDIT_ACTION_SCHEMA_REWRITTEN, serde_json::json!({ "rewritten": operation.result })),
+            OperationInput::Reindex => (AUDIT_ACTION_REINDEXED, serde_json::json!({ "indexed": operation.completed })),
+        };
+        let target = match input {
+            OperationInput::Purge { .. } => operation.result.as_ref().and_then(|c| c["id"].as_str()).unwrap_or(&operation.id),
+            _ => &operation.id,
+        };
+        let entry = AuditEntry::new(&operation.organization_id, action, Some(&operation.created_by), Some(target))
+            .with_details(details);
+        if let Err(e) = self.audit.record(entry).await {
+            tracing::warn!(operation_id = %operation.id, error = %e, "Failed to audit background operation");
+        }
+    }
+}
+
+/// In-process operations (development, tests); they don't survive a restart
+#[derive(Default)]
+pub struct MemoryOperationStore {
+    /// Records with the number of times they were written, their ETag
+    operations: RwLock<HashMap<String, (BulkOperation, u64)>>,
+    inputs: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryOperationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl OperationStore for MemoryOperationStore {
+    async fn put(&self, operation: &BulkOperation) -> Result<(), StorageError> {
+        let mut operations = self.operations.write().await;
+        let path = operation_path(&operation.organization_id, &operation.id);
+        let version = operations.get(&path).map_or(0, |(_, version)| version + 1);
+        operations.insert(path, (operation.clone(), version));
+        Ok(())
+    }
+
+    async fn get(&self, organization_id: &str, operation_id: &str) -> Result<BulkOperation, StorageError> {
+        self.get_tagged(organization_id, operation_id).await.map(|(operation, _)| operation)
+    }
+
+    async fn get_tagged(&self, organization_id: &str, operation_id: &str) -> Result<(BulkOperation, String), StorageError> {
+        self.operations.read().await.get(&operation_path(organization_id, operation_id))
+            .map(|(operation, version)| (operation.clone(), version.to_string()))
+            .ok_or_else(|| StorageError::NotFound(operation_id.to_string()))
+    }
+
+    async fn replace(&self, operation: &BulkOperation, etag: &str) -> Result<Option<String>, StorageError> {
+        let mut operations = self.operations.write().await;
+        let path = operation_path(&operation.organization_id, &operation.id);
+        match operations.get(&path) {
+            Some((_, version)) if version.to_string() == etag => {
+                let version = version + 1;
+                operations.insert(path, (operation.clone(), version));
+                Ok(Some(version.to_string()))
+            }
+            Some(_) => Ok(None),
+            None => Err(StorageError::NotFound(operation.id.clone())),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<BulkOperation>, StorageError> {
+        Ok(self.operations.read().await.values().map(|(operation, _)| operation.clone()).collect())
+    }
+
+    async fn delete(&self, organization_id: &str, operation_id: &str) -> Result<(), StorageError> {
+        self.operations.write().await.remove(&operation_path(organization_id, operation_id));
+        self.inputs.write().await.remove(&input_path(organization_id, operation_id));
+        Ok(())
+    }
+
+    async fn put_input(&self, organization_id: &str, operation_id: &str, body: Vec<u8>) -> Result<(), StorageError> {
+        self.inputs.write().await.insert(input_path(organization_id, operation_id), body);
+        Ok(())
+    }
+
+    async fn get_input(&self, organization_id: &str, operation_id: &str) -> Result<Vec<u8>, StorageError> {
+        self.inputs.read().await.get(&input_path(organization_id, operation_id)).cloned()
+            .ok_or_else(|| StorageError::NotFound(operation_id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Activity, PlannedAction};
+
+    #[test]
+    fn test_slots_go_to_organizations_in_turn() {
+        let limits = BulkLimits { max_running: 2, max_per_org: 1 };
+        let mut queue = Queue::default();
+        for (org, id) in [("org-a", "1"), ("org-a", "2"), ("org-a", "3"), ("org-b", "4")] {
+            queue.push(org, id);
+        }
+        let taken = |org: &str, id: &str| Some((org.to_string(), id.to_string()));
+
+        assert_eq!(queue.take(limits), taken("org-a", "1"));
+        assert_eq!(queue.take(limits), taken("org-b", "4"));
+        assert_eq!(queue.take(limits), None);
+
+        // org-a waits for its own operation, not for org-b's
+        queue.finish("org-b");
+        assert_eq!(queue.take(limits), None);
+        queue.finish("org-a");
+        assert_eq!(queue.take(limits), taken("org-a", "2"));
+    }
+
+    async fn finished(executor: &BulkExecutor, operation_id: &str) -> BulkOperation {
+        for _ in 0..200 {
+            let operation = executor.get("org-1", operation_id).await.unwrap();
+            if !operation.status.is_unfinished() {
+                return operation;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("operation {} did not finish", operation_id);
+    }
+
+    fn activity(i: usize) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": format!("a-{}", i), "title": "Review", "startDate": "2025-03-03T00:00:00Z",
+            "endDate": "2025-03-04T00:00:00Z", "type": "meeting", "color": "#000000",
+            "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+        })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reassign_and_resume_import() {
+        let storage = Storage::in_memory();
+        let store = Arc::new(MemoryOperationStore::new());
+        let executor = Arc::new(BulkExecutor::new(store.clone(), &storage, BulkLimits::default()));
+
+        for i in 0..(STEP_SIZE + 20) {
+            storage.activities.create(activity(i)).await.unwrap();
+        }
+
+        let request: ReassignRequest = serde_json::from_value(serde_json::json!({ "kind": "layer", "from": "layer-1", "to": "layer-2" })).unwrap();
+        let started = executor.start("org-1", "pseudonym-1", OperationInput::Reassign { request }).await.unwrap();
+        assert_eq!(started.total, (STEP_SIZE + 20) as u64);
+        let done = finished(&executor, &started.id).await;
+        assert_eq!(done.status, OperationStatus::Succeeded);
+        assert_eq!(done.completed, done.total);
+        let activities = list_all_activities(storage.activities.as_ref(), "org-1").await.unwrap();
+        assert!(activities.iter().all(|a| a.scope == "layer-2"));
+
+        // An import a recycled host left halfway
+        let archive = Box::new(BackupArchive {
+            format_version: crate::backup::FORMAT_VERSION,
+            organization_id: "org-1".to_string(),
+            exported_at: done.created_at,
+            policy: None,
+            activity_types: vec![],
+            layers: vec![],
+            activities: activities.into_iter().take(3).collect(),
+            shares: vec![],
+            user_settings: vec![],
+        });
+        let input = OperationInput::Import { archive, strategy: ConflictStrategy::Skip };
+        let operation = BulkOperation { id: "op-2".to_string(), status: OperationStatus::Running, completed: 1, total: 3, kind: OperationKind::Import, result: None, ..done.clone() };
+        store.put_input("org-1", "op-2", serde_json::to_vec(&input).unwrap()).await.unwrap();
+        store.put(&operation).await.unwrap();
+
+        assert_eq!(executor.resume().await.unwrap(), 1);
+        let resumed = finished(&executor, "op-2").await;
+        assert_eq!(resumed.status, OperationStatus::Succeeded);
+        assert_eq!(resumed.completed, 3);
+        let summary: ImportSummary = serde_json::from_value(resumed.result.unwrap()).unwrap();
+        assert_eq!(summary.activities.skipped, 2);
+        assert_eq!((resumed.lease_owner, resumed.lease_expires_at), (None, None));
+
+        let rewrite = executor.start("org-1", "pseudonym-1", OperationInput::SchemaRewrite).await.unwrap();
+        let rewrite = finished(&executor, &rewrite.id).await;
+        let summary: SchemaRewriteSummary = serde_json::from_value(rewrite.result.unwrap()).unwrap();
+        assert_eq!(summary.activities, (STEP_SIZE + 20) as u64);
+        assert_eq!(rewrite.completed, rewrite.total);
+    }
+
+    #[tokio::test]
+    async fn test_resume_only_takes_expired_leases() {
+        let storage = Storage::in_memory();
+        let store = Arc::new(MemoryOperationStore::new());
+        let clock = Arc::new(crate::clock::ManualClock::new(Utc::now()));
+        let executor = Arc::new(BulkExecutor::new(store.clone(), &storage, BulkLimits::default()).with_clock(clock.clone()));
+        
+        // An export another instance is running
+        let now = clock.now();
+        let operation = BulkOperation {
+            id: "op-1".to_string(),
+            organization_id: "org-1".to_string(),
+            kind: OperationKind::Export,
+            status: OperationStatus::Running,
+            completed: 0,
+            total: 1,
+            created_by: "pseudonym-1".to_string(),
+            created_at: now,
+            updated_at: now,
+            error: None,
+            result: None,
+            expires_at: None,
+            lease_owner: Some("instance-2".to_string()),
+            lease_expires_at: Some(now + Duration::seconds(DEFAULT_LEASE_SECONDS)),
+        };
+        store.put_input("org-1", "op-1", serde_json::to_vec(&OperationInput::Export).unwrap()).await.unwrap();
+        store.put(&operation).await.unwrap();
+        assert_eq!(executor.resume().await.unwrap(), 0);
+        
+        // Queued twice, it still runs once: the second claim finds it taken
+        clock.advance(Duration::seconds(DEFAULT_LEASE_SECONDS + 1));
+        let (_, etag) = store.get_tagged("org-1", "op-1").await.unwrap();
+        assert_eq!(executor.resume().await.unwrap(), 1);
+        assert_eq!(executor.resume().await.unwrap(), 1);
+        let done = finished(&executor, "op-1").await;
+        assert_eq!(done.status, OperationStatus::Succeeded);
+        assert_eq!(done.lease_owner, None);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let audit = storage.audit.list("org-1", Default::default()).await.unwrap().items;
+        assert_eq!(audit.iter().filter(|e| e.action == AUDIT_ACTION_EXPORTED).count(), 1);
+        
+        // The instance whose lease ran out can't write over the new owner
+        assert_eq!(store.replace(&operation, &etag).await.unwrap(), None);
+        assert_eq!(store.get("org-1", "op-1").await.unwrap().status, OperationStatus::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_export_purge_and_result_expiry() {
+        let storage = Storage::in_memory();
+        let store = Arc::new(MemoryOperationStore::new());
+        let clock = Arc::new(crate::clock::ManualClock::new(Utc::now()));
+        let executor = Arc::new(BulkExecutor::new(store.clone(), &storage, BulkLimits::default()).with_clock(clock.clone()));
+        for i in 0..3 {
+            storage.activities.create(activity(i)).await.unwrap();
+        }
+
+        let export = executor.start("org-1", "pseudonym-1", OperationInput::Export).await.unwrap();
+        let export = finished(&executor, &export.id).await;
+        let archive: BackupArchive = serde_json::from_value(export.result.unwrap()).unwrap();
+        assert_eq!(archive.activities.len(), 3);
+        assert_eq!(export.expires_at, Some(export.updated_at + Duration::days(DEFAULT_RESULT_TTL_DAYS)));
+
+        let mut plan = crate::dry_run::ExecutionPlan::new();
+        plan.add(PlannedEntity::Activity, PlannedAction::Delete, ["a-0", "a-1", "a-2", "a-gone"]);
+        let input = OperationInput::Purge { changes: plan.steps().to_vec(), started_at: clock.now() };
+        let purge = executor.start("org-1", "pseudonym-1", input).await.unwrap();
+        assert_eq!(purge.total, 4);
+        let purge = finished(&executor, &purge.id).await;
+        assert_eq!(purge.status, OperationStatus::Succeeded);
+        let certificate: DeletionCertificate = serde_json::from_value(purge.result.unwrap()).unwrap();
+        assert_eq!((certificate.deleted.activities, certificate.requested_by.as_str()), (4, "pseudonym-1"));
+        assert!(list_all_activities(storage.activities.as_ref(), "org-1").await.unwrap().is_empty());
+        // The export's archive went with the organization
+        assert!(matches!(store.get("org-1", &export.id).await, Err(StorageError::NotFound(_))));
+
+        // Gone once past the TTL, then deleted by the cleanup
+        clock.advance(Duration::days(DEFAULT_RESULT_TTL_DAYS) + Duration::seconds(1));
+        assert!(matches!(executor.get("org-1", &purge.id).await, Err(StorageError::NotFound(_))));
+        assert_eq!(executor.purge_expired().await.unwrap(), 1);
+        assert!(store.list().await.unwrap().is_empty());
+    }
+
+    /// Index remembering the activities pushed into it
+    #[derive(Default)]
+    struct RecordingIndex(std::sync::Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl ActivitySearchIndex for RecordingIndex {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn upsert(&self, activity: &Activity) -> Result<(), crate::search::SearchError> {
+            self.0.lock().unwrap().push(activity.id.clone());
+            Ok(())
+        }
+
+        async fn remove(&self, _organization_id: &str, _activity_id: &str) -> Result<(), crate::search::SearchError> {
+            Ok(())
+        }
+
+        async fn search(&self, _organization_id: &str, _request: &crate::models::SearchActivitiesRequest, _top: usize) -> Result<Vec<String>, crate::search::SearchError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reindex() {
+        let storage = Storage::in_memory();
+        let index = Arc::new(RecordingIndex::default());
+        let executor = Arc::new(BulkExecutor::new(Arc::new(MemoryOperationStore::new()), &storage, BulkLimits::default())
+            .with_search_index(index.clone()));
+        for i in 0..(STEP_SIZE + 5) {
+            storage.activities.create(activity(i)).await.unwrap();
+        }
+
+        let started = executor.start("org-1", "pseudonym-1", OperationInput::Reindex).await.unwrap();
+        assert_eq!(started.kind, OperationKind::Reindex);
+        let done = finished(&executor, &started.id).await;
+        assert_eq!(done.status, OperationStatus::Succeeded);
+        assert_eq!((done.completed, done.total), ((STEP_SIZE + 5) as u64, (STEP_SIZE + 5) as u64));
+        assert_eq!(index.0.lock().unwrap().len(), STEP_SIZE + 5);
+        let audit = storage.audit.list("org-1", Default::default()).await.unwrap();
+        assert!(audit.items.iter().any(|e| e.action == AUDIT_ACTION_REINDEXED));
+    }
+}
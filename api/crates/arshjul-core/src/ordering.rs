@@ -0,0 +1,118 @@
+//! # Activity Draw Order
+//!
+//! Activities of a ring may overlap. So every client stacks them the same
+//! way, public views carry a resolved `zIndex` per activity, from 0 at the
+//! bottom of its ring, and the share preview paints in that order.
+//!
+//! The stacking follows the organization's [`ActivityOrder`], set with
+//! `PUT /api/admin/policy/activity-order`:
+//!
+//! - **Duration** (default) - longer activities below shorter ones, so short
+//!   ones stay visible on top of long ones
+//! - **Priority** - by the activities' `priority`, higher on top, then by duration
+//! - **Type** - holidays at the bottom and deadlines on top, then by duration
+//!
+//! Remaining ties go to the later start on top, then the activity ID, so the
+//! order never depends on storage order. Snapshots and edge copies stack by
+//! duration, as they do not read the organization policy.
+
+use crate::models::{Activity, ActivityOrder, ActivityType};
+use std::cmp::{Ordering, Reverse};
+
+/// Largest priority magnitude accepted on activities
+pub const MAX_PRIORITY: i32 = 1000;
+
+/// Stacking rank of a built-in type, from the bottom
+fn type_rank(activity_type: &ActivityType) -> u8 {
+    match activity_type {
+        ActivityType::Holiday => 0,
+        ActivityType::Other => 1,
+        ActivityType::Planning => 2,
+        ActivityType::Training => 3,
+        ActivityType::Review => 4,
+        ActivityType::Event => 5,
+        ActivityType::Meeting => 6,
+        ActivityType::Deadline => 7,
+    }
+}
+
+/// Bottom-to-top comparison of two activities in the same ring
+pub fn compare(a: &Activity, b: &Activity, order: ActivityOrder) -> Ordering {
+    let duration = |x: &Activity| Reverse(x.end_date - x.start_date);
+    let primary = match order {
+        ActivityOrder::Duration => Ordering::Equal,
+        ActivityOrder::Priority => a.priority.cmp(&b.priority),
+        ActivityOrder::Type => type_rank(&a.activity_type).cmp(&type_rank(&b.activity_type)),
+    };
+    primary
+        .then_with(|| duration(a).cmp(&duration(b)))
+        .then_with(|| a.start_date.cmp(&b.start_date))
+        .then_with(|| a.id.cmp(&b.id))
+}
+
+/// Sort activities by ring, each ring bottom to top
+pub fn sort(activities: &mut [Activity], order: ActivityOrder) {
+    activities.sort_by(|a, b| a.scope.cmp(&b.scope).then_with(|| compare(a, b, order)));
+}
+
+/// Z-index of each activity of a [`sort`]ed slice: its position in its ring
+pub fn z_indices(activities: &[Activity]) -> Vec<u32> {
+    let mut z = 0;
+    activities.iter().enumerate().map(|(i, activity)| {
+        z = if i > 0 && activities[i - 1].scope == activity.scope { z + 1 } else { 0 };
+        z
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn activity(id: &str, activity_type: &str, days: u32, priority: i32, layer: &str) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "title": id, "type": activity_type, "priority": priority,
+            "startDate": "2025-03-01T00:00:00Z",
+            "endDate": format!("2025-03-{:02}T00:00:00Z", days + 1),
+            "color": "#000000", "highlightColor": "#000000",
+            "scope": layer, "scopeId": layer, "organizationId": "org-1",
+        })).unwrap()
+    }
+    
+    fn ids(activities: &[Activity]) -> Vec<&str> {
+        activities.iter().map(|a| a.id.as_str()).collect()
+    }
+    
+    #[test]
+    fn test_orders() {
+        let activities = vec![
+            activity("deadline", "deadline", 1, 0, "layer-1"),
+            activity("holiday", "holiday", 5, 0, "layer-1"),
+            activity("urgent", "meeting", 20, 5, "layer-1"),
+            activity("review", "review", 10, 0, "layer-1"),
+        ];
+        
+        let mut sorted = activities.clone();
+        sort(&mut sorted, ActivityOrder::Duration);
+        assert_eq!(ids(&sorted), ["urgent", "review", "holiday", "deadline"]);
+        
+        sort(&mut sorted, ActivityOrder::Priority);
+        assert_eq!(ids(&sorted), ["review", "holiday", "deadline", "urgent"]);
+        
+        sort(&mut sorted, ActivityOrder::Type);
+        assert_eq!(ids(&sorted), ["holiday", "review", "urgent", "deadline"]);
+    }
+    
+    #[test]
+    fn test_z_indices_per_ring() {
+        let mut activities = vec![
+            activity("b-short", "other", 1, 0, "layer-2"),
+            activity("a-long", "other", 9, 0, "layer-1"),
+            activity("b-long", "other", 9, 0, "layer-2"),
+            activity("a-short", "other", 1, 0, "layer-1"),
+            activity("a-tie", "other", 1, 0, "layer-1"),
+        ];
+        sort(&mut activities, ActivityOrder::Duration);
+        assert_eq!(ids(&activities), ["a-long", "a-short", "a-tie", "b-long", "b-short"]);
+        assert_eq!(z_indices(&activities), [0, 1, 2, 0, 1]);
+    }
+}
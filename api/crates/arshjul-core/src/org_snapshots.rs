@@ -0,0 +1,187 @@
+//! # Organization Snapshots
+//!
+//! Point-in-time copies of an organization, for rolling back accidents such
+//! as a mass delete. Not to be confused with [`crate::snapshots`], the CDN
+//! copies of single shares.
+//!
+//! `POST /api/admin/snapshots` writes the organization's [`BackupArchive`]
+//! (see [`crate::backup`]) to an [`OrganizationSnapshotStore`], at
+//! `{organizationId}/{snapshotId}.json`. `GET /api/admin/snapshots` lists
+//! them, newest first, and `POST /api/admin/snapshots/{id}/restore` writes
+//! one back with [`ConflictStrategy::Overwrite`](crate::models::ConflictStrategy):
+//!
+//! - Entities deleted since come back, from the recycle bin or anew
+//! - Entities changed since get their snapshot version back
+//! - Entities created since stay
+//!
+//! Snapshots hold share keys and user settings: the store must be as private
+//! as the data itself. They are kept until the store's own retention (a
+//! Blob Storage lifecycle rule) removes them, or the organization is purged
+//! (see [`crate::offboarding`]), which deletes its whole prefix.
+
+use crate::models::{BackupArchive, OrganizationSnapshot};
+use crate::storage::StorageError;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Timestamp part of snapshot IDs, which sort by creation time
+const ID_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// A snapshot as the store keeps it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredSnapshot {
+    pub id: String,
+    pub bytes: u64,
+}
+
+/// Blob/object store keeping organization snapshots
+#[async_trait]
+pub trait OrganizationSnapshotStore: Send + Sync {
+    /// Write a snapshot
+    async fn put(&self, organization_id: &str, snapshot_id: &str, body: Vec<u8>) -> Result<(), StorageError>;
+
+    /// Read a snapshot; `NotFound` if the organization has none with this ID
+    async fn get(&self, organization_id: &str, snapshot_id: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Every snapshot of the organization, in any order
+    async fn list(&self, organization_id: &str) -> Result<Vec<StoredSnapshot>, StorageError>;
+
+    /// Delete every snapshot of the organization, returning the number removed
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError>;
+}
+
+/// ID of a snapshot taken at `created_at`; the suffix keeps snapshots of the same second apart
+pub fn snapshot_id(created_at: DateTime<Utc>) -> String {
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", created_at.format(ID_TIME_FORMAT), &suffix[..8])
+}
+
+/// Creation time of a snapshot ID; None for anything [`snapshot_id`] did not make
+pub fn created_at(snapshot_id: &str) -> Option<DateTime<Utc>> {
+    let (time, suffix) = snapshot_id.split_once('-')?;
+    if suffix.len() != 8 || !suffix.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    NaiveDateTime::parse_from_str(time, ID_TIME_FORMAT).ok().map(|t| t.and_utc())
+}
+
+/// Object path of a snapshot
+pub fn snapshot_path(organization_id: &str, snapshot_id: &str) -> String {
+    format!("{}/{}.json", organization_id, snapshot_id)
+}
+
+/// Serialize an archive and write it as a new snapshot
+pub async fn take(store: &dyn OrganizationSnapshotStore, archive: &BackupArchive) -> Result<OrganizationSnapshot, StorageError> {
+    let body = serde_json::to_vec(archive).map_err(|e| StorageError::Serialization(e.to_string()))?;
+    let snapshot = OrganizationSnapshot {
+        id: snapshot_id(archive.exported_at),
+        created_at: archive.exported_at,
+        bytes: body.len() as u64,
+    };
+    store.put(&archive.organization_id, &snapshot.id, body).await?;
+    Ok(snapshot)
+}
+
+/// Read a snapshot back as an archive
+pub async fn load(store: &dyn OrganizationSnapshotStore, organization_id: &str, snapshot_id: &str) -> Result<BackupArchive, StorageError> {
+    // IDs end up in object paths
+    if created_at(snapshot_id).is_none() {
+        return Err(StorageError::NotFound(snapshot_id.to_string()));
+    }
+    let body = store.get(organization_id, snapshot_id).await?;
+    serde_json::from_slice(&body).map_err(|e| StorageError::Serialization(e.to_string()))
+}
+
+/// Snapshots of an organization, newest first
+pub async fn list(store: &dyn OrganizationSnapshotStore, organization_id: &str) -> Result<Vec<OrganizationSnapshot>, StorageError> {
+    let mut snapshots: Vec<OrganizationSnapshot> = store.list(organization_id).await?
+        .into_iter()
+        .filter_map(|s| Some(OrganizationSnapshot { created_at: created_at(&s.id)?, id: s.id, bytes: s.bytes }))
+        .collect();
+    snapshots.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(snapshots)
+}
+
+/// In-process snapshots (development, tests)
+#[derive(Default)]
+pub struct MemoryOrganizationSnapshotStore {
+    snapshots: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryOrganizationSnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl OrganizationSnapshotStore for MemoryOrganizationSnapshotStore {
+    async fn put(&self, organization_id: &str, snapshot_id: &str, body: Vec<u8>) -> Result<(), StorageError> {
+        self.snapshots.write().await.insert(snapshot_path(organization_id, snapshot_id), body);
+        Ok(())
+    }
+
+    async fn get(&self, organization_id: &str, snapshot_id: &str) -> Result<Vec<u8>, StorageError> {
+        self.snapshots.read().await.get(&snapshot_path(organization_id, snapshot_id)).cloned()
+            .ok_or_else(|| StorageError::NotFound(snapshot_id.to_string()))
+    }
+
+    async fn list(&self, organization_id: &str) -> Result<Vec<StoredSnapshot>, StorageError> {
+        let prefix = format!("{}/", organization_id);
+        Ok(self.snapshots.read().await.iter()
+            .filter_map(|(path, body)| Some(StoredSnapshot {
+                id: path.strip_prefix(&prefix)?.strip_suffix(".json")?.to_string(),
+                bytes: body.len() as u64,
+            }))
+            .collect())
+    }
+
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        let prefix = format!("{}/", organization_id);
+        let mut snapshots = self.snapshots.write().await;
+        let before = snapshots.len();
+        snapshots.retain(|path, _| !path.starts_with(&prefix));
+        Ok((before - snapshots.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(organization_id: &str, exported_at: &str) -> BackupArchive {
+        BackupArchive {
+            format_version: crate::backup::FORMAT_VERSION,
+            organization_id: organization_id.to_string(),
+            exported_at: exported_at.parse().unwrap(),
+            policy: None,
+            activity_types: Vec::new(),
+            layers: Vec::new(),
+            activities: Vec::new(),
+            shares: Vec::new(),
+            user_settings: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_take_list_load() {
+        let store = MemoryOrganizationSnapshotStore::new();
+        let first = take(&store, &archive("org-1", "2026-03-01T08:00:00Z")).await.unwrap();
+        let second = take(&store, &archive("org-1", "2026-03-02T08:00:00Z")).await.unwrap();
+        take(&store, &archive("org-2", "2026-03-03T08:00:00Z")).await.unwrap();
+
+        assert!(first.id.starts_with("20260301T080000Z-"));
+        assert_eq!(created_at(&first.id), Some(first.created_at));
+        let listed = list(&store, "org-1").await.unwrap();
+        assert_eq!(listed, vec![second.clone(), first.clone()]);
+
+        let loaded = load(&store, "org-1", &first.id).await.unwrap();
+        assert_eq!(loaded.exported_at, first.created_at);
+
+        // Other organizations' snapshots and made-up IDs are not found
+        assert!(matches!(load(&store, "org-2", &first.id).await, Err(StorageError::NotFound(_))));
+        assert!(matches!(load(&store, "org-1", "../org-2/x").await, Err(StorageError::NotFound(_))));
+    }
+}
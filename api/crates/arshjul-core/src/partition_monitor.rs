@@ -0,0 +1,418 @@
+//! # Partition Monitoring
+//!
+//! Table Storage keeps each organization in one partition per table, served
+//! by one partition server. Lists read the whole partition, so a large
+//! organization gets slower and more throttled as it grows, without any
+//! error to notice. [`PartitionMonitor`] samples the entity count and size
+//! of every organization partition where the backend has a
+//! [`PartitionSampler`] (`PARTITION_MONITOR_INTERVAL_MINUTES`) and
+//!
+//! - keeps the last [`HISTORY_SAMPLES`] samples of each partition, for growth
+//!   per day and the days left until a limit (`GET /api/metrics/partitions`)
+//! - exports the latest sample as gauges in `GET /api/metrics`
+//! - alerts when a partition reaches [`WARN_RATIO`] of a limit (warning) or
+//!   the limit itself (critical), repeating an alert at most once a day
+//!
+//! The limits are no hard limits of the service but the size at which an
+//! organization should move to Cosmos DB or PostgreSQL; tune them with
+//! `PARTITION_ENTITY_LIMIT` and `PARTITION_SIZE_LIMIT_MB`.
+//!
+//! The job is off by default: every instance that runs it scans the
+//! tables, so enable it on one (daily is enough). Samples are saved with the
+//! backend after each run ([`PartitionSampler::save_history`]; Table Storage
+//! keeps them in its `partitionsamples` table) and loaded before the first,
+//! so trends survive host recycles.
+
+use crate::clock::{Clock, SystemClock};
+use crate::models::{PartitionHistory, PartitionLevel, PartitionReport, PartitionSize};
+use crate::slo::AlertError;
+use crate::storage::{PartitionSample, PartitionSampler, StorageError};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Minutes between samples; off unless enabled, as each sample scans every table
+pub const DEFAULT_INTERVAL_MINUTES: u64 = 0;
+
+/// Samples kept per partition
+pub const HISTORY_SAMPLES: usize = 30;
+
+/// Share of a limit that raises a warning
+pub const WARN_RATIO: f64 = 0.8;
+
+/// Hours before an alert for the same partition and level is sent again
+const ALERT_REPEAT_HOURS: i64 = 24;
+
+/// Partition size at which an organization should move to another backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionLimits {
+    pub entities: u64,
+    pub bytes: u64,
+}
+
+impl Default for PartitionLimits {
+    fn default() -> Self {
+        Self { entities: 100_000, bytes: 512 * 1024 * 1024 }
+    }
+}
+
+impl PartitionLimits {
+    fn level(&self, entities: u64, bytes: u64) -> Option<PartitionLevel> {
+        let ratio = (entities as f64 / self.entities as f64).max(bytes as f64 / self.bytes as f64);
+        if ratio >= 1.0 {
+            Some(PartitionLevel::Critical)
+        } else if ratio >= WARN_RATIO {
+            Some(PartitionLevel::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+/// A partition approaching or past its limits
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionAlert {
+    pub level: PartitionLevel,
+    pub partition: PartitionReport,
+    pub entity_limit: u64,
+    pub byte_limit: u64,
+    pub raised_at: DateTime<Utc>,
+}
+
+impl PartitionAlert {
+    /// One-line description for chat and paging tools
+    pub fn summary(&self) -> String {
+        let p = &self.partition;
+        let mut summary = format!(
+            "[{:?}] Organization {} has {} entities ({:.1} MB) in table {}, limits {} entities / {:.0} MB",
+            self.level, p.organization_id, p.entities, mb(p.bytes), p.table, self.entity_limit, mb(self.byte_limit),
+        );
+        if let Some(days) = p.days_to_limit {
+            let _ = write!(summary, ", {:.0} days left at the current growth", days);
+        }
+        summary
+    }
+}
+
+fn mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Destination for partition alerts
+#[async_trait]
+pub trait PartitionAlertSink: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, alert: &PartitionAlert) -> Result<(), AlertError>;
+}
+
+/// Samples partition sizes and alerts on the large ones
+pub struct PartitionMonitor {
+    sampler: Option<Arc<dyn PartitionSampler>>,
+    limits: PartitionLimits,
+    sink: Option<Arc<dyn PartitionAlertSink>>,
+    /// By table and organization, oldest first
+    history: RwLock<HashMap<(String, String), VecDeque<PartitionSize>>>,
+    /// Whether the saved samples were loaded
+    loaded: AtomicBool,
+    alerted: RwLock<HashMap<(String, String, PartitionLevel), DateTime<Utc>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl PartitionMonitor {
+    /// Monitor through `sampler`; without one there is nothing to sample
+    pub fn new(sampler: Option<Arc<dyn PartitionSampler>>, limits: PartitionLimits) -> Self {
+        Self {
+            sampler,
+            limits,
+            sink: None,
+            history: RwLock::new(HashMap::new()),
+            loaded: AtomicBool::new(false),
+            alerted: RwLock::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Send alerts to `sink` too, not only to the log
+    pub fn with_alert_sink(mut self, sink: Arc<dyn PartitionAlertSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Whether there is a backend to sample
+    pub fn is_enabled(&self) -> bool {
+        self.sampler.is_some()
+    }
+
+    /// Sample every partition once and raise due alerts; returns the alerts raised
+    pub async fn run_once(&self) -> Result<Vec<PartitionAlert>, StorageError> {
+        let Some(ref sampler) = self.sampler else {
+            return Ok(Vec::new());
+        };
+        if !self.loaded.swap(true, Ordering::SeqCst) {
+            match sampler.load_history().await {
+                Ok(saved) => self.restore(saved),
+                Err(e) => tracing::warn!(error = %e, "Failed to load partition samples"),
+            }
+        }
+        let samples = sampler.sample_partitions().await?;
+        let history = self.record(samples);
+        if let Err(e) = sampler.save_history(&history).await {
+            tracing::warn!(error = %e, "Failed to save partition samples");
+        }
+
+        let mut raised = Vec::new();
+        for alert in self.due_alerts() {
+            tracing::warn!("{}", alert.summary());
+            let delivered = match self.sink {
+                Some(ref sink) => match sink.send(&alert).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::warn!("Partition alert via {} failed: {}", sink.name(), e);
+                        false
+                    }
+                },
+                None => true,
+            };
+            if delivered {
+                self.mark_alerted(&alert);
+            }
+            raised.push(alert);
+        }
+        Ok(raised)
+    }
+
+    /// Add a sample of every partition; partitions missing from it are forgotten.
+    /// Returns the samples now kept
+    fn record(&self, samples: Vec<PartitionSample>) -> Vec<PartitionHistory> {
+        let at = self.clock.now();
+        let mut history = self.history.write().unwrap_or_else(|e| e.into_inner());
+        let mut current = HashMap::with_capacity(samples.len());
+        for s in samples {
+            let mut kept = history.remove(&(s.table.clone(), s.organization_id.clone())).unwrap_or_default();
+            if kept.len() == HISTORY_SAMPLES {
+                kept.pop_front();
+            }
+            kept.push_back(PartitionSize { at, entities: s.entities, bytes: s.bytes });
+            current.insert((s.table, s.organization_id), kept);
+        }
+        *history = current;
+        history.iter()
+            .map(|((table, organization_id), samples)| PartitionHistory {
+                table: table.clone(),
+                organization_id: organization_id.clone(),
+                samples: samples.iter().copied().collect(),
+            })
+            .collect()
+    }
+
+    /// Take up saved samples, in front of any taken since
+    fn restore(&self, saved: Vec<PartitionHistory>) {
+        let mut history = self.history.write().unwrap_or_else(|e| e.into_inner());
+        for partition in saved {
+            let kept = history.entry((partition.table, partition.organization_id)).or_default();
+            let newer = std::mem::take(kept);
+            kept.extend(partition.samples.into_iter().filter(|s| newer.front().is_none_or(|n| s.at < n.at)));
+            kept.extend(newer);
+            while kept.len() > HISTORY_SAMPLES {
+                kept.pop_front();
+            }
+        }
+    }
+
+    /// Latest size and growth of every partition, largest first
+    pub fn report(&self) -> Vec<PartitionReport> {
+        let history = self.history.read().unwrap_or_else(|e| e.into_inner());
+        let mut reports: Vec<PartitionReport> = history.iter()
+            .filter_map(|((table, organization_id), samples)| self.partition_report(table, organization_id, samples))
+            .collect();
+        reports.sort_by(|a, b| b.entities.cmp(&a.entities)
+            .then_with(|| a.table.cmp(&b.table))
+            .then_with(|| a.organization_id.cmp(&b.organization_id)));
+        reports
+    }
+
+    fn partition_report(&self, table: &str, organization_id: &str, samples: &VecDeque<PartitionSize>) -> Option<PartitionReport> {
+        let (first, last) = (samples.front()?, samples.back()?);
+        let days = (last.at - first.at).num_seconds() as f64 / 86_400.0;
+        let per_day = |from: u64, to: u64| (days > 0.0).then(|| (to as f64 - from as f64) / days);
+        let entities_per_day = per_day(first.entities, last.entities);
+        let bytes_per_day = per_day(first.bytes, last.bytes);
+
+        let days_left = |value: u64, limit: u64, rate: Option<f64>| match rate {
+            Some(rate) if rate > 0.0 && value < limit => Some((limit - value) as f64 / rate),
+            _ => None,
+        };
+        let days_to_limit = [
+            days_left(last.entities, self.limits.entities, entities_per_day),
+            days_left(last.bytes, self.limits.bytes, bytes_per_day),
+        ].into_iter().flatten().reduce(f64::min);
+
+        Some(PartitionReport {
+            table: table.to_string(),
+            organization_id: organization_id.to_string(),
+            entities: last.entities,
+            bytes: last.bytes,
+            sampled_at: last.at,
+            entities_per_day,
+            days_to_limit,
+            level: self.limits.level(last.entities, last.bytes),
+        })
+    }
+
+    /// Partitions at a level that was not alerted in the last day
+    fn due_alerts(&self) -> Vec<PartitionAlert> {
+        let now = self.clock.now();
+        let alerted = self.alerted.read().unwrap_or_else(|e| e.into_inner());
+        self.report().into_iter()
+            .filter_map(|partition| {
+                let level = partition.level?;
+                let key = (partition.table.clone(), partition.organization_id.clone(), level);
+                let recent = alerted.get(&key).is_some_and(|at| now - *at < Duration::hours(ALERT_REPEAT_HOURS));
+                (!recent).then_some(PartitionAlert {
+                    level,
+                    partition,
+                    entity_limit: self.limits.entities,
+                    byte_limit: self.limits.bytes,
+                    raised_at: now,
+                })
+            })
+            .collect()
+    }
+
+    fn mark_alerted(&self, alert: &PartitionAlert) {
+        let key = (alert.partition.table.clone(), alert.partition.organization_id.clone(), alert.level);
+        self.alerted.write().unwrap_or_else(|e| e.into_inner()).insert(key, alert.raised_at);
+    }
+
+    /// Sample every `interval` until the task is dropped
+    pub async fn run(self: Arc<Self>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.run_once().await {
+                Ok(alerts) => tracing::debug!(alerts = alerts.len(), "Sampled partition sizes"),
+                Err(e) => tracing::warn!(error = %e, "Partition sampling failed"),
+            }
+        }
+    }
+
+    /// Latest sample of every partition in the Prometheus text format; empty
+    /// when nothing was sampled
+    pub fn render_prometheus(&self) -> String {
+        let reports = self.report();
+        let mut out = String::new();
+        if reports.is_empty() {
+            return out;
+        }
+
+        let families: [(&str, &str, u64); 2] = [
+            ("arshjul_partition_entities", "Entities in an organization's partition of a table", self.limits.entities),
+            ("arshjul_partition_bytes", "Approximate size of an organization's partition of a table", self.limits.bytes),
+        ];
+        for (family, help, limit) in families {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", family, help, family);
+            for r in &reports {
+                let value = if family == "arshjul_partition_entities" { r.entities } else { r.bytes };
+                let _ = writeln!(out, "{}{{table=\"{}\",organization=\"{}\"}} {}", family, r.table, r.organization_id, value);
+            }
+            let _ = writeln!(out, "# HELP {}_limit Size at which an organization should move to another backend\n# TYPE {}_limit gauge\n{}_limit {}", family, family, family, limit);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeSampler(Mutex<Vec<PartitionSample>>, Mutex<Vec<PartitionHistory>>);
+
+    impl FakeSampler {
+        fn set(&self, activities: u64) {
+            *self.0.lock().unwrap() = vec![
+                PartitionSample { table: "activities".to_string(), organization_id: "org-1".to_string(), entities: activities, bytes: activities * 1000 },
+                PartitionSample { table: "shares".to_string(), organization_id: "org-1".to_string(), entities: 10, bytes: 10_000 },
+            ];
+        }
+    }
+
+    #[async_trait]
+    impl PartitionSampler for FakeSampler {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        async fn sample_partitions(&self) -> Result<Vec<PartitionSample>, StorageError> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        async fn load_history(&self) -> Result<Vec<PartitionHistory>, StorageError> {
+            Ok(self.1.lock().unwrap().clone())
+        }
+
+        async fn save_history(&self, history: &[PartitionHistory]) -> Result<(), StorageError> {
+            *self.1.lock().unwrap() = history.to_vec();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trends_and_alerts() {
+        let clock = Arc::new(ManualClock::new("2026-01-01T00:00:00Z".parse().unwrap()));
+        let sampler = Arc::new(FakeSampler::default());
+        let monitor = PartitionMonitor::new(Some(sampler.clone()), PartitionLimits { entities: 1_000, bytes: 10_000_000 })
+            .with_clock(clock.clone());
+
+        sampler.set(700);
+        assert!(monitor.run_once().await.unwrap().is_empty());
+
+        // 100 entities a day: a warning with 2 days left
+        clock.advance(Duration::days(1));
+        sampler.set(800);
+        let alerts = monitor.run_once().await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].level, PartitionLevel::Warning);
+        assert_eq!(alerts[0].partition.table, "activities");
+        assert_eq!(alerts[0].partition.entities_per_day, Some(100.0));
+        assert_eq!(alerts[0].partition.days_to_limit, Some(2.0));
+
+        // Not repeated within a day, but a new level alerts at once
+        clock.advance(Duration::hours(1));
+        assert!(monitor.run_once().await.unwrap().is_empty());
+        sampler.set(1_000);
+        assert_eq!(monitor.run_once().await.unwrap()[0].level, PartitionLevel::Critical);
+
+        let report = monitor.report();
+        assert_eq!(report.iter().map(|r| (r.table.as_str(), r.entities)).collect::<Vec<_>>(), [("activities", 1_000), ("shares", 10)]);
+        assert_eq!(report[1].level, None);
+        let metrics = monitor.render_prometheus();
+        assert!(metrics.contains("arshjul_partition_entities{table=\"activities\",organization=\"org-1\"} 1000\n"));
+        assert!(metrics.contains("arshjul_partition_entities_limit 1000\n"));
+
+        // A restarted instance picks up the trend from the saved samples
+        let restarted = PartitionMonitor::new(Some(sampler.clone()), PartitionLimits { entities: 1_000, bytes: 10_000_000 })
+            .with_clock(clock.clone());
+        clock.advance(Duration::days(1));
+        sampler.set(1_100);
+        restarted.run_once().await.unwrap();
+        let report = restarted.report();
+        assert_eq!(report[0].entities, 1_100);
+        assert!(report[0].entities_per_day.is_some());
+        assert_eq!(sampler.1.lock().unwrap().iter().map(|h| h.samples.len()).max(), Some(5));
+    }
+}
@@ -0,0 +1,90 @@
+//! # Period Lock
+//!
+//! Organizations can freeze past periods so the historical record behind
+//! year-end reports stays put. Activities that ended before the cutoff are
+//! read-only for everyone but admins: creating them, updating them (moving
+//! one into or out of the locked period included) and deleting them is
+//! rejected with 403.
+//!
+//! | Rule | Cutoff |
+//! |------|--------|
+//! | `before` | a fixed date, e.g. the start of the current financial year |
+//! | `quarters` | start of the quarter `n` quarters back; `1` locks everything before last quarter |
+//!
+//! The rule is part of the [`OrganizationPolicy`](crate::models::OrganizationPolicy),
+//! managed with `PUT /api/admin/policy/period-lock`.
+
+use crate::models::PeriodLockRule;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use thiserror::Error;
+
+/// Longest rolling lock, in quarters
+pub const MAX_LOCK_QUARTERS: u32 = 8;
+
+/// Invalid period lock rules
+#[derive(Debug, Error, PartialEq)]
+pub enum PeriodLockError {
+    #[error("Quarters must be between 1 and {0}")]
+    InvalidQuarters(u32),
+    
+    #[error("Lock date must not be in the future")]
+    FutureDate,
+}
+
+/// Check a rule before it is saved
+pub fn validate(rule: &PeriodLockRule, today: NaiveDate) -> Result<(), PeriodLockError> {
+    match *rule {
+        PeriodLockRule::Before { date } if date > today => Err(PeriodLockError::FutureDate),
+        PeriodLockRule::Quarters { quarters } if !(1..=MAX_LOCK_QUARTERS).contains(&quarters) => {
+            Err(PeriodLockError::InvalidQuarters(MAX_LOCK_QUARTERS))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// First day that is still open
+pub fn cutoff(rule: &PeriodLockRule, today: NaiveDate) -> NaiveDate {
+    match *rule {
+        PeriodLockRule::Before { date } => date,
+        PeriodLockRule::Quarters { quarters } => {
+            let quarter_start = today.year() * 12 + (today.month0() / 3 * 3) as i32;
+            let month = quarter_start - 3 * quarters as i32;
+            NaiveDate::from_ymd_opt(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, 1).unwrap_or(NaiveDate::MIN)
+        }
+    }
+}
+
+/// Whether an activity ending at `end_date` lies in the locked period
+pub fn is_locked(end_date: DateTime<Utc>, cutoff: NaiveDate) -> bool {
+    end_date.date_naive() < cutoff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+    
+    #[test]
+    fn test_cutoff() {
+        let today = date("2025-05-10");
+        assert_eq!(cutoff(&PeriodLockRule::Quarters { quarters: 1 }, today), date("2025-01-01"));
+        assert_eq!(cutoff(&PeriodLockRule::Quarters { quarters: 2 }, today), date("2024-10-01"));
+        assert_eq!(cutoff(&PeriodLockRule::Quarters { quarters: 1 }, date("2025-12-31")), date("2025-07-01"));
+        assert_eq!(cutoff(&PeriodLockRule::Before { date: date("2025-01-01") }, today), date("2025-01-01"));
+        
+        let cutoff = date("2025-01-01");
+        assert!(is_locked("2024-12-31T23:00:00Z".parse().unwrap(), cutoff));
+        assert!(!is_locked("2025-01-01T00:00:00Z".parse().unwrap(), cutoff));
+    }
+    
+    #[test]
+    fn test_validate() {
+        let today = date("2025-05-10");
+        assert_eq!(validate(&PeriodLockRule::Quarters { quarters: 0 }, today), Err(PeriodLockError::InvalidQuarters(MAX_LOCK_QUARTERS)));
+        assert_eq!(validate(&PeriodLockRule::Before { date: date("2025-06-01") }, today), Err(PeriodLockError::FutureDate));
+        assert_eq!(validate(&PeriodLockRule::Quarters { quarters: 4 }, today), Ok(()));
+    }
+}
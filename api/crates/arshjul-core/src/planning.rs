@@ -0,0 +1,194 @@
+//! # Planning Analytics
+//!
+//! Planning discipline metrics for admins (`GET /api/admin/analytics/planning`):
+//!
+//! - **Lead time** - days between entering an activity and its start;
+//!   negative when it was entered after it began
+//! - **Edit churn** - edits per activity on each layer (`editCount`)
+//! - **Cancellations** - activities deleted before they started, recorded in
+//!   the audit log as [`AUDIT_ACTION_ACTIVITY_CANCELLED`]
+//!
+//! Activities and cancellations count towards the year they start in. Weekly
+//! counts use the organization's week numbering (see [`crate::calendar`]).
+
+use crate::calendar;
+use crate::models::{Activity, AuditEntry, CalendarSettings, Layer, LayerPlanning, LeadTimeBucket, LeadTimeSummary, MonthlyPlanning, PlanningAnalytics, WeeklyPlanning};
+use chrono::{DateTime, Datelike, Utc};
+use std::collections::BTreeMap;
+
+/// Audit action recorded when an activity is deleted before it starts
+pub const AUDIT_ACTION_ACTIVITY_CANCELLED: &str = "activity.cancelled";
+
+/// Lead time ranges (label, upper bound in days, exclusive)
+const LEAD_TIME_BUCKETS: [(&str, f64); 5] = [
+    ("after start", 0.0),
+    ("under 1 week", 7.0),
+    ("1-4 weeks", 28.0),
+    ("1-3 months", 91.0),
+    ("3+ months", f64::INFINITY),
+];
+
+/// A cancelled activity as recorded in the audit log
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cancellation {
+    pub layer_id: String,
+    pub start_date: DateTime<Utc>,
+}
+
+impl Cancellation {
+    /// Audit details for a cancelled activity
+    pub fn details(activity: &Activity) -> serde_json::Value {
+        serde_json::json!({
+            "layerId": activity.scope,
+            "startDate": activity.start_date,
+            "createdAt": activity.created_at,
+        })
+    }
+    
+    /// Read back from an [`AUDIT_ACTION_ACTIVITY_CANCELLED`] entry
+    pub fn from_entry(entry: &AuditEntry) -> Option<Self> {
+        if entry.action != AUDIT_ACTION_ACTIVITY_CANCELLED {
+            return None;
+        }
+        let details = entry.details.as_ref()?;
+        Some(Self {
+            layer_id: details.get("layerId")?.as_str()?.to_string(),
+            start_date: serde_json::from_value(details.get("startDate")?.clone()).ok()?,
+        })
+    }
+}
+
+/// Days from creation to start
+fn lead_days(activity: &Activity) -> Option<f64> {
+    activity.created_at.map(|created| (activity.start_date - created).num_minutes() as f64 / 1440.0)
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
+
+fn ratio(part: u64, whole: u64) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+/// Metrics for activities and cancellations starting in `year`
+pub fn compute(year: i32, activities: &[Activity], layers: &[Layer], cancellations: &[Cancellation], calendar_settings: &CalendarSettings) -> PlanningAnalytics {
+    let activities: Vec<&Activity> = activities.iter().filter(|a| a.start_date.year() == year).collect();
+    let cancellations: Vec<&Cancellation> = cancellations.iter().filter(|c| c.start_date.year() == year).collect();
+    
+    let lead_times: Vec<f64> = activities.iter().filter_map(|a| lead_days(a)).collect();
+    let buckets = LEAD_TIME_BUCKETS.iter().enumerate().map(|(i, (label, upper))| {
+        let lower = if i == 0 { f64::NEG_INFINITY } else { LEAD_TIME_BUCKETS[i - 1].1 };
+        LeadTimeBucket {
+            label: label.to_string(),
+            count: lead_times.iter().filter(|d| **d >= lower && **d < *upper).count() as u64,
+        }
+    }).collect();
+    let lead_time = LeadTimeSummary {
+        measured: lead_times.len() as u64,
+        median_days: median(lead_times.clone()),
+        average_days: (!lead_times.is_empty()).then(|| lead_times.iter().sum::<f64>() / lead_times.len() as f64),
+        created_after_start: lead_times.iter().filter(|d| **d < 0.0).count() as u64,
+        buckets,
+    };
+    
+    let months = (1..=12).map(|month| {
+        let in_month: Vec<&&Activity> = activities.iter().filter(|a| a.start_date.month() == month).collect();
+        MonthlyPlanning {
+            month,
+            activities: in_month.len() as u64,
+            median_lead_days: median(in_month.iter().filter_map(|a| lead_days(a)).collect()),
+        }
+    }).collect();
+    
+    // Every week overlapping the year, so charts have no gaps
+    let mut per_week: BTreeMap<_, u64> = calendar::weeks(year, calendar_settings).into_iter().map(|w| (w.number, 0)).collect();
+    for activity in &activities {
+        *per_week.entry(calendar::week_number(activity.start_date.date_naive(), calendar_settings)).or_default() += 1;
+    }
+    let weeks = per_week.into_iter().map(|(week, count)| WeeklyPlanning { week, activities: count }).collect();
+    
+    // Layers with activities or cancellations, in ring order; deleted layers last
+    let mut per_layer: BTreeMap<&str, (u64, u64, u64)> = BTreeMap::new();
+    for activity in &activities {
+        let entry = per_layer.entry(activity.scope.as_str()).or_default();
+        entry.0 += 1;
+        entry.1 += activity.edit_count as u64;
+    }
+    for cancellation in &cancellations {
+        per_layer.entry(cancellation.layer_id.as_str()).or_default().2 += 1;
+    }
+    let mut layer_stats: Vec<LayerPlanning> = per_layer.into_iter().map(|(layer_id, (count, edits, cancelled))| LayerPlanning {
+        layer_id: layer_id.to_string(),
+        layer_name: layers.iter().find(|l| l.id == layer_id).map(|l| l.name.clone()),
+        activities: count,
+        edits,
+        edits_per_activity: ratio(edits, count),
+        cancelled,
+        cancellation_rate: ratio(cancelled, count + cancelled),
+    }).collect();
+    let ring = |id: &str| layers.iter().find(|l| l.id == id).map_or(i32::MAX, |l| l.ring_index);
+    layer_stats.sort_by_key(|l| ring(&l.layer_id));
+    
+    let activity_count = activities.len() as u64;
+    let cancelled_count = cancellations.len() as u64;
+    PlanningAnalytics {
+        year,
+        activity_count,
+        cancelled_count,
+        cancellation_rate: ratio(cancelled_count, activity_count + cancelled_count),
+        lead_time,
+        months,
+        weeks,
+        layers: layer_stats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    
+    fn activity(layer: &str, start: &str, lead_days: i64, edit_count: u32) -> Activity {
+        let start_date = DateTime::parse_from_rfc3339(start).unwrap().to_utc();
+        serde_json::from_value(serde_json::json!({
+            "id": format!("{}-{}", layer, start), "title": "Planned", "startDate": start_date, "endDate": start_date,
+            "type": "meeting", "color": "#000000", "highlightColor": "#000000", "scope": layer, "scopeId": layer,
+            "organizationId": "org-1", "createdAt": start_date - Duration::days(lead_days), "editCount": edit_count,
+        })).unwrap()
+    }
+    
+    #[test]
+    fn test_lead_time_churn_and_cancellations() {
+        let activities = vec![
+            activity("layer-1", "2025-03-10T09:00:00Z", 60, 4),
+            activity("layer-1", "2025-03-20T09:00:00Z", 3, 0),
+            activity("layer-2", "2025-06-01T09:00:00Z", -2, 1),
+            activity("layer-2", "2024-12-01T09:00:00Z", 10, 9),
+        ];
+        let cancelled = activity("layer-2", "2025-09-01T09:00:00Z", 30, 0);
+        let entry = AuditEntry::new("org-1", AUDIT_ACTION_ACTIVITY_CANCELLED, None, Some(&cancelled.id))
+            .with_details(Cancellation::details(&cancelled));
+        let cancellations: Vec<Cancellation> = Cancellation::from_entry(&entry).into_iter().collect();
+        
+        let analytics = compute(2025, &activities, &[], &cancellations, &CalendarSettings::default());
+        assert_eq!(analytics.activity_count, 3);
+        assert_eq!(analytics.cancelled_count, 1);
+        assert_eq!(analytics.cancellation_rate, Some(0.25));
+        assert_eq!(analytics.lead_time.median_days, Some(3.0));
+        assert_eq!(analytics.lead_time.created_after_start, 1);
+        assert_eq!(analytics.lead_time.buckets.iter().map(|b| b.count).collect::<Vec<_>>(), [1, 1, 0, 1, 0]);
+        assert_eq!(analytics.months[2].activities, 2);
+        assert_eq!(analytics.weeks.len(), 53);
+        assert_eq!(analytics.weeks.iter().filter(|w| w.activities > 0).map(|w| w.week.week).collect::<Vec<_>>(), [11, 12, 22]);
+        
+        let layer_2 = analytics.layers.iter().find(|l| l.layer_id == "layer-2").unwrap();
+        assert_eq!((layer_2.activities, layer_2.edits, layer_2.cancelled), (1, 1, 1));
+        assert_eq!(analytics.layers[0].edits_per_activity, Some(2.0));
+    }
+}
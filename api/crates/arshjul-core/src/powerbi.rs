@@ -0,0 +1,207 @@
+//! # Power BI Dataset
+//!
+//! Flattened, paginated tables behind `GET /api/admin/analytics/powerbi`, so
+//! analysts can build dashboards without access to storage:
+//!
+//! | Table | Kind | Row key | Incremental refresh column |
+//! |-------|------|---------|----------------------------|
+//! | `activities` | fact | `activityId` | `modifiedAt` |
+//! | `layers` | dimension | `layerId` | - |
+//! | `activityTypes` | dimension | `activityType` | - |
+//! | `shareViews` | fact | `shareId`, `date`, `country` | `date` |
+//!
+//! Facts are filtered by [`RefreshRange`], which maps to the `RangeStart` and
+//! `RangeEnd` parameters of a Power BI incremental refresh policy. Pages are
+//! in row key order and the continuation token is the last key of the
+//! previous page, like the Table Storage backends.
+
+use crate::models::{Activity, ActivityFact, ActivityTypeConfig, ActivityTypeDimension, DailyShareTraffic, Layer, LayerDimension, ShareViewFact};
+use crate::storage;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use thiserror::Error;
+
+/// Rows per page unless the client asks for fewer or more
+pub const DEFAULT_PAGE_SIZE: u32 = 1000;
+
+/// Upper bound for `pageSize`
+pub const MAX_PAGE_SIZE: u32 = 5000;
+
+/// Share view window when no range is given
+pub const DEFAULT_SHARE_VIEW_DAYS: i64 = 90;
+
+/// Longest share view window per request (one history read per share)
+pub const MAX_SHARE_VIEW_DAYS: i64 = 400;
+
+/// Invalid dataset query
+#[derive(Debug, Error, PartialEq)]
+pub enum PowerBiError {
+    #[error("rangeStart must be before rangeEnd")]
+    EmptyRange,
+    
+    #[error("Share views can be requested for at most {0} days at a time")]
+    RangeTooLong(i64),
+}
+
+/// Half-open `start <= x < end` window; open ends are unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RefreshRange {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl RefreshRange {
+    pub fn new(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Result<Self, PowerBiError> {
+        match (start, end) {
+            (Some(start), Some(end)) if start >= end => Err(PowerBiError::EmptyRange),
+            _ => Ok(Self { start, end }),
+        }
+    }
+    
+    /// Whether a timestamp falls in the window; unknown timestamps only match an unbounded window
+    pub fn contains(&self, at: Option<DateTime<Utc>>) -> bool {
+        match at {
+            Some(at) => self.start.is_none_or(|s| at >= s) && self.end.is_none_or(|e| at < e),
+            None => self.start.is_none() && self.end.is_none(),
+        }
+    }
+    
+    /// Inclusive UTC days covered, defaulting to the last [`DEFAULT_SHARE_VIEW_DAYS`] up to today
+    pub fn days(&self, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), PowerBiError> {
+        let to = self.end.map_or(today, |e| (e - Duration::nanoseconds(1)).date_naive());
+        let from = self.start.map_or(to - Duration::days(DEFAULT_SHARE_VIEW_DAYS - 1), |s| s.date_naive());
+        if (to - from).num_days() >= MAX_SHARE_VIEW_DAYS {
+            return Err(PowerBiError::RangeTooLong(MAX_SHARE_VIEW_DAYS));
+        }
+        Ok((from, to))
+    }
+}
+
+/// Serialized name of a unit enum variant
+fn variant_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// Activities modified within the range
+pub fn activity_facts(activities: &[Activity], range: &RefreshRange) -> Vec<ActivityFact> {
+    activities.iter()
+        .map(|a| (a, a.updated_at.or(a.created_at)))
+        .filter(|(_, modified_at)| range.contains(*modified_at))
+        .map(|(a, modified_at)| ActivityFact {
+            activity_id: a.id.clone(),
+            title: a.title.clone(),
+            layer_id: a.scope.clone(),
+            activity_type: variant_name(&a.activity_type),
+            start_date: a.start_date,
+            end_date: a.end_date,
+            start_day: a.start_date.date_naive(),
+            duration_days: (a.end_date.date_naive() - a.start_date.date_naive()).num_days() + 1,
+            tags: a.tags.join(";"),
+            approval_status: a.approval_status,
+            edit_count: a.edit_count,
+            created_at: a.created_at,
+            modified_at,
+        })
+        .collect()
+}
+
+pub fn layer_dimensions(layers: &[Layer]) -> Vec<LayerDimension> {
+    layers.iter().map(|l| LayerDimension {
+        layer_id: l.id.clone(),
+        name: l.name.clone(),
+        layer_type: l.layer_type.clone(),
+        color: l.color.clone(),
+        ring_index: l.ring_index,
+        is_visible: l.is_visible,
+        requires_approval: l.requires_approval,
+    }).collect()
+}
+
+pub fn activity_type_dimensions(types: &[ActivityTypeConfig]) -> Vec<ActivityTypeDimension> {
+    types.iter().map(|t| ActivityTypeDimension {
+        activity_type: t.key.clone(),
+        label: t.label.clone(),
+        color: t.color.clone(),
+        is_system: t.is_system,
+        sort_order: t.sort_order,
+    }).collect()
+}
+
+/// One row per country and day, plus one for views without a known country
+pub fn share_view_facts(share_id: &str, share_name: Option<&str>, history: &[DailyShareTraffic]) -> Vec<ShareViewFact> {
+    let fact = |day: &DailyShareTraffic, country: Option<&String>, views: u64| ShareViewFact {
+        share_id: share_id.to_string(),
+        share_name: share_name.map(str::to_string),
+        date: day.date,
+        country: country.cloned(),
+        views,
+    };
+    
+    let mut facts = Vec::new();
+    for day in history {
+        let located: u64 = day.countries.values().sum();
+        facts.extend(day.countries.iter().map(|(country, views)| fact(day, Some(country), *views)));
+        if day.views > located {
+            facts.push(fact(day, None, day.views - located));
+        }
+    }
+    facts
+}
+
+/// Row key of share view facts
+pub fn share_view_key(fact: &ShareViewFact) -> String {
+    format!("{}|{}|{}", fact.share_id, fact.date, fact.country.as_deref().unwrap_or(""))
+}
+
+/// The page after `continuation_token` in row key order, and the token for the next one
+pub fn paginate<T>(rows: Vec<T>, key: impl Fn(&T) -> String, page_size: Option<u32>, continuation_token: Option<&str>) -> (Vec<T>, Option<String>) {
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+    storage::paginate(rows, key, page_size, continuation_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().to_utc()
+    }
+    
+    #[test]
+    fn test_refresh_range() {
+        let range = RefreshRange::new(Some(at("2025-03-01T00:00:00Z")), Some(at("2025-04-01T00:00:00Z"))).unwrap();
+        assert!(range.contains(Some(at("2025-03-01T00:00:00Z"))));
+        assert!(!range.contains(Some(at("2025-04-01T00:00:00Z"))));
+        assert!(!range.contains(None));
+        assert!(RefreshRange::default().contains(None));
+        
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let (from, to) = range.days(today).unwrap();
+        assert_eq!((from.to_string(), to.to_string()), ("2025-03-01".to_string(), "2025-03-31".to_string()));
+        assert_eq!(RefreshRange::default().days(today).unwrap().0, today - Duration::days(89));
+        
+        assert_eq!(RefreshRange::new(range.end, range.start), Err(PowerBiError::EmptyRange));
+        let year = RefreshRange::new(Some(at("2024-01-01T00:00:00Z")), Some(at("2025-06-01T00:00:00Z"))).unwrap();
+        assert_eq!(year.days(today), Err(PowerBiError::RangeTooLong(MAX_SHARE_VIEW_DAYS)));
+    }
+    
+    #[test]
+    fn test_share_views_and_pagination() {
+        let day = DailyShareTraffic {
+            organization_id: "org-1".to_string(),
+            share_id: "share-1".to_string(),
+            date: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+            views: 10,
+            countries: HashMap::from([("NO".to_string(), 6), ("SE".to_string(), 1)]),
+        };
+        let facts = share_view_facts("share-1", Some("Board calendar"), &[day]);
+        assert_eq!(facts.iter().map(|f| f.views).sum::<u64>(), 10);
+        
+        let (first, token) = paginate(facts.clone(), share_view_key, Some(2), None);
+        assert_eq!(first.iter().map(|f| f.country.as_deref()).collect::<Vec<_>>(), [None, Some("NO")]);
+        let (rest, token) = paginate(facts, share_view_key, Some(2), token.as_deref());
+        assert_eq!(rest[0].country.as_deref(), Some("SE"));
+        assert_eq!(token, None);
+    }
+}
@@ -0,0 +1,263 @@
+//! # Share Previews
+//!
+//! Teams unfurls pasted links with its own fetcher, which has no share key.
+//! Public shares therefore get a preview image URL that needs none:
+//! `GET /api/public/s/{shortCode}/preview.png?sig=...`, issued with the share
+//! links on creation and key regeneration.
+//!
+//! The image is a [`PREVIEW_SIZE`] pixel PNG of the wheel: approved
+//! activities as colored arcs on one ring per shared layer, without titles,
+//! descriptions or any other text. Signatures are:
+//!
+//! - **Signed** - with the [`KeyRing`] (purpose `preview`) over the short
+//!   code, the share key and the expiry, so regenerating the key revokes them
+//! - **Short-lived** - valid for [`PREVIEW_LINK_TTL_HOURS`], and never past
+//!   the share's expiry
+
+use crate::models::{Activity, ActivityOrder, Layer, ShareLink};
+use crate::ordering;
+use crate::signing_keys::{KeyRing, SigningKeyError};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::f64::consts::PI;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// [`KeyRing`] purpose of preview signatures
+const SIGNING_PURPOSE: &str = "preview";
+
+/// How long a preview link works after it is issued
+pub const PREVIEW_LINK_TTL_HOURS: i64 = 24;
+
+/// Width and height of preview images in pixels
+pub const PREVIEW_SIZE: u32 = 160;
+
+/// Preview link errors
+#[derive(Debug, Error, PartialEq)]
+pub enum PreviewLinkError {
+    #[error("Invalid preview link")]
+    Invalid,
+    
+    #[error("Preview link has expired")]
+    Expired,
+}
+
+/// Signs and verifies preview links (`sig={expiry}.{kid}.{base64url(signature)}`)
+pub struct PreviewSigner {
+    keys: Arc<KeyRing>,
+}
+
+impl PreviewSigner {
+    pub fn new(keys: Arc<KeyRing>) -> Self {
+        Self { keys }
+    }
+    
+    fn message(share: &ShareLink, expires: i64) -> String {
+        format!("{}.{}.{}", share.short_code, share.share_key, expires)
+    }
+    
+    /// Signature for a preview link issued at `now`
+    pub fn sign(&self, share: &ShareLink, now: DateTime<Utc>) -> Result<String, SigningKeyError> {
+        let expires = (now + Duration::hours(PREVIEW_LINK_TTL_HOURS)).min(share.expires_at).timestamp();
+        let signature = self.keys.sign(SIGNING_PURPOSE, Self::message(share, expires).as_bytes())?;
+        Ok(format!("{}.{}", expires, signature))
+    }
+    
+    /// Check a signature against the share and its lifetime
+    pub fn verify(&self, share: &ShareLink, sig: &str, now: DateTime<Utc>) -> Result<(), PreviewLinkError> {
+        let (expires, signature) = sig.trim().split_once('.').ok_or(PreviewLinkError::Invalid)?;
+        let expires: i64 = expires.parse().map_err(|_| PreviewLinkError::Invalid)?;
+        self.keys.verify(SIGNING_PURPOSE, Self::message(share, expires).as_bytes(), signature)
+            .map_err(|_| PreviewLinkError::Invalid)?;
+        
+        if now.timestamp() > expires {
+            return Err(PreviewLinkError::Expired);
+        }
+        Ok(())
+    }
+}
+
+/// `#rgb` or `#rrggbb` as RGB
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgb = [0; 3];
+            for (i, c) in hex.char_indices() {
+                rgb[i] = channel(&c.to_string())? * 17;
+            }
+            Some(rgb)
+        }
+        6 => Some([channel(hex.get(0..2)?)?, channel(hex.get(2..4)?)?, channel(hex.get(4..6)?)?]),
+        _ => None,
+    }
+}
+
+/// The wheel of `year` as a PNG: `layers` as rings from the inside out,
+/// activities as arcs stacked by `order`
+pub fn render(layers: &[Layer], activities: &[Activity], year: i32, order: ActivityOrder) -> Vec<u8> {
+    const BACKGROUND: u8 = 0;
+    const GRID: u8 = 1;
+    const INNER_RADIUS: f64 = 24.0;
+    const OUTER_RADIUS: f64 = 76.0;
+    
+    let year_start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or_default();
+    let days = NaiveDate::from_ymd_opt(year + 1, 1, 1).map_or(365, |next| (next - year_start).num_days()) as usize;
+    let mut palette: Vec<[u8; 3]> = vec![[255, 255, 255], [224, 224, 224]];
+    
+    // Palette index per ring and day, painted bottom to top so the topmost activity of a day wins
+    let mut rings: Vec<&Layer> = layers.iter().collect();
+    rings.sort_by_key(|l| l.ring_index);
+    let mut stacked = activities.to_vec();
+    ordering::sort(&mut stacked, order);
+    let mut painted = vec![vec![GRID; days]; rings.len()];
+    for (ring, layer) in rings.iter().enumerate() {
+        for activity in stacked.iter().filter(|a| a.scope == layer.id) {
+            let rgb = parse_color(&activity.color).unwrap_or([158, 158, 158]);
+            let index = match palette.iter().position(|c| *c == rgb) {
+                Some(i) => i as u8,
+                None if palette.len() < 256 => {
+                    palette.push(rgb);
+                    (palette.len() - 1) as u8
+                }
+                // Palette full
+                None => continue,
+            };
+            let from = (activity.start_date.date_naive() - year_start).num_days().max(0) as usize;
+            let to = ((activity.end_date.date_naive() - year_start).num_days() + 1).clamp(0, days as i64) as usize;
+            for day in painted[ring].iter_mut().take(to).skip(from) {
+                *day = index;
+            }
+        }
+    }
+    
+    let size = PREVIEW_SIZE as usize;
+    let center = PREVIEW_SIZE as f64 / 2.0;
+    let ring_width = (OUTER_RADIUS - INNER_RADIUS) / rings.len().max(1) as f64;
+    let mut pixels = vec![BACKGROUND; size * size];
+    for y in 0..size {
+        for x in 0..size {
+            let (dx, dy) = (x as f64 + 0.5 - center, y as f64 + 0.5 - center);
+            let radius = dx.hypot(dy);
+            if !(INNER_RADIUS..OUTER_RADIUS).contains(&radius) {
+                continue;
+            }
+            // Clockwise from the top, like the wheel
+            let angle = dx.atan2(-dy).rem_euclid(2.0 * PI);
+            let day = ((angle / (2.0 * PI) * days as f64) as usize).min(days - 1);
+            let ring = (((radius - INNER_RADIUS) / ring_width) as usize).min(rings.len().saturating_sub(1));
+            pixels[y * size + x] = painted.get(ring).map_or(GRID, |r| r[day]);
+        }
+    }
+    
+    png(PREVIEW_SIZE, PREVIEW_SIZE, &palette, &pixels)
+}
+
+/// CRC-32 of PNG chunks
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Indexed-color PNG with uncompressed (stored) deflate blocks, without external dependencies
+fn png(width: u32, height: u32, palette: &[[u8; 3]], pixels: &[u8]) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+    
+    // Scanlines, each behind filter type 0
+    let mut raw = Vec::with_capacity(pixels.len() + height as usize);
+    for row in pixels.chunks(width as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(u16::MAX as usize).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        zlib.push(u8::from(i + 1 == blocks.len()));
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    let (a, b) = raw.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65_521;
+        (a, (b + a) % 65_521)
+    });
+    zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+    
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"PLTE", &palette.concat());
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn share() -> ShareLink {
+        serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "k".repeat(64), "shortCode": "Code0001",
+            "visibility": "public", "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2099-01-01T00:00:00Z",
+            "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_preview_links() {
+        let now: DateTime<Utc> = "2025-03-01T10:00:00Z".parse().unwrap();
+        let signer = PreviewSigner::new(crate::signing_keys::tests::key_ring_at(now).await);
+        let mut share = share();
+        let sig = signer.sign(&share, now).unwrap();
+        assert_eq!(signer.verify(&share, &sig, now), Ok(()));
+        assert_eq!(signer.verify(&share, &sig, now + Duration::hours(25)), Err(PreviewLinkError::Expired));
+        assert_eq!(signer.verify(&share, &sig.replace('.', "0."), now), Err(PreviewLinkError::Invalid));
+        
+        // A new share key revokes earlier links
+        share.share_key = "n".repeat(64);
+        assert_eq!(signer.verify(&share, &sig, now), Err(PreviewLinkError::Invalid));
+    }
+    
+    #[test]
+    fn test_render() {
+        let layer: Layer = serde_json::from_value(serde_json::json!({
+            "id": "layer-1", "name": "Sales", "type": "custom", "color": "#000000", "ringIndex": 0,
+            "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+        })).unwrap();
+        let activity: Activity = serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Budget", "startDate": "2025-01-01T00:00:00Z", "endDate": "2025-06-30T00:00:00Z",
+            "type": "deadline", "color": "#c0504d", "highlightColor": "#8c3836", "scope": "layer-1",
+            "scopeId": "layer-1", "organizationId": "org-1",
+        })).unwrap();
+        
+        let png = render(&[layer], &[activity], 2025, ActivityOrder::Duration);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes([png[16], png[17], png[18], png[19]]), PREVIEW_SIZE);
+        // White, grid grey and the activity's color
+        assert_eq!(u32::from_be_bytes([png[33], png[34], png[35], png[36]]), 9);
+        assert_eq!(&png[41..50], &[255, 255, 255, 224, 224, 224, 192, 80, 77]);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+}
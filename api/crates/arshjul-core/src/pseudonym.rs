@@ -0,0 +1,130 @@
+//! # Pseudonymization
+//!
+//! Keyed hashing of user identifiers before they are written to audit and
+//! analytics records, so those stores never hold raw AAD object IDs.
+//!
+//! ## Scheme
+//!
+//! - Organization salt: `HMAC-SHA256(master key, "org:" + organizationId)`
+//! - Pseudonym: `"p1_" + hex(HMAC-SHA256(org salt, userId))[..32]`
+//!
+//! Pseudonyms are stable within an organization (records of one user can be
+//! correlated) but differ across organizations, and cannot be reversed
+//! without the master key. When legally required, an admin resolves
+//! pseudonyms by re-hashing the organization's known user IDs (see
+//! `POST /api/admin/pseudonyms/resolve`); every resolution is audited.
+//!
+//! Rotating `PSEUDONYMIZATION_KEY` breaks correlation with older records.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Prefix identifying the pseudonym scheme version
+pub const PSEUDONYM_PREFIX: &str = "p1_";
+
+/// Minimum master key length
+pub const MIN_KEY_LEN: usize = 32;
+
+/// Maps user identifiers to the value stored in audit/analytics records
+pub trait Pseudonymizer: Send + Sync {
+    /// Pseudonym for a user within an organization
+    fn pseudonymize(&self, organization_id: &str, user_id: &str) -> String;
+}
+
+/// Keyed-hash pseudonymizer with per-organization salts
+pub struct HmacPseudonymizer {
+    master_key: Vec<u8>,
+}
+
+impl HmacPseudonymizer {
+    pub fn new(master_key: &[u8]) -> Self {
+        Self { master_key: master_key.to_vec() }
+    }
+    
+    fn mac(key: &[u8], data: &[u8]) -> Vec<u8> {
+        // HMAC accepts keys of any length
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+impl Pseudonymizer for HmacPseudonymizer {
+    fn pseudonymize(&self, organization_id: &str, user_id: &str) -> String {
+        let salt = Self::mac(&self.master_key, format!("org:{}", organization_id).as_bytes());
+        let digest = Self::mac(&salt, user_id.as_bytes());
+        format!("{}{}", PSEUDONYM_PREFIX, hex::encode(&digest[..16]))
+    }
+}
+
+/// Stores identifiers unchanged (development only)
+pub struct PlainIdentifiers;
+
+impl Pseudonymizer for PlainIdentifiers {
+    fn pseudonymize(&self, _organization_id: &str, user_id: &str) -> String {
+        user_id.to_string()
+    }
+}
+
+/// Build the pseudonymizer from the configured master key
+pub fn from_key(master_key: Option<&str>) -> Arc<dyn Pseudonymizer> {
+    match master_key {
+        Some(key) => Arc::new(HmacPseudonymizer::new(key.as_bytes())),
+        None => {
+            tracing::warn!("PSEUDONYMIZATION_KEY not set - audit records will contain raw user IDs");
+            Arc::new(PlainIdentifiers)
+        }
+    }
+}
+
+/// Match pseudonyms against candidate user IDs
+///
+/// Returns `(pseudonym, user_id)` pairs; unmatched pseudonyms get `None`.
+pub fn resolve(
+    pseudonymizer: &dyn Pseudonymizer,
+    organization_id: &str,
+    pseudonyms: &[String],
+    candidates: &[String],
+) -> Vec<(String, Option<String>)> {
+    let hashed: Vec<(String, &String)> = candidates.iter()
+        .map(|user_id| (pseudonymizer.pseudonymize(organization_id, user_id), user_id))
+        .collect();
+    
+    pseudonyms.iter()
+        .map(|p| {
+            let user_id = hashed.iter().find(|(h, _)| h == p).map(|(_, u)| (*u).clone());
+            (p.clone(), user_id)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_pseudonyms_are_stable_per_organization() {
+        let p = HmacPseudonymizer::new(&[7u8; MIN_KEY_LEN]);
+        let a = p.pseudonymize("org-1", "user-1");
+        
+        assert!(a.starts_with(PSEUDONYM_PREFIX));
+        assert_eq!(a.len(), PSEUDONYM_PREFIX.len() + 32);
+        assert_eq!(a, p.pseudonymize("org-1", "user-1"));
+        assert_ne!(a, p.pseudonymize("org-2", "user-1"));
+        assert_ne!(a, HmacPseudonymizer::new(&[8u8; MIN_KEY_LEN]).pseudonymize("org-1", "user-1"));
+    }
+    
+    #[test]
+    fn test_resolve() {
+        let p = HmacPseudonymizer::new(b"0123456789abcdef0123456789abcdef");
+        let known = p.pseudonymize("org-1", "user-2");
+        let candidates = vec!["user-1".to_string(), "user-2".to_string()];
+        
+        let resolved = resolve(&p, "org-1", &[known.clone(), "p1_unknown".to_string()], &candidates);
+        assert_eq!(resolved[0], (known, Some("user-2".to_string())));
+        assert_eq!(resolved[1].1, None);
+    }
+}
@@ -0,0 +1,399 @@
+//! # Public Share Access
+//!
+//! Runtime-agnostic core of `GET /api/public/s/{shortCode}`: input checks,
+//! key verification and the projection of a share into its public view.
+//!
+//! The functions here do no I/O, so the same logic runs in the Functions
+//! handler and at the edge (`arshjul-edge`, wasm32-wasi / Workers). Edge
+//! runtimes read from a [`PublicShareKv`] populated with denormalized
+//! documents:
+//!
+//! | Key | Value |
+//! |-----|-------|
+//! | `share:{shortCode}` | [`ShareLink`] |
+//! | `tombstone:{shortCode}` | [`ShortCodeTombstone`] |
+//! | `activities:{organizationId}:{year}` | `Vec<Activity>` |
+
+use crate::crypto::{is_valid_share_key, is_valid_short_code, secure_compare};
+use crate::export::ExportLocale;
+use crate::i18n;
+use crate::indexing;
+use crate::markdown;
+use crate::models::*;
+use crate::ordering;
+use crate::recurrence;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Utc};
+use thiserror::Error;
+
+/// Reasons a public share cannot be shown
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PublicAccessError {
+    #[error("Invalid share code")]
+    InvalidCode,
+    
+    #[error("Invalid share key")]
+    InvalidKey,
+    
+    #[error("Share not found")]
+    NotFound,
+    
+    #[error("Share has been removed")]
+    Removed,
+    
+    #[error("Share has been deactivated")]
+    Deactivated,
+    
+    #[error("Share has expired")]
+    Expired,
+    
+    #[error("Storage error: {0}")]
+    Storage(String),
+}
+
+/// Check the request format before any lookup
+pub fn validate_request(short_code: &str, key: &str) -> Result<(), PublicAccessError> {
+    if !is_valid_short_code(short_code) {
+        return Err(PublicAccessError::InvalidCode);
+    }
+    if !is_valid_share_key(key) {
+        return Err(PublicAccessError::InvalidKey);
+    }
+    Ok(())
+}
+
+/// Verify the key (constant time) and that the share is still live
+pub fn authorize(share: &ShareLink, key: &str, now: DateTime<Utc>) -> Result<(), PublicAccessError> {
+    if !secure_compare(&share.share_key, key) {
+        return Err(PublicAccessError::InvalidKey);
+    }
+    check_live(share, now)
+}
+
+/// Verify the share is active and not expired
+pub fn check_live(share: &ShareLink, now: DateTime<Utc>) -> Result<(), PublicAccessError> {
+    if !share.is_active {
+        return Err(PublicAccessError::Deactivated);
+    }
+    if now > share.expires_at {
+        return Err(PublicAccessError::Expired);
+    }
+    Ok(())
+}
+
+/// Year shown by a share
+pub fn share_year(share: &ShareLink, now: DateTime<Utc>) -> i32 {
+    share.layer_config.year.unwrap_or_else(|| now.year())
+}
+
+/// Title shown to viewers: the custom title, then the share name
+pub fn share_title(share: &ShareLink) -> String {
+    i18n::share_title(ExportLocale::English, share)
+}
+
+/// View settings a share is shown with: for shares that follow the
+/// organization, its current default with the share's overrides merged in
+/// field by field, and the share's own custom title
+///
+/// Snapshots and edge copies show the settings stored on the share, which
+/// are the organization default of when it was created.
+pub fn view_settings(share: &ShareLink, organization_default: Option<&ShareViewSettings>) -> ShareViewSettings {
+    if !share.inherit_view_settings {
+        return share.view_settings.clone();
+    }
+    share.view_overrides.merge(&ShareViewSettings {
+        custom_title: share.view_settings.custom_title.clone(),
+        ..organization_default.cloned().unwrap_or_default()
+    })
+}
+
+/// Public view of a share in `year`: only approved activities on the shared
+/// layers, recurring ones as their occurrences in `year`, with sanitized
+/// HTML descriptions unless the share turned them off, each ring stacked by
+/// `order`
+///
+/// `indexable` only follows the share's own flag; the public share handler
+/// applies the organization default.
+pub fn project(share: &ShareLink, activities: Vec<Activity>, year: i32, order: ActivityOrder) -> AccessShareResponse {
+    let activities: Vec<Activity> = activities.into_iter()
+        .filter(|a| a.approval_status == ApprovalStatus::Approved)
+        .filter(|a| share.layer_config.layer_ids.contains(&a.scope))
+        .collect();
+    let mut activities = recurrence::expand(activities, Some(year));
+    ordering::sort(&mut activities, order);
+    let z_indices = ordering::z_indices(&activities);
+    
+    let share_activities: Vec<ShareActivity> = activities.into_iter()
+        .zip(z_indices)
+        .map(|(a, z_index)| ShareActivity {
+            description_html: a.description.as_deref()
+                .filter(|_| share.view_settings.description_html)
+                .map(|d| markdown::render_html(d, a.description_format)),
+            id: a.id,
+            title: a.title,
+            start_date: a.start_date,
+            end_date: a.end_date,
+            color: a.color,
+            highlight_color: a.highlight_color,
+            layer_id: a.scope,
+            description: a.description,
+            links: a.links,
+            z_index,
+        })
+        .collect();
+    
+    AccessShareResponse {
+        success: true,
+        error: None,
+        config: Some(ShareAccessConfig {
+            layers: share.layer_config.clone(),
+            view_settings: share.view_settings.clone(),
+            organization_name: "Organization".to_string(), // TODO: Fetch from org lookup
+            title: share_title(share),
+            indexable: indexing::is_indexable(share, false),
+        }),
+        activities: Some(share_activities),
+        partial: false,
+    }
+}
+
+/// Activities in `GET /api/public/s/{shortCode}/upcoming` without a `limit`
+pub const DEFAULT_UPCOMING: usize = 10;
+
+/// Most activities one upcoming request returns
+pub const MAX_UPCOMING: usize = 50;
+
+/// The first `limit` approved activities or occurrences on the shared
+/// layers that have not ended by `now`, in start order
+///
+/// `activities` may list a series or an activity spanning New Year twice,
+/// once per year read; it is shown once.
+pub fn upcoming(share: &ShareLink, activities: Vec<Activity>, now: DateTime<Utc>, limit: usize) -> Vec<UpcomingActivity> {
+    let activities: Vec<Activity> = activities.into_iter()
+        .filter(|a| a.approval_status == ApprovalStatus::Approved)
+        .filter(|a| share.layer_config.layer_ids.contains(&a.scope))
+        .collect();
+    let mut activities: Vec<Activity> = recurrence::expand(activities, None).into_iter()
+        .filter(|a| a.end_date >= now)
+        .collect();
+    activities.sort_by(|a, b| a.start_date.cmp(&b.start_date).then_with(|| a.id.cmp(&b.id)));
+    activities.dedup_by(|a, b| a.id == b.id);
+    
+    activities.into_iter()
+        .take(limit.min(MAX_UPCOMING))
+        .map(|a| UpcomingActivity {
+            id: a.id,
+            title: a.title,
+            start_date: a.start_date,
+            end_date: a.end_date,
+            color: a.color,
+            layer_id: a.scope,
+        })
+        .collect()
+}
+
+/// Response body for a denied request
+pub fn denied(error: &PublicAccessError) -> AccessShareResponse {
+    AccessShareResponse {
+        success: false,
+        error: Some(error.to_string()),
+        config: None,
+        activities: None,
+        partial: false,
+    }
+}
+
+/// Read-only key-value store for edge runtimes (Workers KV, WASI files, ...)
+///
+/// Not `Send`: edge runtimes are single-threaded.
+#[async_trait(?Send)]
+pub trait PublicShareKv {
+    /// Raw value for a key, if present
+    async fn get(&self, key: &str) -> Result<Option<String>, PublicAccessError>;
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(kv: &dyn PublicShareKv, key: &str) -> Result<Option<T>, PublicAccessError> {
+    match kv.get(key).await? {
+        Some(raw) => serde_json::from_str(&raw)
+            .map(Some)
+            .map_err(|e| PublicAccessError::Storage(format!("{}: {}", key, e))),
+        None => Ok(None),
+    }
+}
+
+/// Serve a public share from a key-value store
+///
+/// View counting is left to the origin; edge reads are side-effect free.
+pub async fn serve_from_kv(
+    kv: &dyn PublicShareKv,
+    short_code: &str,
+    key: &str,
+    now: DateTime<Utc>,
+) -> Result<AccessShareResponse, PublicAccessError> {
+    validate_request(short_code, key)?;
+    
+    let share: ShareLink = match get_json(kv, &format!("share:{}", short_code)).await? {
+        Some(share) => share,
+        None => {
+            let tombstone: Option<ShortCodeTombstone> = get_json(kv, &format!("tombstone:{}", short_code)).await?;
+            return Err(if tombstone.is_some_and(|t| t.is_active(now)) {
+                PublicAccessError::Removed
+            } else {
+                PublicAccessError::NotFound
+            });
+        }
+    };
+    
+    authorize(&share, key, now)?;
+    
+    let activities_key = format!("activities:{}:{}", share.organization_id, share_year(&share, now));
+    let activities: Vec<Activity> = get_json(kv, &activities_key).await?.unwrap_or_default();
+    
+    Ok(project(&share, activities, share_year(&share, now), ActivityOrder::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    
+    struct MapKv(HashMap<String, String>);
+    
+    #[async_trait(?Send)]
+    impl PublicShareKv for MapKv {
+        async fn get(&self, key: &str) -> Result<Option<String>, PublicAccessError> {
+            Ok(self.0.get(key).cloned())
+        }
+    }
+    
+    fn share() -> ShareLink {
+        ShareLink {
+            id: "share-1".to_string(),
+            share_key: "a".repeat(64),
+            short_code: "AbCd1234".to_string(),
+            visibility: ShareVisibility::Public,
+            organization_id: "org-1".to_string(),
+            created_by: "user-1".to_string(),
+            created_at: Utc::now(),
+            expires_at: Utc::now() + chrono::Duration::days(30),
+            renewed_at: None,
+            name: Some("Plan".to_string()),
+            description: None,
+            layer_config: ShareLayerConfig {
+                layer_ids: vec!["layer-1".to_string()],
+                layer_visibility: None,
+                year: Some(2025),
+            },
+            view_settings: ShareViewSettings::default(),
+            inherit_view_settings: false,
+            view_overrides: ShareViewSettingsPatch::default(),
+            stats: ShareStats::default(),
+            is_active: true,
+            ttl: None,
+            report_count: 0,
+            reporters: Vec::new(),
+            publish_snapshot: false,
+            indexable: None,
+            created_by_name: None,
+            review: None,
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            deleted_at: None,
+            partner_sources: Vec::new(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_serve_from_kv() {
+        let share = share();
+        let activity = |id: &str, scope: &str| serde_json::json!({
+            "id": id, "title": id, "startDate": "2025-03-01T00:00:00Z", "endDate": "2025-03-02T00:00:00Z",
+            "type": "meeting", "color": "#000000", "highlightColor": "#ffffff", "scope": scope, "scopeId": scope,
+            "organizationId": "org-1",
+        });
+        let kv = MapKv(HashMap::from([
+            ("share:AbCd1234".to_string(), serde_json::to_string(&share).unwrap()),
+            ("activities:org-1:2025".to_string(), serde_json::json!([
+                activity("shared", "layer-1"),
+                activity("hidden", "layer-2"),
+            ]).to_string()),
+        ]));
+        
+        let response = serve_from_kv(&kv, "AbCd1234", &share.share_key, Utc::now()).await.unwrap();
+        let activities = response.activities.unwrap();
+        assert_eq!(activities.len(), 1);
+        assert_eq!(activities[0].id, "shared");
+        
+        let wrong_key = serve_from_kv(&kv, "AbCd1234", &"b".repeat(64), Utc::now()).await;
+        assert_eq!(wrong_key.unwrap_err(), PublicAccessError::InvalidKey);
+        assert_eq!(serve_from_kv(&kv, "Zzzz9999", &share.share_key, Utc::now()).await.unwrap_err(), PublicAccessError::NotFound);
+    }
+    
+    #[test]
+    fn test_view_settings() {
+        let dark = ShareViewSettings { theme: ShareTheme::Dark, show_legend: false, ..Default::default() };
+        let mut share = share();
+        share.view_settings.custom_title = Some("Board".to_string());
+        
+        // Shares with their own settings keep them
+        assert_eq!(view_settings(&share, Some(&dark)), share.view_settings);
+        
+        // Others follow the organization, or the built-in defaults, keeping their title
+        share.inherit_view_settings = true;
+        let shown = view_settings(&share, Some(&dark));
+        assert_eq!((shown.theme, shown.show_legend, shown.custom_title.as_deref()), (ShareTheme::Dark, false, Some("Board")));
+        assert_eq!(view_settings(&share, None), share.view_settings);
+        
+        // Overrides replace single fields of the default
+        share.view_overrides.show_legend = Some(true);
+        let shown = view_settings(&share, Some(&dark));
+        assert_eq!((shown.theme, shown.show_legend), (ShareTheme::Dark, true));
+    }
+    
+    #[test]
+    fn test_upcoming() {
+        let share = share();
+        let now: DateTime<Utc> = "2025-03-10T00:00:00Z".parse().unwrap();
+        let activity = |id: &str, scope: &str, start: &str, end: &str| serde_json::from_value::<Activity>(serde_json::json!({
+            "id": id, "title": id, "startDate": start, "endDate": end,
+            "type": "meeting", "color": "#000000", "highlightColor": "#ffffff", "scope": scope, "scopeId": scope,
+            "organizationId": "org-1",
+        })).unwrap();
+        let activities = vec![
+            activity("later", "layer-1", "2025-05-01T00:00:00Z", "2025-05-02T00:00:00Z"),
+            activity("past", "layer-1", "2025-03-01T00:00:00Z", "2025-03-02T00:00:00Z"),
+            activity("ongoing", "layer-1", "2025-03-09T00:00:00Z", "2025-03-11T00:00:00Z"),
+            activity("hidden", "layer-2", "2025-04-01T00:00:00Z", "2025-04-02T00:00:00Z"),
+            activity("next", "layer-1", "2025-04-01T00:00:00Z", "2025-04-02T00:00:00Z"),
+        ];
+        
+        let ids = |limit| upcoming(&share, activities.clone(), now, limit).into_iter().map(|a| a.id).collect::<Vec<_>>();
+        assert_eq!(ids(DEFAULT_UPCOMING), ["ongoing", "next", "later"]);
+        assert_eq!(ids(2), ["ongoing", "next"]);
+        
+        // Series are listed by occurrence, once however often they were read
+        let mut series = activity("series", "layer-1", "2025-03-03T00:00:00Z", "2025-03-31T00:00:00Z");
+        series.recurrence = serde_json::from_value(serde_json::json!({
+            "frequency": "weekly",
+            "exceptions": [{ "date": "2025-03-17", "skip": true }, { "date": "2025-03-24", "title": "Moved", "startDate": "2025-03-25T00:00:00Z" }],
+        })).unwrap();
+        let listed = upcoming(&share, vec![series.clone(), series], now, DEFAULT_UPCOMING);
+        let listed: Vec<(&str, &str)> = listed.iter().map(|a| (a.id.as_str(), a.title.as_str())).collect();
+        assert_eq!(listed, [("series_20250310", "series"), ("series_20250324", "Moved"), ("series_20250331", "series")]);
+    }
+    
+    #[test]
+    fn test_project_recurring() {
+        let share = share();
+        let series: Activity = serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Review", "startDate": "2024-11-15T00:00:00Z", "endDate": "2025-03-31T00:00:00Z",
+            "type": "review", "color": "#000000", "highlightColor": "#ffffff", "scope": "layer-1", "scopeId": "layer-1",
+            "organizationId": "org-1",
+            "recurrence": { "frequency": "monthly", "durationDays": 1, "exceptions": [{ "date": "2025-02-15", "skip": true }] },
+        })).unwrap();
+        
+        let response = project(&share, vec![series], 2025, ActivityOrder::default());
+        let ids: Vec<String> = response.activities.unwrap().into_iter().map(|a| a.id).collect();
+        assert_eq!(ids, ["a-1_20250115", "a-1_20250315"]);
+    }
+}
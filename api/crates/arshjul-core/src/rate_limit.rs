@@ -0,0 +1,224 @@
+//! # Rate Limiting
+//!
+//! Fixed-window, in-process request limits for endpoints that fan out to
+//! paid or throttled upstream APIs (e.g. Microsoft Graph). Keys are chosen
+//! by the caller: per user, per organization or per client IP.
+//!
+//! Limits are per instance; a scaled-out deployment allows up to
+//! `instances × limit` requests per window.
+//!
+//! ## Public API plans
+//!
+//! Public share endpoints (share JSON and calendar feed) are limited per
+//! minute twice: across the organization, and per share key (the "API key"
+//! integrators embed). Admins set both in the organization's
+//! [`RatePlan`](crate::models::RatePlan); per-key limits are keyed by share ID
+//! so they survive key regeneration.
+//!
+//! Each client network (see [`ClientInfo::network`](crate::client_info::ClientInfo::network))
+//! is also held to [`DEFAULT_CLIENT_PER_MINUTE`] across the organization, so
+//! one script can't use up the limits of everyone viewing its shares.
+//!
+//! ## Headers
+//!
+//! Every response of a rate limited endpoint, 429s included, tells the
+//! client where it stands so integrators can throttle themselves:
+//!
+//! | Header | Value |
+//! |--------|-------|
+//! | `X-RateLimit-Limit` | requests allowed in the window |
+//! | `X-RateLimit-Remaining` | requests left in the window |
+//! | `X-RateLimit-Reset` | end of the window, Unix seconds |
+//!
+//! Where two limits apply, the headers describe the one with less left.
+
+use crate::models::RatePlan;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Requests allowed in the window
+pub const LIMIT_HEADER: &str = "X-RateLimit-Limit";
+
+/// Requests left in the window
+pub const REMAINING_HEADER: &str = "X-RateLimit-Remaining";
+
+/// End of the window (Unix seconds)
+pub const RESET_HEADER: &str = "X-RateLimit-Reset";
+
+/// Public API requests per minute and organization without a plan
+pub const DEFAULT_ORGANIZATION_PER_MINUTE: u32 = 6000;
+
+/// Public API requests per minute and share key without a plan
+pub const DEFAULT_API_KEY_PER_MINUTE: u32 = 600;
+
+/// Public API requests per minute, client network and organization
+pub const DEFAULT_CLIENT_PER_MINUTE: u32 = 300;
+
+/// Highest per-minute limit a plan can set
+pub const MAX_PLAN_PER_MINUTE: u32 = 100_000;
+
+/// Most share keys with their own limit
+pub const MAX_PLAN_API_KEYS: usize = 200;
+
+/// Invalid rate plans
+#[derive(Debug, Error, PartialEq)]
+pub enum RatePlanError {
+    #[error("Limits must be between 1 and {0} requests per minute")]
+    OutOfRange(u32),
+    
+    #[error("At most {0} share keys can have their own limit")]
+    TooManyApiKeys(usize),
+}
+
+/// Check a plan before it is saved
+pub fn validate_plan(plan: &RatePlan) -> Result<(), RatePlanError> {
+    if plan.api_keys.len() > MAX_PLAN_API_KEYS {
+        return Err(RatePlanError::TooManyApiKeys(MAX_PLAN_API_KEYS));
+    }
+    let in_range = |limit: &u32| (1..=MAX_PLAN_PER_MINUTE).contains(limit);
+    if !plan.organization_per_minute.iter().chain(plan.api_keys.values()).all(in_range) {
+        return Err(RatePlanError::OutOfRange(MAX_PLAN_PER_MINUTE));
+    }
+    Ok(())
+}
+
+/// Per-minute limits of an organization's public API, and of one share key
+pub fn plan_limits(plan: Option<&RatePlan>, share_id: &str) -> (u32, u32) {
+    (
+        plan.and_then(|p| p.organization_per_minute).unwrap_or(DEFAULT_ORGANIZATION_PER_MINUTE),
+        plan.and_then(|p| p.api_keys.get(share_id).copied()).unwrap_or(DEFAULT_API_KEY_PER_MINUTE),
+    )
+}
+
+/// Where a key stands in its current window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// End of the window
+    pub reset_at: DateTime<Utc>,
+}
+
+impl RateLimitStatus {
+    /// `X-RateLimit-*` response headers
+    pub fn headers(&self) -> [(&'static str, String); 3] {
+        [
+            (LIMIT_HEADER, self.limit.to_string()),
+            (REMAINING_HEADER, self.remaining.to_string()),
+            (RESET_HEADER, self.reset_at.timestamp().to_string()),
+        ]
+    }
+    
+    /// The status with less left
+    pub fn tighter(self, other: Self) -> Self {
+        if other.remaining < self.remaining { other } else { self }
+    }
+}
+
+/// Request was rejected; retry after the given number of seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimited {
+    pub retry_after_seconds: i64,
+    pub status: RateLimitStatus,
+}
+
+/// Fixed-window rate limiter
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    windows: Mutex<HashMap<String, (DateTime<Utc>, u32)>>,
+}
+
+impl RateLimiter {
+    /// Allow `limit` requests per key and `window`
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Count a request for `key`
+    pub fn check(&self, key: &str) -> Result<RateLimitStatus, RateLimited> {
+        self.check_at(key, Utc::now())
+    }
+    
+    /// Count a request for `key` at `now`
+    pub fn check_at(&self, key: &str, now: DateTime<Utc>) -> Result<RateLimitStatus, RateLimited> {
+        self.check_limit_at(key, self.limit, now)
+    }
+    
+    /// Count a request for `key` at `now` against a per-key limit (e.g. from a [`RatePlan`])
+    pub fn check_limit_at(&self, key: &str, limit: u32, now: DateTime<Utc>) -> Result<RateLimitStatus, RateLimited> {
+        let Ok(mut windows) = self.windows.lock() else {
+            return Ok(RateLimitStatus { limit, remaining: limit, reset_at: now + self.window });
+        };
+        
+        // Keep the map bounded by dropping finished windows now and then
+        if windows.len() > 10_000 {
+            let window = self.window;
+            windows.retain(|_, (started, _)| *started + window > now);
+        }
+        
+        let (started, count) = windows.entry(key.to_string()).or_insert((now, 0));
+        if *started + self.window <= now {
+            *started = now;
+            *count = 0;
+        }
+        
+        let reset_at = *started + self.window;
+        if *count >= limit {
+            let retry_after = (reset_at - now).num_seconds().max(1);
+            let status = RateLimitStatus { limit, remaining: 0, reset_at };
+            return Err(RateLimited { retry_after_seconds: retry_after, status });
+        }
+        *count += 1;
+        Ok(RateLimitStatus { limit, remaining: limit - *count, reset_at })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_fixed_window() {
+        let limiter = RateLimiter::new(2, Duration::seconds(60));
+        let start = Utc::now();
+        
+        assert!(limiter.check_at("user-1", start).is_ok());
+        assert_eq!(limiter.check_at("user-1", start).map(|s| s.remaining), Ok(0));
+        let limited = limiter.check_at("user-1", start + Duration::seconds(15)).unwrap_err();
+        assert_eq!(limited.retry_after_seconds, 45);
+        assert_eq!(limited.status.reset_at, start + Duration::seconds(60));
+        assert!(limiter.check_at("user-2", start).is_ok());
+        assert!(limiter.check_at("user-1", start + Duration::seconds(60)).is_ok());
+    }
+    
+    #[test]
+    fn test_plans_and_headers() {
+        let mut plan = RatePlan { organization_per_minute: Some(100), ..Default::default() };
+        plan.api_keys.insert("share-1".to_string(), 2);
+        assert_eq!(validate_plan(&plan), Ok(()));
+        assert_eq!(plan_limits(Some(&plan), "share-1"), (100, 2));
+        assert_eq!(plan_limits(Some(&plan), "share-2"), (100, DEFAULT_API_KEY_PER_MINUTE));
+        assert_eq!(plan_limits(None, "share-1"), (DEFAULT_ORGANIZATION_PER_MINUTE, DEFAULT_API_KEY_PER_MINUTE));
+        
+        plan.api_keys.insert("share-2".to_string(), 0);
+        assert_eq!(validate_plan(&plan), Err(RatePlanError::OutOfRange(MAX_PLAN_PER_MINUTE)));
+        
+        let limiter = RateLimiter::new(DEFAULT_API_KEY_PER_MINUTE, Duration::minutes(1));
+        let start = DateTime::from_timestamp(1_750_000_000, 0).unwrap();
+        let organization = limiter.check_limit_at("org:org-1", 100, start).unwrap();
+        let key = limiter.check_limit_at("share:share-1", 2, start).unwrap();
+        let status = organization.tighter(key);
+        assert_eq!(status.headers(), [
+            (LIMIT_HEADER, "2".to_string()),
+            (REMAINING_HEADER, "1".to_string()),
+            (RESET_HEADER, "1750000060".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,128 @@
+//! # Bulk Reassignment
+//!
+//! Reorganizations move whole categories of activities.
+//! `POST /api/admin/reassign` moves every activity of a layer to another
+//! layer, or of a built-in type to another type, optionally only those
+//! starting in a given year:
+//!
+//! ```json
+//! { "kind": "layer", "from": "layer-hr", "to": "layer-people", "year": 2025 }
+//! ```
+//!
+//! The handler plans the updates first (`?dry_run=true` returns the plan),
+//! then writes them with [`ActivityStorage::apply_changes`] in batches of
+//! [`BATCH_SIZE`], one transaction each. If a batch fails, it wrote
+//! nothing, and the batches before it are put back the same way.
+//!
+//! [`ActivityStorage::apply_changes`]: crate::storage::ActivityStorage::apply_changes
+
+use crate::activity_types;
+use crate::models::{Activity, ReassignRequest, Reassignment};
+use chrono::Datelike;
+use thiserror::Error;
+
+/// Activities per batch write; the Table Storage transaction limit
+pub const BATCH_SIZE: usize = 100;
+
+/// Invalid reassignments
+#[derive(Debug, Error, PartialEq)]
+pub enum ReassignError {
+    #[error("Source and target must differ")]
+    SameTarget,
+    
+    #[error("Unknown built-in activity type: {0}")]
+    UnknownType(String),
+    
+    #[error("Year out of range: {0}")]
+    InvalidYear(i32),
+}
+
+/// Check a request before planning it
+pub fn validate(request: &ReassignRequest) -> Result<(), ReassignError> {
+    let (from, to) = match &request.reassignment {
+        Reassignment::Layer { from, to } => (from, to),
+        Reassignment::ActivityType { from, to } => {
+            for key in [from, to] {
+                if activity_types::parse_key(key).is_none() {
+                    return Err(ReassignError::UnknownType(key.clone()));
+                }
+            }
+            (from, to)
+        }
+    };
+    if from == to {
+        return Err(ReassignError::SameTarget);
+    }
+    match request.year {
+        Some(year) if !(1970..=9999).contains(&year) => Err(ReassignError::InvalidYear(year)),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the request moves the activity; `year` matches the start date
+pub fn matches(request: &ReassignRequest, activity: &Activity) -> bool {
+    let source = match &request.reassignment {
+        Reassignment::Layer { from, .. } => activity.scope == *from,
+        Reassignment::ActivityType { from, .. } => activity_types::key_of(&activity.activity_type) == *from,
+    };
+    source && request.year.is_none_or(|year| activity.start_date.year() == year)
+}
+
+/// Move the activity to the target layer or type
+pub fn apply(reassignment: &Reassignment, activity: &mut Activity) {
+    match reassignment {
+        Reassignment::Layer { to, .. } => {
+            activity.scope = to.clone();
+            activity.scope_id = to.clone();
+        }
+        Reassignment::ActivityType { to, .. } => {
+            if let Some(activity_type) = activity_types::parse_key(to) {
+                activity.activity_type = activity_type;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ActivityType;
+    
+    fn request(value: serde_json::Value) -> ReassignRequest {
+        serde_json::from_value(value).unwrap()
+    }
+    
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(&request(serde_json::json!({ "kind": "type", "from": "meeting", "to": "event" }))), Ok(()));
+        assert_eq!(
+            validate(&request(serde_json::json!({ "kind": "type", "from": "meeting", "to": "offsite" }))),
+            Err(ReassignError::UnknownType("offsite".to_string()))
+        );
+        assert_eq!(validate(&request(serde_json::json!({ "kind": "layer", "from": "l-1", "to": "l-1" }))), Err(ReassignError::SameTarget));
+        assert_eq!(
+            validate(&request(serde_json::json!({ "kind": "layer", "from": "l-1", "to": "l-2", "year": 20250 }))),
+            Err(ReassignError::InvalidYear(20250))
+        );
+    }
+    
+    #[test]
+    fn test_matches_and_apply() {
+        let mut activity: Activity = serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Budget", "startDate": "2025-10-01T00:00:00Z", "endDate": "2026-01-15T00:00:00Z",
+            "type": "deadline", "color": "#000000", "highlightColor": "#000000",
+            "scope": "layer-hr", "scopeId": "layer-hr", "organizationId": "org-1",
+        })).unwrap();
+        
+        let by_layer = request(serde_json::json!({ "kind": "layer", "from": "layer-hr", "to": "layer-people", "year": 2025 }));
+        assert!(matches(&by_layer, &activity));
+        assert!(!matches(&request(serde_json::json!({ "kind": "layer", "from": "layer-hr", "to": "layer-people", "year": 2026 })), &activity));
+        apply(&by_layer.reassignment, &mut activity);
+        assert_eq!((activity.scope.as_str(), activity.scope_id.as_str()), ("layer-people", "layer-people"));
+        
+        let by_type = request(serde_json::json!({ "kind": "type", "from": "deadline", "to": "review" }));
+        assert!(matches(&by_type, &activity));
+        apply(&by_type.reassignment, &mut activity);
+        assert_eq!(activity.activity_type, ActivityType::Review);
+    }
+}
@@ -0,0 +1,309 @@
+//! # Recurring Activities
+//!
+//! An activity with a [`Recurrence`] is a series: `startDate` is the start
+//! of the first occurrence and `endDate` the end of the series. Occurrences
+//! start every `interval` days, weeks, months or years after the first and
+//! last `durationDays` days more; each one that ends by `endDate` belongs to
+//! the series. Monthly and yearly series skip months without the first
+//! occurrence's day (no 30 February), as RFC 5545 does.
+//!
+//! Since the stored dates span the whole series, the year filters of
+//! [`ActivityStorage::list_by_layers`] find it in every year it has
+//! occurrences. Share views, upcoming lists and wheel exports show the
+//! occurrences from [`expand`]; calendar feeds keep one event with an
+//! `RRULE` (see [`crate::ics`]).
+//!
+//! ## Exceptions
+//!
+//! An [`OccurrenceException`] changes the occurrence that would start on its
+//! `date`: `skip` drops it, `startDate`/`endDate` move it and `title`
+//! renames it. Moved occurrences stay within the series' dates, so the year
+//! filters still find them.
+//!
+//! [`ActivityStorage::list_by_layers`]: crate::storage::ActivityStorage::list_by_layers
+//! [`OccurrenceException`]: crate::models::OccurrenceException
+
+use crate::models::{Activity, Recurrence, RecurrenceFrequency};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use thiserror::Error;
+
+/// Most occurrences in one series, a year of daily ones
+pub const MAX_OCCURRENCES: usize = 366;
+
+/// Invalid recurrences
+#[derive(Debug, Error, PartialEq)]
+pub enum RecurrenceError {
+    #[error("Recurrence interval must be at least 1")]
+    InvalidInterval,
+    
+    #[error("Recurring activity has no occurrence before its end date")]
+    NoOccurrences,
+    
+    #[error("Recurring activity has more than {MAX_OCCURRENCES} occurrences")]
+    TooManyOccurrences,
+    
+    #[error("No occurrence starts on {0}")]
+    UnknownOccurrence(NaiveDate),
+    
+    #[error("More than one exception for {0}")]
+    DuplicateException(NaiveDate),
+    
+    #[error("Occurrence on {0} must end on or after its start and stay within the series")]
+    InvalidMove(NaiveDate),
+    
+    #[error("Title of the occurrence on {0} must be 1 to 200 characters")]
+    InvalidTitle(NaiveDate),
+}
+
+/// One occurrence of a series, with its exception applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    /// Date it was scheduled to start on
+    pub date: NaiveDate,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub title: String,
+}
+
+/// Id of an expanded occurrence, e.g. `a-1_20250303`
+pub fn occurrence_id(series_id: &str, date: NaiveDate) -> String {
+    format!("{}_{}", series_id, date.format("%Y%m%d"))
+}
+
+fn duration(rule: &Recurrence) -> Duration {
+    Duration::days(rule.duration_days.into())
+}
+
+/// Start of the `n`th scheduled occurrence and whether it is held: months
+/// without the first occurrence's day have none; None past the calendar
+fn nth_start(activity: &Activity, rule: &Recurrence, n: u32) -> Option<(DateTime<Utc>, bool)> {
+    let steps = n.checked_mul(rule.interval)?;
+    let start = activity.start_date;
+    let months = match rule.frequency {
+        RecurrenceFrequency::Daily => return Some((start.checked_add_signed(Duration::days(steps.into()))?, true)),
+        RecurrenceFrequency::Weekly => return Some((start.checked_add_signed(Duration::weeks(steps.into()))?, true)),
+        RecurrenceFrequency::Monthly => steps,
+        RecurrenceFrequency::Yearly => steps.checked_mul(12)?,
+    };
+    // chrono clamps to the month's last day
+    let date = start.checked_add_months(Months::new(months))?;
+    Some((date, date.day() == start.day()))
+}
+
+/// Dates the occurrences of a series are scheduled on, before exceptions;
+/// empty for single activities
+///
+/// Stops after one more than [`MAX_OCCURRENCES`] so [`validate`] can tell.
+pub fn scheduled_dates(activity: &Activity) -> Vec<NaiveDate> {
+    let Some(rule) = activity.recurrence.as_ref().filter(|r| r.interval > 0) else {
+        return Vec::new();
+    };
+    let mut dates = Vec::new();
+    for n in 0.. {
+        let Some((start, held)) = nth_start(activity, rule, n) else {
+            break;
+        };
+        if start + duration(rule) > activity.end_date || dates.len() > MAX_OCCURRENCES {
+            break;
+        }
+        if held {
+            dates.push(start.date_naive());
+        }
+    }
+    dates
+}
+
+/// The occurrences of a series with its exceptions applied, in schedule
+/// order; empty for single activities
+pub fn occurrences(activity: &Activity) -> Vec<Occurrence> {
+    let Some(rule) = &activity.recurrence else {
+        return Vec::new();
+    };
+    let time = activity.start_date.time();
+    scheduled_dates(activity).into_iter()
+        .filter_map(|date| {
+            let exception = rule.exceptions.iter().find(|e| e.date == date);
+            if exception.is_some_and(|e| e.skip) {
+                return None;
+            }
+            let start_date = exception.and_then(|e| e.start_date)
+                .unwrap_or_else(|| date.and_time(time).and_utc());
+            Some(Occurrence {
+                date,
+                start_date,
+                end_date: exception.and_then(|e| e.end_date).unwrap_or(start_date + duration(rule)),
+                title: exception.and_then(|e| e.title.clone()).unwrap_or_else(|| activity.title.clone()),
+            })
+        })
+        .collect()
+}
+
+/// Activity shown for one occurrence of `series`
+pub fn occurrence_activity(series: &Activity, occurrence: &Occurrence) -> Activity {
+    Activity {
+        id: occurrence_id(&series.id, occurrence.date),
+        title: occurrence.title.clone(),
+        start_date: occurrence.start_date,
+        end_date: occurrence.end_date,
+        recurrence: None,
+        ..series.clone()
+    }
+}
+
+/// Replace each series with its occurrences overlapping `year` (all of
+/// them without a year); single activities are kept as they are
+pub fn expand(activities: Vec<Activity>, year: Option<i32>) -> Vec<Activity> {
+    let mut expanded = Vec::with_capacity(activities.len());
+    for activity in activities {
+        if activity.recurrence.is_none() {
+            expanded.push(activity);
+            continue;
+        }
+        expanded.extend(occurrences(&activity).iter()
+            .filter(|o| year.is_none_or(|year| o.start_date.year() <= year && o.end_date.year() >= year))
+            .map(|o| occurrence_activity(&activity, o)));
+    }
+    expanded
+}
+
+/// Check the recurrence of an activity whose dates are already valid
+pub fn validate(activity: &Activity) -> Result<(), RecurrenceError> {
+    let Some(rule) = &activity.recurrence else {
+        return Ok(());
+    };
+    if rule.interval == 0 {
+        return Err(RecurrenceError::InvalidInterval);
+    }
+    let dates = scheduled_dates(activity);
+    if dates.is_empty() {
+        return Err(RecurrenceError::NoOccurrences);
+    }
+    if dates.len() > MAX_OCCURRENCES {
+        return Err(RecurrenceError::TooManyOccurrences);
+    }
+    
+    for (i, exception) in rule.exceptions.iter().enumerate() {
+        let date = exception.date;
+        if !dates.contains(&date) {
+            return Err(RecurrenceError::UnknownOccurrence(date));
+        }
+        if rule.exceptions[..i].iter().any(|e| e.date == date) {
+            return Err(RecurrenceError::DuplicateException(date));
+        }
+        if exception.skip {
+            continue;
+        }
+        let start = exception.start_date.unwrap_or_else(|| date.and_time(activity.start_date.time()).and_utc());
+        let end = exception.end_date.unwrap_or(start + duration(rule));
+        if end < start || start < activity.start_date || end > activity.end_date {
+            return Err(RecurrenceError::InvalidMove(date));
+        }
+        if exception.title.as_ref().is_some_and(|t| t.trim().is_empty() || t.chars().count() > 200) {
+            return Err(RecurrenceError::InvalidTitle(date));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OccurrenceException;
+    
+    fn series(start: &str, end: &str, recurrence: serde_json::Value) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": "a-1", "title": "Staff meeting", "startDate": start, "endDate": end,
+            "type": "meeting", "color": "#000000", "highlightColor": "#000000",
+            "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+            "recurrence": recurrence,
+        })).unwrap()
+    }
+    
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+    
+    #[test]
+    fn test_schedule() {
+        let weekly = series("2025-01-06T09:00:00Z", "2025-02-03T23:59:59Z", serde_json::json!({ "frequency": "weekly", "interval": 2 }));
+        assert_eq!(scheduled_dates(&weekly), vec![date("2025-01-06"), date("2025-01-20"), date("2025-02-03")]);
+        
+        // Occurrences that would end after the series are left out
+        let long = series("2025-01-06T00:00:00Z", "2025-01-20T12:00:00Z", serde_json::json!({ "frequency": "weekly", "durationDays": 1 }));
+        assert_eq!(scheduled_dates(&long), vec![date("2025-01-06"), date("2025-01-13")]);
+        
+        // No 31 February, April or June
+        let monthly = series("2025-01-31T00:00:00Z", "2025-07-31T00:00:00Z", serde_json::json!({ "frequency": "monthly" }));
+        assert_eq!(
+            scheduled_dates(&monthly),
+            ["2025-01-31", "2025-03-31", "2025-05-31", "2025-07-31"].map(date).to_vec(),
+        );
+        
+        let leap = series("2024-02-29T00:00:00Z", "2032-12-31T00:00:00Z", serde_json::json!({ "frequency": "yearly" }));
+        assert_eq!(scheduled_dates(&leap), ["2024-02-29", "2028-02-29", "2032-02-29"].map(date).to_vec());
+        
+        let mut single = weekly.clone();
+        single.recurrence = None;
+        assert!(scheduled_dates(&single).is_empty());
+        assert_eq!(expand(vec![single.clone()], Some(2025)).len(), 1);
+    }
+    
+    #[test]
+    fn test_exceptions() {
+        let activity = series("2025-01-06T09:00:00Z", "2025-01-31T00:00:00Z", serde_json::json!({
+            "frequency": "weekly",
+            "exceptions": [
+                { "date": "2025-01-13", "skip": true },
+                { "date": "2025-01-20", "startDate": "2025-01-22T09:00:00Z" },
+                { "date": "2025-01-27", "title": "Staff meeting (offsite)" },
+            ],
+        }));
+        assert_eq!(validate(&activity), Ok(()));
+        
+        let expanded = expand(vec![activity.clone()], Some(2025));
+        let summary: Vec<(&str, String, &str)> = expanded.iter()
+            .map(|a| (a.id.as_str(), a.start_date.to_rfc3339(), a.title.as_str()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("a-1_20250106", "2025-01-06T09:00:00+00:00".to_string(), "Staff meeting"),
+            ("a-1_20250120", "2025-01-22T09:00:00+00:00".to_string(), "Staff meeting"),
+            ("a-1_20250127", "2025-01-27T09:00:00+00:00".to_string(), "Staff meeting (offsite)"),
+        ]);
+        assert!(expanded.iter().all(|a| a.recurrence.is_none() && a.end_date == a.start_date));
+        assert!(expand(vec![activity.clone()], Some(2026)).is_empty());
+        
+        let with = |exception: OccurrenceException| {
+            let mut changed = activity.clone();
+            changed.recurrence.as_mut().unwrap().exceptions.push(exception);
+            validate(&changed)
+        };
+        let exception = OccurrenceException { date: date("2025-01-08"), skip: true, start_date: None, end_date: None, title: None };
+        assert_eq!(with(exception.clone()), Err(RecurrenceError::UnknownOccurrence(date("2025-01-08"))));
+        assert_eq!(with(OccurrenceException { date: date("2025-01-13"), ..exception.clone() }), Err(RecurrenceError::DuplicateException(date("2025-01-13"))));
+        
+        let moved = OccurrenceException { date: date("2025-01-06"), skip: false, ..exception };
+        let mut first = activity.clone();
+        first.recurrence.as_mut().unwrap().exceptions.clear();
+        let check = |exception: OccurrenceException| {
+            let mut changed = first.clone();
+            changed.recurrence.as_mut().unwrap().exceptions.push(exception);
+            validate(&changed)
+        };
+        let outside = OccurrenceException { start_date: Some("2025-02-03T09:00:00Z".parse().unwrap()), ..moved.clone() };
+        assert_eq!(check(outside), Err(RecurrenceError::InvalidMove(date("2025-01-06"))));
+        let backwards = OccurrenceException { end_date: Some("2025-01-06T08:00:00Z".parse().unwrap()), ..moved.clone() };
+        assert_eq!(check(backwards), Err(RecurrenceError::InvalidMove(date("2025-01-06"))));
+        assert_eq!(check(OccurrenceException { title: Some(" ".to_string()), ..moved }), Err(RecurrenceError::InvalidTitle(date("2025-01-06"))));
+    }
+    
+    #[test]
+    fn test_validate_schedule() {
+        let daily = |end: &str, interval: u32| series("2025-01-01T00:00:00Z", end, serde_json::json!({ "frequency": "daily", "interval": interval }));
+        assert_eq!(validate(&daily("2025-12-31T00:00:00Z", 1)), Ok(()));
+        assert_eq!(validate(&daily("2026-01-02T00:00:00Z", 1)), Err(RecurrenceError::TooManyOccurrences));
+        assert_eq!(validate(&daily("2026-01-02T00:00:00Z", 0)), Err(RecurrenceError::InvalidInterval));
+        
+        let too_long = series("2025-01-01T00:00:00Z", "2025-01-02T00:00:00Z", serde_json::json!({ "frequency": "weekly", "durationDays": 3 }));
+        assert_eq!(validate(&too_long), Err(RecurrenceError::NoOccurrences));
+    }
+}
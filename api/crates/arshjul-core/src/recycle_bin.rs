@@ -0,0 +1,264 @@
+//! # Recycle Bin
+//!
+//! `DELETE /api/shares/{id}` and `DELETE /api/activities/{id}` only set
+//! `deletedAt`. [`RecycleBin`], which [`Storage::new`](crate::storage::Storage::new)
+//! puts in front of every share and activity backend, then hides the item:
+//! `get`, short code lookups and lists no longer return it, so deleted
+//! shares stop working at once. Lists filter after the backend, so pages
+//! may come back short and `totalCount` may still count deleted items.
+//!
+//! Until the retention window (`DELETED_ITEM_RETENTION_DAYS`, default
+//! [`DEFAULT_RETENTION_DAYS`]) has passed, `POST /api/shares/{id}/restore`
+//! and `POST /api/activities/{id}/restore` bring an item back unchanged.
+//! Afterwards [`RecycleBinCleanup`] deletes it for good through the
+//! backend's [`DeletedItemPurger`]; a share's code is then retired like
+//! any deleted share's.
+
+use crate::clock::Clock;
+use crate::models::*;
+use crate::storage::*;
+use async_trait::async_trait;
+use chrono::Duration;
+use std::sync::Arc;
+
+/// Days shares and activities stay restorable after being deleted
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+fn not_deleted<T>(item: T, deleted: bool, id: &str) -> Result<T, StorageError> {
+    if deleted {
+        return Err(StorageError::NotFound(id.to_string()));
+    }
+    Ok(item)
+}
+
+/// Share or activity storage without the items in the recycle bin
+pub struct RecycleBin<S: ?Sized> {
+    inner: Arc<S>,
+}
+
+impl<S: ?Sized> RecycleBin<S> {
+    pub fn new(inner: Arc<S>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl<S: ShareStorage + ?Sized> ShareStorage for RecycleBin<S> {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        self.inner.create(share).await
+    }
+    
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        let share = self.inner.get(organization_id, share_id).await?;
+        let deleted = share.deleted_at.is_some();
+        not_deleted(share, deleted, share_id)
+    }
+    
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        let share = self.inner.get_by_short_code(short_code).await?;
+        let deleted = share.deleted_at.is_some();
+        not_deleted(share, deleted, short_code)
+    }
+    
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        self.inner.update(share).await
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        let (share, etag) = self.inner.get_tagged(organization_id, share_id).await?;
+        let deleted = share.deleted_at.is_some();
+        Ok((not_deleted(share, deleted, share_id)?, etag))
+    }
+    
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        self.inner.replace(share, etag).await
+    }
+    
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.delete(organization_id, share_id).await
+    }
+    
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        self.inner.get_tombstone(short_code).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        let mut result = self.inner.list(organization_id, options).await?;
+        result.items.retain(|s| s.deleted_at.is_none());
+        Ok(result)
+    }
+    
+    async fn list_by_layer(&self, organization_id: &str, layer_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        let mut shares = self.inner.list_by_layer(organization_id, layer_id).await?;
+        shares.retain(|s| s.deleted_at.is_none());
+        Ok(shares)
+    }
+    
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.increment_views(organization_id, share_id).await
+    }
+    
+    async fn get_deleted(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.inner.get_deleted(organization_id, share_id).await
+    }
+    
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        self.inner.list_deleted(organization_id).await
+    }
+}
+
+#[async_trait]
+impl<S: ActivityStorage + ?Sized> ActivityStorage for RecycleBin<S> {
+    async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+        self.inner.create(activity).await
+    }
+    
+    async fn create_many(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        self.inner.create_many(activities).await
+    }
+    
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        let activity = self.inner.get(organization_id, activity_id).await?;
+        let deleted = activity.deleted_at.is_some();
+        not_deleted(activity, deleted, activity_id)
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, activity_id: &str) -> Result<(Activity, String), StorageError> {
+        let (activity, etag) = self.inner.get_tagged(organization_id, activity_id).await?;
+        let deleted = activity.deleted_at.is_some();
+        Ok((not_deleted(activity, deleted, activity_id)?, etag))
+    }
+    
+    async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+        self.inner.update(activity).await
+    }
+    
+    async fn update_batch(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        self.inner.update_batch(activities).await
+    }
+    
+    async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+        self.inner.delete(organization_id, activity_id).await
+    }
+    
+    async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+        self.inner.apply_changes(organization_id, changes).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<Activity>, StorageError> {
+        let mut result = self.inner.list(organization_id, options).await?;
+        result.items.retain(|a| a.deleted_at.is_none());
+        Ok(result)
+    }
+    
+    async fn list_by_layers(
+        &self,
+        organization_id: &str,
+        layer_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<Activity>, StorageError> {
+        let mut activities = self.inner.list_by_layers(organization_id, layer_ids, year).await?;
+        activities.retain(|a| a.deleted_at.is_none());
+        Ok(activities)
+    }
+    
+    async fn get_deleted(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        self.inner.get_deleted(organization_id, activity_id).await
+    }
+    
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<Activity>, StorageError> {
+        self.inner.list_deleted(organization_id).await
+    }
+}
+
+/// Periodic emptying of the recycle bin
+pub struct RecycleBinCleanup {
+    purgers: Vec<Arc<dyn DeletedItemPurger>>,
+    retention: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl RecycleBinCleanup {
+    pub fn new(purgers: Vec<Arc<dyn DeletedItemPurger>>, retention: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self { purgers, retention, clock }
+    }
+    
+    /// Delete items in the recycle bin for longer than the retention period; returns how many
+    pub async fn run_once(&self) -> Result<u64, StorageError> {
+        let cutoff = self.clock.now() - self.retention;
+        let mut total = 0;
+        for purger in &self.purgers {
+            let deleted = purger.purge_deleted(cutoff).await?;
+            if deleted > 0 {
+                tracing::info!(backend = purger.name(), deleted, "Emptied recycle bin");
+            }
+            total += deleted;
+        }
+        Ok(total)
+    }
+    
+    /// Clean up every `interval` until the task is dropped
+    #[cfg(feature = "server")]
+    pub async fn run(self: Arc<Self>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.run_once().await {
+                tracing::warn!(error = %e, "Recycle bin cleanup failed");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::{DateTime, Utc};
+    
+    fn activity(id: &str, deleted_at: Option<&str>) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "title": id, "type": "other", "color": "#000000", "highlightColor": "#000000",
+            "startDate": "2025-03-01T00:00:00Z", "endDate": "2025-03-02T00:00:00Z",
+            "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+            "deletedAt": deleted_at,
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_hides_deleted_items() {
+        let storage = Storage::in_memory();
+        storage.activities.create(activity("kept", None)).await.unwrap();
+        storage.activities.create(activity("binned", Some("2025-03-05T00:00:00Z"))).await.unwrap();
+        
+        assert!(matches!(storage.activities.get("org-1", "binned").await, Err(StorageError::NotFound(_))));
+        assert!(matches!(storage.activities.get_deleted("org-1", "kept").await, Err(StorageError::NotFound(_))));
+        assert_eq!(storage.activities.get_deleted("org-1", "binned").await.unwrap().id, "binned");
+        
+        let listed = storage.activities.list_by_layers("org-1", &["layer-1".to_string()], None).await.unwrap();
+        assert_eq!(listed.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), ["kept"]);
+        let deleted = storage.activities.list_deleted("org-1").await.unwrap();
+        assert_eq!(deleted.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), ["binned"]);
+    }
+    
+    #[tokio::test]
+    async fn test_cleanup_after_retention() {
+        let storage = Storage::in_memory();
+        storage.activities.create(activity("old", Some("2025-03-01T00:00:00Z"))).await.unwrap();
+        storage.activities.create(activity("recent", Some("2025-03-20T00:00:00Z"))).await.unwrap();
+        
+        let now: DateTime<Utc> = "2025-04-10T00:00:00Z".parse().unwrap();
+        let cleanup = RecycleBinCleanup::new(storage.deleted_items.clone(), Duration::days(DEFAULT_RETENTION_DAYS), Arc::new(ManualClock::new(now)));
+        assert_eq!(cleanup.run_once().await.unwrap(), 1);
+        assert!(storage.activities.get_deleted("org-1", "old").await.is_err());
+        assert!(storage.activities.get_deleted("org-1", "recent").await.is_ok());
+    }
+}
@@ -0,0 +1,128 @@
+//! # Schema Versions
+//!
+//! Table Storage rows keep the model as JSON in `data`, so a model change
+//! can leave rows that no longer deserialize. Each row carries the version
+//! of its payload in `schema_version`; rows written before the marker have
+//! none and count as version 0.
+//!
+//! On read, [`upgrade`] runs the payload through the [`MIGRATIONS`] of its
+//! entity type, from its version up to [`current_version`]. Writes always
+//! use the current version, so a row is upgraded for good the next time it
+//! is saved. `POST /api/admin/schema/rewrite` saves all of an organization's
+//! rows again in the background (see [`crate::operations`]), after which
+//! the old migrations no longer run on its reads.
+//!
+//! A row with a version newer than the code knows fails to read instead of
+//! losing the fields it doesn't understand, e.g. while a deployment is
+//! rolled back.
+//!
+//! ## Adding a migration
+//!
+//! 1. Change the model
+//! 2. Append a [`Migration`] from the entity's current version that turns
+//!    the old JSON into the new
+//!
+//! Released migrations are never changed or removed: rows of any version
+//! may still be out there.
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// One step from a version of an entity's payload to the next
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// `entity_type` of the rows
+    pub entity: &'static str,
+    /// Version upgraded from, to `from + 1`
+    pub from: u32,
+    pub description: &'static str,
+    pub upgrade: fn(&mut Value) -> Result<(), String>,
+}
+
+fn unchanged(_: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+/// Every migration, per entity in version order
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { entity: "share", from: 0, description: "Rows written before schema versions", upgrade: unchanged },
+    Migration { entity: "activity", from: 0, description: "Rows written before schema versions", upgrade: unchanged },
+    Migration { entity: "layer", from: 0, description: "Rows written before schema versions", upgrade: unchanged },
+    Migration { entity: "activity_type", from: 0, description: "Rows written before schema versions", upgrade: unchanged },
+    Migration { entity: "user_settings", from: 0, description: "Rows written before schema versions", upgrade: unchanged },
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SchemaError {
+    #[error("{entity} row has schema version {version}, newer than {current}")]
+    Newer { entity: String, version: u32, current: u32 },
+
+    #[error("{entity} row has schema version {version} and no migration from it")]
+    Missing { entity: String, version: u32 },
+
+    #[error("Migrating {entity} row from schema version {from} failed: {reason}")]
+    Failed { entity: String, from: u32, reason: String },
+}
+
+/// Version written for an entity type
+pub fn current_version(entity: &str) -> u32 {
+    current_version_in(MIGRATIONS, entity)
+}
+
+/// Bring a payload of `version` up to the current version
+pub fn upgrade(entity: &str, version: u32, value: Value) -> Result<Value, SchemaError> {
+    upgrade_with(MIGRATIONS, entity, version, value)
+}
+
+fn current_version_in(migrations: &[Migration], entity: &str) -> u32 {
+    migrations.iter().filter(|m| m.entity == entity).map(|m| m.from + 1).max().unwrap_or(0)
+}
+
+fn upgrade_with(migrations: &[Migration], entity: &str, version: u32, mut value: Value) -> Result<Value, SchemaError> {
+    let current = current_version_in(migrations, entity);
+    if version > current {
+        return Err(SchemaError::Newer { entity: entity.to_string(), version, current });
+    }
+    for from in version..current {
+        let migration = migrations.iter().find(|m| m.entity == entity && m.from == from)
+            .ok_or_else(|| SchemaError::Missing { entity: entity.to_string(), version: from })?;
+        (migration.upgrade)(&mut value)
+            .map_err(|reason| SchemaError::Failed { entity: entity.to_string(), from, reason })?;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename_title(value: &mut Value) -> Result<(), String> {
+        let title = value.as_object_mut().and_then(|o| o.remove("name")).ok_or("no name")?;
+        value["title"] = title;
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade_runs_migrations_in_order() {
+        // Every entity's chain starts at 0 and has no gaps
+        for migration in MIGRATIONS {
+            let current = current_version(migration.entity);
+            assert!((0..current).all(|from| MIGRATIONS.iter().any(|m| m.entity == migration.entity && m.from == from)));
+        }
+        assert_eq!(current_version("activity"), 1);
+        assert_eq!(upgrade("activity", 0, json!({ "id": "a-1" })).unwrap(), json!({ "id": "a-1" }));
+
+        let migrations = [
+            Migration { entity: "layer", from: 0, description: "", upgrade: unchanged },
+            Migration { entity: "layer", from: 1, description: "Rename name to title", upgrade: rename_title },
+        ];
+        assert_eq!(upgrade_with(&migrations, "layer", 0, json!({ "name": "Plan" })).unwrap(), json!({ "title": "Plan" }));
+        assert_eq!(upgrade_with(&migrations, "layer", 2, json!({ "title": "Plan" })).unwrap(), json!({ "title": "Plan" }));
+        assert_eq!(
+            upgrade_with(&migrations, "layer", 3, json!({})),
+            Err(SchemaError::Newer { entity: "layer".to_string(), version: 3, current: 2 }),
+        );
+        assert!(matches!(upgrade_with(&migrations, "layer", 1, json!({})), Err(SchemaError::Failed { from: 1, .. })));
+    }
+}
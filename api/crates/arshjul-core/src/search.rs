@@ -0,0 +1,200 @@
+//! # Activity Search
+//!
+//! Full-text search over an organization's activities for
+//! `GET /api/activities/search`.
+//!
+//! Small tenants are served by scanning storage ([`search_in_memory`]).
+//! Large tenants can configure an external [`ActivitySearchIndex`] (Azure AI
+//! Search in `arshjul-azure`), kept current by [`ActivityIndexer`] on the
+//! event bus. When the index fails the handler falls back to the scan, and
+//! index hits are re-read from storage so stale entries never leak.
+
+use crate::events::{ChangeKind, DomainEvent, EntityKind, EventError, EventSubscriber};
+use crate::models::*;
+use crate::storage::{ActivityStorage, StorageError};
+use async_trait::async_trait;
+use chrono::Datelike;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Results returned when `top` is not given
+pub const DEFAULT_SEARCH_RESULTS: usize = 25;
+
+/// Upper bound for `top`
+pub const MAX_SEARCH_RESULTS: usize = 100;
+
+/// Longest accepted search text
+pub const MAX_QUERY_LEN: usize = 200;
+
+/// Search index errors
+#[derive(Debug, Error)]
+pub enum SearchError {
+    #[error("Search index unavailable: {0}")]
+    Unavailable(String),
+    
+    #[error("Invalid search response: {0}")]
+    InvalidResponse(String),
+}
+
+/// External full-text index of activities
+#[async_trait]
+pub trait ActivitySearchIndex: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Add or replace an activity document
+    async fn upsert(&self, activity: &Activity) -> Result<(), SearchError>;
+    
+    /// Remove an activity document
+    async fn remove(&self, organization_id: &str, activity_id: &str) -> Result<(), SearchError>;
+    
+    /// IDs of matching activities in the organization, best match first
+    async fn search(
+        &self,
+        organization_id: &str,
+        request: &SearchActivitiesRequest,
+        top: usize,
+    ) -> Result<Vec<String>, SearchError>;
+}
+
+/// Validate a search request, returning the number of results to return
+pub fn validate_request(request: &SearchActivitiesRequest) -> Result<usize, String> {
+    let text = request.q.trim();
+    if text.is_empty() {
+        return Err("Search text is required".to_string());
+    }
+    if text.chars().count() > MAX_QUERY_LEN {
+        return Err(format!("Search text must be at most {} characters", MAX_QUERY_LEN));
+    }
+    
+    match request.top {
+        None => Ok(DEFAULT_SEARCH_RESULTS),
+        Some(top) if (1..=MAX_SEARCH_RESULTS).contains(&top) => Ok(top),
+        Some(_) => Err(format!("top must be between 1 and {}", MAX_SEARCH_RESULTS)),
+    }
+}
+
+/// Whether an activity passes the year and layer filters
+pub fn matches_filters(activity: &Activity, request: &SearchActivitiesRequest) -> bool {
+    let in_year = request.year
+        .is_none_or(|y| activity.start_date.year() <= y && activity.end_date.year() >= y);
+    let in_layers = request.layer_ids.as_ref()
+        .is_none_or(|ids| ids.contains(&activity.scope));
+    in_year && in_layers
+}
+
+/// Relevance of an activity: 0 unless every term matches; title hits count double
+fn score(activity: &Activity, terms: &[String]) -> usize {
+    let title = activity.title.to_lowercase();
+    let description = activity.description.as_deref().unwrap_or_default().to_lowercase();
+    
+    let mut score = 0;
+    for term in terms {
+        match (title.contains(term.as_str()), description.contains(term.as_str())) {
+            (true, _) => score += 2,
+            (false, true) => score += 1,
+            (false, false) => return 0,
+        }
+    }
+    score
+}
+
+/// Case-insensitive search over activities already loaded from storage
+pub fn search_in_memory(activities: Vec<Activity>, request: &SearchActivitiesRequest, top: usize) -> Vec<Activity> {
+    let terms: Vec<String> = request.q.split_whitespace().map(str::to_lowercase).collect();
+    
+    let mut hits: Vec<(usize, Activity)> = activities.into_iter()
+        .filter(|a| matches_filters(a, request))
+        .map(|a| (score(&a, &terms), a))
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    hits.sort_by(|(sa, a), (sb, b)| sb.cmp(sa).then(a.start_date.cmp(&b.start_date)));
+    
+    hits.into_iter().take(top).map(|(_, a)| a).collect()
+}
+
+/// Event subscriber pushing activity changes into the search index
+pub struct ActivityIndexer {
+    index: Arc<dyn ActivitySearchIndex>,
+    activities: Arc<dyn ActivityStorage>,
+}
+
+impl ActivityIndexer {
+    pub fn new(index: Arc<dyn ActivitySearchIndex>, activities: Arc<dyn ActivityStorage>) -> Self {
+        Self { index, activities }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for ActivityIndexer {
+    fn name(&self) -> &'static str {
+        "search-index"
+    }
+    
+    async fn handle(&self, event: &DomainEvent) -> Result<(), EventError> {
+        let DomainEvent::EntityChanged(change) = event else {
+            return Ok(());
+        };
+        if change.entity != EntityKind::Activity {
+            return Ok(());
+        }
+        
+        let result = match change.change {
+            ChangeKind::Deleted => self.index.remove(&change.organization_id, &change.entity_id).await,
+            ChangeKind::Created | ChangeKind::Updated => {
+                // Index the stored version; the event may be older than the latest write
+                match self.activities.get(&change.organization_id, &change.entity_id).await {
+                    Ok(activity) => self.index.upsert(&activity).await,
+                    Err(StorageError::NotFound(_)) => self.index.remove(&change.organization_id, &change.entity_id).await,
+                    Err(e) => return Err(EventError::Delivery(e.to_string())),
+                }
+            }
+        };
+        
+        result.map_err(|e| EventError::Delivery(format!("{}: {}", self.index.name(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn activity(id: &str, title: &str, description: Option<&str>, start: &str) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "title": title, "description": description,
+            "startDate": start, "endDate": start, "type": "meeting",
+            "color": "#000000", "highlightColor": "#000000",
+            "scope": "layer-1", "scopeId": "layer-1", "organizationId": "org-1",
+        })).unwrap()
+    }
+    
+    #[test]
+    fn test_search_in_memory_ranks_title_matches_first() {
+        let activities = vec![
+            activity("a", "Budget kickoff", None, "2025-02-01T00:00:00Z"),
+            activity("b", "Board meeting", Some("Final budget review"), "2025-01-01T00:00:00Z"),
+            activity("c", "Summer party", None, "2025-06-01T00:00:00Z"),
+            activity("d", "Budget review", None, "2024-03-01T00:00:00Z"),
+        ];
+        let request = SearchActivitiesRequest {
+            q: "BUDGET".to_string(),
+            year: Some(2025),
+            ..Default::default()
+        };
+        
+        let ids: Vec<String> = search_in_memory(activities, &request, 10).into_iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+    
+    #[test]
+    fn test_validate_request() {
+        let mut request = SearchActivitiesRequest { q: "  ".to_string(), ..Default::default() };
+        assert!(validate_request(&request).is_err());
+        
+        request.q = "budget".to_string();
+        assert_eq!(validate_request(&request), Ok(DEFAULT_SEARCH_RESULTS));
+        
+        request.top = Some(MAX_SEARCH_RESULTS + 1);
+        assert!(validate_request(&request).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! # Share Cache
+//!
+//! Public share access looks the share up by short code on every request.
+//! [`CachedShareStorage`] wraps any [`ShareStorage`] and reads
+//! `get_by_short_code` through a [`ShareCache`]:
+//!
+//! - [`InProcessShareCache`] - per instance, bounded
+//! - `RedisShareCache` - shared by all instances (`arshjul-azure`, `redis` feature)
+//!
+//! Updates (key regeneration, deactivation, renewal, ...) and deletes made
+//! through the wrapper drop the cached entry. Entries expire after the TTL,
+//! which bounds how stale writes made elsewhere get: other instances with
+//! an in-process cache, and view counts. Cache failures are logged and the
+//! lookup falls through to the wrapped storage.
+
+use crate::clock::{Clock, SystemClock};
+use crate::models::*;
+use crate::storage::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Shares held by an in-process cache; the entry expiring first is dropped beyond this
+pub const MAX_CACHED_SHARES: usize = 10_000;
+
+/// Cache of shares by short code
+#[async_trait]
+pub trait ShareCache: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Cached share, if any
+    async fn get(&self, short_code: &str) -> Result<Option<ShareLink>, StorageError>;
+    
+    /// Cache a share under its short code
+    async fn put(&self, share: &ShareLink) -> Result<(), StorageError>;
+    
+    /// Drop the cached share
+    async fn remove(&self, short_code: &str) -> Result<(), StorageError>;
+}
+
+/// Cached shares by short code, with their expiry
+type Entries = HashMap<String, (ShareLink, DateTime<Utc>)>;
+
+/// Per-instance share cache
+pub struct InProcessShareCache {
+    entries: Mutex<Entries>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl InProcessShareCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, Arc::new(SystemClock))
+    }
+    
+    /// Expire entries by another time source (tests)
+    pub fn with_clock(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self { entries: Mutex::new(HashMap::new()), ttl, clock }
+    }
+    
+    fn lock(&self) -> Result<MutexGuard<'_, Entries>, StorageError> {
+        self.entries.lock().map_err(|e| StorageError::Storage(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl ShareCache for InProcessShareCache {
+    fn name(&self) -> &'static str {
+        "in-process"
+    }
+    
+    async fn get(&self, short_code: &str) -> Result<Option<ShareLink>, StorageError> {
+        let now = self.clock.now();
+        let mut entries = self.lock()?;
+        match entries.get(short_code) {
+            Some((share, expires)) if *expires > now => Ok(Some(share.clone())),
+            Some(_) => {
+                entries.remove(short_code);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+    
+    async fn put(&self, share: &ShareLink) -> Result<(), StorageError> {
+        let now = self.clock.now();
+        let mut entries = self.lock()?;
+        if entries.len() >= MAX_CACHED_SHARES && !entries.contains_key(&share.short_code) {
+            entries.retain(|_, (_, expires)| *expires > now);
+            if entries.len() >= MAX_CACHED_SHARES {
+                let first = entries.iter().min_by_key(|(_, (_, expires))| *expires).map(|(code, _)| code.clone());
+                if let Some(code) = first {
+                    entries.remove(&code);
+                }
+            }
+        }
+        entries.insert(share.short_code.clone(), (share.clone(), now + self.ttl));
+        Ok(())
+    }
+    
+    async fn remove(&self, short_code: &str) -> Result<(), StorageError> {
+        self.lock()?.remove(short_code);
+        Ok(())
+    }
+}
+
+/// Share storage whose short code lookups are read through a cache
+pub struct CachedShareStorage<S: ?Sized> {
+    inner: Arc<S>,
+    cache: Arc<dyn ShareCache>,
+}
+
+impl<S: ?Sized> CachedShareStorage<S> {
+    pub fn new(inner: Arc<S>, cache: Arc<dyn ShareCache>) -> Self {
+        Self { inner, cache }
+    }
+    
+    async fn forget(&self, short_code: &str) {
+        if let Err(e) = self.cache.remove(short_code).await {
+            tracing::warn!(cache = self.cache.name(), error = %e, "Failed to drop cached share");
+        }
+    }
+}
+
+#[async_trait]
+impl<S: ShareStorage + ?Sized> ShareStorage for CachedShareStorage<S> {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        self.inner.create(share).await
+    }
+    
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.inner.get(organization_id, share_id).await
+    }
+    
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        match self.cache.get(short_code).await {
+            Ok(Some(share)) => return Ok(share),
+            Ok(None) => {}
+            Err(e) => tracing::warn!(cache = self.cache.name(), error = %e, "Share cache unavailable"),
+        }
+        
+        let share = self.inner.get_by_short_code(short_code).await?;
+        if let Err(e) = self.cache.put(&share).await {
+            tracing::warn!(cache = self.cache.name(), error = %e, "Failed to cache share");
+        }
+        Ok(share)
+    }
+    
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let updated = self.inner.update(share).await?;
+        self.forget(&updated.short_code).await;
+        Ok(updated)
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        self.inner.get_tagged(organization_id, share_id).await
+    }
+    
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        let replaced = self.inner.replace(share, etag).await?;
+        if let Some(ref share) = replaced {
+            self.forget(&share.short_code).await;
+        }
+        Ok(replaced)
+    }
+    
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        let short_code = self.inner.get(organization_id, share_id).await?.short_code;
+        self.inner.delete(organization_id, share_id).await?;
+        self.forget(&short_code).await;
+        Ok(())
+    }
+    
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        self.inner.get_tombstone(short_code).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        self.inner.list(organization_id, options).await
+    }
+    
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.increment_views(organization_id, share_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::storage::memory_storage::MemoryShareStorage;
+    
+    fn share() -> ShareLink {
+        serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "k".repeat(64), "shortCode": "Code0001",
+            "visibility": "public", "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2099-01-01T00:00:00Z",
+            "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_reads_through_and_drops_on_write() {
+        let inner = Arc::new(MemoryShareStorage::new());
+        let cached = CachedShareStorage::new(inner.clone(), Arc::new(InProcessShareCache::new(Duration::minutes(5))));
+        inner.create(share()).await.unwrap();
+        assert!(cached.get_by_short_code("Code0001").await.unwrap().is_active);
+        
+        // Writes past the wrapper are not seen until the entry goes
+        let mut deactivated = share();
+        deactivated.is_active = false;
+        inner.update(deactivated.clone()).await.unwrap();
+        assert!(cached.get_by_short_code("Code0001").await.unwrap().is_active);
+        
+        cached.update(deactivated).await.unwrap();
+        assert!(!cached.get_by_short_code("Code0001").await.unwrap().is_active);
+        
+        cached.delete("org-1", "share-1").await.unwrap();
+        assert!(matches!(cached.get_by_short_code("Code0001").await, Err(StorageError::NotFound(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_in_process_cache_expires() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let cache = InProcessShareCache::with_clock(Duration::seconds(60), clock.clone());
+        cache.put(&share()).await.unwrap();
+        assert!(cache.get("Code0001").await.unwrap().is_some());
+        
+        clock.advance(Duration::seconds(61));
+        assert!(cache.get("Code0001").await.unwrap().is_none());
+    }
+}
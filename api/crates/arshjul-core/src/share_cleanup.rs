@@ -0,0 +1,93 @@
+//! # Expired Share Cleanup
+//!
+//! Table Storage has no TTL, so expired shares would stay in the `shares`
+//! table, and their codes in the `shortcodes` index, forever. [`ShareCleanup`]
+//! deletes shares once they have been expired for the retention period,
+//! through the backend's [`ExpiredSharePurger`]:
+//!
+//! - [`ShareCleanup::run_once`] - one pass, for a timer trigger
+//! - [`ShareCleanup::run`] - a pass every interval, for the standalone server
+//!
+//! The retention period (`EXPIRED_SHARE_RETENTION_DAYS`, default
+//! [`DEFAULT_RETENTION_DAYS`]) leaves owners time to renew a share that
+//! lapsed. Deleted shares' codes stay retired like any deleted share's;
+//! shares with a TTL were gone already and leave no tombstone.
+
+use crate::clock::Clock;
+use crate::storage::{ExpiredSharePurger, StorageError};
+use chrono::Duration;
+use std::sync::Arc;
+
+/// Days a share stays after expiring before it is deleted
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Periodic deletion of expired shares
+pub struct ShareCleanup {
+    purger: Arc<dyn ExpiredSharePurger>,
+    retention: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl ShareCleanup {
+    pub fn new(purger: Arc<dyn ExpiredSharePurger>, retention: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self { purger, retention, clock }
+    }
+    
+    /// Delete shares expired for longer than the retention period; returns how many
+    pub async fn run_once(&self) -> Result<u64, StorageError> {
+        let cutoff = self.clock.now() - self.retention;
+        let deleted = self.purger.purge_expired(cutoff).await?;
+        if deleted > 0 {
+            tracing::info!(backend = self.purger.name(), deleted, "Deleted expired shares");
+        }
+        Ok(deleted)
+    }
+    
+    /// Clean up every `interval` until the task is dropped
+    pub async fn run(self: Arc<Self>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.run_once().await {
+                tracing::warn!(backend = self.purger.name(), error = %e, "Expired share cleanup failed");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::models::ShareLink;
+    use crate::storage::memory_storage::MemoryShareStorage;
+    use crate::storage::ShareStorage;
+    use chrono::{DateTime, Utc};
+    
+    fn share(id: &str, short_code: &str, expires_at: DateTime<Utc>, ttl: Option<i64>) -> ShareLink {
+        ShareLink { expires_at, ttl, ..crate::storage_tests::share("org-1", id, short_code, "public") }
+    }
+    
+    #[tokio::test]
+    async fn test_deletes_shares_past_retention() {
+        let now = Utc::now();
+        let clock = Arc::new(ManualClock::new(now));
+        let storage = Arc::new(MemoryShareStorage::with_clock(clock.clone()));
+        storage.create(share("old", "OldCode1", now - Duration::days(40), None)).await.unwrap();
+        storage.create(share("lapsed", "Lapsed01", now - Duration::days(5), None)).await.unwrap();
+        storage.create(share("current", "Current1", now + Duration::days(5), None)).await.unwrap();
+        storage.create(share("ttl", "TtlCode1", now - Duration::days(40), Some(Duration::days(3650).num_seconds()))).await.unwrap();
+        
+        let cleanup = ShareCleanup::new(storage.clone(), Duration::days(DEFAULT_RETENTION_DAYS), clock);
+        assert_eq!(cleanup.run_once().await.unwrap(), 2);
+        assert_eq!(cleanup.run_once().await.unwrap(), 0);
+        
+        assert!(storage.get("org-1", "old").await.is_err());
+        assert!(storage.get("org-1", "lapsed").await.is_ok());
+        assert!(storage.get("org-1", "current").await.is_ok());
+        // The deleted share's code is retired, the TTL share's is free again
+        assert!(storage.get_tombstone("OldCode1").await.unwrap().is_some());
+        assert!(storage.get_tombstone("TtlCode1").await.unwrap().is_none());
+        storage.create(share("reuse", "TtlCode1", now + Duration::days(5), None)).await.unwrap();
+    }
+}
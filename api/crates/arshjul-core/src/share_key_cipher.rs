@@ -0,0 +1,539 @@
+//! # Share Key Encryption
+//!
+//! Share keys are bearer secrets: whoever reads one can open the share.
+//! Storage backends that keep the share as JSON would hand every key to
+//! anyone who can read the storage account, so [`ShareKeyCipher`] seals
+//! them before they are written and opens them after they are read.
+//!
+//! ## Scheme
+//!
+//! Envelope encryption with AES-256-GCM:
+//!
+//! - Each share key is sealed with a fresh random data key, the share ID as
+//!   associated data, so a sealed key copied onto another share won't open
+//! - The data key is sealed with the current key encryption key (KEK), its
+//!   key ID as associated data
+//! - Stored as `enc1:{keyId}:{base64url(nonce + sealed data key)}:{base64url(nonce + sealed share key)}`
+//!
+//! KEKs come from `SHARE_KEY_ENCRYPTION_KEYS` as `id=base64` pairs, the
+//! first one sealing and all of them opening. Rotate by putting a new key
+//! first and keeping the old ones until every share was written again. In
+//! Azure, point the setting at a Key Vault secret with a Key Vault reference
+//! (`@Microsoft.KeyVault(SecretUri=...)`).
+//!
+//! Values without the `enc1:` prefix are plaintext keys written before
+//! encryption was enabled; they are read as they are and sealed on the next
+//! write.
+//!
+//! ## Where keys are sealed
+//!
+//! Table Storage seals inside its entities. Every other place a share is
+//! kept outside the process gets a decorator that seals on the way in and
+//! opens on the way out:
+//!
+//! - [`SealedShareStorage`] - SQLite and Cosmos DB, including the Cosmos DB
+//!   copy of `DUAL_WRITE`
+//! - [`SealedShareCache`] - the Redis share cache
+//! - [`SealedSnapshotStore`] - organization snapshots in Blob Storage
+//! - [`SealedOperationStore`] - archives of background exports and imports
+//!   in Blob Storage
+//!
+//! Backup archives downloaded from `GET /api/admin/export` carry plaintext
+//! keys, so importing one elsewhere keeps its links working (see
+//! [`crate::backup`]).
+
+use crate::models::{BackupArchive, BulkOperation, OperationKind, ShareLink, ShortCodeTombstone};
+use crate::operations::{OperationInput, OperationStore};
+use crate::org_snapshots::{OrganizationSnapshotStore, StoredSnapshot};
+use crate::share_cache::ShareCache;
+use crate::storage::{QueryOptions, QueryResult, ShareStorage, StorageError};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine};
+use rand::Rng;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Prefix of sealed share keys
+pub const SEALED_PREFIX: &str = "enc1:";
+
+/// Length of key encryption and data keys (AES-256)
+pub const KEY_LEN: usize = 32;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShareKeyCipherError {
+    #[error("Invalid share key encryption keys: {0}")]
+    InvalidKeys(String),
+    
+    #[error("Share key sealed with unknown key '{0}'")]
+    UnknownKey(String),
+    
+    #[error("Share key of {0} could not be decrypted")]
+    Decrypt(String),
+}
+
+/// Seals and opens share keys with a set of key encryption keys
+pub struct ShareKeyCipher {
+    current: String,
+    keys: HashMap<String, LessSafeKey>,
+}
+
+impl std::fmt::Debug for ShareKeyCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShareKeyCipher").field("current", &self.current).finish_non_exhaustive()
+    }
+}
+
+fn aes_key(bytes: &[u8]) -> Option<LessSafeKey> {
+    UnboundKey::new(&AES_256_GCM, bytes).ok().map(LessSafeKey::new)
+}
+
+/// `nonce + ciphertext + tag`
+fn seal(key: &LessSafeKey, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let nonce: [u8; NONCE_LEN] = rand::thread_rng().gen();
+    let mut sealed = plaintext.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(aad), &mut sealed)
+        .expect("AES-GCM input within limits");
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&sealed);
+    out
+}
+
+fn open(key: &LessSafeKey, aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut buffer = ciphertext.to_vec();
+    let plaintext = key.open_in_place(nonce, Aad::from(aad), &mut buffer).ok()?;
+    Some(plaintext.to_vec())
+}
+
+impl ShareKeyCipher {
+    /// Parse `id=base64,id=base64`; the first key seals
+    pub fn from_spec(spec: &str) -> Result<Self, ShareKeyCipherError> {
+        let mut current = None;
+        let mut keys = HashMap::new();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (id, encoded) = pair.split_once('=')
+                .ok_or_else(|| ShareKeyCipherError::InvalidKeys(format!("expected id=base64, got '{}'", pair)))?;
+            let id = id.trim();
+            if id.is_empty() || id.contains(':') {
+                return Err(ShareKeyCipherError::InvalidKeys(format!("invalid key ID '{}'", id)));
+            }
+            let bytes = STANDARD.decode(encoded.trim())
+                .map_err(|_| ShareKeyCipherError::InvalidKeys(format!("key '{}' is not base64", id)))?;
+            if bytes.len() != KEY_LEN {
+                return Err(ShareKeyCipherError::InvalidKeys(format!("key '{}' must be {} bytes", id, KEY_LEN)));
+            }
+            let key = aes_key(&bytes)
+                .ok_or_else(|| ShareKeyCipherError::InvalidKeys(format!("key '{}' is not an AES-256 key", id)))?;
+            if keys.insert(id.to_string(), key).is_some() {
+                return Err(ShareKeyCipherError::InvalidKeys(format!("key '{}' is listed twice", id)));
+            }
+            current.get_or_insert_with(|| id.to_string());
+        }
+        let current = current.ok_or_else(|| ShareKeyCipherError::InvalidKeys("no keys".to_string()))?;
+        Ok(Self { current, keys })
+    }
+    
+    /// ID of the key that seals
+    pub fn current_key_id(&self) -> &str {
+        &self.current
+    }
+    
+    /// Seal a share's key for storage
+    pub fn seal(&self, share_id: &str, share_key: &str) -> String {
+        let data_key: [u8; KEY_LEN] = rand::thread_rng().gen();
+        let sealed_key = seal(&self.keys[&self.current], self.current.as_bytes(), &data_key);
+        let sealed_value = seal(
+            &aes_key(&data_key).expect("data key is 32 bytes"),
+            share_id.as_bytes(),
+            share_key.as_bytes(),
+        );
+        format!("{}{}:{}:{}", SEALED_PREFIX, self.current, URL_SAFE_NO_PAD.encode(sealed_key), URL_SAFE_NO_PAD.encode(sealed_value))
+    }
+    
+    /// Open a stored share key; plaintext keys are returned as they are
+    pub fn open(&self, share_id: &str, stored: &str) -> Result<String, ShareKeyCipherError> {
+        let Some(sealed) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+        let decrypt = || ShareKeyCipherError::Decrypt(share_id.to_string());
+        let mut parts = sealed.splitn(3, ':');
+        let (Some(key_id), Some(sealed_key), Some(sealed_value)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(decrypt());
+        };
+        let kek = self.keys.get(key_id).ok_or_else(|| ShareKeyCipherError::UnknownKey(key_id.to_string()))?;
+        
+        let sealed_key = URL_SAFE_NO_PAD.decode(sealed_key).map_err(|_| decrypt())?;
+        let data_key = open(kek, key_id.as_bytes(), &sealed_key).and_then(|k| aes_key(&k)).ok_or_else(decrypt)?;
+        let sealed_value = URL_SAFE_NO_PAD.decode(sealed_value).map_err(|_| decrypt())?;
+        let share_key = open(&data_key, share_id.as_bytes(), &sealed_value).ok_or_else(decrypt)?;
+        String::from_utf8(share_key).map_err(|_| decrypt())
+    }
+}
+
+/// Whether a stored share key is sealed
+pub fn is_sealed(stored: &str) -> bool {
+    stored.starts_with(SEALED_PREFIX)
+}
+
+impl ShareKeyCipher {
+    /// The share with its key sealed; sealed keys are left as they are
+    pub fn seal_share(&self, mut share: ShareLink) -> ShareLink {
+        if !is_sealed(&share.share_key) {
+            share.share_key = self.seal(&share.id, &share.share_key);
+        }
+        share
+    }
+    
+    /// The share with its key opened
+    pub fn open_share(&self, mut share: ShareLink) -> Result<ShareLink, StorageError> {
+        share.share_key = self.open(&share.id, &share.share_key)
+            .map_err(|e| StorageError::Storage(e.to_string()))?;
+        Ok(share)
+    }
+}
+
+/// Share storage that keeps share keys sealed in the backend
+pub struct SealedShareStorage<S: ?Sized> {
+    inner: Arc<S>,
+    cipher: Arc<ShareKeyCipher>,
+}
+
+impl<S: ?Sized> SealedShareStorage<S> {
+    pub fn new(inner: Arc<S>, cipher: Arc<ShareKeyCipher>) -> Self {
+        Self { inner, cipher }
+    }
+}
+
+#[async_trait]
+impl<S: ShareStorage + ?Sized> ShareStorage for SealedShareStorage<S> {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        self.cipher.open_share(self.inner.create(self.cipher.seal_share(share)).await?)
+    }
+    
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.cipher.open_share(self.inner.get(organization_id, share_id).await?)
+    }
+    
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        self.cipher.open_share(self.inner.get_by_short_code(short_code).await?)
+    }
+    
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        self.cipher.open_share(self.inner.update(self.cipher.seal_share(share)).await?)
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        let (share, etag) = self.inner.get_tagged(organization_id, share_id).await?;
+        Ok((self.cipher.open_share(share)?, etag))
+    }
+    
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        match self.inner.replace(self.cipher.seal_share(share), etag).await? {
+            Some(share) => self.cipher.open_share(share).map(Some),
+            None => Ok(None),
+        }
+    }
+    
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.delete(organization_id, share_id).await
+    }
+    
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        self.inner.get_tombstone(short_code).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        let result = self.inner.list(organization_id, options).await?;
+        Ok(QueryResult {
+            items: result.items.into_iter().map(|s| self.cipher.open_share(s)).collect::<Result<_, _>>()?,
+            ..result
+        })
+    }
+    
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.increment_views(organization_id, share_id).await
+    }
+    
+    async fn get_deleted(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.cipher.open_share(self.inner.get_deleted(organization_id, share_id).await?)
+    }
+    
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        self.inner.list_deleted(organization_id).await?.into_iter().map(|s| self.cipher.open_share(s)).collect()
+    }
+}
+
+/// Share cache that keeps share keys sealed in the cache
+pub struct SealedShareCache {
+    inner: Arc<dyn ShareCache>,
+    cipher: Arc<ShareKeyCipher>,
+}
+
+impl SealedShareCache {
+    pub fn new(inner: Arc<dyn ShareCache>, cipher: Arc<ShareKeyCipher>) -> Self {
+        Self { inner, cipher }
+    }
+}
+
+#[async_trait]
+impl ShareCache for SealedShareCache {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+    
+    async fn get(&self, short_code: &str) -> Result<Option<ShareLink>, StorageError> {
+        self.inner.get(short_code).await?.map(|s| self.cipher.open_share(s)).transpose()
+    }
+    
+    async fn put(&self, share: &ShareLink) -> Result<(), StorageError> {
+        self.inner.put(&self.cipher.seal_share(share.clone())).await
+    }
+    
+    async fn remove(&self, short_code: &str) -> Result<(), StorageError> {
+        self.inner.remove(short_code).await
+    }
+}
+
+/// Organization snapshot store that keeps the snapshots' share keys sealed
+pub struct SealedSnapshotStore {
+    inner: Arc<dyn OrganizationSnapshotStore>,
+    cipher: Arc<ShareKeyCipher>,
+}
+
+impl SealedSnapshotStore {
+    pub fn new(inner: Arc<dyn OrganizationSnapshotStore>, cipher: Arc<ShareKeyCipher>) -> Self {
+        Self { inner, cipher }
+    }
+    
+    /// The archive in `body` with `f` applied to its shares
+    fn map_shares(body: &[u8], f: impl Fn(ShareLink) -> Result<ShareLink, StorageError>) -> Result<Vec<u8>, StorageError> {
+        let mut archive: BackupArchive = serde_json::from_slice(body)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        map_archive_shares(&mut archive, f)?;
+        serde_json::to_vec(&archive).map_err(|e| StorageError::Serialization(e.to_string()))
+    }
+}
+
+/// Apply `f` to every share of an archive
+fn map_archive_shares(archive: &mut BackupArchive, f: impl Fn(ShareLink) -> Result<ShareLink, StorageError>) -> Result<(), StorageError> {
+    archive.shares = std::mem::take(&mut archive.shares).into_iter().map(f).collect::<Result<_, _>>()?;
+    Ok(())
+}
+
+#[async_trait]
+impl OrganizationSnapshotStore for SealedSnapshotStore {
+    async fn put(&self, organization_id: &str, snapshot_id: &str, body: Vec<u8>) -> Result<(), StorageError> {
+        let sealed = Self::map_shares(&body, |s| Ok(self.cipher.seal_share(s)))?;
+        self.inner.put(organization_id, snapshot_id, sealed).await
+    }
+    
+    async fn get(&self, organization_id: &str, snapshot_id: &str) -> Result<Vec<u8>, StorageError> {
+        Self::map_shares(&self.inner.get(organization_id, snapshot_id).await?, |s| self.cipher.open_share(s))
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<StoredSnapshot>, StorageError> {
+        self.inner.list(organization_id).await
+    }
+    
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        self.inner.delete_all(organization_id).await
+    }
+}
+
+/// Operation store that keeps the share keys of export results and import
+/// inputs sealed
+pub struct SealedOperationStore {
+    inner: Arc<dyn OperationStore>,
+    cipher: Arc<ShareKeyCipher>,
+}
+
+impl SealedOperationStore {
+    pub fn new(inner: Arc<dyn OperationStore>, cipher: Arc<ShareKeyCipher>) -> Self {
+        Self { inner, cipher }
+    }
+    
+    /// The operation with `f` applied to the shares of an export's archive
+    fn map_result(mut operation: BulkOperation, f: impl Fn(ShareLink) -> Result<ShareLink, StorageError>) -> Result<BulkOperation, StorageError> {
+        if operation.kind != OperationKind::Export {
+            return Ok(operation);
+        }
+        if let Some(result) = operation.result.take() {
+            let mut archive: BackupArchive = serde_json::from_value(result)
+                .map_err(|e| StorageError::Serialization(e.to_string()))?;
+            map_archive_shares(&mut archive, f)?;
+            operation.result = Some(serde_json::to_value(&archive).map_err(|e| StorageError::Serialization(e.to_string()))?);
+        }
+        Ok(operation)
+    }
+    
+    /// The input in `body` with `f` applied to the shares of an import's archive
+    fn map_input(body: Vec<u8>, f: impl Fn(ShareLink) -> Result<ShareLink, StorageError>) -> Result<Vec<u8>, StorageError> {
+        let mut input: OperationInput = serde_json::from_slice(&body)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let OperationInput::Import { archive, .. } = &mut input else {
+            return Ok(body);
+        };
+        map_archive_shares(archive, f)?;
+        serde_json::to_vec(&input).map_err(|e| StorageError::Serialization(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl OperationStore for SealedOperationStore {
+    async fn put(&self, operation: &BulkOperation) -> Result<(), StorageError> {
+        let sealed = Self::map_result(operation.clone(), |s| Ok(self.cipher.seal_share(s)))?;
+        self.inner.put(&sealed).await
+    }
+    
+    async fn get(&self, organization_id: &str, operation_id: &str) -> Result<BulkOperation, StorageError> {
+        Self::map_result(self.inner.get(organization_id, operation_id).await?, |s| self.cipher.open_share(s))
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, operation_id: &str) -> Result<(BulkOperation, String), StorageError> {
+        let (operation, etag) = self.inner.get_tagged(organization_id, operation_id).await?;
+        Ok((Self::map_result(operation, |s| self.cipher.open_share(s))?, etag))
+    }
+    
+    async fn replace(&self, operation: &BulkOperation, etag: &str) -> Result<Option<String>, StorageError> {
+        let sealed = Self::map_result(operation.clone(), |s| Ok(self.cipher.seal_share(s)))?;
+        self.inner.replace(&sealed, etag).await
+    }
+    
+    async fn list(&self) -> Result<Vec<BulkOperation>, StorageError> {
+        self.inner.list().await?.into_iter().map(|o| Self::map_result(o, |s| self.cipher.open_share(s))).collect()
+    }
+    
+    async fn delete(&self, organization_id: &str, operation_id: &str) -> Result<(), StorageError> {
+        self.inner.delete(organization_id, operation_id).await
+    }
+    
+    async fn put_input(&self, organization_id: &str, operation_id: &str, body: Vec<u8>) -> Result<(), StorageError> {
+        let sealed = Self::map_input(body, |s| Ok(self.cipher.seal_share(s)))?;
+        self.inner.put_input(organization_id, operation_id, sealed).await
+    }
+    
+    async fn get_input(&self, organization_id: &str, operation_id: &str) -> Result<Vec<u8>, StorageError> {
+        Self::map_input(self.inner.get_input(organization_id, operation_id).await?, |s| self.cipher.open_share(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn key(byte: u8) -> String {
+        STANDARD.encode([byte; KEY_LEN])
+    }
+    
+    #[test]
+    fn test_seal_open_and_rotation() {
+        let old = ShareKeyCipher::from_spec(&format!("k1={}", key(1))).unwrap();
+        let share_key = "a".repeat(64);
+        let sealed = old.seal("share-1", &share_key);
+        assert!(is_sealed(&sealed) && sealed.starts_with("enc1:k1:"));
+        assert!(!sealed.contains(&share_key));
+        assert_ne!(sealed, old.seal("share-1", &share_key));
+        assert_eq!(old.open("share-1", &sealed).unwrap(), share_key);
+        
+        // Bound to the share, and plaintext passes through
+        assert_eq!(old.open("share-2", &sealed), Err(ShareKeyCipherError::Decrypt("share-2".to_string())));
+        assert_eq!(old.open("share-1", &share_key).unwrap(), share_key);
+        
+        // New key seals, old one still opens
+        let rotated = ShareKeyCipher::from_spec(&format!("k2={}, k1={}", key(2), key(1))).unwrap();
+        assert_eq!(rotated.current_key_id(), "k2");
+        assert_eq!(rotated.open("share-1", &sealed).unwrap(), share_key);
+        assert!(rotated.seal("share-1", &share_key).starts_with("enc1:k2:"));
+        let dropped = ShareKeyCipher::from_spec(&format!("k2={}", key(2))).unwrap();
+        assert_eq!(dropped.open("share-1", &sealed), Err(ShareKeyCipherError::UnknownKey("k1".to_string())));
+        
+        assert!(ShareKeyCipher::from_spec("").is_err());
+        assert!(ShareKeyCipher::from_spec("k1=c2hvcnQ=").is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_sealed_share_storage_and_cache() {
+        use crate::share_cache::InProcessShareCache;
+        use crate::storage::memory_storage::MemoryShareStorage;
+        
+        let cipher = Arc::new(ShareKeyCipher::from_spec(&format!("k1={}", key(1))).unwrap());
+        let share: ShareLink = serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "a".repeat(64), "shortCode": "AbCd1234", "visibility": "public",
+            "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+            "expiresAt": "2026-01-01T00:00:00Z", "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap();
+        
+        let backend = Arc::new(MemoryShareStorage::new());
+        let storage = SealedShareStorage::new(backend.clone(), cipher.clone());
+        assert_eq!(storage.create(share.clone()).await.unwrap().share_key, share.share_key);
+        assert!(is_sealed(&backend.get("org-1", "share-1").await.unwrap().share_key));
+        assert_eq!(storage.get_by_short_code("AbCd1234").await.unwrap().share_key, share.share_key);
+        let listed = storage.list("org-1", QueryOptions::default()).await.unwrap().items;
+        assert_eq!(listed[0].share_key, share.share_key);
+        
+        let inner = Arc::new(InProcessShareCache::new(chrono::Duration::minutes(5)));
+        let cache = SealedShareCache::new(inner.clone(), cipher);
+        cache.put(&share).await.unwrap();
+        assert!(is_sealed(&inner.get("AbCd1234").await.unwrap().unwrap().share_key));
+        assert_eq!(cache.get("AbCd1234").await.unwrap().unwrap().share_key, share.share_key);
+    }
+    
+    #[tokio::test]
+    async fn test_sealed_operation_store() {
+        use crate::operations::MemoryOperationStore;
+        
+        let cipher = Arc::new(ShareKeyCipher::from_spec(&format!("k1={}", key(1))).unwrap());
+        let share: ShareLink = serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "a".repeat(64), "shortCode": "AbCd1234", "visibility": "public",
+            "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+            "expiresAt": "2026-01-01T00:00:00Z", "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap();
+        let archive = BackupArchive {
+            format_version: crate::backup::FORMAT_VERSION,
+            organization_id: "org-1".to_string(),
+            exported_at: "2025-06-01T00:00:00Z".parse().unwrap(),
+            policy: None,
+            activity_types: Vec::new(),
+            layers: Vec::new(),
+            activities: Vec::new(),
+            shares: vec![share.clone()],
+            user_settings: Vec::new(),
+        };
+        let export: BulkOperation = serde_json::from_value(serde_json::json!({
+            "id": "op-1", "organizationId": "org-1", "kind": "export", "status": "succeeded",
+            "completed": 1, "total": 1, "createdBy": "pseudonym-1",
+            "createdAt": "2025-06-01T00:00:00Z", "updatedAt": "2025-06-01T00:00:00Z",
+            "result": serde_json::to_value(&archive).unwrap(),
+        })).unwrap();
+        let share_key = |operation: &BulkOperation| {
+            serde_json::from_value::<BackupArchive>(operation.result.clone().unwrap()).unwrap().shares[0].share_key.clone()
+        };
+        
+        let inner = Arc::new(MemoryOperationStore::new());
+        let store = SealedOperationStore::new(inner.clone(), cipher);
+        store.put(&export).await.unwrap();
+        assert!(is_sealed(&share_key(&inner.get("org-1", "op-1").await.unwrap())));
+        assert_eq!(share_key(&store.get("org-1", "op-1").await.unwrap()), share.share_key);
+        assert_eq!(share_key(&store.list().await.unwrap()[0]), share.share_key);
+        
+        // Import inputs carry archives too
+        let input = OperationInput::Import { archive: Box::new(archive), strategy: crate::models::ConflictStrategy::Skip };
+        store.put_input("org-1", "op-2", serde_json::to_vec(&input).unwrap()).await.unwrap();
+        let stored = inner.get_input("org-1", "op-2").await.unwrap();
+        assert!(!String::from_utf8(stored).unwrap().contains(&share.share_key));
+        let OperationInput::Import { archive, .. } = serde_json::from_slice(&store.get_input("org-1", "op-2").await.unwrap()).unwrap() else {
+            panic!("expected an import");
+        };
+        assert_eq!(archive.shares[0].share_key, share.share_key);
+    }
+}
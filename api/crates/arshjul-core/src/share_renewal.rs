@@ -0,0 +1,274 @@
+//! # Share Renewal Links
+//!
+//! Owners often miss the renewal prompt inside Teams, so expiry reminder
+//! emails carry a link that renews the share without signing in.
+//! [`ShareExpiryReminders::run`] runs once a day for each organization, as
+//! part of the daily notification run (`crate::notifications`), and hands a
+//! [`ShareRenewalReminder`] to the mailer for every active share expiring
+//! in 30, 7 or 1 days.
+//!
+//! The link opens `{base}/renew?token=...`, which posts the token to
+//! `POST /api/shares/renew-by-token`. Tokens are:
+//!
+//! - **Signed** - with the [`KeyRing`] (purpose `renewal`), so they can't be forged or altered
+//! - **Short-lived** - valid for [`RENEWAL_LINK_TTL_HOURS`], and never past the share's expiry
+//! - **Single-use** - bound to the share's expiry when issued; once the share is
+//!   renewed (by the link or in the app) its expiry moves and the token is rejected
+//!
+//! Renewals are written conditional on the ETag the share was read at
+//! ([`crate::storage::ShareStorage::replace`]), so two clicks on one link
+//! can't both pass the single-use check. Deactivated shares get no reminder,
+//! and their links are refused.
+//!
+//! Reminders are written in the owner's language when the job has their
+//! settings ([`ShareExpiryReminders::with_locales`]), else English; the
+//! mailer renders them with [`crate::i18n::share_expiry_reminder`].
+//!
+//! A token renews one share and grants no access to it. Reminders are not
+//! published on the event bus: live updates broadcast to the whole
+//! organization, and the link must only reach the owner.
+
+use crate::i18n;
+use crate::models::{ShareLink, ShareRenewalReminder};
+use crate::signing_keys::{KeyRing, SigningKeyError};
+use crate::storage::{list_all_shares, PolicyStorage, ShareStorage, StorageError, UserSettingsStorage};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// [`KeyRing`] purpose of renewal tokens
+const SIGNING_PURPOSE: &str = "renewal";
+
+/// How long a renewal link works after the reminder is sent
+pub const RENEWAL_LINK_TTL_HOURS: i64 = 72;
+
+/// Days before expiry a reminder is sent
+pub const REMINDER_DAYS: [i64; 3] = [30, 7, 1];
+
+/// Renewal token errors
+#[derive(Debug, Error, PartialEq)]
+pub enum RenewalTokenError {
+    #[error("Invalid renewal link")]
+    Invalid,
+    
+    #[error("Renewal link has expired")]
+    Expired,
+    
+    #[error("Renewal link has already been used")]
+    AlreadyUsed,
+}
+
+/// Reminder delivery errors
+#[derive(Debug, Error)]
+pub enum ReminderError {
+    #[error("Reminder delivery failed: {0}")]
+    Delivery(String),
+}
+
+/// Claims of a renewal link
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenewalToken {
+    #[serde(rename = "o")]
+    pub organization_id: String,
+    
+    #[serde(rename = "s")]
+    pub share_id: String,
+    
+    /// Share expiry when the link was issued
+    #[serde(rename = "x")]
+    pub share_expires_at: DateTime<Utc>,
+    
+    #[serde(rename = "e")]
+    pub expires_at: DateTime<Utc>,
+    
+    /// Random nonce so tokens for the same share differ
+    #[serde(rename = "n")]
+    pub nonce: String,
+}
+
+impl RenewalToken {
+    /// Token for a share, issued at `now`
+    pub fn new(share: &ShareLink, now: DateTime<Utc>) -> Self {
+        Self {
+            organization_id: share.organization_id.clone(),
+            share_id: share.id.clone(),
+            share_expires_at: share.expires_at,
+            expires_at: (now + Duration::hours(RENEWAL_LINK_TTL_HOURS)).min(share.expires_at),
+            nonce: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+    
+    /// Whether the share hasn't been renewed since the token was issued
+    pub fn is_current(&self, share: &ShareLink) -> bool {
+        // Compared in milliseconds, the precision storage keeps
+        self.share_expires_at.timestamp_millis() == share.expires_at.timestamp_millis()
+    }
+}
+
+/// Signs and verifies renewal tokens (`base64url(claims).{kid}.base64url(signature)`)
+pub struct RenewalLinkSigner {
+    keys: Arc<KeyRing>,
+}
+
+impl RenewalLinkSigner {
+    pub fn new(keys: Arc<KeyRing>) -> Self {
+        Self { keys }
+    }
+    
+    pub fn sign(&self, token: &RenewalToken) -> Result<String, SigningKeyError> {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(token).map_err(|e| SigningKeyError::Serialization(e.to_string()))?);
+        let signature = self.keys.sign(SIGNING_PURPOSE, payload.as_bytes())?;
+        Ok(format!("{}.{}", payload, signature))
+    }
+    
+    /// Check the signature and lifetime of a token
+    pub fn verify(&self, token: &str, now: DateTime<Utc>) -> Result<RenewalToken, RenewalTokenError> {
+        let (payload, signature) = token.trim().split_once('.').ok_or(RenewalTokenError::Invalid)?;
+        self.keys.verify(SIGNING_PURPOSE, payload.as_bytes(), signature)
+            .map_err(|_| RenewalTokenError::Invalid)?;
+        
+        let bytes = URL_SAFE_NO_PAD.decode(payload).map_err(|_| RenewalTokenError::Invalid)?;
+        let token: RenewalToken = serde_json::from_slice(&bytes).map_err(|_| RenewalTokenError::Invalid)?;
+        if now > token.expires_at {
+            return Err(RenewalTokenError::Expired);
+        }
+        Ok(token)
+    }
+}
+
+/// Days until the share expires, when a reminder is due today
+pub fn reminder_due(share: &ShareLink, now: DateTime<Utc>) -> Option<i64> {
+    let seconds = (share.expires_at - now).num_seconds();
+    if !share.is_active || seconds <= 0 {
+        return None;
+    }
+    // Rounded up, so a daily run hits each reminder day exactly once
+    let days_left = (seconds + 86_399) / 86_400;
+    REMINDER_DAYS.contains(&days_left).then_some(days_left)
+}
+
+/// Delivers reminders to share owners (e.g. by email)
+#[async_trait]
+pub trait ReminderMailer: Send + Sync {
+    async fn send(&self, reminder: &ShareRenewalReminder) -> Result<(), ReminderError>;
+}
+
+/// Daily reminder job
+pub struct ShareExpiryReminders {
+    shares: Arc<dyn ShareStorage>,
+    signer: Arc<RenewalLinkSigner>,
+    mailer: Arc<dyn ReminderMailer>,
+    base_url: String,
+    locales: Option<(Arc<dyn UserSettingsStorage>, Arc<dyn PolicyStorage>)>,
+}
+
+impl ShareExpiryReminders {
+    pub fn new(shares: Arc<dyn ShareStorage>, signer: Arc<RenewalLinkSigner>, mailer: Arc<dyn ReminderMailer>, base_url: &str) -> Self {
+        Self { shares, signer, mailer, base_url: base_url.trim_end_matches('/').to_string(), locales: None }
+    }
+    
+    /// Write reminders in each owner's locale, else the organization's
+    pub fn with_locales(mut self, user_settings: Arc<dyn UserSettingsStorage>, policies: Arc<dyn PolicyStorage>) -> Self {
+        self.locales = Some((user_settings, policies));
+        self
+    }
+    
+    /// Remind owners of the organization's shares due today; returns the reminders delivered
+    pub async fn run(&self, organization_id: &str, now: DateTime<Utc>) -> Result<Vec<ShareRenewalReminder>, StorageError> {
+        let mut sent = Vec::new();
+        let calendar = match &self.locales {
+            Some((_, policies)) => policies.get(organization_id).await?.calendar.unwrap_or_default(),
+            None => Default::default(),
+        };
+        
+        for share in list_all_shares(self.shares.as_ref(), organization_id).await? {
+            let Some(days_left) = reminder_due(&share, now) else { continue };
+            
+            let owner = match &self.locales {
+                Some((user_settings, _)) => Some(user_settings.get(organization_id, &share.created_by).await?),
+                None => None,
+            };
+            let token = RenewalToken::new(&share, now);
+            let signed = match self.signer.sign(&token) {
+                Ok(signed) => signed,
+                Err(e) => {
+                    tracing::warn!(share_id = %share.id, error = %e, "Failed to sign renewal link");
+                    continue;
+                }
+            };
+            let reminder = ShareRenewalReminder {
+                organization_id: share.organization_id.clone(),
+                share_id: share.id.clone(),
+                share_name: share.name.clone(),
+                owner_id: share.created_by.clone(),
+                expires_at: share.expires_at,
+                days_left,
+                renewal_url: format!("{}/renew?token={}", self.base_url, signed),
+                link_expires_at: token.expires_at,
+                locale: i18n::resolve(owner.as_ref(), &calendar).tag().to_string(),
+            };
+            // One undeliverable reminder must not hold back the rest
+            match self.mailer.send(&reminder).await {
+                Ok(()) => sent.push(reminder),
+                Err(e) => tracing::warn!(share_id = %share.id, error = %e, "Failed to send renewal reminder"),
+            }
+        }
+        
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn share(expires_at: DateTime<Utc>) -> ShareLink {
+        serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "key", "shortCode": "abc12345", "visibility": "public",
+            "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+            "expiresAt": expires_at, "layerConfig": { "layerIds": [] }, "viewSettings": {},
+            "stats": { "views": 0 }, "isActive": true,
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_renewal_token() {
+        let now = Utc::now();
+        let signer = RenewalLinkSigner::new(crate::signing_keys::tests::key_ring_at(now).await);
+        let mut share = share(now + Duration::days(7));
+        let token = RenewalToken::new(&share, now);
+        let signed = signer.verify(&signer.sign(&token).unwrap(), now).unwrap();
+        assert_eq!(signed, token);
+        assert!(signed.is_current(&share));
+        
+        let valid = signer.sign(&token).unwrap();
+        let forged = signer.sign(&RenewalToken { share_id: "share-2".to_string(), ..token.clone() }).unwrap();
+        let tampered = format!("{}.{}", forged.split_once('.').unwrap().0, valid.split_once('.').unwrap().1);
+        assert_eq!(signer.verify(&tampered, now), Err(RenewalTokenError::Invalid));
+        let other = RenewalLinkSigner::new(crate::signing_keys::tests::key_ring_at(now).await);
+        assert_eq!(other.verify(&signer.sign(&token).unwrap(), now), Err(RenewalTokenError::Invalid));
+        
+        let later = now + Duration::hours(RENEWAL_LINK_TTL_HOURS) + Duration::seconds(1);
+        assert_eq!(signer.verify(&signer.sign(&token).unwrap(), later), Err(RenewalTokenError::Expired));
+        
+        share.expires_at = now + Duration::days(365);
+        assert!(!token.is_current(&share));
+    }
+    
+    #[test]
+    fn test_reminder_due() {
+        let now = Utc::now();
+        assert_eq!(reminder_due(&share(now + Duration::days(30)), now), Some(30));
+        assert_eq!(reminder_due(&share(now + Duration::hours(150)), now), Some(7));
+        assert_eq!(reminder_due(&share(now + Duration::hours(5)), now), Some(1));
+        assert_eq!(reminder_due(&share(now + Duration::days(12)), now), None);
+        assert_eq!(reminder_due(&share(now - Duration::hours(1)), now), None);
+        
+        // Links never outlive the share
+        let expiring = share(now + Duration::hours(5));
+        assert_eq!(RenewalToken::new(&expiring, now).expires_at, expiring.expires_at);
+    }
+}
@@ -0,0 +1,312 @@
+//! # Share Traffic Monitoring
+//!
+//! Daily per-share view counts and a detection job for shares whose traffic
+//! suddenly changes - typically a private-ish link leaking to the open
+//! internet.
+//!
+//! Public share access records one view per request, by UTC day and by
+//! country when the edge reports one (see [`crate::client_info`]).
+//! [`ShareTrafficMonitor::check`] checks a day - once a day from a timer
+//! trigger for the previous day, or from [`ShareTrafficMonitor::run`] on the
+//! standalone server - and compares each share with traffic against its baseline,
+//! the mean daily views over the preceding [`AnomalyPolicy::baseline_days`]:
+//!
+//! | Anomaly | Raised when |
+//! |---------|-------------|
+//! | `viewSpike` | views >= `spike_factor` x baseline and >= `min_spike_views` |
+//! | `unusualCountry` | >= `min_country_views` views from a country absent from the baseline |
+//!
+//! Shares with less than [`AnomalyPolicy::min_history_days`] of history are
+//! skipped so launches don't alert. Flagged shares get `review` set and a
+//! [`DomainEvent::ShareTrafficAnomaly`] is published for admin notification.
+
+use crate::clock::{Clock, SystemClock};
+use crate::events::{ChangeKind, DomainEvent, EntityChange, EntityKind, EventBus};
+use crate::models::{DailyShareTraffic, ShareReview, ShareTrafficAlert, TrafficAnomaly};
+use crate::storage::{ShareStorage, StorageError};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+/// Storage for daily share traffic
+#[async_trait]
+pub trait ShareTrafficStore: Send + Sync {
+    /// Count one view
+    async fn record_view(&self, organization_id: &str, share_id: &str, date: NaiveDate, country: Option<&str>) -> Result<(), StorageError>;
+    
+    /// Daily rows for a share in `from..=to`
+    async fn history(&self, organization_id: &str, share_id: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyShareTraffic>, StorageError>;
+    
+    /// `(organization_id, share_id)` of every share viewed on a day
+    async fn viewed_on(&self, date: NaiveDate) -> Result<Vec<(String, String)>, StorageError>;
+}
+
+/// Detection thresholds
+#[derive(Debug, Clone, Copy)]
+pub struct AnomalyPolicy {
+    /// Days before the checked day that make up the baseline
+    pub baseline_days: i64,
+    /// Days with traffic required before anything is flagged
+    pub min_history_days: usize,
+    /// Multiple of the baseline that counts as a spike
+    pub spike_factor: f64,
+    /// Spikes below this many views are ignored
+    pub min_spike_views: u64,
+    /// Views from a new country needed to flag it
+    pub min_country_views: u64,
+}
+
+impl Default for AnomalyPolicy {
+    fn default() -> Self {
+        Self {
+            baseline_days: 28,
+            min_history_days: 7,
+            spike_factor: 10.0,
+            min_spike_views: 50,
+            min_country_views: 5,
+        }
+    }
+}
+
+/// Compare one day against the share's baseline history
+pub fn detect(day: &DailyShareTraffic, baseline: &[DailyShareTraffic], policy: &AnomalyPolicy) -> Vec<TrafficAnomaly> {
+    let history: Vec<&DailyShareTraffic> = baseline.iter().filter(|d| d.date < day.date).collect();
+    if history.len() < policy.min_history_days {
+        return Vec::new();
+    }
+    
+    let mut anomalies = Vec::new();
+    
+    // Days without traffic have no row but still count towards the mean
+    let mean = history.iter().map(|d| d.views).sum::<u64>() as f64 / policy.baseline_days.max(1) as f64;
+    if day.views >= policy.min_spike_views && day.views as f64 >= policy.spike_factor * mean.max(1.0) {
+        anomalies.push(TrafficAnomaly::ViewSpike { views: day.views, baseline: mean });
+    }
+    
+    let known: HashSet<&str> = history.iter().flat_map(|d| d.countries.keys().map(String::as_str)).collect();
+    if !known.is_empty() {
+        let mut new_countries: Vec<(&String, &u64)> = day.countries.iter()
+            .filter(|(c, views)| !known.contains(c.as_str()) && **views >= policy.min_country_views)
+            .collect();
+        new_countries.sort();
+        for (country, views) in new_countries {
+            anomalies.push(TrafficAnomaly::UnusualCountry { country: country.clone(), views: *views });
+        }
+    }
+    
+    anomalies
+}
+
+/// Daily detection job
+pub struct ShareTrafficMonitor {
+    shares: Arc<dyn ShareStorage>,
+    traffic: Arc<dyn ShareTrafficStore>,
+    events: Arc<EventBus>,
+    policy: AnomalyPolicy,
+    clock: Arc<dyn Clock>,
+    /// Last day checked by [`Self::run_once`]
+    checked: Mutex<Option<NaiveDate>>,
+}
+
+impl ShareTrafficMonitor {
+    pub fn new(shares: Arc<dyn ShareStorage>, traffic: Arc<dyn ShareTrafficStore>, events: Arc<EventBus>, policy: AnomalyPolicy) -> Self {
+        Self { shares, traffic, events, policy, clock: Arc::new(SystemClock), checked: Mutex::new(None) }
+    }
+    
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Check yesterday unless already checked; returns the alerts raised
+    pub async fn run_once(&self) -> Result<Vec<ShareTrafficAlert>, StorageError> {
+        let now = self.clock.now();
+        let yesterday = now.date_naive() - Duration::days(1);
+        if *self.checked.lock().unwrap_or_else(|e| e.into_inner()) == Some(yesterday) {
+            return Ok(Vec::new());
+        }
+        
+        let alerts = self.check(yesterday, now).await?;
+        *self.checked.lock().unwrap_or_else(|e| e.into_inner()) = Some(yesterday);
+        Ok(alerts)
+    }
+    
+    /// Check every `interval` until the task is dropped; each day is checked once
+    #[cfg(feature = "server")]
+    pub async fn run(self: Arc<Self>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.run_once().await {
+                Ok(alerts) => tracing::debug!(alerts = alerts.len(), "Checked share traffic"),
+                Err(e) => tracing::warn!(error = %e, "Share traffic check failed"),
+            }
+        }
+    }
+    
+    /// Check every share viewed on `date`; flags them and returns the alerts raised
+    pub async fn check(&self, date: NaiveDate, now: DateTime<Utc>) -> Result<Vec<ShareTrafficAlert>, StorageError> {
+        let mut alerts = Vec::new();
+        
+        for (organization_id, share_id) in self.traffic.viewed_on(date).await? {
+            let from = date - Duration::days(self.policy.baseline_days);
+            let history = self.traffic.history(&organization_id, &share_id, from, date).await?;
+            let Some(day) = history.iter().find(|d| d.date == date) else { continue };
+            
+            let anomalies = detect(day, &history, &self.policy);
+            if anomalies.is_empty() {
+                continue;
+            }
+            
+            let mut share = match self.shares.get(&organization_id, &share_id).await {
+                Ok(share) => share,
+                Err(StorageError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            share.review = Some(ShareReview { anomalies: anomalies.clone(), flagged_at: now });
+            let share = self.shares.update(share).await?;
+            
+            let alert = ShareTrafficAlert {
+                share_id: share.id.clone(),
+                organization_id: share.organization_id.clone(),
+                short_code: share.short_code.clone(),
+                date,
+                anomalies,
+                detected_at: now,
+            };
+            tracing::warn!("Unusual traffic on share {} ({}): {:?}", share.id, share.organization_id, alert.anomalies);
+            
+            self.events.publish(DomainEvent::EntityChanged(
+                EntityChange::new(&share.organization_id, EntityKind::Share, &share.id, ChangeKind::Updated, None)
+                    .with_key(&share.short_code),
+            )).await;
+            self.events.publish(DomainEvent::ShareTrafficAnomaly(alert.clone())).await;
+            alerts.push(alert);
+        }
+        
+        Ok(alerts)
+    }
+}
+
+/// In-memory traffic store (single instance deployments and tests)
+#[derive(Default)]
+pub struct MemoryShareTrafficStore {
+    days: RwLock<HashMap<(String, String, NaiveDate), DailyShareTraffic>>,
+}
+
+impl MemoryShareTrafficStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ShareTrafficStore for MemoryShareTrafficStore {
+    async fn record_view(&self, organization_id: &str, share_id: &str, date: NaiveDate, country: Option<&str>) -> Result<(), StorageError> {
+        let mut days = self.days.write().await;
+        let day = days.entry((organization_id.to_string(), share_id.to_string(), date))
+            .or_insert_with(|| DailyShareTraffic {
+                organization_id: organization_id.to_string(),
+                share_id: share_id.to_string(),
+                date,
+                ..Default::default()
+            });
+        day.views += 1;
+        if let Some(country) = country {
+            *day.countries.entry(country.to_string()).or_default() += 1;
+        }
+        Ok(())
+    }
+    
+    async fn history(&self, organization_id: &str, share_id: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<DailyShareTraffic>, StorageError> {
+        let days = self.days.read().await;
+        let mut history: Vec<DailyShareTraffic> = days.values()
+            .filter(|d| d.organization_id == organization_id && d.share_id == share_id && d.date >= from && d.date <= to)
+            .cloned()
+            .collect();
+        history.sort_by_key(|d| d.date);
+        Ok(history)
+    }
+    
+    async fn viewed_on(&self, date: NaiveDate) -> Result<Vec<(String, String)>, StorageError> {
+        let days = self.days.read().await;
+        Ok(days.keys()
+            .filter(|(_, _, d)| *d == date)
+            .map(|(org, share, _)| (org.clone(), share.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn day(date: NaiveDate, views: u64, countries: &[(&str, u64)]) -> DailyShareTraffic {
+        DailyShareTraffic {
+            organization_id: "org-1".to_string(),
+            share_id: "share-1".to_string(),
+            date,
+            views,
+            countries: countries.iter().map(|(c, v)| (c.to_string(), *v)).collect(),
+        }
+    }
+    
+    #[test]
+    fn test_detect() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let policy = AnomalyPolicy::default();
+        let baseline: Vec<DailyShareTraffic> = (1..=28)
+            .map(|n| day(today - Duration::days(n), 20, &[("NO", 20)]))
+            .collect();
+        
+        // Steady traffic from a known country
+        assert!(detect(&day(today, 25, &[("NO", 25)]), &baseline, &policy).is_empty());
+        
+        let anomalies = detect(&day(today, 400, &[("NO", 390), ("KP", 10)]), &baseline, &policy);
+        assert_eq!(anomalies, vec![
+            TrafficAnomaly::ViewSpike { views: 400, baseline: 20.0 },
+            TrafficAnomaly::UnusualCountry { country: "KP".to_string(), views: 10 },
+        ]);
+        
+        // Too little history
+        assert!(detect(&day(today, 400, &[]), &baseline[..3], &policy).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_run_once_checks_yesterday_once() {
+        use crate::clock::ManualClock;
+        use crate::storage::memory_storage::MemoryShareStorage;
+        
+        let clock = Arc::new(ManualClock::new("2025-03-02T06:00:00Z".parse().unwrap()));
+        let shares = Arc::new(MemoryShareStorage::new());
+        let share: crate::models::ShareLink = serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "k".repeat(64), "shortCode": "Code1",
+            "visibility": "public", "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2099-01-01T00:00:00Z",
+            "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap();
+        shares.create(share).await.unwrap();
+        
+        let traffic = Arc::new(MemoryShareTrafficStore::new());
+        let yesterday = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        for n in 1..=10 {
+            traffic.record_view("org-1", "share-1", yesterday - Duration::days(n), Some("NO")).await.unwrap();
+        }
+        for _ in 0..60 {
+            traffic.record_view("org-1", "share-1", yesterday, Some("NO")).await.unwrap();
+        }
+        
+        let monitor = ShareTrafficMonitor::new(shares.clone(), traffic, Arc::new(EventBus::new()), AnomalyPolicy::default())
+            .with_clock(clock.clone());
+        let alerts = monitor.run_once().await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].date, yesterday);
+        assert!(shares.get("org-1", "share-1").await.unwrap().review.is_some());
+        
+        // Later ticks the same day don't raise the alert again
+        clock.advance(Duration::hours(1));
+        assert!(monitor.run_once().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! # Share URLs
+//!
+//! Every link variant handed out for a share, built in one place so the
+//! share page, embed and calendar feed always agree on paths and keys.
+//!
+//! | Variant | Public | Users |
+//! |---------|--------|-------|
+//! | Share page | `{base}/s/{code}?k={key}` | `{base}/s/{code}` |
+//! | Embed | `{base}/embed/{code}?k={key}` | `{base}/embed/{code}` |
+//! | Calendar | `webcal://{host}/api/public/s/{code}/calendar.ics?k={key}` | - |
+//! | Preview | `{base}/api/public/s/{code}/preview.png?sig={sig}` | - |
+//!
+//! Users shares get no calendar URL: calendar clients can't sign in. The
+//! preview URL is only issued [`with_preview`](ShareUrls::with_preview).
+
+use crate::markdown::escape_html;
+use crate::models::{CreateShareResponse, ShareLink, ShareVisibility};
+use crate::preview::PreviewSigner;
+use chrono::{DateTime, Utc};
+
+/// Embed iframe size in pixels
+const EMBED_SIZE: u32 = 600;
+
+/// Link builder for one share
+#[derive(Clone, Copy)]
+pub struct ShareUrls<'a> {
+    base_url: &'a str,
+    share: &'a ShareLink,
+    preview: Option<(&'a PreviewSigner, DateTime<Utc>)>,
+}
+
+impl<'a> ShareUrls<'a> {
+    /// `base_url` is the public origin, with or without a trailing slash
+    pub fn new(base_url: &'a str, share: &'a ShareLink) -> Self {
+        Self { base_url: base_url.trim_end_matches('/'), share, preview: None }
+    }
+    
+    /// Also issue a preview image URL, signed at `now`
+    pub fn with_preview(mut self, signer: &'a PreviewSigner, now: DateTime<Utc>) -> Self {
+        self.preview = Some((signer, now));
+        self
+    }
+    
+    /// `?k=` query for public shares, empty otherwise
+    fn key_query(&self) -> String {
+        match self.share.visibility {
+            ShareVisibility::Public => format!("?k={}", self.share.share_key),
+            ShareVisibility::Users => String::new(),
+        }
+    }
+    
+    /// Share page URL
+    pub fn share_url(&self) -> String {
+        format!("{}/s/{}{}", self.base_url, self.share.short_code, self.key_query())
+    }
+    
+    /// URL of the embeddable view
+    pub fn embed_url(&self) -> String {
+        format!("{}/embed/{}{}", self.base_url, self.share.short_code, self.key_query())
+    }
+    
+    /// `<iframe>` snippet for the embeddable view
+    pub fn embed_code(&self) -> String {
+        let title = self.share.name.as_deref().unwrap_or("Annual Wheel");
+        format!(
+            r#"<iframe src="{}" width="{}" height="{}" frameborder="0" title="{}"></iframe>"#,
+            escape_html(&self.embed_url()), EMBED_SIZE, EMBED_SIZE, escape_html(title)
+        )
+    }
+    
+    /// `webcal://` calendar subscription (public shares only)
+    pub fn calendar_url(&self) -> Option<String> {
+        if self.share.visibility != ShareVisibility::Public {
+            return None;
+        }
+        let host = self.base_url.trim_start_matches("https://").trim_start_matches("http://");
+        Some(format!("webcal://{}/api/public/s/{}/calendar.ics{}", host, self.share.short_code, self.key_query()))
+    }
+    
+    /// Signed preview image (public shares only)
+    pub fn preview_url(&self) -> Option<String> {
+        let (signer, now) = self.preview?;
+        if self.share.visibility != ShareVisibility::Public {
+            return None;
+        }
+        // No signing key yet: the share is served without a preview
+        let sig = signer.sign(self.share, now).ok()?;
+        Some(format!("{}/api/public/s/{}/preview.png?sig={}", self.base_url, self.share.short_code, sig))
+    }
+    
+    /// Response for create/regenerate-key endpoints
+    pub fn response(&self) -> CreateShareResponse {
+        CreateShareResponse {
+            share: self.share.clone(),
+            share_url: self.share_url(),
+            embed_code: self.embed_code(),
+            calendar_url: self.calendar_url(),
+            preview_url: self.preview_url(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn share(visibility: ShareVisibility, name: Option<&str>) -> ShareLink {
+        serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "k".repeat(64), "shortCode": "AbCd1234",
+            "visibility": visibility, "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2026-01-01T00:00:00Z",
+            "name": name, "layerConfig": { "layerIds": ["layer-1"] },
+            "viewSettings": {}, "stats": { "viewCount": 0 }, "isActive": true,
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_public_urls() {
+        let key = "k".repeat(64);
+        for base in ["https://wheel.example.com", "https://wheel.example.com/"] {
+            let share = share(ShareVisibility::Public, Some("Q1 <plan>"));
+            let urls = ShareUrls::new(base, &share).response();
+            assert_eq!(urls.share_url, format!("https://wheel.example.com/s/AbCd1234?k={}", key));
+            assert_eq!(urls.calendar_url.unwrap(), format!("webcal://wheel.example.com/api/public/s/AbCd1234/calendar.ics?k={}", key));
+            assert!(urls.embed_code.contains(&format!(r#"src="https://wheel.example.com/embed/AbCd1234?k={}""#, key)));
+            assert!(urls.embed_code.contains(r#"title="Q1 &lt;plan&gt;""#));
+            assert_eq!(urls.preview_url, None);
+        }
+        
+        let share = share(ShareVisibility::Public, None);
+        let now: DateTime<Utc> = "2025-03-01T10:00:00Z".parse().unwrap();
+        let signer = PreviewSigner::new(crate::signing_keys::tests::key_ring_at(now).await);
+        let preview = ShareUrls::new("https://wheel.example.com", &share).with_preview(&signer, now).preview_url().unwrap();
+        let sig = preview.strip_prefix("https://wheel.example.com/api/public/s/AbCd1234/preview.png?sig=").unwrap();
+        assert_eq!(signer.verify(&share, sig, now), Ok(()));
+    }
+    
+    #[tokio::test]
+    async fn test_users_urls() {
+        let signer = PreviewSigner::new(crate::signing_keys::tests::key_ring_at(Utc::now()).await);
+        for base in ["http://localhost:7071", "http://localhost:7071/"] {
+            let share = share(ShareVisibility::Users, None);
+            let urls = ShareUrls::new(base, &share).response();
+            assert_eq!(urls.share_url, "http://localhost:7071/s/AbCd1234");
+            assert_eq!(urls.calendar_url, None);
+            assert_eq!(ShareUrls::new(base, &share).with_preview(&signer, Utc::now()).preview_url(), None);
+            assert!(urls.embed_code.contains(r#"src="http://localhost:7071/embed/AbCd1234""#));
+            assert!(urls.embed_code.contains(r#"title="Annual Wheel""#));
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! # Signing Keys
+//!
+//! Signed artifacts (renewal links, preview images, dev tokens, signed URLs)
+//! need keys that can be rotated without breaking the artifacts already
+//! handed out. [`KeyRing`] holds versioned keys from a [`SigningKeyStore`]:
+//!
+//! - **Key IDs** - every signature carries its key's `kid`, so verification
+//!   picks the right key across a rotation
+//! - **Rotation** - [`KeyRing::rotate`] adds a key once the newest is
+//!   [`RotationPolicy::rotate_after`] old. It is published
+//!   [`RotationPolicy::publish_ahead`] before it signs, so verifiers caching
+//!   the JWKS already know it, and old keys keep verifying for
+//!   [`RotationPolicy::verify_for`] after they stop signing
+//! - **Storage** - `KeyVaultKeyStore` keeps keys as Key Vault secrets
+//!   (`arshjul-azure`); [`SecretKeyStore`] derives one key that never
+//!   rotates from a configured secret; [`InMemoryKeyStore`] is per instance,
+//!   for local development only
+//! - **JWKS** - `GET /.well-known/jwks.json` publishes every key's public
+//!   half, for artifacts verified by other services
+//!
+//! A key is a 32-byte secret. Artifacts this API verifies itself are
+//! HMAC-SHA256 signed ([`KeyRing::sign`]) with a subkey per purpose, so a
+//! signature for one kind of artifact is never valid for another. Artifacts
+//! verified elsewhere are compact JWS ([`KeyRing::sign_jws`], `EdDSA`) with
+//! an Ed25519 key pair derived from the same secret.
+//!
+//! Instances rotating at the same time may each add a key; both sign and
+//! verify correctly, and the extra key retires with the other.
+
+use crate::clock::Clock;
+use crate::models::{Jwk, Jwks};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use thiserror::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signing key errors
+#[derive(Debug, Error, PartialEq)]
+pub enum SigningKeyError {
+    #[error("Key storage error: {0}")]
+    Storage(String),
+    
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    
+    #[error("No signing key is active")]
+    NoActiveKey,
+    
+    #[error("Invalid signature")]
+    InvalidSignature,
+}
+
+/// Default of [`RotationPolicy::rotate_after`], in days
+pub const DEFAULT_ROTATION_DAYS: i64 = 90;
+
+/// When keys are added and retired
+#[derive(Debug, Clone, Copy)]
+pub struct RotationPolicy {
+    /// Age of the newest key at which another is added
+    pub rotate_after: Duration,
+    /// How long a new key is published before it signs
+    pub publish_ahead: Duration,
+    /// How long a key verifies after it stops signing; longer than any signed artifact lives
+    pub verify_for: Duration,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            rotate_after: Duration::days(DEFAULT_ROTATION_DAYS),
+            publish_ahead: Duration::days(1),
+            verify_for: Duration::days(7),
+        }
+    }
+}
+
+/// One versioned key
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningKey {
+    pub kid: String,
+    /// 32 random bytes, base64url
+    secret: String,
+    pub created_at: DateTime<Utc>,
+    /// When it starts signing
+    pub active_from: DateTime<Utc>,
+    /// When it stops verifying and is deleted, unless no newer key signs yet
+    pub retire_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("created_at", &self.created_at)
+            .field("active_from", &self.active_from)
+            .field("retire_at", &self.retire_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// New random key signing from `active_from`
+    pub fn generate(now: DateTime<Utc>, active_from: DateTime<Utc>, policy: &RotationPolicy) -> Self {
+        let mut secret = [0u8; 32];
+        let mut suffix = [0u8; 4];
+        rand::thread_rng().fill_bytes(&mut secret);
+        rand::thread_rng().fill_bytes(&mut suffix);
+        Self {
+            // Letters, digits and dashes, as Key Vault secret names allow
+            kid: format!("{}-{}", now.format("%Y%m%d"), hex::encode(suffix)),
+            secret: URL_SAFE_NO_PAD.encode(secret),
+            created_at: now,
+            active_from,
+            retire_at: active_from + policy.rotate_after + policy.verify_for,
+        }
+    }
+    
+    /// Secret derived for one use of the key
+    fn subkey(&self, label: &str) -> Result<[u8; 32], SigningKeyError> {
+        let secret = URL_SAFE_NO_PAD.decode(&self.secret)
+            .map_err(|e| SigningKeyError::Storage(format!("Key {} is corrupt: {}", self.kid, e)))?;
+        let mut mac = HmacSha256::new_from_slice(&secret).expect("HMAC accepts any key length");
+        mac.update(label.as_bytes());
+        Ok(mac.finalize().into_bytes().into())
+    }
+    
+    fn mac(&self, purpose: &str, message: &[u8]) -> Result<HmacSha256, SigningKeyError> {
+        let subkey = self.subkey(&format!("hmac:{}", purpose))?;
+        let mut mac = HmacSha256::new_from_slice(&subkey).expect("HMAC accepts any key length");
+        mac.update(message);
+        Ok(mac)
+    }
+    
+    fn key_pair(&self) -> Result<Ed25519KeyPair, SigningKeyError> {
+        Ed25519KeyPair::from_seed_unchecked(&self.subkey("ed25519")?)
+            .map_err(|e| SigningKeyError::Storage(format!("Key {} is corrupt: {}", self.kid, e)))
+    }
+    
+    /// Public half as a JWK
+    pub fn jwk(&self) -> Result<Jwk, SigningKeyError> {
+        Ok(Jwk {
+            kty: "OKP".to_string(),
+            crv: "Ed25519".to_string(),
+            x: URL_SAFE_NO_PAD.encode(self.key_pair()?.public_key().as_ref()),
+            kid: self.kid.clone(),
+            key_use: "sig".to_string(),
+            alg: "EdDSA".to_string(),
+        })
+    }
+}
+
+/// Where signing keys are kept
+#[async_trait]
+pub trait SigningKeyStore: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    async fn list(&self) -> Result<Vec<SigningKey>, SigningKeyError>;
+    
+    async fn save(&self, key: &SigningKey) -> Result<(), SigningKeyError>;
+    
+    async fn delete(&self, kid: &str) -> Result<(), SigningKeyError>;
+    
+    /// Whether [`KeyRing::rotate`] may add and delete keys
+    fn rotates(&self) -> bool {
+        true
+    }
+}
+
+/// One key derived from a configured secret: every instance with the secret
+/// signs alike, without a vault. It never rotates; changing the secret
+/// invalidates everything signed before.
+pub struct SecretKeyStore {
+    key: SigningKey,
+}
+
+impl SecretKeyStore {
+    pub fn new(secret: &[u8]) -> Self {
+        let derive = |label: &str| {
+            let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+            mac.update(label.as_bytes());
+            mac.finalize().into_bytes()
+        };
+        let since = DateTime::<Utc>::UNIX_EPOCH;
+        Self {
+            key: SigningKey {
+                kid: format!("secret-{}", hex::encode(&derive("kid")[..4])),
+                secret: URL_SAFE_NO_PAD.encode(derive("signing-key")),
+                created_at: since,
+                active_from: since,
+                retire_at: DateTime::<Utc>::MAX_UTC,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl SigningKeyStore for SecretKeyStore {
+    fn name(&self) -> &'static str {
+        "secret"
+    }
+    
+    async fn list(&self) -> Result<Vec<SigningKey>, SigningKeyError> {
+        Ok(vec![self.key.clone()])
+    }
+    
+    async fn save(&self, _: &SigningKey) -> Result<(), SigningKeyError> {
+        Err(SigningKeyError::Storage("Keys derived from a secret can't be added".to_string()))
+    }
+    
+    async fn delete(&self, _: &str) -> Result<(), SigningKeyError> {
+        Err(SigningKeyError::Storage("Keys derived from a secret can't be deleted".to_string()))
+    }
+    
+    fn rotates(&self) -> bool {
+        false
+    }
+}
+
+/// Keys in process memory: per instance and lost on restart, for local development
+#[derive(Default)]
+pub struct InMemoryKeyStore {
+    keys: Mutex<Vec<SigningKey>>,
+}
+
+impl InMemoryKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SigningKeyStore for InMemoryKeyStore {
+    fn name(&self) -> &'static str {
+        "in-memory"
+    }
+    
+    async fn list(&self) -> Result<Vec<SigningKey>, SigningKeyError> {
+        Ok(self.keys.lock().map_err(|e| SigningKeyError::Storage(e.to_string()))?.clone())
+    }
+    
+    async fn save(&self, key: &SigningKey) -> Result<(), SigningKeyError> {
+        let mut keys = self.keys.lock().map_err(|e| SigningKeyError::Storage(e.to_string()))?;
+        keys.retain(|k| k.kid != key.kid);
+        keys.push(key.clone());
+        Ok(())
+    }
+    
+    async fn delete(&self, kid: &str) -> Result<(), SigningKeyError> {
+        self.keys.lock().map_err(|e| SigningKeyError::Storage(e.to_string()))?.retain(|k| k.kid != kid);
+        Ok(())
+    }
+}
+
+/// Key that signs at `now`: the one activated last
+fn signing_key(keys: &[SigningKey], now: DateTime<Utc>) -> Option<&SigningKey> {
+    keys.iter().filter(|k| k.active_from <= now).max_by_key(|k| k.active_from)
+}
+
+/// Keys that verify at `now`, upcoming ones included
+fn verifying_keys(keys: &[SigningKey], now: DateTime<Utc>) -> impl Iterator<Item = &SigningKey> {
+    let signing = signing_key(keys, now).map(|k| k.kid.as_str());
+    keys.iter().filter(move |k| k.retire_at > now || Some(k.kid.as_str()) == signing)
+}
+
+/// Versioned signing keys, cached from a [`SigningKeyStore`]
+pub struct KeyRing {
+    store: Arc<dyn SigningKeyStore>,
+    policy: RotationPolicy,
+    clock: Arc<dyn Clock>,
+    keys: RwLock<Vec<SigningKey>>,
+}
+
+impl KeyRing {
+    /// Empty until [`rotate`](Self::rotate) first runs
+    pub fn new(store: Arc<dyn SigningKeyStore>, policy: RotationPolicy, clock: Arc<dyn Clock>) -> Self {
+        Self { store, policy, clock, keys: RwLock::new(Vec::new()) }
+    }
+    
+    fn keys(&self) -> RwLockReadGuard<'_, Vec<SigningKey>> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Reload the keys from the store
+    pub async fn refresh(&self) -> Result<(), SigningKeyError> {
+        let keys = self.store.list().await?;
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+        Ok(())
+    }
+    
+    /// Delete retired keys and add one when the newest is due; returns the added key's ID
+    pub async fn rotate(&self) -> Result<Option<String>, SigningKeyError> {
+        if !self.store.rotates() {
+            self.refresh().await?;
+            return Ok(None);
+        }
+        let now = self.clock.now();
+        let keys = self.store.list().await?;
+        
+        let verifying: Vec<&str> = verifying_keys(&keys, now).map(|k| k.kid.as_str()).collect();
+        for key in keys.iter().filter(|k| !verifying.contains(&k.kid.as_str())) {
+            self.store.delete(&key.kid).await?;
+            tracing::info!(kid = %key.kid, store = self.store.name(), "Retired signing key");
+        }
+        
+        let due = keys.iter().map(|k| k.created_at).max().is_none_or(|newest| newest + self.policy.rotate_after <= now);
+        let added = if due {
+            // The first key signs right away; later ones once verifiers have seen them
+            let active_from = if keys.is_empty() { now } else { now + self.policy.publish_ahead };
+            let key = SigningKey::generate(now, active_from, &self.policy);
+            self.store.save(&key).await?;
+            tracing::info!(kid = %key.kid, active_from = %key.active_from, store = self.store.name(), "Added signing key");
+            Some(key.kid)
+        } else {
+            None
+        };
+        
+        self.refresh().await?;
+        Ok(added)
+    }
+    
+    /// [`rotate`](Self::rotate) every interval, which also picks up keys added by other instances; spawn once at startup
+    pub async fn run(self: Arc<Self>, interval: std::time::Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(e) = self.rotate().await {
+                tracing::warn!(store = self.store.name(), error = %e, "Signing key rotation failed");
+            }
+        }
+    }
+    
+    /// `{kid}.{base64url(HMAC-SHA256)}` of `message`, for one kind of artifact
+    pub fn sign(&self, purpose: &str, message: &[u8]) -> Result<String, SigningKeyError> {
+        let keys = self.keys();
+        let key = signing_key(&keys, self.clock.now()).ok_or(SigningKeyError::NoActiveKey)?;
+        let mac = key.mac(purpose, message)?.finalize().into_bytes();
+        Ok(format!("{}.{}", key.kid, URL_SAFE_NO_PAD.encode(mac)))
+    }
+    
+    /// Check a [`sign`](Self::sign) signature (constant time)
+    pub fn verify(&self, purpose: &str, message: &[u8], signature: &str) -> Result<(), SigningKeyError> {
+        let (kid, mac) = signature.split_once('.').ok_or(SigningKeyError::InvalidSignature)?;
+        let mac = URL_SAFE_NO_PAD.decode(mac).map_err(|_| SigningKeyError::InvalidSignature)?;
+        let keys = self.keys();
+        let key = verifying_keys(&keys, self.clock.now())
+            .find(|k| k.kid == kid)
+            .ok_or(SigningKeyError::InvalidSignature)?;
+        key.mac(purpose, message)?.verify_slice(&mac).map_err(|_| SigningKeyError::InvalidSignature)
+    }
+    
+    /// Compact JWS of `claims`, verifiable with the JWKS
+    pub fn sign_jws<T: Serialize>(&self, claims: &T) -> Result<String, SigningKeyError> {
+        let keys = self.keys();
+        let key = signing_key(&keys, self.clock.now()).ok_or(SigningKeyError::NoActiveKey)?;
+        let encode = |value: &serde_json::Value| serde_json::to_vec(value)
+            .map(|json| URL_SAFE_NO_PAD.encode(json))
+            .map_err(|e| SigningKeyError::Serialization(e.to_string()));
+        
+        let header = encode(&serde_json::json!({ "alg": "EdDSA", "typ": "JWT", "kid": key.kid }))?;
+        let claims = encode(&serde_json::to_value(claims).map_err(|e| SigningKeyError::Serialization(e.to_string()))?)?;
+        let input = format!("{}.{}", header, claims);
+        let signature = key.key_pair()?.sign(input.as_bytes());
+        Ok(format!("{}.{}", input, URL_SAFE_NO_PAD.encode(signature.as_ref())))
+    }
+    
+    /// Public halves of the keys that verify, upcoming ones included
+    pub fn jwks(&self) -> Result<Jwks, SigningKeyError> {
+        let keys = self.keys();
+        let keys = verifying_keys(&keys, self.clock.now()).map(SigningKey::jwk).collect::<Result<_, _>>()?;
+        Ok(Jwks { keys })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use ring::signature::{UnparsedPublicKey, ED25519};
+    
+    /// Key ring with one key signing at `now`, for tests of signed artifacts
+    pub(crate) async fn key_ring_at(now: DateTime<Utc>) -> Arc<KeyRing> {
+        let ring = KeyRing::new(Arc::new(InMemoryKeyStore::new()), RotationPolicy::default(), Arc::new(ManualClock::new(now)));
+        ring.rotate().await.unwrap();
+        Arc::new(ring)
+    }
+    
+    fn key_ring(clock: Arc<ManualClock>) -> (KeyRing, Arc<InMemoryKeyStore>) {
+        let store = Arc::new(InMemoryKeyStore::new());
+        (KeyRing::new(store.clone(), RotationPolicy::default(), clock), store)
+    }
+    
+    #[tokio::test]
+    async fn test_rotation_keeps_signatures_valid() {
+        let clock = Arc::new(ManualClock::new("2025-01-01T00:00:00Z".parse().unwrap()));
+        let (ring, store) = key_ring(clock.clone());
+        assert_eq!(ring.sign("preview", b"Code0001"), Err(SigningKeyError::NoActiveKey));
+        
+        let first = ring.rotate().await.unwrap().unwrap();
+        assert_eq!(ring.rotate().await.unwrap(), None);
+        let signature = ring.sign("preview", b"Code0001").unwrap();
+        assert!(signature.starts_with(&format!("{}.", first)));
+        assert_eq!(ring.verify("preview", b"Code0001", &signature), Ok(()));
+        assert_eq!(ring.verify("renewal", b"Code0001", &signature), Err(SigningKeyError::InvalidSignature));
+        assert_eq!(ring.verify("preview", b"Code0002", &signature), Err(SigningKeyError::InvalidSignature));
+        
+        // The next key is published a day before it signs
+        clock.advance(Duration::days(90));
+        let second = ring.rotate().await.unwrap().unwrap();
+        assert_eq!(ring.jwks().unwrap().keys.len(), 2);
+        assert!(ring.sign("preview", b"x").unwrap().starts_with(&first));
+        clock.advance(Duration::days(1));
+        assert!(ring.sign("preview", b"x").unwrap().starts_with(&second));
+        assert_eq!(ring.verify("preview", b"Code0001", &signature), Ok(()));
+        
+        // Until the first key retires
+        clock.advance(Duration::days(7));
+        ring.rotate().await.unwrap();
+        assert_eq!(store.list().await.unwrap().len(), 1);
+        assert_eq!(ring.verify("preview", b"Code0001", &signature), Err(SigningKeyError::InvalidSignature));
+    }
+    
+    #[tokio::test]
+    async fn test_secret_keys_match_across_instances() {
+        let clock = Arc::new(ManualClock::new("2025-01-01T00:00:00Z".parse().unwrap()));
+        let ring = |secret: &[u8]| KeyRing::new(Arc::new(SecretKeyStore::new(secret)), RotationPolicy::default(), clock.clone());
+        let (first, second, other) = (ring(b"shared-secret"), ring(b"shared-secret"), ring(b"other-secret"));
+        for ring in [&first, &second, &other] {
+            assert_eq!(ring.rotate().await.unwrap(), None);
+        }
+        
+        let signature = first.sign("renewal", b"token").unwrap();
+        assert_eq!(second.verify("renewal", b"token", &signature), Ok(()));
+        assert_eq!(other.verify("renewal", b"token", &signature), Err(SigningKeyError::InvalidSignature));
+        
+        // Never rotated or retired
+        clock.advance(Duration::days(3650));
+        assert_eq!(first.rotate().await.unwrap(), None);
+        assert_eq!(second.verify("renewal", b"token", &signature), Ok(()));
+        assert_eq!(first.jwks().unwrap().keys.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_jws_verifies_with_jwks() {
+        let (ring, _) = key_ring(Arc::new(ManualClock::new(Utc::now())));
+        ring.rotate().await.unwrap();
+        
+        let jws = ring.sign_jws(&serde_json::json!({ "sub": "share-1" })).unwrap();
+        let (input, signature) = jws.rsplit_once('.').unwrap();
+        let header: serde_json::Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(input.split('.').next().unwrap()).unwrap()).unwrap();
+        let jwk = ring.jwks().unwrap().keys.remove(0);
+        assert_eq!(header["kid"], jwk.kid);
+        assert_eq!(header["alg"], "EdDSA");
+        
+        let public_key = UnparsedPublicKey::new(&ED25519, URL_SAFE_NO_PAD.decode(&jwk.x).unwrap());
+        assert!(public_key.verify(input.as_bytes(), &URL_SAFE_NO_PAD.decode(signature).unwrap()).is_ok());
+        assert!(public_key.verify(b"tampered", &URL_SAFE_NO_PAD.decode(signature).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! # Service Level Objectives
+//!
+//! Built-in service level indicators (SLIs), counted in process:
+//!
+//! - `public_access_availability` - public share and calendar requests answered without a 5xx
+//! - `public_access_latency` - public share and calendar requests answered within the latency threshold
+//! - `auth_success` - presented bearer tokens that validate
+//!
+//! Outcomes are kept in one-minute buckets for the longest window (6 h) and
+//! reported per window with their burn rate: the error rate divided by the
+//! error budget (`1 - objective`). A burn rate of 1 uses up the budget
+//! exactly over the SLO period. `GET /api/metrics` renders everything in the
+//! Prometheus text format.
+//!
+//! ## Alerts
+//!
+//! Multi-window burn rate alerts: **page** when both the 1 h and 5 min
+//! windows burn faster than 14.4x, **ticket** when both the 6 h and 30 min
+//! windows burn faster than 6x. [`SloTracker::watch`] checks every interval
+//! and sends new alerts to an [`AlertSink`], repeating an alert at most once
+//! an hour.
+//!
+//! Counters are per instance; aggregate across instances in the metrics backend.
+
+use crate::clock::{Clock, SystemClock};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Rolling windows reported for every SLI (label, minutes)
+pub const WINDOWS: [(&str, i64); 4] = [("5m", 5), ("30m", 30), ("1h", 60), ("6h", 360)];
+
+/// Default latency threshold for public access
+pub const DEFAULT_LATENCY_THRESHOLD_MS: u64 = 500;
+
+/// Requests needed in the short window before an alert is raised
+const MIN_ALERT_REQUESTS: u64 = 20;
+
+/// Minimum time between repeats of the same alert
+const ALERT_REPEAT_MINUTES: i64 = 60;
+
+/// Service level indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sli {
+    PublicAccessAvailability,
+    PublicAccessLatency,
+    AuthSuccess,
+}
+
+impl Sli {
+    pub const ALL: [Sli; 3] = [Sli::PublicAccessAvailability, Sli::PublicAccessLatency, Sli::AuthSuccess];
+    
+    /// Metric label
+    pub fn name(self) -> &'static str {
+        match self {
+            Sli::PublicAccessAvailability => "public_access_availability",
+            Sli::PublicAccessLatency => "public_access_latency",
+            Sli::AuthSuccess => "auth_success",
+        }
+    }
+}
+
+/// Objectives (target ratio of good events) and the latency threshold
+#[derive(Debug, Clone)]
+pub struct SloConfig {
+    pub availability_objective: f64,
+    pub latency_objective: f64,
+    pub latency_threshold: std::time::Duration,
+    pub auth_objective: f64,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            availability_objective: 0.999,
+            latency_objective: 0.99,
+            latency_threshold: std::time::Duration::from_millis(DEFAULT_LATENCY_THRESHOLD_MS),
+            auth_objective: 0.99,
+        }
+    }
+}
+
+impl SloConfig {
+    pub fn objective(&self, sli: Sli) -> f64 {
+        match sli {
+            Sli::PublicAccessAvailability => self.availability_objective,
+            Sli::PublicAccessLatency => self.latency_objective,
+            Sli::AuthSuccess => self.auth_objective,
+        }
+    }
+}
+
+/// Good and total events of one SLI over one window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct WindowCounts {
+    pub total: u64,
+    pub good: u64,
+}
+
+impl WindowCounts {
+    /// Share of good events (None without traffic)
+    pub fn ratio(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.good as f64 / self.total as f64)
+    }
+    
+    /// Error rate relative to the error budget (None without traffic)
+    pub fn burn_rate(&self, objective: f64) -> Option<f64> {
+        self.ratio().map(|ratio| (1.0 - ratio) / (1.0 - objective))
+    }
+}
+
+/// How urgently a burning budget needs attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Page,
+    Ticket,
+}
+
+/// Alert rules: severity, long window, short window (minutes) and burn rate threshold
+const ALERT_RULES: [(AlertSeverity, i64, i64, f64); 2] = [
+    (AlertSeverity::Page, 60, 5, 14.4),
+    (AlertSeverity::Ticket, 360, 30, 6.0),
+];
+
+/// An error budget burning too fast
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BurnAlert {
+    pub sli: Sli,
+    pub severity: AlertSeverity,
+    pub objective: f64,
+    pub long_window_minutes: i64,
+    pub short_window_minutes: i64,
+    pub long_burn_rate: f64,
+    pub short_burn_rate: f64,
+    pub raised_at: DateTime<Utc>,
+}
+
+impl BurnAlert {
+    /// One-line description for chat and paging tools
+    pub fn summary(&self) -> String {
+        format!(
+            "[{:?}] {} is burning its error budget at {:.1}x ({} min) / {:.1}x ({} min), objective {}%",
+            self.severity, self.sli.name(), self.long_burn_rate, self.long_window_minutes,
+            self.short_burn_rate, self.short_window_minutes, self.objective * 100.0,
+        )
+    }
+}
+
+/// Alert delivery errors
+#[derive(Debug, Error)]
+pub enum AlertError {
+    #[error("Alert delivery failed: {0}")]
+    Delivery(String),
+}
+
+/// Destination for burn rate alerts
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    async fn send(&self, alert: &BurnAlert) -> Result<(), AlertError>;
+}
+
+/// Events of one minute
+struct Bucket {
+    minute: i64,
+    total: u64,
+    good: u64,
+}
+
+/// Rolling SLI counters of this instance
+pub struct SloTracker {
+    config: SloConfig,
+    buckets: Mutex<HashMap<Sli, VecDeque<Bucket>>>,
+    alerted: Mutex<HashMap<(Sli, AlertSeverity), DateTime<Utc>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for SloTracker {
+    fn default() -> Self {
+        Self::new(SloConfig::default())
+    }
+}
+
+impl SloTracker {
+    pub fn new(config: SloConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            alerted: Mutex::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+    
+    /// Use another time source (tests)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    pub fn config(&self) -> &SloConfig {
+        &self.config
+    }
+    
+    fn minute(&self) -> i64 {
+        self.clock.now().timestamp().div_euclid(60)
+    }
+    
+    /// Count one event
+    pub fn record(&self, sli: Sli, good: bool) {
+        let minute = self.minute();
+        let longest = WINDOWS[WINDOWS.len() - 1].1;
+        let Ok(mut buckets) = self.buckets.lock() else { return };
+        let series = buckets.entry(sli).or_default();
+        
+        match series.back_mut() {
+            Some(bucket) if bucket.minute == minute => {
+                bucket.total += 1;
+                bucket.good += good as u64;
+            }
+            _ => series.push_back(Bucket { minute, total: 1, good: good as u64 }),
+        }
+        while series.front().is_some_and(|b| b.minute <= minute - longest) {
+            series.pop_front();
+        }
+    }
+    
+    /// Count a public share or calendar request
+    pub fn record_public_access(&self, status: u16, latency: std::time::Duration) {
+        self.record(Sli::PublicAccessAvailability, status < 500);
+        self.record(Sli::PublicAccessLatency, latency <= self.config.latency_threshold);
+    }
+    
+    /// Count a bearer token validation
+    pub fn record_auth(&self, valid: bool) {
+        self.record(Sli::AuthSuccess, valid);
+    }
+    
+    /// Events over the last `minutes` (including the current minute)
+    pub fn counts(&self, sli: Sli, minutes: i64) -> WindowCounts {
+        let since = self.minute() - minutes;
+        let Ok(buckets) = self.buckets.lock() else { return WindowCounts::default() };
+        buckets.get(&sli).into_iter().flatten()
+            .filter(|b| b.minute > since)
+            .fold(WindowCounts::default(), |acc, b| WindowCounts { total: acc.total + b.total, good: acc.good + b.good })
+    }
+    
+    /// Alerts whose rules currently fire and that were not sent within the last hour
+    pub fn due_alerts(&self) -> Vec<BurnAlert> {
+        let now = self.clock.now();
+        let alerted = self.alerted.lock().map(|a| a.clone()).unwrap_or_default();
+        
+        let mut alerts = Vec::new();
+        for sli in Sli::ALL {
+            let objective = self.config.objective(sli);
+            for (severity, long, short, threshold) in ALERT_RULES {
+                if alerted.get(&(sli, severity)).is_some_and(|at| now - *at < Duration::minutes(ALERT_REPEAT_MINUTES)) {
+                    continue;
+                }
+                let short_counts = self.counts(sli, short);
+                if short_counts.total < MIN_ALERT_REQUESTS {
+                    continue;
+                }
+                let (Some(long_burn_rate), Some(short_burn_rate)) =
+                    (self.counts(sli, long).burn_rate(objective), short_counts.burn_rate(objective)) else { continue };
+                if long_burn_rate > threshold && short_burn_rate > threshold {
+                    alerts.push(BurnAlert {
+                        sli,
+                        severity,
+                        objective,
+                        long_window_minutes: long,
+                        short_window_minutes: short,
+                        long_burn_rate,
+                        short_burn_rate,
+                        raised_at: now,
+                    });
+                }
+            }
+        }
+        alerts
+    }
+    
+    /// Remember that an alert went out
+    pub fn mark_alerted(&self, alert: &BurnAlert) {
+        if let Ok(mut alerted) = self.alerted.lock() {
+            alerted.insert((alert.sli, alert.severity), alert.raised_at);
+        }
+    }
+    
+    /// Send due alerts every interval; spawn once at startup
+    pub async fn watch(self: Arc<Self>, sink: Arc<dyn AlertSink>, interval: std::time::Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            for alert in self.due_alerts() {
+                tracing::warn!("{}", alert.summary());
+                match sink.send(&alert).await {
+                    Ok(()) => self.mark_alerted(&alert),
+                    Err(e) => tracing::warn!("SLO alert via {} failed: {}", sink.name(), e),
+                }
+            }
+        }
+    }
+    
+    /// All SLIs in the Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        let families: [(&str, &str); 4] = [
+            ("arshjul_sli_events", "Events counted for the SLI in the rolling window"),
+            ("arshjul_sli_good_events", "Good events in the rolling window"),
+            ("arshjul_slo_burn_rate", "Error budget burn rate in the rolling window (1 = budget used exactly over the SLO period)"),
+            ("arshjul_slo_objective", "Target ratio of good events"),
+        ];
+        
+        for (family, help) in families {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", family, help, family);
+            for sli in Sli::ALL {
+                let objective = self.config.objective(sli);
+                if family == "arshjul_slo_objective" {
+                    let _ = writeln!(out, "{}{{sli=\"{}\"}} {}", family, sli.name(), objective);
+                    continue;
+                }
+                for (window, minutes) in WINDOWS {
+                    let counts = self.counts(sli, minutes);
+                    let value = match family {
+                        "arshjul_sli_events" => Some(counts.total as f64),
+                        "arshjul_sli_good_events" => Some(counts.good as f64),
+                        _ => counts.burn_rate(objective),
+                    };
+                    if let Some(value) = value {
+                        let _ = writeln!(out, "{}{{sli=\"{}\",window=\"{}\"}} {}", family, sli.name(), window, value);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    
+    #[test]
+    fn test_windows_roll_and_burn_alerts() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let slo = SloTracker::default().with_clock(clock.clone());
+        
+        // 10% errors against a 99.9% objective burns at 100x
+        for i in 0..100 {
+            slo.record_public_access(if i % 10 == 0 { 503 } else { 200 }, std::time::Duration::from_millis(20));
+        }
+        assert_eq!(slo.counts(Sli::PublicAccessAvailability, 5), WindowCounts { total: 100, good: 90 });
+        assert_eq!(slo.counts(Sli::PublicAccessLatency, 5).good, 100);
+        
+        let alerts = slo.due_alerts();
+        assert_eq!(alerts.iter().map(|a| (a.sli, a.severity)).collect::<Vec<_>>(), [
+            (Sli::PublicAccessAvailability, AlertSeverity::Page),
+            (Sli::PublicAccessAvailability, AlertSeverity::Ticket),
+        ]);
+        slo.mark_alerted(&alerts[0]);
+        assert_eq!(slo.due_alerts().len(), 1);
+        
+        let metrics = slo.render_prometheus();
+        assert!(metrics.contains("arshjul_sli_events{sli=\"public_access_availability\",window=\"5m\"} 100\n"));
+        assert!(metrics.contains("arshjul_slo_burn_rate{sli=\"public_access_availability\",window=\"1h\"} "));
+        let burn_rate = slo.counts(Sli::PublicAccessAvailability, 60).burn_rate(0.999).unwrap();
+        assert!((burn_rate - 100.0).abs() < 1e-6);
+        assert!(!metrics.contains("arshjul_slo_burn_rate{sli=\"auth_success\""));
+        
+        clock.advance(Duration::minutes(6));
+        assert_eq!(slo.counts(Sli::PublicAccessAvailability, 5).total, 0);
+        assert_eq!(slo.counts(Sli::PublicAccessAvailability, 60).total, 100);
+        clock.advance(Duration::hours(6));
+        assert_eq!(slo.counts(Sli::PublicAccessAvailability, 360).total, 0);
+    }
+}
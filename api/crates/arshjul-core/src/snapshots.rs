@@ -0,0 +1,214 @@
+//! # Static Share Snapshots
+//!
+//! Takes very high-traffic public shares off the API: the rendered public
+//! view is written to a blob container fronted by a CDN, and
+//! `GET /api/public/s/{shortCode}` answers with a redirect to it.
+//!
+//! ## Lifecycle
+//!
+//! [`SnapshotPublisher`] listens on the event bus:
+//! - share created/updated: re-render, or remove if no longer eligible
+//! - share deleted: remove
+//! - activity/layer/activity type changed: re-render the organization's
+//!   snapshot shares, and the combined snapshot shares of partners it
+//!   grants layers to (`crate::federation`)
+//!
+//! Combined shares are rendered like their public view: partner layers the
+//! grant still covers, redacted as it says. Revoking a grant publishes layer
+//! changes for the partner, which re-renders its snapshots without them.
+//!
+//! Snapshots live at `{shareId}/{hash(shareKey)}.json`, so the URL is as
+//! unguessable as the share link and regenerating the key retires it.
+//! Shares expiring without a change keep their blob until the next event
+//! for the organization; blobs are served with a short max-age.
+
+use crate::clock::{Clock, SystemClock};
+use crate::events::{ChangeKind, DomainEvent, EntityKind, EventError, EventSubscriber};
+use crate::federation;
+use crate::http_cache::{content_hash, PUBLIC_MAX_AGE_SECONDS};
+use crate::models::*;
+use crate::public_access;
+use crate::storage::{list_all_shares, ActivityStorage, PolicyStorage, ShareStorage, StorageError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// Blob/object store receiving snapshots
+#[async_trait]
+pub trait SnapshotStore: Send + Sync {
+    /// Write (or overwrite) an object
+    async fn put(&self, path: &str, content_type: &str, cache_control: &str, body: Vec<u8>) -> Result<(), EventError>;
+    
+    /// Delete every object under a prefix
+    async fn delete_prefix(&self, prefix: &str) -> Result<(), EventError>;
+}
+
+/// Object path of a share's snapshot
+pub fn snapshot_path(share: &ShareLink) -> String {
+    format!("{}/{}.json", share.id, content_hash(share.share_key.as_bytes()))
+}
+
+/// Public snapshot URL, if the share is served from a snapshot
+pub fn snapshot_url(share: &ShareLink, public_base_url: Option<&str>) -> Option<String> {
+    let base = public_base_url?;
+    if !share.publish_snapshot {
+        return None;
+    }
+    Some(format!("{}/{}", base.trim_end_matches('/'), snapshot_path(share)))
+}
+
+/// Whether a share should currently have a snapshot
+fn is_eligible(share: &ShareLink, now: DateTime<Utc>) -> bool {
+    share.publish_snapshot && share.is_active && !share.is_expired(now)
+}
+
+/// Event subscriber keeping snapshots in sync with the data
+pub struct SnapshotPublisher {
+    store: Arc<dyn SnapshotStore>,
+    shares: Arc<dyn ShareStorage>,
+    activities: Arc<dyn ActivityStorage>,
+    policies: Arc<dyn PolicyStorage>,
+    clock: Arc<dyn Clock>,
+}
+
+impl SnapshotPublisher {
+    pub fn new(
+        store: Arc<dyn SnapshotStore>,
+        shares: Arc<dyn ShareStorage>,
+        activities: Arc<dyn ActivityStorage>,
+        policies: Arc<dyn PolicyStorage>,
+    ) -> Self {
+        Self { store, shares, activities, policies, clock: Arc::new(SystemClock) }
+    }
+    
+    /// Use another time source (tests)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Render and upload one share, replacing any older snapshot
+    async fn publish(&self, share: &ShareLink) -> Result<(), EventError> {
+        let now = self.clock.now();
+        let year = public_access::share_year(share, now);
+        let policy = self.policies.get(&share.organization_id).await.map_err(to_event_error)?;
+        let mut shown = share.clone();
+        shown.view_settings = public_access::view_settings(share, policy.share_view_defaults.as_ref());
+        let mut activities = self.activities.list_by_layers(&share.organization_id, &share.layer_config.layer_ids, Some(year))
+            .await.map_err(to_event_error)?;
+        
+        // Partner layers, as the public view shows them
+        for source in std::mem::take(&mut shown.partner_sources) {
+            let grantor = self.policies.get(&source.organization_id).await.map_err(to_event_error)?;
+            let Some(grant) = federation::active_grant(&grantor, &source, &share.organization_id, now) else {
+                continue;
+            };
+            let (layer_ids, granted) = federation::granted_activities(self.activities.as_ref(), grant, &source, year)
+                .await.map_err(to_event_error)?;
+            shown.layer_config.layer_ids.extend(layer_ids);
+            activities.extend(granted);
+        }
+        
+        let body = serde_json::to_vec(&public_access::project(&shown, activities, year, policy.activity_order.unwrap_or_default()))
+            .map_err(|e| EventError::Serialization(e.to_string()))?;
+        
+        // Drops snapshots under a previous key
+        self.store.delete_prefix(&format!("{}/", share.id)).await?;
+        self.store.put(
+            &snapshot_path(share),
+            "application/json",
+            &format!("public, max-age={}", PUBLIC_MAX_AGE_SECONDS),
+            body,
+        ).await
+    }
+    
+    async fn sync_share(&self, organization_id: &str, share_id: &str) -> Result<(), EventError> {
+        match self.shares.get(organization_id, share_id).await {
+            Ok(share) if is_eligible(&share, self.clock.now()) => self.publish(&share).await,
+            Ok(_) | Err(StorageError::NotFound(_)) => self.store.delete_prefix(&format!("{}/", share_id)).await,
+            Err(e) => Err(to_event_error(e)),
+        }
+    }
+    
+    /// Re-render the organization's snapshot shares, and those of partners drawing on its layers
+    async fn sync_organization(&self, organization_id: &str) -> Result<(), EventError> {
+        self.sync_shares(organization_id, |_| true).await?;
+        
+        let now = self.clock.now();
+        let policy = self.policies.get(organization_id).await.map_err(to_event_error)?;
+        let mut partners: Vec<&str> = policy.federation_grants.iter()
+            .filter(|g| g.expires_at.is_none_or(|at| at > now))
+            .map(|g| g.partner_organization_id.as_str())
+            .collect();
+        partners.sort_unstable();
+        partners.dedup();
+        for partner in partners {
+            self.sync_shares(partner, |s| s.partner_sources.iter().any(|p| p.organization_id == organization_id)).await?;
+        }
+        Ok(())
+    }
+    
+    async fn sync_shares(&self, organization_id: &str, affected: impl Fn(&ShareLink) -> bool) -> Result<(), EventError> {
+        let shares = list_all_shares(self.shares.as_ref(), organization_id).await.map_err(to_event_error)?;
+        let now = self.clock.now();
+        for share in shares.iter().filter(|s| s.publish_snapshot && affected(s)) {
+            if is_eligible(share, now) {
+                self.publish(share).await?;
+            } else {
+                self.store.delete_prefix(&format!("{}/", share.id)).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_event_error(e: StorageError) -> EventError {
+    EventError::Delivery(e.to_string())
+}
+
+#[async_trait]
+impl EventSubscriber for SnapshotPublisher {
+    fn name(&self) -> &'static str {
+        "snapshots"
+    }
+    
+    async fn handle(&self, event: &DomainEvent) -> Result<(), EventError> {
+        let DomainEvent::EntityChanged(change) = event else {
+            return Ok(());
+        };
+        
+        match (change.entity, change.change) {
+            (EntityKind::Share, ChangeKind::Deleted) => {
+                self.store.delete_prefix(&format!("{}/", change.entity_id)).await
+            }
+            (EntityKind::Share, _) => self.sync_share(&change.organization_id, &change.entity_id).await,
+            _ => self.sync_organization(&change.organization_id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_snapshot_url() {
+        let mut share: ShareLink = serde_json::from_value(serde_json::json!({
+            "id": "share-1", "shareKey": "a".repeat(64), "shortCode": "AbCd1234",
+            "visibility": "public", "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2026-01-01T00:00:00Z",
+            "layerConfig": { "layerIds": ["layer-1"] }, "viewSettings": {},
+        })).unwrap();
+        
+        assert_eq!(snapshot_url(&share, Some("https://cdn.example.net/snapshots/")), None);
+        
+        share.publish_snapshot = true;
+        let url = snapshot_url(&share, Some("https://cdn.example.net/snapshots/")).unwrap();
+        assert!(url.starts_with("https://cdn.example.net/snapshots/share-1/"));
+        assert!(!url.contains(&share.share_key));
+        assert_eq!(snapshot_url(&share, None), None);
+        
+        share.share_key = "b".repeat(64);
+        assert_ne!(snapshot_url(&share, Some("https://cdn.example.net")).unwrap(), url);
+    }
+}
@@ -0,0 +1,170 @@
+//! # Splitting and Merging Activities
+//!
+//! Planners split a long activity into phases and merge duplicates:
+//!
+//! - `POST /api/activities/{id}/split` with `{ "at": "2025-03-01T00:00:00Z" }`
+//!   ends the activity at `at` and creates a second one from `at` to the
+//!   original end, linked to the first by `splitFrom`
+//! - `POST /api/activities/merge` with `{ "targetId": "a-1", "sourceId": "a-2" }`
+//!   widens the target to cover both, adds the source's tags and links,
+//!   records the source in `mergedFrom` and deletes it
+//!
+//! Both are written with [`ActivityStorage::apply_changes`], so all changes
+//! are stored or none. The audit entry keeps the activities as they were
+//! before; for a merge that is the only record left of the source.
+//!
+//! [`ActivityStorage::apply_changes`]: crate::storage::ActivityStorage::apply_changes
+
+use crate::models::Activity;
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+/// Audit action recorded when an activity is split
+pub const AUDIT_ACTION_SPLIT: &str = "activity.split";
+
+/// Audit action recorded when two activities are merged
+pub const AUDIT_ACTION_MERGED: &str = "activity.merged";
+
+/// Invalid splits and merges
+#[derive(Debug, Error, PartialEq)]
+pub enum SplitMergeError {
+    #[error("Split date must be after the start and before the end of the activity")]
+    OutsideActivity,
+    
+    #[error("An activity cannot be merged with itself")]
+    SameActivity,
+    
+    #[error("Only activities on the same layer can be merged")]
+    DifferentLayers,
+    
+    #[error("Recurring activities cannot be split or merged; change their occurrences instead")]
+    Recurring,
+}
+
+/// Split `activity` at `at` into the shortened original and a new second part
+pub fn split(
+    activity: &Activity,
+    at: DateTime<Utc>,
+    title: Option<String>,
+    second_id: String,
+    now: DateTime<Utc>,
+) -> Result<(Activity, Activity), SplitMergeError> {
+    if activity.recurrence.is_some() {
+        return Err(SplitMergeError::Recurring);
+    }
+    if at <= activity.start_date || at >= activity.end_date {
+        return Err(SplitMergeError::OutsideActivity);
+    }
+    
+    let mut first = activity.clone();
+    first.end_date = at;
+    first.updated_at = Some(now);
+    first.edit_count = first.edit_count.saturating_add(1);
+    
+    let second = Activity {
+        id: second_id,
+        title: title.unwrap_or_else(|| activity.title.clone()),
+        start_date: at,
+        created_at: Some(now),
+        updated_at: Some(now),
+        edit_count: 0,
+        approval_review: None,
+        split_from: Some(activity.id.clone()),
+        merged_from: Vec::new(),
+        ..activity.clone()
+    };
+    Ok((first, second))
+}
+
+/// Merge `source` into `target`: the target's fields win, its dates widen
+/// to cover both, and tags, links and a missing description come from the
+/// source
+pub fn merge(target: &Activity, source: &Activity, now: DateTime<Utc>) -> Result<Activity, SplitMergeError> {
+    if target.id == source.id {
+        return Err(SplitMergeError::SameActivity);
+    }
+    if target.scope != source.scope {
+        return Err(SplitMergeError::DifferentLayers);
+    }
+    if target.recurrence.is_some() || source.recurrence.is_some() {
+        return Err(SplitMergeError::Recurring);
+    }
+    
+    let mut merged = target.clone();
+    merged.start_date = target.start_date.min(source.start_date);
+    merged.end_date = target.end_date.max(source.end_date);
+    for tag in &source.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+    for link in &source.links {
+        if !merged.links.iter().any(|l| l.url == link.url) {
+            merged.links.push(link.clone());
+        }
+    }
+    if merged.description.is_none() {
+        merged.description = source.description.clone();
+        merged.description_format = source.description_format;
+    }
+    merged.merged_from.push(source.id.clone());
+    merged.merged_from.extend(source.merged_from.iter().cloned());
+    merged.updated_at = Some(now);
+    merged.edit_count = merged.edit_count.saturating_add(1);
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn activity(id: &str, start: &str, end: &str, tags: &[&str]) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "title": id, "startDate": start, "endDate": end, "type": "event",
+            "color": "#000000", "highlightColor": "#000000", "scope": "layer-1", "scopeId": "layer-1",
+            "organizationId": "org-1", "tags": tags, "editCount": 2,
+        })).unwrap()
+    }
+    
+    fn date(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+    
+    #[test]
+    fn test_split() {
+        let now = date("2025-01-01T00:00:00Z");
+        let original = activity("a-1", "2025-03-01T00:00:00Z", "2025-06-30T00:00:00Z", &["rollout"]);
+        
+        let (first, second) = split(&original, date("2025-05-01T00:00:00Z"), Some("Phase 2".to_string()), "a-2".to_string(), now).unwrap();
+        assert_eq!((first.start_date, first.end_date), (original.start_date, date("2025-05-01T00:00:00Z")));
+        assert_eq!(first.edit_count, 3);
+        assert_eq!((second.start_date, second.end_date), (date("2025-05-01T00:00:00Z"), original.end_date));
+        assert_eq!((second.id.as_str(), second.title.as_str(), second.edit_count), ("a-2", "Phase 2", 0));
+        assert_eq!(second.split_from.as_deref(), Some("a-1"));
+        assert_eq!(second.tags, ["rollout"]);
+        
+        for at in ["2025-03-01T00:00:00Z", "2025-06-30T00:00:00Z", "2025-07-01T00:00:00Z"] {
+            assert_eq!(split(&original, date(at), None, "a-2".to_string(), now).unwrap_err(), SplitMergeError::OutsideActivity);
+        }
+    }
+    
+    #[test]
+    fn test_merge() {
+        let now = date("2025-01-01T00:00:00Z");
+        let target = activity("a-1", "2025-03-10T00:00:00Z", "2025-03-12T00:00:00Z", &["board"]);
+        let mut source = activity("a-2", "2025-03-09T00:00:00Z", "2025-03-11T00:00:00Z", &["board", "q1"]);
+        source.description = Some("Agenda".to_string());
+        source.merged_from = vec!["a-0".to_string()];
+        
+        let merged = merge(&target, &source, now).unwrap();
+        assert_eq!(merged.id, "a-1");
+        assert_eq!((merged.start_date, merged.end_date), (source.start_date, target.end_date));
+        assert_eq!(merged.tags, ["board", "q1"]);
+        assert_eq!(merged.description.as_deref(), Some("Agenda"));
+        assert_eq!(merged.merged_from, ["a-2", "a-0"]);
+        
+        assert_eq!(merge(&target, &target, now).unwrap_err(), SplitMergeError::SameActivity);
+        source.scope = "layer-2".to_string();
+        assert_eq!(merge(&target, &source, now).unwrap_err(), SplitMergeError::DifferentLayers);
+    }
+}
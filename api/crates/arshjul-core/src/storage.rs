@@ -0,0 +1,2037 @@
+//! # Storage Abstraction Layer
+//!
+//! Provides a unified interface for data storage that works with both:
+//! - Azure Table Storage (default, simple, cheap)
+//! - Azure Cosmos DB (future upgrade path)
+//!
+//! Only the traits and the in-memory implementation live here; the Azure
+//! backends are in the `arshjul-azure` crate.
+//!
+//! ## Design Principles
+//!
+//! 1. **Partition Key = organizationId**: Multi-tenant isolation
+//! 2. **Row Key = id**: Unique identifier per entity
+//! 3. **TTL Support**: For automatic expiration (Cosmos DB native, manual check for Table Storage)
+
+use crate::models::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Storage errors
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Entity not found: {0}")]
+    NotFound(String),
+    
+    #[error("Entity already exists: {0}")]
+    AlreadyExists(String),
+    
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    
+    #[error("Validation error: {0}")]
+    Validation(String),
+    
+    #[error("Storage error: {0}")]
+    Storage(String),
+    
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+    
+    /// Throttled or briefly unavailable; the request was not carried out
+    #[error("Storage temporarily unavailable: {0}")]
+    Transient(String),
+    
+    /// Written by someone else since it was read
+    #[error("Changed since it was read: {0}")]
+    Conflict(String),
+}
+
+impl StorageError {
+    /// Whether the same request may succeed later; `storage_retry` retries these
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, StorageError::Transient(_))
+    }
+}
+
+/// Query options for listing entities
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Maximum number of results
+    pub page_size: Option<u32>,
+    /// Continuation token for pagination
+    pub continuation_token: Option<String>,
+    /// Conditions items must meet; backends translate it
+    pub filter: Option<Filter>,
+}
+
+/// Query result with pagination
+#[derive(Debug, Clone)]
+pub struct QueryResult<T> {
+    pub items: Vec<T>,
+    pub continuation_token: Option<String>,
+    /// Items on all pages; None where the backend can't count them cheaply
+    /// (see [`crate::counters`])
+    pub total_count: Option<u64>,
+}
+
+/// Activity writes of one organization committed together by
+/// [`ActivityStorage::apply_changes`]
+#[derive(Debug, Clone, Default)]
+pub struct ActivityChanges {
+    /// New activities; none may exist
+    pub create: Vec<Activity>,
+    /// Replacements; all must exist
+    pub update: Vec<Activity>,
+    /// IDs of activities to delete; all must exist
+    pub delete: Vec<String>,
+    /// ETags updated and deleted activities were read at (see
+    /// [`ActivityStorage::get_tagged`]); changes to an activity written since
+    /// fail with [`StorageError::Conflict`]. Others are written unconditionally
+    pub etags: HashMap<String, String>,
+}
+
+impl ActivityChanges {
+    /// Number of writes
+    pub fn len(&self) -> usize {
+        self.create.len() + self.update.len() + self.delete.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Refuse a change to an activity that is no longer at the ETag it was read at
+    pub fn check_etag(&self, activity_id: &str, current: &str) -> Result<(), StorageError> {
+        match self.etags.get(activity_id) {
+            Some(expected) if expected != current => Err(StorageError::Conflict(activity_id.to_string())),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Check the ETags of every change against what is stored now
+    pub async fn check_etags<S: ActivityStorage + ?Sized>(&self, storage: &S, organization_id: &str) -> Result<(), StorageError> {
+        for activity_id in self.update.iter().map(|a| &a.id).chain(&self.delete) {
+            if self.etags.contains_key(activity_id) {
+                let (_, etag) = storage.get_tagged(organization_id, activity_id).await?;
+                self.check_etag(activity_id, &etag)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Refuse activities of another organization than the one written to
+    pub fn check_organization(&self, organization_id: &str) -> Result<(), StorageError> {
+        match self.create.iter().chain(&self.update).find(|a| a.organization_id != organization_id) {
+            Some(other) => Err(StorageError::Validation(format!("Activity {} belongs to another organization", other.id))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Properties a [`Filter`] can compare, as serialized (camelCase)
+///
+/// Property names only come from here, so backends can put them into query
+/// text as they are; values are always escaped or passed as parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterField {
+    Id,
+    Visibility,
+    IsActive,
+    /// Layer of an activity
+    Scope,
+    StartDate,
+    EndDate,
+    /// Action of an audit entry
+    Action,
+}
+
+impl FilterField {
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterField::Id => "id",
+            FilterField::Visibility => "visibility",
+            FilterField::IsActive => "isActive",
+            FilterField::Scope => "scope",
+            FilterField::StartDate => "startDate",
+            FilterField::EndDate => "endDate",
+            FilterField::Action => "action",
+        }
+    }
+}
+
+/// Comparison of a [`Filter`] condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Equal to one of a list of values
+    In,
+}
+
+impl FilterOp {
+    fn odata(self) -> &'static str {
+        match self {
+            FilterOp::Eq | FilterOp::In => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Lt => "lt",
+            FilterOp::Le => "le",
+            FilterOp::Gt => "gt",
+            FilterOp::Ge => "ge",
+        }
+    }
+    
+    fn sql(self) -> &'static str {
+        match self {
+            FilterOp::Eq | FilterOp::In => "=",
+            FilterOp::Ne => "!=",
+            FilterOp::Lt => "<",
+            FilterOp::Le => "<=",
+            FilterOp::Gt => ">",
+            FilterOp::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FilterCondition {
+    field: FilterField,
+    op: FilterOp,
+    /// An array for [`FilterOp::In`]
+    value: serde_json::Value,
+}
+
+/// Conditions on top-level properties joined by `and`, built with a
+/// [`FilterBuilder`]
+///
+/// Backends render it in their own syntax ([`Filter::to_odata`],
+/// [`Filter::to_sql`]) or evaluate it on serialized entities
+/// ([`Filter::matches`]); all three agree. A missing property differs from
+/// every value and is neither less nor greater than any.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Filter {
+    conditions: Vec<FilterCondition>,
+}
+
+/// Builds a [`Filter`], e.g.
+/// `FilterBuilder::new().eq(FilterField::Visibility, "public").ne(FilterField::IsActive, false).build()`
+#[derive(Debug, Clone, Default)]
+pub struct FilterBuilder {
+    filter: Filter,
+}
+
+impl FilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    fn push(mut self, field: FilterField, op: FilterOp, value: serde_json::Value) -> Self {
+        self.filter.conditions.push(FilterCondition { field, op, value });
+        self
+    }
+    
+    pub fn eq(self, field: FilterField, value: impl Into<serde_json::Value>) -> Self {
+        self.push(field, FilterOp::Eq, value.into())
+    }
+    
+    pub fn ne(self, field: FilterField, value: impl Into<serde_json::Value>) -> Self {
+        self.push(field, FilterOp::Ne, value.into())
+    }
+    
+    pub fn lt(self, field: FilterField, value: impl Into<serde_json::Value>) -> Self {
+        self.push(field, FilterOp::Lt, value.into())
+    }
+    
+    pub fn le(self, field: FilterField, value: impl Into<serde_json::Value>) -> Self {
+        self.push(field, FilterOp::Le, value.into())
+    }
+    
+    pub fn gt(self, field: FilterField, value: impl Into<serde_json::Value>) -> Self {
+        self.push(field, FilterOp::Gt, value.into())
+    }
+    
+    pub fn ge(self, field: FilterField, value: impl Into<serde_json::Value>) -> Self {
+        self.push(field, FilterOp::Ge, value.into())
+    }
+    
+    /// Property equal to one of `values`; nothing matches an empty list
+    pub fn one_of<V: Into<serde_json::Value>>(self, field: FilterField, values: impl IntoIterator<Item = V>) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        self.push(field, FilterOp::In, serde_json::Value::Array(values))
+    }
+    
+    /// The conditions of `other`, too
+    pub fn and(mut self, other: Filter) -> Self {
+        self.filter.conditions.extend(other.conditions);
+        self
+    }
+    
+    /// The filter; None without conditions
+    pub fn build(self) -> Option<Filter> {
+        Some(self.filter).filter(|f| !f.conditions.is_empty())
+    }
+}
+
+/// OData literal, strings quoted with `''` escapes
+fn odata_literal(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => format!("'{}'", text.replace('\'', "''")),
+        other => other.to_string(),
+    }
+}
+
+/// Order of two JSON values of the same kind; None across kinds
+fn compare_values(a: &serde_json::Value, b: &serde_json::Value) -> Option<std::cmp::Ordering> {
+    use serde_json::Value;
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+impl Filter {
+    /// Activities on one of `layer_ids` that overlap `year`, as listed by
+    /// [`ActivityStorage::list_by_layers`]; backends filtering activities
+    /// they already read use [`on_layers_in_year`] instead
+    ///
+    /// Dates are stored as RFC 3339 strings, so the year bounds are bare year
+    /// prefixes: `"2025-12-31T23:59:59.5Z" < "2026"` but
+    /// `"2026-01-01T00:00:00Z" > "2026"`, whatever the fractional seconds.
+    pub fn layers_and_year(layer_ids: &[String], year: Option<i32>) -> Filter {
+        let mut builder = FilterBuilder::new().one_of(FilterField::Scope, layer_ids.iter().cloned());
+        if let Some(year) = year {
+            builder = builder
+                .lt(FilterField::StartDate, format!("{:04}", year + 1))
+                .ge(FilterField::EndDate, format!("{:04}", year));
+        }
+        builder.filter
+    }
+    
+    /// OData `$filter` (Table Storage) of the conditions on fields stored in
+    /// `columns`; None when there are none
+    ///
+    /// Only map columns every entity of the table has: a comparison with a
+    /// missing column never matches, not even `ne`. The other conditions
+    /// still need [`Filter::matches`].
+    pub fn to_odata(&self, columns: &[(FilterField, &str)]) -> Option<String> {
+        let clauses: Vec<String> = self.conditions.iter()
+            .filter_map(|c| {
+                let (_, column) = columns.iter().find(|(field, _)| *field == c.field)?;
+                match (c.op, &c.value) {
+                    (FilterOp::In, serde_json::Value::Array(values)) if !values.is_empty() => Some(format!("({})", values.iter()
+                        .map(|v| format!("{} eq {}", column, odata_literal(v)))
+                        .collect::<Vec<_>>()
+                        .join(" or "))),
+                    (FilterOp::In, _) => None,
+                    (op, value) => Some(format!("{} {} {}", column, op.odata(), odata_literal(value))),
+                }
+            })
+            .collect();
+        Some(clauses.join(" and ")).filter(|f| !f.is_empty())
+    }
+    
+    /// Cosmos DB SQL conditions on the document `alias`, with the values as
+    /// named parameters `@{prefix}0`, `@{prefix}1`, ...
+    pub fn to_sql(&self, alias: &str, prefix: &str) -> (Vec<String>, Vec<(String, serde_json::Value)>) {
+        let mut conditions = Vec::new();
+        let mut parameters = Vec::new();
+        for (i, c) in self.conditions.iter().enumerate() {
+            let property = format!("{}.{}", alias, c.field.name());
+            let name = format!("@{}{}", prefix, i);
+            conditions.push(match c.op {
+                // Missing properties compare as undefined, which would drop them
+                FilterOp::Ne => format!("(NOT IS_DEFINED({0}) OR {0} != {1})", property, name),
+                FilterOp::In => format!("ARRAY_CONTAINS({}, {})", name, property),
+                op => format!("{} {} {}", property, op.sql(), name),
+            });
+            parameters.push((name, c.value.clone()));
+        }
+        (conditions, parameters)
+    }
+    
+    /// Evaluate against an item, serializing it first
+    pub fn matches_item<T: serde::Serialize>(&self, item: &T) -> Result<bool, StorageError> {
+        let entity = serde_json::to_value(item).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        Ok(self.matches(&entity))
+    }
+    
+    /// Evaluate against a serialized entity
+    pub fn matches(&self, entity: &serde_json::Value) -> bool {
+        use std::cmp::Ordering;
+        self.conditions.iter().all(|c| {
+            let actual = entity.get(c.field.name()).unwrap_or(&serde_json::Value::Null);
+            match c.op {
+                FilterOp::Eq => actual == &c.value,
+                FilterOp::Ne => actual != &c.value,
+                FilterOp::In => c.value.as_array().is_some_and(|values| values.contains(actual)),
+                op => compare_values(actual, &c.value).is_some_and(|order| match op {
+                    FilterOp::Lt => order == Ordering::Less,
+                    FilterOp::Le => order != Ordering::Greater,
+                    FilterOp::Gt => order == Ordering::Greater,
+                    _ => order != Ordering::Less,
+                }),
+            }
+        })
+    }
+}
+
+/// ETag of an activity's content, for backends that keep none of their own
+pub fn activity_etag(activity: &Activity) -> String {
+    crate::http_cache::content_hash(&serde_json::to_vec(activity).unwrap_or_default())
+}
+
+/// ETag of a share's content, for backends that keep none of their own
+pub fn share_etag(share: &ShareLink) -> String {
+    crate::http_cache::content_hash(&serde_json::to_vec(share).unwrap_or_default())
+}
+
+/// Storage trait for shares
+#[async_trait]
+pub trait ShareStorage: Send + Sync {
+    /// Create a new share
+    ///
+    /// Fails with `AlreadyExists` if the short code is in use or retired
+    /// (conditional insert into the short code index).
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError>;
+    
+    /// Get share by ID
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError>;
+    
+    /// Get share by short code (for public access)
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError>;
+    
+    /// Update share
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError>;
+    
+    /// A share and the ETag it was read at (see [`Self::replace`])
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        let share = self.get(organization_id, share_id).await?;
+        let etag = share_etag(&share);
+        Ok((share, etag))
+    }
+    
+    /// Update a share that still has `etag`; None if someone wrote it since
+    ///
+    /// Backends check the ETag in the write itself; this default compares
+    /// content first and is only as safe as the backend's `update`.
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        let (_, current) = self.get_tagged(&share.organization_id, &share.id).await?;
+        if current != etag {
+            return Ok(None);
+        }
+        self.update(share).await.map(Some)
+    }
+    
+    /// Delete share, leaving a short code tombstone (see [`ShortCodeTombstone::for_deleted_share`])
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError>;
+    
+    /// Get the tombstone of a retired short code, if still active
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError>;
+    
+    /// List shares for organization
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError>;
+    
+    /// Shares of the organization showing `layer_id`; backends that can't
+    /// query into the layer list read every share
+    async fn list_by_layer(&self, organization_id: &str, layer_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        let mut shares = list_all_shares(self, organization_id).await?;
+        shares.retain(|s| s.layer_config.layer_ids.iter().any(|id| id == layer_id));
+        Ok(shares)
+    }
+    
+    /// Increment view count (atomic)
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError>;
+    
+    /// Get a share in the recycle bin (see [`crate::recycle_bin`])
+    async fn get_deleted(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        Some(self.get(organization_id, share_id).await?)
+            .filter(|s| s.deleted_at.is_some())
+            .ok_or_else(|| StorageError::NotFound(share_id.to_string()))
+    }
+    
+    /// Shares of the organization in the recycle bin
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        Ok(list_all_shares(self, organization_id).await?.into_iter()
+            .filter(|s| s.deleted_at.is_some())
+            .collect())
+    }
+}
+
+/// Storage trait for activities
+#[async_trait]
+pub trait ActivityStorage: Send + Sync {
+    /// Create activity
+    async fn create(&self, activity: Activity) -> Result<Activity, StorageError>;
+    
+    /// Create several activities, e.g. for an import; backends without
+    /// transactions create them one by one and may stop part way
+    async fn create_many(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let mut created = Vec::with_capacity(activities.len());
+        for activity in activities {
+            created.push(self.create(activity).await?);
+        }
+        Ok(created)
+    }
+    
+    /// Get activity by ID
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError>;
+    
+    /// An activity and the ETag it was read at (see [`ActivityChanges::etags`])
+    async fn get_tagged(&self, organization_id: &str, activity_id: &str) -> Result<(Activity, String), StorageError> {
+        let activity = self.get(organization_id, activity_id).await?;
+        let etag = activity_etag(&activity);
+        Ok((activity, etag))
+    }
+    
+    /// Update activity
+    async fn update(&self, activity: Activity) -> Result<Activity, StorageError>;
+    
+    /// Delete activity
+    async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError>;
+    
+    /// Update several activities of one organization; backends without
+    /// transactions update them one by one and may stop part way
+    async fn update_batch(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let mut updated = Vec::with_capacity(activities.len());
+        for activity in activities {
+            updated.push(self.update(activity).await?);
+        }
+        Ok(updated)
+    }
+    
+    /// Create, update and delete activities of one organization together,
+    /// e.g. for a split or merge; backends without transactions apply the
+    /// changes one by one and may stop part way
+    async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+        changes.check_etags(self, organization_id).await?;
+        for activity in changes.create {
+            self.create(activity).await?;
+        }
+        for activity in changes.update {
+            self.update(activity).await?;
+        }
+        for activity_id in changes.delete {
+            self.delete(organization_id, &activity_id).await?;
+        }
+        Ok(())
+    }
+    
+    /// List activities for organization
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<Activity>, StorageError>;
+    
+    /// List activities for specific layers
+    async fn list_by_layers(
+        &self,
+        organization_id: &str,
+        layer_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<Activity>, StorageError>;
+    
+    /// Get an activity in the recycle bin (see [`crate::recycle_bin`])
+    async fn get_deleted(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        Some(self.get(organization_id, activity_id).await?)
+            .filter(|a| a.deleted_at.is_some())
+            .ok_or_else(|| StorageError::NotFound(activity_id.to_string()))
+    }
+    
+    /// Activities of the organization in the recycle bin
+    async fn list_deleted(&self, organization_id: &str) -> Result<Vec<Activity>, StorageError> {
+        let mut deleted = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let page = self.list(organization_id, QueryOptions { continuation_token, ..Default::default() }).await?;
+            deleted.extend(page.items.into_iter().filter(|a| a.deleted_at.is_some()));
+            match page.continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Storage trait for layers
+#[async_trait]
+pub trait LayerStorage: Send + Sync {
+    /// Create layer
+    async fn create(&self, layer: Layer) -> Result<Layer, StorageError>;
+    
+    /// Get layer by ID
+    async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError>;
+    
+    /// Update layer
+    async fn update(&self, layer: Layer) -> Result<Layer, StorageError>;
+    
+    /// Delete layer
+    async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError>;
+    
+    /// List layers for organization
+    async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError>;
+}
+
+/// Storage trait for activity type configs
+/// Storage trait for activity type configs
+#[async_trait]
+pub trait ActivityTypeStorage: Send + Sync {
+    /// Create or update activity type
+    async fn upsert(&self, config: ActivityTypeConfig) -> Result<ActivityTypeConfig, StorageError>;
+    
+    /// Get activity type by key
+    async fn get(&self, organization_id: &str, key: &str) -> Result<ActivityTypeConfig, StorageError>;
+    
+    /// Delete activity type; system types are refused (see [`ensure_deletable`])
+    async fn delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError>;
+    
+    /// Delete activity type, system types included; only for purging the organization
+    async fn force_delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError>;
+    
+    /// List activity types for organization
+    async fn list(&self, organization_id: &str) -> Result<Vec<ActivityTypeConfig>, StorageError>;
+}
+
+/// Refuse deleting a system activity type, whatever the backend
+pub fn ensure_deletable(config: &ActivityTypeConfig) -> Result<(), StorageError> {
+    if config.is_system {
+        return Err(StorageError::Validation(format!("System activity type can't be deleted: {}", config.key)));
+    }
+    Ok(())
+}
+
+/// Storage trait for user settings
+#[async_trait]
+pub trait UserSettingsStorage: Send + Sync {
+    /// Get user settings (returns default if not found)
+    async fn get(&self, organization_id: &str, user_id: &str) -> Result<UserSettings, StorageError>;
+    
+    /// Create or update user settings
+    async fn upsert(&self, settings: UserSettings) -> Result<UserSettings, StorageError>;
+    
+    /// Delete user settings
+    async fn delete(&self, organization_id: &str, user_id: &str) -> Result<(), StorageError>;
+    
+    /// List all user settings for organization
+    async fn list(&self, organization_id: &str) -> Result<Vec<UserSettings>, StorageError>;
+}
+
+/// Storage trait for the audit log
+#[async_trait]
+pub trait AuditStorage: Send + Sync {
+    /// Append an entry
+    async fn record(&self, entry: AuditEntry) -> Result<(), StorageError>;
+    
+    /// List entries for organization (oldest first)
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<AuditEntry>, StorageError>;
+    
+    /// Delete all entries for organization, returning the number removed
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError>;
+}
+
+/// Storage trait for organization policies
+#[async_trait]
+pub trait PolicyStorage: Send + Sync {
+    /// Get the organization's policy (returns default if not set)
+    async fn get(&self, organization_id: &str) -> Result<OrganizationPolicy, StorageError>;
+    
+    /// Create or replace the policy
+    async fn upsert(&self, policy: OrganizationPolicy) -> Result<OrganizationPolicy, StorageError>;
+    
+    /// Delete the policy
+    async fn delete(&self, organization_id: &str) -> Result<(), StorageError>;
+}
+
+/// Removal of expired shares for backends without native TTL (see [`crate::share_cleanup`])
+#[async_trait]
+pub trait ExpiredSharePurger: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Delete shares of every organization that expired before `cutoff`,
+    /// with their short code index entries; returns how many were deleted.
+    /// Shares with a TTL leave no tombstone, others are retired like a
+    /// deleted share (see [`ShortCodeTombstone::for_deleted_share`]).
+    async fn purge_expired(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError>;
+}
+
+/// Hard deletion of shares and activities left in the recycle bin past the
+/// retention window (see [`crate::recycle_bin`])
+#[async_trait]
+pub trait DeletedItemPurger: Send + Sync {
+    /// Name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Delete shares and activities of every organization that went to the
+    /// recycle bin before `cutoff`; returns how many were deleted. Shares
+    /// leave a tombstone like any deleted share.
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError>;
+}
+
+/// Lightweight checks that a backend's tables or containers answer, for
+/// `GET /api/health` (see [`crate::health`])
+#[async_trait]
+pub trait StorageProbe: Send + Sync {
+    /// Backend name for the report
+    fn name(&self) -> &'static str;
+    
+    /// Tables or containers to probe, one check each
+    fn targets(&self) -> Vec<&'static str>;
+    
+    /// Read (and, where cheap, write) one target
+    async fn probe(&self, target: &str) -> Result<(), StorageError>;
+}
+
+/// Maintained count of one kind of entity in an organization (see [`crate::counters`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Counter {
+    pub value: u64,
+    /// Last full count; later changes were added to it
+    pub counted_at: DateTime<Utc>,
+}
+
+/// Per-organization counters, for totals backends can't count cheaply
+#[async_trait]
+pub trait CounterStorage: Send + Sync {
+    /// A counter; None if it was never set
+    async fn get(&self, organization_id: &str, name: &str) -> Result<Option<Counter>, StorageError>;
+    
+    /// Add `delta` to a counter, not going below zero; a counter that was
+    /// never set stays unset
+    async fn add(&self, organization_id: &str, name: &str, delta: i64) -> Result<(), StorageError>;
+    
+    /// Replace a counter with a full count
+    async fn set(&self, organization_id: &str, name: &str, counter: Counter) -> Result<(), StorageError>;
+}
+
+/// Size of one organization's partition in one table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionSample {
+    pub table: String,
+    pub organization_id: String,
+    pub entities: u64,
+    /// Approximate stored size
+    pub bytes: u64,
+}
+
+/// Per-organization partition sizes of a backend that partitions by
+/// organization, for the [`PartitionMonitor`](crate::partition_monitor::PartitionMonitor)
+#[async_trait]
+pub trait PartitionSampler: Send + Sync {
+    /// Backend name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Every organization partition of every table; scans the tables, so
+    /// call it rarely
+    async fn sample_partitions(&self) -> Result<Vec<PartitionSample>, StorageError>;
+    
+    /// Samples kept by [`Self::save_history`]; none where the backend keeps none
+    async fn load_history(&self) -> Result<Vec<PartitionHistory>, StorageError> {
+        Ok(Vec::new())
+    }
+    
+    /// Keep the samples of every partition across restarts, replacing those kept before
+    async fn save_history(&self, _history: &[PartitionHistory]) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+/// A document the backend's change feed reports as written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedDocument {
+    pub organization_id: String,
+    pub id: String,
+    /// Short code, for shares
+    pub short_code: Option<String>,
+    /// Second of the write (Unix time)
+    pub timestamp: i64,
+}
+
+/// Writes to shares and activities, whichever instance or tool made them,
+/// for the [`ChangeFeedProcessor`](crate::change_feed::ChangeFeedProcessor)
+///
+/// Reports the latest version of each document; deletions don't show.
+#[async_trait]
+pub trait ChangeFeed: Send + Sync {
+    /// Backend name for logs
+    fn name(&self) -> &'static str;
+    
+    /// Shares written at or after `since` (Unix seconds), oldest first
+    async fn shares_changed_since(&self, since: i64) -> Result<Vec<ChangedDocument>, StorageError>;
+    
+    /// Activities written at or after `since` (Unix seconds), oldest first
+    async fn activities_changed_since(&self, since: i64) -> Result<Vec<ChangedDocument>, StorageError>;
+    
+    /// Add a changed share's short code to the backend's index if it is missing;
+    /// returns whether an entry was written
+    async fn repair_short_code(&self, share: &ChangedDocument) -> Result<bool, StorageError>;
+}
+
+/// Whether an activity is on one of `layer_ids` and overlaps `year`: the
+/// condition of [`Filter::layers_and_year`] on the typed activity, for the
+/// public view path where serializing each activity would cost more than the read
+pub fn on_layers_in_year(activity: &Activity, layer_ids: &[String], year: Option<i32>) -> bool {
+    use chrono::Datelike;
+    layer_ids.contains(&activity.scope)
+        && year.is_none_or(|year| activity.start_date.year() <= year && activity.end_date.year() >= year)
+}
+
+/// Load every share of an organization, following continuation tokens
+pub async fn list_all_shares<S: ShareStorage + ?Sized>(storage: &S, organization_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+    list_all_shares_matching(storage, organization_id, None).await
+}
+
+/// Load every share of an organization matching a filter
+pub async fn list_all_shares_matching<S: ShareStorage + ?Sized>(storage: &S, organization_id: &str, filter: Option<Filter>) -> Result<Vec<ShareLink>, StorageError> {
+    let mut shares = Vec::new();
+    let mut continuation_token = None;
+    
+    loop {
+        let page = storage.list(organization_id, QueryOptions {
+            continuation_token,
+            filter: filter.clone(),
+            ..Default::default()
+        }).await?;
+        
+        shares.extend(page.items);
+        
+        match page.continuation_token {
+            Some(token) => continuation_token = Some(token),
+            None => break,
+        }
+    }
+    
+    Ok(shares)
+}
+
+/// Load every activity of an organization, following continuation tokens
+pub async fn list_all_activities<S: ActivityStorage + ?Sized>(storage: &S, organization_id: &str) -> Result<Vec<Activity>, StorageError> {
+    let mut activities = Vec::new();
+    let mut continuation_token = None;
+    
+    loop {
+        let page = storage.list(organization_id, QueryOptions {
+            continuation_token,
+            ..Default::default()
+        }).await?;
+    
+        activities.extend(page.items);
+    
+        match page.continuation_token {
+            Some(token) => continuation_token = Some(token),
+            None => break,
+        }
+    }
+    
+    Ok(activities)
+}
+
+/// Create imported rows one by one; one that already exists is replaced
+/// under [`ConflictStrategy::Overwrite`] and kept otherwise. `NewIds` rows
+/// are expected to carry fresh IDs, so the rare clash is kept too.
+async fn import_rows<T, C, CF, R, RF>(rows: Vec<T>, strategy: ConflictStrategy, create: C, replace: R) -> Result<ImportCount, StorageError>
+where
+    T: Clone,
+    C: Fn(T) -> CF,
+    CF: std::future::Future<Output = Result<T, StorageError>>,
+    R: Fn(T) -> RF,
+    RF: std::future::Future<Output = Result<T, StorageError>>,
+{
+    let mut count = ImportCount::default();
+    for row in rows {
+        match create(row.clone()).await {
+            Ok(_) => count.created += 1,
+            Err(StorageError::AlreadyExists(_)) if strategy == ConflictStrategy::Overwrite => match replace(row).await {
+                Ok(_) => count.overwritten += 1,
+                // Clashed with another row's unique key, e.g. a share's short code
+                Err(StorageError::NotFound(_)) => count.skipped += 1,
+                Err(e) => return Err(e),
+            },
+            Err(StorageError::AlreadyExists(_)) => count.skipped += 1,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(count)
+}
+
+/// Write imported layers (see [`crate::backup`])
+pub async fn import_layers<S: LayerStorage + ?Sized>(storage: &S, layers: Vec<Layer>, strategy: ConflictStrategy) -> Result<ImportCount, StorageError> {
+    import_rows(layers, strategy, |l| storage.create(l), |l| storage.update(l)).await
+}
+
+/// Write imported activities (see [`crate::backup`])
+pub async fn import_activities<S: ActivityStorage + ?Sized>(storage: &S, activities: Vec<Activity>, strategy: ConflictStrategy) -> Result<ImportCount, StorageError> {
+    import_rows(activities, strategy, |a| storage.create(a), |a| storage.update(a)).await
+}
+
+/// Write imported shares (see [`crate::backup`]); one whose short code is
+/// taken by another share is skipped
+pub async fn import_shares<S: ShareStorage + ?Sized>(storage: &S, shares: Vec<ShareLink>, strategy: ConflictStrategy) -> Result<ImportCount, StorageError> {
+    import_rows(shares, strategy, |s| storage.create(s), |s| storage.update(s)).await
+}
+
+/// Write imported activity types of one organization (see [`crate::backup`])
+pub async fn import_activity_types<S: ActivityTypeStorage + ?Sized>(storage: &S, organization_id: &str, types: Vec<ActivityTypeConfig>, strategy: ConflictStrategy) -> Result<ImportCount, StorageError> {
+    let stored: std::collections::HashSet<String> = storage.list(organization_id).await?.into_iter().map(|t| t.key).collect();
+    let mut count = ImportCount::default();
+    for config in types {
+        let exists = stored.contains(&config.key);
+        if exists && strategy != ConflictStrategy::Overwrite {
+            count.skipped += 1;
+            continue;
+        }
+        storage.upsert(config).await?;
+        if exists { count.overwritten += 1 } else { count.created += 1 }
+    }
+    Ok(count)
+}
+
+/// Write imported user settings of one organization (see [`crate::backup`])
+pub async fn import_user_settings<S: UserSettingsStorage + ?Sized>(storage: &S, organization_id: &str, settings: Vec<UserSettings>, strategy: ConflictStrategy) -> Result<ImportCount, StorageError> {
+    // `get` falls back to defaults, so only the list tells what is stored
+    let stored: std::collections::HashSet<String> = storage.list(organization_id).await?.into_iter().map(|s| s.user_id).collect();
+    let mut count = ImportCount::default();
+    for user_settings in settings {
+        let exists = stored.contains(&user_settings.user_id);
+        if exists && strategy != ConflictStrategy::Overwrite {
+            count.skipped += 1;
+            continue;
+        }
+        storage.upsert(user_settings).await?;
+        if exists { count.overwritten += 1 } else { count.created += 1 }
+    }
+    Ok(count)
+}
+
+/// Write an imported policy (see [`crate::backup`]); one that was never
+/// set counts as not stored
+pub async fn import_policy<S: PolicyStorage + ?Sized>(storage: &S, policy: OrganizationPolicy, strategy: ConflictStrategy) -> Result<ImportCount, StorageError> {
+    let exists = storage.get(&policy.organization_id).await?.updated_at.is_some();
+    let mut count = ImportCount::default();
+    if exists && strategy != ConflictStrategy::Overwrite {
+        count.skipped += 1;
+        return Ok(count);
+    }
+    storage.upsert(policy).await?;
+    if exists { count.overwritten += 1 } else { count.created += 1 }
+    Ok(count)
+}
+
+/// Page through rows already in memory the way the backends do: in key
+/// order, with the last key of a page as the token for the next one
+pub fn paginate<T>(mut rows: Vec<T>, key: impl Fn(&T) -> String, page_size: usize, continuation_token: Option<&str>) -> (Vec<T>, Option<String>) {
+    rows.sort_by_cached_key(|r| key(r));
+    
+    let start = continuation_token.map_or(0, |token| rows.partition_point(|r| key(r).as_str() <= token));
+    let more = rows.len() - start > page_size;
+    let page: Vec<T> = rows.into_iter().skip(start).take(page_size).collect();
+    let next = more.then(|| page.last().map(&key)).flatten();
+    (page, next)
+}
+
+/// Combined storage interface
+pub struct Storage {
+    pub shares: Arc<dyn ShareStorage>,
+    pub activities: Arc<dyn ActivityStorage>,
+    pub layers: Arc<dyn LayerStorage>,
+    pub activity_types: Arc<dyn ActivityTypeStorage>,
+    pub user_settings: Arc<dyn UserSettingsStorage>,
+    pub audit: Arc<dyn AuditStorage>,
+    pub policies: Arc<dyn PolicyStorage>,
+    /// Deletes expired shares when the backend has no native TTL
+    pub expired_shares: Option<Arc<dyn ExpiredSharePurger>>,
+    /// Empty the recycle bin after the retention window
+    pub deleted_items: Vec<Arc<dyn DeletedItemPurger>>,
+    /// Checks the backend for `GET /api/health`; None in memory
+    pub probe: Option<Arc<dyn StorageProbe>>,
+    /// Writes made through any instance; None when the backend has no change feed
+    pub change_feed: Option<Arc<dyn ChangeFeed>>,
+    /// Samples partition sizes; None when partitions don't degrade with size
+    pub partitions: Option<Arc<dyn PartitionSampler>>,
+    /// Cache short code lookups are read through, if any
+    pub share_cache: Option<Arc<dyn crate::share_cache::ShareCache>>,
+    /// Calls and latency of every backend, for `GET /api/metrics`
+    pub metrics: Arc<crate::storage_metrics::StorageMetrics>,
+}
+
+impl Storage {
+    /// Bundle the backends, each wrapped in a [`TracedStorage`](crate::traced_storage::TracedStorage);
+    /// shares and activities in the recycle bin are hidden by a [`RecycleBin`](crate::recycle_bin::RecycleBin)
+    pub fn new(
+        shares: Arc<dyn ShareStorage>,
+        activities: Arc<dyn ActivityStorage>,
+        layers: Arc<dyn LayerStorage>,
+        activity_types: Arc<dyn ActivityTypeStorage>,
+        user_settings: Arc<dyn UserSettingsStorage>,
+        audit: Arc<dyn AuditStorage>,
+        policies: Arc<dyn PolicyStorage>,
+    ) -> Self {
+        use crate::recycle_bin::RecycleBin;
+        use crate::traced_storage::TracedStorage;
+        
+        let metrics = Arc::new(crate::storage_metrics::StorageMetrics::new());
+        Self {
+            shares: Arc::new(RecycleBin::new(Arc::new(TracedStorage::new(shares, metrics.clone())))),
+            activities: Arc::new(RecycleBin::new(Arc::new(TracedStorage::new(activities, metrics.clone())))),
+            layers: Arc::new(TracedStorage::new(layers, metrics.clone())),
+            activity_types: Arc::new(TracedStorage::new(activity_types, metrics.clone())),
+            user_settings: Arc::new(TracedStorage::new(user_settings, metrics.clone())),
+            audit: Arc::new(TracedStorage::new(audit, metrics.clone())),
+            policies: Arc::new(TracedStorage::new(policies, metrics.clone())),
+            expired_shares: None,
+            deleted_items: Vec::new(),
+            probe: None,
+            change_feed: None,
+            partitions: None,
+            share_cache: None,
+            metrics,
+        }
+    }
+    
+    /// Label metrics and spans of these entities (`share`, `activity`, ...) with `backend`,
+    /// e.g. `table`; unlabeled entities count as `memory` (see [`crate::storage_metrics`])
+    pub fn with_backend(self, backend: &'static str, entities: &[&'static str]) -> Self {
+        for entity in entities {
+            self.metrics.set_backend(entity, backend);
+        }
+        self
+    }
+    
+    /// Clean up expired shares through `purger`
+    pub fn with_expired_share_purger(mut self, purger: Arc<dyn ExpiredSharePurger>) -> Self {
+        self.expired_shares = Some(purger);
+        self
+    }
+    
+    /// Empty the recycle bin through `purger`, too
+    pub fn with_deleted_item_purger(mut self, purger: Arc<dyn DeletedItemPurger>) -> Self {
+        self.deleted_items.push(purger);
+        self
+    }
+    
+    /// Check the backend through `probe` in `GET /api/health`
+    pub fn with_probe(mut self, probe: Arc<dyn StorageProbe>) -> Self {
+        self.probe = Some(probe);
+        self
+    }
+    
+    /// Keep caches of other instances current through `feed` (see [`crate::change_feed`])
+    pub fn with_change_feed(mut self, feed: Arc<dyn ChangeFeed>) -> Self {
+        self.change_feed = Some(feed);
+        self
+    }
+    
+    /// Watch partition sizes through `sampler` (see [`crate::partition_monitor`])
+    pub fn with_partition_sampler(mut self, sampler: Arc<dyn PartitionSampler>) -> Self {
+        self.partitions = Some(sampler);
+        self
+    }
+    
+    /// Report share totals from `counters` where the backend can't count
+    /// (see [`crate::counters`])
+    pub fn with_counters(mut self, counters: Arc<dyn CounterStorage>) -> Self {
+        self.shares = Arc::new(crate::counters::CountedShares::new(self.shares, counters));
+        self
+    }
+    
+    /// Queue every recorded audit entry on `exporter` too (see [`crate::audit_export`])
+    #[cfg(feature = "server")]
+    pub fn with_audit_export(mut self, exporter: Arc<crate::audit_export::AuditExporter>) -> Self {
+        self.audit = Arc::new(crate::audit_export::ExportingAuditStorage::new(self.audit, exporter));
+        self
+    }
+    
+    /// Note the cache `shares` reads short code lookups through, so the change feed can drop entries
+    pub fn with_share_cache(mut self, cache: Arc<dyn crate::share_cache::ShareCache>) -> Self {
+        self.share_cache = Some(cache);
+        self
+    }
+    
+    /// Every backend in memory, for development and tests
+    pub fn in_memory() -> Self {
+        use memory_storage::*;
+        
+        let shares = Arc::new(MemoryShareStorage::new());
+        let activities = Arc::new(MemoryActivityStorage::new());
+        Self::new(
+            shares.clone(),
+            activities.clone(),
+            Arc::new(MemoryLayerStorage::new()),
+            Arc::new(MemoryActivityTypeStorage::new()),
+            Arc::new(MemoryUserSettingsStorage::new()),
+            Arc::new(MemoryAuditStorage::new()),
+            Arc::new(MemoryPolicyStorage::new()),
+        )
+        .with_deleted_item_purger(shares)
+        .with_deleted_item_purger(activities)
+    }
+}
+
+// ============================================
+// In-Memory Implementation (for testing)
+// ============================================
+
+pub mod memory_storage {
+    use super::*;
+    use crate::clock::{Clock, SystemClock};
+    use std::collections::HashMap;
+    use tokio::sync::RwLock;
+    
+    /// Default page size when `QueryOptions::page_size` is unset
+    pub const DEFAULT_PAGE_SIZE: u32 = 100;
+    
+    /// Maximum page size (Table Storage's per-request limit)
+    pub const MAX_PAGE_SIZE: u32 = 1000;
+    
+    /// A stored share and when it was last written (TTL is counted from there, as in Cosmos DB)
+    struct StoredShare {
+        share: ShareLink,
+        written_at: DateTime<Utc>,
+    }
+    
+    impl StoredShare {
+        fn is_expired(&self, now: DateTime<Utc>) -> bool {
+            self.share.ttl.is_some_and(|ttl| now >= self.written_at + chrono::Duration::seconds(ttl))
+        }
+    }
+    
+    /// Shares and their short code index, behind one lock so they can't drift apart
+    #[derive(Default)]
+    struct ShareTables {
+        shares: HashMap<String, StoredShare>,
+        by_short_code: HashMap<String, String>, // short_code -> key
+        tombstones: HashMap<String, ShortCodeTombstone>,
+    }
+    
+    impl ShareTables {
+        /// Drop TTL-expired shares and their index entries (no tombstone, like a Cosmos DB TTL delete)
+        fn purge_expired(&mut self, now: DateTime<Utc>) {
+            let expired: Vec<String> = self.shares.iter()
+                .filter(|(_, s)| s.is_expired(now))
+                .map(|(k, _)| k.clone())
+                .collect();
+            for key in expired {
+                if let Some(stored) = self.shares.remove(&key) {
+                    self.by_short_code.remove(&stored.share.short_code);
+                }
+            }
+        }
+        
+        /// Fail if a short code is taken by another share or retired
+        fn ensure_short_code_free(&mut self, short_code: &str, key: &str, now: DateTime<Utc>) -> Result<(), StorageError> {
+            if self.by_short_code.get(short_code).is_some_and(|k| k != key) {
+                return Err(StorageError::AlreadyExists(short_code.to_string()));
+            }
+            match self.tombstones.get(short_code) {
+                Some(t) if t.is_active(now) => Err(StorageError::AlreadyExists(short_code.to_string())),
+                Some(_) => {
+                    self.tombstones.remove(short_code);
+                    Ok(())
+                }
+                None => Ok(()),
+            }
+        }
+        
+        /// Write over a stored share, moving its short code in the index
+        fn update(&mut self, share: ShareLink, now: DateTime<Utc>) -> Result<ShareLink, StorageError> {
+            let key = MemoryShareStorage::key(&share.organization_id, &share.id);
+            let old_short_code = match self.shares.get(&key) {
+                Some(stored) => stored.share.short_code.clone(),
+                None => return Err(StorageError::NotFound(share.id.clone())),
+            };
+            
+            // Keep the index in step with a changed short code
+            if old_short_code != share.short_code {
+                self.ensure_short_code_free(&share.short_code, &key, now)?;
+                self.by_short_code.remove(&old_short_code);
+                self.by_short_code.insert(share.short_code.clone(), key.clone());
+            }
+            
+            self.shares.insert(key, StoredShare { share: share.clone(), written_at: now });
+            Ok(share)
+        }
+        
+        fn live(&self, key: &str, now: DateTime<Utc>) -> Option<&ShareLink> {
+            self.shares.get(key).filter(|s| !s.is_expired(now)).map(|s| &s.share)
+        }
+    }
+    
+    /// In-memory share storage with the semantics of the Azure backends:
+    /// TTL expiry, row key ordered pages with continuation tokens, filters
+    /// (see [`Filter`]) and a consistent short code index
+    pub struct MemoryShareStorage {
+        tables: RwLock<ShareTables>,
+        clock: Arc<dyn Clock>,
+    }
+    
+    impl Default for MemoryShareStorage {
+        fn default() -> Self {
+            Self::with_clock(Arc::new(SystemClock))
+        }
+    }
+    
+    impl MemoryShareStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        
+        /// Expire shares and tombstones by another time source (tests)
+        pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+            Self { tables: RwLock::new(ShareTables::default()), clock }
+        }
+        
+        fn key(organization_id: &str, share_id: &str) -> String {
+            format!("{}:{}", organization_id, share_id)
+        }
+    }
+    
+    #[async_trait]
+    impl ShareStorage for MemoryShareStorage {
+        async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+            let key = Self::key(&share.organization_id, &share.id);
+            let now = self.clock.now();
+            let mut tables = self.tables.write().await;
+            tables.purge_expired(now);
+            
+            if tables.shares.contains_key(&key) {
+                return Err(StorageError::AlreadyExists(share.id.clone()));
+            }
+            tables.ensure_short_code_free(&share.short_code, &key, now)?;
+            
+            tables.by_short_code.insert(share.short_code.clone(), key.clone());
+            tables.shares.insert(key, StoredShare { share: share.clone(), written_at: now });
+            Ok(share)
+        }
+        
+        async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+            let tables = self.tables.read().await;
+            tables.live(&Self::key(organization_id, share_id), self.clock.now())
+                .cloned()
+                .ok_or_else(|| StorageError::NotFound(share_id.to_string()))
+        }
+        
+        async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+            let tables = self.tables.read().await;
+            tables.by_short_code.get(short_code)
+                .and_then(|key| tables.live(key, self.clock.now()))
+                .cloned()
+                .ok_or_else(|| StorageError::NotFound(short_code.to_string()))
+        }
+        
+        async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+            let now = self.clock.now();
+            let mut tables = self.tables.write().await;
+            tables.purge_expired(now);
+            tables.update(share, now)
+        }
+        
+        async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+            let now = self.clock.now();
+            let mut tables = self.tables.write().await;
+            tables.purge_expired(now);
+            
+            // Checked under the write lock, so no other write slips in between
+            let current = tables.live(&Self::key(&share.organization_id, &share.id), now)
+                .ok_or_else(|| StorageError::NotFound(share.id.clone()))?;
+            if share_etag(current) != etag {
+                return Ok(None);
+            }
+            tables.update(share, now).map(Some)
+        }
+        
+        async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+            let now = self.clock.now();
+            let mut tables = self.tables.write().await;
+            
+            if let Some(stored) = tables.shares.remove(&Self::key(organization_id, share_id)) {
+                let share = stored.share;
+                tables.by_short_code.remove(&share.short_code);
+                
+                if let Some(tombstone) = ShortCodeTombstone::for_deleted_share(&share, now) {
+                    tables.tombstones.insert(share.short_code.clone(), tombstone);
+                }
+            }
+            
+            Ok(())
+        }
+        
+        async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+            let tables = self.tables.read().await;
+            Ok(tables.tombstones.get(short_code)
+                .filter(|t| t.is_active(self.clock.now()))
+                .cloned())
+        }
+        
+        async fn list(
+            &self,
+            organization_id: &str,
+            options: QueryOptions,
+        ) -> Result<QueryResult<ShareLink>, StorageError> {
+            let filter = options.filter;
+            let page_size = options.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+            let now = self.clock.now();
+            let tables = self.tables.read().await;
+            
+            // Partition scan in row key order, like Table Storage
+            let mut items: Vec<&ShareLink> = tables.shares.values()
+                .filter(|s| s.share.organization_id == organization_id && !s.is_expired(now))
+                .map(|s| &s.share)
+                .collect();
+            items.sort_by(|a, b| a.id.cmp(&b.id));
+            
+            let mut matching = Vec::with_capacity(items.len());
+            for share in items {
+                let keep = match filter {
+                    Some(ref f) => f.matches_item(share)?,
+                    None => true,
+                };
+                if keep {
+                    matching.push(share);
+                }
+            }
+            let total = matching.len() as u64;
+            
+            // The token is the last row key of the previous page
+            let start = match options.continuation_token {
+                Some(ref token) => matching.partition_point(|s| s.id.as_str() <= token.as_str()),
+                None => 0,
+            };
+            let page: Vec<ShareLink> = matching[start..].iter().take(page_size).map(|s| (*s).clone()).collect();
+            let continuation_token = (start + page.len() < matching.len())
+                .then(|| page.last().map(|s| s.id.clone()))
+                .flatten();
+            
+            Ok(QueryResult {
+                items: page,
+                continuation_token,
+                total_count: Some(total),
+            })
+        }
+        
+        async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+            let now = self.clock.now();
+            let mut tables = self.tables.write().await;
+            
+            // Like any Cosmos DB write, this restarts the TTL
+            if let Some(stored) = tables.shares.get_mut(&Self::key(organization_id, share_id)).filter(|s| !s.is_expired(now)) {
+                stored.share.stats.view_count += 1;
+                stored.share.stats.last_accessed_at = Some(now);
+                stored.written_at = now;
+            }
+            
+            Ok(())
+        }
+    }
+    
+    #[async_trait]
+    impl ExpiredSharePurger for MemoryShareStorage {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+        
+        async fn purge_expired(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+            let now = self.clock.now();
+            let mut tables = self.tables.write().await;
+            let expired: Vec<String> = tables.shares.iter()
+                .filter(|(_, s)| s.share.expires_at < cutoff)
+                .map(|(key, _)| key.clone())
+                .collect();
+            
+            for key in &expired {
+                let Some(stored) = tables.shares.remove(key) else { continue };
+                let share = stored.share;
+                tables.by_short_code.remove(&share.short_code);
+                if share.ttl.is_none() {
+                    if let Some(tombstone) = ShortCodeTombstone::for_deleted_share(&share, now) {
+                        tables.tombstones.insert(share.short_code.clone(), tombstone);
+                    }
+                }
+            }
+            Ok(expired.len() as u64)
+        }
+    }
+    
+    #[async_trait]
+    impl DeletedItemPurger for MemoryShareStorage {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+        
+        async fn purge_deleted(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+            let now = self.clock.now();
+            let mut tables = self.tables.write().await;
+            let deleted: Vec<String> = tables.shares.iter()
+                .filter(|(_, s)| s.share.deleted_at.is_some_and(|at| at < cutoff))
+                .map(|(key, _)| key.clone())
+                .collect();
+            
+            for key in &deleted {
+                let Some(stored) = tables.shares.remove(key) else { continue };
+                let share = stored.share;
+                tables.by_short_code.remove(&share.short_code);
+                if let Some(tombstone) = ShortCodeTombstone::for_deleted_share(&share, now) {
+                    tables.tombstones.insert(share.short_code.clone(), tombstone);
+                }
+            }
+            Ok(deleted.len() as u64)
+        }
+    }
+    
+    /// In-memory audit log for testing
+    #[derive(Default)]
+    pub struct MemoryAuditStorage {
+        entries: RwLock<Vec<AuditEntry>>,
+    }
+    
+    impl MemoryAuditStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+    
+    #[async_trait]
+    impl AuditStorage for MemoryAuditStorage {
+        async fn record(&self, entry: AuditEntry) -> Result<(), StorageError> {
+            self.entries.write().await.push(entry);
+            Ok(())
+        }
+        
+        async fn list(
+            &self,
+            organization_id: &str,
+            _options: QueryOptions,
+        ) -> Result<QueryResult<AuditEntry>, StorageError> {
+            let entries = self.entries.read().await;
+            let items: Vec<AuditEntry> = entries.iter()
+                .filter(|e| e.organization_id == organization_id)
+                .cloned()
+                .collect();
+            let total = items.len() as u64;
+            
+            Ok(QueryResult {
+                items,
+                continuation_token: None,
+                total_count: Some(total),
+            })
+        }
+        
+        async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+            let mut entries = self.entries.write().await;
+            let before = entries.len();
+            entries.retain(|e| e.organization_id != organization_id);
+            Ok((before - entries.len()) as u64)
+        }
+    }
+    
+    /// Rows keyed by organization and row key, sorted by row key when listed
+    struct MemoryTable<T> {
+        rows: RwLock<std::collections::BTreeMap<(String, String), T>>,
+    }
+    
+    impl<T: Clone> Default for MemoryTable<T> {
+        fn default() -> Self {
+            Self { rows: RwLock::new(std::collections::BTreeMap::new()) }
+        }
+    }
+    
+    impl<T: Clone> MemoryTable<T> {
+        fn key(organization_id: &str, row_key: &str) -> (String, String) {
+            (organization_id.to_string(), row_key.to_string())
+        }
+        
+        async fn insert(&self, organization_id: &str, row_key: &str, row: T) -> Result<T, StorageError> {
+            let mut rows = self.rows.write().await;
+            let key = Self::key(organization_id, row_key);
+            if rows.contains_key(&key) {
+                return Err(StorageError::AlreadyExists(row_key.to_string()));
+            }
+            rows.insert(key, row.clone());
+            Ok(row)
+        }
+        
+        async fn replace(&self, organization_id: &str, row_key: &str, row: T) -> Result<T, StorageError> {
+            let mut rows = self.rows.write().await;
+            match rows.get_mut(&Self::key(organization_id, row_key)) {
+                Some(existing) => {
+                    *existing = row.clone();
+                    Ok(row)
+                }
+                None => Err(StorageError::NotFound(row_key.to_string())),
+            }
+        }
+        
+        /// Insert rows at once; nothing is written if any of them exists
+        async fn insert_all(&self, rows: Vec<(String, String, T)>) -> Result<Vec<T>, StorageError> {
+            let mut table = self.rows.write().await;
+            let mut keys = std::collections::HashSet::new();
+            if let Some((_, row_key, _)) = rows.iter().find(|(org, row_key, _)| {
+                let key = Self::key(org, row_key);
+                table.contains_key(&key) || !keys.insert(key)
+            }) {
+                return Err(StorageError::AlreadyExists(row_key.clone()));
+            }
+            Ok(rows.into_iter().map(|(org, row_key, row)| {
+                table.insert((org, row_key), row.clone());
+                row
+            }).collect())
+        }
+        
+        /// Replace rows at once; nothing is written unless all of them exist
+        async fn replace_all(&self, rows: Vec<(String, String, T)>) -> Result<Vec<T>, StorageError> {
+            let mut table = self.rows.write().await;
+            if let Some((_, row_key, _)) = rows.iter().find(|(org, row_key, _)| !table.contains_key(&Self::key(org, row_key))) {
+                return Err(StorageError::NotFound(row_key.clone()));
+            }
+            Ok(rows.into_iter().map(|(org, row_key, row)| {
+                table.insert((org, row_key), row.clone());
+                row
+            }).collect())
+        }
+        
+        /// Insert, replace and remove rows of one organization at once;
+        /// nothing is written unless every insert is new and every other
+        /// row exists
+        async fn commit(
+            &self,
+            organization_id: &str,
+            insert: Vec<(String, T)>,
+            replace: Vec<(String, T)>,
+            remove: &[String],
+        ) -> Result<(), StorageError> {
+            let mut table = self.rows.write().await;
+            let mut keys = std::collections::HashSet::new();
+            if let Some((row_key, _)) = insert.iter().find(|(row_key, _)| {
+                let key = Self::key(organization_id, row_key);
+                table.contains_key(&key) || !keys.insert(key)
+            }) {
+                return Err(StorageError::AlreadyExists(row_key.clone()));
+            }
+            if let Some(row_key) = replace.iter().map(|(row_key, _)| row_key).chain(remove)
+                .find(|row_key| !table.contains_key(&Self::key(organization_id, row_key)))
+            {
+                return Err(StorageError::NotFound(row_key.clone()));
+            }
+            for (row_key, row) in insert.into_iter().chain(replace) {
+                table.insert(Self::key(organization_id, &row_key), row);
+            }
+            for row_key in remove {
+                table.remove(&Self::key(organization_id, row_key));
+            }
+            Ok(())
+        }
+        
+        async fn upsert(&self, organization_id: &str, row_key: &str, row: T) -> T {
+            self.rows.write().await.insert(Self::key(organization_id, row_key), row.clone());
+            row
+        }
+        
+        async fn get(&self, organization_id: &str, row_key: &str) -> Option<T> {
+            self.rows.read().await.get(&Self::key(organization_id, row_key)).cloned()
+        }
+        
+        async fn remove(&self, organization_id: &str, row_key: &str) -> Result<(), StorageError> {
+            self.rows.write().await.remove(&Self::key(organization_id, row_key))
+                .map(|_| ())
+                .ok_or_else(|| StorageError::NotFound(row_key.to_string()))
+        }
+        
+        async fn list(&self, organization_id: &str) -> Vec<T> {
+            self.rows.read().await.iter()
+                .filter(|((org, _), _)| org == organization_id)
+                .map(|(_, row)| row.clone())
+                .collect()
+        }
+        
+        /// Remove the rows of every organization matching `predicate`; returns how many
+        async fn remove_where(&self, predicate: impl Fn(&T) -> bool) -> u64 {
+            let mut rows = self.rows.write().await;
+            let before = rows.len();
+            rows.retain(|_, row| !predicate(row));
+            (before - rows.len()) as u64
+        }
+    }
+    
+    /// In-memory activity storage
+    #[derive(Default)]
+    pub struct MemoryActivityStorage {
+        table: MemoryTable<Activity>,
+    }
+    
+    impl MemoryActivityStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+    
+    #[async_trait]
+    impl ActivityStorage for MemoryActivityStorage {
+        async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+            self.table.insert(&activity.organization_id.clone(), &activity.id.clone(), activity).await
+        }
+        
+        async fn create_many(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+            self.table.insert_all(activities.into_iter()
+                .map(|a| (a.organization_id.clone(), a.id.clone(), a))
+                .collect()).await
+        }
+        
+        async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+            self.table.get(organization_id, activity_id).await
+                .ok_or_else(|| StorageError::NotFound(activity_id.to_string()))
+        }
+        
+        async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+            self.table.replace(&activity.organization_id.clone(), &activity.id.clone(), activity).await
+        }
+        
+        async fn update_batch(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+            self.table.replace_all(activities.into_iter()
+                .map(|a| (a.organization_id.clone(), a.id.clone(), a))
+                .collect()).await
+        }
+        
+        async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+            self.table.remove(organization_id, activity_id).await
+        }
+        
+        async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+            changes.check_organization(organization_id)?;
+            changes.check_etags(self, organization_id).await?;
+            let keyed = |activities: Vec<Activity>| activities.into_iter().map(|a| (a.id.clone(), a)).collect();
+            self.table.commit(organization_id, keyed(changes.create), keyed(changes.update), &changes.delete).await
+        }
+        
+        async fn list(
+            &self,
+            organization_id: &str,
+            _options: QueryOptions,
+        ) -> Result<QueryResult<Activity>, StorageError> {
+            let items = self.table.list(organization_id).await;
+            let total = items.len() as u64;
+            Ok(QueryResult { items, continuation_token: None, total_count: Some(total) })
+        }
+        
+        /// `year` keeps activities overlapping that calendar year
+        async fn list_by_layers(
+            &self,
+            organization_id: &str,
+            layer_ids: &[String],
+            year: Option<i32>,
+        ) -> Result<Vec<Activity>, StorageError> {
+            Ok(self.table.list(organization_id).await.into_iter()
+                .filter(|a| on_layers_in_year(a, layer_ids, year))
+                .collect())
+        }
+    }
+    
+    #[async_trait]
+    impl DeletedItemPurger for MemoryActivityStorage {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+        
+        async fn purge_deleted(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+            Ok(self.table.remove_where(|a| a.deleted_at.is_some_and(|at| at < cutoff)).await)
+        }
+    }
+    
+    /// In-memory layer storage
+    #[derive(Default)]
+    pub struct MemoryLayerStorage {
+        table: MemoryTable<Layer>,
+    }
+    
+    impl MemoryLayerStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+    
+    #[async_trait]
+    impl LayerStorage for MemoryLayerStorage {
+        async fn create(&self, layer: Layer) -> Result<Layer, StorageError> {
+            self.table.insert(&layer.organization_id.clone(), &layer.id.clone(), layer).await
+        }
+        
+        async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError> {
+            self.table.get(organization_id, layer_id).await
+                .ok_or_else(|| StorageError::NotFound(layer_id.to_string()))
+        }
+        
+        async fn update(&self, layer: Layer) -> Result<Layer, StorageError> {
+            self.table.replace(&layer.organization_id.clone(), &layer.id.clone(), layer).await
+        }
+        
+        async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError> {
+            self.table.remove(organization_id, layer_id).await
+        }
+        
+        async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError> {
+            let mut layers = self.table.list(organization_id).await;
+            layers.sort_by_key(|l| l.ring_index);
+            Ok(layers)
+        }
+    }
+    
+    /// In-memory activity type storage
+    #[derive(Default)]
+    pub struct MemoryActivityTypeStorage {
+        table: MemoryTable<ActivityTypeConfig>,
+    }
+    
+    impl MemoryActivityTypeStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+    
+    #[async_trait]
+    impl ActivityTypeStorage for MemoryActivityTypeStorage {
+        async fn upsert(&self, config: ActivityTypeConfig) -> Result<ActivityTypeConfig, StorageError> {
+            Ok(self.table.upsert(&config.organization_id.clone(), &config.key.clone(), config).await)
+        }
+        
+        async fn get(&self, organization_id: &str, key: &str) -> Result<ActivityTypeConfig, StorageError> {
+            self.table.get(organization_id, key).await
+                .ok_or_else(|| StorageError::NotFound(key.to_string()))
+        }
+        
+        async fn delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+            ensure_deletable(&self.get(organization_id, key).await?)?;
+            self.table.remove(organization_id, key).await
+        }
+        
+        async fn force_delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+            self.table.remove(organization_id, key).await
+        }
+        
+        async fn list(&self, organization_id: &str) -> Result<Vec<ActivityTypeConfig>, StorageError> {
+            let mut types = self.table.list(organization_id).await;
+            types.sort_by_key(|t| t.sort_order);
+            Ok(types)
+        }
+    }
+    
+    /// In-memory user settings storage
+    #[derive(Default)]
+    pub struct MemoryUserSettingsStorage {
+        table: MemoryTable<UserSettings>,
+    }
+    
+    impl MemoryUserSettingsStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+    
+    #[async_trait]
+    impl UserSettingsStorage for MemoryUserSettingsStorage {
+        async fn get(&self, organization_id: &str, user_id: &str) -> Result<UserSettings, StorageError> {
+            Ok(self.table.get(organization_id, user_id).await
+                .unwrap_or_else(|| UserSettings::new(user_id.to_string(), organization_id.to_string())))
+        }
+        
+        async fn upsert(&self, settings: UserSettings) -> Result<UserSettings, StorageError> {
+            Ok(self.table.upsert(&settings.organization_id.clone(), &settings.user_id.clone(), settings).await)
+        }
+        
+        async fn delete(&self, organization_id: &str, user_id: &str) -> Result<(), StorageError> {
+            self.table.remove(organization_id, user_id).await
+        }
+        
+        async fn list(&self, organization_id: &str) -> Result<Vec<UserSettings>, StorageError> {
+            Ok(self.table.list(organization_id).await)
+        }
+    }
+    
+    /// In-memory counters
+    #[derive(Default)]
+    pub struct MemoryCounterStorage {
+        counters: RwLock<HashMap<(String, String), Counter>>,
+    }
+    
+    impl MemoryCounterStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+    
+    #[async_trait]
+    impl CounterStorage for MemoryCounterStorage {
+        async fn get(&self, organization_id: &str, name: &str) -> Result<Option<Counter>, StorageError> {
+            Ok(self.counters.read().await.get(&(organization_id.to_string(), name.to_string())).copied())
+        }
+        
+        async fn add(&self, organization_id: &str, name: &str, delta: i64) -> Result<(), StorageError> {
+            if let Some(counter) = self.counters.write().await.get_mut(&(organization_id.to_string(), name.to_string())) {
+                counter.value = counter.value.saturating_add_signed(delta);
+            }
+            Ok(())
+        }
+        
+        async fn set(&self, organization_id: &str, name: &str, counter: Counter) -> Result<(), StorageError> {
+            self.counters.write().await.insert((organization_id.to_string(), name.to_string()), counter);
+            Ok(())
+        }
+    }
+    
+    /// Row key of the single policy row per organization
+    const POLICY_ROW_KEY: &str = "policy";
+    
+    /// In-memory organization policy storage
+    #[derive(Default)]
+    pub struct MemoryPolicyStorage {
+        table: MemoryTable<OrganizationPolicy>,
+    }
+    
+    impl MemoryPolicyStorage {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+    
+    #[async_trait]
+    impl PolicyStorage for MemoryPolicyStorage {
+        async fn get(&self, organization_id: &str) -> Result<OrganizationPolicy, StorageError> {
+            Ok(self.table.get(organization_id, POLICY_ROW_KEY).await
+                .unwrap_or_else(|| OrganizationPolicy::new(organization_id)))
+        }
+        
+        async fn upsert(&self, policy: OrganizationPolicy) -> Result<OrganizationPolicy, StorageError> {
+            Ok(self.table.upsert(&policy.organization_id.clone(), POLICY_ROW_KEY, policy).await)
+        }
+        
+        async fn delete(&self, organization_id: &str) -> Result<(), StorageError> {
+            self.table.remove(organization_id, POLICY_ROW_KEY).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::memory_storage::{MemoryActivityStorage, MemoryActivityTypeStorage, MemoryShareStorage};
+    use crate::clock::ManualClock;
+    
+    fn share(id: &str, short_code: &str, visibility: &str) -> ShareLink {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "shareKey": "k".repeat(64), "shortCode": short_code,
+            "visibility": visibility, "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2099-01-01T00:00:00Z",
+            "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_list_pages_and_filters() {
+        let storage = MemoryShareStorage::new();
+        for i in 0..5 {
+            let visibility = if i % 2 == 0 { "public" } else { "users" };
+            storage.create(share(&format!("s-{}", i), &format!("Code000{}", i), visibility)).await.unwrap();
+        }
+        
+        let options = |token: Option<String>| QueryOptions {
+            page_size: Some(2),
+            continuation_token: token,
+            filter: FilterBuilder::new().eq(FilterField::Visibility, "public").ne(FilterField::IsActive, false).build(),
+        };
+        let first = storage.list("org-1", options(None)).await.unwrap();
+        assert_eq!(first.items.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ["s-0", "s-2"]);
+        assert_eq!(first.total_count, Some(3));
+        
+        let second = storage.list("org-1", options(first.continuation_token)).await.unwrap();
+        assert_eq!(second.items.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ["s-4"]);
+        assert_eq!(second.continuation_token, None);
+        
+    }
+    
+    #[test]
+    fn test_filter_rendering() {
+        let filter = FilterBuilder::new()
+            .eq(FilterField::Action, "it's")
+            .ne(FilterField::IsActive, false)
+            .one_of(FilterField::Scope, ["l-1", "l-2"])
+            .build()
+            .unwrap();
+        assert_eq!(
+            filter.to_odata(&[(FilterField::Action, "action"), (FilterField::IsActive, "is_active"), (FilterField::Scope, "scope")]).as_deref(),
+            Some("action eq 'it''s' and is_active ne false and (scope eq 'l-1' or scope eq 'l-2')"),
+        );
+        assert_eq!(filter.to_odata(&[(FilterField::IsActive, "is_active")]).as_deref(), Some("is_active ne false"));
+        assert_eq!(filter.to_odata(&[]), None);
+        
+        let (conditions, parameters) = filter.to_sql("c", "p");
+        assert_eq!(conditions, ["c.action = @p0", "(NOT IS_DEFINED(c.isActive) OR c.isActive != @p1)", "ARRAY_CONTAINS(@p2, c.scope)"]);
+        assert_eq!(parameters[0], ("@p0".to_string(), serde_json::json!("it's")));
+        
+        assert!(filter.matches(&serde_json::json!({ "action": "it's", "scope": "l-2" })));
+        assert!(!filter.matches(&serde_json::json!({ "action": "it's", "isActive": false, "scope": "l-2" })));
+        assert!(!filter.matches(&serde_json::json!({ "action": "it's", "scope": "l-3" })));
+        assert!(FilterBuilder::new().build().is_none());
+        
+        // Year bounds compare with stored dates the way their years do
+        let in_2026 = Filter::layers_and_year(&["l-1".to_string()], Some(2026));
+        for (start, end, overlaps) in [
+            ("2025-06-01T00:00:00Z", "2025-12-31T23:59:59.999Z", false),
+            ("2025-06-01T00:00:00Z", "2026-01-01T00:00:00Z", true),
+            ("2026-12-31T23:59:59Z", "2027-01-02T00:00:00Z", true),
+            ("2027-01-01T00:00:00Z", "2027-01-02T00:00:00Z", false),
+        ] {
+            let activity = serde_json::json!({ "scope": "l-1", "startDate": start, "endDate": end });
+            assert_eq!(in_2026.matches(&activity), overlaps, "{} - {}", start, end);
+            let typed: Activity = serde_json::from_value(serde_json::json!({
+                "id": "a-1", "title": "t", "startDate": start, "endDate": end, "type": "meeting",
+                "color": "#000000", "highlightColor": "#000000", "scope": "l-1", "scopeId": "l-1", "organizationId": "org-1",
+            })).unwrap();
+            assert_eq!(on_layers_in_year(&typed, &["l-1".to_string()], Some(2026)), overlaps, "{} - {}", start, end);
+        }
+        assert!(!Filter::layers_and_year(&[], None).matches(&serde_json::json!({ "scope": "l-1" })));
+    }
+    
+    #[tokio::test]
+    async fn test_short_code_index_and_ttl() {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let storage = MemoryShareStorage::with_clock(clock.clone());
+        storage.create(share("s-1", "OldCode1", "public")).await.unwrap();
+        storage.create(share("s-2", "Taken002", "public")).await.unwrap();
+        
+        let mut moved = share("s-1", "NewCode1", "public");
+        storage.update(moved.clone()).await.unwrap();
+        assert!(storage.get_by_short_code("OldCode1").await.is_err());
+        assert_eq!(storage.get_by_short_code("NewCode1").await.unwrap().id, "s-1");
+        
+        moved.short_code = "Taken002".to_string();
+        assert!(matches!(storage.update(moved).await, Err(StorageError::AlreadyExists(_))));
+        
+        // TTL counts from the last write
+        let mut expiring = share("s-3", "Expire03", "public");
+        expiring.ttl = Some(60);
+        storage.create(expiring).await.unwrap();
+        clock.advance(chrono::Duration::seconds(45));
+        storage.increment_views("org-1", "s-3").await.unwrap();
+        clock.advance(chrono::Duration::seconds(45));
+        assert_eq!(storage.get("org-1", "s-3").await.unwrap().stats.view_count, 1);
+        
+        clock.advance(chrono::Duration::seconds(15));
+        assert!(storage.get("org-1", "s-3").await.is_err());
+        assert!(storage.get_by_short_code("Expire03").await.is_err());
+        assert_eq!(storage.list("org-1", QueryOptions::default()).await.unwrap().items.len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_system_activity_types_are_kept() {
+        let storage = MemoryActivityTypeStorage::new();
+        let config = |key: &str, is_system: bool| -> ActivityTypeConfig {
+            serde_json::from_value(serde_json::json!({
+                "key": key, "label": key, "icon": "event", "color": "#000000", "highlightColor": "#ffffff",
+                "organizationId": "org-1", "isSystem": is_system,
+            })).unwrap()
+        };
+        storage.upsert(config("meeting", true)).await.unwrap();
+        storage.upsert(config("offsite", false)).await.unwrap();
+        
+        assert!(matches!(storage.delete("org-1", "meeting").await, Err(StorageError::Validation(_))));
+        storage.delete("org-1", "offsite").await.unwrap();
+        storage.force_delete("org-1", "meeting").await.unwrap();
+        assert!(storage.list("org-1").await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_list_by_layers_year() {
+        let storage = MemoryActivityStorage::new();
+        let activity = |id: &str, start: &str, end: &str, layer: &str| -> Activity {
+            serde_json::from_value(serde_json::json!({
+                "id": id, "title": id, "startDate": start, "endDate": end, "type": "event",
+                "color": "#000000", "highlightColor": "#000000", "scope": layer, "scopeId": layer,
+                "organizationId": "org-1",
+            })).unwrap()
+        };
+        storage.create(activity("a-1", "2024-12-20T00:00:00Z", "2025-01-05T00:00:00Z", "layer-1")).await.unwrap();
+        storage.create(activity("a-2", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z", "layer-1")).await.unwrap();
+        storage.create(activity("a-3", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z", "layer-2")).await.unwrap();
+        storage.create(activity("a-4", "2026-01-01T00:00:00Z", "2026-01-02T00:00:00Z", "layer-1")).await.unwrap();
+        
+        let ids = |activities: Vec<Activity>| {
+            let mut ids: Vec<String> = activities.into_iter().map(|a| a.id).collect();
+            ids.sort();
+            ids
+        };
+        let layers = ["layer-1".to_string()];
+        // Activities spanning New Year count for both years
+        assert_eq!(ids(storage.list_by_layers("org-1", &layers, Some(2024)).await.unwrap()), ["a-1"]);
+        assert_eq!(ids(storage.list_by_layers("org-1", &layers, Some(2025)).await.unwrap()), ["a-1", "a-2"]);
+        assert_eq!(ids(storage.list_by_layers("org-1", &layers, None).await.unwrap()), ["a-1", "a-2", "a-4"]);
+        assert!(storage.list_by_layers("org-2", &layers, None).await.unwrap().is_empty());
+        
+        // Batches are written entirely or not at all
+        let mut moved = storage.get("org-1", "a-2").await.unwrap();
+        moved.scope = "layer-2".to_string();
+        let missing = activity("a-9", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z", "layer-2");
+        assert!(matches!(storage.update_batch(vec![moved.clone(), missing]).await, Err(StorageError::NotFound(_))));
+        assert_eq!(storage.get("org-1", "a-2").await.unwrap().scope, "layer-1");
+        storage.update_batch(vec![moved]).await.unwrap();
+        assert_eq!(storage.get("org-1", "a-2").await.unwrap().scope, "layer-2");
+        
+        // So are bulk creates
+        let new = activity("a-5", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z", "layer-1");
+        let existing = activity("a-1", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z", "layer-1");
+        assert!(matches!(storage.create_many(vec![new.clone(), existing]).await, Err(StorageError::AlreadyExists(_))));
+        assert!(storage.get("org-1", "a-5").await.is_err());
+        assert_eq!(storage.create_many(vec![new]).await.unwrap().len(), 1);
+        assert!(storage.get("org-1", "a-5").await.is_ok());
+        
+        // And mixed changes
+        let mut renamed = storage.get("org-1", "a-5").await.unwrap();
+        renamed.title = "Renamed".to_string();
+        let changes = ActivityChanges {
+            create: vec![activity("a-6", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z", "layer-1")],
+            update: vec![renamed],
+            delete: vec!["a-1".to_string(), "a-9".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(storage.apply_changes("org-1", changes.clone()).await, Err(StorageError::NotFound(_))));
+        assert!(storage.get("org-1", "a-6").await.is_err());
+        assert_eq!(storage.get("org-1", "a-5").await.unwrap().title, "a-5");
+        assert!(storage.get("org-1", "a-1").await.is_ok());
+        storage.apply_changes("org-1", ActivityChanges { delete: vec!["a-1".to_string()], ..changes }).await.unwrap();
+        assert_eq!(storage.get("org-1", "a-5").await.unwrap().title, "Renamed");
+        assert!(storage.get("org-1", "a-6").await.is_ok());
+        assert!(storage.get("org-1", "a-1").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_import_conflicts() {
+        let storage = MemoryShareStorage::new();
+        storage.create(share("s-1", "Stored01", "public")).await.unwrap();
+        storage.create(share("s-2", "Stored02", "public")).await.unwrap();
+        
+        let archive = || vec![share("s-1", "Stored01", "users"), share("s-3", "Stored02", "users"), share("s-4", "Fresh004", "users")];
+        let skipped = import_shares(&storage, archive(), ConflictStrategy::Skip).await.unwrap();
+        assert_eq!(skipped, ImportCount { created: 1, overwritten: 0, skipped: 2 });
+        assert_eq!(storage.get("org-1", "s-1").await.unwrap().visibility, ShareVisibility::Public);
+        
+        // s-3 clashes with s-2's short code, which overwriting can't resolve
+        let overwritten = import_shares(&storage, archive(), ConflictStrategy::Overwrite).await.unwrap();
+        assert_eq!(overwritten, ImportCount { created: 0, overwritten: 2, skipped: 1 });
+        assert_eq!(storage.get("org-1", "s-1").await.unwrap().visibility, ShareVisibility::Users);
+        assert_eq!(storage.get("org-1", "s-2").await.unwrap().visibility, ShareVisibility::Public);
+    }
+}
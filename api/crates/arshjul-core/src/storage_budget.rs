@@ -0,0 +1,105 @@
+//! # Storage budget
+//!
+//! A share view fans out into several storage calls: the short code lookup,
+//! the organization policy, the view counters, the activities, and a policy
+//! and activity read for every federation partner. On a slow or throttled
+//! backend they add up until the whole request times out.
+//!
+//! [`StorageBudget`] caps the calls and time of one request. The lookup and
+//! the policy are always read; later calls go through
+//! [`RequestBudget::call`], which skips them once the budget is spent and
+//! cuts them off at its deadline. Views degrade in this order:
+//!
+//! 1. View counting (`increment_views`, daily traffic) is skipped unless the
+//!    activity reads still fit after it
+//! 2. Partners that no longer fit are left out
+//! 3. Activities that can't be read in time are left out
+//!
+//! Responses missing activities say so with `partial: true`; uncounted views
+//! are only logged.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+/// Default storage calls per request
+pub const DEFAULT_MAX_CALLS: u32 = 12;
+
+/// Default time per request before optional calls are cut off
+pub const DEFAULT_MAX_DURATION_MS: u64 = 3000;
+
+/// Storage calls and time one request may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageBudget {
+    pub max_calls: u32,
+    pub max_duration: Duration,
+}
+
+impl Default for StorageBudget {
+    fn default() -> Self {
+        Self {
+            max_calls: DEFAULT_MAX_CALLS,
+            max_duration: Duration::from_millis(DEFAULT_MAX_DURATION_MS),
+        }
+    }
+}
+
+impl StorageBudget {
+    /// Start spending the budget on a request
+    pub fn start(&self) -> RequestBudget {
+        RequestBudget {
+            deadline: Instant::now() + self.max_duration,
+            calls_left: AtomicU32::new(self.max_calls),
+        }
+    }
+}
+
+/// Calls and time left to one request
+#[derive(Debug)]
+pub struct RequestBudget {
+    deadline: Instant,
+    calls_left: AtomicU32,
+}
+
+impl RequestBudget {
+    /// Count a call made regardless of the budget
+    pub fn charge(&self) {
+        let _ = self.calls_left.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    /// Whether `calls` more calls fit, with time left
+    pub fn fits(&self, calls: u32) -> bool {
+        self.calls_left.load(Ordering::Relaxed) >= calls && Instant::now() < self.deadline
+    }
+
+    /// Make an optional call, cut off at the deadline
+    ///
+    /// None when the budget was spent before the call or ran out during it.
+    pub async fn call<F: Future>(&self, call: F) -> Option<F::Output> {
+        let remaining = self.deadline.checked_duration_since(Instant::now())?;
+        self.calls_left.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).ok()?;
+        tokio::time::timeout(remaining, call).await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_calls_and_deadline() {
+        let budget = StorageBudget { max_calls: 3, max_duration: Duration::from_millis(50) }.start();
+        budget.charge();
+        assert!(budget.fits(2));
+        assert!(!budget.fits(3));
+
+        assert_eq!(budget.call(async { 1 }).await, Some(1));
+        assert_eq!(budget.call(tokio::time::sleep(Duration::from_secs(5))).await, None);
+        assert_eq!(budget.call(async { 3 }).await, None);
+        assert!(!budget.fits(0));
+
+        // Calls stop at the deadline even with calls left
+        let budget = StorageBudget { max_calls: 3, max_duration: Duration::ZERO }.start();
+        assert_eq!(budget.call(async { 1 }).await, None);
+    }
+}
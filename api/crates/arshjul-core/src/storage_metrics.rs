@@ -0,0 +1,124 @@
+//! # Storage Metrics
+//!
+//! Counters and latency histograms of storage calls, recorded by
+//! [`TracedStorage`](crate::traced_storage::TracedStorage) next to its spans
+//! and rendered in the Prometheus text format by `GET /api/metrics`:
+//!
+//! - `arshjul_storage_operations_total` - calls, by `result` (the span's result label)
+//! - `arshjul_storage_errors_total` - calls the backend failed (`transient` or `error`)
+//! - `arshjul_storage_latency_seconds` - time spent in the backend
+//!
+//! Every series is labeled with `backend` (`memory`, `sqlite`, `table` or
+//! `cosmosdb`), `table` (the entity: `share`, `activity`, ...) and
+//! `operation` (trait method), so a slow Table Storage can be told from a
+//! slow Cosmos DB when both serve one deployment. Backends are named with
+//! [`Storage::with_backend`](crate::storage::Storage::with_backend); entities
+//! nobody named count as `memory`.
+//!
+//! Counters are per instance, like the SLIs; aggregate across instances in
+//! the metrics backend.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+/// Backend label of entities no backend was named for
+pub const DEFAULT_BACKEND: &str = "memory";
+
+/// `table` labels of the storage traits
+pub const ENTITIES: [&str; 7] = ["share", "activity", "layer", "activity_type", "user_settings", "audit", "policy"];
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Series of one operation of one table on one backend
+type SeriesKey = (&'static str, &'static str, &'static str);
+
+#[derive(Debug, Default)]
+struct OperationStats {
+    results: BTreeMap<&'static str, u64>,
+    errors: u64,
+    /// Calls per bucket, not cumulative
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    seconds: f64,
+    count: u64,
+}
+
+/// In-process storage call counters and latency histograms
+#[derive(Debug, Default)]
+pub struct StorageMetrics {
+    backends: RwLock<HashMap<&'static str, &'static str>>,
+    operations: Mutex<BTreeMap<SeriesKey, OperationStats>>,
+}
+
+impl StorageMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Label calls to `table` with `backend`
+    pub fn set_backend(&self, table: &'static str, backend: &'static str) {
+        self.backends.write().unwrap_or_else(|e| e.into_inner()).insert(table, backend);
+    }
+
+    pub fn backend(&self, table: &str) -> &'static str {
+        self.backends.read().unwrap_or_else(|e| e.into_inner()).get(table).copied().unwrap_or(DEFAULT_BACKEND)
+    }
+
+    /// Count one call and its latency
+    pub fn record(&self, table: &'static str, operation: &'static str, result: &'static str, latency: Duration) {
+        let backend = self.backend(table);
+        let seconds = latency.as_secs_f64();
+        let mut operations = self.operations.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = operations.entry((backend, table, operation)).or_default();
+
+        *stats.results.entry(result).or_default() += 1;
+        if matches!(result, "transient" | "error") {
+            stats.errors += 1;
+        }
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&le| seconds <= le) {
+            stats.buckets[bucket] += 1;
+        }
+        stats.seconds += seconds;
+        stats.count += 1;
+    }
+
+    /// Prometheus text format; empty before the first call
+    pub fn render_prometheus(&self) -> String {
+        let operations = self.operations.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        if operations.is_empty() {
+            return out;
+        }
+        let labels = |(backend, table, operation): &SeriesKey| {
+            format!("backend=\"{}\",table=\"{}\",operation=\"{}\"", backend, table, operation)
+        };
+
+        let _ = writeln!(out, "# HELP arshjul_storage_operations_total Storage calls by result\n# TYPE arshjul_storage_operations_total counter");
+        for (key, stats) in operations.iter() {
+            for (result, count) in &stats.results {
+                let _ = writeln!(out, "arshjul_storage_operations_total{{{},result=\"{}\"}} {}", labels(key), result, count);
+            }
+        }
+
+        let _ = writeln!(out, "# HELP arshjul_storage_errors_total Storage calls the backend failed\n# TYPE arshjul_storage_errors_total counter");
+        for (key, stats) in operations.iter() {
+            let _ = writeln!(out, "arshjul_storage_errors_total{{{}}} {}", labels(key), stats.errors);
+        }
+
+        let _ = writeln!(out, "# HELP arshjul_storage_latency_seconds Time spent in the storage backend\n# TYPE arshjul_storage_latency_seconds histogram");
+        for (key, stats) in operations.iter() {
+            let labels = labels(key);
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(stats.buckets) {
+                cumulative += count;
+                let _ = writeln!(out, "arshjul_storage_latency_seconds_bucket{{{},le=\"{}\"}} {}", labels, le, cumulative);
+            }
+            let _ = writeln!(out, "arshjul_storage_latency_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, stats.count);
+            let _ = writeln!(out, "arshjul_storage_latency_seconds_sum{{{}}} {}", labels, stats.seconds);
+            let _ = writeln!(out, "arshjul_storage_latency_seconds_count{{{}}} {}", labels, stats.count);
+        }
+        out
+    }
+}
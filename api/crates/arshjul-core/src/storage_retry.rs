@@ -0,0 +1,386 @@
+//! # Storage Retries
+//!
+//! Table Storage and Cosmos DB throttle busy partitions (429) and shed load
+//! (503). Their backends report both as [`StorageError::Transient`]: the
+//! request was refused, not carried out, so it is safe to send again.
+//! [`RetryingStorage`] wraps a backend and retries those errors under a
+//! [`RetryPolicy`]:
+//!
+//! - Up to `max_attempts` calls in total (`STORAGE_RETRY_MAX_ATTEMPTS`)
+//! - Exponential backoff from `base_delay`, capped at `max_delay`
+//!   (`STORAGE_RETRY_BASE_DELAY_MS`, `STORAGE_RETRY_MAX_DELAY_MS`)
+//! - Full jitter, so throttled callers don't come back in lockstep
+//!
+//! Every other error is returned at once, and so is a throttled call that
+//! writes more than one entity or is not idempotent: `share.create` (share
+//! and short code index), `share.delete` (share and tombstone),
+//! `share.increment_views`, `create_many`, `update_batch` and
+//! `apply_changes`. A throttled later write would be retried along with the
+//! earlier ones already made. Their callers get the error, which handlers
+//! answer with 503 and `Retry-After`.
+
+use crate::models::*;
+use crate::storage::*;
+use async_trait::async_trait;
+use rand::Rng;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Calls per storage request, including the first
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Longest wait between attempts
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Exponential backoff for transient storage errors
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Calls in total; 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Wait a random time up to the backoff instead of all of it
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Longest wait after failed attempt `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+    
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        if self.jitter {
+            backoff.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+        } else {
+            backoff
+        }
+    }
+    
+    /// Run `call` until it succeeds, fails for good, or attempts run out
+    pub async fn run<T, F, Fut>(&self, operation: &'static str, mut call: F) -> Result<T, StorageError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, StorageError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(e) if e.is_retryable() && attempt < self.max_attempts => {
+                    let delay = self.delay(attempt);
+                    tracing::debug!(operation, attempt, delay_ms = delay.as_millis() as u64, error = %e, "Retrying storage call");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Storage backend whose transient errors are retried
+pub struct RetryingStorage<S: ?Sized> {
+    inner: Arc<S>,
+    policy: RetryPolicy,
+}
+
+impl<S: ?Sized> RetryingStorage<S> {
+    pub fn new(inner: Arc<S>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl<S: ShareStorage + ?Sized> ShareStorage for RetryingStorage<S> {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        self.inner.create(share).await
+    }
+    
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.policy.run("share.get", || self.inner.get(organization_id, share_id)).await
+    }
+    
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        self.policy.run("share.get_by_short_code", || self.inner.get_by_short_code(short_code)).await
+    }
+    
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        self.policy.run("share.update", || self.inner.update(share.clone())).await
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        self.policy.run("share.get", || self.inner.get_tagged(organization_id, share_id)).await
+    }
+    
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        self.policy.run("share.update", || self.inner.replace(share.clone(), etag)).await
+    }
+    
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.delete(organization_id, share_id).await
+    }
+    
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        self.policy.run("share.get_tombstone", || self.inner.get_tombstone(short_code)).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        self.policy.run("share.list", || self.inner.list(organization_id, options.clone())).await
+    }
+    
+    async fn list_by_layer(&self, organization_id: &str, layer_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        self.policy.run("share.list", || self.inner.list_by_layer(organization_id, layer_id)).await
+    }
+    
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.inner.increment_views(organization_id, share_id).await
+    }
+}
+
+#[async_trait]
+impl<S: ActivityStorage + ?Sized> ActivityStorage for RetryingStorage<S> {
+    async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+        self.policy.run("activity.create", || self.inner.create(activity.clone())).await
+    }
+    
+    async fn create_many(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        self.inner.create_many(activities).await
+    }
+    
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        self.policy.run("activity.get", || self.inner.get(organization_id, activity_id)).await
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, activity_id: &str) -> Result<(Activity, String), StorageError> {
+        self.policy.run("activity.get", || self.inner.get_tagged(organization_id, activity_id)).await
+    }
+    
+    async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+        self.policy.run("activity.update", || self.inner.update(activity.clone())).await
+    }
+    
+    async fn update_batch(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        self.inner.update_batch(activities).await
+    }
+    
+    async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+        self.policy.run("activity.delete", || self.inner.delete(organization_id, activity_id)).await
+    }
+    
+    async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+        self.inner.apply_changes(organization_id, changes).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<Activity>, StorageError> {
+        self.policy.run("activity.list", || self.inner.list(organization_id, options.clone())).await
+    }
+    
+    async fn list_by_layers(
+        &self,
+        organization_id: &str,
+        layer_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<Activity>, StorageError> {
+        self.policy.run("activity.list_by_layers", || self.inner.list_by_layers(organization_id, layer_ids, year)).await
+    }
+}
+
+#[async_trait]
+impl<S: LayerStorage + ?Sized> LayerStorage for RetryingStorage<S> {
+    async fn create(&self, layer: Layer) -> Result<Layer, StorageError> {
+        self.policy.run("layer.create", || self.inner.create(layer.clone())).await
+    }
+    
+    async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError> {
+        self.policy.run("layer.get", || self.inner.get(organization_id, layer_id)).await
+    }
+    
+    async fn update(&self, layer: Layer) -> Result<Layer, StorageError> {
+        self.policy.run("layer.update", || self.inner.update(layer.clone())).await
+    }
+    
+    async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError> {
+        self.policy.run("layer.delete", || self.inner.delete(organization_id, layer_id)).await
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError> {
+        self.policy.run("layer.list", || self.inner.list(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: ActivityTypeStorage + ?Sized> ActivityTypeStorage for RetryingStorage<S> {
+    async fn upsert(&self, config: ActivityTypeConfig) -> Result<ActivityTypeConfig, StorageError> {
+        self.policy.run("activity_type.upsert", || self.inner.upsert(config.clone())).await
+    }
+    
+    async fn get(&self, organization_id: &str, key: &str) -> Result<ActivityTypeConfig, StorageError> {
+        self.policy.run("activity_type.get", || self.inner.get(organization_id, key)).await
+    }
+    
+    async fn delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.policy.run("activity_type.delete", || self.inner.delete(organization_id, key)).await
+    }
+    
+    async fn force_delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.policy.run("activity_type.force_delete", || self.inner.force_delete(organization_id, key)).await
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<ActivityTypeConfig>, StorageError> {
+        self.policy.run("activity_type.list", || self.inner.list(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: UserSettingsStorage + ?Sized> UserSettingsStorage for RetryingStorage<S> {
+    async fn get(&self, organization_id: &str, user_id: &str) -> Result<UserSettings, StorageError> {
+        self.policy.run("user_settings.get", || self.inner.get(organization_id, user_id)).await
+    }
+    
+    async fn upsert(&self, settings: UserSettings) -> Result<UserSettings, StorageError> {
+        self.policy.run("user_settings.upsert", || self.inner.upsert(settings.clone())).await
+    }
+    
+    async fn delete(&self, organization_id: &str, user_id: &str) -> Result<(), StorageError> {
+        self.policy.run("user_settings.delete", || self.inner.delete(organization_id, user_id)).await
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<UserSettings>, StorageError> {
+        self.policy.run("user_settings.list", || self.inner.list(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: AuditStorage + ?Sized> AuditStorage for RetryingStorage<S> {
+    async fn record(&self, entry: AuditEntry) -> Result<(), StorageError> {
+        self.policy.run("audit.record", || self.inner.record(entry.clone())).await
+    }
+    
+    async fn list(&self, organization_id: &str, options: QueryOptions) -> Result<QueryResult<AuditEntry>, StorageError> {
+        self.policy.run("audit.list", || self.inner.list(organization_id, options.clone())).await
+    }
+    
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        self.policy.run("audit.delete_all", || self.inner.delete_all(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: PolicyStorage + ?Sized> PolicyStorage for RetryingStorage<S> {
+    async fn get(&self, organization_id: &str) -> Result<OrganizationPolicy, StorageError> {
+        self.policy.run("policy.get", || self.inner.get(organization_id)).await
+    }
+    
+    async fn upsert(&self, policy: OrganizationPolicy) -> Result<OrganizationPolicy, StorageError> {
+        self.policy.run("policy.upsert", || self.inner.upsert(policy.clone())).await
+    }
+    
+    async fn delete(&self, organization_id: &str) -> Result<(), StorageError> {
+        self.policy.run("policy.delete", || self.inner.delete(organization_id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory_storage::MemoryLayerStorage;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    
+    /// Layer storage that is throttled for the first `failures` calls
+    struct Throttled {
+        inner: MemoryLayerStorage,
+        failures: u32,
+        calls: AtomicU32,
+    }
+    
+    #[async_trait]
+    impl LayerStorage for Throttled {
+        async fn create(&self, layer: Layer) -> Result<Layer, StorageError> {
+            self.inner.create(layer).await
+        }
+        
+        async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(StorageError::Transient("429".to_string()));
+            }
+            self.inner.get(organization_id, layer_id).await
+        }
+        
+        async fn update(&self, layer: Layer) -> Result<Layer, StorageError> {
+            self.inner.update(layer).await
+        }
+        
+        async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError> {
+            self.inner.delete(organization_id, layer_id).await
+        }
+        
+        async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError> {
+            self.inner.list(organization_id).await
+        }
+    }
+    
+    fn policy() -> RetryPolicy {
+        RetryPolicy { max_attempts: 3, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(2), jitter: false }
+    }
+    
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy { jitter: false, ..Default::default() };
+        let delays: Vec<u128> = (1..=6).map(|attempt| policy.backoff(attempt).as_millis()).collect();
+        assert_eq!(delays, [200, 400, 800, 1600, 3200, 5000]);
+        assert_eq!(policy.backoff(u32::MAX), DEFAULT_MAX_DELAY);
+    }
+    
+    #[tokio::test]
+    async fn test_retries_transient_errors_only() {
+        let throttled = |failures| Arc::new(Throttled { inner: MemoryLayerStorage::new(), failures, calls: AtomicU32::new(0) });
+        let layer: Layer = serde_json::from_value(serde_json::json!({
+            "id": "layer-1", "name": "Sales", "type": "custom", "color": "#000000", "ringIndex": 1,
+            "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+        })).unwrap();
+        
+        // Recovers within the attempts
+        let backend = throttled(2);
+        backend.create(layer).await.unwrap();
+        let storage = RetryingStorage::new(backend.clone(), policy());
+        assert_eq!(storage.get("org-1", "layer-1").await.unwrap().name, "Sales");
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 3);
+        
+        // Gives up after them
+        let backend = throttled(5);
+        let storage = RetryingStorage::new(backend.clone(), policy());
+        assert!(matches!(storage.get("org-1", "layer-1").await, Err(StorageError::Transient(_))));
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 3);
+        
+        // Terminal errors are not retried
+        let backend = throttled(0);
+        let storage = RetryingStorage::new(backend.clone(), policy());
+        assert!(matches!(storage.get("org-1", "missing").await, Err(StorageError::NotFound(_))));
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+    }
+}
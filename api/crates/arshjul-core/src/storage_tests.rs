@@ -0,0 +1,389 @@
+//! # Storage Conformance Suite
+//!
+//! Behaviour every storage backend must share, as reusable async checks:
+//!
+//! - [`share_storage_suite`] - CRUD, conditional updates, short code index
+//!   and tombstones, view counts, paging with continuation tokens and filters
+//! - [`concurrent_views_suite`] - views counted in parallel on one share
+//!   all count
+//! - [`share_ttl_suite`] - TTL expiry and reuse of expired short codes; time
+//!   passes on the [`ManualClock`] the backend reads, or on the wall clock
+//!   ([`WallTime`]) for backends that expire shares server-side
+//! - [`activity_storage_suite`] - CRUD, `list_by_layers` year overlap, bulk
+//!   writes and `apply_changes`
+//! - [`layer_storage_suite`] - CRUD and `ringIndex` ordering
+//! - [`policy_storage_suite`] - defaults for unset policies, replace and delete
+//! - [`audit_storage_suite`] - entries oldest first, per organization, and
+//!   deleted together
+//!
+//! Each check panics on the first difference. Run them against a fresh,
+//! empty store; data goes into organizations prefixed `conformance-`.
+//!
+//! ```ignore
+//! #[tokio::test]
+//! async fn test_conformance() {
+//!     let storage = MyStorage::new();
+//!     storage_tests::share_storage_suite(&storage).await;
+//! }
+//! ```
+//!
+//! Enabled for other crates' tests by the `test-util` feature.
+
+use crate::clock::{Clock, ManualClock};
+use crate::models::*;
+use crate::storage::{ActivityChanges, ActivityStorage, Filter, FilterBuilder, FilterField, AuditStorage, LayerStorage, PolicyStorage, QueryOptions, ShareStorage, StorageError};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+/// Views [`concurrent_views_suite`] counts at once
+pub const CONCURRENT_VIEWS: u64 = 10;
+
+/// Time as the backend under test sees it
+#[async_trait]
+pub trait TestTime: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+    
+    /// Let `duration` pass
+    async fn pass(&self, duration: Duration);
+}
+
+#[async_trait]
+impl TestTime for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        Clock::now(self)
+    }
+    
+    async fn pass(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Wall-clock time, for backends that don't read a [`Clock`]
+pub struct WallTime;
+
+#[async_trait]
+impl TestTime for WallTime {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+    
+    async fn pass(&self, duration: Duration) {
+        tokio::time::sleep(duration.to_std().unwrap_or_default()).await;
+    }
+}
+
+/// Share in `organization_id` that expires in 2099
+pub fn share(organization_id: &str, id: &str, short_code: &str, visibility: &str) -> ShareLink {
+    serde_json::from_value(serde_json::json!({
+        "id": id, "shareKey": "k".repeat(64), "shortCode": short_code,
+        "visibility": visibility, "organizationId": organization_id, "createdBy": "user-1",
+        "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2099-01-01T00:00:00Z",
+        "layerConfig": { "layerIds": [] }, "viewSettings": {},
+    })).unwrap()
+}
+
+/// Activity in `organization_id` on `layer`, from `start` to `end` (RFC 3339)
+pub fn activity(organization_id: &str, id: &str, layer: &str, start: &str, end: &str) -> Activity {
+    serde_json::from_value(serde_json::json!({
+        "id": id, "title": id, "startDate": start, "endDate": end,
+        "type": "meeting", "color": "#000000", "highlightColor": "#000000",
+        "scope": layer, "scopeId": layer, "organizationId": organization_id,
+    })).unwrap()
+}
+
+/// Layer in `organization_id` on ring `ring_index`
+pub fn layer(organization_id: &str, id: &str, ring_index: i32) -> Layer {
+    serde_json::from_value(serde_json::json!({
+        "id": id, "name": id, "type": "custom", "color": "#000000", "ringIndex": ring_index,
+        "organizationId": organization_id, "createdBy": "admin", "createdAt": "2025-01-01T00:00:00Z",
+    })).unwrap()
+}
+
+fn ids<'a>(items: impl IntoIterator<Item = &'a Activity>) -> Vec<&'a str> {
+    let mut ids: Vec<&str> = items.into_iter().map(|a| a.id.as_str()).collect();
+    ids.sort();
+    ids
+}
+
+/// Every page of a share listing, following continuation tokens
+async fn list_all(storage: &dyn ShareStorage, organization_id: &str, filter: Option<Filter>) -> Vec<ShareLink> {
+    let mut shares = Vec::new();
+    let mut token = None;
+    loop {
+        let options = QueryOptions {
+            page_size: Some(2),
+            continuation_token: token,
+            filter: filter.clone(),
+        };
+        let page = storage.list(organization_id, options).await.unwrap();
+        assert!(page.items.len() <= 2, "page larger than requested");
+        shares.extend(page.items);
+        token = page.continuation_token;
+        if token.is_none() {
+            return shares;
+        }
+    }
+}
+
+/// Share CRUD, short code index, tombstones, view counts and paging
+pub async fn share_storage_suite(storage: &dyn ShareStorage) {
+    let org = "conformance-shares";
+    
+    // CRUD
+    let created = storage.create(share(org, "s-1", "Conf0001", "public")).await.unwrap();
+    assert_eq!(storage.get(org, "s-1").await.unwrap().short_code, created.short_code);
+    assert!(matches!(storage.create(share(org, "s-1", "Conf0002", "public")).await, Err(StorageError::AlreadyExists(_))));
+    assert!(matches!(storage.get(org, "missing").await, Err(StorageError::NotFound(_))));
+    assert!(matches!(storage.get("conformance-other", "s-1").await, Err(StorageError::NotFound(_))));
+    
+    let mut deactivated = created.clone();
+    deactivated.is_active = false;
+    storage.update(deactivated).await.unwrap();
+    assert!(!storage.get(org, "s-1").await.unwrap().is_active);
+    assert!(matches!(storage.update(share(org, "missing", "Conf0009", "public")).await, Err(StorageError::NotFound(_))));
+    
+    storage.increment_views(org, "s-1").await.unwrap();
+    assert_eq!(storage.get(org, "s-1").await.unwrap().stats.view_count, created.stats.view_count + 1);
+    
+    // Conditional updates lose to any write since the read
+    let (mut renamed, etag) = storage.get_tagged(org, "s-1").await.unwrap();
+    renamed.name = Some("Board".to_string());
+    storage.increment_views(org, "s-1").await.unwrap();
+    assert!(storage.replace(renamed, &etag).await.unwrap().is_none());
+    let (mut renamed, etag) = storage.get_tagged(org, "s-1").await.unwrap();
+    renamed.name = Some("Board".to_string());
+    assert!(storage.replace(renamed.clone(), &etag).await.unwrap().is_some());
+    assert!(storage.replace(renamed, &etag).await.unwrap().is_none());
+    assert_eq!(storage.get(org, "s-1").await.unwrap().name.as_deref(), Some("Board"));
+    
+    // Short codes are unique, and follow a changed code
+    assert!(matches!(storage.create(share(org, "s-2", "Conf0001", "public")).await, Err(StorageError::AlreadyExists(_))));
+    assert_eq!(storage.get_by_short_code("Conf0001").await.unwrap().id, "s-1");
+    let mut moved = storage.get(org, "s-1").await.unwrap();
+    moved.short_code = "Conf0003".to_string();
+    storage.update(moved).await.unwrap();
+    assert!(matches!(storage.get_by_short_code("Conf0001").await, Err(StorageError::NotFound(_))));
+    assert_eq!(storage.get_by_short_code("Conf0003").await.unwrap().id, "s-1");
+    
+    // Deleting retires the code
+    storage.delete(org, "s-1").await.unwrap();
+    assert!(matches!(storage.get(org, "s-1").await, Err(StorageError::NotFound(_))));
+    assert!(matches!(storage.get_by_short_code("Conf0003").await, Err(StorageError::NotFound(_))));
+    assert!(storage.get_tombstone("Conf0003").await.unwrap().is_some());
+    assert!(matches!(storage.create(share(org, "s-3", "Conf0003", "public")).await, Err(StorageError::AlreadyExists(_))));
+    assert!(storage.get_tombstone("Conf0004").await.unwrap().is_none());
+    
+    // Pages in ID order, each at most the page size, filters applied across pages
+    let org = "conformance-pages";
+    for i in 0..5 {
+        let visibility = if i % 2 == 0 { "public" } else { "users" };
+        storage.create(share(org, &format!("p-{}", i), &format!("Page000{}", i), visibility)).await.unwrap();
+    }
+    let all: Vec<String> = list_all(storage, org, None).await.into_iter().map(|s| s.id).collect();
+    assert_eq!(all, ["p-0", "p-1", "p-2", "p-3", "p-4"]);
+    let public: Vec<String> = list_all(storage, org, FilterBuilder::new().eq(FilterField::Visibility, "public").build()).await.into_iter().map(|s| s.id).collect();
+    assert_eq!(public, ["p-0", "p-2", "p-4"]);
+    assert!(list_all(storage, "conformance-empty", None).await.is_empty());
+}
+
+/// Views counted in parallel on one share all count
+pub async fn concurrent_views_suite(storage: Arc<dyn ShareStorage>) {
+    let org = "conformance-views";
+    let created = storage.create(share(org, "v-1", "View0001", "public")).await.unwrap();
+    let views: Vec<_> = (0..CONCURRENT_VIEWS)
+        .map(|_| {
+            let storage = storage.clone();
+            tokio::spawn(async move { storage.increment_views(org, "v-1").await })
+        })
+        .collect();
+    for view in views {
+        view.await.unwrap().unwrap();
+    }
+    assert_eq!(storage.get(org, "v-1").await.unwrap().stats.view_count, created.stats.view_count + CONCURRENT_VIEWS);
+}
+
+/// Shares with a TTL disappear once it passes, and their codes can be claimed again
+pub async fn share_ttl_suite(storage: &dyn ShareStorage, time: &dyn TestTime) {
+    let org = "conformance-ttl";
+    let ttl = Duration::seconds(2);
+    let mut expiring = share(org, "t-1", "Ttl00001", "public");
+    expiring.ttl = Some(ttl.num_seconds());
+    expiring.expires_at = time.now() + ttl;
+    storage.create(expiring).await.unwrap();
+    storage.create(share(org, "t-2", "Ttl00002", "public")).await.unwrap();
+    assert_eq!(storage.get_by_short_code("Ttl00001").await.unwrap().id, "t-1");
+    
+    time.pass(ttl + Duration::seconds(1)).await;
+    assert!(matches!(storage.get(org, "t-1").await, Err(StorageError::NotFound(_))));
+    assert!(matches!(storage.get_by_short_code("Ttl00001").await, Err(StorageError::NotFound(_))));
+    let listed: Vec<String> = list_all(storage, org, None).await.into_iter().map(|s| s.id).collect();
+    assert_eq!(listed, ["t-2"]);
+    
+    // Expiry leaves no tombstone
+    assert!(storage.get_tombstone("Ttl00001").await.unwrap().is_none());
+    storage.create(share(org, "t-3", "Ttl00001", "public")).await.unwrap();
+    assert_eq!(storage.get_by_short_code("Ttl00001").await.unwrap().id, "t-3");
+}
+
+/// Activity CRUD, layer and year queries, bulk writes and mixed changes
+pub async fn activity_storage_suite(storage: &dyn ActivityStorage) {
+    let org = "conformance-activities";
+    
+    let created = storage.create(activity(org, "a-1", "layer-1", "2024-12-20T00:00:00Z", "2025-01-05T00:00:00Z")).await.unwrap();
+    assert_eq!(storage.get(org, "a-1").await.unwrap().title, created.title);
+    assert!(matches!(
+        storage.create(activity(org, "a-1", "layer-2", "2025-01-01T00:00:00Z", "2025-01-01T00:00:00Z")).await,
+        Err(StorageError::AlreadyExists(_))
+    ));
+    assert!(matches!(storage.get(org, "missing").await, Err(StorageError::NotFound(_))));
+    assert!(matches!(storage.get("conformance-other", "a-1").await, Err(StorageError::NotFound(_))));
+    
+    let mut renamed = created.clone();
+    renamed.title = "Renamed".to_string();
+    storage.update(renamed).await.unwrap();
+    assert_eq!(storage.get(org, "a-1").await.unwrap().title, "Renamed");
+    assert!(matches!(
+        storage.update(activity(org, "missing", "layer-1", "2025-01-01T00:00:00Z", "2025-01-01T00:00:00Z")).await,
+        Err(StorageError::NotFound(_))
+    ));
+    
+    // Bulk writes
+    let created = storage.create_many(vec![
+        activity(org, "a-2", "layer-1", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z"),
+        activity(org, "a-3", "layer-2", "2025-06-01T00:00:00Z", "2025-06-02T00:00:00Z"),
+        activity(org, "a-4", "layer-1", "2026-01-01T00:00:00Z", "2026-01-02T00:00:00Z"),
+    ]).await.unwrap();
+    assert_eq!(created.len(), 3);
+    let mut moved = storage.get(org, "a-3").await.unwrap();
+    moved.scope = "layer-1".to_string();
+    storage.update_batch(vec![moved]).await.unwrap();
+    assert_eq!(storage.get(org, "a-3").await.unwrap().scope, "layer-1");
+    
+    let listed = storage.list(org, QueryOptions { page_size: Some(1000), ..Default::default() }).await.unwrap();
+    assert_eq!(ids(&listed.items), ["a-1", "a-2", "a-3", "a-4"]);
+    
+    // Activities spanning New Year count for both years
+    let layers = ["layer-1".to_string()];
+    assert_eq!(ids(&storage.list_by_layers(org, &layers, Some(2024)).await.unwrap()), ["a-1"]);
+    assert_eq!(ids(&storage.list_by_layers(org, &layers, Some(2025)).await.unwrap()), ["a-1", "a-2", "a-3"]);
+    assert_eq!(ids(&storage.list_by_layers(org, &layers, None).await.unwrap()), ["a-1", "a-2", "a-3", "a-4"]);
+    assert!(storage.list_by_layers(org, &[], None).await.unwrap().is_empty());
+    assert!(storage.list_by_layers("conformance-other", &layers, None).await.unwrap().is_empty());
+    
+    storage.delete(org, "a-1").await.unwrap();
+    assert!(matches!(storage.get(org, "a-1").await, Err(StorageError::NotFound(_))));
+    
+    // Mixed changes, as for a split or merge
+    let (mut shortened, etag) = storage.get_tagged(org, "a-2").await.unwrap();
+    let (_, deleted_etag) = storage.get_tagged(org, "a-4").await.unwrap();
+    shortened.end_date = "2025-06-01T12:00:00Z".parse().unwrap();
+    storage.apply_changes(org, ActivityChanges {
+        create: vec![activity(org, "a-5", "layer-1", "2025-06-01T12:00:00Z", "2025-06-02T00:00:00Z")],
+        update: vec![shortened.clone()],
+        delete: vec!["a-4".to_string()],
+        etags: [("a-2".to_string(), etag.clone()), ("a-4".to_string(), deleted_etag)].into(),
+    }).await.unwrap();
+    assert_eq!(storage.get(org, "a-2").await.unwrap().end_date, shortened.end_date);
+    assert_eq!(ids(&storage.list_by_layers(org, &layers, None).await.unwrap()), ["a-2", "a-3", "a-5"]);
+    
+    // Changes to an activity written since it was read are refused, and none is applied
+    let stale = ActivityChanges {
+        update: vec![Activity { title: "Stale".to_string(), ..shortened.clone() }],
+        delete: vec!["a-5".to_string()],
+        etags: [("a-2".to_string(), etag)].into(),
+        ..Default::default()
+    };
+    assert!(matches!(storage.apply_changes(org, stale).await, Err(StorageError::Conflict(_))));
+    assert_eq!(ids(&storage.list_by_layers(org, &layers, None).await.unwrap()), ["a-2", "a-3", "a-5"]);
+    assert_ne!(storage.get(org, "a-2").await.unwrap().title, "Stale");
+    assert!(matches!(
+        storage.apply_changes(org, ActivityChanges { delete: vec!["a-4".to_string()], ..Default::default() }).await,
+        Err(StorageError::NotFound(_))
+    ));
+}
+
+/// Layer CRUD and ring order
+pub async fn layer_storage_suite(storage: &dyn LayerStorage) {
+    let org = "conformance-layers";
+    
+    storage.create(layer(org, "outer", 2)).await.unwrap();
+    storage.create(layer(org, "inner", 0)).await.unwrap();
+    storage.create(layer(org, "middle", 1)).await.unwrap();
+    assert!(matches!(storage.create(layer(org, "inner", 3)).await, Err(StorageError::AlreadyExists(_))));
+    assert!(matches!(storage.get(org, "missing").await, Err(StorageError::NotFound(_))));
+    
+    let mut renamed = storage.get(org, "middle").await.unwrap();
+    renamed.name = "Renamed".to_string();
+    storage.update(renamed).await.unwrap();
+    assert_eq!(storage.get(org, "middle").await.unwrap().name, "Renamed");
+    assert!(matches!(storage.update(layer(org, "missing", 4)).await, Err(StorageError::NotFound(_))));
+    
+    let order: Vec<String> = storage.list(org).await.unwrap().into_iter().map(|l| l.id).collect();
+    assert_eq!(order, ["inner", "middle", "outer"]);
+    assert!(storage.list("conformance-other").await.unwrap().is_empty());
+    
+    storage.delete(org, "inner").await.unwrap();
+    assert!(matches!(storage.get(org, "inner").await, Err(StorageError::NotFound(_))));
+    assert_eq!(storage.list(org).await.unwrap().len(), 2);
+}
+
+/// Policy defaults, replace and delete
+pub async fn policy_storage_suite(storage: &dyn PolicyStorage) {
+    let org = "conformance-policies";
+    
+    assert_eq!(storage.get(org).await.unwrap(), OrganizationPolicy::new(org));
+    assert!(matches!(storage.delete(org).await, Err(StorageError::NotFound(_))));
+    
+    let policy = OrganizationPolicy { indexable: true, ..OrganizationPolicy::new(org) };
+    storage.upsert(policy.clone()).await.unwrap();
+    assert_eq!(storage.get(org).await.unwrap(), policy);
+    assert_eq!(storage.get("conformance-other").await.unwrap(), OrganizationPolicy::new("conformance-other"));
+    
+    let replaced = OrganizationPolicy { indexable: false, ..policy };
+    storage.upsert(replaced.clone()).await.unwrap();
+    assert_eq!(storage.get(org).await.unwrap(), replaced);
+    
+    storage.delete(org).await.unwrap();
+    assert_eq!(storage.get(org).await.unwrap(), OrganizationPolicy::new(org));
+}
+
+/// Audit entries in order, per organization, deleted together
+pub async fn audit_storage_suite(storage: &dyn AuditStorage) {
+    let org = "conformance-audit";
+    
+    // IDs in recording order, as time-ordered IDs are
+    for (n, action) in ["first", "second", "third"].into_iter().enumerate() {
+        let entry = AuditEntry { id: format!("{:020}-entry", n), ..AuditEntry::new(org, action, Some("user-1"), None) };
+        storage.record(entry).await.unwrap();
+    }
+    storage.record(AuditEntry::new("conformance-other", "other", None, None)).await.unwrap();
+    
+    let listed = storage.list(org, QueryOptions::default()).await.unwrap();
+    let actions: Vec<String> = listed.items.into_iter().map(|e| e.action).collect();
+    assert_eq!(actions, ["first", "second", "third"]);
+    assert_eq!(listed.total_count, Some(3));
+    
+    assert_eq!(storage.delete_all(org).await.unwrap(), 3);
+    assert!(storage.list(org, QueryOptions::default()).await.unwrap().items.is_empty());
+    assert_eq!(storage.list("conformance-other", QueryOptions::default()).await.unwrap().items.len(), 1);
+    assert_eq!(storage.delete_all(org).await.unwrap(), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory_storage::{MemoryActivityStorage, MemoryAuditStorage, MemoryLayerStorage, MemoryPolicyStorage, MemoryShareStorage};
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_memory_storage_conforms() {
+        share_storage_suite(&MemoryShareStorage::new()).await;
+        concurrent_views_suite(Arc::new(MemoryShareStorage::new())).await;
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        share_ttl_suite(&MemoryShareStorage::with_clock(clock.clone()), clock.as_ref()).await;
+        activity_storage_suite(&MemoryActivityStorage::new()).await;
+        layer_storage_suite(&MemoryLayerStorage::new()).await;
+        policy_storage_suite(&MemoryPolicyStorage::new()).await;
+        audit_storage_suite(&MemoryAuditStorage::new()).await;
+    }
+}
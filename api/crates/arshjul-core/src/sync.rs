@@ -0,0 +1,209 @@
+//! # Delta Sync
+//!
+//! Graph-style delta queries so the Teams tab can keep an offline-capable
+//! local cache of activities, layers and activity types.
+//!
+//! ## Protocol
+//!
+//! 1. The client calls `GET /api/delta` without a token and receives every
+//!    entity plus a `syncToken`.
+//! 2. Subsequent calls pass `?token={syncToken}` and only receive entities
+//!    modified since that token was issued.
+//! 3. Every response carries the full set of live IDs per entity type, so the
+//!    client prunes cached entries that were deleted in the meantime.
+//!
+//! Tokens are opaque to clients (URL-safe base64 of a small JSON document).
+//! A token issued for another organization, or older than
+//! [`MAX_TOKEN_AGE_DAYS`], triggers a full resync (`fullSync: true`).
+
+use crate::models::*;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Current sync token format version
+pub const SYNC_TOKEN_VERSION: u8 = 1;
+
+/// Tokens older than this force a full resync
+pub const MAX_TOKEN_AGE_DAYS: i64 = 30;
+
+/// Margin subtracted from the token timestamp to tolerate clock skew between instances
+const CLOCK_SKEW_SECONDS: i64 = 5;
+
+/// Sync token errors
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("Invalid sync token")]
+    InvalidToken,
+    
+    #[error("Unsupported sync token version: {0}")]
+    UnsupportedVersion(u8),
+}
+
+/// Decoded sync token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncToken {
+    /// Token format version
+    #[serde(rename = "v")]
+    pub version: u8,
+    
+    /// Organization the token was issued for
+    #[serde(rename = "o")]
+    pub organization_id: String,
+    
+    /// Point in time the client is synchronized up to
+    #[serde(rename = "t")]
+    pub since: DateTime<Utc>,
+}
+
+impl SyncToken {
+    pub fn new(organization_id: &str, since: DateTime<Utc>) -> Self {
+        Self {
+            version: SYNC_TOKEN_VERSION,
+            organization_id: organization_id.to_string(),
+            since,
+        }
+    }
+    
+    /// Encode as an opaque, URL-safe string
+    pub fn encode(&self) -> String {
+        // Serializing a struct of plain fields cannot fail
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+    
+    /// Decode a token previously produced by [`SyncToken::encode`]
+    pub fn decode(token: &str) -> Result<Self, SyncError> {
+        let bytes = URL_SAFE_NO_PAD.decode(token.trim())
+            .map_err(|_| SyncError::InvalidToken)?;
+        let token: SyncToken = serde_json::from_slice(&bytes)
+            .map_err(|_| SyncError::InvalidToken)?;
+        
+        if token.version != SYNC_TOKEN_VERSION {
+            return Err(SyncError::UnsupportedVersion(token.version));
+        }
+        
+        Ok(token)
+    }
+    
+    /// Check if the token is too old for an incremental sync
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        now - self.since > Duration::days(MAX_TOKEN_AGE_DAYS)
+    }
+    
+    /// Lower bound used when comparing modification timestamps
+    pub fn changed_since(&self) -> DateTime<Utc> {
+        self.since - Duration::seconds(CLOCK_SKEW_SECONDS)
+    }
+}
+
+/// Entities that can participate in delta sync
+pub trait SyncEntity {
+    /// Stable identifier used by the client cache
+    fn sync_id(&self) -> &str;
+    
+    /// Last modification time, if tracked
+    fn last_modified(&self) -> Option<DateTime<Utc>>;
+}
+
+impl SyncEntity for Activity {
+    fn sync_id(&self) -> &str {
+        &self.id
+    }
+    
+    fn last_modified(&self) -> Option<DateTime<Utc>> {
+        self.updated_at.or(self.created_at)
+    }
+}
+
+impl SyncEntity for Layer {
+    fn sync_id(&self) -> &str {
+        &self.id
+    }
+    
+    fn last_modified(&self) -> Option<DateTime<Utc>> {
+        Some(self.updated_at.unwrap_or(self.created_at))
+    }
+}
+
+impl SyncEntity for ActivityTypeConfig {
+    fn sync_id(&self) -> &str {
+        &self.key
+    }
+    
+    fn last_modified(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+}
+
+/// Split entities into the changed subset and the full live ID set
+///
+/// Entities without a modification timestamp are always treated as changed,
+/// since we cannot prove the client already has them.
+pub fn compute_delta<T: SyncEntity>(items: Vec<T>, since: Option<DateTime<Utc>>) -> EntityDelta<T> {
+    let ids = items.iter().map(|i| i.sync_id().to_string()).collect();
+    
+    let changed = match since {
+        None => items,
+        Some(since) => items.into_iter()
+            .filter(|i| i.last_modified().is_none_or(|m| m >= since))
+            .collect(),
+    };
+    
+    EntityDelta { changed, ids }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn layer(id: &str, updated_at: Option<DateTime<Utc>>) -> Layer {
+        Layer {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            layer_type: LayerType::Custom,
+            color: "#000000".to_string(),
+            ring_index: 0,
+            is_visible: true,
+            requires_approval: false,
+            visible_to_groups: Vec::new(),
+            team_ids: Vec::new(),
+            channel_ids: Vec::new(),
+            organization_id: "org".to_string(),
+            created_by: "user".to_string(),
+            created_at: Utc::now() - Duration::days(10),
+            updated_at,
+        }
+    }
+    
+    #[test]
+    fn test_sync_token_round_trip() {
+        let token = SyncToken::new("org-123", Utc::now());
+        let encoded = token.encode();
+        assert!(!encoded.contains('='));
+        assert_eq!(SyncToken::decode(&encoded).unwrap(), token);
+        
+        assert!(SyncToken::decode("not-a-token").is_err());
+        assert!(!token.is_stale(Utc::now()));
+        assert!(token.is_stale(Utc::now() + Duration::days(MAX_TOKEN_AGE_DAYS + 1)));
+    }
+    
+    #[test]
+    fn test_compute_delta() {
+        let since = Utc::now() - Duration::days(1);
+        let layers = vec![
+            layer("old", None),
+            layer("new", Some(Utc::now())),
+        ];
+        
+        let delta = compute_delta(layers.clone(), Some(since));
+        assert_eq!(delta.ids, vec!["old".to_string(), "new".to_string()]);
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(delta.changed[0].id, "new");
+        
+        let full = compute_delta(layers, None);
+        assert_eq!(full.changed.len(), 2);
+    }
+}
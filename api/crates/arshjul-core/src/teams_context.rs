@@ -0,0 +1,260 @@
+//! # Teams Context
+//!
+//! A Teams tab added to a team channel can pass the team and channel it runs
+//! in, so one app instance shows a different wheel per team. The frontend
+//! reads them from the Teams JS SDK context and sends them with each request:
+//!
+//! | Header | Value |
+//! |--------|-------|
+//! | `X-Teams-Team-Id` | `team.groupId`, the team's Microsoft 365 group ID |
+//! | `X-Teams-Channel-Id` | `channel.id`, e.g. `19:...@thread.tacv2` (optional) |
+//!
+//! The headers are client-supplied, so the team is only trusted after the
+//! SSO token shows the user is a member of its group, via the `groups` claim
+//! or the directory (see [`crate::layer_access`] for the same check on
+//! restricted layers). Without a directory, membership can only be confirmed
+//! from the claim.
+//!
+//! Layers and shares are scoped to teams (`team_ids`, every channel of the
+//! team) and channels (`channel_ids`). Within a team or channel, list
+//! endpoints leave out what is scoped elsewhere; unscoped layers and shares
+//! appear everywhere. Requests without Teams context (personal tab, browser)
+//! see the whole organization.
+//!
+//! Shares created from a channel are scoped to it, or to the team when the
+//! tab has no channel. Admins widen or narrow the scope of layers and shares
+//! afterwards, e.g. to show one team's layer in another team.
+//!
+//! Scoping only picks what is listed - access to layers is still governed by
+//! `visible_to_groups`, and share links work wherever they are opened.
+
+use crate::auth::UserContext;
+use crate::directory::DirectoryService;
+use crate::models::{Layer, ShareLink};
+use thiserror::Error;
+
+/// Header carrying the team's group ID
+pub const TEAM_ID_HEADER: &str = "x-teams-team-id";
+
+/// Header carrying the channel ID
+pub const CHANNEL_ID_HEADER: &str = "x-teams-channel-id";
+
+/// Longest accepted channel ID
+const MAX_CHANNEL_ID_LEN: usize = 256;
+
+/// Most teams and channels one layer or share can be scoped to
+pub const MAX_SCOPE_ENTRIES: usize = 50;
+
+/// Teams context errors
+#[derive(Debug, Error, PartialEq)]
+pub enum TeamsContextError {
+    #[error("Invalid team ID: expected the team's group ID")]
+    InvalidTeamId,
+    
+    #[error("Invalid channel ID")]
+    InvalidChannelId,
+    
+    #[error("Channel ID given without a team ID")]
+    ChannelWithoutTeam,
+    
+    #[error("At most {0} teams and channels can be given")]
+    TooManyScopes(usize),
+    
+    #[error("Not a member of the team")]
+    NotAMember,
+    
+    #[error("Team membership check failed: {0}")]
+    Directory(String),
+}
+
+/// Team and channel a request was made from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeamsContext {
+    pub team_id: String,
+    pub channel_id: Option<String>,
+}
+
+impl TeamsContext {
+    /// Parse the Teams headers; None when the request carries no team
+    pub fn from_headers(headers: &[(String, String)]) -> Result<Option<Self>, TeamsContextError> {
+        let header = |name: &str| headers.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim())
+            .filter(|v| !v.is_empty());
+        
+        let channel_id = header(CHANNEL_ID_HEADER).map(str::to_string);
+        if channel_id.as_deref().is_some_and(|c| !is_channel_id(c)) {
+            return Err(TeamsContextError::InvalidChannelId);
+        }
+        
+        match header(TEAM_ID_HEADER) {
+            Some(team_id) if is_group_id(team_id) => Ok(Some(Self {
+                team_id: team_id.to_ascii_lowercase(),
+                channel_id,
+            })),
+            Some(_) => Err(TeamsContextError::InvalidTeamId),
+            None if channel_id.is_some() => Err(TeamsContextError::ChannelWithoutTeam),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Whether the value is a group object ID (GUID)
+pub fn is_group_id(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    parts.iter().map(|p| p.len()).eq([8, 4, 4, 4, 12])
+        && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Whether the value looks like a Teams channel thread ID
+pub fn is_channel_id(value: &str) -> bool {
+    value.len() <= MAX_CHANNEL_ID_LEN
+        && value.starts_with("19:")
+        && (value.ends_with("@thread.tacv2") || value.ends_with("@thread.skype"))
+}
+
+/// Confirm the user belongs to the team's group
+pub async fn verify_membership(
+    team: &TeamsContext,
+    user: &UserContext,
+    directory: Option<&dyn DirectoryService>,
+) -> Result<(), TeamsContextError> {
+    // The claim may list only security groups, so a miss still asks the directory
+    if user.groups.as_ref().is_some_and(|groups| groups.iter().any(|g| g.eq_ignore_ascii_case(&team.team_id))) {
+        return Ok(());
+    }
+    
+    let Some(directory) = directory else {
+        return Err(TeamsContextError::NotAMember);
+    };
+    let member_of = directory.member_groups(&user.organization_id, &user.user_id, std::slice::from_ref(&team.team_id)).await
+        .map_err(|e| TeamsContextError::Directory(e.to_string()))?;
+    if member_of.contains(&team.team_id) {
+        Ok(())
+    } else {
+        Err(TeamsContextError::NotAMember)
+    }
+}
+
+/// Validated, normalized team and channel IDs for a layer or share
+pub fn validate_scope(team_ids: &[String], channel_ids: &[String]) -> Result<(Vec<String>, Vec<String>), TeamsContextError> {
+    let mut teams: Vec<String> = team_ids.iter().map(|t| t.trim().to_ascii_lowercase()).collect();
+    let mut channels: Vec<String> = channel_ids.iter().map(|c| c.trim().to_string()).collect();
+    if !teams.iter().all(|t| is_group_id(t)) {
+        return Err(TeamsContextError::InvalidTeamId);
+    }
+    if !channels.iter().all(|c| is_channel_id(c)) {
+        return Err(TeamsContextError::InvalidChannelId);
+    }
+    
+    teams.sort();
+    teams.dedup();
+    channels.sort();
+    channels.dedup();
+    if teams.len() + channels.len() > MAX_SCOPE_ENTRIES {
+        return Err(TeamsContextError::TooManyScopes(MAX_SCOPE_ENTRIES));
+    }
+    Ok((teams, channels))
+}
+
+/// Scope of a share created from the context: its channel, else its team
+pub fn scope_of(context: Option<&TeamsContext>) -> (Vec<String>, Vec<String>) {
+    match context {
+        Some(TeamsContext { channel_id: Some(channel_id), .. }) => (Vec::new(), vec![channel_id.clone()]),
+        Some(context) => (vec![context.team_id.clone()], Vec::new()),
+        None => (Vec::new(), Vec::new()),
+    }
+}
+
+/// Whether something scoped to these teams and channels is listed in the context (always outside Teams)
+pub fn in_scope(team_ids: &[String], channel_ids: &[String], context: Option<&TeamsContext>) -> bool {
+    let Some(context) = context else {
+        return true;
+    };
+    (team_ids.is_empty() && channel_ids.is_empty())
+        || team_ids.contains(&context.team_id)
+        || context.channel_id.as_ref().is_some_and(|c| channel_ids.contains(c))
+}
+
+/// Whether the layer is part of the wheel shown in the team or channel
+pub fn on_team_wheel(layer: &Layer, context: Option<&TeamsContext>) -> bool {
+    in_scope(&layer.team_ids, &layer.channel_ids, context)
+}
+
+/// Whether the share is listed in the team or channel
+pub fn share_in_scope(share: &ShareLink, context: Option<&TeamsContext>) -> bool {
+    in_scope(&share.team_ids, &share.channel_ids, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const TEAM: &str = "0f3e5a4c-2b1d-4c8e-9a7f-6d5e4c3b2a10";
+    
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+    
+    #[test]
+    fn test_from_headers() {
+        assert_eq!(TeamsContext::from_headers(&[]), Ok(None));
+        
+        let context = TeamsContext::from_headers(&headers(&[
+            ("X-Teams-Team-Id", &TEAM.to_uppercase()),
+            ("X-Teams-Channel-Id", "19:abc123@thread.tacv2"),
+        ])).unwrap().unwrap();
+        assert_eq!(context.team_id, TEAM);
+        assert_eq!(context.channel_id.as_deref(), Some("19:abc123@thread.tacv2"));
+        
+        assert_eq!(TeamsContext::from_headers(&headers(&[("x-teams-team-id", "general")])), Err(TeamsContextError::InvalidTeamId));
+        assert_eq!(TeamsContext::from_headers(&headers(&[("x-teams-team-id", TEAM), ("x-teams-channel-id", "general")])), Err(TeamsContextError::InvalidChannelId));
+        assert_eq!(TeamsContext::from_headers(&headers(&[("x-teams-channel-id", "19:abc123@thread.tacv2")])), Err(TeamsContextError::ChannelWithoutTeam));
+    }
+    
+    #[tokio::test]
+    async fn test_membership_and_team_wheel() {
+        let team = TeamsContext { team_id: TEAM.to_string(), channel_id: None };
+        let mut user = UserContext {
+            user_id: "user-1".to_string(),
+            organization_id: "org-1".to_string(),
+            display_name: None,
+            email: None,
+            is_admin: true,
+            roles: vec![],
+            groups: Some(vec![TEAM.to_string()]),
+            scopes: None,
+            team: None,
+        };
+        assert_eq!(verify_membership(&team, &user, None).await, Ok(()));
+        
+        user.groups = Some(vec!["other".to_string()]);
+        assert_eq!(verify_membership(&team, &user, None).await, Err(TeamsContextError::NotAMember));
+        
+        let mut layer: Layer = serde_json::from_value(serde_json::json!({
+            "id": "layer-1", "name": "Sales", "type": "custom", "color": "#000000", "ringIndex": 0,
+            "isVisible": true, "organizationId": "org-1", "createdBy": "user-1", "createdAt": "2025-01-01T00:00:00Z",
+        })).unwrap();
+        assert!(on_team_wheel(&layer, Some(&team)));
+        
+        layer.team_ids = vec!["a1b2c3d4-0000-0000-0000-000000000000".to_string()];
+        assert!(!on_team_wheel(&layer, Some(&team)));
+        assert!(on_team_wheel(&layer, None));
+    }
+    
+    #[test]
+    fn test_channel_scope() {
+        let channel = TeamsContext { team_id: TEAM.to_string(), channel_id: Some("19:planning@thread.tacv2".to_string()) };
+        let other_channel = TeamsContext { channel_id: Some("19:general@thread.tacv2".to_string()), ..channel.clone() };
+        
+        let (teams, channels) = scope_of(Some(&channel));
+        assert!(teams.is_empty());
+        assert!(in_scope(&teams, &channels, Some(&channel)));
+        assert!(!in_scope(&teams, &channels, Some(&other_channel)));
+        assert!(in_scope(&[TEAM.to_string()], &[], Some(&other_channel)));
+        
+        let (teams, channels) = validate_scope(&[TEAM.to_uppercase(), TEAM.to_string()], &[" 19:general@thread.tacv2".to_string()]).unwrap();
+        assert_eq!((teams, channels), (vec![TEAM.to_string()], vec!["19:general@thread.tacv2".to_string()]));
+        assert_eq!(validate_scope(&["sales".to_string()], &[]), Err(TeamsContextError::InvalidTeamId));
+    }
+}
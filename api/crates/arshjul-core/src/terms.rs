@@ -0,0 +1,339 @@
+//! # School Year Terms
+//!
+//! Schools and municipalities plan by the school year. An organization
+//! defines its terms, school holidays and exam periods once
+//! (`PUT /api/admin/policy/terms`), and [`TermPopulator`] generates them as
+//! whole-day activities on a system layer of type `terms`
+//! ([`TERMS_LAYER_ID`]):
+//!
+//! | Period | Activity type | Tag |
+//! |--------|---------------|-----|
+//! | `term` | `other` | `term` |
+//! | `holiday` | `holiday` | `school-holiday` |
+//! | `exam` | `deadline` | `exam` |
+//!
+//! Activity IDs are derived from the kind and start date of the period, so
+//! every run updates the activities in place: new periods are created,
+//! changed ones updated and removed ones deleted. The layer and its
+//! activities can't be written through the API; they change with the term
+//! structure. The populator runs after each change of the term structure and
+//! daily from [`TermPopulator::run_every`] (`TERMS_ORGANIZATIONS`), which
+//! undoes writes that bypassed the API. Removing the term structure removes
+//! the layer.
+//!
+//! The layer sits at [`TERMS_RING_INDEX`], innermost and below the rings of
+//! user layers, which count from 0.
+
+use crate::clock::{Clock, SystemClock};
+use crate::models::{Activity, ActivityType, Layer, LayerType, TermPeriod, TermPeriodKind, TermStructure, TermSyncResult};
+use crate::storage::{ActivityStorage, LayerStorage, PolicyStorage, StorageError};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// ID of the generated layer in every organization
+pub const TERMS_LAYER_ID: &str = "system-terms";
+
+/// Ring of the generated layer, inside every user layer
+pub const TERMS_RING_INDEX: i32 = -1;
+
+/// Most periods in a term structure
+pub const MAX_PERIODS: usize = 200;
+
+/// Invalid term structures
+#[derive(Debug, Error, PartialEq)]
+pub enum TermError {
+    #[error("Layer and period names must not be empty")]
+    EmptyName,
+    
+    #[error("Period ends before it starts: {0}")]
+    EndBeforeStart(String),
+    
+    #[error("Two periods of the same kind start on {0}")]
+    DuplicateStart(NaiveDate),
+    
+    #[error("At most {MAX_PERIODS} periods")]
+    TooManyPeriods,
+}
+
+/// Check a term structure before it is saved
+pub fn validate(terms: &TermStructure) -> Result<(), TermError> {
+    if terms.periods.len() > MAX_PERIODS {
+        return Err(TermError::TooManyPeriods);
+    }
+    if terms.layer_name.trim().is_empty() {
+        return Err(TermError::EmptyName);
+    }
+    
+    let mut starts = BTreeSet::new();
+    for period in &terms.periods {
+        if period.name.trim().is_empty() {
+            return Err(TermError::EmptyName);
+        }
+        if period.end < period.start {
+            return Err(TermError::EndBeforeStart(period.name.clone()));
+        }
+        if !starts.insert((period.kind, period.start)) {
+            return Err(TermError::DuplicateStart(period.start));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a layer is generated from the term structure, and so not writable
+pub fn is_system_layer(layer_id: &str) -> bool {
+    layer_id == TERMS_LAYER_ID
+}
+
+fn kind_key(kind: TermPeriodKind) -> &'static str {
+    match kind {
+        TermPeriodKind::Term => "term",
+        TermPeriodKind::Holiday => "holiday",
+        TermPeriodKind::Exam => "exam",
+    }
+}
+
+/// ID of the activity generated for a period
+pub fn activity_id(period: &TermPeriod) -> String {
+    format!("term-{}-{}", kind_key(period.kind), period.start)
+}
+
+/// The generated layer, for an organization that has none yet
+pub fn layer(organization_id: &str, terms: &TermStructure, now: DateTime<Utc>) -> Layer {
+    Layer {
+        id: TERMS_LAYER_ID.to_string(),
+        name: terms.layer_name.clone(),
+        description: Some("Generated from the term structure".to_string()),
+        layer_type: LayerType::Terms,
+        color: "#4F81BD".to_string(),
+        ring_index: TERMS_RING_INDEX,
+        is_visible: true,
+        requires_approval: false,
+        visible_to_groups: Vec::new(),
+        team_ids: Vec::new(),
+        channel_ids: Vec::new(),
+        organization_id: organization_id.to_string(),
+        created_by: "system".to_string(),
+        created_at: now,
+        updated_at: None,
+    }
+}
+
+/// Activity for a period
+pub fn activity(organization_id: &str, period: &TermPeriod, now: DateTime<Utc>) -> Activity {
+    let (activity_type, color, highlight_color, tag) = match period.kind {
+        TermPeriodKind::Term => (ActivityType::Other, "#4F81BD", "#385D8A", "term"),
+        TermPeriodKind::Holiday => (ActivityType::Holiday, "#9BBB59", "#71893F", "school-holiday"),
+        TermPeriodKind::Exam => (ActivityType::Deadline, "#C0504D", "#8C3836", "exam"),
+    };
+    let midnight = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    
+    Activity {
+        id: activity_id(period),
+        title: period.name.trim().to_string(),
+        start_date: midnight(period.start),
+        end_date: midnight(period.end),
+        activity_type,
+        color: color.to_string(),
+        highlight_color: highlight_color.to_string(),
+        description: None,
+        description_format: Default::default(),
+        links: Vec::new(),
+        tags: vec![tag.to_string()],
+        priority: 0,
+        scope: TERMS_LAYER_ID.to_string(),
+        scope_id: TERMS_LAYER_ID.to_string(),
+        organization_id: organization_id.to_string(),
+        created_by: Some("system".to_string()),
+        created_at: Some(now),
+        updated_at: Some(now),
+        edit_count: 0,
+        approval_status: Default::default(),
+        approval_review: None,
+        split_from: None,
+        merged_from: Vec::new(),
+        deleted_at: None,
+        recurrence: None,
+        created_by_name: None,
+    }
+}
+
+/// Whether a stored activity still shows the period as generated
+fn is_current(stored: &Activity, generated: &Activity) -> bool {
+    stored.title == generated.title
+        && stored.start_date == generated.start_date
+        && stored.end_date == generated.end_date
+        && stored.activity_type == generated.activity_type
+        && stored.color == generated.color
+        && stored.highlight_color == generated.highlight_color
+        && stored.tags == generated.tags
+}
+
+/// Generates the terms layer of organizations from their term structure
+pub struct TermPopulator {
+    layers: Arc<dyn LayerStorage>,
+    activities: Arc<dyn ActivityStorage>,
+    policies: Arc<dyn PolicyStorage>,
+    clock: Arc<dyn Clock>,
+}
+
+impl TermPopulator {
+    pub fn new(layers: Arc<dyn LayerStorage>, activities: Arc<dyn ActivityStorage>, policies: Arc<dyn PolicyStorage>) -> Self {
+        Self { layers, activities, policies, clock: Arc::new(SystemClock) }
+    }
+    
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Run for the organizations every `interval` until the task is dropped
+    #[cfg(feature = "server")]
+    pub async fn run_every(self: Arc<Self>, organization_ids: Vec<String>, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for organization_id in &organization_ids {
+                match self.run(organization_id, self.clock.now()).await {
+                    Ok(result) => tracing::debug!(organization_id = %organization_id, ?result, "Synced terms layer"),
+                    Err(e) => tracing::warn!(organization_id = %organization_id, error = %e, "Terms layer sync failed"),
+                }
+            }
+        }
+    }
+    
+    /// Bring the organization's terms layer in line with its term structure
+    pub async fn run(&self, organization_id: &str, now: DateTime<Utc>) -> Result<TermSyncResult, StorageError> {
+        let terms = self.policies.get(organization_id).await?.terms;
+        let existing = match self.layers.get(organization_id, TERMS_LAYER_ID).await {
+            Ok(layer) => Some(layer),
+            Err(StorageError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let stored = self.activities.list_by_layers(organization_id, &[TERMS_LAYER_ID.to_string()], None).await?;
+        let mut result = TermSyncResult::default();
+        
+        let Some(terms) = terms else {
+            for activity in stored {
+                self.activities.delete(organization_id, &activity.id).await?;
+                result.deleted.push(activity.id);
+            }
+            if existing.is_some() {
+                self.layers.delete(organization_id, TERMS_LAYER_ID).await?;
+            }
+            return Ok(result);
+        };
+        
+        // Layers created before the ring was reserved move off the user layers' rings
+        match existing {
+            Some(mut layer) if layer.name != terms.layer_name || layer.ring_index != TERMS_RING_INDEX => {
+                layer.name = terms.layer_name.clone();
+                layer.ring_index = TERMS_RING_INDEX;
+                layer.updated_at = Some(now);
+                self.layers.update(layer).await?;
+            }
+            Some(_) => {}
+            None => {
+                self.layers.create(layer(organization_id, &terms, now)).await?;
+            }
+        }
+        
+        let generated: Vec<Activity> = terms.periods.iter().map(|p| activity(organization_id, p, now)).collect();
+        for activity in stored.iter().filter(|s| !generated.iter().any(|g| g.id == s.id)) {
+            self.activities.delete(organization_id, &activity.id).await?;
+            result.deleted.push(activity.id.clone());
+        }
+        for mut activity in generated {
+            match stored.iter().find(|s| s.id == activity.id) {
+                Some(current) if is_current(current, &activity) => {}
+                Some(current) => {
+                    activity.created_at = current.created_at;
+                    activity.edit_count = current.edit_count + 1;
+                    result.updated.push(self.activities.update(activity).await?.id);
+                }
+                None => result.created.push(self.activities.create(activity).await?.id),
+            }
+        }
+        
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory_storage::{MemoryActivityStorage, MemoryLayerStorage, MemoryPolicyStorage};
+    
+    fn period(name: &str, kind: TermPeriodKind, start: &str, end: &str) -> TermPeriod {
+        TermPeriod { name: name.to_string(), kind, start: start.parse().unwrap(), end: end.parse().unwrap() }
+    }
+    
+    fn terms(periods: Vec<TermPeriod>) -> TermStructure {
+        TermStructure { layer_name: "Skoleåret 2025/2026".to_string(), periods }
+    }
+    
+    #[test]
+    fn test_validate() {
+        let autumn = period("Autumn term", TermPeriodKind::Term, "2025-08-18", "2025-12-19");
+        let exams = period("Exams", TermPeriodKind::Exam, "2025-08-18", "2025-08-22");
+        assert_eq!(validate(&terms(vec![autumn.clone(), exams])), Ok(()));
+        assert_eq!(validate(&terms(vec![autumn.clone(), autumn.clone()])), Err(TermError::DuplicateStart("2025-08-18".parse().unwrap())));
+        assert_eq!(
+            validate(&terms(vec![period("Winter break", TermPeriodKind::Holiday, "2026-02-27", "2026-02-23")])),
+            Err(TermError::EndBeforeStart("Winter break".to_string()))
+        );
+        assert_eq!(validate(&terms(vec![period(" ", TermPeriodKind::Term, "2025-08-18", "2025-12-19")])), Err(TermError::EmptyName));
+        
+        let activity = activity("org-1", &autumn, Utc::now());
+        assert_eq!(activity.id, "term-term-2025-08-18");
+        assert_eq!(activity.end_date.to_rfc3339(), "2025-12-19T00:00:00+00:00");
+    }
+    
+    #[tokio::test]
+    async fn test_populator() {
+        let layers = Arc::new(MemoryLayerStorage::new());
+        let activities = Arc::new(MemoryActivityStorage::new());
+        let policies = Arc::new(MemoryPolicyStorage::new());
+        let populator = TermPopulator::new(layers.clone(), activities.clone(), policies.clone());
+        let set_terms = |terms: Option<TermStructure>| {
+            let policies = policies.clone();
+            async move {
+                let mut policy = policies.get("org-1").await.unwrap();
+                policy.terms = terms;
+                policies.upsert(policy).await.unwrap();
+            }
+        };
+        
+        set_terms(Some(terms(vec![
+            period("Autumn term", TermPeriodKind::Term, "2025-08-18", "2025-12-19"),
+            period("Autumn break", TermPeriodKind::Holiday, "2025-09-29", "2025-10-03"),
+        ]))).await;
+        let first = populator.run("org-1", Utc::now()).await.unwrap();
+        assert_eq!(first.created, ["term-term-2025-08-18", "term-holiday-2025-09-29"]);
+        assert_eq!(layers.get("org-1", TERMS_LAYER_ID).await.unwrap().layer_type, LayerType::Terms);
+        assert_eq!(populator.run("org-1", Utc::now()).await.unwrap(), TermSyncResult::default());
+        
+        // A layer on a user ring moves back to its own
+        let moved = Layer { ring_index: 0, ..layers.get("org-1", TERMS_LAYER_ID).await.unwrap() };
+        layers.update(moved).await.unwrap();
+        populator.run("org-1", Utc::now()).await.unwrap();
+        assert_eq!(layers.get("org-1", TERMS_LAYER_ID).await.unwrap().ring_index, TERMS_RING_INDEX);
+        
+        set_terms(Some(terms(vec![
+            period("Autumn term", TermPeriodKind::Term, "2025-08-18", "2025-12-18"),
+            period("Exams", TermPeriodKind::Exam, "2025-12-01", "2025-12-12"),
+        ]))).await;
+        let second = populator.run("org-1", Utc::now()).await.unwrap();
+        assert_eq!((second.created, second.updated, second.deleted), (
+            vec!["term-exam-2025-12-01".to_string()],
+            vec!["term-term-2025-08-18".to_string()],
+            vec!["term-holiday-2025-09-29".to_string()],
+        ));
+        
+        set_terms(None).await;
+        assert_eq!(populator.run("org-1", Utc::now()).await.unwrap().deleted.len(), 2);
+        assert!(layers.get("org-1", TERMS_LAYER_ID).await.is_err());
+        assert!(activities.list_by_layers("org-1", &[TERMS_LAYER_ID.to_string()], None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,364 @@
+//! # Storage Tracing
+//!
+//! [`TracedStorage`] wraps any storage backend and runs each trait method in
+//! a `storage` span with these fields:
+//!
+//! - `backend` - `memory`, `sqlite`, `table` or `cosmosdb`
+//! - `entity` - `share`, `activity`, `layer`, `activity_type`, `user_settings` or `audit`
+//! - `operation` - trait method name
+//! - `org` - organization ID (empty for short code and tombstone lookups)
+//! - `result` - `ok`, `not_found`, `already_exists`, `unauthorized`, `invalid`, `transient` or `error`
+//! - `latency_ms` - time spent in the backend
+//!
+//! Each call is also counted in [`StorageMetrics`], with the same labels
+//! (see [`crate::storage_metrics`]).
+//!
+//! [`Storage::new`] applies it to every backend, so Table Storage, Cosmos DB
+//! and the in-memory stores are instrumented the same way.
+
+use crate::models::*;
+use crate::storage::*;
+use crate::storage_metrics::StorageMetrics;
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing::Instrument;
+
+/// Storage backend whose calls are traced and counted
+pub struct TracedStorage<S: ?Sized> {
+    inner: Arc<S>,
+    metrics: Arc<StorageMetrics>,
+}
+
+impl<S: ?Sized> TracedStorage<S> {
+    pub fn new(inner: Arc<S>, metrics: Arc<StorageMetrics>) -> Self {
+        Self { inner, metrics }
+    }
+    
+    /// Run one storage call inside its span
+    async fn traced<T>(
+        &self,
+        entity: &'static str,
+        operation: &'static str,
+        org: Option<&str>,
+        call: impl Future<Output = Result<T, StorageError>>,
+    ) -> Result<T, StorageError> {
+        let backend = self.metrics.backend(entity);
+        let span = tracing::info_span!("storage", backend, entity, operation, org = Empty, result = Empty, latency_ms = Empty);
+        if let Some(org) = org {
+            span.record("org", org);
+        }
+        
+        let started = Instant::now();
+        let result = call.instrument(span.clone()).await;
+        let latency = started.elapsed();
+        span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+        span.record("result", outcome(&result));
+        self.metrics.record(entity, operation, outcome(&result), latency);
+        result
+    }
+}
+
+/// Result label of a storage call
+fn outcome<T>(result: &Result<T, StorageError>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(StorageError::NotFound(_)) => "not_found",
+        Err(StorageError::AlreadyExists(_)) => "already_exists",
+        Err(StorageError::Unauthorized(_)) => "unauthorized",
+        Err(StorageError::Validation(_)) => "invalid",
+        Err(StorageError::Transient(_)) => "transient",
+        Err(StorageError::Conflict(_)) => "conflict",
+        Err(StorageError::Storage(_) | StorageError::Serialization(_)) => "error",
+    }
+}
+
+#[async_trait]
+impl<S: ShareStorage + ?Sized> ShareStorage for TracedStorage<S> {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let org = share.organization_id.clone();
+        self.traced("share", "create", Some(&org), self.inner.create(share)).await
+    }
+    
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        self.traced("share", "get", Some(organization_id), self.inner.get(organization_id, share_id)).await
+    }
+    
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        self.traced("share", "get_by_short_code", None, self.inner.get_by_short_code(short_code)).await
+    }
+    
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let org = share.organization_id.clone();
+        self.traced("share", "update", Some(&org), self.inner.update(share)).await
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, share_id: &str) -> Result<(ShareLink, String), StorageError> {
+        self.traced("share", "get", Some(organization_id), self.inner.get_tagged(organization_id, share_id)).await
+    }
+    
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        let org = share.organization_id.clone();
+        self.traced("share", "update", Some(&org), self.inner.replace(share, etag)).await
+    }
+    
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.traced("share", "delete", Some(organization_id), self.inner.delete(organization_id, share_id)).await
+    }
+    
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        self.traced("share", "get_tombstone", None, self.inner.get_tombstone(short_code)).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        self.traced("share", "list", Some(organization_id), self.inner.list(organization_id, options)).await
+    }
+    
+    async fn list_by_layer(&self, organization_id: &str, layer_id: &str) -> Result<Vec<ShareLink>, StorageError> {
+        self.traced("share", "list", Some(organization_id), self.inner.list_by_layer(organization_id, layer_id)).await
+    }
+    
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        self.traced("share", "increment_views", Some(organization_id), self.inner.increment_views(organization_id, share_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: ActivityStorage + ?Sized> ActivityStorage for TracedStorage<S> {
+    async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+        let org = activity.organization_id.clone();
+        self.traced("activity", "create", Some(&org), self.inner.create(activity)).await
+    }
+    
+    async fn create_many(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let org = activities.first().map(|a| a.organization_id.clone());
+        self.traced("activity", "create_many", org.as_deref(), self.inner.create_many(activities)).await
+    }
+    
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        self.traced("activity", "get", Some(organization_id), self.inner.get(organization_id, activity_id)).await
+    }
+    
+    async fn get_tagged(&self, organization_id: &str, activity_id: &str) -> Result<(Activity, String), StorageError> {
+        self.traced("activity", "get", Some(organization_id), self.inner.get_tagged(organization_id, activity_id)).await
+    }
+    
+    async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+        let org = activity.organization_id.clone();
+        self.traced("activity", "update", Some(&org), self.inner.update(activity)).await
+    }
+    
+    async fn update_batch(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let org = activities.first().map(|a| a.organization_id.clone());
+        self.traced("activity", "update_batch", org.as_deref(), self.inner.update_batch(activities)).await
+    }
+    
+    async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+        self.traced("activity", "delete", Some(organization_id), self.inner.delete(organization_id, activity_id)).await
+    }
+    
+    async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+        self.traced("activity", "apply_changes", Some(organization_id), self.inner.apply_changes(organization_id, changes)).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<Activity>, StorageError> {
+        self.traced("activity", "list", Some(organization_id), self.inner.list(organization_id, options)).await
+    }
+    
+    async fn list_by_layers(
+        &self,
+        organization_id: &str,
+        layer_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<Activity>, StorageError> {
+        self.traced("activity", "list_by_layers", Some(organization_id), self.inner.list_by_layers(organization_id, layer_ids, year)).await
+    }
+}
+
+#[async_trait]
+impl<S: LayerStorage + ?Sized> LayerStorage for TracedStorage<S> {
+    async fn create(&self, layer: Layer) -> Result<Layer, StorageError> {
+        let org = layer.organization_id.clone();
+        self.traced("layer", "create", Some(&org), self.inner.create(layer)).await
+    }
+    
+    async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError> {
+        self.traced("layer", "get", Some(organization_id), self.inner.get(organization_id, layer_id)).await
+    }
+    
+    async fn update(&self, layer: Layer) -> Result<Layer, StorageError> {
+        let org = layer.organization_id.clone();
+        self.traced("layer", "update", Some(&org), self.inner.update(layer)).await
+    }
+    
+    async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError> {
+        self.traced("layer", "delete", Some(organization_id), self.inner.delete(organization_id, layer_id)).await
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError> {
+        self.traced("layer", "list", Some(organization_id), self.inner.list(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: ActivityTypeStorage + ?Sized> ActivityTypeStorage for TracedStorage<S> {
+    async fn upsert(&self, config: ActivityTypeConfig) -> Result<ActivityTypeConfig, StorageError> {
+        let org = config.organization_id.clone();
+        self.traced("activity_type", "upsert", Some(&org), self.inner.upsert(config)).await
+    }
+    
+    async fn get(&self, organization_id: &str, key: &str) -> Result<ActivityTypeConfig, StorageError> {
+        self.traced("activity_type", "get", Some(organization_id), self.inner.get(organization_id, key)).await
+    }
+    
+    async fn delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.traced("activity_type", "delete", Some(organization_id), self.inner.delete(organization_id, key)).await
+    }
+    
+    async fn force_delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.traced("activity_type", "force_delete", Some(organization_id), self.inner.force_delete(organization_id, key)).await
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<ActivityTypeConfig>, StorageError> {
+        self.traced("activity_type", "list", Some(organization_id), self.inner.list(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: UserSettingsStorage + ?Sized> UserSettingsStorage for TracedStorage<S> {
+    async fn get(&self, organization_id: &str, user_id: &str) -> Result<UserSettings, StorageError> {
+        self.traced("user_settings", "get", Some(organization_id), self.inner.get(organization_id, user_id)).await
+    }
+    
+    async fn upsert(&self, settings: UserSettings) -> Result<UserSettings, StorageError> {
+        let org = settings.organization_id.clone();
+        self.traced("user_settings", "upsert", Some(&org), self.inner.upsert(settings)).await
+    }
+    
+    async fn delete(&self, organization_id: &str, user_id: &str) -> Result<(), StorageError> {
+        self.traced("user_settings", "delete", Some(organization_id), self.inner.delete(organization_id, user_id)).await
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<UserSettings>, StorageError> {
+        self.traced("user_settings", "list", Some(organization_id), self.inner.list(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: PolicyStorage + ?Sized> PolicyStorage for TracedStorage<S> {
+    async fn get(&self, organization_id: &str) -> Result<OrganizationPolicy, StorageError> {
+        self.traced("policy", "get", Some(organization_id), self.inner.get(organization_id)).await
+    }
+    
+    async fn upsert(&self, policy: OrganizationPolicy) -> Result<OrganizationPolicy, StorageError> {
+        let org = policy.organization_id.clone();
+        self.traced("policy", "upsert", Some(&org), self.inner.upsert(policy)).await
+    }
+    
+    async fn delete(&self, organization_id: &str) -> Result<(), StorageError> {
+        self.traced("policy", "delete", Some(organization_id), self.inner.delete(organization_id)).await
+    }
+}
+
+#[async_trait]
+impl<S: AuditStorage + ?Sized> AuditStorage for TracedStorage<S> {
+    async fn record(&self, entry: AuditEntry) -> Result<(), StorageError> {
+        let org = entry.organization_id.clone();
+        self.traced("audit", "record", Some(&org), self.inner.record(entry)).await
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<AuditEntry>, StorageError> {
+        self.traced("audit", "list", Some(organization_id), self.inner.list(organization_id, options)).await
+    }
+    
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        self.traced("audit", "delete_all", Some(organization_id), self.inner.delete_all(organization_id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory_storage::*;
+    use std::fmt::Debug;
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+    
+    /// Collects `name=value` pairs of every span field
+    #[derive(Default)]
+    struct Fields(Mutex<Vec<String>>);
+    
+    impl Visit for &Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.lock().unwrap().push(format!("{}={:?}", field.name(), value));
+        }
+        
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.lock().unwrap().push(format!("{}={}", field.name(), value));
+        }
+    }
+    
+    struct Recorder(Arc<Fields>);
+    
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            span.record(&mut &*self.0);
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, values: &Record<'_>) { values.record(&mut &*self.0) }
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+    
+    #[tokio::test]
+    async fn test_spans_and_metrics_carry_backend_entity_and_result() {
+        let fields = Arc::new(Fields::default());
+        let _guard = tracing::subscriber::set_default(Recorder(fields.clone()));
+        let storage = Storage::new(
+            Arc::new(MemoryShareStorage::new()),
+            Arc::new(MemoryActivityStorage::new()),
+            Arc::new(MemoryLayerStorage::new()),
+            Arc::new(MemoryActivityTypeStorage::new()),
+            Arc::new(MemoryUserSettingsStorage::new()),
+            Arc::new(MemoryAuditStorage::new()),
+            Arc::new(MemoryPolicyStorage::new()),
+        ).with_backend("sqlite", &["layer"]);
+        
+        assert!(storage.layers.get("org-1", "missing").await.is_err());
+        storage.activity_types.list("org-1").await.unwrap();
+        
+        let fields = fields.0.lock().unwrap();
+        for expected in ["backend=sqlite", "entity=layer", "operation=get", "org=org-1", "result=not_found"] {
+            assert!(fields.iter().any(|f| f == expected), "{} not in {:?}", expected, fields);
+        }
+        assert!(fields.iter().any(|f| f.starts_with("latency_ms=")));
+        
+        let metrics = storage.metrics.render_prometheus();
+        for expected in [
+            "arshjul_storage_operations_total{backend=\"sqlite\",table=\"layer\",operation=\"get\",result=\"not_found\"} 1",
+            "arshjul_storage_errors_total{backend=\"sqlite\",table=\"layer\",operation=\"get\"} 0",
+            "arshjul_storage_latency_seconds_count{backend=\"memory\",table=\"activity_type\",operation=\"list\"} 1",
+        ] {
+            assert!(metrics.contains(expected), "{} not in {}", expected, metrics);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! # Annual Wheel (Årshjul) Edge
+//!
+//! Read-only `GET /api/public/s/{shortCode}?k={key}` for edge runtimes.
+//! Share lookup, key check and projection come from
+//! [`arshjul_core::public_access`]; this crate only adds the key-value
+//! backends and request plumbing.
+//!
+//! ## Runtimes
+//!
+//! - **WASI** (`wasm32-wasip1`): the `arshjul-edge` binary speaks WAGI
+//!   (CGI over WASI) and reads documents from a preopened directory
+//!   ([`DirKv`], `KV_DIR`, default `/kv`).
+//! - **Cloudflare Workers**: implement [`PublicShareKv`] over a KV
+//!   namespace binding and call [`handle_request`] from the `fetch` handler.
+//!
+//! The KV documents are written by the origin; view counts are not
+//! tracked at the edge.
+
+use arshjul_core::http_cache::CachePolicy;
+use arshjul_core::public_access::{self, PublicAccessError, PublicShareKv};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::PathBuf;
+
+/// Route prefix served at the edge
+pub const ROUTE_PREFIX: &str = "/api/public/s/";
+
+/// Minimal HTTP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Key-value store backed by files (`{dir}/{segment}/{segment}.json`)
+pub struct DirKv {
+    root: PathBuf,
+}
+
+impl DirKv {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+    
+    /// File path for a key; `None` for keys that could escape the root
+    fn path_for(&self, key: &str) -> Option<PathBuf> {
+        let mut path = self.root.clone();
+        for segment in key.split(':') {
+            let safe = !segment.is_empty()
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !safe {
+                return None;
+            }
+            path.push(segment);
+        }
+        Some(path.with_extension("json"))
+    }
+}
+
+#[async_trait(?Send)]
+impl PublicShareKv for DirKv {
+    async fn get(&self, key: &str) -> Result<Option<String>, PublicAccessError> {
+        let Some(path) = self.path_for(key) else {
+            return Ok(None);
+        };
+        match std::fs::read_to_string(path) {
+            Ok(raw) => Ok(Some(raw)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(PublicAccessError::Storage(e.to_string())),
+        }
+    }
+}
+
+/// Value of a query string parameter
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v)
+}
+
+fn json_response(status: u16, policy: CachePolicy, body: String) -> EdgeResponse {
+    EdgeResponse {
+        status,
+        headers: vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Cache-Control".to_string(), policy.header_value()),
+        ],
+        body,
+    }
+}
+
+/// Handle a request for the public share route
+pub async fn handle_request(kv: &dyn PublicShareKv, method: &str, path: &str, query: &str) -> EdgeResponse {
+    let Some(short_code) = path.strip_prefix(ROUTE_PREFIX).filter(|c| !c.contains('/')) else {
+        return json_response(404, CachePolicy::NoStore, r#"{"code":"NOT_FOUND","message":"Not found"}"#.to_string());
+    };
+    if method != "GET" && method != "HEAD" {
+        return json_response(405, CachePolicy::NoStore, r#"{"code":"METHOD_NOT_ALLOWED","message":"Read-only endpoint"}"#.to_string());
+    }
+    
+    let key = query_param(query, "k").unwrap_or_default();
+    let (body, policy) = match public_access::serve_from_kv(kv, short_code, key, Utc::now()).await {
+        Ok(response) => (response, CachePolicy::for_request(method, path)),
+        Err(PublicAccessError::Storage(e)) => {
+            return json_response(500, CachePolicy::NoStore, serde_json::json!({
+                "code": "INTERNAL_ERROR",
+                "message": e,
+            }).to_string());
+        }
+        Err(e) => (public_access::denied(&e), CachePolicy::NoStore),
+    };
+    
+    // Same contract as the origin: denials are 200 with `success: false`
+    json_response(200, policy, serde_json::to_string(&body).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_dir_kv_rejects_traversal() {
+        let kv = DirKv::new("/kv");
+        assert_eq!(kv.path_for("share:AbCd1234"), Some(PathBuf::from("/kv/share/AbCd1234.json")));
+        assert_eq!(kv.path_for("share:../secrets"), None);
+        assert_eq!(kv.path_for("share:"), None);
+    }
+    
+    #[test]
+    fn test_routing() {
+        let kv = DirKv::new("/nonexistent");
+        let run = |method, path, query| futures::executor::block_on(handle_request(&kv, method, path, query));
+        
+        assert_eq!(run("GET", "/api/shares", "").status, 404);
+        assert_eq!(run("POST", "/api/public/s/AbCd1234", "").status, 405);
+        
+        let missing = run("GET", "/api/public/s/AbCd1234", &format!("k={}", "a".repeat(64)));
+        assert_eq!(missing.status, 200);
+        assert!(missing.body.contains("Share not found"));
+        assert!(missing.headers.contains(&("Cache-Control".to_string(), "no-store".to_string())));
+    }
+}
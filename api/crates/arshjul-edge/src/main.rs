@@ -0,0 +1,26 @@
+//! # WAGI Entry Point
+//!
+//! One request per process: the host passes the request in CGI environment
+//! variables (`REQUEST_METHOD`, `PATH_INFO`, `QUERY_STRING`) and reads the
+//! CGI response from stdout.
+
+use arshjul_edge::{handle_request, DirKv};
+use std::env;
+
+fn main() {
+    let method = env::var("REQUEST_METHOD").unwrap_or_else(|_| "GET".to_string());
+    let path = env::var("PATH_INFO").unwrap_or_default();
+    let query = env::var("QUERY_STRING").unwrap_or_default();
+    let kv = DirKv::new(env::var("KV_DIR").unwrap_or_else(|_| "/kv".to_string()));
+    
+    let response = futures::executor::block_on(handle_request(&kv, &method, &path, &query));
+    
+    println!("Status: {}", response.status);
+    for (name, value) in &response.headers {
+        println!("{}: {}", name, value);
+    }
+    println!();
+    if method != "HEAD" {
+        print!("{}", response.body);
+    }
+}
@@ -0,0 +1,806 @@
+//! # Configuration Module
+//!
+//! Environment-based configuration for the Annual Wheel API.
+//! Supports multiple storage backends and authentication settings.
+//!
+//! ## Environment Variables
+//!
+//! ### Storage Configuration
+//!
+//! **Storage Type Selection:**
+//! - `STORAGE_TYPE` - Storage backend: `memory`, `sqlite`, `table`, or `cosmosdb` (default: `memory`)
+//!
+//! **SQLite (`sqlite` feature, local development):**
+//! - `SQLITE_PATH` - Database file, created if missing (default: `arshjul.db`)
+//!
+//! **Azure Table Storage:**
+//! - `AZURE_STORAGE_ACCOUNT` - Storage account name
+//! - `AZURE_STORAGE_ACCESS_KEY` - Storage account access key
+//!
+//! **Azure Cosmos DB:**
+//! - `COSMOS_CONNECTION_STRING` - Full Cosmos DB connection string
+//! - `COSMOS_DATABASE` - Database name (default: `arshjul`)
+//!
+//! **Migration between Table Storage and Cosmos DB (`azure` feature):**
+//! - `DUAL_WRITE` - Entity types written to both, with the one read from, e.g. `share=table,activity=cosmosdb`; entities are `share`, `activity`, `layer`, `activity_type` and `user_settings`. Needs both backends configured and `STORAGE_TYPE` `table` or `cosmosdb`, which keeps every other entity type
+//! - `DUAL_WRITE_COMPARE_EVERY` - Compare one in this many point reads of dual-written entities with the secondary (default: 100; 1 compares every read)
+//!
+//! ### Authentication
+//! - `AZURE_CLIENT_ID` - Azure AD app registration client ID
+//! - `AZURE_TENANT_ID` - Azure AD tenant ID (default: `common`)
+//! - `AZURE_ADDITIONAL_AUDIENCES` - Comma-separated token audiences accepted besides the client ID, e.g. the app ID URI `api://{domain}/{clientId}` of a Teams app (`api://{clientId}` is always accepted)
+//!
+//! ### Live Updates (optional)
+//! - `AZURE_SIGNALR_CONNECTION_STRING` - Azure SignalR Service connection string
+//! - `SIGNALR_HUB` - Hub name (default: `arshjul`)
+//!
+//! ### Client Identity
+//! - `TRUSTED_PROXIES` - Comma-separated CIDR ranges of proxies allowed to set `X-Forwarded-For`
+//! - `TRUST_AZURE_FRONT_DOOR` - Trust `X-Azure-ClientIP` from Azure Front Door (default: `false`)
+//! - `AZURE_FRONT_DOOR_ID` - Expected `X-Azure-FDID` header value (optional)
+//!
+//! ### Share Snapshots
+//! - `SNAPSHOT_CONTAINER_SAS_URL` - Blob container SAS URL snapshots are written to (optional, `azure` feature)
+//! - `SNAPSHOT_PUBLIC_BASE_URL` - CDN URL serving that container; enables redirects
+//!
+//! ### Organization Snapshots
+//! - `ORG_SNAPSHOT_CONTAINER_SAS_URL` - SAS URL of a private blob container (read, write and list permissions) keeping point-in-time organization snapshots; `/api/admin/snapshots` is disabled when unset (`azure` feature)
+//!
+//! ### Background Operations
+//! - `BULK_MAX_RUNNING` - Background imports, reassignments, exports and purges running at once on an instance (default: `2`, at most `16`)
+//! - `BULK_MAX_PER_ORG` - Of which one organization's (default: `1`, at most `16`); also per instance, so each instance may run this many for an organization
+//! - `OPERATIONS_CONTAINER_SAS_URL` - SAS URL of a private blob container (read, write, list and delete permissions) keeping operation progress and results, so a recycled host resumes them; kept in memory when unset (`azure` feature)
+//! - `OPERATION_RESULT_TTL_DAYS` - Days finished operations and their results are kept before the daily cleanup (default: `7`, `1`-`90`)
+//!
+//! ### Cache Invalidation
+//! - `FRONT_DOOR_ENDPOINT_RESOURCE_ID` - Front Door endpoint resource ID to purge on share changes (optional)
+//! - `REDIS_URL` - Redis connection URL holding cached share responses (optional, `redis` feature)
+//! - `REDIS_KEY_PREFIX` - Key prefix of cached share responses (default: `arshjul:share:`); public request nonces are kept under `{REDIS_KEY_PREFIX}nonce:`
+//! - `CHANGE_FEED_INTERVAL_SECONDS` - Poll the Cosmos DB change feed this often and purge caches for writes made by other instances (default: `0`, disabled; at most `3600`)
+//!
+//! ### Scale-out
+//! - `INSTANCE_COUNT` - Instances the API runs on (default: `1`). Above 1, `REDIS_URL` is required (`redis` feature): it holds the public request nonces under `{REDIS_KEY_PREFIX}nonce:`, which would otherwise only be redeemable on the instance that issued them, and the activity edit locks under `{REDIS_KEY_PREFIX}lock:`, which would otherwise only exclude editors on the same instance
+//!
+//! ### Share Cache
+//! - `SHARE_CACHE_TTL_SECONDS` - Cache share lookups by short code this long (default: `0`, disabled); in Redis under `{REDIS_KEY_PREFIX}record:` when `REDIS_URL` is set, in process otherwise
+//!
+//! ### Activity Search
+//! - `AZURE_SEARCH_ENDPOINT` - Azure AI Search service URL; enables indexed search (optional)
+//! - `AZURE_SEARCH_API_KEY` - Admin key (Managed Identity when unset)
+//! - `AZURE_SEARCH_INDEX` - Index name (default: `activities`)
+//!
+//! ### Audit Export
+//! - `LOGS_INGESTION_ENDPOINT` - Data collection endpoint; forwards audit entries to Log Analytics (optional)
+//! - `LOGS_INGESTION_RULE_ID` - Immutable ID of the data collection rule (`dcr-...`)
+//! - `LOGS_INGESTION_STREAM` - Stream declared in the rule (default: `Custom-ArshjulAudit_CL`)
+//! - `AUDIT_EXPORT_INTERVAL_SECONDS` - How often queued entries are sent (default: `30`)
+//!
+//! ### Directory (`graph` feature)
+//! - `GRAPH_CLIENT_SECRET` - Client secret of the `AZURE_CLIENT_ID` app registration; enables display names and the people picker (optional)
+//! - `DIRECTORY_CACHE_TTL_MINUTES` - How long resolved names are cached (default: `60`)
+//!
+//! ### Service Level Objectives
+//! - `METRICS_TOKEN` - Bearer token for `GET /api/metrics`; the endpoint is disabled when unset
+//! - `SLO_LATENCY_THRESHOLD_MS` - Public access requests slower than this count against the latency SLO (default: `500`)
+//! - `SLO_ALERT_WEBHOOK_URL` - HTTPS webhook receiving error budget burn and partition size alerts (optional, `webhooks` feature)
+//! - `MAIL_WEBHOOK_URL` - HTTPS webhook delivering digest and reminder emails (optional, `webhooks` feature)
+//! - `NOTIFICATION_ORGANIZATIONS` - Comma-separated organization IDs that get digests, activity reminders and share expiry reminders once a day; requires `MAIL_WEBHOOK_URL`, and must be set on one instance only
+//! - `TERMS_ORGANIZATIONS` - Comma-separated organization IDs whose terms layer is regenerated from their term structure once a day, undoing writes that bypassed the API (optional, one instance only)
+//! - `SHARE_TRAFFIC_MONITOR` - Count public share views by day and country, and flag shares whose traffic suddenly changes once a day (default: `false`). Counts are kept in memory, so enable it on a single-instance deployment only
+//! - `PARTITION_MONITOR_INTERVAL_MINUTES` - Sample the entity count and size of every organization's Table Storage partitions this often (default: `0`, disabled; at most `10080`). Scans every table, so enable it on one instance only, e.g. `1440`
+//! - `PARTITION_ENTITY_LIMIT` / `PARTITION_SIZE_LIMIT_MB` - Partition size at which an organization should move to another backend; alerts start at 80% (default: `100000` entities / `512` MB)
+//!
+//! ### Privacy
+//! - `PSEUDONYMIZATION_KEY` - Master key (min. 32 characters) for hashing user IDs in audit/analytics records
+//! - `SHARE_KEY_ENCRYPTION_KEYS` - Comma-separated `id=base64` AES-256 keys sealing share keys at rest in Table Storage, the first one sealing (a Key Vault reference in Azure); plaintext when unset
+//!
+//! ### Application Settings
+//! - `BASE_URL` - Base URL for share links (default: `http://localhost:7071`)
+//! - `LINK_DOMAIN_DENYLIST` - Comma-separated domains activity links may not point to (subdomains included)
+//! - `SHARE_REPORT_THRESHOLD` - Abuse reports before a public share is deactivated (default: `3`, `0` disables)
+//! - `SHARE_VIEW_STORAGE_CALLS` / `SHARE_VIEW_STORAGE_MS` - Storage calls and time of one share view; past them view counts and then activities are left out, and the response is marked `partial` (default: `12` calls / `3000` ms)
+//! - `SIGNING_KEY_VAULT_URL` - Azure Key Vault (`https://{vault}.vault.azure.net`) keeping the versioned signing keys of renewal links, preview links and federation tokens, published at `GET /.well-known/jwks.json`
+//! - `SIGNING_KEY_SECRET` - Secret (min. 32 characters) one signing key is derived from when there is no vault; never rotates. One of the two is required unless `STORAGE_TYPE=memory`, which falls back to per-instance keys
+//! - `SIGNING_KEY_ROTATION_DAYS` - Days between signing key rotations (default: `90`, `7`-`365`)
+//! - `EXPIRED_SHARE_RETENTION_DAYS` - Days expired shares are kept before the daily cleanup deletes them from Table Storage (default: `30`, `0`-`3650`)
+//! - `DELETED_ITEM_RETENTION_DAYS` - Days deleted shares and activities stay restorable in the recycle bin before the daily cleanup (default: `30`, `0`-`3650`)
+//! - `STORAGE_RETRY_MAX_ATTEMPTS` - Calls per Table Storage or Cosmos DB request when throttled (429) or busy (503), including the first (default: `4`, `1`-`10`; `1` disables retries)
+//! - `STORAGE_RETRY_BASE_DELAY_MS` - Backoff before the first retry, doubled for each further one (default: `200`, `1`-`60000`)
+//! - `STORAGE_RETRY_MAX_DELAY_MS` - Longest backoff between retries; waits are jittered up to it (default: `5000`, at least the base delay, at most `60000`)
+//! - `SITEMAP_ORGANIZATIONS` - Comma-separated organization IDs whose opted-in public shares `GET /sitemap.xml` lists; the sitemap is disabled when unset
+//! - `RECORD_CONTRACTS_DIR` - Directory sanitized request/response pairs are recorded to as contract fixtures (optional, never in production)
+//! - `RUST_LOG` - Log level (default: `info`); admins can raise it for their organization via `PUT /api/admin/logging`
+
+use arshjul_core::client_info::TrustedProxyConfig;
+use arshjul_core::audit_export::DEFAULT_FLUSH_INTERVAL_SECONDS;
+use arshjul_core::directory::DEFAULT_CACHE_TTL_MINUTES;
+use arshjul_core::dual_write::DEFAULT_COMPARE_EVERY;
+use arshjul_core::pseudonym::MIN_KEY_LEN;
+use arshjul_core::slo::DEFAULT_LATENCY_THRESHOLD_MS;
+use arshjul_core::partition_monitor::{self, PartitionLimits};
+use arshjul_core::signing_keys::DEFAULT_ROTATION_DAYS;
+use arshjul_core::storage_budget::{self, StorageBudget};
+use arshjul_core::operations::{self, BulkLimits};
+use arshjul_core::share_key_cipher::ShareKeyCipher;
+use arshjul_core::storage_retry::{RetryPolicy, DEFAULT_BASE_DELAY, DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_DELAY};
+use arshjul_core::recycle_bin;
+use arshjul_core::share_cleanup::DEFAULT_RETENTION_DAYS;
+#[cfg(feature = "azure")]
+use arshjul_azure::signalr::SignalRConfig;
+use std::env;
+use thiserror::Error;
+
+/// Configuration errors
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Missing required environment variable: {0}")]
+    MissingEnvVar(String),
+    
+    #[error("Invalid storage type: {0}. Valid options: memory, sqlite, table, cosmosdb")]
+    InvalidStorageType(String),
+    
+    #[error("Configuration error: {0}")]
+    Invalid(String),
+}
+
+/// Storage backend type
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum StorageType {
+    /// In-memory storage (development only)
+    #[default]
+    Memory,
+    /// SQLite file (local development)
+    Sqlite,
+    /// Azure Table Storage
+    TableStorage,
+    /// Azure Cosmos DB
+    CosmosDb,
+}
+
+impl StorageType {
+    /// Parse from string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, ConfigError> {
+        match s.to_lowercase().as_str() {
+            "memory" | "mem" | "inmemory" | "in-memory" => Ok(StorageType::Memory),
+            "sqlite" => Ok(StorageType::Sqlite),
+            "table" | "tables" | "tablestorage" | "table-storage" | "azuretable" => Ok(StorageType::TableStorage),
+            "cosmos" | "cosmosdb" | "cosmos-db" => Ok(StorageType::CosmosDb),
+            _ => Err(ConfigError::InvalidStorageType(s.to_string())),
+        }
+    }
+}
+
+/// Entity types that can be written to both Table Storage and Cosmos DB
+pub const DUAL_WRITE_ENTITIES: [&str; 5] = ["share", "activity", "layer", "activity_type", "user_settings"];
+
+/// Parse `DUAL_WRITE`: `entity=primary` pairs, e.g. `share=table,activity=cosmosdb`
+pub fn parse_dual_write(value: &str) -> Result<Vec<(&'static str, StorageType)>, ConfigError> {
+    let mut pairs: Vec<(&'static str, StorageType)> = Vec::new();
+    for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let invalid = || ConfigError::Invalid(format!(
+            "DUAL_WRITE entries must be entity=table|cosmosdb with entity one of {:?}, got '{}'", DUAL_WRITE_ENTITIES, pair
+        ));
+        let (entity, primary) = pair.split_once('=').ok_or_else(invalid)?;
+        let entity = DUAL_WRITE_ENTITIES.into_iter().find(|e| *e == entity.trim()).ok_or_else(invalid)?;
+        let primary = StorageType::from_str(primary.trim()).ok()
+            .filter(|p| matches!(p, StorageType::TableStorage | StorageType::CosmosDb))
+            .ok_or_else(invalid)?;
+        if pairs.iter().any(|(e, _)| *e == entity) {
+            return Err(ConfigError::Invalid(format!("DUAL_WRITE lists {} twice", entity)));
+        }
+        pairs.push((entity, primary));
+    }
+    Ok(pairs)
+}
+
+/// Azure Table Storage configuration
+#[derive(Debug, Clone)]
+pub struct TableStorageConfig {
+    /// Storage account name
+    pub account_name: String,
+    /// Storage account access key (optional - use Managed Identity if not provided)
+    pub access_key: Option<String>,
+}
+
+/// Azure Cosmos DB configuration
+#[derive(Debug, Clone)]
+pub struct CosmosDbConfig {
+    /// Cosmos DB endpoint URL (e.g., "https://myaccount.documents.azure.com")
+    pub endpoint: String,
+    /// Database name
+    pub database_name: String,
+    /// Primary key (optional - use Managed Identity if not provided)
+    pub primary_key: Option<String>,
+}
+
+/// Authentication configuration
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    /// Azure AD client ID (audience)
+    pub client_id: String,
+    /// Azure AD tenant ID
+    pub tenant_id: String,
+    /// Audiences accepted besides the client ID
+    pub additional_audiences: Vec<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            client_id: String::new(),
+            tenant_id: "common".to_string(),
+            additional_audiences: Vec::new(),
+        }
+    }
+}
+
+/// Application configuration
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// Storage type to use
+    pub storage_type: StorageType,
+    /// Table Storage configuration (when storage_type is TableStorage)
+    pub table_storage: Option<TableStorageConfig>,
+    /// Cosmos DB configuration (when storage_type is CosmosDb)
+    pub cosmos_db: Option<CosmosDbConfig>,
+    /// Entity types written to both Table Storage and Cosmos DB, with the one read from
+    pub dual_write: Vec<(&'static str, StorageType)>,
+    /// Point reads of dual-written entities per comparison with the secondary
+    pub dual_write_compare_every: u64,
+    /// SQLite database file (when storage_type is Sqlite)
+    pub sqlite_path: Option<String>,
+    /// Authentication configuration
+    pub auth: AuthConfig,
+    /// Base URL for share links
+    pub base_url: String,
+    /// Azure SignalR Service for live updates (disabled when not configured)
+    #[cfg(feature = "azure")]
+    pub signalr: Option<SignalRConfig>,
+    /// Abuse reports before a public share is deactivated automatically (0 = never)
+    pub share_report_threshold: u32,
+    /// Storage calls and time of one share view before it degrades
+    pub storage_budget: StorageBudget,
+    /// Proxy headers trusted for client IP resolution
+    pub trusted_proxies: TrustedProxyConfig,
+    /// Master key for pseudonymizing user IDs (raw IDs are stored when unset)
+    pub pseudonymization_key: Option<String>,
+    /// Blob container SAS URL for share snapshots
+    pub snapshot_container_sas_url: Option<String>,
+    /// CDN base URL for share snapshots
+    pub snapshot_public_base_url: Option<String>,
+    /// Blob container SAS URL for organization snapshots
+    pub org_snapshot_container_sas_url: Option<String>,
+    /// Background operations running at once
+    pub bulk_limits: BulkLimits,
+    /// Blob container SAS URL for background operation progress
+    pub operations_container_sas_url: Option<String>,
+    /// Days finished background operations are kept
+    pub operation_result_ttl_days: i64,
+    /// Azure Front Door endpoint purged on share changes
+    pub front_door_endpoint_resource_id: Option<String>,
+    /// Redis holding cached share responses
+    pub redis_url: Option<String>,
+    /// Key prefix of cached share responses in Redis
+    pub redis_key_prefix: String,
+    /// Instances the API runs on
+    pub instance_count: u32,
+    /// Lifetime of cached share lookups (0 disables the cache)
+    pub share_cache_ttl_seconds: u64,
+    /// Seconds between change feed polls (0 disables the processor)
+    pub change_feed_interval_seconds: u64,
+    /// Azure AI Search service for activity search
+    pub search_endpoint: Option<String>,
+    /// Azure AI Search admin key
+    pub search_api_key: Option<String>,
+    /// Azure AI Search index name
+    pub search_index: String,
+    /// Logs Ingestion data collection endpoint for audit export
+    pub logs_ingestion_endpoint: Option<String>,
+    /// Data collection rule receiving audit entries
+    pub logs_ingestion_rule_id: Option<String>,
+    /// Stream name in the data collection rule
+    pub logs_ingestion_stream: String,
+    /// Seconds between audit export flushes
+    pub audit_export_interval_seconds: u64,
+    /// Client secret for Microsoft Graph app-only access
+    pub graph_client_secret: Option<String>,
+    /// Lifetime of cached directory lookups
+    pub directory_cache_ttl_minutes: i64,
+    /// Domains activity links may not point to
+    pub link_domain_denylist: Vec<String>,
+    /// Where handler exchanges are recorded as contract fixtures
+    pub record_contracts_dir: Option<String>,
+    /// Bearer token protecting the metrics endpoint
+    pub metrics_token: Option<String>,
+    /// Keys sealing share keys at rest, `id=base64` pairs
+    pub share_key_encryption_keys: Option<String>,
+    /// Key Vault keeping the signing keys
+    pub signing_key_vault_url: Option<String>,
+    /// Secret one signing key is derived from when there is no vault
+    pub signing_key_secret: Option<String>,
+    /// Days between signing key rotations
+    pub signing_key_rotation_days: i64,
+    /// Days expired shares are kept before cleanup deletes them
+    pub expired_share_retention_days: i64,
+    /// Days deleted shares and activities stay in the recycle bin
+    pub deleted_item_retention_days: i64,
+    /// Backoff for throttled Table Storage and Cosmos DB requests
+    pub storage_retry: RetryPolicy,
+    /// Organizations listed in the share sitemap
+    pub sitemap_organizations: Vec<String>,
+    /// Latency threshold of the public access SLO
+    pub slo_latency_threshold_ms: u64,
+    /// Webhook receiving error budget burn and partition size alerts
+    pub slo_alert_webhook_url: Option<String>,
+    /// Webhook delivering digest and reminder emails
+    pub mail_webhook_url: Option<String>,
+    /// Organizations the daily notification run mails
+    pub notification_organizations: Vec<String>,
+    /// Organizations whose terms layer is regenerated daily
+    pub terms_organizations: Vec<String>,
+    /// Count share views and check them for unusual traffic daily
+    pub share_traffic_monitor: bool,
+    /// Minutes between partition size samples (0 disables the monitor)
+    pub partition_monitor_interval_minutes: u64,
+    /// Partition size that raises alerts
+    pub partition_limits: PartitionLimits,
+}
+
+impl AppConfig {
+    /// Load configuration from environment variables
+    pub fn from_env() -> Result<Self, ConfigError> {
+        // Determine storage type
+        let storage_type = env::var("STORAGE_TYPE")
+            .map(|s| StorageType::from_str(&s))
+            .unwrap_or(Ok(StorageType::Memory))?;
+        
+        // Entity types written to both Azure backends need both configured
+        let dual_write = env::var("DUAL_WRITE").map(|v| parse_dual_write(&v)).unwrap_or(Ok(Vec::new()))?;
+        if !dual_write.is_empty() && !matches!(storage_type, StorageType::TableStorage | StorageType::CosmosDb) {
+            return Err(ConfigError::Invalid("DUAL_WRITE requires STORAGE_TYPE table or cosmosdb".to_string()));
+        }
+        let dual_write_compare_every = match env::var("DUAL_WRITE_COMPARE_EVERY") {
+            Ok(v) => v.parse().ok().filter(|n| *n > 0).ok_or_else(|| ConfigError::Invalid(
+                format!("DUAL_WRITE_COMPARE_EVERY must be a positive integer, got '{}'", v)
+            ))?,
+            Err(_) => DEFAULT_COMPARE_EVERY,
+        };
+        
+        // Load storage-specific configuration
+        let table_storage = if storage_type == StorageType::TableStorage || !dual_write.is_empty() {
+            let account_name = env::var("AZURE_STORAGE_ACCOUNT")
+                .map_err(|_| ConfigError::MissingEnvVar("AZURE_STORAGE_ACCOUNT".to_string()))?;
+            // Access key is now optional - prefer Managed Identity
+            let access_key = env::var("AZURE_STORAGE_ACCESS_KEY").ok();
+            
+            if access_key.is_none() {
+                tracing::info!("No AZURE_STORAGE_ACCESS_KEY found - will use Managed Identity for Table Storage");
+            }
+            
+            Some(TableStorageConfig { account_name, access_key })
+        } else {
+            None
+        };
+        
+        let cosmos_db = if storage_type == StorageType::CosmosDb || !dual_write.is_empty() {
+            let endpoint = env::var("COSMOS_ENDPOINT")
+                .map_err(|_| ConfigError::MissingEnvVar("COSMOS_ENDPOINT".to_string()))?;
+            let database_name = env::var("COSMOS_DATABASE")
+                .unwrap_or_else(|_| "arshjul".to_string());
+            // Primary key is optional - prefer Managed Identity
+            let primary_key = env::var("COSMOS_PRIMARY_KEY").ok();
+            
+            if primary_key.is_none() {
+                tracing::info!("No COSMOS_PRIMARY_KEY found - will use Managed Identity for Cosmos DB");
+            }
+            
+            Some(CosmosDbConfig { endpoint, database_name, primary_key })
+        } else {
+            None
+        };
+        
+        let sqlite_path = (storage_type == StorageType::Sqlite).then(|| env::var("SQLITE_PATH")
+            .unwrap_or_else(|_| "arshjul.db".to_string()));
+        
+        // Load auth configuration
+        let auth = AuthConfig {
+            client_id: env::var("AZURE_CLIENT_ID")
+                .unwrap_or_else(|_| String::new()),
+            tenant_id: env::var("AZURE_TENANT_ID")
+                .unwrap_or_else(|_| "common".to_string()),
+            additional_audiences: env::var("AZURE_ADDITIONAL_AUDIENCES")
+                .map(|list| list.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect())
+                .unwrap_or_default(),
+        };
+        
+        // Load app configuration
+        let base_url = env::var("BASE_URL")
+            .unwrap_or_else(|_| "http://localhost:7071".to_string());
+        
+        // Live updates are optional
+        #[cfg(feature = "azure")]
+        let signalr = match env::var("AZURE_SIGNALR_CONNECTION_STRING") {
+            Ok(connection_string) => {
+                let hub = env::var("SIGNALR_HUB")
+                    .unwrap_or_else(|_| "arshjul".to_string());
+                Some(SignalRConfig::from_connection_string(&connection_string, &hub).ok_or_else(|| ConfigError::Invalid(
+                    "AZURE_SIGNALR_CONNECTION_STRING must contain Endpoint and AccessKey".to_string()
+                ))?)
+            }
+            Err(_) => None,
+        };
+        
+        let share_report_threshold = match env::var("SHARE_REPORT_THRESHOLD") {
+            Ok(v) => v.parse().map_err(|_| ConfigError::Invalid(
+                format!("SHARE_REPORT_THRESHOLD must be a non-negative integer, got '{}'", v)
+            ))?,
+            Err(_) => 3,
+        };
+        
+        let budget_limit = |name: &str, default: u64| match env::var(name) {
+            Ok(v) => v.parse().ok().filter(|n| (1..=60_000).contains(n)).ok_or_else(|| ConfigError::Invalid(
+                format!("{} must be between 1 and 60000, got '{}'", name, v)
+            )),
+            Err(_) => Ok(default),
+        };
+        let storage_budget = StorageBudget {
+            max_calls: budget_limit("SHARE_VIEW_STORAGE_CALLS", storage_budget::DEFAULT_MAX_CALLS as u64)? as u32,
+            max_duration: std::time::Duration::from_millis(budget_limit("SHARE_VIEW_STORAGE_MS", storage_budget::DEFAULT_MAX_DURATION_MS)?),
+        };
+        
+        let bulk_limit = |name: &str, default: usize| match env::var(name) {
+            Ok(v) => v.parse().ok().filter(|n| (1..=16).contains(n)).ok_or_else(|| ConfigError::Invalid(
+                format!("{} must be between 1 and 16, got '{}'", name, v)
+            )),
+            Err(_) => Ok(default),
+        };
+        let bulk_limits = BulkLimits {
+            max_running: bulk_limit("BULK_MAX_RUNNING", operations::DEFAULT_MAX_RUNNING)?,
+            max_per_org: bulk_limit("BULK_MAX_PER_ORG", operations::DEFAULT_MAX_PER_ORG)?,
+        };
+        
+        let instance_count = match env::var("INSTANCE_COUNT") {
+            Ok(v) => v.parse().ok().filter(|n| *n >= 1).ok_or_else(|| ConfigError::Invalid(
+                format!("INSTANCE_COUNT must be a positive integer, got '{}'", v)
+            ))?,
+            Err(_) => 1,
+        };
+        
+        let trusted_proxies = TrustedProxyConfig {
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .map(|list| TrustedProxyConfig::parse_proxies(&list))
+                .unwrap_or(Ok(Vec::new()))
+                .map_err(|e| ConfigError::Invalid(format!("TRUSTED_PROXIES: {}", e)))?,
+            trust_front_door: env::var("TRUST_AZURE_FRONT_DOOR")
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(false),
+            front_door_id: env::var("AZURE_FRONT_DOOR_ID").ok(),
+        };
+        
+        let directory_cache_ttl_minutes = match env::var("DIRECTORY_CACHE_TTL_MINUTES") {
+            Ok(v) => v.parse().ok().filter(|m| *m > 0).ok_or_else(|| ConfigError::Invalid(
+                format!("DIRECTORY_CACHE_TTL_MINUTES must be a positive integer, got '{}'", v)
+            ))?,
+            Err(_) => DEFAULT_CACHE_TTL_MINUTES,
+        };
+        
+        let share_cache_ttl_seconds = match env::var("SHARE_CACHE_TTL_SECONDS") {
+            Ok(v) => v.parse().ok().filter(|s| *s <= 86_400).ok_or_else(|| ConfigError::Invalid(
+                format!("SHARE_CACHE_TTL_SECONDS must be between 0 and 86400, got '{}'", v)
+            ))?,
+            Err(_) => 0,
+        };
+        
+        let change_feed_interval_seconds = match env::var("CHANGE_FEED_INTERVAL_SECONDS") {
+            Ok(v) => v.parse().ok().filter(|s| *s <= 3_600).ok_or_else(|| ConfigError::Invalid(
+                format!("CHANGE_FEED_INTERVAL_SECONDS must be between 0 and 3600, got '{}'", v)
+            ))?,
+            Err(_) => 0,
+        };
+        
+        let signing_key_rotation_days = match env::var("SIGNING_KEY_ROTATION_DAYS") {
+            Ok(v) => v.parse().ok().filter(|d| (7..=365).contains(d)).ok_or_else(|| ConfigError::Invalid(
+                format!("SIGNING_KEY_ROTATION_DAYS must be between 7 and 365, got '{}'", v)
+            ))?,
+            Err(_) => DEFAULT_ROTATION_DAYS,
+        };
+        
+        let expired_share_retention_days = match env::var("EXPIRED_SHARE_RETENTION_DAYS") {
+            Ok(v) => v.parse().ok().filter(|d| (0..=3650).contains(d)).ok_or_else(|| ConfigError::Invalid(
+                format!("EXPIRED_SHARE_RETENTION_DAYS must be between 0 and 3650, got '{}'", v)
+            ))?,
+            Err(_) => DEFAULT_RETENTION_DAYS,
+        };
+        
+        let operation_result_ttl_days = match env::var("OPERATION_RESULT_TTL_DAYS") {
+            Ok(v) => v.parse().ok().filter(|d| (1..=90).contains(d)).ok_or_else(|| ConfigError::Invalid(
+                format!("OPERATION_RESULT_TTL_DAYS must be between 1 and 90, got '{}'", v)
+            ))?,
+            Err(_) => operations::DEFAULT_RESULT_TTL_DAYS,
+        };
+        
+        let deleted_item_retention_days = match env::var("DELETED_ITEM_RETENTION_DAYS") {
+            Ok(v) => v.parse().ok().filter(|d| (0..=3650).contains(d)).ok_or_else(|| ConfigError::Invalid(
+                format!("DELETED_ITEM_RETENTION_DAYS must be between 0 and 3650, got '{}'", v)
+            ))?,
+            Err(_) => recycle_bin::DEFAULT_RETENTION_DAYS,
+        };
+        
+        let storage_retry_max_attempts = match env::var("STORAGE_RETRY_MAX_ATTEMPTS") {
+            Ok(v) => v.parse().ok().filter(|n| (1..=10).contains(n)).ok_or_else(|| ConfigError::Invalid(
+                format!("STORAGE_RETRY_MAX_ATTEMPTS must be between 1 and 10, got '{}'", v)
+            ))?,
+            Err(_) => DEFAULT_MAX_ATTEMPTS,
+        };
+        let retry_delay = |name: &str, default: std::time::Duration| match env::var(name) {
+            Ok(v) => v.parse().ok().filter(|ms| (1..=60_000).contains(ms)).map(std::time::Duration::from_millis).ok_or_else(|| ConfigError::Invalid(
+                format!("{} must be between 1 and 60000 milliseconds, got '{}'", name, v)
+            )),
+            Err(_) => Ok(default),
+        };
+        let storage_retry = RetryPolicy {
+            max_attempts: storage_retry_max_attempts,
+            base_delay: retry_delay("STORAGE_RETRY_BASE_DELAY_MS", DEFAULT_BASE_DELAY)?,
+            max_delay: retry_delay("STORAGE_RETRY_MAX_DELAY_MS", DEFAULT_MAX_DELAY)?,
+            jitter: true,
+        };
+        if storage_retry.max_delay < storage_retry.base_delay {
+            return Err(ConfigError::Invalid("STORAGE_RETRY_MAX_DELAY_MS must be at least STORAGE_RETRY_BASE_DELAY_MS".to_string()));
+        }
+        
+        let slo_latency_threshold_ms = match env::var("SLO_LATENCY_THRESHOLD_MS") {
+            Ok(v) => v.parse().ok().filter(|ms| *ms > 0).ok_or_else(|| ConfigError::Invalid(
+                format!("SLO_LATENCY_THRESHOLD_MS must be a positive integer, got '{}'", v)
+            ))?,
+            Err(_) => DEFAULT_LATENCY_THRESHOLD_MS,
+        };
+        
+        let partition_monitor_interval_minutes = match env::var("PARTITION_MONITOR_INTERVAL_MINUTES") {
+            Ok(v) => v.parse().ok().filter(|m| *m <= 10_080).ok_or_else(|| ConfigError::Invalid(
+                format!("PARTITION_MONITOR_INTERVAL_MINUTES must be between 0 and 10080, got '{}'", v)
+            ))?,
+            Err(_) => partition_monitor::DEFAULT_INTERVAL_MINUTES,
+        };
+        
+        let partition_limit = |name: &str, default: u64| match env::var(name) {
+            Ok(v) => v.parse().ok().filter(|n| *n > 0).ok_or_else(|| ConfigError::Invalid(
+                format!("{} must be a positive integer, got '{}'", name, v)
+            )),
+            Err(_) => Ok(default),
+        };
+        let default_limits = PartitionLimits::default();
+        let partition_limits = PartitionLimits {
+            entities: partition_limit("PARTITION_ENTITY_LIMIT", default_limits.entities)?,
+            bytes: partition_limit("PARTITION_SIZE_LIMIT_MB", default_limits.bytes / (1024 * 1024))? * 1024 * 1024,
+        };
+        
+        let audit_export_interval_seconds = match env::var("AUDIT_EXPORT_INTERVAL_SECONDS") {
+            Ok(v) => v.parse().ok().filter(|s| *s > 0).ok_or_else(|| ConfigError::Invalid(
+                format!("AUDIT_EXPORT_INTERVAL_SECONDS must be a positive integer, got '{}'", v)
+            ))?,
+            Err(_) => DEFAULT_FLUSH_INTERVAL_SECONDS,
+        };
+        
+        let pseudonymization_key = env::var("PSEUDONYMIZATION_KEY").ok();
+        if pseudonymization_key.as_ref().is_some_and(|k| k.len() < MIN_KEY_LEN) {
+            return Err(ConfigError::Invalid(format!(
+                "PSEUDONYMIZATION_KEY must be at least {} characters", MIN_KEY_LEN
+            )));
+        }
+        
+        Ok(Self {
+            storage_type,
+            table_storage,
+            cosmos_db,
+            dual_write,
+            dual_write_compare_every,
+            sqlite_path,
+            auth,
+            base_url,
+            #[cfg(feature = "azure")]
+            signalr,
+            share_report_threshold,
+            storage_budget,
+            trusted_proxies,
+            pseudonymization_key,
+            snapshot_container_sas_url: env::var("SNAPSHOT_CONTAINER_SAS_URL").ok(),
+            snapshot_public_base_url: env::var("SNAPSHOT_PUBLIC_BASE_URL").ok(),
+            org_snapshot_container_sas_url: env::var("ORG_SNAPSHOT_CONTAINER_SAS_URL").ok().filter(|u| !u.is_empty()),
+            bulk_limits,
+            operations_container_sas_url: env::var("OPERATIONS_CONTAINER_SAS_URL").ok().filter(|u| !u.is_empty()),
+            operation_result_ttl_days,
+            front_door_endpoint_resource_id: env::var("FRONT_DOOR_ENDPOINT_RESOURCE_ID").ok(),
+            redis_url: env::var("REDIS_URL").ok(),
+            redis_key_prefix: env::var("REDIS_KEY_PREFIX")
+                .unwrap_or_else(|_| "arshjul:share:".to_string()),
+            instance_count,
+            share_cache_ttl_seconds,
+            change_feed_interval_seconds,
+            search_endpoint: env::var("AZURE_SEARCH_ENDPOINT").ok(),
+            search_api_key: env::var("AZURE_SEARCH_API_KEY").ok(),
+            search_index: env::var("AZURE_SEARCH_INDEX")
+                .unwrap_or_else(|_| "activities".to_string()),
+            logs_ingestion_endpoint: env::var("LOGS_INGESTION_ENDPOINT").ok(),
+            logs_ingestion_rule_id: env::var("LOGS_INGESTION_RULE_ID").ok(),
+            logs_ingestion_stream: env::var("LOGS_INGESTION_STREAM")
+                .unwrap_or_else(|_| "Custom-ArshjulAudit_CL".to_string()),
+            audit_export_interval_seconds,
+            graph_client_secret: env::var("GRAPH_CLIENT_SECRET").ok(),
+            directory_cache_ttl_minutes,
+            link_domain_denylist: env::var("LINK_DOMAIN_DENYLIST")
+                .map(|list| list.split(',').map(|d| d.trim().to_string()).filter(|d| !d.is_empty()).collect())
+                .unwrap_or_default(),
+            record_contracts_dir: env::var("RECORD_CONTRACTS_DIR").ok().filter(|d| !d.is_empty()),
+            metrics_token: env::var("METRICS_TOKEN").ok().filter(|t| !t.is_empty()),
+            share_key_encryption_keys: env::var("SHARE_KEY_ENCRYPTION_KEYS").ok().filter(|k| !k.is_empty()),
+            signing_key_vault_url: env::var("SIGNING_KEY_VAULT_URL").ok().filter(|u| !u.is_empty()),
+            signing_key_secret: env::var("SIGNING_KEY_SECRET").ok().filter(|s| !s.is_empty()),
+            signing_key_rotation_days,
+            expired_share_retention_days,
+            deleted_item_retention_days,
+            storage_retry,
+            sitemap_organizations: env::var("SITEMAP_ORGANIZATIONS")
+                .map(|list| list.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect())
+                .unwrap_or_default(),
+            slo_latency_threshold_ms,
+            slo_alert_webhook_url: env::var("SLO_ALERT_WEBHOOK_URL").ok().filter(|u| !u.is_empty()),
+            mail_webhook_url: env::var("MAIL_WEBHOOK_URL").ok().filter(|u| !u.is_empty()),
+            notification_organizations: env::var("NOTIFICATION_ORGANIZATIONS")
+                .map(|list| list.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect())
+                .unwrap_or_default(),
+            terms_organizations: env::var("TERMS_ORGANIZATIONS")
+                .map(|list| list.split(',').map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect())
+                .unwrap_or_default(),
+            share_traffic_monitor: env::var("SHARE_TRAFFIC_MONITOR")
+                .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+                .unwrap_or(false),
+            partition_monitor_interval_minutes,
+            partition_limits,
+        })
+    }
+    
+    /// Validate configuration
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.snapshot_container_sas_url.is_some() != self.snapshot_public_base_url.is_some() {
+            return Err(ConfigError::Invalid(
+                "SNAPSHOT_CONTAINER_SAS_URL and SNAPSHOT_PUBLIC_BASE_URL must be set together".to_string()
+            ));
+        }
+        
+        if self.graph_client_secret.is_some() && self.auth.client_id.is_empty() {
+            return Err(ConfigError::Invalid(
+                "GRAPH_CLIENT_SECRET requires AZURE_CLIENT_ID".to_string()
+            ));
+        }
+        
+        if self.logs_ingestion_endpoint.is_some() != self.logs_ingestion_rule_id.is_some() {
+            return Err(ConfigError::Invalid(
+                "LOGS_INGESTION_ENDPOINT and LOGS_INGESTION_RULE_ID must be set together".to_string()
+            ));
+        }
+        
+        if self.search_api_key.is_some() && self.search_endpoint.is_none() {
+            return Err(ConfigError::Invalid(
+                "AZURE_SEARCH_API_KEY requires AZURE_SEARCH_ENDPOINT".to_string()
+            ));
+        }
+        
+        if self.metrics_token.as_ref().is_some_and(|t| t.len() < 32) {
+            return Err(ConfigError::Invalid(
+                "METRICS_TOKEN must be at least 32 characters".to_string()
+            ));
+        }
+        
+        if let Some(ref keys) = self.share_key_encryption_keys {
+            ShareKeyCipher::from_spec(keys)
+                .map_err(|e| ConfigError::Invalid(format!("SHARE_KEY_ENCRYPTION_KEYS: {}", e)))?;
+        }
+        
+        if self.signing_key_vault_url.as_ref().is_some_and(|u| !u.starts_with("https://")) {
+            return Err(ConfigError::Invalid(
+                "SIGNING_KEY_VAULT_URL must be an https:// URL".to_string()
+            ));
+        }
+        
+        if self.signing_key_secret.as_ref().is_some_and(|s| s.len() < 32) {
+            return Err(ConfigError::Invalid(
+                "SIGNING_KEY_SECRET must be at least 32 characters".to_string()
+            ));
+        }
+        
+        // Per-instance keys would sign links and tokens other instances reject
+        if self.storage_type != StorageType::Memory && self.signing_key_vault_url.is_none() && self.signing_key_secret.is_none() {
+            return Err(ConfigError::Invalid(
+                "SIGNING_KEY_VAULT_URL or SIGNING_KEY_SECRET is required unless STORAGE_TYPE=memory".to_string()
+            ));
+        }
+        
+        if self.slo_alert_webhook_url.as_ref().is_some_and(|u| !u.starts_with("https://")) {
+            return Err(ConfigError::Invalid(
+                "SLO_ALERT_WEBHOOK_URL must be an https:// URL".to_string()
+            ));
+        }
+        
+        if self.mail_webhook_url.as_ref().is_some_and(|u| !u.starts_with("https://")) {
+            return Err(ConfigError::Invalid(
+                "MAIL_WEBHOOK_URL must be an https:// URL".to_string()
+            ));
+        }
+        
+        if !self.notification_organizations.is_empty() && self.mail_webhook_url.is_none() {
+            return Err(ConfigError::Invalid(
+                "NOTIFICATION_ORGANIZATIONS requires MAIL_WEBHOOK_URL".to_string()
+            ));
+        }
+        
+        match self.storage_type {
+            StorageType::Memory => Ok(()),
+            
+            StorageType::Sqlite => {
+                if self.sqlite_path.as_deref().is_none_or(str::is_empty) {
+                    return Err(ConfigError::Invalid(
+                        "SQLite selected but SQLITE_PATH is empty".to_string()
+                    ));
+                }
+                Ok(())
+            }
+            
+            StorageType::TableStorage => {
+                if self.table_storage.is_none() {
+                    return Err(ConfigError::Invalid(
+                        "Table Storage selected but configuration is missing".to_string()
+                    ));
+                }
+                Ok(())
+            }
+            
+            StorageType::CosmosDb => {
+                if self.cosmos_db.is_none() {
+                    return Err(ConfigError::Invalid(
+                        "Cosmos DB selected but configuration is missing".to_string()
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+    
+    /// Get storage type display name
+    pub fn storage_display_name(&self) -> &'static str {
+        match self.storage_type {
+            StorageType::Memory => "In-Memory (development)",
+            StorageType::Sqlite => "SQLite (development)",
+            StorageType::TableStorage => "Azure Table Storage",
+            StorageType::CosmosDb => "Azure Cosmos DB",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_storage_type_parsing() {
+        assert_eq!(StorageType::from_str("memory").unwrap(), StorageType::Memory);
+        assert_eq!(StorageType::from_str("SQLite").unwrap(), StorageType::Sqlite);
+        assert_eq!(StorageType::from_str("table").unwrap(), StorageType::TableStorage);
+        assert_eq!(StorageType::from_str("cosmosdb").unwrap(), StorageType::CosmosDb);
+        assert_eq!(StorageType::from_str("cosmos-db").unwrap(), StorageType::CosmosDb);
+        assert!(StorageType::from_str("invalid").is_err());
+    }
+    
+    #[test]
+    fn test_dual_write_parsing() {
+        assert_eq!(
+            parse_dual_write("share=table, activity=cosmos").unwrap(),
+            vec![("share", StorageType::TableStorage), ("activity", StorageType::CosmosDb)],
+        );
+        assert!(parse_dual_write("").unwrap().is_empty());
+        assert_eq!(parse_dual_write("layer=cosmosdb").unwrap(), vec![("layer", StorageType::CosmosDb)]);
+        assert!(parse_dual_write("audit=table").is_err());
+        assert!(parse_dual_write("share=memory").is_err());
+        assert!(parse_dual_write("share=table,share=cosmosdb").is_err());
+    }
+}
@@ -0,0 +1,33 @@
+//! # Annual Wheel (Årshjul) Server
+//!
+//! Configuration and wiring for the `arshjul-api` binary.
+//!
+//! ## Cargo Features
+//!
+//! - `azure` (default) - Table Storage, Cosmos DB and SignalR adapters
+//! - `sqlite` - File-backed storage for local development (`STORAGE_TYPE=sqlite`)
+//! - `graph` - Creator display names and people picker via Microsoft Graph (implies `azure`)
+//! - `webhooks` - Error budget burn alerts to `SLO_ALERT_WEBHOOK_URL`
+//!
+//! Build without default features for a minimal self-hosted binary.
+
+pub mod config;
+pub mod log_filter;
+pub mod storage;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_storage;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+
+pub use config::*;
+
+/// Cargo features compiled into this binary
+pub fn enabled_features() -> Vec<&'static str> {
+    let features: [(&'static str, bool); 4] = [
+        ("azure", cfg!(feature = "azure")),
+        ("sqlite", cfg!(feature = "sqlite")),
+        ("graph", cfg!(feature = "graph")),
+        ("webhooks", cfg!(feature = "webhooks")),
+    ];
+    features.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect()
+}
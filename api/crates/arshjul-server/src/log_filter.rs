@@ -0,0 +1,152 @@
+//! # Per-Organization Log Filter
+//!
+//! Tracing filter for the `arshjul-api` binary: everything at the `RUST_LOG`
+//! level is logged as usual, and events and spans below it are logged too
+//! when they happen inside a span of an organization with an active
+//! [`LogOverrides`] entry.
+//!
+//! Spans are tied to an organization by an `org` field, as recorded by the
+//! storage spans (see `arshjul_core::traced_storage`).
+
+use arshjul_core::log_overrides::LogOverrides;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Metadata, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Filter};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Span field naming the organization
+const ORG_FIELD: &str = "org";
+
+/// Organization of a span (stored in its extensions)
+struct SpanOrg(String);
+
+/// Finds the `org` field among a span's values
+#[derive(Default)]
+struct OrgVisitor(Option<String>);
+
+impl Visit for OrgVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == ORG_FIELD {
+            self.0 = Some(value.to_string());
+        }
+    }
+    
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == ORG_FIELD {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Global level plus per-organization overrides
+pub struct OrgLogFilter {
+    base: LevelFilter,
+    overrides: Arc<LogOverrides>,
+}
+
+impl OrgLogFilter {
+    pub fn new(base: LevelFilter, overrides: Arc<LogOverrides>) -> Self {
+        Self { base, overrides }
+    }
+    
+    /// Global level from `RUST_LOG` (a plain level such as `info`; default `info`)
+    pub fn from_env(overrides: Arc<LogOverrides>) -> Self {
+        let base = std::env::var("RUST_LOG").ok()
+            .and_then(|level| level.trim().parse().ok())
+            .unwrap_or(LevelFilter::INFO);
+        Self::new(base, overrides)
+    }
+    
+    fn tag<S: Subscriber + for<'a> LookupSpan<'a>>(id: &Id, org: OrgVisitor, cx: &Context<'_, S>) {
+        if let (Some(org), Some(span)) = (org.0.filter(|o| !o.is_empty()), cx.span(id)) {
+            span.extensions_mut().replace(SpanOrg(org));
+        }
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Filter<S> for OrgLogFilter {
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        if *meta.level() <= self.base {
+            return true;
+        }
+        
+        let Some(current) = cx.lookup_current() else {
+            return false;
+        };
+        current.scope()
+            .find_map(|span| span.extensions().get::<SpanOrg>().map(|org| self.overrides.level(&org.0)))
+            .flatten()
+            .is_some_and(|level| *meta.level() <= level)
+    }
+    
+    fn callsite_enabled(&self, meta: &'static Metadata<'static>) -> Interest {
+        if *meta.level() <= self.base {
+            Interest::always()
+        } else {
+            Interest::sometimes()
+        }
+    }
+    
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        // An override may enable trace for some organization at any time
+        Some(LevelFilter::TRACE)
+    }
+    
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, cx: Context<'_, S>) {
+        let mut org = OrgVisitor::default();
+        attrs.record(&mut org);
+        Self::tag(id, org, &cx);
+    }
+    
+    fn on_record(&self, id: &Id, values: &Record<'_>, cx: Context<'_, S>) {
+        let mut org = OrgVisitor::default();
+        values.record(&mut org);
+        Self::tag(id, org, &cx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arshjul_core::models::VerboseLevel;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tracing_subscriber::layer::{Layer, SubscriberExt};
+    
+    /// Counts the events it sees
+    struct Counter(Arc<AtomicUsize>);
+    
+    impl<S: Subscriber> Layer<S> for Counter {
+        fn on_event(&self, _: &tracing::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    
+    #[test]
+    fn test_debug_only_for_overridden_org() {
+        let overrides = Arc::new(LogOverrides::new());
+        overrides.set("org-1", VerboseLevel::Debug, chrono::Duration::minutes(5), "admin");
+        let count = Arc::new(AtomicUsize::new(0));
+        let subscriber = tracing_subscriber::registry()
+            .with(Counter(count.clone()).with_filter(OrgLogFilter::new(LevelFilter::INFO, overrides)));
+        
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("outside any organization");
+            tracing::info_span!("storage", org = "org-2").in_scope(|| tracing::debug!("other tenant"));
+            assert_eq!(count.load(Ordering::SeqCst), 0);
+            
+            let span = tracing::info_span!("storage", org = tracing::field::Empty);
+            span.record("org", "org-1");
+            span.in_scope(|| {
+                tracing::debug!("overridden tenant");
+                tracing::trace!("still too verbose");
+                tracing::info!("always logged");
+            });
+            assert_eq!(count.load(Ordering::SeqCst), 2);
+        });
+    }
+}
@@ -0,0 +1,585 @@
+//! # Azure Functions Entry Point
+//!
+//! Main entry point for the Annual Wheel API deployed as Azure Functions.
+//!
+//! ## Deployment Options
+//!
+//! 1. **Azure Functions (Consumption)** - Pay per execution, auto-scale
+//! 2. **Azure Functions (Premium)** - Pre-warmed instances, VNet support
+//! 3. **Azure Container Apps** - Containerized deployment
+//!
+//! ## Commands
+//!
+//! - `arshjul-api` - Run the API
+//! - `arshjul-api init-storage` - Create the tables or containers of the
+//!   configured backend and exit; run at deploy time with an identity allowed
+//!   to manage them, since the API only checks that they exist
+//!
+//! ## Environment Variables
+//!
+//! ### Storage Configuration
+//! - `STORAGE_TYPE` - Storage backend: `memory`, `sqlite`, `table`, or `cosmosdb` (default: `memory`)
+//!
+//! **For SQLite (`sqlite` feature):**
+//! - `SQLITE_PATH` - Database file (default: `arshjul.db`)
+//!
+//! **For Azure Table Storage:**
+//! - `AZURE_STORAGE_ACCOUNT` - Storage account name
+//! - `AZURE_STORAGE_ACCESS_KEY` - Storage account access key
+//!
+//! **For Azure Cosmos DB:**
+//! - `COSMOS_CONNECTION_STRING` - Full Cosmos DB connection string
+//! - `COSMOS_DATABASE` - Database name (default: `arshjul`)
+//!
+//! ### Authentication
+//! - `AZURE_CLIENT_ID` - Azure AD app registration client ID
+//! - `AZURE_TENANT_ID` - Azure AD tenant ID (optional)
+//! - `AZURE_ADDITIONAL_AUDIENCES` - Token audiences accepted besides the client ID (optional)
+//!
+//! ### Live Updates
+//! - `AZURE_SIGNALR_CONNECTION_STRING` - Azure SignalR Service connection string (optional)
+//! - `SIGNALR_HUB` - Hub name (default: `arshjul`)
+//!
+//! ### Cache Invalidation
+//! - `FRONT_DOOR_ENDPOINT_RESOURCE_ID` - Azure Front Door endpoint to purge (optional)
+//! - `REDIS_URL` - Azure Cache for Redis URL (optional, `redis` feature); also holds public request nonces
+//! - `SHARE_CACHE_TTL_SECONDS` - Cache share lookups by short code, in Redis when configured (optional)
+//!
+//! ### Scale-out
+//! - `INSTANCE_COUNT` - Instances the API runs on (default: `1`); above 1, `REDIS_URL` is required for public request nonces and edit locks (see the README)
+//!
+//! ### Search (`azure` feature)
+//! - `AZURE_SEARCH_ENDPOINT` - Azure AI Search service; indexes activities as they change (optional)
+//! - `AZURE_SEARCH_API_KEY` - Admin key (Managed Identity when unset)
+//! - `AZURE_SEARCH_INDEX` - Index name (default: `activities`)
+//!
+//! ### Audit Export
+//! - `LOGS_INGESTION_ENDPOINT` / `LOGS_INGESTION_RULE_ID` - Forward audit entries to Log Analytics (optional)
+//!
+//! ### Directory (`graph` feature)
+//! - `GRAPH_CLIENT_SECRET` - Enables creator display names via Microsoft Graph (optional)
+//!
+//! ### Application
+//! - `BASE_URL` - Base URL for share links (defaults to function app URL)
+//! - `METRICS_TOKEN` - Bearer token for `GET /api/metrics` (optional)
+//! - `SHARE_KEY_ENCRYPTION_KEYS` - Seal share keys at rest in Table Storage (optional)
+//! - `ORG_SNAPSHOT_CONTAINER_SAS_URL` - Private blob container of point-in-time organization snapshots (optional)
+//! - `BULK_MAX_RUNNING` / `BULK_MAX_PER_ORG` / `OPERATIONS_CONTAINER_SAS_URL` - Background operations at once on each instance, per organization on each instance, and where their progress and leases are kept (default: `2` / `1`, in memory)
+//! - `OPERATION_RESULT_TTL_DAYS` - Days finished background operations and their results are kept (default: `7`)
+//! - `SIGNING_KEY_VAULT_URL` / `SIGNING_KEY_ROTATION_DAYS` - Versioned keys signing renewal links, preview links and federation tokens, in Key Vault
+//! - `SIGNING_KEY_SECRET` - Secret one signing key is derived from without a vault (one of the two is required unless `STORAGE_TYPE=memory`)
+//! - `EXPIRED_SHARE_RETENTION_DAYS` - Days expired shares stay in Table Storage before the daily cleanup (default: `30`)
+//! - `DELETED_ITEM_RETENTION_DAYS` - Days deleted shares and activities stay restorable before the daily cleanup (default: `30`)
+//! - `STORAGE_RETRY_MAX_ATTEMPTS` / `STORAGE_RETRY_BASE_DELAY_MS` / `STORAGE_RETRY_MAX_DELAY_MS` - Backoff for throttled Azure storage requests (default: `4` attempts, `200`-`5000` ms)
+//! - `SITEMAP_ORGANIZATIONS` - Organizations listed in `GET /sitemap.xml` (optional)
+//! - `SLO_ALERT_WEBHOOK_URL` - Error budget burn and partition size alerts (optional, `webhooks` feature)
+//! - `MAIL_WEBHOOK_URL` / `NOTIFICATION_ORGANIZATIONS` - Daily digests and reminders, in each user's language, for the listed organizations (optional, `webhooks` feature, one instance only)
+//! - `TERMS_ORGANIZATIONS` - Regenerate the terms layer of the listed organizations daily (optional, one instance only)
+//! - `SHARE_TRAFFIC_MONITOR` - Count public share views and flag unusual traffic daily (default: off, one instance only; counts are in memory)
+//! - `PARTITION_MONITOR_INTERVAL_MINUTES` / `PARTITION_ENTITY_LIMIT` / `PARTITION_SIZE_LIMIT_MB` - Table Storage partition size sampling and alert limits (default: off, one instance only; `100000` entities / `512` MB)
+//! - `RECORD_CONTRACTS_DIR` - Record sanitized exchanges as contract fixtures (optional, development only)
+
+use arshjul_core::{
+    auth::{TokenValidator, TokenValidatorConfig},
+    search::ActivitySearchIndex,
+    contract::ExchangeRecorder,
+    events::{EventBus, LiveUpdateService},
+    export::ExporterRegistry,
+    handlers::{HandlerContext, DIRECTORY_SEARCHES_PER_MINUTE},
+    locks::{LockStore, MemoryLockStore},
+    pseudonym::{HmacPseudonymizer, PlainIdentifiers},
+    rate_limit::{self, RateLimiter},
+    invalidation::{self, CacheInvalidation, RetryPolicy, ShareCacheInvalidator},
+    change_feed::ChangeFeedProcessor,
+    log_overrides::LogOverrides,
+    share_renewal::RenewalLinkSigner,
+    preview::PreviewSigner,
+    nonce::{InProcessNonceStore, NonceStore},
+    org_snapshots::OrganizationSnapshotStore,
+    share_key_cipher::{SealedOperationStore, SealedSnapshotStore},
+    operations::{self, BulkExecutor, MemoryOperationStore, OperationStore},
+    recycle_bin::RecycleBinCleanup,
+    share_cleanup::ShareCleanup,
+    signing_keys::{InMemoryKeyStore, KeyRing, RotationPolicy, SecretKeyStore, SigningKeyStore},
+    clock::SystemClock,
+    slo::{SloConfig, SloTracker},
+    health::HealthChecker,
+    partition_monitor::PartitionMonitor,
+    terms::TermPopulator,
+    share_traffic::{AnomalyPolicy, MemoryShareTrafficStore, ShareTrafficMonitor, ShareTrafficStore},
+    deprecation::{Deprecations, DEPRECATED_ROUTES},
+};
+#[cfg(feature = "azure")]
+use arshjul_azure::{
+    signalr::{SignalRBroadcaster, SignalRClient},
+    cache_purge::FrontDoorPurger,
+    log_analytics::LogAnalyticsSink,
+    access_log_sink::AccessLogSink,
+    key_vault::{self, KeyVaultKeyStore},
+    blob_snapshots::BlobSnapshotStore,
+    ai_search::AzureSearchIndex,
+};
+#[cfg(feature = "azure")]
+use arshjul_core::audit_export::AuditExporter;
+#[cfg(feature = "azure")]
+use arshjul_core::search::ActivityIndexer;
+#[cfg(feature = "azure")]
+use arshjul_core::snapshots::SnapshotPublisher;
+#[cfg(feature = "azure")]
+use arshjul_core::access_log::{self, AccessLogForwarder};
+#[cfg(feature = "redis")]
+use arshjul_azure::cache_purge::RedisInvalidator;
+#[cfg(feature = "redis")]
+use arshjul_azure::redis_nonce_store::RedisNonceStore;
+#[cfg(feature = "redis")]
+use arshjul_azure::redis_lock_store::RedisLockStore;
+#[cfg(feature = "graph")]
+use arshjul_azure::graph::GraphClient;
+#[cfg(feature = "webhooks")]
+use arshjul_server::webhooks::{WebhookAlertSink, WebhookMailer};
+#[cfg(feature = "webhooks")]
+use arshjul_core::{
+    notifications::{LocalizedReminderMailer, Mailer, NotificationJobs},
+    share_renewal::ShareExpiryReminders,
+};
+#[cfg(feature = "graph")]
+use arshjul_core::directory::{DirectoryCache, DirectoryService};
+use arshjul_server::config::AppConfig;
+use arshjul_server::storage;
+use arshjul_server::log_filter::OrgLogFilter;
+use tracing_subscriber::prelude::*;
+use std::sync::Arc;
+
+// For now, we use a simple HTTP server for local development
+// In production, this would be Azure Functions bindings
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Initialize logging; admins can raise the level of their own organization
+    // for a while (PUT /api/admin/logging)
+    let log_overrides = Arc::new(LogOverrides::new());
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(OrgLogFilter::from_env(log_overrides.clone())))
+        .init();
+    
+    // Load environment variables
+    dotenvy::dotenv().ok();
+    
+    // Load configuration from environment
+    let config = AppConfig::from_env()?;
+    config.validate()?;
+    
+    // Deploy-time bootstrap: create tables/containers, then exit
+    if std::env::args().nth(1).as_deref() == Some("init-storage") {
+        storage::init_storage(&config).await?;
+        return Ok(());
+    }
+    
+    // Initialize storage based on configuration; tables/containers must exist
+    let storage = storage::from_config(&config).await?;
+    
+    // Audit export: every entry recorded in the audit table is also queued for Log Analytics, flushed in the background
+    #[cfg(feature = "azure")]
+    let storage = match (&config.logs_ingestion_endpoint, &config.logs_ingestion_rule_id) {
+        (Some(endpoint), Some(rule_id)) => {
+            tracing::info!("Audit export enabled to Log Analytics (stream: {})", config.logs_ingestion_stream);
+            let sink = LogAnalyticsSink::new(endpoint, rule_id, &config.logs_ingestion_stream)?;
+            let exporter = Arc::new(AuditExporter::new(Arc::new(sink), RetryPolicy::default()));
+            tokio::spawn(exporter.clone().run(std::time::Duration::from_secs(config.audit_export_interval_seconds)));
+            storage.with_audit_export(exporter)
+        }
+        _ => storage,
+    };
+    
+    // Backends without native TTL: delete long-expired shares once a day
+    if let Some(ref purger) = storage.expired_shares {
+        let retention = chrono::Duration::days(config.expired_share_retention_days);
+        let cleanup = Arc::new(ShareCleanup::new(purger.clone(), retention, Arc::new(SystemClock)));
+        tokio::spawn(cleanup.run(std::time::Duration::from_secs(24 * 3600)));
+    }
+    
+    // Empty the recycle bin of items deleted before the retention window once a day
+    if !storage.deleted_items.is_empty() {
+        let retention = chrono::Duration::days(config.deleted_item_retention_days);
+        let cleanup = Arc::new(RecycleBinCleanup::new(storage.deleted_items.clone(), retention, Arc::new(SystemClock)));
+        tokio::spawn(cleanup.run(std::time::Duration::from_secs(24 * 3600)));
+    }
+    
+    // Initialize token validator
+    let token_validator = TokenValidator::new(TokenValidatorConfig {
+        audience: config.auth.client_id.clone(),
+        additional_audiences: config.auth.additional_audiences.clone(),
+        ..Default::default()
+    });
+    
+    // Creator display names from Microsoft Graph
+    #[cfg(feature = "graph")]
+    let directory: Option<Arc<dyn DirectoryService>> = config.graph_client_secret.as_ref().map(|secret| {
+        tracing::info!("Directory lookups enabled via Microsoft Graph (cache: {} min)", config.directory_cache_ttl_minutes);
+        Arc::new(DirectoryCache::new(
+            Arc::new(GraphClient::new(&config.auth.client_id, secret)),
+            chrono::Duration::minutes(config.directory_cache_ttl_minutes),
+        )) as Arc<dyn DirectoryService>
+    });
+    
+    // Share access forwarding to SIEMs; organizations opt in through their policy.
+    // Without a credential forwarding is off; the API itself doesn't need it
+    #[cfg(feature = "azure")]
+    let access_log = match AccessLogSink::new() {
+        Ok(sink) => {
+            let forwarder = Arc::new(AccessLogForwarder::new(Arc::new(sink), RetryPolicy::default()));
+            tokio::spawn(forwarder.clone().run(std::time::Duration::from_secs(access_log::DEFAULT_FLUSH_INTERVAL_SECONDS)));
+            Some(forwarder)
+        }
+        Err(e) => {
+            tracing::warn!("Share access log forwarding disabled: {}", e);
+            None
+        }
+    };
+    
+    // Live updates: broadcast entity changes through Azure SignalR when configured
+    let mut event_bus = EventBus::new();
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut live_updates: Option<Arc<dyn LiveUpdateService>> = None;
+    #[cfg(feature = "azure")]
+    if let Some(ref signalr_config) = config.signalr {
+        tracing::info!("Live updates enabled via Azure SignalR: {} (hub: {})", signalr_config.endpoint, signalr_config.hub);
+        let signalr = Arc::new(SignalRClient::new(signalr_config.clone()));
+        event_bus.subscribe(Arc::new(SignalRBroadcaster::new(signalr.clone())));
+        live_updates = Some(signalr);
+    }
+    
+    // Purge cached public share responses on every relevant change
+    let mut invalidation = CacheInvalidation::new(storage.shares.clone(), RetryPolicy::default());
+    #[cfg(feature = "azure")]
+    if let Some(ref endpoint) = config.front_door_endpoint_resource_id {
+        tracing::info!("Front Door purge enabled for {}", endpoint);
+        invalidation.register(Arc::new(FrontDoorPurger::new(endpoint)?));
+    }
+    #[cfg(feature = "redis")]
+    if let Some(ref url) = config.redis_url {
+        tracing::info!("Redis cache invalidation enabled (prefix: {})", config.redis_key_prefix);
+        invalidation.register(Arc::new(RedisInvalidator::new(url, &config.redis_key_prefix)?));
+    }
+    
+    // Follow writes of other instances through the change feed, dropping this instance's cached shares too
+    let change_feed = storage.change_feed.clone().filter(|_| config.change_feed_interval_seconds > 0);
+    if let (Some(_), Some(ref cache)) = (&change_feed, &storage.share_cache) {
+        invalidation.register(Arc::new(ShareCacheInvalidator::new(cache.clone())));
+    }
+    let invalidation = Arc::new(invalidation);
+    event_bus.subscribe(invalidation.clone());
+    tokio::spawn(invalidation.clone().run(std::time::Duration::from_secs(invalidation::DEFAULT_RETRY_INTERVAL_SECONDS)));
+    
+    // Static snapshots of high-traffic public shares, served through the CDN
+    #[cfg(feature = "azure")]
+    if let Some(ref url) = config.snapshot_container_sas_url {
+        let store = BlobSnapshotStore::from_sas_url(url)
+            .ok_or_else(|| anyhow::anyhow!("SNAPSHOT_CONTAINER_SAS_URL must be a container SAS URL"))?;
+        tracing::info!("Share snapshots published to the CDN container");
+        event_bus.subscribe(Arc::new(SnapshotPublisher::new(
+            Arc::new(store),
+            storage.shares.clone(),
+            storage.activities.clone(),
+            storage.policies.clone(),
+        )));
+    }
+    
+    // Indexed activity search in Azure AI Search, kept current from the bus
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut activity_search: Option<Arc<dyn ActivitySearchIndex>> = None;
+    #[cfg(feature = "azure")]
+    if let Some(ref endpoint) = config.search_endpoint {
+        tracing::info!("Activity search enabled via Azure AI Search: {} (index: {})", endpoint, config.search_index);
+        let index: Arc<dyn ActivitySearchIndex> = Arc::new(AzureSearchIndex::new(endpoint, &config.search_index, config.search_api_key.as_deref())?);
+        event_bus.subscribe(Arc::new(ActivityIndexer::new(index.clone(), storage.activities.clone())));
+        activity_search = Some(index);
+    }
+    let event_bus = Arc::new(event_bus);
+    if let Some(feed) = change_feed {
+        tracing::info!("Change feed of {} polled every {}s", feed.name(), config.change_feed_interval_seconds);
+        let processor = Arc::new(ChangeFeedProcessor::new(feed, invalidation, Arc::new(SystemClock)));
+        tokio::spawn(processor.run(std::time::Duration::from_secs(config.change_feed_interval_seconds)));
+    }
+    
+    // Service level indicators for GET /api/metrics; burn alerts go to a webhook when configured
+    let slo = Arc::new(SloTracker::new(SloConfig {
+        latency_threshold: std::time::Duration::from_millis(config.slo_latency_threshold_ms),
+        ..Default::default()
+    }));
+    #[cfg(feature = "webhooks")]
+    if let Some(ref url) = config.slo_alert_webhook_url {
+        tracing::info!("Error budget burn alerts enabled via webhook");
+        tokio::spawn(slo.clone().watch(Arc::new(WebhookAlertSink::new(url)), std::time::Duration::from_secs(60)));
+    }
+    if config.metrics_token.is_none() {
+        tracing::info!("METRICS_TOKEN not set - GET /api/metrics is disabled");
+    }
+    
+    // Deprecation/Sunset headers on deprecated routes; callers are reported at GET /api/metrics/deprecations
+    let deprecations = Arc::new(Deprecations::new(DEPRECATED_ROUTES.to_vec()));
+    
+    // Partition sizes for GET /api/metrics/partitions; alerts go to the SLO webhook when configured
+    #[cfg_attr(not(feature = "webhooks"), allow(unused_mut))]
+    let mut partitions = PartitionMonitor::new(storage.partitions.clone(), config.partition_limits);
+    #[cfg(feature = "webhooks")]
+    if let Some(ref url) = config.slo_alert_webhook_url {
+        partitions = partitions.with_alert_sink(Arc::new(WebhookAlertSink::new(url)));
+    }
+    let partitions = Arc::new(partitions);
+    if partitions.is_enabled() && config.partition_monitor_interval_minutes > 0 {
+        tracing::info!("Partition sizes sampled every {} min", config.partition_monitor_interval_minutes);
+        tokio::spawn(partitions.clone().run(std::time::Duration::from_secs(config.partition_monitor_interval_minutes * 60)));
+    }
+    
+    // Daily views of public shares; yesterday's are checked for unusual traffic, looking every hour
+    let share_traffic: Option<Arc<dyn ShareTrafficStore>> = config.share_traffic_monitor.then(|| {
+        let traffic: Arc<dyn ShareTrafficStore> = Arc::new(MemoryShareTrafficStore::new());
+        let monitor = ShareTrafficMonitor::new(storage.shares.clone(), traffic.clone(), event_bus.clone(), AnomalyPolicy::default());
+        tracing::info!("Share traffic monitoring enabled");
+        tokio::spawn(Arc::new(monitor).run(std::time::Duration::from_secs(3600)));
+        traffic
+    });
+    
+    // Storage probes for GET /api/health (Front Door health probes, monitoring)
+    let health = Arc::new(HealthChecker::new(storage.probe.clone()));
+    
+    // Versioned keys signing renewal links, preview links and federation tokens,
+    // shared by every instance; rotation is checked hourly
+    let signing_key_store: Option<Arc<dyn SigningKeyStore>> = match (&config.signing_key_vault_url, &config.signing_key_secret) {
+        #[cfg(feature = "azure")]
+        (Some(url), _) => match KeyVaultKeyStore::new(url, key_vault::DEFAULT_SECRET_PREFIX) {
+            Ok(store) => {
+                tracing::info!("Signing keys kept in Key Vault: {}", url);
+                Some(Arc::new(store))
+            }
+            Err(e) => {
+                tracing::warn!("Signing keys unavailable - renewal links, preview links and federation are disabled: {}", e);
+                None
+            }
+        },
+        #[cfg(not(feature = "azure"))]
+        (Some(_), None) => {
+            return Err(anyhow::anyhow!("SIGNING_KEY_VAULT_URL requires a build with the `azure` feature"));
+        }
+        (_, Some(secret)) => {
+            tracing::info!("Signing key derived from SIGNING_KEY_SECRET");
+            Some(Arc::new(SecretKeyStore::new(secret.as_bytes())))
+        }
+        (None, None) => {
+            tracing::warn!("Neither SIGNING_KEY_VAULT_URL nor SIGNING_KEY_SECRET set - signing keys are kept in memory per instance");
+            Some(Arc::new(InMemoryKeyStore::new()))
+        }
+    };
+    let rotation = RotationPolicy {
+        rotate_after: chrono::Duration::days(config.signing_key_rotation_days),
+        ..Default::default()
+    };
+    let signing_keys = signing_key_store.map(|store| Arc::new(KeyRing::new(store, rotation, Arc::new(SystemClock))));
+    if let Some(ref keys) = signing_keys {
+        if let Err(e) = keys.rotate().await {
+            tracing::warn!("Signing keys unavailable, retrying hourly: {}", e);
+        }
+        tokio::spawn(keys.clone().run(std::time::Duration::from_secs(3600)));
+    }
+    
+    // Renewal links in share expiry reminders and preview image links, signed with the key ring
+    let renewal_links = signing_keys.clone().map(|keys| Arc::new(RenewalLinkSigner::new(keys)));
+    let preview_links = signing_keys.clone().map(|keys| Arc::new(PreviewSigner::new(keys)));
+    
+    // Digests, activity reminders and share expiry reminders once a day, in each recipient's language
+    #[cfg(feature = "webhooks")]
+    if let (Some(ref url), false) = (&config.mail_webhook_url, config.notification_organizations.is_empty()) {
+        let mailer: Arc<dyn Mailer> = Arc::new(WebhookMailer::new(url));
+        #[cfg_attr(not(feature = "graph"), allow(unused_mut))]
+        let mut jobs = NotificationJobs::new(
+            storage.activities.clone(),
+            storage.layers.clone(),
+            storage.user_settings.clone(),
+            storage.policies.clone(),
+            mailer.clone(),
+            Arc::new(SystemClock),
+        );
+        #[cfg(feature = "graph")]
+        if let Some(ref directory) = directory {
+            jobs = jobs.with_directory(directory.clone());
+        }
+        if let Some(ref signer) = renewal_links {
+            let reminders = ShareExpiryReminders::new(storage.shares.clone(), signer.clone(), Arc::new(LocalizedReminderMailer::new(mailer)), &config.base_url)
+                .with_locales(storage.user_settings.clone(), storage.policies.clone());
+            jobs = jobs.with_share_reminders(reminders);
+        }
+        tracing::info!("Daily notifications enabled for {} organization(s)", config.notification_organizations.len());
+        tokio::spawn(Arc::new(jobs).run(config.notification_organizations.clone(), std::time::Duration::from_secs(24 * 3600)));
+    }
+    #[cfg(not(feature = "webhooks"))]
+    if !config.notification_organizations.is_empty() {
+        tracing::warn!("NOTIFICATION_ORGANIZATIONS set but the webhooks feature is off - no notifications are sent");
+    }
+    
+    // Terms layers follow their term structure; the daily run repairs writes that bypassed the API
+    if !config.terms_organizations.is_empty() {
+        let populator = TermPopulator::new(storage.layers.clone(), storage.activities.clone(), storage.policies.clone());
+        tracing::info!("Daily terms layer sync enabled for {} organization(s)", config.terms_organizations.len());
+        tokio::spawn(Arc::new(populator).run_every(config.terms_organizations.clone(), std::time::Duration::from_secs(24 * 3600)));
+    }
+    
+    // Single-use nonces of public POSTs; in Redis so any instance can redeem them
+    let nonces: Arc<dyn NonceStore> = match config.redis_url {
+        #[cfg(feature = "redis")]
+        Some(ref url) => Arc::new(RedisNonceStore::new(url, &format!("{}nonce:", config.redis_key_prefix))?),
+        _ if config.instance_count > 1 => {
+            return Err(anyhow::anyhow!("INSTANCE_COUNT above 1 requires REDIS_URL (and a build with the `redis` feature) for public request nonces"));
+        }
+        _ => {
+            tracing::warn!("REDIS_URL not set - public request nonces are kept in memory, valid on this instance only");
+            Arc::new(InProcessNonceStore::new())
+        }
+    };
+    
+    // Activity edit locks; per-instance locks exclude nothing once scaled out
+    let locks: Arc<dyn LockStore> = match config.redis_url {
+        #[cfg(feature = "redis")]
+        Some(ref url) => Arc::new(RedisLockStore::new(url, &format!("{}lock:", config.redis_key_prefix))?),
+        _ if config.instance_count > 1 => {
+            return Err(anyhow::anyhow!("INSTANCE_COUNT above 1 requires REDIS_URL (and a build with the `redis` feature) for edit locks"));
+        }
+        _ => {
+            tracing::warn!("REDIS_URL not set - edit locks are kept in memory, held on this instance only");
+            Arc::new(MemoryLockStore::new())
+        }
+    };
+    
+    // Point-in-time organization snapshots of /api/admin/snapshots, in a private blob container
+    let org_snapshots: Option<Arc<dyn OrganizationSnapshotStore>> = match config.org_snapshot_container_sas_url {
+        #[cfg(feature = "azure")]
+        Some(ref url) => Some(Arc::new(BlobSnapshotStore::from_sas_url(url)
+            .ok_or_else(|| anyhow::anyhow!("ORG_SNAPSHOT_CONTAINER_SAS_URL must be a container SAS URL"))?)),
+        _ => {
+            tracing::info!("ORG_SNAPSHOT_CONTAINER_SAS_URL not set - /api/admin/snapshots is disabled");
+            None
+        }
+    };
+    // Snapshots hold share keys; seal them like the backend does
+    let org_snapshots: Option<Arc<dyn OrganizationSnapshotStore>> = match (org_snapshots, storage::share_key_cipher(&config)?) {
+        (Some(snapshots), Some(cipher)) => Some(Arc::new(SealedSnapshotStore::new(snapshots, cipher))),
+        (snapshots, _) => snapshots,
+    };
+    
+    // Background imports, reassignments, exports and purges; progress in a private blob container survives host recycles
+    let operation_store: Arc<dyn OperationStore> = match config.operations_container_sas_url {
+        #[cfg(feature = "azure")]
+        Some(ref url) => Arc::new(BlobSnapshotStore::from_sas_url(url)
+            .ok_or_else(|| anyhow::anyhow!("OPERATIONS_CONTAINER_SAS_URL must be a container SAS URL"))?),
+        _ => {
+            tracing::info!("OPERATIONS_CONTAINER_SAS_URL not set - background operations are lost on restart");
+            Arc::new(MemoryOperationStore::new())
+        }
+    };
+    // Export results and import inputs hold share keys; seal them like the backend does
+    let operation_store: Arc<dyn OperationStore> = match storage::share_key_cipher(&config)? {
+        Some(cipher) => Arc::new(SealedOperationStore::new(operation_store, cipher)),
+        None => operation_store,
+    };
+    let mut operations = BulkExecutor::new(operation_store, &storage, config.bulk_limits)
+        .with_result_ttl(chrono::Duration::days(config.operation_result_ttl_days))
+        .with_events(event_bus.clone());
+    if let Some(ref index) = activity_search {
+        operations = operations.with_search_index(index.clone());
+    }
+    if let Some(ref snapshots) = org_snapshots {
+        operations = operations.with_org_snapshots(snapshots.clone());
+    }
+    let operations = Arc::new(operations);
+    // Delete operations past their result TTL once a day
+    tokio::spawn(operations.clone().run_cleanup(std::time::Duration::from_secs(24 * 3600)));
+    // Resume operations an earlier host left, then take over those whose instance stopped renewing its lease
+    tokio::spawn(operations.clone().run_resume(std::time::Duration::from_secs(operations::DEFAULT_LEASE_SECONDS as u64)));
+    
+    // Everything the handlers share; the bindings serve requests with it
+    let _handler_context = HandlerContext {
+        share_storage: storage.shares.clone(),
+        activity_storage: storage.activities.clone(),
+        layer_storage: storage.layers.clone(),
+        activity_type_storage: storage.activity_types.clone(),
+        user_settings_storage: storage.user_settings.clone(),
+        audit_storage: storage.audit.clone(),
+        policy_storage: storage.policies.clone(),
+        token_validator,
+        base_url: config.base_url.clone(),
+        share_report_threshold: config.share_report_threshold,
+        trusted_proxies: config.trusted_proxies.clone(),
+        events: event_bus,
+        live_updates,
+        locks,
+        pseudonymizer: match config.pseudonymization_key {
+            Some(ref key) => Arc::new(HmacPseudonymizer::new(key.as_bytes())),
+            None => Arc::new(PlainIdentifiers),
+        },
+        snapshot_base_url: config.snapshot_public_base_url.clone(),
+        activity_search,
+        #[cfg(feature = "graph")]
+        directory,
+        #[cfg(not(feature = "graph"))]
+        directory: None,
+        directory_search_limiter: Arc::new(RateLimiter::new(DIRECTORY_SEARCHES_PER_MINUTE, chrono::Duration::minutes(1))),
+        public_api_limiter: Arc::new(RateLimiter::new(rate_limit::DEFAULT_API_KEY_PER_MINUTE, chrono::Duration::minutes(1))),
+        exporters: Arc::new(ExporterRegistry::default()),
+        link_domain_denylist: config.link_domain_denylist.clone(),
+        share_traffic,
+        #[cfg(feature = "azure")]
+        access_log,
+        #[cfg(not(feature = "azure"))]
+        access_log: None,
+        log_overrides,
+        slo,
+        metrics_token: config.metrics_token.clone(),
+        deprecations,
+        partitions,
+        storage_metrics: storage.metrics.clone(),
+        storage_budget: config.storage_budget,
+        org_snapshots,
+        operations: Some(operations),
+        health,
+        renewal_links,
+        preview_links,
+        signing_keys,
+        sitemap_organizations: config.sitemap_organizations.clone(),
+        sitemap_cache: Arc::new(Default::default()),
+        nonces,
+        clock: Arc::new(SystemClock),
+    };
+    
+    // Contract recording: bindings pass handler results through the recorder
+    let _contract_recorder = match config.record_contracts_dir {
+        Some(ref dir) => {
+            tracing::warn!("Recording API exchanges to {} - do not enable in production", dir);
+            Some(ExchangeRecorder::new(dir)?)
+        }
+        None => None,
+    };
+    
+    tracing::info!("Annual Wheel API starting (features: {:?})...", arshjul_server::enabled_features());
+    tracing::info!("Base URL: {}", config.base_url);
+    
+    // In a real Azure Functions deployment, the runtime handles HTTP routing
+    // For local development, you could add a simple HTTP server here
+    
+    println!("🌟 Annual Wheel API");
+    println!("==================");
+    println!("Storage: {}", config.storage_display_name());
+    println!("Base URL: {}", config.base_url);
+    println!();
+    println!("API Endpoints:");
+    println!("  POST   /api/shares              - Create share");
+    println!("  GET    /api/shares              - List shares");
+    println!("  GET    /api/shares/{{id}}         - Get share");
+    println!("  DELETE /api/shares/{{id}}         - Delete share");
+    println!("  POST   /api/shares/{{id}}/renew   - Renew share");
+    println!("  GET    /api/public/s/{{code}}     - Access public share");
+    println!("  GET    /api/health              - Storage health check");
+    println!();
+    println!("For Azure Functions deployment, configure function.json bindings.");
+    
+    Ok(())
+}
@@ -0,0 +1,837 @@
+//! # SQLite Storage
+//!
+//! Local development backend (`STORAGE_TYPE=sqlite`) keeping all data in one
+//! file, `SQLITE_PATH`, so it survives restarts. It implements every storage
+//! trait, audit log and policies included, with the semantics of the
+//! in-memory backend: TTL expiry, row key ordered pages, filters and a
+//! short code index with tombstones.
+//!
+//! Entities are stored as JSON in one table per kind, keyed by organization
+//! and row key. Shares also keep their short code (unique) and expiry in
+//! columns. Batch updates run in a transaction, as does emptying the
+//! recycle bin, which scans both tables.
+//!
+//! All requests share one connection behind a mutex: plenty for a developer
+//! machine, not meant for production. `GET /api/health` reads a row of
+//! every table.
+
+use arshjul_core::clock::{Clock, SystemClock};
+use arshjul_core::models::*;
+use arshjul_core::storage::memory_storage::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use arshjul_core::storage::{
+    ensure_deletable, on_layers_in_year, paginate, ActivityChanges, ActivityStorage, ActivityTypeStorage, AuditStorage, DeletedItemPurger, LayerStorage,
+    PolicyStorage, QueryOptions, QueryResult, activity_etag, share_etag, ShareStorage, StorageError, StorageProbe, UserSettingsStorage,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Tables of entities without extra columns
+const ACTIVITIES: &str = "activities";
+const LAYERS: &str = "layers";
+const ACTIVITY_TYPES: &str = "activity_types";
+const USER_SETTINGS: &str = "user_settings";
+const POLICIES: &str = "policies";
+
+/// Row key of the single policy row per organization
+const POLICY_ROW_KEY: &str = "policy";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS shares (
+        organization_id TEXT NOT NULL,
+        row_key TEXT NOT NULL,
+        short_code TEXT NOT NULL UNIQUE,
+        expires_at INTEGER,
+        body TEXT NOT NULL,
+        PRIMARY KEY (organization_id, row_key)
+    );
+    CREATE TABLE IF NOT EXISTS short_code_tombstones (
+        short_code TEXT PRIMARY KEY,
+        body TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS audit_entries (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        organization_id TEXT NOT NULL,
+        body TEXT NOT NULL
+    );
+";
+
+fn db(e: rusqlite::Error) -> StorageError {
+    StorageError::Storage(e.to_string())
+}
+
+fn to_json<T: Serialize>(row: &T) -> Result<String, StorageError> {
+    serde_json::to_string(row).map_err(|e| StorageError::Serialization(e.to_string()))
+}
+
+fn from_json<T: DeserializeOwned>(body: &str) -> Result<T, StorageError> {
+    serde_json::from_str(body).map_err(|e| StorageError::Serialization(e.to_string()))
+}
+
+/// Unix time the share expires at when written now (TTL restarts on every write, as in Cosmos DB)
+fn expires_at(share: &ShareLink, now: i64) -> Option<i64> {
+    share.ttl.map(|ttl| now + ttl)
+}
+
+/// All storage in one SQLite database
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+    clock: Arc<dyn Clock>,
+}
+
+impl SqliteStorage {
+    /// Open (or create) the database file and its tables
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::init(Connection::open(path).map_err(db)?)
+    }
+    
+    /// Database that lives as long as the value (tests)
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::init(Connection::open_in_memory().map_err(db)?)
+    }
+    
+    fn init(conn: Connection) -> Result<Self, StorageError> {
+        conn.execute_batch(SCHEMA).map_err(db)?;
+        for table in [ACTIVITIES, LAYERS, ACTIVITY_TYPES, USER_SETTINGS, POLICIES] {
+            conn.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    organization_id TEXT NOT NULL,
+                    row_key TEXT NOT NULL,
+                    body TEXT NOT NULL,
+                    PRIMARY KEY (organization_id, row_key)
+                );",
+                table
+            )).map_err(db)?;
+        }
+        Ok(Self { conn: Mutex::new(conn), clock: Arc::new(SystemClock) })
+    }
+    
+    /// Expire shares and tombstones by another time source (tests)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic mid-statement leaves nothing half-written; SQLite rolls back
+        self.conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
+    fn insert<T: Serialize>(&self, table: &str, organization_id: &str, row_key: &str, row: &T) -> Result<(), StorageError> {
+        let inserted = self.conn().execute(
+            &format!("INSERT OR IGNORE INTO {} (organization_id, row_key, body) VALUES (?1, ?2, ?3)", table),
+            params![organization_id, row_key, to_json(row)?],
+        ).map_err(db)?;
+        if inserted == 0 {
+            return Err(StorageError::AlreadyExists(row_key.to_string()));
+        }
+        Ok(())
+    }
+    
+    fn replace<T: Serialize>(conn: &Connection, table: &str, organization_id: &str, row_key: &str, row: &T) -> Result<(), StorageError> {
+        let updated = conn.execute(
+            &format!("UPDATE {} SET body = ?3 WHERE organization_id = ?1 AND row_key = ?2", table),
+            params![organization_id, row_key, to_json(row)?],
+        ).map_err(db)?;
+        if updated == 0 {
+            return Err(StorageError::NotFound(row_key.to_string()));
+        }
+        Ok(())
+    }
+    
+    fn upsert<T: Serialize>(&self, table: &str, organization_id: &str, row_key: &str, row: &T) -> Result<(), StorageError> {
+        self.conn().execute(
+            &format!("INSERT OR REPLACE INTO {} (organization_id, row_key, body) VALUES (?1, ?2, ?3)", table),
+            params![organization_id, row_key, to_json(row)?],
+        ).map_err(db)?;
+        Ok(())
+    }
+    
+    fn get<T: DeserializeOwned>(&self, table: &str, organization_id: &str, row_key: &str) -> Result<Option<T>, StorageError> {
+        let body: Option<String> = self.conn().query_row(
+            &format!("SELECT body FROM {} WHERE organization_id = ?1 AND row_key = ?2", table),
+            params![organization_id, row_key],
+            |row| row.get(0),
+        ).optional().map_err(db)?;
+        body.as_deref().map(from_json).transpose()
+    }
+    
+    fn remove(&self, table: &str, organization_id: &str, row_key: &str) -> Result<(), StorageError> {
+        let removed = self.conn().execute(
+            &format!("DELETE FROM {} WHERE organization_id = ?1 AND row_key = ?2", table),
+            params![organization_id, row_key],
+        ).map_err(db)?;
+        if removed == 0 {
+            return Err(StorageError::NotFound(row_key.to_string()));
+        }
+        Ok(())
+    }
+    
+    /// Rows of an organization in row key order
+    fn list<T: DeserializeOwned>(&self, table: &str, organization_id: &str) -> Result<Vec<T>, StorageError> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!("SELECT body FROM {} WHERE organization_id = ?1 ORDER BY row_key", table)).map_err(db)?;
+        let bodies = statement.query_map(params![organization_id], |row| row.get::<_, String>(0)).map_err(db)?;
+        bodies.map(|body| from_json(&body.map_err(db)?)).collect()
+    }
+    
+    /// Live share by a condition on the shares table
+    fn live_share(conn: &Connection, condition: &str, params: impl rusqlite::Params, now: i64) -> Result<Option<ShareLink>, StorageError> {
+        let body: Option<String> = conn.query_row(
+            &format!("SELECT body FROM shares WHERE {} AND (expires_at IS NULL OR expires_at > {})", condition, now),
+            params,
+            |row| row.get(0),
+        ).optional().map_err(db)?;
+        body.as_deref().map(from_json).transpose()
+    }
+    
+    /// Drop TTL-expired shares (no tombstone, like a Cosmos DB TTL delete)
+    fn purge_expired(conn: &Connection, now: i64) -> Result<(), StorageError> {
+        conn.execute("DELETE FROM shares WHERE expires_at IS NOT NULL AND expires_at <= ?1", params![now]).map_err(db)?;
+        Ok(())
+    }
+    
+    /// Fail if a short code is taken by another share or retired
+    fn ensure_short_code_free(&self, conn: &Connection, share: &ShareLink) -> Result<(), StorageError> {
+        let taken: Option<(String, String)> = conn.query_row(
+            "SELECT organization_id, row_key FROM shares WHERE short_code = ?1",
+            params![share.short_code],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional().map_err(db)?;
+        if taken.is_some_and(|(org, id)| org != share.organization_id || id != share.id) {
+            return Err(StorageError::AlreadyExists(share.short_code.clone()));
+        }
+        
+        match Self::tombstone(conn, &share.short_code)? {
+            Some(t) if t.is_active(self.clock.now()) => Err(StorageError::AlreadyExists(share.short_code.clone())),
+            Some(_) => {
+                conn.execute("DELETE FROM short_code_tombstones WHERE short_code = ?1", params![share.short_code]).map_err(db)?;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+    
+    fn tombstone(conn: &Connection, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        let body: Option<String> = conn.query_row(
+            "SELECT body FROM short_code_tombstones WHERE short_code = ?1",
+            params![short_code],
+            |row| row.get(0),
+        ).optional().map_err(db)?;
+        body.as_deref().map(from_json).transpose()
+    }
+    
+    fn write_share(conn: &Connection, share: &ShareLink, now: i64) -> Result<(), StorageError> {
+        conn.execute(
+            "UPDATE shares SET short_code = ?3, expires_at = ?4, body = ?5 WHERE organization_id = ?1 AND row_key = ?2",
+            params![share.organization_id, share.id, share.short_code, expires_at(share, now), to_json(share)?],
+        ).map_err(db)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ShareStorage for SqliteStorage {
+    async fn create(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let now = self.clock.now().timestamp();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        Self::purge_expired(&tx, now)?;
+        
+        let exists = tx.query_row(
+            "SELECT 1 FROM shares WHERE organization_id = ?1 AND row_key = ?2",
+            params![share.organization_id, share.id],
+            |_| Ok(()),
+        ).optional().map_err(db)?;
+        if exists.is_some() {
+            return Err(StorageError::AlreadyExists(share.id.clone()));
+        }
+        self.ensure_short_code_free(&tx, &share)?;
+        
+        tx.execute(
+            "INSERT INTO shares (organization_id, row_key, short_code, expires_at, body) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![share.organization_id, share.id, share.short_code, expires_at(&share, now), to_json(&share)?],
+        ).map_err(db)?;
+        tx.commit().map_err(db)?;
+        Ok(share)
+    }
+    
+    async fn get(&self, organization_id: &str, share_id: &str) -> Result<ShareLink, StorageError> {
+        Self::live_share(&self.conn(), "organization_id = ?1 AND row_key = ?2", params![organization_id, share_id], self.clock.now().timestamp())?
+            .ok_or_else(|| StorageError::NotFound(share_id.to_string()))
+    }
+    
+    async fn get_by_short_code(&self, short_code: &str) -> Result<ShareLink, StorageError> {
+        Self::live_share(&self.conn(), "short_code = ?1", params![short_code], self.clock.now().timestamp())?
+            .ok_or_else(|| StorageError::NotFound(short_code.to_string()))
+    }
+    
+    async fn update(&self, share: ShareLink) -> Result<ShareLink, StorageError> {
+        let now = self.clock.now().timestamp();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        Self::purge_expired(&tx, now)?;
+        
+        let old_short_code: String = tx.query_row(
+            "SELECT short_code FROM shares WHERE organization_id = ?1 AND row_key = ?2",
+            params![share.organization_id, share.id],
+            |row| row.get(0),
+        ).optional().map_err(db)?.ok_or_else(|| StorageError::NotFound(share.id.clone()))?;
+        if old_short_code != share.short_code {
+            self.ensure_short_code_free(&tx, &share)?;
+        }
+        
+        Self::write_share(&tx, &share, now)?;
+        tx.commit().map_err(db)?;
+        Ok(share)
+    }
+    
+    async fn replace(&self, share: ShareLink, etag: &str) -> Result<Option<ShareLink>, StorageError> {
+        let now = self.clock.now().timestamp();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        Self::purge_expired(&tx, now)?;
+        
+        // Read and written in one transaction on the only connection
+        let current = Self::live_share(&tx, "organization_id = ?1 AND row_key = ?2", params![share.organization_id, share.id], now)?
+            .ok_or_else(|| StorageError::NotFound(share.id.clone()))?;
+        if share_etag(&current) != etag {
+            return Ok(None);
+        }
+        if current.short_code != share.short_code {
+            self.ensure_short_code_free(&tx, &share)?;
+        }
+        
+        Self::write_share(&tx, &share, now)?;
+        tx.commit().map_err(db)?;
+        Ok(Some(share))
+    }
+    
+    async fn delete(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        let now = self.clock.now();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        
+        let body: Option<String> = tx.query_row(
+            "DELETE FROM shares WHERE organization_id = ?1 AND row_key = ?2 RETURNING body",
+            params![organization_id, share_id],
+            |row| row.get(0),
+        ).optional().map_err(db)?;
+        if let Some(share) = body.as_deref().map(from_json::<ShareLink>).transpose()? {
+            if let Some(tombstone) = ShortCodeTombstone::for_deleted_share(&share, now) {
+                tx.execute(
+                    "INSERT OR REPLACE INTO short_code_tombstones (short_code, body) VALUES (?1, ?2)",
+                    params![share.short_code, to_json(&tombstone)?],
+                ).map_err(db)?;
+            }
+        }
+        
+        tx.commit().map_err(db)
+    }
+    
+    async fn get_tombstone(&self, short_code: &str) -> Result<Option<ShortCodeTombstone>, StorageError> {
+        Ok(Self::tombstone(&self.conn(), short_code)?.filter(|t| t.is_active(self.clock.now())))
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        options: QueryOptions,
+    ) -> Result<QueryResult<ShareLink>, StorageError> {
+        let filter = options.filter;
+        let page_size = options.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+        let now = self.clock.now().timestamp();
+        
+        let bodies: Vec<String> = {
+            let conn = self.conn();
+            let mut statement = conn.prepare(
+                "SELECT body FROM shares WHERE organization_id = ?1 AND (expires_at IS NULL OR expires_at > ?2)"
+            ).map_err(db)?;
+            let rows = statement.query_map(params![organization_id, now], |row| row.get(0)).map_err(db)?;
+            rows.collect::<Result<_, _>>().map_err(db)?
+        };
+        
+        let mut matching = Vec::with_capacity(bodies.len());
+        for body in bodies {
+            let value: serde_json::Value = from_json(&body)?;
+            if filter.as_ref().is_none_or(|f| f.matches(&value)) {
+                matching.push(from_json::<ShareLink>(&body)?);
+            }
+        }
+        let total = matching.len() as u64;
+        let (items, continuation_token) = paginate(matching, |s| s.id.clone(), page_size, options.continuation_token.as_deref());
+        
+        Ok(QueryResult {
+            items,
+            continuation_token,
+            total_count: Some(total),
+        })
+    }
+    
+    async fn increment_views(&self, organization_id: &str, share_id: &str) -> Result<(), StorageError> {
+        let now = self.clock.now();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        
+        if let Some(mut share) = Self::live_share(&tx, "organization_id = ?1 AND row_key = ?2", params![organization_id, share_id], now.timestamp())? {
+            share.stats.view_count += 1;
+            share.stats.last_accessed_at = Some(now);
+            Self::write_share(&tx, &share, now.timestamp())?;
+        }
+        
+        tx.commit().map_err(db)
+    }
+}
+
+#[async_trait]
+impl ActivityStorage for SqliteStorage {
+    async fn create(&self, activity: Activity) -> Result<Activity, StorageError> {
+        self.insert(ACTIVITIES, &activity.organization_id, &activity.id, &activity)?;
+        Ok(activity)
+    }
+    
+    async fn create_many(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        for activity in &activities {
+            let inserted = tx.execute(
+                &format!("INSERT OR IGNORE INTO {} (organization_id, row_key, body) VALUES (?1, ?2, ?3)", ACTIVITIES),
+                params![activity.organization_id, activity.id, to_json(activity)?],
+            ).map_err(db)?;
+            if inserted == 0 {
+                return Err(StorageError::AlreadyExists(activity.id.clone()));
+            }
+        }
+        tx.commit().map_err(db)?;
+        Ok(activities)
+    }
+    
+    async fn get(&self, organization_id: &str, activity_id: &str) -> Result<Activity, StorageError> {
+        SqliteStorage::get(self, ACTIVITIES, organization_id, activity_id)?
+            .ok_or_else(|| StorageError::NotFound(activity_id.to_string()))
+    }
+    
+    async fn update(&self, activity: Activity) -> Result<Activity, StorageError> {
+        Self::replace(&self.conn(), ACTIVITIES, &activity.organization_id, &activity.id, &activity)?;
+        Ok(activity)
+    }
+    
+    async fn update_batch(&self, activities: Vec<Activity>) -> Result<Vec<Activity>, StorageError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        for activity in &activities {
+            Self::replace(&tx, ACTIVITIES, &activity.organization_id, &activity.id, activity)?;
+        }
+        tx.commit().map_err(db)?;
+        Ok(activities)
+    }
+    
+    async fn delete(&self, organization_id: &str, activity_id: &str) -> Result<(), StorageError> {
+        self.remove(ACTIVITIES, organization_id, activity_id)
+    }
+    
+    async fn apply_changes(&self, organization_id: &str, changes: ActivityChanges) -> Result<(), StorageError> {
+        changes.check_organization(organization_id)?;
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        
+        // Checked in the transaction that writes them
+        for activity_id in changes.etags.keys() {
+            let body: Option<String> = tx.query_row(
+                &format!("SELECT body FROM {} WHERE organization_id = ?1 AND row_key = ?2", ACTIVITIES),
+                params![organization_id, activity_id],
+                |row| row.get(0),
+            ).optional().map_err(db)?;
+            let current: Activity = from_json(&body.ok_or_else(|| StorageError::NotFound(activity_id.clone()))?)?;
+            changes.check_etag(activity_id, &activity_etag(&current))?;
+        }
+        for activity in &changes.create {
+            let inserted = tx.execute(
+                &format!("INSERT OR IGNORE INTO {} (organization_id, row_key, body) VALUES (?1, ?2, ?3)", ACTIVITIES),
+                params![organization_id, activity.id, to_json(activity)?],
+            ).map_err(db)?;
+            if inserted == 0 {
+                return Err(StorageError::AlreadyExists(activity.id.clone()));
+            }
+        }
+        for activity in &changes.update {
+            Self::replace(&tx, ACTIVITIES, organization_id, &activity.id, activity)?;
+        }
+        for activity_id in &changes.delete {
+            let removed = tx.execute(
+                &format!("DELETE FROM {} WHERE organization_id = ?1 AND row_key = ?2", ACTIVITIES),
+                params![organization_id, activity_id],
+            ).map_err(db)?;
+            if removed == 0 {
+                return Err(StorageError::NotFound(activity_id.clone()));
+            }
+        }
+        tx.commit().map_err(db)
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        _options: QueryOptions,
+    ) -> Result<QueryResult<Activity>, StorageError> {
+        let items: Vec<Activity> = SqliteStorage::list(self, ACTIVITIES, organization_id)?;
+        let total = items.len() as u64;
+        Ok(QueryResult { items, continuation_token: None, total_count: Some(total) })
+    }
+    
+    /// `year` keeps activities overlapping that calendar year
+    async fn list_by_layers(
+        &self,
+        organization_id: &str,
+        layer_ids: &[String],
+        year: Option<i32>,
+    ) -> Result<Vec<Activity>, StorageError> {
+        Ok(SqliteStorage::list::<Activity>(self, ACTIVITIES, organization_id)?.into_iter()
+            .filter(|a| on_layers_in_year(a, layer_ids, year))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl DeletedItemPurger for SqliteStorage {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+    
+    async fn purge_deleted(&self, cutoff: DateTime<Utc>) -> Result<u64, StorageError> {
+        let now = self.clock.now();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(db)?;
+        
+        // Only rows binned before the cutoff are read; they are checked again once parsed
+        let binned = |table: &str| format!(
+            "SELECT body FROM {} WHERE julianday(json_extract(body, '$.deletedAt')) < julianday(?1)", table
+        );
+        let cutoff_text = cutoff.to_rfc3339();
+        let shares: Vec<ShareLink> = {
+            let mut statement = tx.prepare(&binned("shares")).map_err(db)?;
+            let bodies = statement.query_map(params![cutoff_text], |row| row.get::<_, String>(0)).map_err(db)?;
+            bodies.map(|body| from_json(&body.map_err(db)?)).collect::<Result<_, _>>()?
+        };
+        let activities: Vec<Activity> = {
+            let mut statement = tx.prepare(&binned(ACTIVITIES)).map_err(db)?;
+            let bodies = statement.query_map(params![cutoff_text], |row| row.get::<_, String>(0)).map_err(db)?;
+            bodies.map(|body| from_json(&body.map_err(db)?)).collect::<Result<_, _>>()?
+        };
+        
+        let mut deleted = 0;
+        for share in shares.iter().filter(|s| s.deleted_at.is_some_and(|at| at < cutoff)) {
+            tx.execute("DELETE FROM shares WHERE organization_id = ?1 AND row_key = ?2", params![share.organization_id, share.id]).map_err(db)?;
+            if let Some(tombstone) = ShortCodeTombstone::for_deleted_share(share, now) {
+                tx.execute(
+                    "INSERT OR REPLACE INTO short_code_tombstones (short_code, body) VALUES (?1, ?2)",
+                    params![share.short_code, to_json(&tombstone)?],
+                ).map_err(db)?;
+            }
+            deleted += 1;
+        }
+        for activity in activities.iter().filter(|a| a.deleted_at.is_some_and(|at| at < cutoff)) {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE organization_id = ?1 AND row_key = ?2", ACTIVITIES),
+                params![activity.organization_id, activity.id],
+            ).map_err(db)?;
+            deleted += 1;
+        }
+        
+        tx.commit().map_err(db)?;
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl LayerStorage for SqliteStorage {
+    async fn create(&self, layer: Layer) -> Result<Layer, StorageError> {
+        self.insert(LAYERS, &layer.organization_id, &layer.id, &layer)?;
+        Ok(layer)
+    }
+    
+    async fn get(&self, organization_id: &str, layer_id: &str) -> Result<Layer, StorageError> {
+        SqliteStorage::get(self, LAYERS, organization_id, layer_id)?
+            .ok_or_else(|| StorageError::NotFound(layer_id.to_string()))
+    }
+    
+    async fn update(&self, layer: Layer) -> Result<Layer, StorageError> {
+        Self::replace(&self.conn(), LAYERS, &layer.organization_id, &layer.id, &layer)?;
+        Ok(layer)
+    }
+    
+    async fn delete(&self, organization_id: &str, layer_id: &str) -> Result<(), StorageError> {
+        self.remove(LAYERS, organization_id, layer_id)
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<Layer>, StorageError> {
+        let mut layers: Vec<Layer> = SqliteStorage::list(self, LAYERS, organization_id)?;
+        layers.sort_by_key(|l| l.ring_index);
+        Ok(layers)
+    }
+}
+
+#[async_trait]
+impl ActivityTypeStorage for SqliteStorage {
+    async fn upsert(&self, config: ActivityTypeConfig) -> Result<ActivityTypeConfig, StorageError> {
+        SqliteStorage::upsert(self, ACTIVITY_TYPES, &config.organization_id, &config.key, &config)?;
+        Ok(config)
+    }
+    
+    async fn get(&self, organization_id: &str, key: &str) -> Result<ActivityTypeConfig, StorageError> {
+        SqliteStorage::get(self, ACTIVITY_TYPES, organization_id, key)?
+            .ok_or_else(|| StorageError::NotFound(key.to_string()))
+    }
+    
+    async fn delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        ensure_deletable(&ActivityTypeStorage::get(self, organization_id, key).await?)?;
+        self.remove(ACTIVITY_TYPES, organization_id, key)
+    }
+    
+    async fn force_delete(&self, organization_id: &str, key: &str) -> Result<(), StorageError> {
+        self.remove(ACTIVITY_TYPES, organization_id, key)
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<ActivityTypeConfig>, StorageError> {
+        let mut types: Vec<ActivityTypeConfig> = SqliteStorage::list(self, ACTIVITY_TYPES, organization_id)?;
+        types.sort_by_key(|t| t.sort_order);
+        Ok(types)
+    }
+}
+
+#[async_trait]
+impl UserSettingsStorage for SqliteStorage {
+    async fn get(&self, organization_id: &str, user_id: &str) -> Result<UserSettings, StorageError> {
+        Ok(SqliteStorage::get(self, USER_SETTINGS, organization_id, user_id)?
+            .unwrap_or_else(|| UserSettings::new(user_id.to_string(), organization_id.to_string())))
+    }
+    
+    async fn upsert(&self, settings: UserSettings) -> Result<UserSettings, StorageError> {
+        SqliteStorage::upsert(self, USER_SETTINGS, &settings.organization_id, &settings.user_id, &settings)?;
+        Ok(settings)
+    }
+    
+    async fn delete(&self, organization_id: &str, user_id: &str) -> Result<(), StorageError> {
+        self.remove(USER_SETTINGS, organization_id, user_id)
+    }
+    
+    async fn list(&self, organization_id: &str) -> Result<Vec<UserSettings>, StorageError> {
+        SqliteStorage::list(self, USER_SETTINGS, organization_id)
+    }
+}
+
+#[async_trait]
+impl AuditStorage for SqliteStorage {
+    async fn record(&self, entry: AuditEntry) -> Result<(), StorageError> {
+        self.conn().execute(
+            "INSERT INTO audit_entries (organization_id, body) VALUES (?1, ?2)",
+            params![entry.organization_id, to_json(&entry)?],
+        ).map_err(db)?;
+        Ok(())
+    }
+    
+    async fn list(
+        &self,
+        organization_id: &str,
+        _options: QueryOptions,
+    ) -> Result<QueryResult<AuditEntry>, StorageError> {
+        let bodies: Vec<String> = {
+            let conn = self.conn();
+            let mut statement = conn.prepare("SELECT body FROM audit_entries WHERE organization_id = ?1 ORDER BY seq").map_err(db)?;
+            let rows = statement.query_map(params![organization_id], |row| row.get(0)).map_err(db)?;
+            rows.collect::<Result<_, _>>().map_err(db)?
+        };
+        let items = bodies.iter().map(|body| from_json(body)).collect::<Result<Vec<AuditEntry>, _>>()?;
+        let total = items.len() as u64;
+        
+        Ok(QueryResult {
+            items,
+            continuation_token: None,
+            total_count: Some(total),
+        })
+    }
+    
+    async fn delete_all(&self, organization_id: &str) -> Result<u64, StorageError> {
+        let removed = self.conn().execute("DELETE FROM audit_entries WHERE organization_id = ?1", params![organization_id]).map_err(db)?;
+        Ok(removed as u64)
+    }
+}
+
+#[async_trait]
+impl PolicyStorage for SqliteStorage {
+    async fn get(&self, organization_id: &str) -> Result<OrganizationPolicy, StorageError> {
+        Ok(SqliteStorage::get(self, POLICIES, organization_id, POLICY_ROW_KEY)?
+            .unwrap_or_else(|| OrganizationPolicy::new(organization_id)))
+    }
+    
+    async fn upsert(&self, policy: OrganizationPolicy) -> Result<OrganizationPolicy, StorageError> {
+        SqliteStorage::upsert(self, POLICIES, &policy.organization_id, POLICY_ROW_KEY, &policy)?;
+        Ok(policy)
+    }
+    
+    async fn delete(&self, organization_id: &str) -> Result<(), StorageError> {
+        self.remove(POLICIES, organization_id, POLICY_ROW_KEY)
+    }
+}
+
+/// Tables `GET /api/health` reads
+const PROBED_TABLES: [&str; 8] = ["shares", "short_code_tombstones", "audit_entries", ACTIVITIES, LAYERS, ACTIVITY_TYPES, USER_SETTINGS, POLICIES];
+
+#[async_trait]
+impl StorageProbe for SqliteStorage {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+    
+    fn targets(&self) -> Vec<&'static str> {
+        PROBED_TABLES.to_vec()
+    }
+    
+    async fn probe(&self, target: &str) -> Result<(), StorageError> {
+        // Table names can't be bound as parameters
+        let table = PROBED_TABLES.iter().find(|t| **t == target)
+            .ok_or_else(|| StorageError::NotFound(target.to_string()))?;
+        self.conn().query_row(&format!("SELECT count(*) FROM (SELECT 1 FROM {} LIMIT 1)", table), [], |_| Ok(()))
+            .map_err(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arshjul_core::storage::{FilterBuilder, FilterField};
+    
+    fn activity(id: &str, layer: &str, start: &str) -> Activity {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "title": id, "startDate": start, "endDate": start,
+            "type": "meeting", "color": "#000000", "highlightColor": "#000000",
+            "scope": layer, "scopeId": layer, "organizationId": "org-1",
+        })).unwrap()
+    }
+    
+    fn share(id: &str, short_code: &str, visibility: &str) -> ShareLink {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "shareKey": "k".repeat(64), "shortCode": short_code,
+            "visibility": visibility, "organizationId": "org-1", "createdBy": "user-1",
+            "createdAt": "2025-01-01T00:00:00Z", "expiresAt": "2099-01-01T00:00:00Z",
+            "layerConfig": { "layerIds": [] }, "viewSettings": {},
+        })).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_data_survives_reopening() {
+        let path = std::env::temp_dir().join(format!("arshjul-test-{}.db", std::process::id()));
+        {
+            let storage = SqliteStorage::open(&path).unwrap();
+            ActivityStorage::create(&storage, activity("a-1", "layer-1", "2025-03-01T09:00:00Z")).await.unwrap();
+            AuditStorage::record(&storage, AuditEntry::new("org-1", "activity.created", None, Some("a-1"))).await.unwrap();
+        }
+        
+        let storage = SqliteStorage::open(&path).unwrap();
+        assert_eq!(ActivityStorage::get(&storage, "org-1", "a-1").await.unwrap().title, "a-1");
+        assert_eq!(AuditStorage::list(&storage, "org-1", QueryOptions::default()).await.unwrap().items.len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_conformance() {
+        use arshjul_core::clock::ManualClock;
+        use arshjul_core::storage_tests;
+        
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        storage_tests::share_storage_suite(&storage).await;
+        storage_tests::concurrent_views_suite(Arc::new(SqliteStorage::open_in_memory().unwrap())).await;
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        storage_tests::share_ttl_suite(&SqliteStorage::open_in_memory().unwrap().with_clock(clock.clone()), clock.as_ref()).await;
+        storage_tests::activity_storage_suite(&storage).await;
+        storage_tests::layer_storage_suite(&storage).await;
+        storage_tests::policy_storage_suite(&storage).await;
+        storage_tests::audit_storage_suite(&storage).await;
+    }
+    
+    #[tokio::test]
+    async fn test_activities() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        ActivityStorage::create(&storage, activity("a-1", "layer-1", "2025-03-01T09:00:00Z")).await.unwrap();
+        ActivityStorage::create(&storage, activity("a-2", "layer-1", "2024-03-01T09:00:00Z")).await.unwrap();
+        assert!(matches!(
+            ActivityStorage::create(&storage, activity("a-1", "layer-2", "2025-03-01T09:00:00Z")).await,
+            Err(StorageError::AlreadyExists(_))
+        ));
+        
+        let in_2025 = storage.list_by_layers("org-1", &["layer-1".to_string()], Some(2025)).await.unwrap();
+        assert_eq!(in_2025.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), ["a-1"]);
+        
+        // A missing activity rolls back the whole batch
+        let moved = activity("a-1", "layer-2", "2025-03-01T09:00:00Z");
+        let result = storage.update_batch(vec![moved.clone(), activity("a-3", "layer-2", "2025-03-01T09:00:00Z")]).await;
+        assert!(matches!(result, Err(StorageError::NotFound(_))));
+        assert_eq!(ActivityStorage::get(&storage, "org-1", "a-1").await.unwrap().scope, "layer-1");
+        
+        storage.update_batch(vec![moved]).await.unwrap();
+        assert_eq!(ActivityStorage::get(&storage, "org-1", "a-1").await.unwrap().scope, "layer-2");
+    }
+    
+    #[tokio::test]
+    async fn test_shares_and_short_codes() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        for i in 0..3 {
+            let visibility = if i == 1 { "users" } else { "public" };
+            ShareStorage::create(&storage, share(&format!("s-{}", i), &format!("Code000{}", i), visibility)).await.unwrap();
+        }
+        assert!(matches!(
+            ShareStorage::create(&storage, share("s-9", "Code0001", "public")).await,
+            Err(StorageError::AlreadyExists(_))
+        ));
+        
+        let options = QueryOptions { page_size: Some(1), filter: FilterBuilder::new().eq(FilterField::Visibility, "public").build(), ..Default::default() };
+        let first = ShareStorage::list(&storage, "org-1", options.clone()).await.unwrap();
+        assert_eq!((first.items[0].id.as_str(), first.total_count), ("s-0", Some(2)));
+        let second = ShareStorage::list(&storage, "org-1", QueryOptions { continuation_token: first.continuation_token, ..options }).await.unwrap();
+        assert_eq!((second.items[0].id.as_str(), second.continuation_token), ("s-2", None));
+        
+        storage.increment_views("org-1", "s-0").await.unwrap();
+        assert_eq!(storage.get_by_short_code("Code0000").await.unwrap().stats.view_count, 1);
+        
+        // A deleted share retires its short code
+        ShareStorage::delete(&storage, "org-1", "s-0").await.unwrap();
+        assert!(storage.get_by_short_code("Code0000").await.is_err());
+        assert!(storage.get_tombstone("Code0000").await.unwrap().is_some());
+        assert!(ShareStorage::create(&storage, share("s-3", "Code0000", "public")).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_purge_deleted() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let deleted_at = |at: &str| Some(at.parse::<DateTime<Utc>>().unwrap());
+        let mut old = share("s-0", "Code0000", "public");
+        old.deleted_at = deleted_at("2025-01-01T00:00:00Z");
+        ShareStorage::create(&storage, old).await.unwrap();
+        ShareStorage::create(&storage, share("s-1", "Code0001", "public")).await.unwrap();
+        let mut binned = activity("a-1", "layer-1", "2025-03-01T09:00:00Z");
+        binned.deleted_at = deleted_at("2025-01-01T00:00:00Z");
+        ActivityStorage::create(&storage, binned).await.unwrap();
+        let mut recent = activity("a-2", "layer-1", "2025-03-01T09:00:00Z");
+        recent.deleted_at = deleted_at("2025-03-01T00:00:00Z");
+        ActivityStorage::create(&storage, recent).await.unwrap();
+        
+        assert_eq!(storage.purge_deleted("2025-02-01T00:00:00Z".parse().unwrap()).await.unwrap(), 2);
+        assert!(ShareStorage::get(&storage, "org-1", "s-0").await.is_err());
+        assert!(storage.get_tombstone("Code0000").await.unwrap().is_some());
+        assert!(ShareStorage::get(&storage, "org-1", "s-1").await.is_ok());
+        assert!(ActivityStorage::get(&storage, "org-1", "a-1").await.is_err());
+        assert!(ActivityStorage::get(&storage, "org-1", "a-2").await.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_probe_reads_every_table() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        for table in storage.targets() {
+            storage.probe(table).await.unwrap();
+        }
+        assert!(matches!(storage.probe("sqlite_master; DROP TABLE shares").await, Err(StorageError::NotFound(_))));
+    }
+}
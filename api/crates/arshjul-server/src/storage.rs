@@ -0,0 +1,447 @@
+//! # Storage Wiring
+//!
+//! Builds the complete [`Storage`] bundle for the backend selected by
+//! `STORAGE_TYPE`, so the binary and tests get every trait object from one
+//! call:
+//!
+//! | Backend | Shares | Activities | Layers | Activity types | User settings |
+//! |---------|--------|------------|--------|----------------|---------------|
+//! | `memory` | memory | memory | memory | memory | memory |
+//! | `sqlite` | SQLite | SQLite | SQLite | SQLite | SQLite |
+//! | `table` | Table Storage | Table Storage | Table Storage | Table Storage | Table Storage |
+//! | `cosmosdb` | Cosmos DB | Cosmos DB | Cosmos DB | Cosmos DB | Cosmos DB |
+//!
+//! Organization policies and audit entries are stored by every backend but
+//! memory: SQLite in its file, Table Storage and Cosmos DB in their
+//! `policies` and `audit` tables or containers.
+//!
+//! Table Storage tables and Cosmos DB containers are created by
+//! [`init_storage`] (`arshjul-api init-storage`) at deploy time. At startup
+//! they are only checked, with one listing request, and a missing one stops
+//! the service rather than failing requests later.
+//!
+//! With `SHARE_CACHE_TTL_SECONDS` set, short code lookups of every backend
+//! are read through a share cache: Redis when `REDIS_URL` is set (`redis`
+//! feature), in process otherwise (see `arshjul_core::share_cache`).
+//!
+//! With `SHARE_KEY_ENCRYPTION_KEYS` set, share keys are sealed at rest
+//! (see `arshjul_core::share_key_cipher`): Table Storage seals them in its
+//! entities, SQLite, Cosmos DB (including its `DUAL_WRITE` copy) and the
+//! Redis share cache behind a `SealedShareStorage` or `SealedShareCache`.
+//! Memory and the in-process cache never leave the process and store them
+//! as they are.
+//!
+//! Table Storage has no TTL; it comes with a purger for the expired share
+//! cleanup (see `arshjul_core::share_cleanup`).
+//!
+//! Every backend comes with a purger that empties the recycle bin after
+//! `DELETED_ITEM_RETENTION_DAYS` (see `arshjul_core::recycle_bin`).
+//!
+//! Table Storage and Cosmos DB requests refused with 429 or 503 are retried
+//! with backoff (`STORAGE_RETRY_*`, see `arshjul_core::storage_retry`).
+//!
+//! Cosmos DB also comes with a change feed; when `CHANGE_FEED_INTERVAL_SECONDS`
+//! is set, every instance follows it to drop cache entries for writes made
+//! elsewhere (see `arshjul_core::change_feed`).
+//!
+//! Table Storage also samples the size of every organization's partitions
+//! for the partition monitor (see `arshjul_core::partition_monitor`).
+//!
+//! Table Storage and Cosmos DB can't count a partition without reading it,
+//! so share list totals come from their `counters` table or container (see
+//! `arshjul_core::counters`). Memory and SQLite count exactly.
+//!
+//! With `DUAL_WRITE` set, the entity types it lists are written to both
+//! Table Storage and Cosmos DB and read from the one it names (see
+//! `arshjul_core::dual_write`). Purgers, probes, counters and the change
+//! feed stay those of `STORAGE_TYPE`.
+//!
+//! Storage calls are counted and timed per backend for `GET /api/metrics`;
+//! dual-written entities count as `dual` (see
+//! `arshjul_core::storage_metrics`).
+//!
+//! SQLite, Table Storage and Cosmos DB come with a probe for `GET /api/health`
+//! (see `arshjul_core::health`); it bypasses the retries, so throttling shows.
+
+use crate::config::{AppConfig, StorageType};
+use arshjul_core::storage::{ActivityStorage, ActivityTypeStorage, AuditStorage, ChangeFeed, CounterStorage, DeletedItemPurger, ExpiredSharePurger, LayerStorage, PartitionSampler, PolicyStorage, ShareStorage, Storage, StorageProbe, UserSettingsStorage};
+use arshjul_core::storage::memory_storage::{
+    MemoryShareStorage, MemoryActivityStorage, MemoryLayerStorage,
+    MemoryActivityTypeStorage, MemoryUserSettingsStorage, MemoryAuditStorage, MemoryPolicyStorage,
+};
+#[cfg(feature = "sqlite")]
+use crate::sqlite_storage::SqliteStorage;
+#[cfg(feature = "azure")]
+use arshjul_azure::{cosmos_storage::CosmosStorageClient, table_storage::TableStorageClient};
+use arshjul_core::share_cache::{CachedShareStorage, InProcessShareCache, ShareCache};
+#[cfg(feature = "azure")]
+use arshjul_core::storage_retry::{RetryPolicy, RetryingStorage};
+use arshjul_core::share_key_cipher::ShareKeyCipher;
+#[cfg(any(feature = "sqlite", feature = "azure"))]
+use arshjul_core::share_key_cipher::SealedShareStorage;
+#[cfg(feature = "redis")]
+use arshjul_core::share_key_cipher::SealedShareCache;
+#[cfg(feature = "azure")]
+use arshjul_core::dual_write::{Backend, DualWriteStorage};
+#[cfg(feature = "redis")]
+use arshjul_azure::redis_share_cache::RedisShareCache;
+use std::sync::Arc;
+
+/// Storage that depends on the configured backend
+type BackendStorage = (
+    Arc<dyn ShareStorage>,
+    Arc<dyn ActivityStorage>,
+    Arc<dyn LayerStorage>,
+    Arc<dyn ActivityTypeStorage>,
+    Arc<dyn UserSettingsStorage>,
+);
+
+/// Connect to the configured backend and bundle its storage
+pub async fn from_config(config: &AppConfig) -> anyhow::Result<Storage> {
+    #[cfg(feature = "sqlite")]
+    if config.storage_type == StorageType::Sqlite {
+        let path = config.sqlite_path.as_deref()
+            .ok_or_else(|| anyhow::anyhow!("SQLite is not configured"))?;
+        tracing::info!("Using SQLite storage: {}", path);
+        let sqlite = Arc::new(SqliteStorage::open(path)?);
+        let cache = share_cache(config)?;
+        let shares = sealed(config, sqlite.clone() as Arc<dyn ShareStorage>)?;
+        let storage = Storage::new(read_through(shares, cache.as_ref()), sqlite.clone(), sqlite.clone(), sqlite.clone(), sqlite.clone(), sqlite.clone(), sqlite.clone())
+            .with_backend("sqlite", &arshjul_core::storage_metrics::ENTITIES)
+            .with_deleted_item_purger(sqlite.clone())
+            .with_probe(sqlite);
+        return Ok(match cache {
+            Some(cache) => storage.with_share_cache(cache),
+            None => storage,
+        });
+    }
+    
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut expired_shares: Option<Arc<dyn ExpiredSharePurger>> = None;
+    let mut deleted_items: Vec<Arc<dyn DeletedItemPurger>> = Vec::new();
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut probe: Option<Arc<dyn StorageProbe>> = None;
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut change_feed: Option<Arc<dyn ChangeFeed>> = None;
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut partitions: Option<Arc<dyn PartitionSampler>> = None;
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut counters: Option<Arc<dyn CounterStorage>> = None;
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut policies: Arc<dyn PolicyStorage> = Arc::new(MemoryPolicyStorage::new());
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut audit: Arc<dyn AuditStorage> = Arc::new(MemoryAuditStorage::new());
+    // Metric labels of the entities the backend keeps
+    #[cfg_attr(not(feature = "azure"), allow(unused_mut))]
+    let mut backend: (&'static str, &'static [&'static str]) = ("memory", &[]);
+    let (share_storage, activity_storage, layer_storage, activity_type_storage, user_settings_storage): BackendStorage = match config.storage_type {
+        StorageType::Memory => {
+            tracing::info!("Using in-memory storage (development mode)");
+            let shares = Arc::new(MemoryShareStorage::new());
+            let activities = Arc::new(MemoryActivityStorage::new());
+            deleted_items.push(shares.clone());
+            deleted_items.push(activities.clone());
+            (
+                shares,
+                activities,
+                Arc::new(MemoryLayerStorage::new()),
+                Arc::new(MemoryActivityTypeStorage::new()),
+                Arc::new(MemoryUserSettingsStorage::new()),
+            )
+        }
+        #[cfg(feature = "azure")]
+        StorageType::TableStorage => {
+            let table_client = table_client(config).await?;
+            table_client.check_tables().await?;
+            
+            let table_client = Arc::new(table_client);
+            expired_shares = Some(table_client.clone());
+            deleted_items.push(table_client.clone());
+            probe = Some(table_client.clone());
+            partitions = Some(table_client.clone());
+            counters = Some(table_client.clone());
+            policies = Arc::new(RetryingStorage::new(table_client.clone(), config.storage_retry.clone()));
+            audit = Arc::new(RetryingStorage::new(table_client.clone(), config.storage_retry.clone()));
+            backend = ("table", &arshjul_core::storage_metrics::ENTITIES);
+            with_retries(&config.storage_retry, (table_client.clone(), table_client.clone(), table_client.clone(), table_client.clone(), table_client))
+        }
+        #[cfg(feature = "azure")]
+        StorageType::CosmosDb => {
+            let cosmos_client = cosmos_client(config).await?;
+            cosmos_client.check_containers().await?;
+            
+            let cosmos_client = Arc::new(cosmos_client);
+            probe = Some(cosmos_client.clone());
+            change_feed = Some(cosmos_client.clone());
+            deleted_items.push(cosmos_client.clone());
+            counters = Some(cosmos_client.clone());
+            policies = Arc::new(RetryingStorage::new(cosmos_client.clone(), config.storage_retry.clone()));
+            audit = Arc::new(RetryingStorage::new(cosmos_client.clone(), config.storage_retry.clone()));
+            backend = ("cosmosdb", &arshjul_core::storage_metrics::ENTITIES);
+            with_retries(&config.storage_retry, (sealed(config, cosmos_client.clone() as Arc<dyn ShareStorage>)?, cosmos_client.clone(), cosmos_client.clone(), cosmos_client.clone(), cosmos_client))
+        }
+        StorageType::Sqlite => {
+            return Err(anyhow::anyhow!(
+                "{} requires a build with the `sqlite` feature", config.storage_display_name()
+            ));
+        }
+        #[cfg(not(feature = "azure"))]
+        StorageType::TableStorage | StorageType::CosmosDb => {
+            return Err(anyhow::anyhow!(
+                "{} requires a build with the `azure` feature", config.storage_display_name()
+            ));
+        }
+    };
+    
+    #[cfg(feature = "azure")]
+    let (share_storage, activity_storage, layer_storage, activity_type_storage, user_settings_storage) =
+        with_dual_writes(config, (share_storage, activity_storage, layer_storage, activity_type_storage, user_settings_storage)).await?;
+    
+    let cache = share_cache(config)?;
+    let storage = Storage::new(
+        read_through(share_storage, cache.as_ref()),
+        activity_storage,
+        layer_storage,
+        activity_type_storage,
+        user_settings_storage,
+        audit,
+        policies,
+    ).with_backend(backend.0, backend.1);
+    for (entity, _) in &config.dual_write {
+        storage.metrics.set_backend(entity, "dual");
+    }
+    let storage = deleted_items.into_iter()
+        .fold(storage, |storage, purger| storage.with_deleted_item_purger(purger));
+    let storage = match probe {
+        Some(probe) => storage.with_probe(probe),
+        None => storage,
+    };
+    let storage = match change_feed {
+        Some(feed) => storage.with_change_feed(feed),
+        None => storage,
+    };
+    let storage = match partitions {
+        Some(sampler) => storage.with_partition_sampler(sampler),
+        None => storage,
+    };
+    let storage = match counters {
+        Some(counters) => storage.with_counters(counters),
+        None => storage,
+    };
+    let storage = match cache {
+        Some(cache) => storage.with_share_cache(cache),
+        None => storage,
+    };
+    Ok(match expired_shares {
+        Some(purger) => storage.with_expired_share_purger(purger),
+        None => storage,
+    })
+}
+
+/// Create the configured backend's tables or containers (`arshjul-api init-storage`)
+///
+/// Run once per deployment with an identity allowed to manage them; the
+/// service itself then only checks that they exist. Memory needs nothing,
+/// and SQLite creates its tables when the file is opened.
+pub async fn init_storage(config: &AppConfig) -> anyhow::Result<()> {
+    match config.storage_type {
+        StorageType::Memory => tracing::info!("In-memory storage needs no initialization"),
+        #[cfg(feature = "sqlite")]
+        StorageType::Sqlite => {
+            let path = config.sqlite_path.as_deref()
+                .ok_or_else(|| anyhow::anyhow!("SQLite is not configured"))?;
+            SqliteStorage::open(path)?;
+            tracing::info!("SQLite database ready: {}", path);
+        }
+        #[cfg(feature = "azure")]
+        StorageType::TableStorage => {
+            let created = table_client(config).await?.create_tables().await?;
+            tracing::info!("Table Storage ready; created {:?} of {:?}", created, TableStorageClient::table_names());
+        }
+        #[cfg(feature = "azure")]
+        StorageType::CosmosDb => {
+            let created = cosmos_client(config).await?.create_containers().await?;
+            tracing::info!("Cosmos DB ready; created {:?} of {:?}", created, CosmosStorageClient::container_names());
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StorageType::Sqlite => {
+            return Err(anyhow::anyhow!(
+                "{} requires a build with the `sqlite` feature", config.storage_display_name()
+            ));
+        }
+        #[cfg(not(feature = "azure"))]
+        StorageType::TableStorage | StorageType::CosmosDb => {
+            return Err(anyhow::anyhow!(
+                "{} requires a build with the `azure` feature", config.storage_display_name()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Table Storage client for the configured account
+#[cfg(feature = "azure")]
+async fn table_client(config: &AppConfig) -> anyhow::Result<TableStorageClient> {
+    let table_config = config.table_storage.as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Table Storage is not configured"))?;
+    tracing::info!("Connecting to Azure Table Storage: {}", table_config.account_name);
+    
+    // Use Managed Identity if no access key provided, otherwise use access key
+    let client = if let Some(ref access_key) = table_config.access_key {
+        tracing::info!("Using access key authentication");
+        TableStorageClient::new_with_access_key(
+            &table_config.account_name,
+            access_key,
+        ).await?
+    } else {
+        tracing::info!("Using Managed Identity authentication");
+        TableStorageClient::new_with_managed_identity(
+            &table_config.account_name,
+        ).await?
+    };
+    
+    Ok(match share_key_cipher(config)? {
+        Some(cipher) => client.with_share_key_cipher(cipher),
+        None => client,
+    })
+}
+
+/// The cipher sealing share keys at rest, if `SHARE_KEY_ENCRYPTION_KEYS` is set
+pub fn share_key_cipher(config: &AppConfig) -> anyhow::Result<Option<Arc<ShareKeyCipher>>> {
+    Ok(match config.share_key_encryption_keys {
+        Some(ref keys) => {
+            let cipher = ShareKeyCipher::from_spec(keys)?;
+            tracing::info!("Share keys sealed at rest with key {}", cipher.current_key_id());
+            Some(Arc::new(cipher))
+        }
+        None => None,
+    })
+}
+
+/// Seal share keys in a backend that doesn't seal them itself
+#[cfg(any(feature = "sqlite", feature = "azure"))]
+fn sealed(config: &AppConfig, shares: Arc<dyn ShareStorage>) -> anyhow::Result<Arc<dyn ShareStorage>> {
+    Ok(match share_key_cipher(config)? {
+        Some(cipher) => Arc::new(SealedShareStorage::new(shares, cipher)),
+        None => shares,
+    })
+}
+
+/// Cosmos DB client for the configured database
+#[cfg(feature = "azure")]
+async fn cosmos_client(config: &AppConfig) -> anyhow::Result<CosmosStorageClient> {
+    let cosmos_config = config.cosmos_db.as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Cosmos DB is not configured"))?;
+    tracing::info!("Connecting to Azure Cosmos DB: endpoint={}, database={}",
+        cosmos_config.endpoint, cosmos_config.database_name);
+    
+    // Use primary key if provided, otherwise error (Managed Identity requires SDK version alignment)
+    if let Some(ref primary_key) = cosmos_config.primary_key {
+        tracing::info!("Using primary key authentication");
+        Ok(CosmosStorageClient::new_with_key(
+            &cosmos_config.endpoint,
+            &cosmos_config.database_name,
+            primary_key,
+        ).await?)
+    } else {
+        // For Managed Identity with Cosmos DB, recommend using Table Storage instead
+        // or configuring Easy Auth at the Azure Functions level
+        tracing::warn!("Cosmos DB Managed Identity not available - use COSMOS_PRIMARY_KEY or switch to Table Storage");
+        Err(anyhow::anyhow!(
+            "Cosmos DB requires COSMOS_PRIMARY_KEY. For Managed Identity, use Table Storage (STORAGE_TYPE=table)."
+        ))
+    }
+}
+
+/// Retry throttled requests to a cloud backend
+#[cfg(feature = "azure")]
+fn with_retries(policy: &RetryPolicy, (shares, activities, layers, activity_types, user_settings): BackendStorage) -> BackendStorage {
+    if policy.max_attempts > 1 {
+        tracing::info!("Throttled storage requests retried up to {} times", policy.max_attempts - 1);
+    }
+    (
+        Arc::new(RetryingStorage::new(shares, policy.clone())),
+        Arc::new(RetryingStorage::new(activities, policy.clone())),
+        Arc::new(RetryingStorage::new(layers, policy.clone())),
+        Arc::new(RetryingStorage::new(activity_types, policy.clone())),
+        Arc::new(RetryingStorage::new(user_settings, policy.clone())),
+    )
+}
+
+/// Write the `DUAL_WRITE` entity types to both Table Storage and Cosmos DB
+#[cfg(feature = "azure")]
+async fn with_dual_writes(config: &AppConfig, (shares, activities, layers, activity_types, user_settings): BackendStorage) -> anyhow::Result<BackendStorage> {
+    if config.dual_write.is_empty() {
+        return Ok((shares, activities, layers, activity_types, user_settings));
+    }
+    
+    let table = Arc::new(table_client(config).await?);
+    table.check_tables().await?;
+    let cosmos = Arc::new(cosmos_client(config).await?);
+    cosmos.check_containers().await?;
+    let table = with_retries(&config.storage_retry, (table.clone(), table.clone(), table.clone(), table.clone(), table));
+    let cosmos = with_retries(&config.storage_retry, (sealed(config, cosmos.clone() as Arc<dyn ShareStorage>)?, cosmos.clone(), cosmos.clone(), cosmos.clone(), cosmos));
+    
+    let primary = |entity: &str| config.dual_write.iter().find(|(e, _)| *e == entity).map(|(_, primary)| primary.clone());
+    let compare_every = config.dual_write_compare_every;
+    fn dual<S: ?Sized>(entity: &'static str, primary: StorageType, compare_every: u64, table: Arc<S>, cosmos: Arc<S>) -> DualWriteStorage<S> {
+        tracing::info!("Dual-writing {} to Table Storage and Cosmos DB, reading from {:?}", entity, primary);
+        let (table, cosmos) = (Backend::new("table", table), Backend::new("cosmosdb", cosmos));
+        match primary {
+            StorageType::CosmosDb => DualWriteStorage::new(entity, cosmos, table),
+            _ => DualWriteStorage::new(entity, table, cosmos),
+        }.with_compare_every(compare_every)
+    }
+    
+    Ok((
+        match primary("share") {
+            Some(p) => Arc::new(dual("share", p, compare_every, table.0, cosmos.0)),
+            None => shares,
+        },
+        match primary("activity") {
+            Some(p) => Arc::new(dual("activity", p, compare_every, table.1, cosmos.1)),
+            None => activities,
+        },
+        match primary("layer") {
+            Some(p) => Arc::new(dual("layer", p, compare_every, table.2, cosmos.2)),
+            None => layers,
+        },
+        match primary("activity_type") {
+            Some(p) => Arc::new(dual("activity_type", p, compare_every, table.3, cosmos.3)),
+            None => activity_types,
+        },
+        match primary("user_settings") {
+            Some(p) => Arc::new(dual("user_settings", p, compare_every, table.4, cosmos.4)),
+            None => user_settings,
+        },
+    ))
+}
+
+/// The configured share cache, if any
+fn share_cache(config: &AppConfig) -> anyhow::Result<Option<Arc<dyn ShareCache>>> {
+    let ttl = config.share_cache_ttl_seconds;
+    if ttl == 0 {
+        return Ok(None);
+    }
+    
+    #[cfg(feature = "redis")]
+    if let Some(ref url) = config.redis_url {
+        tracing::info!("Share lookups cached in Redis for {}s", ttl);
+        let cache: Arc<dyn ShareCache> = Arc::new(RedisShareCache::new(url, &format!("{}record:", config.redis_key_prefix), ttl)?);
+        return Ok(Some(match share_key_cipher(config)? {
+            Some(cipher) => Arc::new(SealedShareCache::new(cache, cipher)),
+            None => cache,
+        }));
+    }
+    
+    tracing::info!("Share lookups cached in process for {}s", ttl);
+    Ok(Some(Arc::new(InProcessShareCache::new(chrono::Duration::seconds(ttl as i64)))))
+}
+
+/// Read short code lookups through the share cache, if any
+fn read_through(shares: Arc<dyn ShareStorage>, cache: Option<&Arc<dyn ShareCache>>) -> Arc<dyn ShareStorage> {
+    match cache {
+        Some(cache) => Arc::new(CachedShareStorage::new(shares, cache.clone())),
+        None => shares,
+    }
+}
@@ -0,0 +1,81 @@
+//! # Webhooks
+//!
+//! Outgoing webhooks (`webhooks` feature). Error budget burn alerts and
+//! partition size alerts are POSTed as JSON to `SLO_ALERT_WEBHOOK_URL`; the
+//! `text` field makes the payload readable in a Teams or Slack incoming
+//! webhook, and `alert` carries the details for automation (Logic Apps,
+//! paging bridges). Digest and reminder emails are POSTed to
+//! `MAIL_WEBHOOK_URL` as [`EmailMessage`] JSON.
+
+use arshjul_core::notifications::{EmailMessage, Mailer};
+use arshjul_core::partition_monitor::{PartitionAlert, PartitionAlertSink};
+use arshjul_core::share_renewal::ReminderError;
+use arshjul_core::slo::{AlertError, AlertSink, BurnAlert};
+use async_trait::async_trait;
+
+/// Sends alerts to one webhook URL
+pub struct WebhookAlertSink {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl WebhookAlertSink {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string(), http: reqwest::Client::new() }
+    }
+    
+    async fn post(&self, payload: serde_json::Value) -> Result<(), AlertError> {
+        let response = self.http.post(&self.url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| AlertError::Delivery(e.to_string()))?;
+        
+        if !response.status().is_success() {
+            return Err(AlertError::Delivery(format!("webhook returned {}", response.status())));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookAlertSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+    
+    async fn send(&self, alert: &BurnAlert) -> Result<(), AlertError> {
+        self.post(serde_json::json!({ "text": alert.summary(), "alert": alert })).await
+    }
+}
+
+/// Hands digest and reminder emails to a mail webhook (e.g. a Logic App
+/// sending through Microsoft Graph), which resolves the recipient's address
+pub struct WebhookMailer {
+    sink: WebhookAlertSink,
+}
+
+impl WebhookMailer {
+    pub fn new(url: &str) -> Self {
+        Self { sink: WebhookAlertSink::new(url) }
+    }
+}
+
+#[async_trait]
+impl Mailer for WebhookMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), ReminderError> {
+        self.sink.post(serde_json::json!(message)).await
+            .map_err(|e| ReminderError::Delivery(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl PartitionAlertSink for WebhookAlertSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+    
+    async fn send(&self, alert: &PartitionAlert) -> Result<(), AlertError> {
+        self.post(serde_json::json!({ "text": alert.summary(), "alert": alert })).await
+    }
+}
@@ -0,0 +1,51 @@
+//! `GET /api/public/s/{shortCode}/calendar.ics`: query parsing, filtering
+//! and rendering of user-written activity text
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use arshjul_core::export::ExportLocale;
+use arshjul_core::ics::{self, CalendarFilter};
+use arshjul_core::models::{Activity, CalendarFeedQuery};
+use arshjul_fuzz::{date, FuzzActivity};
+use chrono::Utc;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    layers: Option<String>,
+    types: Option<String>,
+    tags: Option<String>,
+    from: Option<u32>,
+    to: Option<u32>,
+    title: String,
+    norwegian: bool,
+    activities: Vec<FuzzActivity>,
+}
+
+fuzz_target!(|input: Input| {
+    let query = CalendarFeedQuery {
+        layers: input.layers,
+        types: input.types,
+        tags: input.tags,
+        from: input.from.map(date),
+        to: input.to.map(date),
+    };
+    let Ok(filter) = CalendarFilter::parse(&query) else { return };
+    
+    let activities: Vec<Activity> = input.activities.into_iter()
+        .enumerate()
+        .map(|(i, a)| a.into_activity(i))
+        .filter(|a| filter.matches(a))
+        .collect();
+    let locale = if input.norwegian { ExportLocale::Norwegian } else { ExportLocale::English };
+    let calendar = ics::render_calendar(&ics::calendar_name(&input.title, &[]), &activities, Utc::now(), locale);
+    
+    // User text must never break out of its content line
+    assert!(calendar.ends_with("\r\n"));
+    for line in calendar.trim_end_matches("\r\n").split("\r\n") {
+        assert!(!line.contains(['\r', '\n']), "bare line break in {:?}", line);
+        assert!(line.len() <= 75, "unfolded line {:?}", line);
+    }
+    assert_eq!(calendar.matches("BEGIN:VEVENT").count(), activities.len());
+});
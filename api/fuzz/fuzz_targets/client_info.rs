@@ -0,0 +1,41 @@
+//! Client IP, user agent and country resolution from request headers
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use arshjul_core::client_info::{ClientInfo, TrustedProxyConfig};
+use libfuzzer_sys::fuzz_target;
+use std::net::IpAddr;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    headers: Vec<(String, String)>,
+    peer_addr: Option<IpAddr>,
+    trusted_proxies: String,
+    trust_front_door: bool,
+    front_door_id: Option<String>,
+}
+
+fuzz_target!(|input: Input| {
+    let config = TrustedProxyConfig {
+        trusted_proxies: TrustedProxyConfig::parse_proxies(&input.trusted_proxies).unwrap_or_default(),
+        trust_front_door: input.trust_front_door,
+        front_door_id: input.front_door_id,
+    };
+    let client = ClientInfo::from_headers(&input.headers, input.peer_addr, &config);
+    
+    // Only a trusted edge may report the country
+    if config.trusted_proxies.is_empty() && !config.trust_front_door {
+        assert_eq!(client.country, None);
+    }
+    let forwarded = input.headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("x-forwarded-for"));
+    if !forwarded && !config.trust_front_door {
+        assert_eq!(client.ip, input.peer_addr);
+    }
+    if let Some(country) = client.country {
+        assert!(country.len() == 2 && country.bytes().all(|c| c.is_ascii_uppercase()));
+    }
+    if let Some(user_agent) = client.user_agent {
+        assert!(!user_agent.is_empty() && user_agent.chars().count() <= 512);
+    }
+});
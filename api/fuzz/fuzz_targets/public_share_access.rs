@@ -0,0 +1,93 @@
+//! `GET /api/public/s/{shortCode}?k=`: request checks, key verification and
+//! the public projection of activities
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use arshjul_core::models::*;
+use arshjul_core::public_access::{self, PublicAccessError};
+use arshjul_fuzz::FuzzActivity;
+use chrono::{Duration, Utc};
+use libfuzzer_sys::fuzz_target;
+
+const SHORT_CODE: &str = "AbCd2345";
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    short_code: String,
+    key: String,
+    is_active: bool,
+    expired: bool,
+    description_html: bool,
+    activities: Vec<FuzzActivity>,
+}
+
+fn share(input: &Input) -> ShareLink {
+    let now = Utc::now();
+    ShareLink {
+        id: "share-1".to_string(),
+        share_key: "a1".repeat(32),
+        short_code: SHORT_CODE.to_string(),
+        visibility: ShareVisibility::Public,
+        organization_id: "org-1".to_string(),
+        created_by: "user-1".to_string(),
+        created_at: now - Duration::days(30),
+        expires_at: if input.expired { now - Duration::days(1) } else { now + Duration::days(30) },
+        renewed_at: None,
+        name: None,
+        description: None,
+        layer_config: ShareLayerConfig {
+            layer_ids: vec!["layer-0".to_string(), "layer-1".to_string()],
+            layer_visibility: None,
+            year: None,
+        },
+        view_settings: ShareViewSettings { description_html: input.description_html, ..Default::default() },
+        inherit_view_settings: false,
+        view_overrides: Default::default(),
+        stats: ShareStats::default(),
+        is_active: input.is_active,
+        ttl: None,
+        report_count: 0,
+        reporters: Vec::new(),
+        publish_snapshot: false,
+        indexable: None,
+        created_by_name: None,
+        review: None,
+        team_ids: Vec::new(),
+        channel_ids: Vec::new(),
+        deleted_at: None,
+        partner_sources: Vec::new(),
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let share = share(&input);
+    if public_access::validate_request(&input.short_code, &input.key).is_err() {
+        return;
+    }
+    assert_eq!(input.short_code.len(), 8);
+    assert_eq!(input.key.len(), 64);
+    
+    match public_access::authorize(&share, &input.key, Utc::now()) {
+        Err(PublicAccessError::InvalidKey) => assert_ne!(input.key, share.share_key),
+        result => {
+            assert_eq!(input.key, share.share_key);
+            if result.is_ok() {
+                assert!(share.is_active && !input.expired);
+            }
+        }
+    }
+    
+    let activities: Vec<Activity> = input.activities.into_iter()
+        .enumerate()
+        .map(|(i, a)| a.into_activity(i))
+        .collect();
+    let view = public_access::project(&share, activities, public_access::share_year(&share, Utc::now()), ActivityOrder::default());
+    for activity in view.activities.unwrap_or_default() {
+        assert!(share.layer_config.layer_ids.contains(&activity.layer_id));
+        if let Some(html) = activity.description_html {
+            assert!(share.view_settings.description_html);
+            assert!(!html.to_lowercase().contains("<script"), "unescaped markup: {}", html);
+        }
+    }
+});
@@ -0,0 +1,65 @@
+//! Shared input types for the fuzz targets
+
+use arbitrary::Arbitrary;
+use arshjul_core::models::{Activity, ActivityType, ApprovalStatus, DescriptionFormat};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Days from CE covering years 1..=9999
+const MAX_DAY: i32 = 3_652_059;
+
+/// Date from an arbitrary day number (clamped to years 1..=9999)
+pub fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_num_days_from_ce_opt(1 + (day % MAX_DAY as u32) as i32).unwrap()
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+/// User-written parts of an activity
+#[derive(Debug, Arbitrary)]
+pub struct FuzzActivity {
+    pub title: String,
+    pub description: Option<String>,
+    pub markdown: bool,
+    pub tags: Vec<String>,
+    pub layer: u8,
+    pub approved: bool,
+    pub start_day: u32,
+    pub length_days: u16,
+}
+
+impl FuzzActivity {
+    pub fn into_activity(self, index: usize) -> Activity {
+        let start = date(self.start_day);
+        let end = start.checked_add_days(chrono::Days::new(self.length_days as u64)).unwrap_or(start);
+        Activity {
+            id: format!("activity-{}", index),
+            title: self.title,
+            start_date: midnight(start),
+            end_date: midnight(end),
+            activity_type: ActivityType::Other,
+            color: "#3b82f6".to_string(),
+            highlight_color: "#1d4ed8".to_string(),
+            description: self.description,
+            description_format: if self.markdown { DescriptionFormat::Markdown } else { DescriptionFormat::Plain },
+            links: Vec::new(),
+            tags: self.tags,
+            priority: 0,
+            scope: format!("layer-{}", self.layer % 4),
+            scope_id: format!("layer-{}", self.layer % 4),
+            organization_id: "org-1".to_string(),
+            created_by: None,
+            created_at: None,
+            updated_at: None,
+            edit_count: 0,
+            approval_status: if self.approved { ApprovalStatus::Approved } else { ApprovalStatus::PendingApproval },
+            approval_review: None,
+            split_from: None,
+            merged_from: Vec::new(),
+            deleted_at: None,
+            recurrence: None,
+            created_by_name: None,
+        }
+    }
+}
@@ -6,7 +6,7 @@
 /**
  * Layer types - admin can create layers of different types
  */
-export type LayerType = 'holidays' | 'organization' | 'custom';
+export type LayerType = 'holidays' | 'organization' | 'terms' | 'custom';
 
 /**
  * Admin-configurable layer
@@ -223,6 +223,8 @@ export interface UserSettings {
   layerVisibility?: Record<string, boolean>;
   // Other user preferences
   theme?: 'light' | 'dark' | 'system';
+  // Language of digests, reminders and exports ('en' | 'nb'); unset follows the organization
+  locale?: string;
   updatedAt: Date;
 }
 
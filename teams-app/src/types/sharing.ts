@@ -184,6 +184,9 @@ export interface AccessShareResponse {
   
   /** Activities for the share (if access granted) */
   activities?: ShareActivity[];
+  
+  /** Some activities were left out to answer in time */
+  partial?: boolean;
 }
 
 /**